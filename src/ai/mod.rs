@@ -0,0 +1,381 @@
+//! Client for OpenAI-compatible chat completion APIs
+//!
+//! Used for AI-generated chat responses such as personalized welcome messages.
+
+use anyhow::{Result, anyhow};
+use reqwest::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::twitch::MAX_MESSAGE_LENGTH;
+
+/// Default timeout for AI requests
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Added to the instructions when asking again for a shorter response
+const CONCISE_INSTRUCTION: &str = "Be concise: reply in under 400 characters.";
+
+/// What to do when a response is too long for a chat message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LengthStrategy {
+    /// Cut the response at a word boundary
+    Truncate,
+    /// Ask the model once for a shorter response, then truncate if still too long
+    #[default]
+    Retry,
+}
+
+impl FromStr for LengthStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "truncate" => Ok(LengthStrategy::Truncate),
+            "retry" => Ok(LengthStrategy::Retry),
+            other => Err(anyhow!("Unknown AI length strategy: {}", other)),
+        }
+    }
+}
+
+/// Shorten text to fit a limit without cutting a word in half
+///
+/// # Arguments
+/// * `text` - The text to shorten
+/// * `max_chars` - The most characters the result may have, including the ellipsis
+///
+/// # Returns
+/// The text unchanged if it fits, otherwise its longest whole-word prefix followed by "…"
+pub fn truncate_at_word_boundary(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    // Leave room for the ellipsis
+    let cut: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    let next = text.chars().nth(cut.chars().count());
+    let cut = match next {
+        // The cut already falls between words
+        Some(c) if c.is_whitespace() => cut.as_str(),
+        _ => cut
+            .rfind(char::is_whitespace)
+            .map_or(cut.as_str(), |end| &cut[..end]),
+    };
+
+    format!("{}…", cut.trim_end())
+}
+
+/// A single message in a chat completion request
+#[derive(Debug, Serialize)]
+struct ChatMessage {
+    role: String,
+    content: String,
+}
+
+/// Request body for the chat completions endpoint
+#[derive(Debug, Serialize)]
+struct ChatCompletionRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    max_tokens: u32,
+}
+
+/// Response from the chat completions endpoint
+#[derive(Debug, Deserialize)]
+struct ChatCompletionResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: ResponseMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseMessage {
+    content: String,
+}
+
+/// Client for an OpenAI-compatible chat completion API
+pub struct AiClient {
+    /// HTTP client for API calls
+    http_client: HttpClient,
+    /// Base URL of the API (e.g. https://api.openai.com/v1)
+    api_base: String,
+    /// API key sent as a bearer token
+    api_key: String,
+    /// The model to request completions from
+    model: String,
+    /// What to do when a response is too long for chat
+    length_strategy: LengthStrategy,
+}
+
+impl AiClient {
+    /// Create a new AI client with the default timeout
+    ///
+    /// # Arguments
+    /// * `api_base` - Base URL of the OpenAI-compatible API
+    /// * `api_key` - API key for the service
+    /// * `model` - The model to use for completions
+    ///
+    /// # Returns
+    /// A new AiClient instance
+    pub fn new(api_base: &str, api_key: &str, model: &str) -> Result<Self> {
+        Self::with_timeout(api_base, api_key, model, DEFAULT_TIMEOUT)
+    }
+
+    /// Create a new AI client with a custom request timeout
+    ///
+    /// # Arguments
+    /// * `api_base` - Base URL of the OpenAI-compatible API
+    /// * `api_key` - API key for the service
+    /// * `model` - The model to use for completions
+    /// * `timeout` - How long to wait for a response before giving up
+    ///
+    /// # Returns
+    /// A new AiClient instance
+    pub fn with_timeout(
+        api_base: &str,
+        api_key: &str,
+        model: &str,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let http_client = HttpClient::builder().timeout(timeout).build()?;
+
+        Ok(AiClient {
+            http_client,
+            api_base: api_base.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            length_strategy: LengthStrategy::default(),
+        })
+    }
+
+    /// Choose what to do when a response is too long for chat
+    ///
+    /// # Arguments
+    /// * `strategy` - The strategy for over-long responses
+    pub fn set_length_strategy(&mut self, strategy: LengthStrategy) {
+        self.length_strategy = strategy;
+    }
+
+    /// Ask the model for a response that fits in one chat message
+    ///
+    /// The response is joined onto a single line. If it's over the chat
+    /// limit, the length strategy decides whether to ask once more for a
+    /// shorter one before truncating at a word boundary.
+    ///
+    /// # Arguments
+    /// * `system_prompt` - Instructions describing how the model should behave
+    /// * `user_prompt` - The request to respond to
+    ///
+    /// # Returns
+    /// A single-line response of at most MAX_MESSAGE_LENGTH characters
+    pub async fn complete_for_chat(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+    ) -> Result<String> {
+        let single_line = |text: String| text.lines().collect::<Vec<_>>().join(" ");
+
+        let mut response = single_line(self.complete(system_prompt, user_prompt).await?);
+        if response.chars().count() <= MAX_MESSAGE_LENGTH {
+            return Ok(response);
+        }
+
+        if self.length_strategy == LengthStrategy::Retry {
+            debug!(
+                "AI response is {} characters, asking for a shorter one",
+                response.chars().count()
+            );
+            let concise_prompt = format!("{} {}", system_prompt, CONCISE_INSTRUCTION);
+            response = single_line(self.complete(&concise_prompt, user_prompt).await?);
+        }
+
+        Ok(truncate_at_word_boundary(&response, MAX_MESSAGE_LENGTH))
+    }
+
+    /// Ask the model for a completion
+    ///
+    /// # Arguments
+    /// * `system_prompt` - Instructions describing how the model should behave
+    /// * `user_prompt` - The request to respond to
+    ///
+    /// # Returns
+    /// The model's response text, trimmed
+    pub async fn complete(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
+        let request = ChatCompletionRequest {
+            model: self.model.clone(),
+            messages: vec![
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: system_prompt.to_string(),
+                },
+                ChatMessage {
+                    role: "user".to_string(),
+                    content: user_prompt.to_string(),
+                },
+            ],
+            max_tokens: 150,
+        };
+
+        debug!("Requesting AI completion from {}", self.api_base);
+        let response = self
+            .http_client
+            .post(format!("{}/chat/completions", self.api_base))
+            .bearer_auth(&self.api_key)
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(anyhow!("AI API error ({}): {}", status, error_text));
+        }
+
+        let completion: ChatCompletionResponse = response.json().await?;
+        let content = completion
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content.trim().to_string())
+            .ok_or_else(|| anyhow!("AI API returned no choices"))?;
+
+        if content.is_empty() {
+            return Err(anyhow!("AI API returned an empty response"));
+        }
+
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+
+    #[tokio::test]
+    async fn test_complete() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_header("authorization", "Bearer test_key")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"model":"test-model"}"#.to_string(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"choices":[{"message":{"role":"assistant","content":"  Hello!  "}}]}"#)
+            .create_async()
+            .await;
+
+        let client = AiClient::new(&server.url(), "test_key", "test-model")?;
+        let response = client.complete("Be nice", "Say hello").await?;
+
+        mock.assert_async().await;
+        assert_eq!(response, "Hello!");
+        Ok(())
+    }
+
+    /// Mock a completion whose content is the given text
+    async fn mock_completion(
+        server: &mut mockito::ServerGuard,
+        body: Matcher,
+        content: &str,
+    ) -> mockito::Mock {
+        let response = serde_json::json!({
+            "choices": [{"message": {"role": "assistant", "content": content}}]
+        });
+        server
+            .mock("POST", "/chat/completions")
+            .match_body(body)
+            .with_status(200)
+            .with_body(response.to_string())
+            .expect(1)
+            .create_async()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_long_response_is_retried() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let long = mock_completion(&mut server, Matcher::Any, &"word ".repeat(150)).await;
+        let concise = mock_completion(
+            &mut server,
+            Matcher::Regex(CONCISE_INSTRUCTION.to_string()),
+            "Short and sweet.",
+        )
+        .await;
+
+        let client = AiClient::new(&server.url(), "test_key", "test-model")?;
+        let response = client.complete_for_chat("Be nice", "Say hello").await?;
+
+        long.assert_async().await;
+        concise.assert_async().await;
+        assert_eq!(response, "Short and sweet.");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_still_long_response_is_truncated() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let _long = mock_completion(&mut server, Matcher::Any, &"word ".repeat(150)).await;
+        let _still_long = mock_completion(&mut server, Matcher::Any, &"again ".repeat(100)).await;
+
+        let client = AiClient::new(&server.url(), "test_key", "test-model")?;
+        let response = client.complete_for_chat("Be nice", "Say hello").await?;
+
+        assert!(response.chars().count() <= MAX_MESSAGE_LENGTH);
+        assert!(response.starts_with("again again"));
+        assert!(response.ends_with("again…"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_truncate_strategy_does_not_retry() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let long = mock_completion(&mut server, Matcher::Any, &"word ".repeat(150)).await;
+
+        let mut client = AiClient::new(&server.url(), "test_key", "test-model")?;
+        client.set_length_strategy(LengthStrategy::Truncate);
+        let response = client.complete_for_chat("Be nice", "Say hello").await?;
+
+        long.assert_async().await;
+        assert!(response.chars().count() <= MAX_MESSAGE_LENGTH);
+        assert!(response.ends_with("word…"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_at_word_boundary() {
+        assert_eq!(truncate_at_word_boundary("short", 10), "short");
+        assert_eq!(
+            truncate_at_word_boundary("hello wonderful world", 12),
+            "hello…"
+        );
+        assert_eq!(
+            truncate_at_word_boundary("hello world again", 12),
+            "hello world…"
+        );
+        // A single long word has no boundary to cut at
+        assert_eq!(truncate_at_word_boundary("abcdefghij", 5), "abcd…");
+    }
+
+    #[tokio::test]
+    async fn test_complete_api_error() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .with_status(401)
+            .with_body(r#"{"error":{"message":"invalid api key"}}"#)
+            .create_async()
+            .await;
+
+        let client = AiClient::new(&server.url(), "bad_key", "test-model")?;
+        let result = client.complete("Be nice", "Say hello").await;
+
+        assert!(result.unwrap_err().to_string().contains("invalid api key"));
+        Ok(())
+    }
+}
@@ -0,0 +1,708 @@
+//! Recurring chat announcements, each on its own schedule
+//!
+//! Every announcement has its own interval and can be limited to a time of
+//! day, like a sponsor message that only runs in the evening. Announcements
+//! are kept in the shared store so they survive a restart; when each one last
+//! ran is only kept in memory, so after a restart each waits a full interval.
+//! Announcements can also be given in the config, and are added to the store
+//! when the bot starts.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Local, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Mutex as AsyncMutex;
+use tokio::time::Instant;
+use tracing::{error, info};
+
+use crate::duration::parse_duration;
+use crate::features::{ChannelFeatures, Feature};
+use crate::store::Store;
+use crate::twitch::{AnnouncementColor, HelixChatClient, MessageSender};
+
+/// Store namespace announcements are kept under, keyed by number
+const NAMESPACE: &str = "announcements";
+
+/// Shortest interval an announcement may have, so chat isn't flooded
+pub const MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Longest interval an announcement may have
+pub const MAX_INTERVAL: Duration = Duration::from_secs(30 * 86400);
+
+/// How often the scheduler checks for due announcements
+const TICK: Duration = Duration::from_secs(5);
+
+/// The time of day an announcement may run, in the bot's local time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActiveWindow {
+    /// When the window opens
+    pub start: NaiveTime,
+    /// When the window closes; before `start` means the window spans midnight
+    pub end: NaiveTime,
+}
+
+impl ActiveWindow {
+    /// Check whether a time of day falls in the window
+    ///
+    /// # Arguments
+    /// * `time` - The time of day
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl FromStr for ActiveWindow {
+    type Err = anyhow::Error;
+
+    /// Parse a window like "18:00-23:30"
+    fn from_str(s: &str) -> Result<Self> {
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| anyhow!("Time windows look like 18:00-23:30, not {}", s))?;
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .map_err(|_| anyhow!("Invalid time {} (use HH:MM)", time))
+        };
+
+        let window = ActiveWindow {
+            start: parse(start)?,
+            end: parse(end)?,
+        };
+        if window.start == window.end {
+            return Err(anyhow!("The time window {} is empty", s));
+        }
+        Ok(window)
+    }
+}
+
+impl fmt::Display for ActiveWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// A recurring announcement
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Announcement {
+    /// Number used to manage the announcement with !announce
+    pub id: u64,
+    /// The message posted in chat
+    pub message: String,
+    /// Seconds between posts
+    pub interval_secs: u64,
+    /// Time of day the announcement may run, if limited
+    pub window: Option<ActiveWindow>,
+    /// Whether the announcement is running
+    pub enabled: bool,
+    /// Login of the user who added the announcement
+    pub created_by: String,
+    /// When the announcement was added
+    pub created_at: DateTime<Utc>,
+}
+
+impl Announcement {
+    /// Get the time between posts
+    ///
+    /// Intervals edited into the store by hand are cut down to MAX_INTERVAL,
+    /// so scheduling the next post can't overflow.
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs).min(MAX_INTERVAL)
+    }
+}
+
+/// Check that an announcement's interval is between MIN_INTERVAL and MAX_INTERVAL
+///
+/// # Arguments
+/// * `interval` - Time between posts
+fn check_interval(interval: Duration) -> Result<()> {
+    if interval < MIN_INTERVAL {
+        return Err(anyhow!("Announcements can run at most once a minute"));
+    }
+    if interval > MAX_INTERVAL {
+        return Err(anyhow!("Announcements have to run at least every 30 days"));
+    }
+    Ok(())
+}
+
+/// An announcement like "15m 18:00-23:00 Follow the channel!", as given in
+/// the config or to !announce add
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ConfiguredAnnouncement {
+    /// The message posted in chat
+    pub message: String,
+    /// Time between posts
+    pub interval: Duration,
+    /// Time of day the announcement may run, if limited
+    pub window: Option<ActiveWindow>,
+}
+
+impl FromStr for ConfiguredAnnouncement {
+    type Err = anyhow::Error;
+
+    /// Parse `<interval> [HH:MM-HH:MM] <message>`
+    fn from_str(s: &str) -> Result<Self> {
+        let (interval, rest) = s
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| anyhow!("Announcements need an interval and a message"))?;
+        let interval =
+            parse_duration(interval).map_err(|_| anyhow!("Invalid interval: {}", interval))?;
+
+        // A leading HH:MM-HH:MM limits the time of day
+        let rest = rest.trim_start();
+        let (window, message) = match rest.split_once(char::is_whitespace) {
+            Some((first, message)) if first.contains(':') => {
+                (Some(first.parse::<ActiveWindow>()?), message.trim())
+            }
+            _ => (None, rest),
+        };
+        if message.is_empty() {
+            return Err(anyhow!("Announcements need an interval and a message"));
+        }
+        check_interval(interval)?;
+
+        Ok(ConfiguredAnnouncement {
+            message: message.to_string(),
+            interval,
+            window,
+        })
+    }
+}
+
+impl TryFrom<String> for ConfiguredAnnouncement {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// Keeps announcements and posts each one when it's due
+pub struct Announcer {
+    /// The client for sending messages
+    client: Arc<dyn MessageSender>,
+    /// Where announcements are kept
+    store: Arc<dyn Store>,
+    /// The channel announcements are posted in
+    channel: String,
+    /// When each announcement is next due, by number
+    next_due: Mutex<HashMap<u64, Instant>>,
+    /// Chat messages seen since the last announcement was posted
+    chat_messages: AtomicU64,
+    /// How many chat messages must arrive between announcements
+    min_chat_messages: u64,
+    /// Which features are on in each channel
+    features: Arc<ChannelFeatures>,
+    /// Helix client used to post Twitch announcements
+    helix: Option<Arc<AsyncMutex<HelixChatClient>>>,
+    /// Color scheduled announcements are posted in as Twitch announcements,
+    /// or None to post them as chat messages
+    color: Option<AnnouncementColor>,
+}
+
+impl Announcer {
+    /// Create an announcer
+    ///
+    /// # Arguments
+    /// * `client` - The Twitch client for sending messages
+    /// * `store` - Where announcements are kept
+    /// * `channel` - The channel announcements are posted in
+    ///
+    /// # Returns
+    /// A new Announcer instance
+    pub fn new(client: Arc<dyn MessageSender>, store: Arc<dyn Store>, channel: &str) -> Self {
+        Announcer {
+            client,
+            store,
+            channel: channel.to_string(),
+            next_due: Mutex::new(HashMap::new()),
+            chat_messages: AtomicU64::new(0),
+            min_chat_messages: 0,
+            features: Arc::new(ChannelFeatures::default()),
+            helix: None,
+            color: None,
+        }
+    }
+
+    /// Set the Helix client used to post Twitch announcements
+    ///
+    /// # Arguments
+    /// * `helix` - The Helix client for the announcements API
+    pub fn set_helix(&mut self, helix: Arc<AsyncMutex<HelixChatClient>>) {
+        self.helix = Some(helix);
+    }
+
+    /// Post scheduled announcements as highlighted Twitch announcements
+    ///
+    /// Needs a Helix client from `set_helix`.
+    ///
+    /// # Arguments
+    /// * `color` - The highlight color, or None to post chat messages
+    pub fn set_color(&mut self, color: Option<AnnouncementColor>) {
+        self.color = color;
+    }
+
+    /// Post a highlighted Twitch announcement
+    ///
+    /// # Arguments
+    /// * `channel` - The channel to announce in
+    /// * `message` - The announcement text
+    /// * `color` - The highlight color
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn post_twitch_announcement(
+        &self,
+        channel: &str,
+        message: &str,
+        color: AnnouncementColor,
+    ) -> Result<()> {
+        let Some(helix) = &self.helix else {
+            return Err(anyhow!("Twitch announcements need a Helix client"));
+        };
+
+        let mut helix = helix.lock().await;
+        let broadcaster_id = helix.get_broadcaster_id(channel).await?;
+        let moderator_id = helix.get_bot_user_id().await?;
+        helix
+            .send_announcement(&broadcaster_id, &moderator_id, message, color)
+            .await?;
+        Ok(())
+    }
+
+    /// Set which features are on in each channel
+    ///
+    /// # Arguments
+    /// * `features` - The features for each channel; nothing is posted if announcements are off
+    pub fn set_features(&mut self, features: Arc<ChannelFeatures>) {
+        self.features = features;
+    }
+
+    /// Hold announcements back until chat has been active
+    ///
+    /// Announcements that come due before enough messages have been sent
+    /// since the last announcement wait until they have, so the bot doesn't
+    /// talk to an empty channel.
+    ///
+    /// # Arguments
+    /// * `count` - How many chat messages must arrive between announcements (0 disables the check)
+    pub fn set_min_chat_messages(&mut self, count: u64) {
+        self.min_chat_messages = count;
+    }
+
+    /// Count a chat message towards the activity announcements wait for
+    ///
+    /// # Arguments
+    /// * `channel` - Login of the channel the message was sent in
+    pub fn record_chat_message(&self, channel: &str) {
+        if channel.eq_ignore_ascii_case(&self.channel) {
+            self.chat_messages.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Add the announcements given in the config that aren't stored yet
+    ///
+    /// An announcement counts as stored if one has the same message, so
+    /// changing a configured announcement's interval later has no effect
+    /// until the stored one is removed.
+    ///
+    /// # Arguments
+    /// * `configured` - The announcements from the config
+    ///
+    /// # Returns
+    /// How many announcements were added
+    pub fn add_configured(&self, configured: &[ConfiguredAnnouncement]) -> Result<usize> {
+        let stored = self.list()?;
+        let mut added = 0;
+        for announcement in configured {
+            if stored
+                .iter()
+                .any(|existing| existing.message == announcement.message)
+            {
+                continue;
+            }
+            self.add(
+                &announcement.message,
+                announcement.interval,
+                announcement.window,
+                "config",
+            )?;
+            added += 1;
+        }
+        Ok(added)
+    }
+
+    /// Get every announcement, ordered by number
+    pub fn list(&self) -> Result<Vec<Announcement>> {
+        let mut announcements: Vec<Announcement> = self
+            .store
+            .list_as(NAMESPACE)?
+            .into_iter()
+            .map(|(_, announcement)| announcement)
+            .collect();
+        announcements.sort_by_key(|announcement| announcement.id);
+        Ok(announcements)
+    }
+
+    /// Add an announcement, first posted one interval from now
+    ///
+    /// # Arguments
+    /// * `message` - The message to post
+    /// * `interval` - Time between posts, from a minute to 30 days
+    /// * `window` - Time of day the announcement may run, if limited
+    /// * `created_by` - Login of the user adding it
+    ///
+    /// # Returns
+    /// The new announcement
+    pub fn add(
+        &self,
+        message: &str,
+        interval: Duration,
+        window: Option<ActiveWindow>,
+        created_by: &str,
+    ) -> Result<Announcement> {
+        check_interval(interval)?;
+
+        let id = self
+            .list()?
+            .last()
+            .map_or(0, |announcement| announcement.id)
+            + 1;
+        let announcement = Announcement {
+            id,
+            message: message.to_string(),
+            interval_secs: interval.as_secs(),
+            window,
+            enabled: true,
+            created_by: created_by.to_string(),
+            created_at: Utc::now(),
+        };
+
+        self.store
+            .set_as(NAMESPACE, &id.to_string(), &announcement)?;
+        Ok(announcement)
+    }
+
+    /// Remove an announcement
+    ///
+    /// # Arguments
+    /// * `id` - The announcement's number
+    ///
+    /// # Returns
+    /// true if the announcement existed
+    pub fn remove(&self, id: u64) -> Result<bool> {
+        self.next_due.lock().unwrap().remove(&id);
+        self.store.remove(NAMESPACE, &id.to_string())
+    }
+
+    /// Turn an announcement on or off
+    ///
+    /// A re-enabled announcement waits a full interval before running.
+    ///
+    /// # Arguments
+    /// * `id` - The announcement's number
+    /// * `enabled` - Whether it should run
+    ///
+    /// # Returns
+    /// The updated announcement, or None if there's no announcement with that number
+    pub fn set_enabled(&self, id: u64, enabled: bool) -> Result<Option<Announcement>> {
+        let Some(mut announcement) = self
+            .store
+            .get_as::<Announcement>(NAMESPACE, &id.to_string())?
+        else {
+            return Ok(None);
+        };
+
+        announcement.enabled = enabled;
+        self.store
+            .set_as(NAMESPACE, &id.to_string(), &announcement)?;
+        self.next_due.lock().unwrap().remove(&id);
+        Ok(Some(announcement))
+    }
+
+    /// Find the announcements due now, and schedule their next run
+    ///
+    /// An announcement that comes due outside its window, or while chat is
+    /// too quiet, runs as soon as the window opens and chat has been active.
+    ///
+    /// # Arguments
+    /// * `time_of_day` - The current local time of day
+    ///
+    /// # Returns
+    /// The announcements to post
+    fn take_due(&self, time_of_day: NaiveTime) -> Result<Vec<Announcement>> {
+        let announcements = self.list()?;
+        let now = Instant::now();
+        let chat_active = self.chat_messages.load(Ordering::Relaxed) >= self.min_chat_messages;
+        let mut next_due = self.next_due.lock().unwrap();
+
+        // Forget announcements that were removed or turned off elsewhere
+        next_due.retain(|id, _| {
+            announcements
+                .iter()
+                .any(|announcement| announcement.id == *id && announcement.enabled)
+        });
+
+        let mut due = Vec::new();
+        for announcement in announcements.into_iter().filter(|a| a.enabled) {
+            let next = *next_due
+                .entry(announcement.id)
+                .or_insert_with(|| now + announcement.interval());
+            let in_window = announcement
+                .window
+                .is_none_or(|window| window.contains(time_of_day));
+
+            if now >= next && in_window && chat_active {
+                next_due.insert(announcement.id, now + announcement.interval());
+                due.push(announcement);
+            }
+        }
+        Ok(due)
+    }
+
+    /// Post the announcements that are due
+    ///
+    /// # Arguments
+    /// * `time_of_day` - The current local time of day
+    ///
+    /// # Returns
+    /// The numbers of the announcements posted
+    pub async fn tick(&self, time_of_day: NaiveTime) -> Result<Vec<u64>> {
+        if !self
+            .features
+            .is_enabled(&self.channel, Feature::Announcements)
+        {
+            return Ok(Vec::new());
+        }
+
+        let due = self.take_due(time_of_day)?;
+        if !due.is_empty() {
+            self.chat_messages.store(0, Ordering::Relaxed);
+        }
+
+        let mut posted = Vec::new();
+        for announcement in due {
+            info!("Posting announcement #{}", announcement.id);
+            match self.color {
+                Some(color) => {
+                    self.post_twitch_announcement(&self.channel, &announcement.message, color)
+                        .await?
+                }
+                None => {
+                    self.client
+                        .say(&self.channel, &announcement.message)
+                        .await?
+                }
+            }
+            posted.push(announcement.id);
+        }
+        Ok(posted)
+    }
+
+    /// Post announcements as they come due, forever
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            tokio::time::sleep(TICK).await;
+            if let Err(e) = self.tick(Local::now().time()).await {
+                error!("Error posting announcements: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{StoreBackend, open_store};
+    use crate::users::MockTwitchClient;
+    use tempfile::tempdir;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_active_window() -> Result<()> {
+        let evening: ActiveWindow = "18:00-23:30".parse()?;
+        assert!(evening.contains(time(18, 0)));
+        assert!(!evening.contains(time(23, 30)));
+        assert_eq!(evening.to_string(), "18:00-23:30");
+
+        // Windows can span midnight
+        let late: ActiveWindow = "22:00-02:00".parse()?;
+        assert!(late.contains(time(23, 0)));
+        assert!(late.contains(time(1, 0)));
+        assert!(!late.contains(time(12, 0)));
+
+        assert!("18:00".parse::<ActiveWindow>().is_err());
+        assert!("6pm-11pm".parse::<ActiveWindow>().is_err());
+        assert!("18:00-18:00".parse::<ActiveWindow>().is_err());
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_each_announcement_has_its_own_schedule() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let client = MockTwitchClient::new();
+        let announcer = Announcer::new(
+            Arc::new(client.clone()),
+            open_store(StoreBackend::File, temp_dir.path(), None)?,
+            "test_channel",
+        );
+        let noon = time(12, 0);
+
+        announcer.add("Follow!", Duration::from_secs(60), None, "mod")?;
+        announcer.add("Discord!", Duration::from_secs(150), None, "mod")?;
+        assert!(announcer.tick(noon).await?.is_empty());
+
+        let mut posted = Vec::new();
+        for _ in 0..6 {
+            tokio::time::advance(Duration::from_secs(60)).await;
+            posted.push(announcer.tick(noon).await?);
+        }
+        // Every minute for the first; at 3 and 6 minutes for the second,
+        // since it's checked once a minute here
+        assert_eq!(
+            posted,
+            vec![vec![1], vec![1], vec![1, 2], vec![1], vec![1], vec![1, 2]]
+        );
+        assert_eq!(client.sent_messages().len(), 8);
+
+        // A disabled announcement stops until it's enabled again
+        announcer.set_enabled(1, false)?;
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert!(announcer.tick(noon).await?.is_empty());
+        assert!(!announcer.list()?[0].enabled);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_announcement_waits_for_its_window() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let client = MockTwitchClient::new();
+        let announcer = Announcer::new(
+            Arc::new(client.clone()),
+            open_store(StoreBackend::File, temp_dir.path(), None)?,
+            "test_channel",
+        );
+
+        announcer.add(
+            "Evening sponsor",
+            Duration::from_secs(600),
+            Some("18:00-23:00".parse()?),
+            "mod",
+        )?;
+        assert!(
+            announcer
+                .add("Too often", Duration::from_secs(10), None, "mod")
+                .is_err()
+        );
+        assert!(announcer.tick(time(17, 0)).await?.is_empty());
+
+        // Due, but outside the window
+        tokio::time::advance(Duration::from_secs(600)).await;
+        assert!(announcer.tick(time(17, 10)).await?.is_empty());
+
+        // Runs as soon as the window opens
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert_eq!(announcer.tick(time(18, 0)).await?, vec![1]);
+        assert_eq!(
+            client.sent_messages(),
+            vec![("test_channel".to_string(), "Evening sponsor".to_string())]
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stored_interval_is_capped() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let store = open_store(StoreBackend::File, temp_dir.path(), None)?;
+        let announcer = Announcer::new(
+            Arc::new(MockTwitchClient::new()),
+            store.clone(),
+            "test_channel",
+        );
+        let mut announcement = announcer.add("Follow!", MIN_INTERVAL, None, "mod")?;
+
+        // An interval edited into the store by hand can't overflow the schedule
+        announcement.interval_secs = u64::MAX;
+        store.set_as(NAMESPACE, "1", &announcement)?;
+        assert_eq!(announcer.list()?[0].interval(), MAX_INTERVAL);
+        assert!(announcer.tick(time(12, 0)).await?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_configured_announcement() -> Result<()> {
+        let follow: ConfiguredAnnouncement = "15m Remember to follow!".parse()?;
+        assert_eq!(follow.interval, Duration::from_secs(900));
+        assert_eq!(follow.window, None);
+        assert_eq!(follow.message, "Remember to follow!");
+
+        let sponsor: ConfiguredAnnouncement = "1h 18:00-23:00 Check out our sponsor".parse()?;
+        assert_eq!(sponsor.window, Some("18:00-23:00".parse()?));
+        assert_eq!(sponsor.message, "Check out our sponsor");
+
+        assert!("15m".parse::<ConfiguredAnnouncement>().is_err());
+        assert!("soon Follow!".parse::<ConfiguredAnnouncement>().is_err());
+        assert!("10s Spam".parse::<ConfiguredAnnouncement>().is_err());
+        assert!("31d Spam".parse::<ConfiguredAnnouncement>().is_err());
+        assert!(
+            "1h 18:00 Follow!"
+                .parse::<ConfiguredAnnouncement>()
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_configured_announcements_wait_for_chat() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let client = MockTwitchClient::new();
+        let store = open_store(StoreBackend::File, temp_dir.path(), None)?;
+        let mut announcer = Announcer::new(Arc::new(client.clone()), store.clone(), "test_channel");
+        announcer.set_min_chat_messages(2);
+        let noon = time(12, 0);
+
+        let configured = vec!["1m Follow!".parse()?, "2m Discord!".parse()?];
+        assert_eq!(announcer.add_configured(&configured)?, 2);
+        // Already stored ones aren't added again on the next start
+        let restarted = Announcer::new(Arc::new(client.clone()), store, "test_channel");
+        assert_eq!(restarted.add_configured(&configured)?, 0);
+        assert_eq!(announcer.list()?.len(), 2);
+        assert!(announcer.tick(noon).await?.is_empty());
+
+        // Due, but chat has been quiet
+        tokio::time::advance(Duration::from_secs(60)).await;
+        announcer.record_chat_message("test_channel");
+        announcer.record_chat_message("other_channel");
+        assert!(announcer.tick(noon).await?.is_empty());
+
+        announcer.record_chat_message("Test_Channel");
+        assert_eq!(announcer.tick(noon).await?, vec![1]);
+
+        // The count starts over after posting
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert!(announcer.tick(noon).await?.is_empty());
+        announcer.record_chat_message("test_channel");
+        announcer.record_chat_message("test_channel");
+        assert_eq!(announcer.tick(noon).await?, vec![1, 2]);
+        assert_eq!(client.sent_messages().len(), 3);
+        Ok(())
+    }
+}
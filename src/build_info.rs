@@ -0,0 +1,60 @@
+//! Version and build details, set by the build script
+
+/// The crate version
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Short hash of the commit the bot was built from, if built from a git checkout
+pub const COMMIT: Option<&str> = option_env!("SOM_BUILD_COMMIT");
+
+/// The date the bot was built (UTC), as YYYY-MM-DD
+pub const BUILD_DATE: Option<&str> = option_env!("SOM_BUILD_DATE");
+
+/// Describe a build
+///
+/// # Arguments
+/// * `version` - The crate version
+/// * `commit` - The commit the build came from, if known
+/// * `build_date` - When it was built, if known
+///
+/// # Returns
+/// A line like "som_chatbot v0.1.0 (commit abc1234, built 2024-01-31)"
+pub fn describe(version: &str, commit: Option<&str>, build_date: Option<&str>) -> String {
+    let details: Vec<String> = [
+        commit.map(|commit| format!("commit {}", commit)),
+        build_date.map(|date| format!("built {}", date)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    if details.is_empty() {
+        format!("som_chatbot v{}", version)
+    } else {
+        format!("som_chatbot v{} ({})", version, details.join(", "))
+    }
+}
+
+/// Describe the running build
+pub fn current() -> String {
+    describe(VERSION, COMMIT, BUILD_DATE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(
+            describe("1.2.3", Some("abc1234"), Some("2024-01-31")),
+            "som_chatbot v1.2.3 (commit abc1234, built 2024-01-31)"
+        );
+        assert_eq!(describe("1.2.3", None, None), "som_chatbot v1.2.3");
+
+        let current = current();
+        assert!(current.contains(VERSION));
+        if let Some(commit) = COMMIT {
+            assert!(current.contains(commit));
+        }
+    }
+}
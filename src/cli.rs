@@ -14,9 +14,13 @@ pub struct Cli {
     #[arg(short, long)]
     pub debug: bool,
 
-    /// The command prefix for the bot
-    #[arg(short, long, default_value = "!")]
-    pub prefix: String,
+    /// Also write logs to this file, rotated daily (overrides LOG_FILE)
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<String>,
+
+    /// The command prefix for the bot (overrides config file, default "!")
+    #[arg(short, long)]
+    pub prefix: Option<String>,
 
     /// Subcommands
     #[command(subcommand)]
@@ -28,9 +32,13 @@ pub struct Cli {
 pub enum Commands {
     /// Start the bot
     Start {
-        /// Channel to join (overrides config file)
+        /// Channel to join (overrides TWITCH_CHANNEL and the config file)
         #[arg(short, long)]
         channel: Option<String>,
+
+        /// Connect to chat without TLS (overrides config file)
+        #[arg(long)]
+        no_tls: bool,
     },
 
     /// Generate a sample .env file
@@ -46,6 +54,23 @@ pub enum Commands {
         #[arg(short, long)]
         force: bool,
     },
+
+    /// Revoke the stored token with Twitch and delete it
+    Logout,
+
+    /// Export per-user chat statistics to a CSV file
+    ExportStats {
+        /// Path to write the CSV file to
+        #[arg(default_value = "chat_stats.csv")]
+        path: String,
+    },
+
+    /// Run offline, reading chat from stdin or a file and printing what the bot would send
+    Simulate {
+        /// File of chat messages, one per line like `alice: hello` (default: stdin)
+        #[arg(short, long, value_name = "FILE")]
+        input: Option<String>,
+    },
 }
 
 #[cfg(test)]
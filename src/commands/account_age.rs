@@ -0,0 +1,155 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext};
+use crate::duration::format_calendar_age;
+use crate::twitch::{HelixChatClient, HelixUser, TwitchError};
+
+/// How long a looked-up account stays cached
+const CACHE_TTL: Duration = Duration::from_secs(600);
+
+/// A command that shows how old a Twitch account is
+#[derive(Default)]
+pub struct AccountAgeCommand {
+    /// Recently looked-up users and when they were fetched, keyed by login
+    cache: std::sync::Mutex<HashMap<String, (HelixUser, Instant)>>,
+}
+
+impl AccountAgeCommand {
+    /// Create a new account age command
+    ///
+    /// # Returns
+    /// A new AccountAgeCommand instance
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Look up a user, using the cache when the entry is fresh
+    ///
+    /// # Arguments
+    /// * `helix` - The Helix client used to look up users
+    /// * `login` - The user's login name
+    ///
+    /// # Returns
+    /// The user, or None if no account has that name
+    async fn lookup(
+        &self,
+        helix: &Mutex<HelixChatClient>,
+        login: &str,
+    ) -> Result<Option<HelixUser>> {
+        if let Some((user, fetched)) = self.cache.lock().unwrap().get(login)
+            && fetched.elapsed() < CACHE_TTL
+        {
+            return Ok(Some(user.clone()));
+        }
+
+        let user = match helix.lock().await.get_user_by_login(login).await {
+            Ok(user) => user,
+            Err(TwitchError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|_, (_, fetched)| fetched.elapsed() < CACHE_TTL);
+        cache.insert(login.to_string(), (user.clone(), Instant::now()));
+        Ok(Some(user))
+    }
+}
+
+#[async_trait]
+impl Command for AccountAgeCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let login = match args.first() {
+            Some(target) => target.trim_start_matches('@').to_lowercase(),
+            None => msg.sender.login.clone(),
+        };
+
+        let Some(user) = self.lookup(ctx.helix()?, &login).await? else {
+            return Ok(Some(format!("Couldn't find a user named {}", login)));
+        };
+        let Some(created_at) = user.created_at else {
+            return Ok(Some(format!(
+                "Twitch didn't say when {}'s account was created",
+                user.display_name
+            )));
+        };
+
+        Ok(Some(format!(
+            "{}'s account is {} old (created {})",
+            user.display_name,
+            format_calendar_age(created_at, Utc::now()),
+            created_at.format("%Y-%m-%d")
+        )))
+    }
+
+    fn help(&self) -> &str {
+        "Shows how old a Twitch account is. Usage: !accountage [user]"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context_with_helix, create_test_privmsg_with_badges};
+    use mockito::{Matcher, Server};
+
+    #[tokio::test]
+    async fn test_account_age_defaults_to_caller_and_caches() -> Result<()> {
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/users")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"999","login":"test_bot","display_name":"Test_Bot"}]}"#)
+            .create_async()
+            .await;
+        let lookup = server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "test_user".into()))
+            .with_status(200)
+            .with_body(
+                r#"{"data":[{"id":"123","login":"test_user","display_name":"Test_User",
+                "created_at":"2016-12-14T20:32:28Z"}]}"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "nobody".into()))
+            .with_status(200)
+            .with_body(r#"{"data":[]}"#)
+            .create_async()
+            .await;
+
+        let ctx = create_test_context_with_helix(&server.url()).await;
+        let command = AccountAgeCommand::new();
+        let msg = create_test_privmsg_with_badges("!accountage", &[]);
+
+        let response = command.execute(&ctx, &msg, vec![]).await?.unwrap();
+        assert!(response.starts_with("Test_User's account is "));
+        assert!(response.ends_with("old (created 2016-12-14)"));
+
+        // The second lookup comes from the cache
+        let again = command.execute(&ctx, &msg, vec!["@Test_User"]).await?;
+        assert_eq!(again, Some(response));
+        lookup.assert_async().await;
+
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["nobody"]).await?,
+            Some("Couldn't find a user named nobody".to_string())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext, Permission};
+use crate::duration::format_ago;
+use crate::users::UserRecord;
+
+/// Describe how long ago something happened, if it's known
+fn ago(time: Option<DateTime<Utc>>) -> Option<String> {
+    time.map(|time| format_ago((Utc::now() - time).to_std().unwrap_or_default()))
+}
+
+/// Summarize what the bot knows about a user in one line
+fn describe(login: &str, record: &UserRecord) -> String {
+    let messages = match record.message_count {
+        1 => "1 message".to_string(),
+        count => format!("{} messages", count),
+    };
+    // Users migrated from the old text file have no timestamps
+    let first_seen = ago(record.first_seen).unwrap_or_else(|| "before tracking".to_string());
+    let last_seen = ago(record.last_seen).unwrap_or_else(|| "before tracking".to_string());
+    let welcomed = match ago(record.welcomed_at) {
+        Some(ago) => format!("welcomed {}", ago),
+        None => "never welcomed".to_string(),
+    };
+    format!(
+        "@{}: {}, first seen {}, last seen {}, {}",
+        login, messages, first_seen, last_seen, welcomed
+    )
+}
+
+/// A command that shows a user's chat activity to moderators
+pub struct ActivityCommand;
+
+#[async_trait]
+impl Command for ActivityCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        _msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let Some(login) = args
+            .first()
+            .map(|arg| arg.trim_start_matches('@').to_lowercase())
+        else {
+            return Ok(Some("Usage: !activity @user".to_string()));
+        };
+
+        let response = match ctx.user_manager.get_user_by_login(&login)? {
+            Some(record) => describe(&login, &record),
+            None => format!("I haven't seen @{} chat yet.", login),
+        };
+        Ok(Some(response))
+    }
+
+    fn help(&self) -> &str {
+        "Shows a user's message count, first and last seen, and welcome status. Usage: !activity @user"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+
+    #[tokio::test]
+    async fn test_activity_command() -> Result<()> {
+        let ctx = create_test_context();
+        let command = ActivityCommand;
+        let msg = create_test_privmsg_with_badges("!activity", &["moderator"]);
+        assert_eq!(command.permission(), Permission::Moderator);
+
+        ctx.user_manager.record_chat("300", "regular")?;
+        ctx.user_manager.record_chat("300", "regular")?;
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["@Regular"]).await?,
+            Some(
+                "@regular: 2 messages, first seen just now, last seen just now, never welcomed"
+                    .to_string()
+            )
+        );
+
+        ctx.user_manager.mark_welcomed("300")?;
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["regular"]).await?,
+            Some(
+                "@regular: 2 messages, first seen just now, last seen just now, welcomed just now"
+                    .to_string()
+            )
+        );
+
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["@stranger"]).await?,
+            Some("I haven't seen @stranger chat yet.".to_string())
+        );
+        assert_eq!(
+            command.execute(&ctx, &msg, vec![]).await?,
+            Some("Usage: !activity @user".to_string())
+        );
+        Ok(())
+    }
+}
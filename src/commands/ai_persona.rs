@@ -0,0 +1,135 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::info;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext, Permission};
+use crate::features::Feature;
+use crate::personality::{AiPersona, Personality};
+
+/// How !aipersona is used
+const USAGE: &str = "Usage: !aipersona friendly|snarky|formal|reload";
+
+/// A command that switches the tone of AI responses while the bot runs
+pub struct AiPersonaCommand {
+    persona: Arc<AiPersona>,
+    prompt_file: Option<PathBuf>,
+}
+
+impl AiPersonaCommand {
+    /// Create a new AI persona command
+    ///
+    /// # Arguments
+    /// * `persona` - The persona AI prompts are built from
+    /// * `prompt_file` - File `!aipersona reload` reads the prompt from, if configured
+    ///
+    /// # Returns
+    /// A new AiPersonaCommand instance
+    pub fn new(persona: Arc<AiPersona>, prompt_file: Option<PathBuf>) -> Self {
+        AiPersonaCommand {
+            persona,
+            prompt_file,
+        }
+    }
+}
+
+#[async_trait]
+impl Command for AiPersonaCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let Some(name) = args.first() else {
+            return Ok(Some(format!(
+                "AI persona: {}. {}",
+                self.persona.name(),
+                USAGE
+            )));
+        };
+
+        let response = if name.eq_ignore_ascii_case("reload") {
+            match &self.prompt_file {
+                Some(path) => match self.persona.load_file(path) {
+                    Ok(()) => {
+                        info!("{} reloaded the AI prompt", msg.sender.name);
+                        format!("Reloaded the AI prompt from {}.", path.display())
+                    }
+                    Err(e) => format!("Couldn't reload the AI prompt: {}", e),
+                },
+                None => "There's no AI_PROMPT_FILE to reload.".to_string(),
+            }
+        } else {
+            match name.parse::<Personality>() {
+                Ok(personality) => {
+                    self.persona.set_preset(personality);
+                    info!(
+                        "{} switched the AI persona to {}",
+                        msg.sender.name,
+                        self.persona.name()
+                    );
+                    format!("AI persona is now {}.", self.persona.name())
+                }
+                Err(e) => format!("{}. {}", e, USAGE),
+            }
+        };
+        Ok(Some(response))
+    }
+
+    fn help(&self) -> &str {
+        "Switches the tone of AI responses, or reloads the AI prompt file. Usage: !aipersona friendly|snarky|formal|reload"
+    }
+
+    fn feature(&self) -> Option<Feature> {
+        Some(Feature::Ai)
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Broadcaster
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_aipersona_command() -> Result<()> {
+        let ctx = create_test_context();
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("prompt.txt");
+        let persona = Arc::new(AiPersona::new(Personality::Friendly));
+        let command = AiPersonaCommand::new(persona.clone(), Some(path.clone()));
+        let msg = create_test_privmsg_with_badges("!aipersona", &["broadcaster"]);
+
+        assert_eq!(command.permission(), Permission::Broadcaster);
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["Snarky"]).await?,
+            Some("AI persona is now snarky.".to_string())
+        );
+        assert_eq!(persona.system_prompt(), Personality::Snarky.system_prompt());
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["grumpy"]).await?,
+            Some(format!("Unknown personality: grumpy. {}", USAGE))
+        );
+
+        std::fs::write(&path, "You are a pirate Twitch chatbot.")?;
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["reload"]).await?,
+            Some(format!("Reloaded the AI prompt from {}.", path.display()))
+        );
+        assert_eq!(persona.system_prompt(), "You are a pirate Twitch chatbot.");
+
+        let without_file = AiPersonaCommand::new(persona, None);
+        assert_eq!(
+            without_file.execute(&ctx, &msg, vec!["reload"]).await?,
+            Some("There's no AI_PROMPT_FILE to reload.".to_string())
+        );
+        Ok(())
+    }
+}
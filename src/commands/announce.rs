@@ -0,0 +1,308 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::info;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::announcements::{Announcement, Announcer, ConfiguredAnnouncement};
+use crate::commands::{Command, CommandContext, Permission};
+use crate::duration::format_duration;
+use crate::twitch::AnnouncementColor;
+
+/// How !announce is used
+const USAGE: &str = "Usage: !announce add <interval> [HH:MM-HH:MM] <message> | now [color] <message> | list | enable <id> | disable <id> | remove <id>";
+
+/// Describe an announcement in one line for !announce list
+fn describe(announcement: &Announcement) -> String {
+    let mut schedule = format!("every {}", format_duration(announcement.interval()));
+    if let Some(window) = announcement.window {
+        schedule.push_str(&format!(", {}", window));
+    }
+    if !announcement.enabled {
+        schedule.push_str(", off");
+    }
+    format!(
+        "#{} ({}): {}",
+        announcement.id, schedule, announcement.message
+    )
+}
+
+/// A command that manages recurring announcements
+pub struct AnnounceCommand {
+    announcer: Arc<Announcer>,
+}
+
+impl AnnounceCommand {
+    /// Create a new announce command
+    ///
+    /// # Arguments
+    /// * `announcer` - The announcer whose announcements are managed
+    ///
+    /// # Returns
+    /// A new AnnounceCommand instance
+    pub fn new(announcer: Arc<Announcer>) -> Self {
+        AnnounceCommand { announcer }
+    }
+
+    /// Add an announcement from `<interval> [HH:MM-HH:MM] <message>`
+    fn add(&self, msg: &PrivmsgMessage, args: &[&str]) -> Result<String> {
+        if args.is_empty() {
+            return Ok(USAGE.to_string());
+        }
+        let parsed = match args.join(" ").parse::<ConfiguredAnnouncement>() {
+            Ok(parsed) => parsed,
+            Err(e) => return Ok(e.to_string()),
+        };
+
+        match self.announcer.add(
+            &parsed.message,
+            parsed.interval,
+            parsed.window,
+            &msg.sender.login,
+        ) {
+            Ok(announcement) => {
+                info!(
+                    "{} added announcement #{}",
+                    msg.sender.name, announcement.id
+                );
+                Ok(format!("Added announcement {}", describe(&announcement)))
+            }
+            Err(e) => Ok(e.to_string()),
+        }
+    }
+
+    /// Post a Twitch announcement right away from `[color] <message>`
+    async fn post_now(&self, msg: &PrivmsgMessage, args: &[&str]) -> Result<Option<String>> {
+        // A leading color picks the highlight; anything else starts the message
+        let (color, words) = match args.split_first() {
+            Some((first, words)) => match first.parse::<AnnouncementColor>() {
+                Ok(color) => (color, words),
+                Err(_) => (AnnouncementColor::default(), args),
+            },
+            None => (AnnouncementColor::default(), args),
+        };
+        if words.is_empty() {
+            return Ok(Some(USAGE.to_string()));
+        }
+
+        self.announcer
+            .post_twitch_announcement(&msg.channel_login, &words.join(" "), color)
+            .await?;
+        info!("{} posted a {} announcement", msg.sender.name, color);
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl Command for AnnounceCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let Some((subcommand, rest)) = args.split_first() else {
+            return Ok(Some(USAGE.to_string()));
+        };
+        if subcommand.eq_ignore_ascii_case("now") {
+            return self.post_now(msg, rest).await;
+        }
+
+        let id = rest
+            .first()
+            .and_then(|id| id.trim_start_matches('#').parse().ok());
+
+        let response = match (subcommand.to_lowercase().as_str(), id) {
+            ("add", _) => self.add(msg, rest)?,
+            ("list", _) => {
+                let announcements = self.announcer.list()?;
+                if announcements.is_empty() {
+                    "There are no announcements.".to_string()
+                } else {
+                    announcements
+                        .iter()
+                        .map(describe)
+                        .collect::<Vec<_>>()
+                        .join(" | ")
+                }
+            }
+            (action @ ("enable" | "disable"), Some(id)) => {
+                match self.announcer.set_enabled(id, action == "enable")? {
+                    Some(announcement) if announcement.enabled => {
+                        format!("Announcement #{} is on.", id)
+                    }
+                    Some(_) => format!("Announcement #{} is off.", id),
+                    None => format!("There's no announcement #{}.", id),
+                }
+            }
+            ("remove", Some(id)) => {
+                if self.announcer.remove(id)? {
+                    info!("{} removed announcement #{}", msg.sender.name, id);
+                    format!("Removed announcement #{}.", id)
+                } else {
+                    format!("There's no announcement #{}.", id)
+                }
+            }
+            _ => USAGE.to_string(),
+        };
+        Ok(Some(response))
+    }
+
+    fn help(&self) -> &str {
+        "Manage recurring announcements, or post a Twitch announcement now. Usage: !announce add <interval> [HH:MM-HH:MM] <message> | now [color] <message> | list | enable <id> | disable <id> | remove <id>"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{StoreBackend, open_store};
+    use crate::test_helpers::{
+        create_authenticated_oauth_manager, create_test_context, create_test_privmsg_with_badges,
+    };
+    use crate::twitch::HelixChatClient;
+    use crate::users::MockTwitchClient;
+    use mockito::{Matcher, Server};
+    use tempfile::tempdir;
+    use tokio::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_announce_command() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let ctx = create_test_context();
+        let announcer = Arc::new(Announcer::new(
+            Arc::new(MockTwitchClient::new()),
+            open_store(StoreBackend::File, temp_dir.path(), None)?,
+            "test_channel",
+        ));
+        let command = AnnounceCommand::new(announcer.clone());
+        let msg = create_test_privmsg_with_badges("!announce", &["moderator"]);
+        let run = |args: &'static str| command.execute(&ctx, &msg, args.split(' ').collect());
+
+        assert_eq!(command.permission(), Permission::Moderator);
+        assert_eq!(
+            run("add 15m Follow the channel!").await?,
+            Some("Added announcement #1 (every 15m): Follow the channel!".to_string())
+        );
+        assert_eq!(
+            run("add 1h 18:00-23:00 Check out our sponsor").await?,
+            Some(
+                "Added announcement #2 (every 1h, 18:00-23:00): Check out our sponsor".to_string()
+            )
+        );
+        assert_eq!(
+            run("add 10s Spam").await?,
+            Some("Announcements can run at most once a minute".to_string())
+        );
+        assert_eq!(
+            run("add 99999999h Spam").await?,
+            Some("Invalid interval: 99999999h".to_string())
+        );
+        assert_eq!(
+            run("add 1h 18:00 Spam").await?,
+            Some("Time windows look like 18:00-23:30, not 18:00".to_string())
+        );
+        assert_eq!(
+            run("add 15m").await?,
+            Some("Announcements need an interval and a message".to_string())
+        );
+        assert_eq!(
+            run("add 31d Spam").await?,
+            Some("Announcements have to run at least every 30 days".to_string())
+        );
+
+        assert_eq!(
+            run("disable 1").await?,
+            Some("Announcement #1 is off.".to_string())
+        );
+        assert_eq!(
+            run("list").await?,
+            Some(
+                "#1 (every 15m, off): Follow the channel! | #2 (every 1h, 18:00-23:00): Check out our sponsor"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            run("enable #1").await?,
+            Some("Announcement #1 is on.".to_string())
+        );
+        assert_eq!(
+            run("remove 2").await?,
+            Some("Removed announcement #2.".to_string())
+        );
+        assert_eq!(
+            run("enable 5").await?,
+            Some("There's no announcement #5.".to_string())
+        );
+        assert_eq!(announcer.list()?.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_announce_now_posts_twitch_announcement() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let ctx = create_test_context();
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/users")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"999","login":"test_bot","display_name":"Test_Bot"}]}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "test_channel".into()))
+            .with_status(200)
+            .with_body(
+                r#"{"data":[{"id":"456","login":"test_channel","display_name":"Test_Channel"}]}"#,
+            )
+            .create_async()
+            .await;
+        let announcement = server
+            .mock("POST", "/chat/announcements")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("broadcaster_id".into(), "456".into()),
+                Matcher::UrlEncoded("moderator_id".into(), "999".into()),
+            ]))
+            .match_body(Matcher::Json(serde_json::json!({
+                "message": "Giveaway starts now!", "color": "purple"
+            })))
+            .with_status(204)
+            .create_async()
+            .await;
+
+        let helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+        let mut announcer = Announcer::new(
+            Arc::new(MockTwitchClient::new()),
+            open_store(StoreBackend::File, temp_dir.path(), None)?,
+            "home_channel",
+        );
+        announcer.set_helix(Arc::new(Mutex::new(helix)));
+        let command = AnnounceCommand::new(Arc::new(announcer));
+        // The announcement goes to the channel the command was typed in
+        let msg = create_test_privmsg_with_badges("!announce", &["moderator"]);
+
+        let result = command
+            .execute(
+                &ctx,
+                &msg,
+                vec!["now", "purple", "Giveaway", "starts", "now!"],
+            )
+            .await?;
+        assert_eq!(result, None);
+        announcement.assert_async().await;
+
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["now", "blue"]).await?,
+            Some(USAGE.to_string())
+        );
+        Ok(())
+    }
+}
@@ -1,12 +1,24 @@
-use crate::commands::Command;
+use crate::build_info;
+use crate::commands::{Command, CommandContext, CommandPrefixes, CommandRegistry};
 use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
 use twitch_irc::message::PrivmsgMessage;
 
 /// A simple ping command that responds with "Pong!"
 pub struct PingCommand;
 
+#[async_trait]
 impl Command for PingCommand {
-    fn execute(&self, msg: &PrivmsgMessage, _args: Vec<&str>) -> Result<Option<String>> {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        _args: Vec<&str>,
+    ) -> Result<Option<String>> {
         // Echo the message and sender name to confirm we're receiving commands
         Ok(Some(format!(
             "Pong! Received from {} who said: {}",
@@ -19,57 +31,154 @@ impl Command for PingCommand {
     }
 }
 
-/// A command that displays help information for all commands
+/// A command that reports which build of the bot is running
+pub struct VersionCommand;
+
+#[async_trait]
+impl Command for VersionCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        _msg: &PrivmsgMessage,
+        _args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        Ok(Some(build_info::current()))
+    }
+
+    fn help(&self) -> &str {
+        "Shows the bot's version, build commit, and build date"
+    }
+}
+
+/// List a registry's commands in alphabetical order, each with its prefix
+fn list_commands(registry: &CommandRegistry, prefix: &str) -> String {
+    let mut names = registry.get_command_names();
+    names.sort();
+    names
+        .iter()
+        .map(|name| format!("{}{}", prefix, name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A command that lists the commands actually registered, so the list can't
+/// drift from what the bot answers
+pub struct CommandsCommand {
+    prefixes: CommandPrefixes,
+    registry: Arc<RwLock<CommandRegistry>>,
+}
+
+impl CommandsCommand {
+    /// Create a new commands command
+    ///
+    /// # Arguments
+    /// * `prefixes` - The command prefixes, so each channel sees its own
+    /// * `registry` - The registry whose commands are listed
+    ///
+    /// # Returns
+    /// A new CommandsCommand instance
+    pub fn new(prefixes: CommandPrefixes, registry: Arc<RwLock<CommandRegistry>>) -> Self {
+        CommandsCommand { prefixes, registry }
+    }
+}
+
+#[async_trait]
+impl Command for CommandsCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let registry = self.registry.read().await;
+        let prefix = self.prefixes.for_channel(&msg.channel_login);
+
+        let Some(name) = args.first() else {
+            return Ok(Some(format!(
+                "Commands: {}",
+                list_commands(&registry, prefix)
+            )));
+        };
+
+        // Aliases and a typed prefix both find the command
+        let name = name.trim_start_matches(prefix);
+        let response = match registry.get_command(name) {
+            Some(command) => format!("{}{}: {}", prefix, name.to_lowercase(), command.help()),
+            None => format!("Unknown command: {}{}", prefix, name),
+        };
+        Ok(Some(response))
+    }
+
+    fn help(&self) -> &str {
+        "Lists every command, or shows what one does. Usage: !commands [command]"
+    }
+}
+
+/// A command that displays help information for all commands, taken from the
+/// commands themselves
 pub struct HelpCommand {
-    prefix: String,
-    descriptions: Vec<(String, String)>,
+    prefixes: CommandPrefixes,
+    registry: Arc<RwLock<CommandRegistry>>,
 }
 
 impl HelpCommand {
     /// Create a new help command
     ///
     /// # Arguments
-    /// * `prefix` - The command prefix (e.g., "!")
-    /// * `descriptions` - A list of (command_name, help_text) pairs
+    /// * `prefixes` - The command prefixes, so each channel sees its own
+    /// * `registry` - The registry whose commands are described
     ///
     /// # Returns
     /// A new HelpCommand instance
-    pub fn new(prefix: String, descriptions: Vec<(String, String)>) -> Self {
-        HelpCommand {
-            prefix,
-            descriptions,
-        }
+    pub fn new(prefixes: CommandPrefixes, registry: Arc<RwLock<CommandRegistry>>) -> Self {
+        HelpCommand { prefixes, registry }
     }
 }
 
+#[async_trait]
 impl Command for HelpCommand {
-    fn execute(&self, _msg: &PrivmsgMessage, args: Vec<&str>) -> Result<Option<String>> {
-        if args.is_empty() {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let registry = self.registry.read().await;
+        let prefix = self.prefixes.for_channel(&msg.channel_login);
+
+        let Some(name) = args.first() else {
             // Show a list of all commands
-            let commands: Vec<String> = self
-                .descriptions
-                .iter()
-                .map(|(name, _)| format!("{}{}", self.prefix, name))
-                .collect();
-
-            Ok(Some(format!("Available commands: {}", commands.join(", "))))
-        } else {
-            // Show help for a specific command
-            let command_name = args[0].to_lowercase();
-
-            if let Some((_, help)) = self
-                .descriptions
-                .iter()
-                .find(|(name, _)| name.to_lowercase() == command_name)
-            {
-                Ok(Some(help.clone()))
-            } else {
-                Ok(Some(format!(
-                    "Unknown command: {}{}",
-                    self.prefix, command_name
-                )))
-            }
+            return Ok(Some(format!(
+                "Available commands: {}",
+                list_commands(&registry, prefix)
+            )));
+        };
+
+        // Show help for a specific command
+        let name = name.trim_start_matches(prefix).to_lowercase();
+        match registry.get_command(&name) {
+            Some(command) => Ok(Some(command.help().to_string())),
+            None => Ok(Some(format!("Unknown command: {}{}", prefix, name))),
+        }
+    }
+
+    async fn execute_lines(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Vec<String>> {
+        let listing = args.is_empty();
+        let mut lines: Vec<String> = self.execute(ctx, msg, args).await?.into_iter().collect();
+
+        // Point at per-command help after the list of commands
+        if listing {
+            lines.push(format!(
+                "Type {}help <command> to see what a command does.",
+                self.prefixes.for_channel(&msg.channel_login)
+            ));
         }
+        Ok(lines)
     }
 
     fn help(&self) -> &str {
@@ -77,9 +186,125 @@ impl Command for HelpCommand {
     }
 }
 
-/// A command that shows information about uptime
+/// Unit words used when displaying uptime
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UptimeUnits {
+    pub day: String,
+    pub hour: String,
+    pub minute: String,
+    pub second: String,
+}
+
+impl Default for UptimeUnits {
+    fn default() -> Self {
+        UptimeUnits::new("d", "h", "m", "s")
+    }
+}
+
+impl UptimeUnits {
+    /// Create a set of unit words
+    ///
+    /// # Arguments
+    /// * `day` - The suffix for days
+    /// * `hour` - The suffix for hours
+    /// * `minute` - The suffix for minutes
+    /// * `second` - The suffix for seconds
+    ///
+    /// # Returns
+    /// A new UptimeUnits instance
+    pub fn new(day: &str, hour: &str, minute: &str, second: &str) -> Self {
+        UptimeUnits {
+            day: day.to_string(),
+            hour: hour.to_string(),
+            minute: minute.to_string(),
+            second: second.to_string(),
+        }
+    }
+
+    /// Get the unit words for a language
+    ///
+    /// # Arguments
+    /// * `locale` - A language code like "en" or "de-DE"
+    ///
+    /// # Returns
+    /// The unit words for the language, or None if it isn't supported
+    pub fn for_locale(locale: &str) -> Option<Self> {
+        let language = locale
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match language.as_str() {
+            "en" => Some(UptimeUnits::default()),
+            "de" => Some(UptimeUnits::new("T", "Std", "Min", "Sek")),
+            "es" => Some(UptimeUnits::new("d", "h", "min", "s")),
+            "fr" => Some(UptimeUnits::new("j", "h", "min", "s")),
+            _ => None,
+        }
+    }
+}
+
+/// Format an uptime like "1d 6h 3m 0s", leaving out days when there are none
+///
+/// # Arguments
+/// * `elapsed` - How long the bot has been running
+/// * `units` - The unit words to use
+///
+/// # Returns
+/// The formatted uptime
+pub fn format_uptime(elapsed: Duration, units: &UptimeUnits) -> String {
+    let total = elapsed.as_secs();
+    let days = total / 86400;
+    let hours = (total % 86400) / 3600;
+    let minutes = (total % 3600) / 60;
+    let seconds = total % 60;
+
+    let time = format!(
+        "{}{} {}{} {}{}",
+        hours, units.hour, minutes, units.minute, seconds, units.second
+    );
+
+    if days > 0 {
+        format!("{}{} {}", days, units.day, time)
+    } else {
+        time
+    }
+}
+
+/// Format an uptime compactly, like "1:02:03" or "1:06:03:00" with days
+///
+/// # Arguments
+/// * `elapsed` - How long the bot has been running
+///
+/// # Returns
+/// The formatted uptime
+pub fn format_uptime_compact(elapsed: Duration) -> String {
+    let total = elapsed.as_secs();
+    let days = total / 86400;
+    let hours = (total % 86400) / 3600;
+    let minutes = (total % 3600) / 60;
+    let seconds = total % 60;
+
+    if days > 0 {
+        format!("{}:{:02}:{:02}:{:02}", days, hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    }
+}
+
+/// Format an uptime in the style picked by the command's arguments
+fn format_uptime_for_args(elapsed: Duration, units: &UptimeUnits, args: &[&str]) -> String {
+    match args.first() {
+        Some(arg) if arg.eq_ignore_ascii_case("compact") => format_uptime_compact(elapsed),
+        _ => format_uptime(elapsed, units),
+    }
+}
+
+/// A command that shows how long the bot process has been running
 pub struct UptimeCommand {
     started_at: std::time::Instant,
+    units: UptimeUnits,
 }
 
 impl UptimeCommand {
@@ -87,36 +312,99 @@ impl UptimeCommand {
     ///
     /// # Returns
     /// A new UptimeCommand instance
+    #[allow(dead_code)]
     pub fn new() -> Self {
+        Self::with_units(UptimeUnits::default())
+    }
+
+    /// Create a new uptime command with localized unit words
+    ///
+    /// # Arguments
+    /// * `units` - The unit words to display
+    ///
+    /// # Returns
+    /// A new UptimeCommand instance
+    pub fn with_units(units: UptimeUnits) -> Self {
         UptimeCommand {
             started_at: std::time::Instant::now(),
+            units,
         }
     }
 }
 
+#[async_trait]
 impl Command for UptimeCommand {
-    fn execute(&self, _msg: &PrivmsgMessage, _args: Vec<&str>) -> Result<Option<String>> {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        _msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
         let elapsed = self.started_at.elapsed();
+        let uptime = format_uptime_for_args(elapsed, &self.units, &args);
 
-        let hours = elapsed.as_secs() / 3600;
-        let minutes = (elapsed.as_secs() % 3600) / 60;
-        let seconds = elapsed.as_secs() % 60;
+        Ok(Some(format!("Bot has been running for {}", uptime)))
+    }
 
-        Ok(Some(format!(
-            "Bot has been running for {}h {}m {}s",
-            hours, minutes, seconds
-        )))
+    fn help(&self) -> &str {
+        "Shows how long the bot has been running. Usage: !botuptime [compact]"
+    }
+}
+
+/// A command that shows how long the channel's stream has been live
+pub struct StreamUptimeCommand {
+    units: UptimeUnits,
+}
+
+impl StreamUptimeCommand {
+    /// Create a new stream uptime command
+    ///
+    /// # Arguments
+    /// * `units` - The unit words to display
+    ///
+    /// # Returns
+    /// A new StreamUptimeCommand instance
+    pub fn new(units: UptimeUnits) -> Self {
+        StreamUptimeCommand { units }
+    }
+}
+
+#[async_trait]
+impl Command for StreamUptimeCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let started_at = ctx
+            .helix()?
+            .lock()
+            .await
+            .get_stream_started_at(&msg.channel_login)
+            .await?;
+
+        let Some(started_at) = started_at else {
+            return Ok(Some("Stream is offline".to_string()));
+        };
+
+        let elapsed = (Utc::now() - started_at).to_std().unwrap_or_default();
+        let uptime = format_uptime_for_args(elapsed, &self.units, &args);
+
+        Ok(Some(format!("Stream has been live for {}", uptime)))
     }
 
     fn help(&self) -> &str {
-        "Shows how long the bot has been running"
+        "Shows how long the stream has been live. Usage: !uptime [compact]"
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
+    use crate::test_helpers::{create_test_context, create_test_context_with_helix};
+    use mockito::{Matcher, Server};
+    use std::collections::HashMap;
     use twitch_irc::message::{Badge, Emote, IRCMessage, IRCPrefix, IRCTags, TwitchUserBasics};
 
     fn create_dummy_privmsg() -> PrivmsgMessage {
@@ -150,38 +438,266 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_ping_command() {
+    #[tokio::test]
+    async fn test_ping_command() {
+        let ctx = create_test_context();
         let command = PingCommand;
 
         // Create a dummy message
         let msg = create_dummy_privmsg();
 
         // Execute the command
-        let result = command.execute(&msg, Vec::new()).unwrap();
+        let result = command.execute(&ctx, &msg, Vec::new()).await.unwrap();
 
         // Assert the result contains "Pong!"
         assert!(result.unwrap().contains("Pong!"));
     }
 
-    #[test]
-    fn test_help_command() {
-        let descriptions = vec![
-            ("ping".to_string(), "Responds with Pong!".to_string()),
-            ("help".to_string(), "Shows help information".to_string()),
-        ];
+    #[tokio::test]
+    async fn test_version_command() -> Result<()> {
+        let ctx = create_test_context();
+        let result = VersionCommand
+            .execute(&ctx, &create_dummy_privmsg(), Vec::new())
+            .await?
+            .unwrap();
 
-        let command = HelpCommand::new("!".to_string(), descriptions);
+        assert!(result.contains(build_info::VERSION));
+        if let Some(commit) = build_info::COMMIT {
+            assert!(result.contains(commit));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_help_command() {
+        let ctx = create_test_context();
+        let registry = Arc::new(RwLock::new(CommandRegistry::new()));
+        let command = Arc::new(HelpCommand::new(
+            CommandPrefixes::new("!".to_string(), HashMap::new()).unwrap(),
+            registry.clone(),
+        ));
+        {
+            let mut registry = registry.write().await;
+            registry.register("ping", Arc::new(PingCommand));
+            registry.register("help", command.clone());
+        }
 
         // Create a dummy message
         let msg = create_dummy_privmsg();
 
         // Execute the command with no args (list all commands)
-        let result = command.execute(&msg, Vec::new()).unwrap();
-        assert_eq!(result, Some("Available commands: !ping, !help".to_string()));
+        let result = command.execute(&ctx, &msg, Vec::new()).await.unwrap();
+        assert_eq!(result, Some("Available commands: !help, !ping".to_string()));
 
-        // Execute the command with a specific command
-        let result = command.execute(&msg, vec!["ping"]).unwrap();
+        // Execute the command with a specific command, with or without its prefix
+        let result = command.execute(&ctx, &msg, vec!["ping"]).await.unwrap();
         assert_eq!(result, Some("Responds with Pong!".to_string()));
+        let result = command.execute(&ctx, &msg, vec!["!PING"]).await.unwrap();
+        assert_eq!(result, Some("Responds with Pong!".to_string()));
+
+        // The list of commands is followed by a hint about per-command help
+        let lines = command.execute_lines(&ctx, &msg, Vec::new()).await.unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                "Available commands: !help, !ping".to_string(),
+                "Type !help <command> to see what a command does.".to_string()
+            ]
+        );
+        let lines = command
+            .execute_lines(&ctx, &msg, vec!["ping"])
+            .await
+            .unwrap();
+        assert_eq!(lines, vec!["Responds with Pong!".to_string()]);
+
+        // Commands registered later are described too
+        registry
+            .write()
+            .await
+            .register("version", Arc::new(VersionCommand));
+        let result = command.execute(&ctx, &msg, vec!["version"]).await.unwrap();
+        assert_eq!(
+            result,
+            Some("Shows the bot's version, build commit, and build date".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_help_uses_channel_prefix() {
+        let ctx = create_test_context();
+        let registry = Arc::new(RwLock::new(CommandRegistry::new()));
+        let prefixes = CommandPrefixes::new(
+            "!".to_string(),
+            HashMap::from([("test_channel".to_string(), "?".to_string())]),
+        )
+        .unwrap();
+        let help = Arc::new(HelpCommand::new(prefixes.clone(), registry.clone()));
+        let commands = Arc::new(CommandsCommand::new(prefixes, registry.clone()));
+        registry
+            .write()
+            .await
+            .register("ping", Arc::new(PingCommand));
+
+        let msg = create_dummy_privmsg();
+        let lines = help.execute_lines(&ctx, &msg, Vec::new()).await.unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                "Available commands: ?ping".to_string(),
+                "Type ?help <command> to see what a command does.".to_string()
+            ]
+        );
+        assert_eq!(
+            commands.execute(&ctx, &msg, vec!["?ping"]).await.unwrap(),
+            Some("?ping: Responds with Pong!".to_string())
+        );
+        assert_eq!(
+            commands.execute(&ctx, &msg, vec!["dance"]).await.unwrap(),
+            Some("Unknown command: ?dance".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commands_command_lists_registry() {
+        let ctx = create_test_context();
+        let msg = create_dummy_privmsg();
+        let registry = Arc::new(RwLock::new(CommandRegistry::new()));
+        let command = Arc::new(CommandsCommand::new(
+            CommandPrefixes::new("!".to_string(), HashMap::new()).unwrap(),
+            registry.clone(),
+        ));
+        {
+            let mut registry = registry.write().await;
+            registry.register("version", Arc::new(VersionCommand));
+            registry.register("ping", Arc::new(PingCommand));
+            registry.register("commands", command.clone());
+            registry.register_alias("pong", "ping");
+        }
+
+        assert_eq!(
+            command.execute(&ctx, &msg, Vec::new()).await.unwrap(),
+            Some("Commands: !commands, !ping, !version".to_string())
+        );
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["!Pong"]).await.unwrap(),
+            Some("!pong: Responds with Pong!".to_string())
+        );
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["dance"]).await.unwrap(),
+            Some("Unknown command: !dance".to_string())
+        );
+
+        // Commands added later show up without any other change
+        registry.write().await.unregister("version");
+        registry
+            .write()
+            .await
+            .register("botuptime", Arc::new(UptimeCommand::new()));
+        assert_eq!(
+            command.execute(&ctx, &msg, Vec::new()).await.unwrap(),
+            Some("Commands: !botuptime, !commands, !ping".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_uptime() {
+        let units = UptimeUnits::default();
+        assert_eq!(format_uptime(Duration::from_secs(3723), &units), "1h 2m 3s");
+        // 30 hours rolls over into days
+        assert_eq!(
+            format_uptime(Duration::from_secs(30 * 3600 + 180), &units),
+            "1d 6h 3m 0s"
+        );
+        assert_eq!(
+            format_uptime(Duration::from_secs(86399), &units),
+            "23h 59m 59s"
+        );
+        assert_eq!(
+            format_uptime(Duration::from_secs(86400), &units),
+            "1d 0h 0m 0s"
+        );
+    }
+
+    #[test]
+    fn test_format_uptime_compact() {
+        assert_eq!(format_uptime_compact(Duration::from_secs(3723)), "1:02:03");
+        assert_eq!(format_uptime_compact(Duration::from_secs(59)), "0:00:59");
+        assert_eq!(
+            format_uptime_compact(Duration::from_secs(30 * 3600 + 180)),
+            "1:06:03:00"
+        );
+    }
+
+    #[test]
+    fn test_localized_uptime_units() {
+        let units = UptimeUnits::for_locale("de-DE").unwrap();
+        assert_eq!(
+            format_uptime(Duration::from_secs(90061), &units),
+            "1T 1Std 1Min 1Sek"
+        );
+        assert!(UptimeUnits::for_locale("xx").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_uptime_command_compact() {
+        let ctx = create_test_context();
+        let command = UptimeCommand::new();
+        let msg = create_dummy_privmsg();
+
+        let result = command.execute(&ctx, &msg, vec!["compact"]).await.unwrap();
+        assert_eq!(result, Some("Bot has been running for 0:00:00".to_string()));
+
+        let result = command.execute(&ctx, &msg, Vec::new()).await.unwrap();
+        assert_eq!(
+            result,
+            Some("Bot has been running for 0h 0m 0s".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_uptime_live() {
+        let mut server = Server::new_async().await;
+        let started_at = (Utc::now() - chrono::Duration::seconds(3723)).to_rfc3339();
+        let _mock = server
+            .mock("GET", "/streams")
+            .match_query(Matcher::UrlEncoded(
+                "user_login".into(),
+                "test_channel".into(),
+            ))
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"data":[{{"id":"1","user_login":"test_channel","type":"live","started_at":"{}"}}]}}"#,
+                started_at
+            ))
+            .create_async()
+            .await;
+
+        let ctx = create_test_context_with_helix(&server.url()).await;
+        let command = StreamUptimeCommand::new(UptimeUnits::default());
+        let result = command
+            .execute(&ctx, &create_dummy_privmsg(), vec!["compact"])
+            .await
+            .unwrap();
+        assert_eq!(result, Some("Stream has been live for 1:02:03".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_stream_uptime_offline() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/streams")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(r#"{"data":[],"pagination":{}}"#)
+            .create_async()
+            .await;
+
+        let ctx = create_test_context_with_helix(&server.url()).await;
+        let command = StreamUptimeCommand::new(UptimeUnits::default());
+        let result = command
+            .execute(&ctx, &create_dummy_privmsg(), Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(result, Some("Stream is offline".to_string()));
     }
 }
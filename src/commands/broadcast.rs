@@ -0,0 +1,153 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::{error, info};
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{ArgMode, Command, CommandContext, Permission};
+use crate::twitch::{JoinedChannels, MessageSender};
+
+/// A command that sends one message to every channel the bot is in
+///
+/// Only the broadcaster of the bot's home channel, the one it was started
+/// for, may use it; other channels' broadcasters can't post in each other's chat.
+pub struct BroadcastCommand {
+    /// The client for sending messages
+    client: Arc<dyn MessageSender>,
+    channels: Arc<JoinedChannels>,
+}
+
+impl BroadcastCommand {
+    /// Create a new broadcast command
+    ///
+    /// # Arguments
+    /// * `client` - The Twitch client for sending messages
+    /// * `channels` - The tracked set of joined channels
+    ///
+    /// # Returns
+    /// A new BroadcastCommand instance
+    pub fn new(client: Arc<dyn MessageSender>, channels: Arc<JoinedChannels>) -> Self {
+        BroadcastCommand { client, channels }
+    }
+}
+
+#[async_trait]
+impl Command for BroadcastCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        if !msg
+            .channel_login
+            .eq_ignore_ascii_case(ctx.channel.trim_start_matches('#'))
+        {
+            info!(
+                "{} tried to broadcast from {}, which isn't the home channel",
+                msg.sender.name, msg.channel_login
+            );
+            return Ok(Some(format!(
+                "!broadcast only works in {}'s chat.",
+                ctx.channel
+            )));
+        }
+
+        let Some(message) = args.first().map(|text| text.trim()) else {
+            return Ok(Some("Usage: !broadcast <message>".to_string()));
+        };
+
+        // Each send goes through the client's rate limiting, so a failure in
+        // one channel doesn't stop the rest
+        let mut failed = Vec::new();
+        for channel in self.channels.names() {
+            if let Err(e) = self.client.say(&channel, message).await {
+                error!("Error broadcasting to {}: {}", channel, e);
+                failed.push(channel);
+            }
+        }
+        info!("{} broadcast a message", msg.sender.name);
+
+        if failed.is_empty() {
+            // The broadcast itself shows up here, so there's nothing to add
+            Ok(None)
+        } else {
+            Ok(Some(format!("Couldn't send to {}.", failed.join(", "))))
+        }
+    }
+
+    fn help(&self) -> &str {
+        "Sends a message to every channel the bot is in. Usage: !broadcast <message>"
+    }
+
+    fn arg_mode(&self) -> ArgMode {
+        ArgMode::Raw
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Broadcaster
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+    use crate::users::MockTwitchClient;
+
+    #[tokio::test]
+    async fn test_broadcast_sends_to_every_channel() -> Result<()> {
+        let ctx = create_test_context();
+        let client = Arc::new(MockTwitchClient::new());
+        let channels = Arc::new(JoinedChannels::new());
+        channels.join("test_channel");
+        channels.join("other_channel");
+        let command = BroadcastCommand::new(client.clone(), channels);
+        let msg = create_test_privmsg_with_badges("!broadcast", &["broadcaster"]);
+
+        assert_eq!(command.permission(), Permission::Broadcaster);
+        let result = command
+            .execute(&ctx, &msg, vec!["Live on YouTube now!"])
+            .await?;
+        assert_eq!(result, None);
+        assert_eq!(
+            client.sent_messages(),
+            vec![
+                (
+                    "other_channel".to_string(),
+                    "Live on YouTube now!".to_string()
+                ),
+                (
+                    "test_channel".to_string(),
+                    "Live on YouTube now!".to_string()
+                ),
+            ]
+        );
+
+        assert_eq!(
+            command.execute(&ctx, &msg, vec![]).await?,
+            Some("Usage: !broadcast <message>".to_string())
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_refused_in_other_channels() -> Result<()> {
+        let ctx = create_test_context();
+        let client = Arc::new(MockTwitchClient::new());
+        let channels = Arc::new(JoinedChannels::new());
+        channels.join("test_channel");
+        channels.join("other_channel");
+        let command = BroadcastCommand::new(client.clone(), channels);
+
+        // The broadcaster of another joined channel can't post everywhere
+        let mut msg = create_test_privmsg_with_badges("!broadcast", &["broadcaster"]);
+        msg.channel_login = "other_channel".to_string();
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["Follow me!"]).await?,
+            Some("!broadcast only works in test_channel's chat.".to_string())
+        );
+        assert!(client.sent_messages().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+//! Commands that only work in some channels
+//!
+//! When the bot is in several channels, a command can be limited to the
+//! channels it makes sense in, e.g. a death counter for one streamer's game.
+//! Used anywhere else, the command is ignored, or answered with a configured
+//! reply. Commands without a list work everywhere.
+
+use std::collections::{HashMap, HashSet};
+
+/// Which channels each limited command works in
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandChannels {
+    /// Channels for limited commands, keyed by command name
+    commands: HashMap<String, HashSet<String>>,
+    /// Reply to a limited command used elsewhere; None ignores it
+    reply: Option<String>,
+}
+
+impl CommandChannels {
+    /// Create channel limits where listed commands only work in their channels
+    ///
+    /// # Arguments
+    /// * `commands` - The channel logins for limited commands, keyed by command name
+    ///
+    /// # Returns
+    /// A new CommandChannels instance
+    pub fn new(commands: HashMap<String, Vec<String>>) -> Self {
+        let commands = commands
+            .into_iter()
+            .map(|(command, channels)| {
+                let channels = channels
+                    .iter()
+                    .map(|channel| channel.trim_start_matches('#').to_lowercase())
+                    .collect();
+                (command.to_lowercase(), channels)
+            })
+            .collect();
+        CommandChannels {
+            commands,
+            reply: None,
+        }
+    }
+
+    /// Answer limited commands used in other channels
+    ///
+    /// # Arguments
+    /// * `reply` - The reply ({command} and {channels} are replaced)
+    pub fn set_reply(&mut self, reply: &str) {
+        self.reply = Some(reply.to_string());
+    }
+
+    /// Check whether a command works in a channel
+    ///
+    /// # Arguments
+    /// * `command` - The name of the command
+    /// * `channel` - Login of the channel it was used in
+    ///
+    /// # Returns
+    /// true if the command has no list of its own or lists the channel
+    pub fn is_allowed(&self, command: &str, channel: &str) -> bool {
+        self.commands.get(command).is_none_or(|channels| {
+            channels.contains(&channel.trim_start_matches('#').to_lowercase())
+        })
+    }
+
+    /// Get the reply to a limited command used in another channel
+    ///
+    /// # Arguments
+    /// * `command` - The command as it was typed, with its prefix
+    /// * `name` - The name of the command
+    ///
+    /// # Returns
+    /// The reply, or None if the command should be ignored
+    pub fn wrong_channel_reply(&self, command: &str, name: &str) -> Option<String> {
+        let reply = self.reply.as_ref()?;
+        let mut channels: Vec<&str> = self
+            .commands
+            .get(name)
+            .map(|channels| channels.iter().map(String::as_str).collect())
+            .unwrap_or_default();
+        channels.sort_unstable();
+        Some(
+            reply
+                .replace("{command}", command)
+                .replace("{channels}", &channels.join(", ")),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_channels() {
+        let mut limits = CommandChannels::new(HashMap::from([(
+            "Deaths".to_string(),
+            vec!["#Busy_Channel".to_string(), "other_channel".to_string()],
+        )]));
+
+        assert!(limits.is_allowed("deaths", "busy_channel"));
+        assert!(limits.is_allowed("deaths", "other_channel"));
+        assert!(!limits.is_allowed("deaths", "quiet_channel"));
+        // Commands without a list work everywhere
+        assert!(limits.is_allowed("ping", "quiet_channel"));
+
+        assert_eq!(limits.wrong_channel_reply("!deaths", "deaths"), None);
+        limits.set_reply("{command} only works in {channels}.");
+        assert_eq!(
+            limits.wrong_channel_reply("!deaths", "deaths"),
+            Some("!deaths only works in busy_channel, other_channel.".to_string())
+        );
+    }
+}
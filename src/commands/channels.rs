@@ -0,0 +1,159 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::info;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext, Permission};
+use crate::twitch::{ChannelParter, ChannelStatus, JoinedChannels};
+
+/// A command that lists the channels the bot is in, or leaves one
+pub struct ChannelsCommand {
+    channels: Arc<JoinedChannels>,
+    parter: Arc<dyn ChannelParter>,
+}
+
+impl ChannelsCommand {
+    /// Create a new channels command
+    ///
+    /// # Arguments
+    /// * `channels` - The tracked set of joined channels
+    /// * `parter` - The client used to leave channels
+    ///
+    /// # Returns
+    /// A new ChannelsCommand instance
+    pub fn new(channels: Arc<JoinedChannels>, parter: Arc<dyn ChannelParter>) -> Self {
+        ChannelsCommand { channels, parter }
+    }
+
+    /// Leave a channel other than the home channel
+    fn part(&self, ctx: &CommandContext, msg: &PrivmsgMessage, channel: &str) -> String {
+        let channel = channel.trim_start_matches('#').to_lowercase();
+        if channel.eq_ignore_ascii_case(ctx.channel.trim_start_matches('#')) {
+            return format!("I can't leave {}, my home channel.", channel);
+        }
+
+        if self.parter.part_channel(&channel) {
+            info!("{} had the bot leave {}", msg.sender.name, channel);
+            format!("Left {}.", channel)
+        } else {
+            format!("I'm not in {}.", channel)
+        }
+    }
+}
+
+/// Describe a channel and the bot's status in it
+fn describe_channel(name: &str, status: ChannelStatus) -> String {
+    let connection = if status.connected {
+        "connected"
+    } else {
+        "disconnected"
+    };
+
+    if status.moderator {
+        format!("{} ({}, mod)", name, connection)
+    } else {
+        format!("{} ({})", name, connection)
+    }
+}
+
+#[async_trait]
+impl Command for ChannelsCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        match args.as_slice() {
+            [] => {}
+            [subcommand, channel] if subcommand.eq_ignore_ascii_case("part") => {
+                return Ok(Some(self.part(ctx, msg, channel)));
+            }
+            _ => return Ok(Some("Usage: !channels [part <channel>]".to_string())),
+        }
+
+        let channels: Vec<String> = self
+            .channels
+            .list()
+            .into_iter()
+            .map(|(name, status)| describe_channel(&name, status))
+            .collect();
+
+        if channels.is_empty() {
+            return Ok(Some("I'm not in any channels.".to_string()));
+        }
+
+        Ok(Some(format!("Channels: {}", channels.join(", "))))
+    }
+
+    fn help(&self) -> &str {
+        "Lists the channels the bot is in, or leaves one. Usage: !channels [part <channel>]"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Broadcaster
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+
+    impl ChannelParter for JoinedChannels {
+        fn part_channel(&self, channel: &str) -> bool {
+            self.part(channel)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_channels_follow_join_and_part() -> Result<()> {
+        let ctx = create_test_context();
+        let channels = Arc::new(JoinedChannels::new());
+        let command = ChannelsCommand::new(channels.clone(), channels.clone());
+        let msg = create_test_privmsg_with_badges("!channels", &["broadcaster"]);
+
+        assert_eq!(command.permission(), Permission::Broadcaster);
+        assert_eq!(
+            command.execute(&ctx, &msg, vec![]).await?,
+            Some("I'm not in any channels.".to_string())
+        );
+
+        channels.join("test_channel");
+        channels.join("another_channel");
+        channels.set_moderator("test_channel", true);
+        assert_eq!(
+            command.execute(&ctx, &msg, vec![]).await?,
+            Some(
+                "Channels: another_channel (connected), test_channel (connected, mod)".to_string()
+            )
+        );
+
+        assert_eq!(
+            command
+                .execute(&ctx, &msg, vec!["part", "#Another_Channel"])
+                .await?,
+            Some("Left another_channel.".to_string())
+        );
+        assert_eq!(
+            command
+                .execute(&ctx, &msg, vec!["part", "another_channel"])
+                .await?,
+            Some("I'm not in another_channel.".to_string())
+        );
+        // The home channel can't be left
+        assert_eq!(
+            command
+                .execute(&ctx, &msg, vec!["part", "test_channel"])
+                .await?,
+            Some("I can't leave test_channel, my home channel.".to_string())
+        );
+        channels.set_connected(false);
+        assert_eq!(
+            command.execute(&ctx, &msg, vec![]).await?,
+            Some("Channels: test_channel (disconnected, mod)".to_string())
+        );
+        Ok(())
+    }
+}
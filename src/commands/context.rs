@@ -0,0 +1,55 @@
+use anyhow::{Result, anyhow};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::twitch::HelixChatClient;
+use crate::users::UserManager;
+
+/// Shared state every command can use, passed to `Command::execute`
+///
+/// Commands that need chatter records or the Helix API get them from here
+/// instead of each keeping their own copies.
+pub struct CommandContext {
+    /// Known chatters and their stats
+    pub user_manager: Arc<UserManager>,
+    /// Client for Helix API calls, when connected to Twitch
+    pub helix: Option<Arc<Mutex<HelixChatClient>>>,
+    /// The channel the bot was started for
+    pub channel: String,
+}
+
+impl CommandContext {
+    /// Create a new command context without a Helix client
+    ///
+    /// # Arguments
+    /// * `user_manager` - The user manager tracking chatters
+    /// * `channel` - The channel the bot was started for
+    ///
+    /// # Returns
+    /// A new CommandContext instance
+    pub fn new(user_manager: Arc<UserManager>, channel: String) -> Self {
+        CommandContext {
+            user_manager,
+            helix: None,
+            channel,
+        }
+    }
+
+    /// Let commands make Helix API calls
+    ///
+    /// # Arguments
+    /// * `helix` - The Helix client to share
+    pub fn set_helix(&mut self, helix: Arc<Mutex<HelixChatClient>>) {
+        self.helix = Some(helix);
+    }
+
+    /// Get the Helix client
+    ///
+    /// # Returns
+    /// The client, or an error if the bot isn't connected to Twitch
+    pub fn helix(&self) -> Result<&Mutex<HelixChatClient>> {
+        self.helix
+            .as_deref()
+            .ok_or_else(|| anyhow!("The Twitch API isn't available"))
+    }
+}
@@ -0,0 +1,228 @@
+//! Cooldowns that stop a command being spammed
+//!
+//! A command can be limited globally (once every N seconds for the whole
+//! channel) and per user (once every N seconds for each chatter). Both apply:
+//! a use must be clear of both to go through. Each channel has its own
+//! cooldowns. Chatters with an exempt role (by default just the broadcaster)
+//! skip cooldowns entirely, and their uses don't start a cooldown for anyone
+//! else. Uses older than the longest cooldown are forgotten. Cooldowns are
+//! only kept in memory, so a restart clears them.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+use twitch_irc::message::PrivmsgMessage;
+
+/// Roles exempt from cooldowns when none are configured
+pub const DEFAULT_EXEMPT_ROLES: &[&str] = &["broadcaster"];
+
+/// How often commands may be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CooldownSettings {
+    /// Minimum time between uses of a command by anyone
+    pub global: Duration,
+    /// Minimum time between uses of a command by the same user
+    pub per_user: Duration,
+    /// Badge names (e.g. "broadcaster", "moderator", "vip") that skip cooldowns
+    pub exempt_roles: Vec<String>,
+}
+
+impl Default for CooldownSettings {
+    fn default() -> Self {
+        CooldownSettings {
+            global: Duration::ZERO,
+            per_user: Duration::ZERO,
+            exempt_roles: DEFAULT_EXEMPT_ROLES.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+}
+
+/// When commands were last used
+#[derive(Default)]
+struct LastUses {
+    /// By channel and command name
+    global: HashMap<(String, String), Instant>,
+    /// By channel, command name, and user ID
+    per_user: HashMap<(String, String, String), Instant>,
+}
+
+/// Tracks command cooldowns
+#[derive(Default)]
+pub struct CooldownTracker {
+    settings: CooldownSettings,
+    last_uses: Mutex<LastUses>,
+}
+
+impl CooldownTracker {
+    /// Create a new cooldown tracker
+    ///
+    /// # Arguments
+    /// * `settings` - How often commands may be used
+    ///
+    /// # Returns
+    /// A new CooldownTracker instance
+    pub fn new(settings: CooldownSettings) -> Self {
+        CooldownTracker {
+            settings,
+            last_uses: Mutex::new(LastUses::default()),
+        }
+    }
+
+    /// Check whether the sender of a message has a role exempt from cooldowns
+    ///
+    /// # Arguments
+    /// * `msg` - The message to check
+    pub fn is_exempt(&self, msg: &PrivmsgMessage) -> bool {
+        msg.badges
+            .iter()
+            .any(|badge| self.settings.exempt_roles.contains(&badge.name))
+    }
+
+    /// Check a command use against its cooldowns, and start them if it goes through
+    ///
+    /// # Arguments
+    /// * `command` - The name of the command being used
+    /// * `msg` - The message using the command
+    ///
+    /// # Returns
+    /// None if the command may run, or how long until it may be used again
+    pub fn check(&self, command: &str, msg: &PrivmsgMessage) -> Option<Duration> {
+        if self.is_exempt(msg) {
+            return None;
+        }
+
+        let now = Instant::now();
+        let mut last_uses = self.last_uses.lock().unwrap();
+
+        // Uses older than the longest cooldown can't hold anything back
+        let longest = self.settings.global.max(self.settings.per_user);
+        last_uses
+            .global
+            .retain(|_, last| now.duration_since(*last) < longest);
+        last_uses
+            .per_user
+            .retain(|_, last| now.duration_since(*last) < longest);
+
+        let global_key = (msg.channel_login.clone(), command.to_string());
+        let user_key = (
+            msg.channel_login.clone(),
+            command.to_string(),
+            msg.sender.id.clone(),
+        );
+        let remaining = |last: Option<&Instant>, cooldown: Duration| {
+            last.map(|last| cooldown.saturating_sub(now.duration_since(*last)))
+                .unwrap_or_default()
+        };
+        let wait = remaining(last_uses.global.get(&global_key), self.settings.global).max(
+            remaining(last_uses.per_user.get(&user_key), self.settings.per_user),
+        );
+        if !wait.is_zero() {
+            return Some(wait);
+        }
+
+        if !self.settings.global.is_zero() {
+            last_uses.global.insert(global_key, now);
+        }
+        if !self.settings.per_user.is_zero() {
+            last_uses.per_user.insert(user_key, now);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::create_test_privmsg_with_badges;
+
+    /// A message from a different user than the default test sender
+    fn privmsg_from(id: &str, badges: &[&str]) -> PrivmsgMessage {
+        let mut msg = create_test_privmsg_with_badges("!ping", badges);
+        msg.sender.id = id.to_string();
+        msg
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_broadcaster_bypasses_cooldown() {
+        let tracker = CooldownTracker::new(CooldownSettings {
+            per_user: Duration::from_secs(30),
+            ..Default::default()
+        });
+        let viewer = privmsg_from("1", &[]);
+        let broadcaster = privmsg_from("2", &["broadcaster"]);
+
+        assert_eq!(tracker.check("ping", &viewer), None);
+        assert_eq!(
+            tracker.check("ping", &viewer),
+            Some(Duration::from_secs(30))
+        );
+
+        for _ in 0..3 {
+            assert_eq!(tracker.check("ping", &broadcaster), None);
+        }
+
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert_eq!(tracker.check("ping", &viewer), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_global_and_per_user_cooldowns_compose() {
+        let tracker = CooldownTracker::new(CooldownSettings {
+            global: Duration::from_secs(10),
+            per_user: Duration::from_secs(60),
+            exempt_roles: vec!["moderator".to_string()],
+        });
+        let first = privmsg_from("1", &[]);
+        let second = privmsg_from("2", &[]);
+        let moderator = privmsg_from("3", &["moderator"]);
+
+        assert_eq!(tracker.check("ping", &first), None);
+        // The global cooldown holds back everyone else
+        assert_eq!(
+            tracker.check("ping", &second),
+            Some(Duration::from_secs(10))
+        );
+        // Other commands aren't affected
+        assert_eq!(tracker.check("uptime", &second), None);
+        // Configured roles bypass both cooldowns
+        assert_eq!(tracker.check("ping", &moderator), None);
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert_eq!(tracker.check("ping", &second), None);
+        // The per-user cooldown still holds back the first user
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert_eq!(tracker.check("ping", &first), Some(Duration::from_secs(40)));
+
+        // The broadcaster is only exempt when configured
+        let broadcaster = privmsg_from("4", &["broadcaster"]);
+        assert!(!tracker.is_exempt(&broadcaster));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cooldowns_are_per_channel_and_pruned() {
+        let tracker = CooldownTracker::new(CooldownSettings {
+            global: Duration::from_secs(10),
+            per_user: Duration::from_secs(60),
+            ..Default::default()
+        });
+        let viewer = privmsg_from("1", &[]);
+        let mut elsewhere = privmsg_from("1", &[]);
+        elsewhere.channel_login = "other_channel".to_string();
+
+        assert_eq!(tracker.check("ping", &viewer), None);
+        // A use in one channel doesn't hold back the same command in another
+        assert_eq!(tracker.check("ping", &elsewhere), None);
+        assert_eq!(
+            tracker.check("ping", &elsewhere),
+            Some(Duration::from_secs(60))
+        );
+
+        // Once the longest cooldown has passed, the uses are forgotten
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert_eq!(tracker.check("uptime", &viewer), None);
+        let last_uses = tracker.last_uses.lock().unwrap();
+        assert_eq!(last_uses.global.len(), 1);
+        assert_eq!(last_uses.per_user.len(), 1);
+    }
+}
@@ -0,0 +1,271 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::info;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext, CommandRegistry, Permission};
+use crate::counters::Counters;
+
+/// A command that shows and updates one named counter, e.g. !deaths
+///
+/// Anyone can see the count; only moderators can change it.
+pub struct CounterCommand {
+    name: String,
+    counters: Arc<Counters>,
+    help: String,
+}
+
+impl CounterCommand {
+    /// Create a new counter command
+    ///
+    /// # Arguments
+    /// * `name` - The counter's name, also used as the command name
+    /// * `counters` - The shared counters
+    ///
+    /// # Returns
+    /// A new CounterCommand instance
+    pub fn new(name: &str, counters: Arc<Counters>) -> Self {
+        CounterCommand {
+            name: name.to_string(),
+            counters,
+            help: format!(
+                "Shows the {} counter; mods can change it. Usage: !{} [+1|-1|set <n>|reset]",
+                name, name
+            ),
+        }
+    }
+
+    fn usage(&self) -> String {
+        format!("Usage: !{} [+1|-1|set <n>|reset]", self.name)
+    }
+}
+
+#[async_trait]
+impl Command for CounterCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let name = &self.name;
+
+        let Some(arg) = args.first() else {
+            return Ok(Some(format!("{}: {}", name, self.counters.get(name)?)));
+        };
+
+        if !Permission::Moderator.is_satisfied_by(msg) {
+            info!(
+                "{} is not allowed to change the {} counter",
+                msg.sender.name, name
+            );
+            return Ok(None);
+        }
+
+        let value = match (*arg, args.get(1)) {
+            ("set", Some(value)) => match value.parse() {
+                Ok(value) => {
+                    self.counters.set(name, value)?;
+                    value
+                }
+                Err(_) => return Ok(Some(self.usage())),
+            },
+            ("reset", None) => {
+                self.counters.set(name, 0)?;
+                0
+            }
+            (delta, None) if delta.starts_with(['+', '-']) => match delta.parse() {
+                Ok(delta) => self.counters.add(name, delta)?,
+                Err(_) => return Ok(Some(self.usage())),
+            },
+            _ => return Ok(Some(self.usage())),
+        };
+
+        Ok(Some(format!("{}: {}", name, value)))
+    }
+
+    fn help(&self) -> &str {
+        &self.help
+    }
+
+    fn counter_name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+}
+
+/// A command that creates and deletes counter commands while the bot runs
+pub struct CounterAdminCommand {
+    counters: Arc<Counters>,
+    registry: Arc<RwLock<CommandRegistry>>,
+}
+
+impl CounterAdminCommand {
+    /// Create a new counter admin command
+    ///
+    /// # Arguments
+    /// * `counters` - The shared counters
+    /// * `registry` - The registry new counter commands are added to
+    ///
+    /// # Returns
+    /// A new CounterAdminCommand instance
+    pub fn new(counters: Arc<Counters>, registry: Arc<RwLock<CommandRegistry>>) -> Self {
+        CounterAdminCommand { counters, registry }
+    }
+}
+
+#[async_trait]
+impl Command for CounterAdminCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let (Some(action), Some(name)) = (args.first(), args.get(1)) else {
+            return Ok(Some("Usage: !counter add|remove <name>".to_string()));
+        };
+        let name = name.to_lowercase();
+
+        let response = match *action {
+            "add" => {
+                if !Counters::is_valid_name(&name) {
+                    return Ok(Some(
+                        "Counter names can only use letters, digits, and underscores.".to_string(),
+                    ));
+                }
+
+                let mut registry = self.registry.write().await;
+                if registry.has_command(&name) {
+                    format!("!{} is already a command.", name)
+                } else {
+                    self.counters.create(&name)?;
+                    registry.register(
+                        name.clone(),
+                        Arc::new(CounterCommand::new(&name, self.counters.clone())),
+                    );
+                    info!("{} added the {} counter", msg.sender.name, name);
+                    format!("Added the !{} counter.", name)
+                }
+            }
+            "remove" => {
+                if self.counters.delete(&name)? {
+                    // A stored counter is skipped at startup if a built-in
+                    // command took its name, so only remove counter commands
+                    let mut registry = self.registry.write().await;
+                    let is_counter = registry
+                        .get_command(&name)
+                        .is_some_and(|command| command.counter_name() == Some(name.as_str()));
+                    if is_counter {
+                        registry.unregister(&name);
+                    }
+                    info!("{} removed the {} counter", msg.sender.name, name);
+                    format!("Removed the !{} counter.", name)
+                } else {
+                    format!("There's no {} counter.", name)
+                }
+            }
+            _ => "Usage: !counter add|remove <name>".to_string(),
+        };
+
+        Ok(Some(response))
+    }
+
+    fn help(&self) -> &str {
+        "Creates or deletes a counter command. Usage: !counter add|remove <name>"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{StoreBackend, open_store};
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_counter_command() -> Result<()> {
+        let ctx = create_test_context();
+        let temp_dir = tempdir()?;
+        let counters = Arc::new(Counters::new(open_store(
+            StoreBackend::File,
+            temp_dir.path(),
+            None,
+        )?));
+        let command = CounterCommand::new("deaths", counters);
+        let viewer = create_test_privmsg_with_badges("!deaths +1", &[]);
+        let moderator = create_test_privmsg_with_badges("!deaths +1", &["moderator"]);
+
+        assert_eq!(
+            command.execute(&ctx, &viewer, vec![]).await?,
+            Some("deaths: 0".to_string())
+        );
+        // Viewers can't change the count
+        assert_eq!(command.execute(&ctx, &viewer, vec!["+1"]).await?, None);
+
+        assert_eq!(
+            command.execute(&ctx, &moderator, vec!["+1"]).await?,
+            Some("deaths: 1".to_string())
+        );
+        assert_eq!(
+            command.execute(&ctx, &moderator, vec!["set", "5"]).await?,
+            Some("deaths: 5".to_string())
+        );
+        assert_eq!(
+            command.execute(&ctx, &moderator, vec!["-2"]).await?,
+            Some("deaths: 3".to_string())
+        );
+        assert_eq!(
+            command
+                .execute(&ctx, &moderator, vec!["set", "lots"])
+                .await?,
+            Some("Usage: !deaths [+1|-1|set <n>|reset]".to_string())
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_counters_are_registered_dynamically() -> Result<()> {
+        let ctx = create_test_context();
+        let temp_dir = tempdir()?;
+        let counters = Arc::new(Counters::new(open_store(
+            StoreBackend::File,
+            temp_dir.path(),
+            None,
+        )?));
+        let registry = Arc::new(RwLock::new(CommandRegistry::new()));
+        let admin = Arc::new(CounterAdminCommand::new(counters.clone(), registry.clone()));
+        registry.write().await.register("counter", admin.clone());
+        let msg = create_test_privmsg_with_badges("!counter add wins", &["moderator"]);
+
+        assert_eq!(
+            admin.execute(&ctx, &msg, vec!["add", "wins"]).await?,
+            Some("Added the !wins counter.".to_string())
+        );
+        assert!(registry.read().await.has_command("wins"));
+        assert_eq!(
+            admin.execute(&ctx, &msg, vec!["add", "counter"]).await?,
+            Some("!counter is already a command.".to_string())
+        );
+
+        assert_eq!(
+            admin.execute(&ctx, &msg, vec!["remove", "wins"]).await?,
+            Some("Removed the !wins counter.".to_string())
+        );
+        assert!(!registry.read().await.has_command("wins"));
+
+        // A stored counter shadowed by another command leaves that command alone
+        counters.create("counter")?;
+        assert_eq!(
+            admin.execute(&ctx, &msg, vec!["remove", "counter"]).await?,
+            Some("Removed the !counter counter.".to_string())
+        );
+        assert!(registry.read().await.has_command("counter"));
+        Ok(())
+    }
+}
@@ -1,34 +1,89 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
 use rand::prelude::IndexedRandom;
 use rand::rng;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 use twitch_irc::message::PrivmsgMessage;
 
-use crate::commands::Command;
+use crate::commands::{Command, CommandContext, Permission};
+use crate::personality::Personality;
+
+/// Default 8-ball reply ({user}, {question}, and {response} are replaced)
+pub const DEFAULT_EIGHT_BALL_FORMAT: &str = "🎱 {response}";
+
+/// How many of the latest answers the 8-ball avoids repeating by default
+pub const DEFAULT_EIGHT_BALL_REPEAT_WINDOW: usize = 1;
 
 /// Possible response types for the 8-ball
-enum ResponseType {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseType {
     Affirmative,
     Negative,
     Neutral,
     Uncertain,
 }
 
-/// A command that simulates a Magic 8-Ball
-pub struct EightBallCommand {
-    // All possible responses organized by type
-    responses: Vec<(ResponseType, Vec<&'static str>)>,
+impl ResponseType {
+    /// Get the name shown in chat
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResponseType::Affirmative => "affirmative",
+            ResponseType::Negative => "negative",
+            ResponseType::Neutral => "neutral",
+            ResponseType::Uncertain => "uncertain",
+        }
+    }
 }
 
-impl Default for EightBallCommand {
-    fn default() -> Self {
-        Self::new()
+/// The responses of one type and how often the type is picked
+#[derive(Debug, Clone)]
+struct Category {
+    kind: ResponseType,
+    responses: Vec<String>,
+    /// Explicit weight; without one every response is equally likely
+    weight: Option<u32>,
+}
+
+impl Category {
+    /// Get the weight used when picking a type
+    ///
+    /// Weights are u64 so that adding up the four types' u32 weights can't
+    /// overflow.
+    fn weight(&self) -> u64 {
+        self.weight.map_or(self.responses.len() as u64, u64::from)
     }
 }
 
-impl EightBallCommand {
-    /// Create a new 8-ball command with default responses
-    pub fn new() -> Self {
-        let responses = vec![
+/// One response type in a response file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CategoryFile {
+    responses: Vec<String>,
+    weight: Option<u32>,
+}
+
+/// A themed response file, with a table for each response type
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ResponseFile {
+    affirmative: Option<CategoryFile>,
+    negative: Option<CategoryFile>,
+    neutral: Option<CategoryFile>,
+    uncertain: Option<CategoryFile>,
+}
+
+/// The 8-ball's possible responses, organized by type
+#[derive(Debug, Clone)]
+pub struct EightBallResponses {
+    categories: Vec<Category>,
+}
+
+impl Default for EightBallResponses {
+    fn default() -> Self {
+        let categories = vec![
             (
                 ResponseType::Affirmative,
                 vec![
@@ -76,7 +131,261 @@ impl EightBallCommand {
             ),
         ];
 
-        EightBallCommand { responses }
+        Self::new(
+            categories
+                .into_iter()
+                .map(|(kind, responses)| (kind, responses.into_iter().map(String::from).collect()))
+                .collect(),
+        )
+    }
+}
+
+impl EightBallResponses {
+    /// Create a response set where every response is equally likely
+    ///
+    /// # Arguments
+    /// * `categories` - The responses for each type; empty types are never chosen
+    ///
+    /// # Returns
+    /// A new EightBallResponses instance
+    pub fn new(categories: Vec<(ResponseType, Vec<String>)>) -> Self {
+        let categories = categories
+            .into_iter()
+            .filter(|(_, responses)| !responses.is_empty())
+            .map(|(kind, responses)| Category {
+                kind,
+                responses,
+                weight: None,
+            })
+            .collect();
+        EightBallResponses { categories }
+    }
+
+    /// Parse a themed response set from TOML
+    ///
+    /// Each response type is an optional table with a `responses` list and
+    /// an optional `weight`.
+    ///
+    /// # Arguments
+    /// * `contents` - The file contents
+    ///
+    /// # Returns
+    /// The response set, or an error if it is malformed or has no responses
+    pub fn parse(contents: &str) -> Result<Self> {
+        let file: ResponseFile = toml::from_str(contents)?;
+        let tables = [
+            (ResponseType::Affirmative, file.affirmative),
+            (ResponseType::Negative, file.negative),
+            (ResponseType::Neutral, file.neutral),
+            (ResponseType::Uncertain, file.uncertain),
+        ];
+
+        let mut weights = Vec::new();
+        let mut categories = Vec::new();
+        for (kind, table) in tables {
+            let Some(table) = table else {
+                continue;
+            };
+            weights.extend(table.weight.map(|weight| (kind, weight)));
+            categories.push((kind, table.responses));
+        }
+
+        let mut responses = Self::new(categories);
+        if responses.categories.is_empty() {
+            return Err(anyhow!("No 8-ball responses given"));
+        }
+        for (kind, weight) in weights {
+            responses.set_weight(kind, weight);
+        }
+        if responses.total_weight() == 0 {
+            return Err(anyhow!("The 8-ball response weights can't all be zero"));
+        }
+        Ok(responses)
+    }
+
+    /// Read a themed response set from a TOML file
+    ///
+    /// # Arguments
+    /// * `path` - Path to the response file
+    ///
+    /// # Returns
+    /// The response set
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Couldn't read 8-ball file {}: {}", path.display(), e))?;
+        Self::parse(&contents).map_err(|e| anyhow!("Invalid 8-ball file {}: {}", path.display(), e))
+    }
+
+    /// Give a response type an explicit weight
+    ///
+    /// A type with weight 6 is picked twice as often as one with weight 3.
+    /// Without explicit weights a type's weight is its number of responses,
+    /// so every response is equally likely.
+    ///
+    /// # Arguments
+    /// * `kind` - The response type
+    /// * `weight` - Its relative weight
+    pub fn set_weight(&mut self, kind: ResponseType, weight: u32) {
+        for category in &mut self.categories {
+            if category.kind == kind {
+                category.weight = Some(weight);
+            }
+        }
+    }
+
+    /// Get the combined weight of every response type
+    fn total_weight(&self) -> u64 {
+        self.categories.iter().map(Category::weight).sum()
+    }
+
+    /// Get the chance of each response type being chosen
+    ///
+    /// A type is chosen first, in proportion to its weight, and then one of
+    /// its responses.
+    ///
+    /// # Returns
+    /// Each type with its number of responses and its chance as a fraction
+    pub fn odds(&self) -> Vec<(ResponseType, usize, f64)> {
+        let total = self.total_weight();
+        self.categories
+            .iter()
+            .map(|category| {
+                let share = category.weight() as f64 / total.max(1) as f64;
+                (category.kind, category.responses.len(), share)
+            })
+            .collect()
+    }
+
+    /// Pick a random response
+    fn choose(&self) -> Option<&str> {
+        let mut rng = rng();
+        let category = self
+            .categories
+            .choose_weighted(&mut rng, Category::weight)
+            .ok()?;
+        category.responses.choose(&mut rng).map(String::as_str)
+    }
+
+    /// Pick a random response that isn't one of the recent ones
+    ///
+    /// Falls back to any response if every one of them is recent.
+    ///
+    /// # Arguments
+    /// * `recent` - The responses to avoid
+    fn choose_excluding(&self, recent: &VecDeque<String>) -> Option<&str> {
+        let fresh = |response: &String| !recent.contains(response);
+        let categories: Vec<&Category> = self
+            .categories
+            .iter()
+            .filter(|category| category.responses.iter().any(fresh))
+            .collect();
+
+        let mut rng = rng();
+        let Ok(category) = categories.choose_weighted(&mut rng, |category| category.weight())
+        else {
+            return self.choose();
+        };
+        let responses: Vec<&String> = category
+            .responses
+            .iter()
+            .filter(|response| fresh(response))
+            .collect();
+        responses
+            .choose(&mut rng)
+            .map(|response| (*response).as_str())
+    }
+}
+
+/// A command that simulates a Magic 8-Ball
+pub struct EightBallCommand {
+    // All possible responses organized by type
+    responses: Arc<EightBallResponses>,
+    // Reply when the command is used without a question
+    intro: &'static str,
+    // Reply template with {user}, {question}, and {response} placeholders
+    format: String,
+    // The latest answers, oldest first
+    recent: Mutex<VecDeque<String>>,
+    // How many of the latest answers to avoid repeating
+    repeat_window: usize,
+}
+
+impl Default for EightBallCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EightBallCommand {
+    /// Create a new 8-ball command with default responses
+    pub fn new() -> Self {
+        Self::with_personality(Personality::default())
+    }
+
+    /// Create a new 8-ball command using a personality preset's intro
+    ///
+    /// # Arguments
+    /// * `personality` - The preset whose phrasing to use
+    ///
+    /// # Returns
+    /// A new EightBallCommand instance
+    pub fn with_personality(personality: Personality) -> Self {
+        Self::with_responses(
+            Arc::new(EightBallResponses::default()),
+            personality,
+            DEFAULT_EIGHT_BALL_FORMAT.to_string(),
+        )
+    }
+
+    /// Create a new 8-ball command with a custom response set
+    ///
+    /// # Arguments
+    /// * `responses` - The responses to choose from
+    /// * `personality` - The preset whose phrasing to use
+    /// * `format` - The reply template ({user}, {question}, and {response} are replaced)
+    ///
+    /// # Returns
+    /// A new EightBallCommand instance
+    pub fn with_responses(
+        responses: Arc<EightBallResponses>,
+        personality: Personality,
+        format: String,
+    ) -> Self {
+        EightBallCommand {
+            responses,
+            intro: personality.eight_ball_intro(),
+            format,
+            recent: Mutex::new(VecDeque::new()),
+            repeat_window: DEFAULT_EIGHT_BALL_REPEAT_WINDOW,
+        }
+    }
+
+    /// Set how many of the latest answers the 8-ball avoids repeating
+    ///
+    /// # Arguments
+    /// * `window` - How many answers to remember (0 allows repeats)
+    pub fn set_repeat_window(&mut self, window: usize) {
+        self.repeat_window = window;
+        let mut recent = self.recent.lock().unwrap();
+        while recent.len() > window {
+            recent.pop_front();
+        }
+    }
+
+    /// Forget the latest answers, so any answer can come up next
+    ///
+    /// # Returns
+    /// How many answers were forgotten
+    pub fn reset(&self) -> usize {
+        let mut recent = self.recent.lock().unwrap();
+        let forgotten = recent.len();
+        recent.clear();
+        forgotten
+    }
+
+    /// Get the response set this command chooses from
+    pub fn responses(&self) -> Arc<EightBallResponses> {
+        self.responses.clone()
     }
 
     /// Get a random response from the 8-ball
@@ -87,18 +396,23 @@ impl EightBallCommand {
     /// # Returns
     /// A string response to the question
     fn get_random_response(&self, _question: &str) -> String {
-        let mut rng = rng();
+        let mut recent = self.recent.lock().unwrap();
+        let response = self
+            .responses
+            .choose_excluding(&recent)
+            .unwrap_or(
+                // Fallback in case something goes wrong
+                "The magic 8-ball is cloudy right now.",
+            )
+            .to_string();
 
-        // First, choose a random response type
-        if let Some((_, responses)) = self.responses.choose(&mut rng) {
-            // Then choose a random response of that type
-            if let Some(response) = responses.choose(&mut rng) {
-                return response.to_string();
+        if self.repeat_window > 0 {
+            if recent.len() == self.repeat_window {
+                recent.pop_front();
             }
+            recent.push_back(response.clone());
         }
-
-        // Fallback in case something goes wrong
-        "The magic 8-ball is cloudy right now.".to_string()
+        response
     }
 
     /// In the future, this could be replaced with an AI-based response selector
@@ -109,33 +423,119 @@ impl EightBallCommand {
     }
 }
 
+#[async_trait]
 impl Command for EightBallCommand {
-    fn execute(&self, _msg: &PrivmsgMessage, args: Vec<&str>) -> Result<Option<String>> {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
         // If there are no arguments, prompt for a question
         if args.is_empty() {
-            return Ok(Some(
-                "Ask me a question and I shall reveal your fate!".to_string(),
-            ));
+            return Ok(Some(self.intro.to_string()));
+        }
+
+        // Moderators can inspect and clear the anti-repeat memory
+        if args.len() == 1 && Permission::Moderator.is_satisfied_by(msg) {
+            match args[0].to_lowercase().as_str() {
+                "recent" => {
+                    let recent = self.recent.lock().unwrap();
+                    let reply = if recent.is_empty() {
+                        "🎱 No recent answers.".to_string()
+                    } else {
+                        let answers: Vec<&str> = recent.iter().map(String::as_str).collect();
+                        format!("🎱 Recent answers: {}", answers.join(" | "))
+                    };
+                    return Ok(Some(reply));
+                }
+                "reset" => {
+                    let forgotten = self.reset();
+                    let plural = if forgotten == 1 { "" } else { "s" };
+                    return Ok(Some(format!(
+                        "🎱 Forgot {} recent answer{}.",
+                        forgotten, plural
+                    )));
+                }
+                _ => {}
+            }
         }
 
-        // Join all arguments to form the question (just for internal use)
+        // Join all arguments to form the question
         let question = args.join(" ");
 
         // Get a response from the 8-ball
         let response = self.get_response(&question);
 
-        // Format the response - just return the answer with the 8-ball emoji
-        Ok(Some(format!("🎱 {}", response)))
+        // The question goes in last so placeholders typed into it stay as they are
+        Ok(Some(
+            self.format
+                .replace("{user}", &msg.sender.name)
+                .replace("{response}", &response)
+                .replace("{question}", &question),
+        ))
+    }
+
+    fn help(&self) -> &str {
+        "Ask the Magic 8-Ball a yes/no question. Usage: !8ball <question> (mods: !8ball recent | reset)"
+    }
+}
+
+/// A command that shows how the 8-ball picks its answers
+pub struct EightBallOddsCommand {
+    responses: Arc<EightBallResponses>,
+}
+
+impl EightBallOddsCommand {
+    /// Create a new 8-ball odds command
+    ///
+    /// # Arguments
+    /// * `responses` - The response set the 8-ball chooses from
+    ///
+    /// # Returns
+    /// A new EightBallOddsCommand instance
+    pub fn new(responses: Arc<EightBallResponses>) -> Self {
+        EightBallOddsCommand { responses }
+    }
+}
+
+#[async_trait]
+impl Command for EightBallOddsCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        _msg: &PrivmsgMessage,
+        _args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let odds = self.responses.odds();
+        if odds.is_empty() {
+            return Ok(Some("🎱 The 8-ball has no responses.".to_string()));
+        }
+
+        let total: usize = odds.iter().map(|(_, count, _)| count).sum();
+        let categories: Vec<String> = odds
+            .iter()
+            .map(|(kind, count, share)| {
+                format!("{} {} ({:.0}%)", kind.name(), count, share * 100.0)
+            })
+            .collect();
+
+        Ok(Some(format!(
+            "🎱 {} responses: {}",
+            total,
+            categories.join(", ")
+        )))
     }
 
     fn help(&self) -> &str {
-        "Ask the Magic 8-Ball a yes/no question. Usage: !8ball <question>"
+        "Shows how many answers the 8-ball has of each kind and how likely each kind is. Usage: !8ballodds"
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_helpers::create_test_context;
     use chrono::Utc;
     use twitch_irc::message::{Badge, Emote, IRCMessage, IRCPrefix, IRCTags, TwitchUserBasics};
 
@@ -173,30 +573,245 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_eight_ball_command_no_args() {
+    #[tokio::test]
+    async fn test_eight_ball_command_no_args() {
+        let ctx = create_test_context();
         let command = EightBallCommand::new();
         let msg = create_dummy_privmsg();
 
         // Execute the command with no arguments
-        let result = command.execute(&msg, Vec::new()).unwrap();
+        let result = command.execute(&ctx, &msg, Vec::new()).await.unwrap();
         assert_eq!(
             result,
             Some("Ask me a question and I shall reveal your fate!".to_string())
         );
     }
 
-    #[test]
-    fn test_eight_ball_command_with_question() {
+    #[tokio::test]
+    async fn test_eight_ball_command_with_question() {
+        let ctx = create_test_context();
         let command = EightBallCommand::new();
         let msg = create_dummy_privmsg();
 
         // Execute the command with a question
-        let result = command.execute(&msg, vec!["Will", "I", "win?"]).unwrap();
+        let result = command
+            .execute(&ctx, &msg, vec!["Will", "I", "win?"])
+            .await
+            .unwrap();
 
         // We can't check the exact response since it's random, but we can check the format
         let result = result.unwrap();
         assert!(result.starts_with("🎱 "));
         assert!(result.len() > 10); // Make sure there's a substantial response
     }
+
+    #[tokio::test]
+    async fn test_eight_ball_personality_intro() {
+        let ctx = create_test_context();
+        let command = EightBallCommand::with_personality(Personality::Formal);
+        let msg = create_dummy_privmsg();
+
+        let result = command.execute(&ctx, &msg, Vec::new()).await.unwrap();
+        assert_eq!(
+            result,
+            Some(Personality::Formal.eight_ball_intro().to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_eight_ball_odds_default() -> Result<()> {
+        let ctx = create_test_context();
+        let command = EightBallOddsCommand::new(EightBallCommand::new().responses());
+        let msg = create_dummy_privmsg();
+
+        let result = command.execute(&ctx, &msg, Vec::new()).await?;
+        assert_eq!(
+            result,
+            Some(
+                "🎱 25 responses: affirmative 10 (40%), negative 5 (20%), neutral 5 (20%), uncertain 5 (20%)"
+                    .to_string()
+            )
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_eight_ball_odds_custom() -> Result<()> {
+        let ctx = create_test_context();
+        let responses = Arc::new(EightBallResponses::new(vec![
+            (
+                ResponseType::Affirmative,
+                vec!["Yes!".to_string(), "Absolutely.".to_string()],
+            ),
+            (ResponseType::Negative, vec!["Nope.".to_string()]),
+            (ResponseType::Neutral, Vec::new()),
+        ]));
+        let eight_ball = EightBallCommand::with_responses(
+            responses.clone(),
+            Personality::default(),
+            DEFAULT_EIGHT_BALL_FORMAT.to_string(),
+        );
+        let command = EightBallOddsCommand::new(responses);
+        let msg = create_dummy_privmsg();
+
+        let result = command.execute(&ctx, &msg, Vec::new()).await?;
+        assert_eq!(
+            result,
+            Some("🎱 3 responses: affirmative 2 (67%), negative 1 (33%)".to_string())
+        );
+
+        // The 8-ball answers from the same set
+        let answer = eight_ball
+            .execute(&ctx, &msg, vec!["Really?"])
+            .await?
+            .unwrap();
+        assert!(["🎱 Yes!", "🎱 Absolutely.", "🎱 Nope."].contains(&answer.as_str()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_eight_ball_response_file() -> Result<()> {
+        let responses = EightBallResponses::parse(
+            r#"
+            [affirmative]
+            responses = ["The stars say yes.", "The cards agree."]
+            weight = 3
+
+            [negative]
+            responses = ["The void says no."]
+            "#,
+        )?;
+        let odds: Vec<_> = responses
+            .odds()
+            .into_iter()
+            .map(|(kind, count, share)| (kind, count, (share * 100.0).round()))
+            .collect();
+        assert_eq!(
+            odds,
+            vec![
+                (ResponseType::Affirmative, 2, 75.0),
+                (ResponseType::Negative, 1, 25.0)
+            ]
+        );
+
+        assert!(EightBallResponses::parse("").is_err());
+        assert!(EightBallResponses::parse("[maybe]\nresponses = [\"Hmm.\"]").is_err());
+        assert!(
+            EightBallResponses::parse(
+                "[affirmative]\nresponses = [\"Yes.\"]\nweight = 0\n[negative]\nresponses = [\"No.\"]\nweight = 0",
+            )
+            .is_err()
+        );
+
+        // The largest weights add up without overflowing
+        let responses = EightBallResponses::parse(
+            "[affirmative]\nresponses = [\"Yes.\"]\nweight = 4294967295\n[negative]\nresponses = [\"No.\"]\nweight = 4294967295",
+        )?;
+        assert!(responses.choose().is_some());
+        assert_eq!(responses.odds()[0].2, 0.5);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_eight_ball_custom_format() -> Result<()> {
+        let ctx = create_test_context();
+        let responses = Arc::new(EightBallResponses::new(vec![(
+            ResponseType::Affirmative,
+            vec!["Yes.".to_string()],
+        )]));
+        let command = EightBallCommand::with_responses(
+            responses,
+            Personality::default(),
+            "@{user} asked: {question} 🎱 {response}".to_string(),
+        );
+        let msg = create_dummy_privmsg();
+
+        let result = command
+            .execute(&ctx, &msg, vec!["Will", "I", "win?"])
+            .await?;
+        assert_eq!(
+            result,
+            Some("@Test_User asked: Will I win? 🎱 Yes.".to_string())
+        );
+
+        // Placeholders typed into the question are left alone
+        let result = command.execute(&ctx, &msg, vec!["{response}?"]).await?;
+        assert_eq!(
+            result,
+            Some("@Test_User asked: {response}? 🎱 Yes.".to_string())
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_eight_ball_does_not_repeat() -> Result<()> {
+        let ctx = create_test_context();
+        let responses = Arc::new(EightBallResponses::new(vec![
+            (ResponseType::Affirmative, vec!["Yes.".to_string()]),
+            (ResponseType::Negative, vec!["No.".to_string()]),
+        ]));
+        let command = EightBallCommand::with_responses(
+            responses,
+            Personality::default(),
+            "{response}".into(),
+        );
+        let msg = create_dummy_privmsg();
+
+        let mut last = command
+            .execute(&ctx, &msg, vec!["Will", "I", "win?"])
+            .await?;
+        for _ in 0..20 {
+            let answer = command
+                .execute(&ctx, &msg, vec!["Will", "I", "win?"])
+                .await?;
+            assert_ne!(answer, last);
+            last = answer;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_eight_ball_reset() -> Result<()> {
+        let ctx = create_test_context();
+        let responses = Arc::new(EightBallResponses::new(vec![(
+            ResponseType::Affirmative,
+            vec!["Yes.".to_string()],
+        )]));
+        let mut command = EightBallCommand::with_responses(
+            responses,
+            Personality::default(),
+            "{response}".into(),
+        );
+        command.set_repeat_window(3);
+        let msg = create_dummy_privmsg();
+        let mut moderator = create_dummy_privmsg();
+        moderator.badges.push(Badge {
+            name: "moderator".to_string(),
+            version: "1".to_string(),
+        });
+
+        // With a single answer, repeating it is the only option
+        command.execute(&ctx, &msg, vec!["Really?"]).await?;
+        command.execute(&ctx, &msg, vec!["Really?"]).await?;
+        assert_eq!(
+            command.execute(&ctx, &moderator, vec!["recent"]).await?,
+            Some("🎱 Recent answers: Yes. | Yes.".to_string())
+        );
+
+        // Only moderators can reset; anyone else is asking a question
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["reset"]).await?,
+            Some("Yes.".to_string())
+        );
+        assert_eq!(
+            command.execute(&ctx, &moderator, vec!["reset"]).await?,
+            Some("🎱 Forgot 3 recent answers.".to_string())
+        );
+        assert_eq!(command.reset(), 0);
+        assert_eq!(
+            command.execute(&ctx, &moderator, vec!["recent"]).await?,
+            Some("🎱 No recent answers.".to_string())
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,76 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext};
+use crate::duration::format_ago;
+
+/// A command that reports when the bot first saw a user chat
+pub struct FirstSeenCommand;
+
+#[async_trait]
+impl Command for FirstSeenCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        // Default to the sender when no user is given
+        let login = args
+            .first()
+            .map(|arg| arg.trim_start_matches('@').to_lowercase())
+            .unwrap_or_else(|| msg.sender.login.clone());
+
+        let response = match ctx.user_manager.get_user_by_login(&login)? {
+            None => format!("I haven't seen @{} chat yet.", login),
+            Some(record) => match record.first_seen {
+                Some(first_seen) => {
+                    let elapsed = (Utc::now() - first_seen).to_std().unwrap_or_default();
+                    format!("I first saw @{} {}.", login, format_ago(elapsed))
+                }
+                // Users migrated from the old text file have no timestamps
+                None => format!("I know @{}, but not when I first saw them.", login),
+            },
+        };
+
+        Ok(Some(response))
+    }
+
+    fn help(&self) -> &str {
+        "Shows when the bot first saw a user chat. Usage: !firstseen [@user]"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+
+    #[tokio::test]
+    async fn test_first_seen_known_user() -> Result<()> {
+        let ctx = create_test_context();
+        ctx.user_manager.record_chat("300", "regular")?;
+        let command = FirstSeenCommand;
+        let msg = create_test_privmsg_with_badges("!firstseen @Regular", &[]);
+
+        let result = command.execute(&ctx, &msg, vec!["@Regular"]).await?;
+        assert_eq!(result, Some("I first saw @regular just now.".to_string()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_first_seen_unknown_user() -> Result<()> {
+        let ctx = create_test_context();
+        let command = FirstSeenCommand;
+        let msg = create_test_privmsg_with_badges("!firstseen stranger", &[]);
+
+        let result = command.execute(&ctx, &msg, vec!["stranger"]).await?;
+        assert_eq!(
+            result,
+            Some("I haven't seen @stranger chat yet.".to_string())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+//! Follow checks for follower-only commands
+//!
+//! Commands can be limited to chatters who follow the channel, either by
+//! declaring `Permission::Follower` or through the config. Badges don't show
+//! follows, so they're looked up with Helix and cached per user for a short
+//! while; a chatter who follows after being turned away only waits out the
+//! cache.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::Instant;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::CommandContext;
+
+/// How long a follow check is reused for
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Checks whether chatters follow the channel
+#[derive(Default)]
+pub struct FollowGate {
+    /// Recent follow checks and when they were made, keyed by channel and user ID
+    cache: std::sync::Mutex<HashMap<(String, String), (bool, Instant)>>,
+}
+
+impl FollowGate {
+    /// Create a new follow gate
+    ///
+    /// # Returns
+    /// A new FollowGate instance
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check whether the sender of a message follows the channel it was sent in
+    ///
+    /// # Arguments
+    /// * `ctx` - The command context whose Helix client checks follows
+    /// * `msg` - The message whose sender should be checked
+    ///
+    /// # Returns
+    /// true if the sender follows the channel
+    pub async fn follows(&self, ctx: &CommandContext, msg: &PrivmsgMessage) -> Result<bool> {
+        let key = (msg.channel_id.clone(), msg.sender.id.clone());
+        if let Some((follows, checked)) = self.cache.lock().unwrap().get(&key)
+            && checked.elapsed() < CACHE_TTL
+        {
+            return Ok(*follows);
+        }
+
+        let follows = ctx
+            .helix()?
+            .lock()
+            .await
+            .get_followed_at(&msg.channel_id, &msg.sender.id)
+            .await?
+            .is_some();
+
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|_, (_, checked)| checked.elapsed() < CACHE_TTL);
+        cache.insert(key, (follows, Instant::now()));
+        Ok(follows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context_with_helix, create_test_privmsg_with_badges};
+    use mockito::{Matcher, Server};
+
+    #[tokio::test]
+    async fn test_follow_gate() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let following = server
+            .mock("GET", "/channels/followers")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("broadcaster_id".into(), "456".into()),
+                Matcher::UrlEncoded("user_id".into(), "123".into()),
+            ]))
+            .with_status(200)
+            .with_body(
+                r#"{"total":1,"data":[{"user_id":"123","user_login":"test_user",
+                "user_name":"Test_User","followed_at":"2022-05-24T22:22:08Z"}],"pagination":{}}"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let not_following = server
+            .mock("GET", "/channels/followers")
+            .match_query(Matcher::UrlEncoded("user_id".into(), "300".into()))
+            .with_status(200)
+            .with_body(r#"{"total":5,"data":[],"pagination":{}}"#)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let ctx = create_test_context_with_helix(&server.url()).await;
+        let gate = FollowGate::new();
+        let follower = create_test_privmsg_with_badges("!quote", &[]);
+        let mut viewer = create_test_privmsg_with_badges("!quote", &[]);
+        viewer.sender.id = "300".to_string();
+
+        assert!(gate.follows(&ctx, &follower).await?);
+        assert!(!gate.follows(&ctx, &viewer).await?);
+
+        // Checks are cached for a while
+        assert!(gate.follows(&ctx, &follower).await?);
+        assert!(!gate.follows(&ctx, &viewer).await?);
+        // Only pause around the jump, since a paused clock skips ahead to the
+        // HTTP client's timeout while waiting on the mock server
+        tokio::time::pause();
+        tokio::time::advance(CACHE_TTL).await;
+        tokio::time::resume();
+        assert!(!gate.follows(&ctx, &viewer).await?);
+
+        following.assert_async().await;
+        not_following.assert_async().await;
+        Ok(())
+    }
+}
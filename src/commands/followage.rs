@@ -0,0 +1,107 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext};
+use crate::duration::format_calendar_age;
+use crate::twitch::TwitchError;
+
+/// A command that shows how long someone has followed the channel
+pub struct FollowageCommand;
+
+#[async_trait]
+impl Command for FollowageCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let mut helix = ctx.helix()?.lock().await;
+
+        // The caller's ID comes with the message; anyone else has to be looked up
+        let (name, user_id) = match args.first() {
+            Some(target) => {
+                let login = target.trim_start_matches('@').to_lowercase();
+                match helix.get_broadcaster_id(&login).await {
+                    Ok(id) => (login, id),
+                    Err(TwitchError::NotFound(_)) => {
+                        return Ok(Some(format!("Couldn't find a user named {}", login)));
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            None => (msg.sender.name.clone(), msg.sender.id.clone()),
+        };
+
+        // The room ID is the broadcaster's user ID
+        let response = match helix.get_followed_at(&msg.channel_id, &user_id).await? {
+            Some(followed_at) => format!(
+                "{} has been following {} for {} (since {})",
+                name,
+                msg.channel_login,
+                format_calendar_age(followed_at, Utc::now()),
+                followed_at.format("%Y-%m-%d")
+            ),
+            None => format!("{} is not following {}", name, msg.channel_login),
+        };
+        Ok(Some(response))
+    }
+
+    fn help(&self) -> &str {
+        "Shows how long someone has followed the channel. Usage: !followage [user]"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context_with_helix, create_test_privmsg_with_badges};
+    use mockito::{Matcher, Server};
+
+    #[tokio::test]
+    async fn test_followage() -> Result<()> {
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "lurker".into()))
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"300","login":"lurker","display_name":"Lurker"}]}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/channels/followers")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("broadcaster_id".into(), "456".into()),
+                Matcher::UrlEncoded("user_id".into(), "123".into()),
+            ]))
+            .with_status(200)
+            .with_body(
+                r#"{"total":1,"data":[{"user_id":"123","followed_at":"2022-05-24T22:22:08Z"}]}"#,
+            )
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/channels/followers")
+            .match_query(Matcher::UrlEncoded("user_id".into(), "300".into()))
+            .with_status(200)
+            .with_body(r#"{"total":1,"data":[]}"#)
+            .create_async()
+            .await;
+
+        let ctx = create_test_context_with_helix(&server.url()).await;
+        let command = FollowageCommand;
+        let msg = create_test_privmsg_with_badges("!followage", &[]);
+
+        let response = command.execute(&ctx, &msg, vec![]).await?.unwrap();
+        assert!(response.starts_with("Test_User has been following test_channel for "));
+        assert!(response.ends_with("(since 2022-05-24)"));
+
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["@Lurker"]).await?,
+            Some("lurker is not following test_channel".to_string())
+        );
+        Ok(())
+    }
+}
@@ -1,18 +1,210 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow, bail};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 use twitch_irc::message::PrivmsgMessage;
 
-use crate::commands::CommandRegistry;
-use crate::twitch::TwitchClient;
+use crate::commands::{
+    Command, CommandChannels, CommandContext, CommandPrefixes, CommandRegistry, CommandUsage,
+    CooldownTracker, FollowGate, Permission, PrefixConflictDetector, Rejection,
+};
+use crate::features::{ChannelFeatures, Feature};
+use crate::twitch::{MAX_MESSAGE_LENGTH, MessageSender, TwitchError, split_message};
+
+/// The shortest gap between the messages of one command response
+///
+/// The outbound rate limiter allows bursts, so without a gap a response of
+/// several messages would arrive all at once, and Twitch drops messages that
+/// a regular chatter sends much faster than one a second.
+const RESPONSE_MESSAGE_GAP: Duration = Duration::from_secs(1);
+
+/// Send the messages of one response in order, paced `RESPONSE_MESSAGE_GAP` apart
+///
+/// Sending stops at the first message that fails.
+///
+/// # Arguments
+/// * `messages` - The messages to send, in order
+/// * `send` - Sends one message
+///
+/// # Returns
+/// A Result indicating success or failure
+async fn send_paced<F, Fut>(messages: Vec<String>, mut send: F) -> Result<()>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    for (i, message) in messages.into_iter().enumerate() {
+        if i > 0 {
+            tokio::time::sleep(RESPONSE_MESSAGE_GAP).await;
+        }
+        send(message).await?;
+    }
+    Ok(())
+}
+
+/// Sort a command's result into the replies to post and whether it did what it was asked
+///
+/// A `Rejection` is posted like a reply, and a reply starting with "Usage:"
+/// means the command couldn't use its input.
+///
+/// # Arguments
+/// * `result` - What the command returned
+///
+/// # Returns
+/// The replies and whether the command succeeded, or the error of a command that failed
+fn command_outcome(result: Result<Vec<String>>) -> Result<(Vec<String>, bool)> {
+    match result {
+        Ok(responses) => {
+            let succeeded = !responses
+                .first()
+                .is_some_and(|response| response.starts_with("Usage:"));
+            Ok((responses, succeeded))
+        }
+        Err(e) => match e.downcast::<Rejection>() {
+            Ok(Rejection(reply)) => Ok((vec![reply], false)),
+            Err(e) => Err(e),
+        },
+    }
+}
+
+/// How the parts of a command response are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Delivery {
+    /// A chat reply to the message that ran the command
+    Reply,
+    /// A normal chat message, for commands that didn't come from chat
+    Say,
+    /// A whisper to the user who ran the command
+    Whisper,
+}
+
+/// Send one part of a command response
+///
+/// # Arguments
+/// * `client` - The client to send with
+/// * `msg` - The message that triggered the command
+/// * `delivery` - How to send it
+/// * `response` - The text to send
+///
+/// # Returns
+/// A Result indicating success or failure
+async fn send_part(
+    client: &dyn MessageSender,
+    msg: &PrivmsgMessage,
+    delivery: Delivery,
+    response: &str,
+) -> Result<()> {
+    match delivery {
+        Delivery::Reply => send_response(client, msg, response).await,
+        Delivery::Say => client.say(&msg.channel_login, response).await,
+        Delivery::Whisper => {
+            whisper_response(client, msg, response).await;
+            Ok(())
+        }
+    }
+}
+
+/// Whisper one message to the user who ran a command
+///
+/// Failures are logged rather than posted, since the reply is private.
+///
+/// # Arguments
+/// * `client` - The client to send with
+/// * `msg` - The chat message that triggered the command
+/// * `response` - The text to whisper
+async fn whisper_response(client: &dyn MessageSender, msg: &PrivmsgMessage, response: &str) {
+    match client
+        .whisper(&msg.channel_login, &msg.sender, response)
+        .await
+    {
+        Ok(()) => debug!("Whispered reply to {}", msg.sender.name),
+        Err(e) if matches!(e.downcast_ref(), Some(TwitchError::PhoneNotVerified)) => {
+            error!("Couldn't whisper {}: {}", msg.sender.name, e);
+        }
+        Err(e) => warn!("Failed to whisper reply to {}: {}", msg.sender.name, e),
+    }
+}
+
+/// Send one chat message in reply to a command
+///
+/// # Arguments
+/// * `client` - The client to send with
+/// * `msg` - The chat message that triggered the command
+/// * `response` - The text to send
+///
+/// # Returns
+/// A Result indicating success or failure
+async fn send_response(
+    client: &dyn MessageSender,
+    msg: &PrivmsgMessage,
+    response: &str,
+) -> Result<()> {
+    // Use the message ID for replies
+    let msg_id = &msg.message_id;
+    // Try to use the reply API
+    match client.reply(&msg.channel_login, response, msg_id).await {
+        Ok(_) => {
+            debug!("Successfully sent reply to message ID {}", msg_id);
+        }
+        Err(e) if matches!(e.downcast_ref(), Some(TwitchError::MessageDropped { .. })) => {
+            // Sending the same text as a normal message would be dropped too
+            warn!("Twitch dropped the reply to message ID {}: {}", msg_id, e);
+        }
+        Err(e) => {
+            // If reply fails, fall back to normal message
+            warn!(
+                "Failed to send reply, falling back to normal message: {}",
+                e
+            );
+            client.say(&msg.channel_login, response).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Split a chat message into a command name and the text after it
+///
+/// Whitespace around the message and between the prefix and the name is
+/// ignored. The remainder starts after the single whitespace character that
+/// ends the name, so commands taking raw text see any further spacing as typed.
+///
+/// # Arguments
+/// * `text` - The chat message text
+/// * `prefix` - The command prefix in the message's channel
+///
+/// # Returns
+/// The lowercased command name and the remainder, or None if the message isn't a command
+pub fn parse_command<'a>(text: &'a str, prefix: &str) -> Option<(String, &'a str)> {
+    let without_prefix = text.trim_start().strip_prefix(prefix)?.trim_start();
+    let name_end = without_prefix
+        .find(char::is_whitespace)
+        .unwrap_or(without_prefix.len());
+    if name_end == 0 {
+        return None;
+    }
+
+    let (name, rest) = without_prefix.split_at(name_end);
+    let separator = rest.chars().next().map_or(0, char::len_utf8);
+    Some((name.to_lowercase(), &rest[separator..]))
+}
 
 /// Handler for processing incoming chat messages and executing commands
 pub struct CommandHandler {
-    client: Arc<TwitchClient>,
+    /// The client for sending replies (a TwitchClient, or a mock offline)
+    client: Arc<dyn MessageSender>,
     registry: Arc<RwLock<CommandRegistry>>,
-    prefix: String,
-    bot_username: String,
+    prefixes: CommandPrefixes,
+    context: CommandContext,
+    cooldowns: Arc<CooldownTracker>,
+    follow_gate: Option<Arc<FollowGate>>,
+    /// Permissions that replace what a command declares, keyed by command name
+    permissions: HashMap<String, Permission>,
+    conflicts: Option<PrefixConflictDetector>,
+    usage: Option<Arc<CommandUsage>>,
+    features: Arc<ChannelFeatures>,
+    channel_limits: Arc<CommandChannels>,
 }
 
 impl CommandHandler {
@@ -21,24 +213,122 @@ impl CommandHandler {
     /// # Arguments
     /// * `client` - The Twitch client for sending messages
     /// * `registry` - The registry of available commands
-    /// * `prefix` - The command prefix (e.g., "!")
+    /// * `prefixes` - The command prefix (e.g., "!") for each channel
+    /// * `context` - Shared state passed to every command
     ///
     /// # Returns
     /// A new CommandHandler instance
     pub fn new(
-        client: Arc<TwitchClient>,
+        client: Arc<dyn MessageSender>,
         registry: Arc<RwLock<CommandRegistry>>,
-        prefix: String,
-        bot_username: String,
+        prefixes: CommandPrefixes,
+        context: CommandContext,
     ) -> Self {
         CommandHandler {
             client,
             registry,
-            prefix,
-            bot_username,
+            prefixes,
+            context,
+            cooldowns: Arc::new(CooldownTracker::default()),
+            follow_gate: None,
+            permissions: HashMap::new(),
+            conflicts: None,
+            usage: None,
+            features: Arc::new(ChannelFeatures::default()),
+            channel_limits: Arc::new(CommandChannels::default()),
         }
     }
 
+    /// Warn when another bot seems to answer the same commands
+    ///
+    /// # Arguments
+    /// * `detector` - The detector to pass chat messages and answered commands to
+    pub fn set_conflict_detector(&mut self, detector: PrefixConflictDetector) {
+        self.conflicts = Some(detector);
+    }
+
+    /// Limit how often commands can be used
+    ///
+    /// # Arguments
+    /// * `cooldowns` - The cooldown tracker to consult before running a command
+    pub fn set_cooldowns(&mut self, cooldowns: Arc<CooldownTracker>) {
+        self.cooldowns = cooldowns;
+    }
+
+    /// Look up follows for commands limited to followers
+    ///
+    /// Without it, only moderators can use follower-only commands.
+    ///
+    /// # Arguments
+    /// * `follow_gate` - The follow checker to consult before running such a command
+    pub fn set_follow_gate(&mut self, follow_gate: Arc<FollowGate>) {
+        self.follow_gate = Some(follow_gate);
+    }
+
+    /// Change who may run some commands, e.g. to limit them to subscribers
+    ///
+    /// # Arguments
+    /// * `permissions` - The permission for each command, keyed by command name
+    pub fn set_permissions(&mut self, permissions: HashMap<String, Permission>) {
+        self.permissions = permissions;
+    }
+
+    /// Check whether the sender of a message may run a command
+    ///
+    /// # Arguments
+    /// * `permission` - Who may run the command
+    /// * `msg` - The message running the command
+    ///
+    /// # Returns
+    /// true if the sender is allowed
+    async fn is_allowed(&self, permission: Permission, msg: &PrivmsgMessage) -> bool {
+        if permission.is_satisfied_by(msg) {
+            return true;
+        }
+        if permission != Permission::Follower {
+            return false;
+        }
+
+        let Some(gate) = &self.follow_gate else {
+            return false;
+        };
+        match gate.follows(&self.context, msg).await {
+            Ok(follows) => follows,
+            Err(e) => {
+                // Don't turn followers away because Twitch couldn't be reached
+                warn!(
+                    "Couldn't check whether {} follows, allowing the command: {}",
+                    msg.sender.name, e
+                );
+                true
+            }
+        }
+    }
+
+    /// Count how often each command is used
+    ///
+    /// # Arguments
+    /// * `usage` - The tally to record each command run in
+    pub fn set_usage(&mut self, usage: Arc<CommandUsage>) {
+        self.usage = Some(usage);
+    }
+
+    /// Set which features are on in each channel
+    ///
+    /// # Arguments
+    /// * `features` - The features for each channel; commands are ignored where they're off
+    pub fn set_features(&mut self, features: Arc<ChannelFeatures>) {
+        self.features = features;
+    }
+
+    /// Limit some commands to some channels
+    ///
+    /// # Arguments
+    /// * `channel_limits` - The channels each limited command works in
+    pub fn set_channel_limits(&mut self, channel_limits: Arc<CommandChannels>) {
+        self.channel_limits = channel_limits;
+    }
+
     /// Process an incoming chat message
     ///
     /// # Arguments
@@ -47,78 +337,126 @@ impl CommandHandler {
     /// # Returns
     /// A Result indicating success or failure
     pub async fn handle_message(&self, msg: PrivmsgMessage) -> Result<()> {
-        let content = msg.message_text.trim();
-
-        debug!("Processing message for commands: '{}'", content);
-        debug!("Command prefix: '{}'", self.prefix);
+        // Look out for another bot answering the commands we answer
+        if let Some(conflicts) = &self.conflicts {
+            conflicts.observe(&msg);
+        }
 
-        // Check if the message is a command (starts with the prefix)
-        if !content.starts_with(&self.prefix) {
-            debug!("Message does not start with prefix, ignoring");
+        if !self
+            .features
+            .is_enabled(&msg.channel_login, Feature::Commands)
+        {
             return Ok(());
         }
 
-        // Parse the command name and arguments
-        let without_prefix = &content[self.prefix.len()..];
-        let parts: Vec<&str> = without_prefix.split_whitespace().collect();
+        let prefix = self.prefixes.for_channel(&msg.channel_login);
+
+        debug!("Processing message for commands: '{}'", msg.message_text);
+        debug!("Command prefix: '{}'", prefix);
 
-        if parts.is_empty() {
-            debug!("Empty command after prefix, ignoring");
+        // Parse the command name; arguments depend on the command
+        let Some((command_name, remainder)) = parse_command(&msg.message_text, prefix) else {
+            debug!("Message is not a command, ignoring");
             return Ok(());
-        }
+        };
 
-        let command_name = parts[0].to_lowercase();
-        let args = if parts.len() > 1 {
-            parts[1..].to_vec()
-        } else {
-            Vec::new()
+        debug!(
+            "Command name: '{}', remainder: {:?}",
+            command_name, remainder
+        );
+
+        // Get the command from the registry; aliases share the command's
+        // cooldowns and usage count
+        let command = {
+            let registry = self.registry.read().await;
+
+            // Log available commands for debugging
+            let available_commands = registry.get_command_names();
+            debug!("Available commands: {:?}", available_commands);
+
+            registry
+                .canonical_name(&command_name)
+                .and_then(|canonical| {
+                    registry
+                        .get_command(&canonical)
+                        .map(|command| (canonical, command))
+                })
         };
 
-        debug!("Command name: '{}', args: {:?}", command_name, args);
+        if let Some((command_name, command)) = command {
+            if let Some(feature) = command.feature()
+                && !self.features.is_enabled(&msg.channel_login, feature)
+            {
+                info!(
+                    "'{}' needs {:?}, which is off in {}, ignoring",
+                    command_name, feature, msg.channel_login
+                );
+                return Ok(());
+            }
+
+            if !self
+                .channel_limits
+                .is_allowed(&command_name, &msg.channel_login)
+            {
+                info!(
+                    "'{}' isn't available in {}, ignoring",
+                    command_name, msg.channel_login
+                );
+                let typed = format!("{}{}", prefix, command_name);
+                return match self
+                    .channel_limits
+                    .wrong_channel_reply(&typed, &command_name)
+                {
+                    Some(reply) => send_response(self.client.as_ref(), &msg, &reply).await,
+                    None => Ok(()),
+                };
+            }
 
-        // Get the command from the registry
-        let registry = self.registry.read().await;
+            let permission = self
+                .permissions
+                .get(&command_name)
+                .copied()
+                .unwrap_or_else(|| command.permission());
+            if !self.is_allowed(permission, &msg).await {
+                info!(
+                    "{} is not allowed to run '{}' (requires {:?})",
+                    msg.sender.name, command_name, permission
+                );
+                return match permission.denial_reply() {
+                    Some(reply) => send_response(self.client.as_ref(), &msg, reply).await,
+                    None => Ok(()),
+                };
+            }
 
-        // Log available commands for debugging
-        let available_commands = registry.get_command_names();
-        debug!("Available commands: {:?}", available_commands);
+            if let Some(wait) = self.cooldowns.check(&command_name, &msg) {
+                debug!(
+                    "'{}' is on cooldown for {} for another {:?}",
+                    command_name, msg.sender.name, wait
+                );
+                return Ok(());
+            }
+
+            if let Some(usage) = &self.usage
+                && let Err(e) = usage.record(&command_name)
+            {
+                error!("Error saving command usage: {}", e);
+            }
+
+            let args = command.arg_mode().args(remainder);
+            let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+            debug!("Args: {:?}", args);
 
-        if let Some(command) = registry.get_command(&command_name) {
             info!("Found command '{}', executing", command_name);
-            match command.execute(&msg, args) {
-                Ok(Some(response)) => {
-                    // Send the response to the chat
-                    info!(
-                        "Command '{}' returning response: '{}'",
-                        command_name, response
-                    );
-                    let mut client = self.client.as_ref().clone();
-
-                    // Use the message ID for replies
-                    let msg_id = &msg.message_id;
-                    // Try to use the reply API
-                    match client
-                        .send_reply(&msg.channel_login, &response, msg_id, &self.bot_username)
-                        .await
-                    {
-                        Ok(_) => {
-                            debug!("Successfully sent reply to message ID {}", msg_id);
-                        }
-                        Err(e) => {
-                            // If reply fails, fall back to normal message
-                            warn!(
-                                "Failed to send reply, falling back to normal message: {}",
-                                e
-                            );
-                            client
-                                .send_message(&msg.channel_login, &response, &self.bot_username)
-                                .await?;
-                        }
-                    }
-                }
-                Ok(None) => {
-                    // No response needed
-                    debug!("Command '{}' executed with no response", command_name);
+            match command_outcome(command.execute_lines(&self.context, &msg, args).await) {
+                Ok((responses, _)) => {
+                    self.send_responses(
+                        &command_name,
+                        command.as_ref(),
+                        &msg,
+                        Delivery::Reply,
+                        responses,
+                    )
+                    .await?;
                 }
                 Err(e) => {
                     // Command execution failed
@@ -131,10 +469,646 @@ impl CommandHandler {
 
         Ok(())
     }
+
+    /// Run the command in a message without checking who may run it
+    ///
+    /// Permissions, cooldowns, and channel limits are skipped, e.g. for a
+    /// channel point reward the viewer already paid for. The message didn't
+    /// come from chat, so responses are posted rather than replied.
+    ///
+    /// # Arguments
+    /// * `msg` - The message holding the command, sent as the user it runs for
+    ///
+    /// # Returns
+    /// An error if there is no such command, it failed, or it rejected its input
+    pub async fn run_unchecked(&self, msg: &PrivmsgMessage) -> Result<()> {
+        let prefix = self.prefixes.for_channel(&msg.channel_login);
+        let (command_name, remainder) = parse_command(&msg.message_text, prefix)
+            .ok_or_else(|| anyhow!("'{}' isn't a command", msg.message_text))?;
+
+        let command = {
+            let registry = self.registry.read().await;
+            registry
+                .canonical_name(&command_name)
+                .and_then(|canonical| {
+                    registry
+                        .get_command(&canonical)
+                        .map(|command| (canonical, command))
+                })
+        };
+        let (command_name, command) =
+            command.ok_or_else(|| anyhow!("No command named '{}'", command_name))?;
+
+        if let Some(usage) = &self.usage
+            && let Err(e) = usage.record(&command_name)
+        {
+            error!("Error saving command usage: {}", e);
+        }
+
+        info!("Running '{}' for {}", command_name, msg.sender.name);
+        let args = command.arg_mode().args(remainder);
+        let args = args.iter().map(AsRef::as_ref).collect();
+        let (responses, succeeded) =
+            command_outcome(command.execute_lines(&self.context, msg, args).await)?;
+        self.send_responses(
+            &command_name,
+            command.as_ref(),
+            msg,
+            Delivery::Say,
+            responses,
+        )
+        .await?;
+        if !succeeded {
+            bail!("'{}' couldn't use '{}'", command_name, msg.message_text);
+        }
+        Ok(())
+    }
+
+    /// Send a command's responses, split to fit and paced apart
+    ///
+    /// Only the first message is sent before returning; the rest follow
+    /// from a separate task, and failures sending them are logged.
+    ///
+    /// # Arguments
+    /// * `command_name` - The name of the command that responded
+    /// * `command` - The command that responded
+    /// * `msg` - The message that triggered the command
+    /// * `delivery` - How to send the responses unless the command whispers them
+    /// * `responses` - The command's responses
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    async fn send_responses(
+        &self,
+        command_name: &str,
+        command: &dyn Command,
+        msg: &PrivmsgMessage,
+        delivery: Delivery,
+        responses: Vec<String>,
+    ) -> Result<()> {
+        if responses.is_empty() {
+            // No response needed
+            debug!("Command '{}' executed with no response", command_name);
+            return Ok(());
+        }
+
+        if let Some(conflicts) = &self.conflicts {
+            conflicts.record_command(msg);
+        }
+
+        // Split each response to fit in chat messages
+        let mut parts = Vec::new();
+        for response in responses {
+            info!(
+                "Command '{}' returning response: '{}'",
+                command_name, response
+            );
+            parts.extend(split_message(&response, MAX_MESSAGE_LENGTH));
+        }
+
+        // Send the first part now, and pace the rest from a task of their
+        // own so the chat connection isn't held up while they wait
+        let delivery = if command.whisper_replies() {
+            Delivery::Whisper
+        } else {
+            delivery
+        };
+        let mut parts = parts.into_iter();
+        if let Some(first) = parts.next() {
+            send_part(self.client.as_ref(), msg, delivery, &first).await?;
+        }
+        let rest: Vec<String> = parts.collect();
+        if !rest.is_empty() {
+            let client = self.client.clone();
+            let msg = msg.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(RESPONSE_MESSAGE_GAP).await;
+                let sent = send_paced(rest, |part| {
+                    let (client, msg) = (&client, &msg);
+                    async move { send_part(client.as_ref(), msg, delivery, &part).await }
+                })
+                .await;
+                if let Err(e) = sent {
+                    warn!("Couldn't send the rest of a command response: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    // Note: Testing CommandHandler would require mocking TwitchClient
-    // These tests will be added later
+    use super::*;
+    use crate::commands::{ActivityCommand, ArgMode, PingCommand, PointsCommand};
+    use crate::points::PointsService;
+    use crate::store::{StoreBackend, open_store};
+    use crate::test_helpers::{
+        create_test_context, create_test_context_with_helix, create_test_privmsg_with_badges,
+    };
+    use crate::users::MockTwitchClient;
+    use async_trait::async_trait;
+    use std::collections::HashSet;
+    use tempfile::tempdir;
+
+    /// Create a handler with `!ping` and `!activity` that sends through `client`
+    fn create_test_handler(client: &MockTwitchClient) -> CommandHandler {
+        let mut registry = CommandRegistry::new();
+        registry.register("ping", Arc::new(PingCommand));
+        registry.register("activity", Arc::new(ActivityCommand));
+        CommandHandler::new(
+            Arc::new(client.clone()),
+            Arc::new(RwLock::new(registry)),
+            CommandPrefixes::new("!".to_string(), HashMap::new()).unwrap(),
+            create_test_context(),
+        )
+    }
+
+    /// Parse a message and split its arguments as a command with `mode` would get them
+    fn parse(text: &str, mode: ArgMode) -> Option<(String, Vec<String>)> {
+        parse_command(text, "!").map(|(name, remainder)| {
+            let args = mode.args(remainder);
+            (name, args.into_iter().map(String::from).collect())
+        })
+    }
+
+    /// The arguments a test expects
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_command_split_args() {
+        let split = |text| parse(text, ArgMode::Split);
+
+        assert_eq!(split("!8ball"), Some(("8ball".to_string(), vec![])));
+        assert_eq!(split("!8ball    "), Some(("8ball".to_string(), vec![])));
+        assert_eq!(
+            split("  !8Ball  will   I win?  "),
+            Some(("8ball".to_string(), words(&["will", "I", "win?"])))
+        );
+        assert_eq!(split("! ping"), Some(("ping".to_string(), vec![])));
+        assert_eq!(split("!"), None);
+        assert_eq!(split("!   "), None);
+        assert_eq!(split("hello !ping"), None);
+    }
+
+    #[test]
+    fn test_parse_command_quoted_args() {
+        let quoted = |text| parse(text, ArgMode::Quoted);
+
+        assert_eq!(quoted("!poll   "), Some(("poll".to_string(), vec![])));
+        assert_eq!(
+            quoted(r#"!poll start "Best game?" Celeste "Hollow Knight""#),
+            Some((
+                "poll".to_string(),
+                words(&["start", "Best game?", "Celeste", "Hollow Knight"])
+            ))
+        );
+        // Escaped quotes and backslashes are kept, other backslashes left alone
+        assert_eq!(
+            quoted(r#"!poll start "Say \"hi\"" \o/ a\\b"#),
+            Some((
+                "poll".to_string(),
+                words(&["start", r#"Say "hi""#, r"\o/", r"a\b"])
+            ))
+        );
+        // An unclosed quote runs to the end, and empty quotes give nothing
+        assert_eq!(
+            quoted(r#"!poll start "" "Best  game?"#),
+            Some(("poll".to_string(), words(&["start", "Best  game?"])))
+        );
+    }
+
+    #[test]
+    fn test_parse_command_raw_remainder() {
+        let raw = |text| parse(text, ArgMode::Raw);
+
+        assert_eq!(raw("!addquote"), Some(("addquote".to_string(), vec![])));
+        assert_eq!(raw("!addquote   "), Some(("addquote".to_string(), vec![])));
+
+        // Only the space ending the name is dropped
+        assert_eq!(
+            raw("  !addquote  so   close  "),
+            Some(("addquote".to_string(), words(&[" so   close  "])))
+        );
+        assert_eq!(
+            raw("!addquote\tso close"),
+            Some(("addquote".to_string(), words(&["so close"])))
+        );
+    }
+
+    #[test]
+    fn test_parse_command_multi_char_prefix() {
+        assert_eq!(
+            parse_command(">>8ball will I win?", ">>"),
+            Some(("8ball".to_string(), "will I win?"))
+        );
+        assert_eq!(
+            parse_command(">> ping", ">>"),
+            Some(("ping".to_string(), ""))
+        );
+        assert_eq!(parse_command(">ping", ">>"), None);
+        assert_eq!(parse_command("!ping", ">>"), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_response_messages_are_sent_in_order_and_paced() {
+        let start = tokio::time::Instant::now();
+        let sent = std::sync::Mutex::new(Vec::new());
+        let messages = vec![
+            "first".to_string(),
+            "second".to_string(),
+            "third".to_string(),
+        ];
+
+        send_paced(messages, |message| {
+            sent.lock().unwrap().push((start.elapsed(), message));
+            async { Ok(()) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            sent.into_inner().unwrap(),
+            vec![
+                (Duration::ZERO, "first".to_string()),
+                (RESPONSE_MESSAGE_GAP, "second".to_string()),
+                (RESPONSE_MESSAGE_GAP * 2, "third".to_string()),
+            ]
+        );
+
+        // A failed message stops the rest of the response
+        let mut attempts = 0;
+        let result = send_paced(vec!["a".to_string(), "b".to_string()], |_| {
+            attempts += 1;
+            async { Err(anyhow::anyhow!("dropped")) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_handler_replies_through_its_sender() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let handler = create_test_handler(&client);
+
+        handler
+            .handle_message(create_test_privmsg_with_badges("!PING", &[]))
+            .await?;
+        // Chat that isn't a command, and unknown commands, get no reply
+        handler
+            .handle_message(create_test_privmsg_with_badges("hello !ping", &[]))
+            .await?;
+        handler
+            .handle_message(create_test_privmsg_with_badges("!nope", &[]))
+            .await?;
+        // Moderator commands are ignored for viewers
+        handler
+            .handle_message(create_test_privmsg_with_badges("!activity @someone", &[]))
+            .await?;
+        assert_eq!(
+            client.sent_messages(),
+            vec![(
+                "test_channel".to_string(),
+                "Pong! Received from Test_User who said: !PING".to_string()
+            )]
+        );
+
+        handler
+            .handle_message(create_test_privmsg_with_badges(
+                "!activity @someone",
+                &["moderator"],
+            ))
+            .await?;
+        assert_eq!(
+            client.sent_messages().last(),
+            Some(&(
+                "test_channel".to_string(),
+                "I haven't seen @someone chat yet.".to_string()
+            ))
+        );
+
+        // A denied command can explain itself
+        let client = MockTwitchClient::new();
+        let mut handler = create_test_handler(&client);
+        handler.set_permissions(HashMap::from([(
+            "ping".to_string(),
+            Permission::Subscriber,
+        )]));
+        handler
+            .handle_message(create_test_privmsg_with_badges("!ping", &[]))
+            .await?;
+        assert_eq!(
+            client.sent_messages(),
+            vec![(
+                "test_channel".to_string(),
+                "This command is just for subscribers, sorry!".to_string()
+            )]
+        );
+        Ok(())
+    }
+
+    /// A command only followers may use
+    struct FollowerCommand;
+
+    #[async_trait]
+    impl Command for FollowerCommand {
+        async fn execute(
+            &self,
+            _ctx: &CommandContext,
+            _msg: &PrivmsgMessage,
+            _args: Vec<&str>,
+        ) -> Result<Option<String>> {
+            Ok(Some("Thanks for following!".to_string()))
+        }
+
+        fn help(&self) -> &str {
+            "Thanks a follower"
+        }
+
+        fn permission(&self) -> Permission {
+            Permission::Follower
+        }
+    }
+
+    #[tokio::test]
+    async fn test_follower_commands_check_follows() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _following = server
+            .mock("GET", "/channels/followers")
+            .match_query(mockito::Matcher::UrlEncoded("user_id".into(), "123".into()))
+            .with_status(200)
+            .with_body(
+                r#"{"total":1,"data":[{"user_id":"123","user_login":"test_user",
+                "user_name":"Test_User","followed_at":"2022-05-24T22:22:08Z"}],"pagination":{}}"#,
+            )
+            .create_async()
+            .await;
+        let _not_following = server
+            .mock("GET", "/channels/followers")
+            .match_query(mockito::Matcher::UrlEncoded("user_id".into(), "300".into()))
+            .with_status(200)
+            .with_body(r#"{"total":5,"data":[],"pagination":{}}"#)
+            .create_async()
+            .await;
+
+        let client = MockTwitchClient::new();
+        let mut registry = CommandRegistry::new();
+        registry.register("thanks", Arc::new(FollowerCommand));
+        let mut handler = CommandHandler::new(
+            Arc::new(client.clone()),
+            Arc::new(RwLock::new(registry)),
+            CommandPrefixes::new("!".to_string(), HashMap::new())?,
+            create_test_context_with_helix(&server.url()).await,
+        );
+        handler.set_follow_gate(Arc::new(FollowGate::new()));
+
+        // A viewer who doesn't follow is told why, and the command doesn't run
+        let mut viewer = create_test_privmsg_with_badges("!thanks", &[]);
+        viewer.sender.id = "300".to_string();
+        handler.handle_message(viewer).await?;
+        assert_eq!(
+            client.sent_messages(),
+            vec![(
+                "test_channel".to_string(),
+                Permission::Follower.denial_reply().unwrap().to_string()
+            )]
+        );
+
+        handler
+            .handle_message(create_test_privmsg_with_badges("!thanks", &[]))
+            .await?;
+        assert_eq!(
+            client.sent_messages().last(),
+            Some(&(
+                "test_channel".to_string(),
+                "Thanks for following!".to_string()
+            ))
+        );
+        Ok(())
+    }
+
+    /// A command that answers in two messages
+    struct TwoLinesCommand;
+
+    #[async_trait]
+    impl Command for TwoLinesCommand {
+        async fn execute(
+            &self,
+            _ctx: &CommandContext,
+            _msg: &PrivmsgMessage,
+            _args: Vec<&str>,
+        ) -> Result<Option<String>> {
+            Ok(None)
+        }
+
+        async fn execute_lines(
+            &self,
+            _ctx: &CommandContext,
+            _msg: &PrivmsgMessage,
+            _args: Vec<&str>,
+        ) -> Result<Vec<String>> {
+            Ok(vec!["first".to_string(), "second".to_string()])
+        }
+
+        fn help(&self) -> &str {
+            "Answers twice"
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_later_response_parts_dont_hold_up_the_handler() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let handler = create_test_handler(&client);
+        handler
+            .registry
+            .write()
+            .await
+            .register("two", Arc::new(TwoLinesCommand));
+
+        let start = tokio::time::Instant::now();
+        handler
+            .handle_message(create_test_privmsg_with_badges("!two", &[]))
+            .await?;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        assert_eq!(
+            client.sent_messages(),
+            vec![("test_channel".to_string(), "first".to_string())]
+        );
+
+        // The second part follows after the gap
+        tokio::time::sleep(RESPONSE_MESSAGE_GAP * 2).await;
+        assert_eq!(client.sent_messages().len(), 2);
+        assert_eq!(client.sent_messages()[1].1, "second");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_whispered_replies_skip_chat() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let points = Arc::new(PointsService::new(
+            open_store(StoreBackend::File, temp_dir.path(), None)?,
+            10,
+        ));
+        let client = MockTwitchClient::new();
+        let handler = create_test_handler(&client);
+        handler
+            .registry
+            .write()
+            .await
+            .register("points", Arc::new(PointsCommand::new(points)));
+
+        handler
+            .handle_message(create_test_privmsg_with_badges("!points", &[]))
+            .await?;
+        // The mock shows whispers in the channel, marked as whispers
+        assert_eq!(
+            client.sent_messages(),
+            vec![(
+                "test_channel".to_string(),
+                "(whisper to Test_User) You have 0 points.".to_string()
+            )]
+        );
+        Ok(())
+    }
+
+    /// A command that turns down whatever it's asked
+    struct RejectingCommand;
+
+    #[async_trait]
+    impl Command for RejectingCommand {
+        async fn execute(
+            &self,
+            _ctx: &CommandContext,
+            _msg: &PrivmsgMessage,
+            _args: Vec<&str>,
+        ) -> Result<Option<String>> {
+            Err(Rejection("Not today".to_string()).into())
+        }
+
+        fn help(&self) -> &str {
+            "Says no"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejections_are_posted() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let handler = create_test_handler(&client);
+        handler
+            .registry
+            .write()
+            .await
+            .register("no", Arc::new(RejectingCommand));
+
+        handler
+            .handle_message(create_test_privmsg_with_badges("!no", &[]))
+            .await?;
+        assert_eq!(
+            client.sent_messages(),
+            vec![("test_channel".to_string(), "Not today".to_string())]
+        );
+
+        // Run on a viewer's behalf, it's posted and counts as failed
+        assert!(
+            handler
+                .run_unchecked(&create_test_privmsg_with_badges("!no", &[]))
+                .await
+                .is_err()
+        );
+        assert_eq!(client.sent_messages().len(), 2);
+        Ok(())
+    }
+
+    /// A sender that records whether each message was a reply
+    #[derive(Default)]
+    struct ReplyRecorder {
+        sent: std::sync::Mutex<Vec<(Option<String>, String)>>,
+    }
+
+    #[async_trait]
+    impl MessageSender for ReplyRecorder {
+        async fn say(&self, _channel: &str, message: &str) -> Result<()> {
+            self.sent.lock().unwrap().push((None, message.to_string()));
+            Ok(())
+        }
+
+        async fn reply(&self, _channel: &str, message: &str, reply_to: &str) -> Result<()> {
+            self.sent
+                .lock()
+                .unwrap()
+                .push((Some(reply_to.to_string()), message.to_string()));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_commands_not_from_chat_are_said() -> Result<()> {
+        let client = Arc::new(ReplyRecorder::default());
+        let mut registry = CommandRegistry::new();
+        registry.register("two", Arc::new(TwoLinesCommand));
+        let handler = CommandHandler::new(
+            client.clone(),
+            Arc::new(RwLock::new(registry)),
+            CommandPrefixes::new("!".to_string(), HashMap::new())?,
+            create_test_context(),
+        );
+
+        // Chat commands reply to the message that ran them
+        handler
+            .handle_message(create_test_privmsg_with_badges("!two", &[]))
+            .await?;
+        assert_eq!(
+            client.sent.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            vec![(Some("abc".to_string()), "first".to_string())]
+        );
+
+        // There's nothing to reply to for a command run on a viewer's behalf
+        handler
+            .run_unchecked(&create_test_privmsg_with_badges("!two", &[]))
+            .await?;
+        assert_eq!(
+            client.sent.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            vec![(None, "first".to_string())]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_commands_follow_their_feature() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let points = Arc::new(PointsService::new(
+            open_store(StoreBackend::File, temp_dir.path(), None)?,
+            10,
+        ));
+        let client = MockTwitchClient::new();
+        let mut handler = create_test_handler(&client);
+        handler.set_features(Arc::new(ChannelFeatures::new(HashMap::from([(
+            "test_channel".to_string(),
+            HashSet::from([Feature::Commands]),
+        )]))));
+        handler
+            .registry
+            .write()
+            .await
+            .register("points", Arc::new(PointsCommand::new(points)));
+
+        // Points are off here, while other commands still work
+        handler
+            .handle_message(create_test_privmsg_with_badges("!points", &[]))
+            .await?;
+        handler
+            .handle_message(create_test_privmsg_with_badges("!ping", &[]))
+            .await?;
+        assert_eq!(
+            client.sent_messages(),
+            vec![(
+                "test_channel".to_string(),
+                "Pong! Received from Test_User who said: !ping".to_string()
+            )]
+        );
+        Ok(())
+    }
 }
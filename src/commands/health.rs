@@ -0,0 +1,199 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext, Permission};
+use crate::duration::format_duration;
+use crate::twitch::{DeliveryTracker, JoinedChannels, OutboundLimiter};
+
+/// A snapshot of the bot's resource usage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthMetrics {
+    /// Resident memory of the process in bytes, where the platform reports it
+    pub memory_bytes: Option<u64>,
+    /// How long the bot has been running
+    pub uptime: Duration,
+    /// The channels the bot is in
+    pub channels: Vec<String>,
+    /// Chat messages waiting for the outbound rate limit
+    pub send_queue: usize,
+    /// Sent messages Twitch hasn't acknowledged yet
+    pub awaiting_delivery: usize,
+}
+
+/// Format health metrics as a single chat message
+///
+/// # Arguments
+/// * `metrics` - The metrics to report
+///
+/// # Returns
+/// A line like "Memory: 42.5 MB | Uptime: 1h 2m | Channels (1): test_channel | ..."
+pub fn format_health(metrics: &HealthMetrics) -> String {
+    let memory = match metrics.memory_bytes {
+        Some(bytes) => format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0)),
+        None => "unknown".to_string(),
+    };
+    let channels = if metrics.channels.is_empty() {
+        "none".to_string()
+    } else {
+        metrics.channels.join(", ")
+    };
+
+    format!(
+        "Memory: {} | Uptime: {} | Channels ({}): {} | Send queue: {} | Awaiting delivery: {}",
+        memory,
+        format_duration(metrics.uptime),
+        metrics.channels.len(),
+        channels,
+        metrics.send_queue,
+        metrics.awaiting_delivery
+    )
+}
+
+/// Read the resident memory from the contents of /proc/self/status
+///
+/// # Arguments
+/// * `status` - The file contents
+///
+/// # Returns
+/// The resident memory in bytes, if the file lists it
+fn parse_vm_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Get the resident memory of the bot process
+///
+/// Only Linux is supported; elsewhere memory is reported as unknown.
+fn resident_memory() -> Option<u64> {
+    if cfg!(target_os = "linux") {
+        parse_vm_rss(&std::fs::read_to_string("/proc/self/status").ok()?)
+    } else {
+        None
+    }
+}
+
+/// A command that reports the bot's memory use, uptime, channels, and queues
+pub struct HealthCommand {
+    started_at: Instant,
+    channels: Arc<JoinedChannels>,
+    outbound: Arc<OutboundLimiter>,
+    delivery: Arc<DeliveryTracker>,
+}
+
+impl HealthCommand {
+    /// Create a new health command
+    ///
+    /// # Arguments
+    /// * `channels` - The tracked set of joined channels
+    /// * `outbound` - The limiter pacing outbound chat messages
+    /// * `delivery` - The tracker of unacknowledged sends
+    ///
+    /// # Returns
+    /// A new HealthCommand instance
+    pub fn new(
+        channels: Arc<JoinedChannels>,
+        outbound: Arc<OutboundLimiter>,
+        delivery: Arc<DeliveryTracker>,
+    ) -> Self {
+        HealthCommand {
+            started_at: Instant::now(),
+            channels,
+            outbound,
+            delivery,
+        }
+    }
+
+    /// Gather the current metrics
+    fn metrics(&self) -> HealthMetrics {
+        HealthMetrics {
+            memory_bytes: resident_memory(),
+            uptime: self.started_at.elapsed(),
+            channels: self.channels.names(),
+            send_queue: self.outbound.queued(),
+            awaiting_delivery: self.delivery.pending_count(),
+        }
+    }
+}
+
+#[async_trait]
+impl Command for HealthCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        _msg: &PrivmsgMessage,
+        _args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        Ok(Some(format_health(&self.metrics())))
+    }
+
+    fn help(&self) -> &str {
+        "Shows the bot's memory use, uptime, channels, and queues. Usage: !health"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Broadcaster
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+
+    #[test]
+    fn test_format_health() {
+        let metrics = HealthMetrics {
+            memory_bytes: Some(44_564_480),
+            uptime: Duration::from_secs(3720),
+            channels: vec!["test_channel".to_string(), "other".to_string()],
+            send_queue: 3,
+            awaiting_delivery: 1,
+        };
+        assert_eq!(
+            format_health(&metrics),
+            "Memory: 42.5 MB | Uptime: 1h 2m | Channels (2): test_channel, other | Send queue: 3 | Awaiting delivery: 1"
+        );
+
+        let metrics = HealthMetrics {
+            memory_bytes: None,
+            uptime: Duration::ZERO,
+            channels: Vec::new(),
+            send_queue: 0,
+            awaiting_delivery: 0,
+        };
+        assert_eq!(
+            format_health(&metrics),
+            "Memory: unknown | Uptime: 0s | Channels (0): none | Send queue: 0 | Awaiting delivery: 0"
+        );
+    }
+
+    #[test]
+    fn test_parse_vm_rss() {
+        let status = "Name:\tsom_chatbot\nVmPeak:\t  90000 kB\nVmRSS:\t   43520 kB\nThreads:\t8\n";
+        assert_eq!(parse_vm_rss(status), Some(43520 * 1024));
+        assert_eq!(parse_vm_rss("Name:\tsom_chatbot\n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_health_command() -> Result<()> {
+        let ctx = create_test_context();
+        let channels = Arc::new(JoinedChannels::new());
+        channels.join("test_channel");
+        let command = HealthCommand::new(
+            channels,
+            Arc::new(OutboundLimiter::default()),
+            Arc::new(DeliveryTracker::new()),
+        );
+        let msg = create_test_privmsg_with_badges("!health", &["broadcaster"]);
+
+        assert_eq!(command.permission(), Permission::Broadcaster);
+        let report = command.execute(&ctx, &msg, vec![]).await?.unwrap();
+        assert!(report.contains("Channels (1): test_channel"));
+        assert!(report.contains("Send queue: 0"));
+        Ok(())
+    }
+}
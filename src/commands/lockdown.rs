@@ -0,0 +1,157 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::info;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext, Permission};
+use crate::moderation::{LiftOutcome, Lockdown};
+
+/// A command that turns the anti-raid lockdown on or off
+pub struct LockdownCommand {
+    lockdown: Arc<Lockdown>,
+}
+
+impl LockdownCommand {
+    /// Create a new lockdown command
+    ///
+    /// # Arguments
+    /// * `lockdown` - The lockdown controller
+    ///
+    /// # Returns
+    /// A new LockdownCommand instance
+    pub fn new(lockdown: Arc<Lockdown>) -> Self {
+        LockdownCommand { lockdown }
+    }
+}
+
+#[async_trait]
+impl Command for LockdownCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let response = match args.first().map(|arg| arg.to_lowercase()).as_deref() {
+            Some("on") => {
+                if self.lockdown.enable(&msg.channel_id).await? {
+                    info!("{} started a lockdown", msg.sender.name);
+                    format!("Lockdown is on: {}.", self.lockdown.describe())
+                } else {
+                    "Lockdown is already on.".to_string()
+                }
+            }
+            Some("off") => match self.lockdown.disable(&msg.channel_id).await? {
+                LiftOutcome::Restored => {
+                    info!("{} ended the lockdown", msg.sender.name);
+                    "Lockdown is off. Chat settings have been restored.".to_string()
+                }
+                LiftOutcome::ChatModesUnknown => {
+                    info!("{} ended the lockdown", msg.sender.name);
+                    "Lockdown is off. I didn't know the chat settings from before it, so please set them back by hand.".to_string()
+                }
+                LiftOutcome::NotActive => "Lockdown isn't on.".to_string(),
+            },
+            _ => "Usage: !lockdown on|off".to_string(),
+        };
+
+        Ok(Some(response))
+    }
+
+    fn help(&self) -> &str {
+        "Turns on anti-raid chat restrictions, or restores the previous settings. Usage: !lockdown on|off"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moderation::{LockdownSettings, NewAccountAction, NewAccountGuard};
+    use crate::test_helpers::{
+        create_authenticated_oauth_manager, create_test_context, create_test_privmsg_with_badges,
+    };
+    use crate::twitch::HelixChatClient;
+    use crate::users::UserManager;
+    use mockito::{Matcher, Server};
+    use tokio::sync::Mutex;
+    use twitch_irc::message::{IRCMessage, ServerMessage};
+
+    #[tokio::test]
+    async fn test_lockdown_on_and_off() -> Result<()> {
+        let ctx = create_test_context();
+        let mut server = Server::new_async().await;
+        let _bot = server
+            .mock("GET", "/users")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"999","login":"test_bot","display_name":"Test_Bot"}]}"#)
+            .create_async()
+            .await;
+        let lock = server
+            .mock("PATCH", "/chat/settings")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("broadcaster_id".into(), "456".into()),
+                Matcher::UrlEncoded("moderator_id".into(), "999".into()),
+            ]))
+            .match_body(Matcher::PartialJsonString(
+                r#"{"follower_mode":true,"follower_mode_duration":10}"#.to_string(),
+            ))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+        let restore = server
+            .mock("PATCH", "/chat/settings")
+            .match_query(Matcher::Any)
+            .match_body(Matcher::PartialJsonString(
+                r#"{"follower_mode":false}"#.to_string(),
+            ))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+        let helix = Arc::new(Mutex::new(helix));
+        let guard = Arc::new(NewAccountGuard::new(
+            helix.clone(),
+            Arc::new(UserManager::new("test.db")?),
+            chrono::Duration::days(7),
+            NewAccountAction::Flag,
+        ));
+        let lockdown = Lockdown::new(helix, guard, LockdownSettings::default());
+        let room_state = IRCMessage::parse(
+            "@emote-only=0;followers-only=-1;r9k=0;room-id=456;slow=0;subs-only=0 :tmi.twitch.tv ROOMSTATE #test_channel",
+        )?;
+        if let ServerMessage::RoomState(state) = ServerMessage::try_from(room_state)? {
+            lockdown.update_room_state(&state);
+        }
+        let command = LockdownCommand::new(Arc::new(lockdown));
+        let msg = create_test_privmsg_with_badges("!lockdown on", &["moderator"]);
+
+        assert_eq!(command.permission(), Permission::Moderator);
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["on"]).await?,
+            Some("Lockdown is on: followers-only (10 min), new account flagging.".to_string())
+        );
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["off"]).await?,
+            Some("Lockdown is off. Chat settings have been restored.".to_string())
+        );
+        assert_eq!(
+            command.execute(&ctx, &msg, vec![]).await?,
+            Some("Usage: !lockdown on|off".to_string())
+        );
+
+        lock.assert_async().await;
+        restore.assert_async().await;
+        Ok(())
+    }
+}
@@ -1,36 +1,333 @@
+mod account_age;
+mod activity;
+mod ai_persona;
+mod announce;
 mod basic;
+mod broadcast;
+mod channel_limits;
+mod channels;
+mod context;
+mod cooldown;
+mod counter;
 mod eight_ball;
+mod first_seen;
+mod follow_gate;
+mod followage;
 mod handler;
+mod health;
+mod lockdown;
+mod mod_log;
+mod moderation;
+mod points;
+mod poll;
+mod prefix;
+mod quote;
+mod raffle;
+mod rate_limit;
+mod shoutout;
+mod silence;
+mod stats;
+mod usage;
+mod verbose;
+mod watch_time;
+mod welcome;
 
 use anyhow::Result;
+use async_trait::async_trait;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::Arc;
 use twitch_irc::message::PrivmsgMessage;
 
-pub use basic::{HelpCommand, PingCommand, UptimeCommand};
-pub use eight_ball::EightBallCommand;
+use crate::features::Feature;
+
+pub use account_age::AccountAgeCommand;
+pub use activity::ActivityCommand;
+pub use ai_persona::AiPersonaCommand;
+pub use announce::AnnounceCommand;
+pub use basic::{
+    CommandsCommand, HelpCommand, PingCommand, StreamUptimeCommand, UptimeCommand, UptimeUnits,
+    VersionCommand,
+};
+pub use broadcast::BroadcastCommand;
+pub use channel_limits::CommandChannels;
+pub use channels::ChannelsCommand;
+pub use context::CommandContext;
+pub use cooldown::{CooldownSettings, CooldownTracker};
+pub use counter::{CounterAdminCommand, CounterCommand};
+pub use eight_ball::{
+    DEFAULT_EIGHT_BALL_FORMAT, DEFAULT_EIGHT_BALL_REPEAT_WINDOW, EightBallCommand,
+    EightBallOddsCommand, EightBallResponses,
+};
+pub use first_seen::FirstSeenCommand;
+pub use follow_gate::FollowGate;
+pub use followage::FollowageCommand;
 pub use handler::CommandHandler;
+pub use health::HealthCommand;
+pub use lockdown::LockdownCommand;
+pub use mod_log::ModLogCommand;
+pub use moderation::{BanCommand, TimeoutCommand, UnbanCommand, UndoCommand};
+pub use points::{GambleCommand, PointsCommand, SlotsCommand};
+pub use poll::{PollCommand, VoteCommand};
+pub use prefix::{CommandPrefixes, PrefixConflictDetector};
+pub use quote::{AddQuoteCommand, QuoteCommand};
+pub use raffle::{JoinCommand, RaffleCommand};
+pub use rate_limit::RateLimitCommand;
+pub use shoutout::ShoutoutCommand;
+pub use silence::SilenceCommand;
+pub use stats::StatsCommand;
+pub use usage::CommandUsage;
+pub use verbose::VerboseCommand;
+pub use watch_time::WatchTimeCommand;
+pub use welcome::{ForgetUserCommand, MuteWelcomeCommand, SimulateNewChatterCommand};
+
+/// Who is allowed to run a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Anyone in chat
+    Everyone,
+    /// Chatters who follow the channel, and moderators
+    ///
+    /// Badges don't show follows, so the command handler looks them up.
+    Follower,
+    /// Subscribers (including founders), and moderators
+    Subscriber,
+    /// Channel moderators and the broadcaster
+    Moderator,
+    /// Only the broadcaster
+    Broadcaster,
+}
+
+impl Permission {
+    /// Check whether the sender of a message has this permission
+    ///
+    /// # Arguments
+    /// * `msg` - The chat message whose sender should be checked
+    ///
+    /// # Returns
+    /// true if the sender is allowed, false otherwise (for `Follower`, only
+    /// moderators are allowed by their badges)
+    pub fn is_satisfied_by(&self, msg: &PrivmsgMessage) -> bool {
+        let has_badge = |name: &str| msg.badges.iter().any(|badge| badge.name == name);
+        let is_moderator = has_badge("moderator") || has_badge("broadcaster");
+
+        match self {
+            Permission::Everyone => true,
+            Permission::Follower | Permission::Moderator => is_moderator,
+            Permission::Subscriber => {
+                is_moderator || has_badge("subscriber") || has_badge("founder")
+            }
+            Permission::Broadcaster => has_badge("broadcaster"),
+        }
+    }
+
+    /// Get the reply to someone turned away by this permission
+    ///
+    /// # Returns
+    /// The reply for permissions a viewer can earn, or None to ignore the command silently
+    pub fn denial_reply(&self) -> Option<&'static str> {
+        match self {
+            Permission::Follower => Some("Follow the channel to use this command!"),
+            Permission::Subscriber => Some("This command is just for subscribers, sorry!"),
+            _ => None,
+        }
+    }
+}
+
+/// How a command receives the text after its name
+///
+/// Whatever the mode, a message with nothing but whitespace after the command
+/// name gives no arguments, just like a message with nothing after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArgMode {
+    /// One argument per word, with all whitespace dropped
+    #[default]
+    Split,
+    /// One argument per word or "double-quoted phrase", without the quotes;
+    /// `\"` is a literal quote and `\\` a literal backslash
+    Quoted,
+    /// A single argument holding everything after the space that follows
+    /// the command name, exactly as typed, including repeated, leading, and
+    /// trailing whitespace
+    Raw,
+}
+
+impl ArgMode {
+    /// Turn the text after a command name into arguments
+    ///
+    /// # Arguments
+    /// * `remainder` - The text after the space following the command name
+    ///
+    /// # Returns
+    /// The arguments to pass to the command
+    pub fn args<'a>(&self, remainder: &'a str) -> Vec<Cow<'a, str>> {
+        if remainder.trim().is_empty() {
+            return Vec::new();
+        }
+        match self {
+            ArgMode::Split => remainder.split_whitespace().map(Cow::Borrowed).collect(),
+            ArgMode::Quoted => split_quoted(remainder)
+                .into_iter()
+                .map(Cow::Owned)
+                .collect(),
+            ArgMode::Raw => vec![Cow::Borrowed(remainder)],
+        }
+    }
+}
+
+/// Split text into words, keeping "quoted phrases" together
+///
+/// A quote can open in the middle of a word, and one left open runs to the
+/// end of the text. A backslash only escapes a quote or another backslash,
+/// so something like `\o/` comes through unchanged.
+///
+/// # Arguments
+/// * `text` - The text to split
+///
+/// # Returns
+/// The words and phrases, without the quotes
+fn split_quoted(text: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some('"' | '\\')) => {
+                current.extend(chars.next());
+            }
+            '"' => {
+                if quoted && !current.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+                quoted = !quoted;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+
+    parts
+}
+
+/// A command turning down what it was asked to do, e.g. a shoutout for a
+/// channel that doesn't exist
+///
+/// Return it as the error from `execute`. The handler posts the message like
+/// any other reply, but a command run for a channel point redemption counts
+/// as failed, so the viewer gets their points back. Replies starting with
+/// "Usage:" count as rejections too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection(pub String);
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Rejection {}
 
 /// Trait for defining chat commands
+#[async_trait]
 pub trait Command: Send + Sync {
     /// Execute the command based on a chat message
     ///
     /// # Arguments
+    /// * `ctx` - Shared state such as the user manager
     /// * `msg` - The chat message that triggered the command
     /// * `args` - The arguments provided to the command
     ///
     /// # Returns
     /// A string response to send to the chat, or None if no response is needed
-    fn execute(&self, msg: &PrivmsgMessage, args: Vec<&str>) -> Result<Option<String>>;
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>>;
+
+    /// Execute the command, replying with any number of chat messages
+    ///
+    /// Commands whose response is naturally several messages override this;
+    /// the default sends `execute`'s response, if any.
+    ///
+    /// # Arguments
+    /// * `ctx` - Shared state such as the user manager
+    /// * `msg` - The chat message that triggered the command
+    /// * `args` - The arguments provided to the command
+    ///
+    /// # Returns
+    /// The messages to send to the chat, in order
+    async fn execute_lines(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Vec<String>> {
+        Ok(self.execute(ctx, msg, args).await?.into_iter().collect())
+    }
+
+    /// How the text after the command name is turned into arguments
+    ///
+    /// Commands that take phrases, like a poll's question and options, return
+    /// `ArgMode::Quoted`. Commands that need the text exactly as typed, such
+    /// as the spacing in a quote, return `ArgMode::Raw`.
+    fn arg_mode(&self) -> ArgMode {
+        ArgMode::Split
+    }
+
+    /// Whether replies should be whispered to the sender instead of posted in chat
+    ///
+    /// Commands with private replies, like a user's own balance, return true.
+    /// Whispered replies are never posted in chat, even if the whisper fails.
+    fn whisper_replies(&self) -> bool {
+        false
+    }
+
+    /// The feature the command belongs to, if it can be turned off per channel
+    ///
+    /// The command is ignored in channels where that feature is off, as well
+    /// as where commands are.
+    fn feature(&self) -> Option<Feature> {
+        None
+    }
+
+    /// The counter this command shows, if it's a counter command
+    ///
+    /// Lets `!counter remove` tell a counter's command apart from a built-in
+    /// command that has the same name.
+    fn counter_name(&self) -> Option<&str> {
+        None
+    }
 
     /// Get the help text for this command
     #[allow(dead_code)]
     fn help(&self) -> &str;
+
+    /// Get the permission required to run this command
+    fn permission(&self) -> Permission {
+        Permission::Everyone
+    }
 }
 
 /// A registry of available commands
+///
+/// Names are case-insensitive. A command has one canonical name and any
+/// number of aliases, e.g. !shoutout for !so.
 pub struct CommandRegistry {
+    /// Commands by canonical name, lowercase
     commands: HashMap<String, Arc<dyn Command>>,
+    /// Canonical names by alias, lowercase
+    aliases: HashMap<String, String>,
 }
 
 impl CommandRegistry {
@@ -38,45 +335,106 @@ impl CommandRegistry {
     pub fn new() -> Self {
         CommandRegistry {
             commands: HashMap::new(),
+            aliases: HashMap::new(),
         }
     }
 
     /// Register a command with the given name
     ///
+    /// A command registered under an existing alias replaces the alias.
+    ///
     /// # Arguments
     /// * `name` - The name of the command (without prefix)
     /// * `command` - The command implementation
     pub fn register<S: Into<String>>(&mut self, name: S, command: Arc<dyn Command>) {
-        self.commands.insert(name.into(), command);
+        let name = name.into().to_lowercase();
+        self.aliases.remove(&name);
+        self.commands.insert(name, command);
+    }
+
+    /// Make another name run an already registered command
+    ///
+    /// # Arguments
+    /// * `alias` - The extra name (without prefix)
+    /// * `name` - The name or an alias of the command it runs
+    ///
+    /// # Returns
+    /// true if the alias was added, false if there's no such command or the
+    /// alias is already a command's canonical name
+    pub fn register_alias<A: AsRef<str>, S: AsRef<str>>(&mut self, alias: A, name: S) -> bool {
+        let alias = alias.as_ref().to_lowercase();
+        let Some(canonical) = self.canonical_name(name) else {
+            return false;
+        };
+        if self.commands.contains_key(&alias) {
+            return false;
+        }
+        self.aliases.insert(alias, canonical);
+        true
+    }
+
+    /// Remove a command or an alias from the registry
+    ///
+    /// Removing a command by its canonical name removes its aliases too.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the command or alias to remove
+    ///
+    /// # Returns
+    /// true if the command was registered, false otherwise
+    pub fn unregister<S: AsRef<str>>(&mut self, name: S) -> bool {
+        let name = name.as_ref().to_lowercase();
+        if self.aliases.remove(&name).is_some() {
+            return true;
+        }
+        if self.commands.remove(&name).is_none() {
+            return false;
+        }
+        self.aliases.retain(|_, canonical| *canonical != name);
+        true
+    }
+
+    /// Get the canonical name for a command name or alias
+    ///
+    /// # Arguments
+    /// * `name` - The name or alias to look up
+    ///
+    /// # Returns
+    /// The command's canonical name, or None if no command has that name
+    pub fn canonical_name<S: AsRef<str>>(&self, name: S) -> Option<String> {
+        let name = name.as_ref().to_lowercase();
+        if self.commands.contains_key(&name) {
+            return Some(name);
+        }
+        self.aliases.get(&name).cloned()
     }
 
     /// Check if a command exists in the registry
     ///
     /// # Arguments
-    /// * `name` - The name of the command to check
+    /// * `name` - The name or alias of the command to check
     ///
     /// # Returns
     /// true if the command exists, false otherwise
-    #[allow(dead_code)]
     pub fn has_command<S: AsRef<str>>(&self, name: S) -> bool {
-        self.commands.contains_key(name.as_ref())
+        self.canonical_name(name).is_some()
     }
 
     /// Get a command from the registry
     ///
     /// # Arguments
-    /// * `name` - The name of the command to get
+    /// * `name` - The name or alias of the command to get
     ///
     /// # Returns
     /// Some(command) if the command exists, None otherwise
     pub fn get_command<S: AsRef<str>>(&self, name: S) -> Option<Arc<dyn Command>> {
-        self.commands.get(name.as_ref()).cloned()
+        self.commands.get(&self.canonical_name(name)?).cloned()
     }
 
     /// Get all command names in the registry
     ///
     /// # Returns
-    /// A vector of command names
+    /// A vector of canonical command names, without aliases
     pub fn get_command_names(&self) -> Vec<String> {
         self.commands.keys().cloned().collect()
     }
@@ -85,12 +443,19 @@ impl CommandRegistry {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_helpers::create_test_privmsg_with_badges;
     use std::sync::Arc;
 
     struct TestCommand;
 
+    #[async_trait]
     impl Command for TestCommand {
-        fn execute(&self, _msg: &PrivmsgMessage, args: Vec<&str>) -> Result<Option<String>> {
+        async fn execute(
+            &self,
+            _ctx: &CommandContext,
+            _msg: &PrivmsgMessage,
+            args: Vec<&str>,
+        ) -> Result<Option<String>> {
             Ok(Some(format!(
                 "Test command executed with {} args",
                 args.len()
@@ -118,4 +483,72 @@ mod tests {
         assert!(registry.get_command("test").is_some());
         assert!(registry.get_command("unknown").is_none());
     }
+
+    #[test]
+    fn test_command_registry_names_and_aliases() {
+        let mut registry = CommandRegistry::new();
+        registry.register("8Ball", Arc::new(TestCommand));
+        registry.register("so", Arc::new(TestCommand));
+
+        // Names are case-insensitive
+        assert!(registry.has_command("8ball"));
+        assert!(registry.get_command("8BALL").is_some());
+
+        assert!(registry.register_alias("ShoutOut", "so"));
+        assert!(registry.register_alias("sho", "shoutout"));
+        assert!(!registry.register_alias("so", "8ball"));
+        assert!(!registry.register_alias("x", "unknown"));
+        assert!(registry.has_command("shoutout"));
+        assert!(registry.get_command("SHOUTOUT").is_some());
+        assert_eq!(registry.canonical_name("sho"), Some("so".to_string()));
+
+        // Only canonical names are listed
+        let mut names = registry.get_command_names();
+        names.sort();
+        assert_eq!(names, vec!["8ball", "so"]);
+
+        // Removing an alias keeps the command; removing the command drops its aliases
+        assert!(registry.unregister("sho"));
+        assert!(registry.has_command("so"));
+        assert!(registry.unregister("so"));
+        assert!(!registry.has_command("shoutout"));
+        assert!(!registry.unregister("so"));
+    }
+
+    #[test]
+    fn test_arg_modes() {
+        assert_eq!(ArgMode::Split.args("  a   b  "), vec!["a", "b"]);
+        assert_eq!(ArgMode::Raw.args("  a   b  "), vec!["  a   b  "]);
+
+        // Whitespace alone is no arguments in either mode
+        for mode in [ArgMode::Split, ArgMode::Raw] {
+            assert!(mode.args("").is_empty());
+            assert!(mode.args("   \t ").is_empty());
+        }
+    }
+
+    #[test]
+    fn test_permission_checks_badges() {
+        let viewer = create_test_privmsg_with_badges("hi", &[]);
+        let moderator = create_test_privmsg_with_badges("hi", &["moderator"]);
+        let broadcaster = create_test_privmsg_with_badges("hi", &["broadcaster"]);
+
+        assert!(Permission::Everyone.is_satisfied_by(&viewer));
+        assert!(!Permission::Moderator.is_satisfied_by(&viewer));
+        assert!(Permission::Moderator.is_satisfied_by(&moderator));
+        assert!(Permission::Moderator.is_satisfied_by(&broadcaster));
+        assert!(!Permission::Broadcaster.is_satisfied_by(&moderator));
+        assert!(Permission::Broadcaster.is_satisfied_by(&broadcaster));
+
+        let subscriber = create_test_privmsg_with_badges("hi", &["subscriber"]);
+        let founder = create_test_privmsg_with_badges("hi", &["founder"]);
+        assert!(Permission::Subscriber.is_satisfied_by(&subscriber));
+        assert!(Permission::Subscriber.is_satisfied_by(&founder));
+        assert!(Permission::Subscriber.is_satisfied_by(&moderator));
+        assert!(!Permission::Subscriber.is_satisfied_by(&viewer));
+        // Follows are looked up by the handler; badges only let mods through
+        assert!(!Permission::Follower.is_satisfied_by(&subscriber));
+        assert!(Permission::Follower.is_satisfied_by(&moderator));
+        assert!(Permission::Moderator.denial_reply().is_none());
+    }
 }
@@ -0,0 +1,124 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext, Permission};
+use crate::moderation::ModLog;
+
+/// How many entries one reply shows
+const SHOWN_ENTRIES: usize = 3;
+
+/// A command that shows the bans, timeouts, and deleted messages seen in chat
+pub struct ModLogCommand {
+    mod_log: Arc<ModLog>,
+}
+
+impl ModLogCommand {
+    /// Create a new mod log command
+    ///
+    /// # Arguments
+    /// * `mod_log` - The shared moderation log
+    ///
+    /// # Returns
+    /// A new ModLogCommand instance
+    pub fn new(mod_log: Arc<ModLog>) -> Self {
+        ModLogCommand { mod_log }
+    }
+}
+
+#[async_trait]
+impl Command for ModLogCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let user = args.first().map(|arg| arg.trim_start_matches('@'));
+        let entries = match user {
+            Some(login) => self.mod_log.for_user(login),
+            None => self.mod_log.recent(),
+        };
+
+        // Newest first, from this channel only
+        let shown: Vec<String> = entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.channel == msg.channel_login)
+            .take(SHOWN_ENTRIES)
+            .map(|entry| entry.describe())
+            .collect();
+
+        let response = match (shown.is_empty(), user) {
+            (true, Some(login)) => format!("I haven't seen @{} moderated recently.", login),
+            (true, None) => "I haven't seen any moderation recently.".to_string(),
+            (false, _) => shown.join(" | "),
+        };
+        Ok(Some(response))
+    }
+
+    fn help(&self) -> &str {
+        "Shows the latest bans, timeouts, and deleted messages. Usage: !modlog [@user]"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moderation::ModLogEntry;
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+    use twitch_irc::message::{ClearChatMessage, IRCMessage};
+
+    fn record(log: &ModLog, src: &str) {
+        let clear = ClearChatMessage::try_from(IRCMessage::parse(src).unwrap()).unwrap();
+        log.record(ModLogEntry::from(&clear)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_mod_log_command() -> Result<()> {
+        let ctx = create_test_context();
+        let log = Arc::new(ModLog::new(None));
+        let command = ModLogCommand::new(log.clone());
+        let msg = create_test_privmsg_with_badges("!modlog", &["moderator"]);
+
+        assert_eq!(
+            command.execute(&ctx, &msg, vec![]).await?,
+            Some("I haven't seen any moderation recently.".to_string())
+        );
+
+        record(
+            &log,
+            "@room-id=456;target-user-id=300;tmi-sent-ts=1594561360331 :tmi.twitch.tv CLEARCHAT #test_channel :spammer",
+        );
+        record(
+            &log,
+            "@ban-duration=60;room-id=456;target-user-id=301;tmi-sent-ts=1594561360331 :tmi.twitch.tv CLEARCHAT #test_channel :loud",
+        );
+        record(
+            &log,
+            "@room-id=789;target-user-id=302;tmi-sent-ts=1594561360331 :tmi.twitch.tv CLEARCHAT #other_channel :elsewhere",
+        );
+
+        assert_eq!(
+            command.execute(&ctx, &msg, vec![]).await?,
+            Some(
+                "#test_channel: loud (301) was timed out for 1m | #test_channel: spammer (300) was banned"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["@Spammer"]).await?,
+            Some("#test_channel: spammer (300) was banned".to_string())
+        );
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["elsewhere"]).await?,
+            Some("I haven't seen @elsewhere moderated recently.".to_string())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,544 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext, Permission};
+use crate::duration::{format_duration, parse_duration};
+use crate::moderation::{AuditEntry, MAX_TIMEOUT_SECS, ModerationAction, ModerationAuditLog};
+use crate::twitch::HelixChatClient;
+
+/// Normalize a user argument like "@SomeUser" to a Twitch login
+fn normalize_login(arg: &str) -> String {
+    arg.trim_start_matches('@').to_lowercase()
+}
+
+/// Lift a ban or timeout, resolving the bot's moderator ID on the way
+///
+/// # Arguments
+/// * `helix` - The Helix client to use
+/// * `broadcaster_id` - ID of the channel the user is banned in
+/// * `user_id` - ID of the user to unban
+async fn unban(helix: &Mutex<HelixChatClient>, broadcaster_id: &str, user_id: &str) -> Result<()> {
+    let mut helix = helix.lock().await;
+    let moderator_id = helix.get_bot_user_id().await?;
+    helix
+        .unban_user(broadcaster_id, &moderator_id, user_id)
+        .await?;
+    Ok(())
+}
+
+/// Ban or time out a user, resolving the bot's moderator ID on the way
+///
+/// # Arguments
+/// * `helix` - The Helix client to use
+/// * `broadcaster_id` - ID of the channel to ban the user in
+/// * `user_id` - ID of the user to ban
+/// * `duration` - Timeout length in seconds, or None for a permanent ban
+/// * `reason` - The reason shown to moderators
+async fn ban(
+    helix: &Mutex<HelixChatClient>,
+    broadcaster_id: &str,
+    user_id: &str,
+    duration: Option<u64>,
+    reason: &str,
+) -> Result<()> {
+    let mut helix = helix.lock().await;
+    let moderator_id = helix.get_bot_user_id().await?;
+    helix
+        .ban_user(broadcaster_id, &moderator_id, user_id, duration, reason)
+        .await?;
+    Ok(())
+}
+
+/// Resolve a login to a user ID, with a chat-friendly error message
+///
+/// # Returns
+/// The user ID, or the message to reply with if the user can't be found
+async fn resolve_user(
+    helix: &Mutex<HelixChatClient>,
+    login: &str,
+) -> std::result::Result<String, String> {
+    helix
+        .lock()
+        .await
+        .get_broadcaster_id(login)
+        .await
+        .map_err(|e| {
+            warn!("Failed to resolve user {}: {}", login, e);
+            format!("Couldn't find a user named {}", login)
+        })
+}
+
+/// A command that permanently bans a user
+pub struct BanCommand {
+    audit_log: Arc<ModerationAuditLog>,
+}
+
+impl BanCommand {
+    /// Create a new ban command
+    ///
+    /// # Arguments
+    /// * `audit_log` - The log bans are recorded in, so !undo can reverse them
+    ///
+    /// # Returns
+    /// A new BanCommand instance
+    pub fn new(audit_log: Arc<ModerationAuditLog>) -> Self {
+        BanCommand { audit_log }
+    }
+}
+
+#[async_trait]
+impl Command for BanCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let Some(target) = args.first() else {
+            return Ok(Some("Usage: !ban <user> [reason]".to_string()));
+        };
+        let login = normalize_login(target);
+        let reason = args[1..].join(" ");
+
+        let user_id = match resolve_user(ctx.helix()?, &login).await {
+            Ok(id) => id,
+            Err(response) => return Ok(Some(response)),
+        };
+
+        match ban(ctx.helix()?, &msg.channel_id, &user_id, None, &reason).await {
+            Ok(()) => {
+                info!("{} banned {}", msg.sender.name, login);
+                self.audit_log.record(AuditEntry::new(
+                    ModerationAction::Ban,
+                    &msg.channel_id,
+                    &user_id,
+                    &login,
+                ));
+                Ok(Some(format!("{} has been banned.", login)))
+            }
+            Err(e) => {
+                warn!("Failed to ban {}: {}", login, e);
+                Ok(Some(format!("Couldn't ban {}: {}", login, e)))
+            }
+        }
+    }
+
+    fn help(&self) -> &str {
+        "Permanently ban a user. Usage: !ban <user> [reason]"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+/// A command that times out a user
+pub struct TimeoutCommand {
+    audit_log: Arc<ModerationAuditLog>,
+}
+
+impl TimeoutCommand {
+    /// Create a new timeout command
+    ///
+    /// # Arguments
+    /// * `audit_log` - The log timeouts are recorded in, so !undo can reverse them
+    ///
+    /// # Returns
+    /// A new TimeoutCommand instance
+    pub fn new(audit_log: Arc<ModerationAuditLog>) -> Self {
+        TimeoutCommand { audit_log }
+    }
+}
+
+#[async_trait]
+impl Command for TimeoutCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let (Some(target), Some(duration_arg)) = (args.first(), args.get(1)) else {
+            return Ok(Some(
+                "Usage: !timeout <user> <duration> [reason] (e.g. 60 or 10m)".to_string(),
+            ));
+        };
+        let login = normalize_login(target);
+        let reason = args[2..].join(" ");
+
+        let duration_secs = match parse_duration(duration_arg) {
+            Ok(duration) if (1..=MAX_TIMEOUT_SECS).contains(&duration.as_secs()) => {
+                duration.as_secs()
+            }
+            _ => return Ok(Some(format!("Invalid timeout duration: {}", duration_arg))),
+        };
+
+        let user_id = match resolve_user(ctx.helix()?, &login).await {
+            Ok(id) => id,
+            Err(response) => return Ok(Some(response)),
+        };
+
+        match ban(
+            ctx.helix()?,
+            &msg.channel_id,
+            &user_id,
+            Some(duration_secs),
+            &reason,
+        )
+        .await
+        {
+            Ok(()) => {
+                info!(
+                    "{} timed out {} for {}s",
+                    msg.sender.name, login, duration_secs
+                );
+                self.audit_log.record(AuditEntry::new(
+                    ModerationAction::Timeout { duration_secs },
+                    &msg.channel_id,
+                    &user_id,
+                    &login,
+                ));
+                Ok(Some(format!(
+                    "{} has been timed out for {}.",
+                    login,
+                    format_duration(std::time::Duration::from_secs(duration_secs))
+                )))
+            }
+            Err(e) => {
+                warn!("Failed to time out {}: {}", login, e);
+                Ok(Some(format!("Couldn't time out {}: {}", login, e)))
+            }
+        }
+    }
+
+    fn help(&self) -> &str {
+        "Time out a user. Usage: !timeout <user> <duration> [reason]"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+/// A command that lifts a ban or timeout from a user
+pub struct UnbanCommand;
+
+#[async_trait]
+impl Command for UnbanCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let Some(target) = args.first() else {
+            return Ok(Some("Usage: !unban <user>".to_string()));
+        };
+        let login = normalize_login(target);
+
+        let user_id = match resolve_user(ctx.helix()?, &login).await {
+            Ok(id) => id,
+            Err(response) => return Ok(Some(response)),
+        };
+
+        match unban(ctx.helix()?, &msg.channel_id, &user_id).await {
+            Ok(()) => {
+                info!("{} lifted the ban on {}", msg.sender.name, login);
+                Ok(Some(format!("{} has been unbanned.", login)))
+            }
+            Err(e) => {
+                warn!("Failed to unban {}: {}", login, e);
+                Ok(Some(format!("Couldn't unban {}: {}", login, e)))
+            }
+        }
+    }
+
+    fn help(&self) -> &str {
+        "Lift a ban or timeout from a user. Usage: !unban <user>"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+/// A command that reverses the bot's most recent moderation action
+pub struct UndoCommand {
+    audit_log: Arc<ModerationAuditLog>,
+}
+
+impl UndoCommand {
+    /// Create a new undo command
+    ///
+    /// # Arguments
+    /// * `audit_log` - The log of moderation actions taken by the bot
+    ///
+    /// # Returns
+    /// A new UndoCommand instance
+    pub fn new(audit_log: Arc<ModerationAuditLog>) -> Self {
+        UndoCommand { audit_log }
+    }
+}
+
+#[async_trait]
+impl Command for UndoCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        _args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let Some(entry) = self.audit_log.pop_last_for_channel(&msg.channel_id) else {
+            return Ok(Some("There is no moderation action to undo.".to_string()));
+        };
+
+        // Bans and timeouts are both reversed by lifting the ban
+        match unban(ctx.helix()?, &entry.broadcaster_id, &entry.user_id).await {
+            Ok(()) => {
+                info!(
+                    "{} undid {:?} on {} (taken at {})",
+                    msg.sender.name, entry.action, entry.user_login, entry.created_at
+                );
+                Ok(Some(format!(
+                    "Undid the last action: {} has been unbanned.",
+                    entry.user_login
+                )))
+            }
+            Err(e) => {
+                warn!("Failed to undo action on {}: {}", entry.user_login, e);
+                let login = entry.user_login.clone();
+                // Keep the entry so the undo can be retried
+                self.audit_log.record(entry);
+                Ok(Some(format!(
+                    "Couldn't undo the action on {}: {}",
+                    login, e
+                )))
+            }
+        }
+    }
+
+    fn help(&self) -> &str {
+        "Reverse the bot's most recent ban or timeout. Usage: !undo"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{
+        create_test_context, create_test_context_with_helix, create_test_privmsg_with_badges,
+    };
+    use mockito::{Matcher, Server, ServerGuard};
+
+    async fn mock_bot_user(server: &mut ServerGuard) -> mockito::Mock {
+        server
+            .mock("GET", "/users")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"999","login":"test_bot","display_name":"Test_Bot"}]}"#)
+            .create_async()
+            .await
+    }
+
+    #[test]
+    fn test_normalize_login() {
+        assert_eq!(normalize_login("@SomeUser"), "someuser");
+        assert_eq!(normalize_login("other"), "other");
+    }
+
+    #[tokio::test]
+    async fn test_unban_command_requires_moderator() {
+        let command = UnbanCommand;
+        assert_eq!(command.permission(), Permission::Moderator);
+    }
+
+    #[tokio::test]
+    async fn test_unban_command_sends_unban_request() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let ctx = create_test_context_with_helix(&server.url()).await;
+        let _bot = mock_bot_user(&mut server).await;
+        let _target = server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "baduser".into()))
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"300","login":"baduser","display_name":"BadUser"}]}"#)
+            .create_async()
+            .await;
+        let unban = server
+            .mock("DELETE", "/moderation/bans")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("broadcaster_id".into(), "456".into()),
+                Matcher::UrlEncoded("moderator_id".into(), "999".into()),
+                Matcher::UrlEncoded("user_id".into(), "300".into()),
+            ]))
+            .with_status(204)
+            .create_async()
+            .await;
+
+        let command = UnbanCommand;
+        let msg = create_test_privmsg_with_badges("!unban @BadUser", &["moderator"]);
+        let result = command.execute(&ctx, &msg, vec!["@BadUser"]).await?;
+
+        unban.assert_async().await;
+        assert_eq!(result, Some("baduser has been unbanned.".to_string()));
+        Ok(())
+    }
+
+    async fn mock_target_user(server: &mut ServerGuard) -> mockito::Mock {
+        server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "baduser".into()))
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"300","login":"baduser","display_name":"BadUser"}]}"#)
+            .create_async()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_timeout_command_bans_with_duration() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let ctx = create_test_context_with_helix(&server.url()).await;
+        let _bot = mock_bot_user(&mut server).await;
+        let _target = mock_target_user(&mut server).await;
+        let timeout = server
+            .mock("POST", "/moderation/bans")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("broadcaster_id".into(), "456".into()),
+                Matcher::UrlEncoded("moderator_id".into(), "999".into()),
+            ]))
+            .match_body(Matcher::Json(serde_json::json!({
+                "data": {"user_id": "300", "duration": 600, "reason": "spamming links"}
+            })))
+            .with_status(200)
+            .with_body("{}")
+            .create_async()
+            .await;
+
+        let audit_log = Arc::new(ModerationAuditLog::new());
+        let command = TimeoutCommand::new(audit_log.clone());
+        let msg = create_test_privmsg_with_badges("!timeout", &["moderator"]);
+
+        assert_eq!(command.permission(), Permission::Moderator);
+        let result = command
+            .execute(&ctx, &msg, vec!["@BadUser", "10m", "spamming", "links"])
+            .await?;
+
+        timeout.assert_async().await;
+        assert_eq!(
+            result,
+            Some("baduser has been timed out for 10m.".to_string())
+        );
+        // The timeout can be undone
+        let entry = audit_log.pop_last_for_channel("456").unwrap();
+        assert_eq!(
+            entry.action,
+            ModerationAction::Timeout { duration_secs: 600 }
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_timeout_command_rejects_invalid_duration() -> Result<()> {
+        let ctx = create_test_context();
+        let command = TimeoutCommand::new(Arc::new(ModerationAuditLog::new()));
+        let msg = create_test_privmsg_with_badges("!timeout", &["moderator"]);
+
+        let result = command
+            .execute(&ctx, &msg, vec!["baduser", "forever"])
+            .await?;
+        assert_eq!(
+            result,
+            Some("Invalid timeout duration: forever".to_string())
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ban_command_reports_missing_scope() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let ctx = create_test_context_with_helix(&server.url()).await;
+        let _bot = mock_bot_user(&mut server).await;
+        let _target = mock_target_user(&mut server).await;
+        let _ban = server
+            .mock("POST", "/moderation/bans")
+            .match_query(Matcher::Any)
+            .with_status(401)
+            .with_body(
+                r#"{"error":"Unauthorized","status":401,"message":"Missing scope: moderator:manage:banned_users"}"#,
+            )
+            .create_async()
+            .await;
+
+        let audit_log = Arc::new(ModerationAuditLog::new());
+        let command = BanCommand::new(audit_log.clone());
+        let msg = create_test_privmsg_with_badges("!ban baduser", &["moderator"]);
+
+        let result = command.execute(&ctx, &msg, vec!["baduser"]).await?.unwrap();
+        assert!(result.starts_with("Couldn't ban baduser: The bot's token is missing"));
+        assert!(result.contains("auth --force"));
+        assert_eq!(audit_log.len(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_undo_reverses_last_audit_entry() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let ctx = create_test_context_with_helix(&server.url()).await;
+        let _bot = mock_bot_user(&mut server).await;
+        let unban = server
+            .mock("DELETE", "/moderation/bans")
+            .match_query(Matcher::UrlEncoded("user_id".into(), "301".into()))
+            .with_status(204)
+            .create_async()
+            .await;
+
+        let audit_log = Arc::new(ModerationAuditLog::new());
+        audit_log.record(AuditEntry::new(
+            ModerationAction::Ban,
+            "456",
+            "300",
+            "first",
+        ));
+        audit_log.record(AuditEntry::new(
+            ModerationAction::Timeout { duration_secs: 600 },
+            "456",
+            "301",
+            "second",
+        ));
+
+        let command = UndoCommand::new(audit_log.clone());
+        let msg = create_test_privmsg_with_badges("!undo", &["moderator"]);
+        let result = command.execute(&ctx, &msg, Vec::new()).await?;
+
+        unban.assert_async().await;
+        assert_eq!(
+            result,
+            Some("Undid the last action: second has been unbanned.".to_string())
+        );
+        // Only the undone entry is removed
+        assert_eq!(audit_log.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_undo_with_empty_log() -> Result<()> {
+        let ctx = create_test_context();
+        let command = UndoCommand::new(Arc::new(ModerationAuditLog::new()));
+        let msg = create_test_privmsg_with_badges("!undo", &["moderator"]);
+
+        let result = command.execute(&ctx, &msg, Vec::new()).await?;
+        assert_eq!(
+            result,
+            Some("There is no moderation action to undo.".to_string())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,264 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use rand::{Rng, rng};
+use std::sync::Arc;
+use tracing::info;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext};
+use crate::features::Feature;
+use crate::points::{PointsService, SlotsTable};
+
+/// Read the amount a chatter wants to bet
+///
+/// # Arguments
+/// * `arg` - The amount as typed, or "all"
+/// * `balance` - The chatter's balance, which "all" bets
+///
+/// # Returns
+/// The amount, or None if it isn't a positive whole number
+fn parse_bet(arg: Option<&str>, balance: u64) -> Option<u64> {
+    let arg = arg?;
+    let amount = if arg.eq_ignore_ascii_case("all") {
+        balance
+    } else {
+        arg.parse().ok()?
+    };
+    (amount > 0).then_some(amount)
+}
+
+/// A command that shows the sender's points
+pub struct PointsCommand {
+    points: Arc<PointsService>,
+}
+
+impl PointsCommand {
+    /// Create a new points command
+    ///
+    /// # Arguments
+    /// * `points` - The shared points balances
+    ///
+    /// # Returns
+    /// A new PointsCommand instance
+    pub fn new(points: Arc<PointsService>) -> Self {
+        PointsCommand { points }
+    }
+}
+
+#[async_trait]
+impl Command for PointsCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        _args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let balance = self.points.balance(&msg.sender.id)?;
+        Ok(Some(format!("You have {} points.", balance)))
+    }
+
+    fn whisper_replies(&self) -> bool {
+        true
+    }
+
+    fn feature(&self) -> Option<Feature> {
+        Some(Feature::Points)
+    }
+
+    fn help(&self) -> &str {
+        "Whispers how many points you have. Points are earned by watching while the stream is live"
+    }
+}
+
+/// A command that bets points on the slot machine
+pub struct SlotsCommand {
+    points: Arc<PointsService>,
+    table: SlotsTable,
+}
+
+impl SlotsCommand {
+    /// Create a new slots command
+    ///
+    /// # Arguments
+    /// * `points` - The shared points balances
+    /// * `table` - The reel symbols and what they pay
+    ///
+    /// # Returns
+    /// A new SlotsCommand instance
+    pub fn new(points: Arc<PointsService>, table: SlotsTable) -> Self {
+        SlotsCommand { points, table }
+    }
+}
+
+#[async_trait]
+impl Command for SlotsCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let balance = self.points.balance(&msg.sender.id)?;
+        let Some(amount) = parse_bet(args.first().copied(), balance) else {
+            return Ok(Some(
+                "Bet a whole number of points, e.g. !slots 50".to_string(),
+            ));
+        };
+
+        let reels = self.table.spin(&mut rng());
+        let Some(outcome) = self
+            .points
+            .bet(&msg.sender.id, amount, |bet| self.table.payout(&reels, bet))?
+        else {
+            return Ok(Some(format!("You only have {} points.", balance)));
+        };
+        info!(
+            "{} bet {} on slots and got {} back",
+            msg.sender.name, amount, outcome.winnings
+        );
+
+        let result = if outcome.winnings > amount {
+            format!("you win {} points!", outcome.winnings)
+        } else if outcome.winnings == amount {
+            "you get your bet back.".to_string()
+        } else {
+            format!("no luck, you lose {} points.", amount)
+        };
+        Ok(Some(format!(
+            "@{} [ {} ] {} Balance: {}",
+            msg.sender.name,
+            reels.join(" | "),
+            result,
+            outcome.balance
+        )))
+    }
+
+    fn feature(&self) -> Option<Feature> {
+        Some(Feature::Points)
+    }
+
+    fn help(&self) -> &str {
+        "Bets points on the slot machine. Three of a kind pay out, two of a kind give your bet back. Usage: !slots <amount>|all"
+    }
+}
+
+/// A command that bets points on a coin flip, doubling or losing them
+pub struct GambleCommand {
+    points: Arc<PointsService>,
+}
+
+impl GambleCommand {
+    /// Create a new gamble command
+    ///
+    /// # Arguments
+    /// * `points` - The shared points balances
+    ///
+    /// # Returns
+    /// A new GambleCommand instance
+    pub fn new(points: Arc<PointsService>) -> Self {
+        GambleCommand { points }
+    }
+}
+
+#[async_trait]
+impl Command for GambleCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let balance = self.points.balance(&msg.sender.id)?;
+        let Some(amount) = parse_bet(args.first().copied(), balance) else {
+            return Ok(Some(
+                "Bet a whole number of points, e.g. !gamble 50".to_string(),
+            ));
+        };
+
+        // Rolls over 50 double the bet
+        let roll: u8 = rng().random_range(1..=100);
+        let Some(outcome) = self.points.bet(&msg.sender.id, amount, |bet| {
+            if roll > 50 { bet.saturating_mul(2) } else { 0 }
+        })?
+        else {
+            return Ok(Some(format!("You only have {} points.", balance)));
+        };
+        info!("{} gambled {} and rolled {}", msg.sender.name, amount, roll);
+
+        let result = if outcome.winnings > 0 { "won" } else { "lost" };
+        Ok(Some(format!(
+            "@{} rolled {} and {} {} points! Balance: {}",
+            msg.sender.name, roll, result, amount, outcome.balance
+        )))
+    }
+
+    fn feature(&self) -> Option<Feature> {
+        Some(Feature::Points)
+    }
+
+    fn help(&self) -> &str {
+        "Bets points on a roll of 1-100; over 50 doubles them. Usage: !gamble <amount>|all"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{StoreBackend, open_store};
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_betting_commands() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let ctx = create_test_context();
+        let points = Arc::new(PointsService::new(
+            open_store(StoreBackend::File, temp_dir.path(), None)?,
+            100,
+        ));
+        let slots = SlotsCommand::new(points.clone(), SlotsTable::default());
+        let gamble = GambleCommand::new(points.clone());
+        let msg = create_test_privmsg_with_badges("!gamble", &[]);
+
+        // Nothing to bet yet, and nonsense bets are refused
+        assert_eq!(
+            gamble.execute(&ctx, &msg, vec!["10"]).await?,
+            Some("You only have 0 points.".to_string())
+        );
+        for bet in ["-5", "0", "lots"] {
+            assert_eq!(
+                slots.execute(&ctx, &msg, vec![bet]).await?,
+                Some("Bet a whole number of points, e.g. !slots 50".to_string())
+            );
+        }
+
+        points.accrue(std::slice::from_ref(&msg.sender.id))?;
+        assert_eq!(
+            PointsCommand::new(points.clone())
+                .execute(&ctx, &msg, vec![])
+                .await?,
+            Some("You have 100 points.".to_string())
+        );
+
+        // Going all in either doubles the balance or empties it
+        let response = gamble.execute(&ctx, &msg, vec!["all"]).await?.unwrap();
+        let balance = points.balance(&msg.sender.id)?;
+        if response.contains(" won 100 points") {
+            assert_eq!(balance, 200);
+        } else {
+            assert!(response.contains(" lost 100 points"));
+            assert_eq!(balance, 0);
+        }
+        assert!(response.ends_with(&format!("Balance: {}", balance)));
+
+        // A spin takes the bet and pays back according to the table
+        points.accrue(std::slice::from_ref(&msg.sender.id))?;
+        let before = points.balance(&msg.sender.id)?;
+        let response = slots.execute(&ctx, &msg, vec!["50"]).await?.unwrap();
+        assert!(response.starts_with("@Test_User [ "));
+        let after = points.balance(&msg.sender.id)?;
+        assert!(response.ends_with(&format!("Balance: {}", after)));
+        assert!(after + 50 >= before);
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::info;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{ArgMode, Command, CommandContext, Permission};
+use crate::polls::{PollService, VoteOutcome};
+
+/// Usage text for !poll
+const POLL_USAGE: &str = "Usage: !poll start \"question\" option1 option2 [...] | !poll end";
+
+/// A command that starts and ends polls
+pub struct PollCommand {
+    polls: Arc<PollService>,
+}
+
+impl PollCommand {
+    /// Create a new poll command
+    ///
+    /// # Arguments
+    /// * `polls` - The shared poll service
+    ///
+    /// # Returns
+    /// A new PollCommand instance
+    pub fn new(polls: Arc<PollService>) -> Self {
+        PollCommand { polls }
+    }
+}
+
+#[async_trait]
+impl Command for PollCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let channel = &msg.channel_login;
+
+        let response = match args.first().map(|arg| arg.to_lowercase()).as_deref() {
+            Some("start") if args.len() >= 4 => {
+                let question = args[1];
+                let options: Vec<String> = args[2..].iter().map(|arg| arg.to_string()).collect();
+                let listing: Vec<String> = options
+                    .iter()
+                    .enumerate()
+                    .map(|(i, option)| format!("{}) {}", i + 1, option))
+                    .collect();
+
+                if self.polls.start(channel, question, options) {
+                    info!("{} started a poll: {}", msg.sender.name, question);
+                    format!(
+                        "📊 Poll: {} {} — vote with !vote <number>",
+                        question,
+                        listing.join(" ")
+                    )
+                } else {
+                    "A poll is already running. End it with !poll end first.".to_string()
+                }
+            }
+            Some("end") => match self.polls.end(channel) {
+                Some(results) => results.summary(),
+                None => "There's no poll running.".to_string(),
+            },
+            _ => POLL_USAGE.to_string(),
+        };
+
+        Ok(Some(response))
+    }
+
+    fn arg_mode(&self) -> ArgMode {
+        ArgMode::Quoted
+    }
+
+    fn help(&self) -> &str {
+        "Runs a chat poll. Usage: !poll start \"question\" option1 option2 [...] | !poll end"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+/// A command that votes in the running poll
+pub struct VoteCommand {
+    polls: Arc<PollService>,
+}
+
+impl VoteCommand {
+    /// Create a new vote command
+    ///
+    /// # Arguments
+    /// * `polls` - The shared poll service
+    ///
+    /// # Returns
+    /// A new VoteCommand instance
+    pub fn new(polls: Arc<PollService>) -> Self {
+        VoteCommand { polls }
+    }
+}
+
+#[async_trait]
+impl Command for VoteCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let channel = &msg.channel_login;
+        let option = args.first().and_then(|arg| arg.parse::<usize>().ok());
+
+        let outcome = match option {
+            Some(option) => self.polls.vote(channel, &msg.sender.id, option),
+            None => match self.polls.option_count(channel) {
+                Some(count) => VoteOutcome::OutOfRange(count),
+                None => VoteOutcome::NoPoll,
+            },
+        };
+
+        // Counted votes get no reply to keep chat readable
+        Ok(match outcome {
+            VoteOutcome::Counted | VoteOutcome::Changed => None,
+            VoteOutcome::NoPoll => Some("There's no poll running.".to_string()),
+            VoteOutcome::OutOfRange(count) => Some(format!(
+                "@{} vote with a number from 1 to {}.",
+                msg.sender.name, count
+            )),
+        })
+    }
+
+    fn help(&self) -> &str {
+        "Votes in the running poll. Usage: !vote <number>"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+
+    #[tokio::test]
+    async fn test_poll_round() -> Result<()> {
+        let ctx = create_test_context();
+        let polls = Arc::new(PollService::new());
+        let poll = PollCommand::new(polls.clone());
+        let vote = VoteCommand::new(polls);
+        let moderator = create_test_privmsg_with_badges("!poll", &["moderator"]);
+        let viewer = create_test_privmsg_with_badges("!vote", &[]);
+
+        assert_eq!(poll.permission(), Permission::Moderator);
+        assert_eq!(
+            vote.execute(&ctx, &viewer, vec!["1"]).await?,
+            Some("There's no poll running.".to_string())
+        );
+
+        assert_eq!(poll.arg_mode(), ArgMode::Quoted);
+        let args = vec!["start", "Best game?", "Celeste", "Hollow Knight"];
+        assert_eq!(
+            poll.execute(&ctx, &moderator, args).await?,
+            Some(
+                "📊 Poll: Best game? 1) Celeste 2) Hollow Knight — vote with !vote <number>"
+                    .to_string()
+            )
+        );
+
+        assert_eq!(vote.execute(&ctx, &viewer, vec!["2"]).await?, None);
+        assert_eq!(
+            vote.execute(&ctx, &viewer, vec!["3"]).await?,
+            Some("@Test_User vote with a number from 1 to 2.".to_string())
+        );
+        assert_eq!(
+            vote.execute(&ctx, &viewer, vec!["two"]).await?,
+            Some("@Test_User vote with a number from 1 to 2.".to_string())
+        );
+
+        assert_eq!(
+            poll.execute(&ctx, &moderator, vec!["end"]).await?,
+            Some(
+                "📊 Best game?: Celeste 0 (0%), Hollow Knight 1 (100%). Winner: Hollow Knight"
+                    .to_string()
+            )
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,306 @@
+//! Command prefixes per channel, and detection of other bots answering the same commands
+//!
+//! When another bot in a channel shares the prefix, both answer every command.
+//! Each channel can get its own prefix, and the `PrefixConflictDetector`
+//! warns the operator when it looks like another bot answered a command the
+//! bot also handled.
+
+use anyhow::{Result, bail};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::warn;
+use twitch_irc::message::PrivmsgMessage;
+
+/// How long after a command another bot's reply to it is noticed
+const REPLY_WINDOW: Duration = Duration::from_secs(5);
+
+/// How long after a command a message mentioning its sender counts as a response
+///
+/// Shorter than the reply window, since people mention each other all the time
+/// but rarely within a couple of seconds of a command.
+const MENTION_WINDOW: Duration = Duration::from_secs(2);
+
+/// Check that a prefix can be typed in chat and isn't taken by Twitch
+///
+/// # Arguments
+/// * `prefix` - The prefix to check
+///
+/// # Returns
+/// A Result with an error explaining why the prefix can't be used
+pub fn validate_prefix(prefix: &str) -> Result<()> {
+    if prefix.is_empty() {
+        bail!("Command prefixes can't be empty");
+    }
+    if prefix.chars().any(char::is_whitespace) {
+        bail!("Command prefix '{}' can't contain spaces", prefix);
+    }
+    // Twitch treats messages starting with these as its own chat commands
+    if prefix.starts_with('/') || prefix.starts_with('.') {
+        bail!(
+            "Command prefix '{}' can't start with '/' or '.', which Twitch uses for its own commands",
+            prefix
+        );
+    }
+    Ok(())
+}
+
+/// The command prefix used in each channel
+#[derive(Debug, Clone)]
+pub struct CommandPrefixes {
+    /// Prefix for channels without their own
+    default: String,
+    /// Prefixes for specific channels, keyed by lowercase channel login
+    channels: HashMap<String, String>,
+}
+
+impl CommandPrefixes {
+    /// Create prefixes for all channels, checking that each can be used
+    ///
+    /// Whitespace around each prefix is dropped, so `--prefix " >> "` gives
+    /// `>>`. Each channel only answers its own prefix, so prefixes in
+    /// different channels may extend each other, like `!` and `!!`.
+    ///
+    /// # Arguments
+    /// * `default` - Prefix for channels without their own
+    /// * `channels` - Prefixes for specific channels, keyed by channel login
+    ///
+    /// # Returns
+    /// The prefixes, or an error describing the first problem found
+    pub fn new(default: String, channels: HashMap<String, String>) -> Result<Self> {
+        let default = default.trim().to_string();
+        let channels: HashMap<String, String> = channels
+            .into_iter()
+            .map(|(channel, prefix)| {
+                (
+                    channel.trim_start_matches('#').to_lowercase(),
+                    prefix.trim().to_string(),
+                )
+            })
+            .collect();
+
+        validate_prefix(&default)?;
+        for prefix in channels.values() {
+            validate_prefix(prefix)?;
+        }
+
+        Ok(CommandPrefixes { default, channels })
+    }
+
+    /// Get the prefix used in channels without their own
+    pub fn default_prefix(&self) -> &str {
+        &self.default
+    }
+
+    /// Get the prefix used in a channel
+    ///
+    /// # Arguments
+    /// * `channel` - The channel's login
+    pub fn for_channel(&self, channel: &str) -> &str {
+        self.channels
+            .get(&channel.to_lowercase())
+            .unwrap_or(&self.default)
+    }
+}
+
+/// A command the bot answered recently
+struct AnsweredCommand {
+    channel: String,
+    message_id: String,
+    sender_login: String,
+    answered: Instant,
+}
+
+/// Notices when another bot seems to answer the same commands as this one
+///
+/// A message counts as another bot's answer when it replies to a command the
+/// bot answered within a few seconds, or mentions the command's sender within
+/// a couple of seconds. Each suspected bot is reported once per channel.
+pub struct PrefixConflictDetector {
+    /// Login of this bot, whose own messages are ignored
+    bot_login: String,
+    /// Commands answered within the last few seconds
+    recent: Mutex<VecDeque<AnsweredCommand>>,
+    /// Channel and login of each bot already reported
+    reported: Mutex<HashSet<(String, String)>>,
+}
+
+impl PrefixConflictDetector {
+    /// Create a new conflict detector
+    ///
+    /// # Arguments
+    /// * `bot_login` - Login of this bot
+    ///
+    /// # Returns
+    /// A new PrefixConflictDetector instance
+    pub fn new(bot_login: &str) -> Self {
+        PrefixConflictDetector {
+            bot_login: bot_login.to_lowercase(),
+            recent: Mutex::new(VecDeque::new()),
+            reported: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Remember a command the bot answered
+    ///
+    /// # Arguments
+    /// * `msg` - The chat message with the command
+    pub fn record_command(&self, msg: &PrivmsgMessage) {
+        let mut recent = self.recent.lock().unwrap();
+        let now = Instant::now();
+        recent.retain(|command| now.duration_since(command.answered) < REPLY_WINDOW);
+        recent.push_back(AnsweredCommand {
+            channel: msg.channel_login.clone(),
+            message_id: msg.message_id.clone(),
+            sender_login: msg.sender.login.to_lowercase(),
+            answered: now,
+        });
+    }
+
+    /// Check whether a chat message looks like another bot answering a command
+    ///
+    /// # Arguments
+    /// * `msg` - Any chat message
+    ///
+    /// # Returns
+    /// The login of the other bot, the first time it's noticed in the channel
+    pub fn observe(&self, msg: &PrivmsgMessage) -> Option<String> {
+        let login = msg.sender.login.to_lowercase();
+        if login == self.bot_login {
+            return None;
+        }
+
+        let reply_to = match msg.source.tags.0.get("reply-parent-msg-id") {
+            Some(Some(id)) => Some(id.as_str()),
+            _ => None,
+        };
+        let text = msg.message_text.to_lowercase();
+        let now = Instant::now();
+
+        let answered = self.recent.lock().unwrap().iter().any(|command| {
+            let elapsed = now.duration_since(command.answered);
+            command.channel == msg.channel_login
+                && command.sender_login != login
+                && ((elapsed < REPLY_WINDOW && reply_to == Some(command.message_id.as_str()))
+                    || (elapsed < MENTION_WINDOW
+                        && text.contains(&format!("@{}", command.sender_login))))
+        });
+        if !answered {
+            return None;
+        }
+
+        let first_time = self
+            .reported
+            .lock()
+            .unwrap()
+            .insert((msg.channel_login.clone(), login.clone()));
+        if !first_time {
+            return None;
+        }
+
+        warn!(
+            "{} seems to answer the same commands as the bot in #{}. Set a different prefix for this channel with CHANNEL_PREFIXES to avoid double responses.",
+            login, msg.channel_login
+        );
+        Some(login)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::create_test_privmsg_with_badges;
+
+    fn prefixes(default: &str, channels: &[(&str, &str)]) -> Result<CommandPrefixes> {
+        CommandPrefixes::new(
+            default.to_string(),
+            channels
+                .iter()
+                .map(|(channel, prefix)| (channel.to_string(), prefix.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_validate_prefix() {
+        assert!(validate_prefix("!").is_ok());
+        assert!(validate_prefix("?!").is_ok());
+        assert!(validate_prefix("").is_err());
+        assert!(validate_prefix("! ").is_err());
+        assert!(validate_prefix("/").is_err());
+        assert!(validate_prefix(".cmd").is_err());
+    }
+
+    #[test]
+    fn test_prefix_per_channel() -> Result<()> {
+        let prefixes = prefixes("!", &[("#Busy_Channel", "?"), ("quiet", "!")])?;
+        assert_eq!(prefixes.for_channel("busy_channel"), "?");
+        assert_eq!(prefixes.for_channel("quiet"), "!");
+        assert_eq!(prefixes.for_channel("other"), "!");
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefixes_are_normalized() -> Result<()> {
+        let normalized = prefixes(" >> ", &[("busy_channel", "?? ")])?;
+        assert_eq!(normalized.default_prefix(), ">>");
+        assert_eq!(normalized.for_channel("other"), ">>");
+        assert_eq!(normalized.for_channel("busy_channel"), "??");
+
+        // An empty prefix would make every message a command
+        assert!(CommandPrefixes::new(String::new(), HashMap::new()).is_err());
+        assert!(CommandPrefixes::new("   ".to_string(), HashMap::new()).is_err());
+        assert!(prefixes("!", &[("busy_channel", "")]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefixes_in_other_channels_may_overlap() -> Result<()> {
+        // A channel only answers its own prefix, so `!!ping` is only a command in busy_channel
+        let overlapping = prefixes("!", &[("busy_channel", "!!")])?;
+        assert_eq!(overlapping.for_channel("busy_channel"), "!!");
+        assert_eq!(overlapping.for_channel("other"), "!");
+        assert!(prefixes("?", &[("busy_channel", "!"), ("other", "~")]).is_ok());
+        assert!(prefixes("!", &[("busy_channel", "/")]).is_err());
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_detects_other_bot_replying() {
+        let detector = PrefixConflictDetector::new("Test_Bot");
+        let command = create_test_privmsg_with_badges("!ping", &[]);
+        detector.record_command(&command);
+
+        // Our own reply, and chatter unrelated to the command, are ignored
+        let mut own = create_test_privmsg_with_badges("Pong!", &[]);
+        own.sender.login = "test_bot".to_string();
+        assert_eq!(detector.observe(&own), None);
+        let mut other = create_test_privmsg_with_badges("hello", &[]);
+        other.sender.login = "other_bot".to_string();
+        assert_eq!(detector.observe(&other), None);
+
+        let mut reply = create_test_privmsg_with_badges("Pong from me too", &[]);
+        reply.sender.login = "other_bot".to_string();
+        reply.source.tags.0.insert(
+            "reply-parent-msg-id".to_string(),
+            Some(command.message_id.clone()),
+        );
+        assert_eq!(detector.observe(&reply), Some("other_bot".to_string()));
+        // Each bot is only reported once
+        assert_eq!(detector.observe(&reply), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_late_mention_is_not_a_response() {
+        let detector = PrefixConflictDetector::new("test_bot");
+        let command = create_test_privmsg_with_badges("!ping", &[]);
+        detector.record_command(&command);
+
+        let mut mention = create_test_privmsg_with_badges("@test_user pong", &[]);
+        mention.sender.login = "other_bot".to_string();
+
+        tokio::time::advance(MENTION_WINDOW).await;
+        assert_eq!(detector.observe(&mention), None);
+    }
+}
@@ -0,0 +1,278 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::info;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{ArgMode, Command, CommandContext, Permission};
+use crate::quotes::{Quote, QuoteStore};
+
+/// How !quote is used
+const USAGE: &str = "Usage: !quote [number | @author | search <keyword>]";
+
+/// Format a quote for chat
+fn format_quote(quote: &Quote) -> String {
+    let author = quote
+        .author
+        .as_ref()
+        .map(|author| format!(" - {}", author))
+        .unwrap_or_default();
+    format!(
+        "#{}: {}{} (added {})",
+        quote.id,
+        quote.text,
+        author,
+        quote.created_at.format("%Y-%m-%d")
+    )
+}
+
+/// A command that shows a stored quote
+pub struct QuoteCommand {
+    quotes: Arc<RwLock<QuoteStore>>,
+}
+
+impl QuoteCommand {
+    /// Create a new quote command
+    ///
+    /// # Arguments
+    /// * `quotes` - The shared quote database
+    ///
+    /// # Returns
+    /// A new QuoteCommand instance
+    pub fn new(quotes: Arc<RwLock<QuoteStore>>) -> Self {
+        QuoteCommand { quotes }
+    }
+}
+
+#[async_trait]
+impl Command for QuoteCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        _msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let quotes = self.quotes.read().await;
+
+        let response = match args.first() {
+            None => match quotes.random()? {
+                Some(quote) => format_quote(&quote),
+                None => "There are no quotes yet.".to_string(),
+            },
+            Some(arg) if arg.starts_with('@') && arg.len() > 1 => {
+                match quotes.random_where(|quote| quote.is_by(arg))? {
+                    Some(quote) => format_quote(&quote),
+                    None => format!("No quotes from {}.", arg.trim_start_matches('@')),
+                }
+            }
+            Some(arg) if arg.eq_ignore_ascii_case("search") => {
+                let keyword = args[1..].join(" ");
+                if keyword.is_empty() {
+                    USAGE.to_string()
+                } else {
+                    match quotes.random_where(|quote| quote.mentions(&keyword))? {
+                        Some(quote) => format_quote(&quote),
+                        None => format!("No quotes match '{}'.", keyword),
+                    }
+                }
+            }
+            Some(arg) => match arg.trim_start_matches('#').parse::<u64>() {
+                Ok(id) => match quotes.get(id)? {
+                    Some(quote) => format_quote(&quote),
+                    None => format!("There's no quote #{}.", id),
+                },
+                Err(_) => USAGE.to_string(),
+            },
+        };
+
+        Ok(Some(response))
+    }
+
+    fn arg_mode(&self) -> ArgMode {
+        ArgMode::Quoted
+    }
+
+    fn help(&self) -> &str {
+        "Shows a random quote, a specific one by number, or a random one by author or keyword. Usage: !quote [number | @author | search <keyword>]"
+    }
+}
+
+/// A command that adds a quote
+pub struct AddQuoteCommand {
+    quotes: Arc<RwLock<QuoteStore>>,
+}
+
+impl AddQuoteCommand {
+    /// Create a new add quote command
+    ///
+    /// # Arguments
+    /// * `quotes` - The shared quote database
+    ///
+    /// # Returns
+    /// A new AddQuoteCommand instance
+    pub fn new(quotes: Arc<RwLock<QuoteStore>>) -> Self {
+        AddQuoteCommand { quotes }
+    }
+}
+
+#[async_trait]
+impl Command for AddQuoteCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        // Spacing inside the quote is kept as typed
+        let text = args.join(" ");
+        let text = text.trim();
+
+        // A leading @name says who the quote is from
+        let (first, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let (author, text) = if first.starts_with('@') && first.len() > 1 {
+            (Some(first), rest.trim())
+        } else {
+            (None, text)
+        };
+        if text.is_empty() {
+            return Ok(Some("Usage: !addquote [@author] <text>".to_string()));
+        }
+
+        let quote = self
+            .quotes
+            .write()
+            .await
+            .add(text, author, &msg.sender.login)?;
+        info!("{} added quote #{}", msg.sender.name, quote.id);
+
+        Ok(Some(format!("Added quote #{}.", quote.id)))
+    }
+
+    fn help(&self) -> &str {
+        "Adds a quote, optionally saying who it's from. Usage: !addquote [@author] <text>"
+    }
+
+    fn arg_mode(&self) -> ArgMode {
+        ArgMode::Raw
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{StoreBackend, open_store};
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+    use chrono::Utc;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_add_and_get_quote() -> Result<()> {
+        let ctx = create_test_context();
+        let temp_dir = tempdir()?;
+        let store = open_store(StoreBackend::File, temp_dir.path(), None)?;
+        let quotes = Arc::new(RwLock::new(QuoteStore::new(store)));
+        let quote = QuoteCommand::new(quotes.clone());
+        let add_quote = AddQuoteCommand::new(quotes);
+
+        let msg = create_test_privmsg_with_badges("!quote", &[]);
+        assert_eq!(
+            quote.execute(&ctx, &msg, vec![]).await?,
+            Some("There are no quotes yet.".to_string())
+        );
+
+        let msg = create_test_privmsg_with_badges("!addquote that was close", &["moderator"]);
+        assert_eq!(add_quote.permission(), Permission::Moderator);
+        assert_eq!(
+            add_quote
+                .execute(&ctx, &msg, vec!["that", "was", "close"])
+                .await?,
+            Some("Added quote #1.".to_string())
+        );
+
+        let today = Utc::now().format("%Y-%m-%d");
+        let msg = create_test_privmsg_with_badges("!quote 1", &[]);
+        assert_eq!(
+            quote.execute(&ctx, &msg, vec!["1"]).await?,
+            Some(format!("#1: that was close (added {})", today))
+        );
+        assert_eq!(
+            quote.execute(&ctx, &msg, vec![]).await?,
+            Some(format!("#1: that was close (added {})", today))
+        );
+        assert_eq!(
+            quote.execute(&ctx, &msg, vec!["4"]).await?,
+            Some("There's no quote #4.".to_string())
+        );
+
+        // Quotes get the raw text, keeping its inner spacing
+        assert_eq!(add_quote.arg_mode(), ArgMode::Raw);
+        add_quote.execute(&ctx, &msg, vec![" so   close "]).await?;
+        assert_eq!(
+            quote.execute(&ctx, &msg, vec!["2"]).await?,
+            Some(format!("#2: so   close (added {})", today))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_quote_by_author_and_keyword() -> Result<()> {
+        let ctx = create_test_context();
+        let temp_dir = tempdir()?;
+        let store = open_store(StoreBackend::File, temp_dir.path(), None)?;
+        let quotes = Arc::new(RwLock::new(QuoteStore::new(store)));
+        let quote = QuoteCommand::new(quotes.clone());
+        let add_quote = AddQuoteCommand::new(quotes);
+
+        let msg = create_test_privmsg_with_badges("!addquote", &["moderator"]);
+        add_quote
+            .execute(&ctx, &msg, vec!["@StreamerOne I never miss"])
+            .await?;
+        add_quote
+            .execute(&ctx, &msg, vec!["nobody saw that"])
+            .await?;
+        assert_eq!(
+            add_quote.execute(&ctx, &msg, vec!["@guest"]).await?,
+            Some("Usage: !addquote [@author] <text>".to_string())
+        );
+
+        let today = Utc::now().format("%Y-%m-%d");
+        let msg = create_test_privmsg_with_badges("!quote", &[]);
+        assert_eq!(
+            quote.execute(&ctx, &msg, vec!["@streamerone"]).await?,
+            Some(format!("#1: I never miss - streamerone (added {})", today))
+        );
+        assert_eq!(
+            quote.execute(&ctx, &msg, vec!["search", "SAW"]).await?,
+            Some(format!("#2: nobody saw that (added {})", today))
+        );
+
+        assert_eq!(
+            quote.execute(&ctx, &msg, vec!["@guest"]).await?,
+            Some("No quotes from guest.".to_string())
+        );
+        assert_eq!(
+            quote
+                .execute(&ctx, &msg, vec!["search", "speed", "run"])
+                .await?,
+            Some("No quotes match 'speed run'.".to_string())
+        );
+        // A quoted keyword arrives as one argument
+        assert_eq!(quote.arg_mode(), ArgMode::Quoted);
+        assert_eq!(
+            quote
+                .execute(&ctx, &msg, vec!["search", "never miss"])
+                .await?,
+            Some(format!("#1: I never miss - streamerone (added {})", today))
+        );
+        assert_eq!(
+            quote.execute(&ctx, &msg, vec!["search"]).await?,
+            Some(USAGE.to_string())
+        );
+        Ok(())
+    }
+}
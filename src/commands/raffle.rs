@@ -0,0 +1,161 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use rand::rng;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext, Permission};
+use crate::raffle::{EntryRejection, Raffle};
+
+/// A command that runs a giveaway raffle
+pub struct RaffleCommand {
+    raffle: Arc<Mutex<Raffle>>,
+}
+
+impl RaffleCommand {
+    /// Create a new raffle command
+    ///
+    /// # Arguments
+    /// * `raffle` - The shared raffle
+    ///
+    /// # Returns
+    /// A new RaffleCommand instance
+    pub fn new(raffle: Arc<Mutex<Raffle>>) -> Self {
+        RaffleCommand { raffle }
+    }
+}
+
+#[async_trait]
+impl Command for RaffleCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let mut raffle = self.raffle.lock().unwrap();
+
+        let response = match args.first().map(|arg| arg.to_lowercase()).as_deref() {
+            Some("open") => {
+                raffle.open();
+                info!("{} opened a raffle", msg.sender.name);
+                "🎁 The raffle is open! Type !join to enter.".to_string()
+            }
+            Some("close") => {
+                let entrants = raffle.close();
+                format!("The raffle is closed. Entrants: {}", entrants)
+            }
+            Some("draw") => match raffle.draw(&mut rng()) {
+                Some(winner) => {
+                    info!("{} won the raffle", winner.username);
+                    format!("🎉 The winner is @{}!", winner.username)
+                }
+                None => "There's no one left to draw.".to_string(),
+            },
+            Some("reset") => {
+                raffle.reset();
+                "The raffle has been reset and past winners can enter again.".to_string()
+            }
+            _ => "Usage: !raffle open|close|draw|reset".to_string(),
+        };
+
+        Ok(Some(response))
+    }
+
+    fn help(&self) -> &str {
+        "Runs a giveaway raffle. Usage: !raffle open|close|draw|reset"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+/// A command that enters the sender into the raffle
+pub struct JoinCommand {
+    raffle: Arc<Mutex<Raffle>>,
+}
+
+impl JoinCommand {
+    /// Create a new join command
+    ///
+    /// # Arguments
+    /// * `raffle` - The shared raffle
+    ///
+    /// # Returns
+    /// A new JoinCommand instance
+    pub fn new(raffle: Arc<Mutex<Raffle>>) -> Self {
+        JoinCommand { raffle }
+    }
+}
+
+#[async_trait]
+impl Command for JoinCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        _args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let name = &msg.sender.name;
+
+        let response = match self.raffle.lock().unwrap().enter(msg) {
+            Ok(1) => format!("@{} joined the raffle.", name),
+            Ok(weight) => format!("@{} joined the raffle with {} entries.", name, weight),
+            Err(EntryRejection::Closed) => "There's no raffle open right now.".to_string(),
+            Err(EntryRejection::AlreadyEntered) => format!("@{} is already in the raffle.", name),
+            Err(EntryRejection::AlreadyWon) => {
+                format!("@{} already won a raffle this stream.", name)
+            }
+        };
+
+        Ok(Some(response))
+    }
+
+    fn help(&self) -> &str {
+        "Enters you into the open raffle. Usage: !join"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raffle::RaffleWeights;
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+
+    #[tokio::test]
+    async fn test_raffle_round() -> Result<()> {
+        let ctx = create_test_context();
+        let raffle = Arc::new(Mutex::new(Raffle::new(RaffleWeights::default())));
+        let command = RaffleCommand::new(raffle.clone());
+        let join = JoinCommand::new(raffle);
+        let mod_msg = create_test_privmsg_with_badges("!raffle", &["moderator"]);
+        let sub_msg = create_test_privmsg_with_badges("!join", &["subscriber"]);
+
+        assert_eq!(command.permission(), Permission::Moderator);
+        assert_eq!(
+            join.execute(&ctx, &sub_msg, vec![]).await?,
+            Some("There's no raffle open right now.".to_string())
+        );
+
+        command.execute(&ctx, &mod_msg, vec!["open"]).await?;
+        assert_eq!(
+            join.execute(&ctx, &sub_msg, vec![]).await?,
+            Some("@Test_User joined the raffle with 2 entries.".to_string())
+        );
+        assert_eq!(
+            command.execute(&ctx, &mod_msg, vec!["close"]).await?,
+            Some("The raffle is closed. Entrants: 1".to_string())
+        );
+        assert_eq!(
+            command.execute(&ctx, &mod_msg, vec!["draw"]).await?,
+            Some("🎉 The winner is @Test_User!".to_string())
+        );
+        assert_eq!(
+            command.execute(&ctx, &mod_msg, vec!["draw"]).await?,
+            Some("There's no one left to draw.".to_string())
+        );
+        Ok(())
+    }
+}
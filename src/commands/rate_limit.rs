@@ -0,0 +1,128 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::sync::Arc;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext, Permission};
+use crate::twitch::{HelixRateLimit, OutboundLimiter};
+
+/// A command that reports how close the bot is to its rate limits
+pub struct RateLimitCommand {
+    outbound: Arc<OutboundLimiter>,
+}
+
+impl RateLimitCommand {
+    /// Create a new rate limit command
+    ///
+    /// # Arguments
+    /// * `outbound` - The limiter pacing outbound chat messages
+    ///
+    /// # Returns
+    /// A new RateLimitCommand instance
+    pub fn new(outbound: Arc<OutboundLimiter>) -> Self {
+        RateLimitCommand { outbound }
+    }
+}
+
+/// Describe a Helix rate-limit state for chat, labelled e.g. "Helix" or "Helix /users"
+fn describe_helix(label: &str, rate_limit: Option<HelixRateLimit>) -> String {
+    match rate_limit {
+        Some(rate_limit) => {
+            let resets_in = (rate_limit.reset - Utc::now()).num_seconds().max(0);
+            format!(
+                "{}: {}/{} points remaining, resets in {}s",
+                label, rate_limit.remaining, rate_limit.limit, resets_in
+            )
+        }
+        None => format!("{}: no requests made yet", label),
+    }
+}
+
+#[async_trait]
+impl Command for RateLimitCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        _msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let chat = format!(
+            "Chat: {}/{} messages available",
+            self.outbound.available(),
+            self.outbound.capacity()
+        );
+        let helix = ctx.helix()?.lock().await;
+        // An endpoint like "/users" reports what Twitch last said about it
+        let helix = match args.first() {
+            Some(endpoint) => {
+                let path = format!("/{}", endpoint.trim_start_matches('/'));
+                describe_helix(&format!("Helix {}", path), helix.rate_limit_for(&path))
+            }
+            None => describe_helix("Helix", helix.rate_limit()),
+        };
+
+        Ok(Some(format!("{} | {}", chat, helix)))
+    }
+
+    fn help(&self) -> &str {
+        "Shows the bot's current rate-limit status, optionally for one Helix endpoint. Usage: !ratelimit [endpoint]"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context_with_helix, create_test_privmsg_with_badges};
+    use mockito::{Matcher, Server};
+
+    #[tokio::test]
+    async fn test_ratelimit_reports_captured_values() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let reset = (Utc::now() + chrono::Duration::seconds(30)).timestamp();
+        let _mock = server
+            .mock("GET", "/users")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_header("Ratelimit-Limit", "800")
+            .with_header("Ratelimit-Remaining", "795")
+            .with_header("Ratelimit-Reset", &reset.to_string())
+            .with_body(r#"{"data":[{"id":"999","login":"test_bot","display_name":"Test_Bot"}]}"#)
+            .create_async()
+            .await;
+
+        let ctx = create_test_context_with_helix(&server.url()).await;
+        let outbound = Arc::new(OutboundLimiter::new(20, 100));
+        let command = RateLimitCommand::new(outbound.clone());
+        let msg = create_test_privmsg_with_badges("!ratelimit", &["moderator"]);
+
+        let result = command.execute(&ctx, &msg, Vec::new()).await?.unwrap();
+        assert_eq!(
+            result,
+            "Chat: 20/20 messages available | Helix: no requests made yet"
+        );
+
+        ctx.helix()?.lock().await.get_bot_user_id().await?;
+        outbound.acquire().await;
+        outbound.acquire().await;
+
+        let result = command.execute(&ctx, &msg, Vec::new()).await?.unwrap();
+        assert!(result.starts_with(
+            "Chat: 18/20 messages available | Helix: 795/800 points remaining, resets in "
+        ));
+
+        // One endpoint's state, with or without the leading slash
+        let result = command.execute(&ctx, &msg, vec!["users"]).await?.unwrap();
+        assert!(result.contains("| Helix /users: 795/800 points remaining"));
+        let result = command
+            .execute(&ctx, &msg, vec!["/chat/messages"])
+            .await?
+            .unwrap();
+        assert!(result.ends_with("| Helix /chat/messages: no requests made yet"));
+        Ok(())
+    }
+}
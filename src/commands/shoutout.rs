@@ -0,0 +1,159 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use tracing::{info, warn};
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext, Permission, Rejection};
+use crate::users::format_shoutout;
+
+/// A command that promotes another streamer and gives them a native Twitch shoutout
+pub struct ShoutoutCommand {
+    /// Promo line template (use {login} as placeholder)
+    message: String,
+}
+
+impl ShoutoutCommand {
+    /// Create a new shoutout command
+    ///
+    /// # Arguments
+    /// * `message` - The promo line template (use {login} as placeholder)
+    ///
+    /// # Returns
+    /// A new ShoutoutCommand instance
+    pub fn new(message: String) -> Self {
+        ShoutoutCommand { message }
+    }
+}
+
+#[async_trait]
+impl Command for ShoutoutCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let Some(target) = args.first() else {
+            return Ok(Some("Usage: !so @streamer".to_string()));
+        };
+        let login = target.trim_start_matches('@').to_lowercase();
+
+        let mut helix = ctx.helix()?.lock().await;
+        let target_id = match helix.get_broadcaster_id(&login).await {
+            Ok(id) => id,
+            Err(_) => {
+                return Err(Rejection(format!("Couldn't find a channel named {}", login)).into());
+            }
+        };
+
+        let promo = format_shoutout(&self.message, &login);
+
+        // The promo line is posted even if the native shoutout can't be sent
+        match helix.send_shoutout(&msg.channel_id, &target_id).await {
+            Ok(true) => {
+                info!("{} shouted out {}", msg.sender.name, login);
+                Ok(Some(promo))
+            }
+            Ok(false) => Ok(Some(format!("{} (Twitch shoutout is on cooldown)", promo))),
+            Err(e) => {
+                warn!("Failed to send native shoutout to {}: {}", login, e);
+                Ok(Some(promo))
+            }
+        }
+    }
+
+    fn help(&self) -> &str {
+        "Promote another streamer and give them a Twitch shoutout. Usage: !so @streamer"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context_with_helix, create_test_privmsg_with_badges};
+    use crate::users::DEFAULT_SHOUTOUT_MESSAGE;
+    use mockito::{Matcher, Mock, Server, ServerGuard};
+
+    /// Mock the user lookups for the bot and the shoutout target
+    async fn mock_users(server: &mut ServerGuard) -> (Mock, Mock) {
+        let bot = server
+            .mock("GET", "/users")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"999","login":"test_bot","display_name":"Test_Bot"}]}"#)
+            .create_async()
+            .await;
+        let target = server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "friend".into()))
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"300","login":"friend","display_name":"Friend"}]}"#)
+            .create_async()
+            .await;
+        (bot, target)
+    }
+
+    fn create_command() -> ShoutoutCommand {
+        ShoutoutCommand::new(DEFAULT_SHOUTOUT_MESSAGE.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_shoutout_sends_native_shoutout() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let ctx = create_test_context_with_helix(&server.url()).await;
+        let _users = mock_users(&mut server).await;
+        let shoutout = server
+            .mock("POST", "/chat/shoutouts")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("from_broadcaster_id".into(), "456".into()),
+                Matcher::UrlEncoded("to_broadcaster_id".into(), "300".into()),
+                Matcher::UrlEncoded("moderator_id".into(), "999".into()),
+            ]))
+            .with_status(204)
+            .create_async()
+            .await;
+
+        let command = create_command();
+        let msg = create_test_privmsg_with_badges("!so @Friend", &["moderator"]);
+
+        assert_eq!(command.permission(), Permission::Moderator);
+        let result = command.execute(&ctx, &msg, vec!["@Friend"]).await?;
+        assert_eq!(
+            result,
+            Some("Go check out @friend at https://twitch.tv/friend!".to_string())
+        );
+        shoutout.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shoutout_on_cooldown_still_posts_promo() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let ctx = create_test_context_with_helix(&server.url()).await;
+        let _users = mock_users(&mut server).await;
+        let _shoutout = server
+            .mock("POST", "/chat/shoutouts")
+            .match_query(Matcher::Any)
+            .with_status(429)
+            .with_body(r#"{"error":"Too Many Requests","status":429,"message":"cooldown"}"#)
+            .create_async()
+            .await;
+
+        let command = create_command();
+        let msg = create_test_privmsg_with_badges("!so friend", &["moderator"]);
+
+        let result = command.execute(&ctx, &msg, vec!["friend"]).await?;
+        assert_eq!(
+            result,
+            Some(
+                "Go check out @friend at https://twitch.tv/friend! (Twitch shoutout is on cooldown)"
+                    .to_string()
+            )
+        );
+        Ok(())
+    }
+}
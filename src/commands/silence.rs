@@ -0,0 +1,84 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext};
+use crate::duration::format_duration;
+use crate::silence::SilenceTracker;
+
+/// A command that reports how long chat was quiet before it
+pub struct SilenceCommand {
+    silence: Arc<SilenceTracker>,
+}
+
+impl SilenceCommand {
+    /// Create a new silence command
+    ///
+    /// # Arguments
+    /// * `silence` - The tracker of chat silence
+    ///
+    /// # Returns
+    /// A new SilenceCommand instance
+    pub fn new(silence: Arc<SilenceTracker>) -> Self {
+        SilenceCommand { silence }
+    }
+}
+
+#[async_trait]
+impl Command for SilenceCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        _args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        // The !silence message itself is the latest, so report the gap before it
+        let response = match self.silence.previous_silence() {
+            Some(gap) => format!(
+                "{} broke {} of silence.",
+                msg.sender.name,
+                format_duration(gap)
+            ),
+            None => "Nobody else has chatted yet.".to_string(),
+        };
+        Ok(Some(response))
+    }
+
+    fn help(&self) -> &str {
+        "Shows how long chat was quiet before your message"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+    use crate::users::MockTwitchClient;
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_silence_command() -> Result<()> {
+        let ctx = create_test_context();
+        let silence = Arc::new(SilenceTracker::new(
+            Arc::new(MockTwitchClient::new()),
+            "test_channel",
+        ));
+        let command = SilenceCommand::new(silence.clone());
+        let msg = create_test_privmsg_with_badges("!silence", &[]);
+
+        silence.record_chat_message("test_channel");
+        assert_eq!(
+            command.execute(&ctx, &msg, vec![]).await?,
+            Some("Nobody else has chatted yet.".to_string())
+        );
+
+        tokio::time::advance(Duration::from_secs(125)).await;
+        silence.record_chat_message("test_channel");
+        assert_eq!(
+            command.execute(&ctx, &msg, vec![]).await?,
+            Some("Test_User broke 2m 5s of silence.".to_string())
+        );
+        Ok(())
+    }
+}
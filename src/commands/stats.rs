@@ -0,0 +1,111 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext, CommandUsage, Permission};
+
+/// How many commands !stats lists by default
+const DEFAULT_TOP: usize = 5;
+
+/// The most commands !stats lists
+const MAX_TOP: usize = 15;
+
+/// A command that reports the most used commands
+pub struct StatsCommand {
+    usage: Arc<CommandUsage>,
+}
+
+impl StatsCommand {
+    /// Create a new stats command
+    ///
+    /// # Arguments
+    /// * `usage` - The command usage counts to report
+    ///
+    /// # Returns
+    /// A new StatsCommand instance
+    pub fn new(usage: Arc<CommandUsage>) -> Self {
+        StatsCommand { usage }
+    }
+}
+
+#[async_trait]
+impl Command for StatsCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        _msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let limit = match args.first() {
+            Some(arg) => match arg.parse::<usize>() {
+                Ok(limit) if limit > 0 => limit.min(MAX_TOP),
+                _ => return Ok(Some("Usage: !stats [count]".to_string())),
+            },
+            None => DEFAULT_TOP,
+        };
+
+        let top = self.usage.top(limit);
+        if top.is_empty() {
+            return Ok(Some("No commands have been used yet.".to_string()));
+        }
+
+        let commands: Vec<String> = top
+            .iter()
+            .map(|(name, uses)| format!("{} ({})", name, uses))
+            .collect();
+        Ok(Some(format!("Top commands: {}", commands.join(", "))))
+    }
+
+    fn help(&self) -> &str {
+        "Shows the most used commands. Usage: !stats [count]"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{StoreBackend, open_store};
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_stats_command() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let ctx = create_test_context();
+        let usage = Arc::new(CommandUsage::load(open_store(
+            StoreBackend::File,
+            temp_dir.path(),
+            None,
+        )?)?);
+        let command = StatsCommand::new(usage.clone());
+        let msg = create_test_privmsg_with_badges("!stats", &["moderator"]);
+
+        assert_eq!(command.permission(), Permission::Moderator);
+        assert_eq!(
+            command.execute(&ctx, &msg, vec![]).await?,
+            Some("No commands have been used yet.".to_string())
+        );
+
+        for name in ["ping", "quote", "ping", "8ball", "ping", "quote"] {
+            usage.record(name)?;
+        }
+        assert_eq!(
+            command.execute(&ctx, &msg, vec![]).await?,
+            Some("Top commands: ping (3), quote (2), 8ball (1)".to_string())
+        );
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["1"]).await?,
+            Some("Top commands: ping (3)".to_string())
+        );
+        assert_eq!(
+            command.execute(&ctx, &msg, vec!["lots"]).await?,
+            Some("Usage: !stats [count]".to_string())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+//! How often each command is used
+//!
+//! Each use is written through to the shared store, which batches the writes
+//! along with the rest of its changes when saves are deferred.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::store::Store;
+
+/// Store namespace usage counts are kept under, keyed by command name
+const NAMESPACE: &str = "command_usage";
+
+/// Tallies how often each command is used
+pub struct CommandUsage {
+    /// Where the counts are persisted
+    store: Arc<dyn Store>,
+    /// Uses by command name
+    uses: RwLock<HashMap<String, u64>>,
+}
+
+impl CommandUsage {
+    /// Load the counts kept in a store
+    ///
+    /// # Arguments
+    /// * `store` - Where the counts are persisted
+    ///
+    /// # Returns
+    /// A new CommandUsage instance
+    pub fn load(store: Arc<dyn Store>) -> Result<Self> {
+        let uses = store.list_as(NAMESPACE)?.into_iter().collect();
+        Ok(CommandUsage {
+            store,
+            uses: RwLock::new(uses),
+        })
+    }
+
+    /// Count one use of a command
+    ///
+    /// # Arguments
+    /// * `command` - The command's name
+    ///
+    /// # Returns
+    /// An error if the new count couldn't be written to the store
+    pub fn record(&self, command: &str) -> Result<()> {
+        let mut uses = self.uses.write().unwrap();
+        let count = uses.entry(command.to_string()).or_default();
+        *count += 1;
+        self.store.set_as(NAMESPACE, command, count)
+    }
+
+    /// Get the most used commands
+    ///
+    /// # Arguments
+    /// * `limit` - How many commands to return at most
+    ///
+    /// # Returns
+    /// Command names with their use counts, most used first
+    pub fn top(&self, limit: usize) -> Vec<(String, u64)> {
+        let uses = self.uses.read().unwrap();
+        let mut top: Vec<(String, u64)> = uses
+            .iter()
+            .map(|(name, uses)| (name.clone(), *uses))
+            .collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(limit);
+        top
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{StoreBackend, open_store};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_usage_counts_survive_reload() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let store = open_store(StoreBackend::File, temp_dir.path(), None)?;
+        let usage = CommandUsage::load(store.clone())?;
+
+        for command in ["ping", "8ball", "ping", "quote", "ping", "8ball"] {
+            usage.record(command)?;
+        }
+        assert_eq!(
+            usage.top(2),
+            vec![("ping".to_string(), 3), ("8ball".to_string(), 2)]
+        );
+
+        // Each use is written through to the store
+        assert_eq!(CommandUsage::load(store.clone())?.top(10), usage.top(10));
+
+        let reloaded = CommandUsage::load(store)?;
+        assert_eq!(reloaded.top(10), usage.top(10));
+        reloaded.record("quote")?;
+        assert_eq!(
+            reloaded.top(10),
+            vec![
+                ("ping".to_string(), 3),
+                ("8ball".to_string(), 2),
+                ("quote".to_string(), 2)
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::info;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext, Permission};
+use crate::logging::LogControl;
+
+/// A command that turns verbose logging on or off without restarting
+///
+/// `!verbose on <target>` enables debug logging for a single target only.
+pub struct VerboseCommand {
+    log_control: Arc<LogControl>,
+}
+
+impl VerboseCommand {
+    /// Create a new verbose command
+    ///
+    /// # Arguments
+    /// * `log_control` - The control for the bot's log filter
+    ///
+    /// # Returns
+    /// A new VerboseCommand instance
+    pub fn new(log_control: Arc<LogControl>) -> Self {
+        VerboseCommand { log_control }
+    }
+}
+
+#[async_trait]
+impl Command for VerboseCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let verbose = match args.first().map(|arg| arg.to_lowercase()).as_deref() {
+            Some("on") => true,
+            Some("off") => false,
+            _ => {
+                return Ok(Some(format!(
+                    "The log filter is {}. Usage: !verbose on|off [target]",
+                    self.log_control.current_filter()
+                )));
+            }
+        };
+
+        let target = args.get(1).copied();
+        if let Err(e) = self.log_control.set_verbose(verbose, target) {
+            return Ok(Some(format!("Couldn't change the log filter: {}", e)));
+        }
+        info!(
+            "{} set the log filter to {}",
+            msg.sender.name,
+            self.log_control.current_filter()
+        );
+
+        let state = if verbose { "on" } else { "off" };
+        Ok(Some(match (verbose, target) {
+            (true, Some(target)) => format!("Verbose logging is now on for {}.", target),
+            _ => format!("Verbose logging is now {}.", state),
+        }))
+    }
+
+    fn help(&self) -> &str {
+        "Turn verbose logging on or off. Usage: !verbose on|off [target]"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Broadcaster
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+
+    #[tokio::test]
+    async fn test_verbose_command_toggles_level() -> Result<()> {
+        let ctx = create_test_context();
+        let (_filter, control) = LogControl::new("info")?;
+        let control = Arc::new(control);
+        let command = VerboseCommand::new(control.clone());
+        let msg = create_test_privmsg_with_badges("!verbose on", &["broadcaster"]);
+
+        assert_eq!(command.permission(), Permission::Broadcaster);
+
+        let result = command.execute(&ctx, &msg, vec!["on"]).await?;
+        assert_eq!(result, Some("Verbose logging is now on.".to_string()));
+        assert_eq!(control.current_filter(), "debug");
+
+        let result = command.execute(&ctx, &msg, vec!["OFF"]).await?;
+        assert_eq!(result, Some("Verbose logging is now off.".to_string()));
+        assert_eq!(control.current_filter(), "info");
+
+        let msg = create_test_privmsg_with_badges(
+            "!verbose on som_chatbot::twitch::helix",
+            &["broadcaster"],
+        );
+        let result = command
+            .execute(&ctx, &msg, vec!["on", "som_chatbot::twitch::helix"])
+            .await?;
+        assert_eq!(
+            result,
+            Some("Verbose logging is now on for som_chatbot::twitch::helix.".to_string())
+        );
+        assert_eq!(
+            control.current_filter(),
+            "som_chatbot::twitch::helix=debug,info"
+        );
+        Ok(())
+    }
+}
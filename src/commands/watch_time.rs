@@ -0,0 +1,67 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::time::Duration;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext};
+use crate::duration::format_duration;
+
+/// A command that shows how long a viewer has watched the stream
+pub struct WatchTimeCommand;
+
+#[async_trait]
+impl Command for WatchTimeCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        // Default to the sender when no user is given
+        let login = args
+            .first()
+            .map(|arg| arg.trim_start_matches('@').to_lowercase())
+            .unwrap_or_else(|| msg.sender.login.clone());
+
+        let response = match ctx.user_manager.watch_minutes_by_login(&login)? {
+            Some(minutes) if minutes > 0 => format!(
+                "@{} has watched {} for {}.",
+                login,
+                msg.channel_login,
+                format_duration(Duration::from_secs(minutes * 60))
+            ),
+            _ => format!("I haven't seen @{} watch yet.", login),
+        };
+        Ok(Some(response))
+    }
+
+    fn help(&self) -> &str {
+        "Shows how long someone has watched the stream. Usage: !watchtime [@user]"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{create_test_context, create_test_privmsg_with_badges};
+
+    #[tokio::test]
+    async fn test_watch_time() -> Result<()> {
+        let ctx = create_test_context();
+        ctx.user_manager
+            .add_watch_minutes(&[("300".to_string(), "regular".to_string())], 125)?;
+        let msg = create_test_privmsg_with_badges("!watchtime @Regular", &[]);
+
+        assert_eq!(
+            WatchTimeCommand
+                .execute(&ctx, &msg, vec!["@Regular"])
+                .await?,
+            Some("@regular has watched test_channel for 2h 5m.".to_string())
+        );
+        assert_eq!(
+            WatchTimeCommand.execute(&ctx, &msg, vec![]).await?,
+            Some("I haven't seen @test_user watch yet.".to_string())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,370 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::info;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::commands::{Command, CommandContext, Permission};
+use crate::duration::{format_duration, parse_duration};
+use crate::users::WelcomeService;
+
+/// A command that temporarily silences welcome messages
+pub struct MuteWelcomeCommand {
+    welcome_service: Arc<WelcomeService>,
+}
+
+impl MuteWelcomeCommand {
+    /// Create a new mute welcome command
+    ///
+    /// # Arguments
+    /// * `welcome_service` - The welcome service to mute
+    ///
+    /// # Returns
+    /// A new MuteWelcomeCommand instance
+    pub fn new(welcome_service: Arc<WelcomeService>) -> Self {
+        MuteWelcomeCommand { welcome_service }
+    }
+}
+
+#[async_trait]
+impl Command for MuteWelcomeCommand {
+    async fn execute(
+        &self,
+        _ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let Some(arg) = args.first() else {
+            return Ok(Some(
+                "Usage: !mutewelcome <duration> (e.g. 10m) or !mutewelcome off".to_string(),
+            ));
+        };
+
+        if arg.eq_ignore_ascii_case("off") {
+            let response = if self.welcome_service.unmute() {
+                "Welcome messages have resumed."
+            } else {
+                "Welcome messages aren't muted."
+            };
+            return Ok(Some(response.to_string()));
+        }
+
+        let duration = match parse_duration(arg) {
+            Ok(duration) if !duration.is_zero() => duration,
+            _ => return Ok(Some(format!("Invalid duration: {}", arg))),
+        };
+
+        self.welcome_service.mute_for(&msg.channel_login, duration);
+        info!(
+            "{} muted welcomes for {}",
+            msg.sender.name,
+            format_duration(duration)
+        );
+        Ok(Some(format!(
+            "Welcome messages muted for {}.",
+            format_duration(duration)
+        )))
+    }
+
+    fn help(&self) -> &str {
+        "Silence welcome messages for a while. Usage: !mutewelcome <duration> or !mutewelcome off"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+/// A command that welcomes someone as if they were chatting for the first time
+pub struct SimulateNewChatterCommand {
+    welcome_service: Arc<WelcomeService>,
+}
+
+impl SimulateNewChatterCommand {
+    /// Create a new simulate new chatter command
+    ///
+    /// # Arguments
+    /// * `welcome_service` - The welcome service to run
+    ///
+    /// # Returns
+    /// A new SimulateNewChatterCommand instance
+    pub fn new(welcome_service: Arc<WelcomeService>) -> Self {
+        SimulateNewChatterCommand { welcome_service }
+    }
+}
+
+#[async_trait]
+impl Command for SimulateNewChatterCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let Some(target) = args.first() else {
+            return Ok(Some("Usage: !simnew <login>".to_string()));
+        };
+        let login = target.trim_start_matches('@').to_lowercase();
+
+        let user_id = match ctx.helix()?.lock().await.get_broadcaster_id(&login).await {
+            Ok(id) => id,
+            Err(_) => return Ok(Some(format!("Couldn't find a user named {}", login))),
+        };
+
+        info!(
+            "{} simulated a first-time chat from {} ({})",
+            msg.sender.name, login, user_id
+        );
+        // The welcome itself is the response
+        self.welcome_service
+            .simulate_first_time(&msg.channel_login, &login)
+            .await?;
+        Ok(None)
+    }
+
+    fn help(&self) -> &str {
+        "Show the welcome a first-time chatter would get, without changing their record. Usage: !simnew <login>"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Broadcaster
+    }
+}
+
+/// A command that forgets a chatter so they're welcomed again
+pub struct ForgetUserCommand;
+
+#[async_trait]
+impl Command for ForgetUserCommand {
+    async fn execute(
+        &self,
+        ctx: &CommandContext,
+        msg: &PrivmsgMessage,
+        args: Vec<&str>,
+    ) -> Result<Option<String>> {
+        let Some(target) = args.first() else {
+            return Ok(Some("Usage: !forget @user".to_string()));
+        };
+        let login = target.trim_start_matches('@').to_lowercase();
+
+        let user_id = match ctx.helix()?.lock().await.get_broadcaster_id(&login).await {
+            Ok(id) => id,
+            Err(_) => return Ok(Some(format!("Couldn't find a user named {}", login))),
+        };
+
+        if !ctx.user_manager.forget_user(&user_id)? {
+            return Ok(Some(format!("{} hasn't chatted here yet.", login)));
+        }
+        info!("{} forgot {} ({})", msg.sender.name, login, user_id);
+        Ok(Some(format!(
+            "Forgot {}; they'll be welcomed again next time they chat.",
+            login
+        )))
+    }
+
+    fn help(&self) -> &str {
+        "Forget a chatter so they're welcomed again next time they chat. Usage: !forget @user"
+    }
+
+    fn permission(&self) -> Permission {
+        Permission::Moderator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{
+        create_test_context, create_test_context_with_helix, create_test_privmsg_with_badges,
+    };
+    use crate::users::{ChatterStatus, MockTwitchClient, UserManager};
+    use mockito::{Matcher, Server, ServerGuard};
+
+    fn create_command() -> (MuteWelcomeCommand, Arc<WelcomeService>) {
+        let service = Arc::new(WelcomeService::new(
+            Arc::new(MockTwitchClient::new()),
+            Arc::new(UserManager::new("test.db").unwrap()),
+            None,
+        ));
+        (MuteWelcomeCommand::new(service.clone()), service)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_mute_welcome_command() -> Result<()> {
+        let ctx = create_test_context();
+        let (command, service) = create_command();
+        let msg = create_test_privmsg_with_badges("!mutewelcome 10m", &["moderator"]);
+
+        assert_eq!(command.permission(), Permission::Moderator);
+
+        let result = command.execute(&ctx, &msg, vec!["10m"]).await?;
+        assert_eq!(result, Some("Welcome messages muted for 10m.".to_string()));
+        assert!(service.is_muted());
+
+        let result = command.execute(&ctx, &msg, vec!["off"]).await?;
+        assert_eq!(result, Some("Welcome messages have resumed.".to_string()));
+        assert!(!service.is_muted());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mute_welcome_invalid_duration() -> Result<()> {
+        let ctx = create_test_context();
+        let (command, service) = create_command();
+        let msg = create_test_privmsg_with_badges("!mutewelcome soon", &["moderator"]);
+
+        let result = command.execute(&ctx, &msg, vec!["soon"]).await?;
+        assert_eq!(result, Some("Invalid duration: soon".to_string()));
+        assert!(!service.is_muted());
+
+        // An enormous duration is refused instead of overflowing
+        let result = command
+            .execute(&ctx, &msg, vec!["18446744073709551615"])
+            .await?;
+        assert_eq!(
+            result,
+            Some("Invalid duration: 18446744073709551615".to_string())
+        );
+        assert!(!service.is_muted());
+        Ok(())
+    }
+
+    async fn create_simnew_command(
+        server: &mut ServerGuard,
+        user_manager: Arc<UserManager>,
+    ) -> (SimulateNewChatterCommand, MockTwitchClient, CommandContext) {
+        server
+            .mock("GET", "/users")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"999","login":"test_bot","display_name":"Test_Bot"}]}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "newbie".into()))
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"300","login":"newbie","display_name":"Newbie"}]}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "nobody".into()))
+            .with_status(200)
+            .with_body(r#"{"data":[]}"#)
+            .create_async()
+            .await;
+
+        let client = MockTwitchClient::new();
+        let service = Arc::new(WelcomeService::new(
+            Arc::new(client.clone()),
+            user_manager,
+            Some(vec!["Welcome, {username}!".to_string()]),
+        ));
+        (
+            SimulateNewChatterCommand::new(service),
+            client,
+            create_test_context_with_helix(&server.url()).await,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_simnew_welcomes_without_marking_user() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let user_manager = Arc::new(UserManager::new("test.db")?);
+        let (command, client, ctx) = create_simnew_command(&mut server, user_manager.clone()).await;
+        let msg = create_test_privmsg_with_badges("!simnew @Newbie", &["broadcaster"]);
+
+        assert_eq!(command.permission(), Permission::Broadcaster);
+        let result = command.execute(&ctx, &msg, vec!["@Newbie"]).await?;
+        assert_eq!(result, None);
+        assert_eq!(
+            client.sent_messages(),
+            vec![("test_channel".to_string(), "Welcome, newbie!".to_string())]
+        );
+
+        // The user is still new when they really chat
+        assert!(!user_manager.is_known_user("300"));
+        assert!(user_manager.get_user("300")?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_simnew_leaves_known_user_unchanged() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let user_manager = Arc::new(UserManager::new("test.db")?);
+        user_manager.record_chat("300", "newbie")?;
+        user_manager.mark_welcomed("300")?;
+        let before = user_manager.get_user("300")?;
+
+        let (command, client, ctx) = create_simnew_command(&mut server, user_manager.clone()).await;
+        let msg = create_test_privmsg_with_badges("!simnew newbie", &["broadcaster"]);
+
+        command.execute(&ctx, &msg, vec!["newbie"]).await?;
+        assert_eq!(client.sent_messages().len(), 1);
+        assert_eq!(user_manager.get_user("300")?, before);
+
+        let result = command.execute(&ctx, &msg, vec!["nobody"]).await?;
+        assert_eq!(
+            result,
+            Some("Couldn't find a user named nobody".to_string())
+        );
+        assert_eq!(client.sent_messages().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_forget_user() -> Result<()> {
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "helper".into()))
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"300","login":"helper","display_name":"Helper"}]}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "nobody".into()))
+            .with_status(200)
+            .with_body(r#"{"data":[]}"#)
+            .create_async()
+            .await;
+        let user_manager = Arc::new(UserManager::new("test.db")?);
+        user_manager.record_chat("300", "helper")?;
+        user_manager.record_chat("400", "regular")?;
+        let mut ctx = create_test_context_with_helix(&server.url()).await;
+        ctx.user_manager = user_manager.clone();
+        let command = ForgetUserCommand;
+        let msg = create_test_privmsg_with_badges("!forget @Helper", &["moderator"]);
+
+        assert_eq!(command.permission(), Permission::Moderator);
+        let result = command.execute(&ctx, &msg, vec!["@Helper"]).await?;
+        assert_eq!(
+            result,
+            Some("Forgot helper; they'll be welcomed again next time they chat.".to_string())
+        );
+
+        // The forgotten user is new again while others stay known
+        assert!(!user_manager.is_known_user("300"));
+        assert!(user_manager.is_known_user("400"));
+        assert_eq!(
+            user_manager.record_chat("300", "helper")?,
+            ChatterStatus::FirstTime
+        );
+        assert_eq!(
+            user_manager.record_chat("400", "regular")?,
+            ChatterStatus::SameSession
+        );
+
+        user_manager.forget_user("300")?;
+        let result = command.execute(&ctx, &msg, vec!["helper"]).await?;
+        assert_eq!(result, Some("helper hasn't chatted here yet.".to_string()));
+        let result = command.execute(&ctx, &msg, vec!["nobody"]).await?;
+        assert_eq!(
+            result,
+            Some("Couldn't find a user named nobody".to_string())
+        );
+        Ok(())
+    }
+}
@@ -1,6 +1,137 @@
 use anyhow::Result;
 use dotenv::dotenv;
-use std::env;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env::{self, VarError};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::ai::LengthStrategy;
+use crate::announcements::ConfiguredAnnouncement;
+use crate::commands::{
+    CooldownSettings, DEFAULT_EIGHT_BALL_FORMAT, DEFAULT_EIGHT_BALL_REPEAT_WINDOW, UptimeUnits,
+};
+use crate::counters::Counters;
+use crate::events::EventMessages;
+use crate::features::ChannelFeatures;
+use crate::greeting::DEFAULT_GREETING_DELAY;
+use crate::moderation::{
+    AutomodRules, LockdownSettings, MAX_LOCKDOWN_FOLLOW_MINUTES, MAX_TIMEOUT_SECS, NewAccountAction,
+};
+use crate::personality::Personality;
+use crate::points::{DEFAULT_POINTS_INTERVAL, DEFAULT_POINTS_PER_INTERVAL, SlotsTable};
+use crate::raffle::RaffleWeights;
+use crate::redemptions::RedemptionActions;
+use crate::silence::DEFAULT_SILENCE_PROMPT;
+use crate::store::{DEFAULT_SAVE_INTERVAL, StoreBackend};
+use crate::twitch::{
+    AnnouncementColor, CHAT_MESSAGES_PER_WINDOW, DEFAULT_REDIRECT_URI,
+    MODERATOR_CHAT_MESSAGES_PER_WINDOW,
+};
+use crate::users::{DEFAULT_SHOUTOUT_MESSAGE, MAX_WELCOME_GRACE_SECS, WelcomeMode};
+
+/// The error when no channel is given on the command line or in the config
+const NO_CHANNEL_ERROR: &str = "No channel to join. Pass one with `start --channel <name>`, or set TWITCH_CHANNEL in the environment or .env (or `channel` in the config file)";
+
+/// The channel used offline when none is configured
+const OFFLINE_CHANNEL: &str = "simulated_channel";
+
+/// The bot's name offline when none is configured
+const OFFLINE_BOT_USERNAME: &str = "som_chatbot";
+
+/// The AI model used when AI_MODEL is not set
+const DEFAULT_AI_MODEL: &str = "gpt-4o-mini";
+
+/// Separates the templates in WELCOME_MESSAGES
+const WELCOME_TEMPLATE_SEPARATOR: &str = "|";
+
+/// Separates the announcements in ANNOUNCEMENTS
+const ANNOUNCEMENT_SEPARATOR: &str = "|";
+
+/// The command prefix used when COMMAND_PREFIX is not set
+pub const DEFAULT_COMMAND_PREFIX: &str = "!";
+
+/// Settings that can be given in a TOML config file
+///
+/// Each one has an environment variable that overrides it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    /// TWITCH_CLIENT_ID
+    client_id: Option<String>,
+    /// TWITCH_CHANNEL
+    channel: Option<String>,
+    /// TWITCH_BOT_USERNAME
+    bot_username: Option<String>,
+    /// DATA_DIR
+    data_dir: Option<String>,
+    /// STORE_BACKEND
+    store_backend: Option<String>,
+    /// COMMAND_PREFIX
+    command_prefix: Option<String>,
+    /// CHANNEL_PREFIXES, as a table of channel = "prefix"
+    channel_prefixes: Option<HashMap<String, String>>,
+    /// WELCOME_MODE
+    welcome_mode: Option<String>,
+    /// WELCOME_WINDOW_SECS
+    welcome_window_secs: Option<u64>,
+    /// CAPTURE_FIRST_WORDS
+    capture_first_words: Option<bool>,
+    /// WELCOME_GRACE_SECS
+    welcome_grace_secs: Option<u64>,
+    /// WELCOME_ENABLED
+    welcome_enabled: Option<bool>,
+    /// WELCOME_USE_AI
+    welcome_use_ai: Option<bool>,
+    /// WELCOME_MESSAGES
+    welcome_messages: Option<Vec<String>>,
+    /// ANNOUNCEMENTS
+    announcements: Option<Vec<ConfiguredAnnouncement>>,
+    /// ANNOUNCEMENT_MIN_CHAT_MESSAGES
+    announcement_min_chat_messages: Option<u64>,
+}
+
+impl FileConfig {
+    /// Read a TOML config file
+    ///
+    /// # Arguments
+    /// * `path` - Path to the file
+    ///
+    /// # Returns
+    /// The settings in the file
+    fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Couldn't read config file {}: {}", path.display(), e))?;
+        toml::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    /// Get a setting by the name of the environment variable that overrides it
+    ///
+    /// Settings kept as lists are read by `Config::from_sources` directly
+    /// instead, since they don't need parsing from a string.
+    fn get(&self, var: &str) -> Option<String> {
+        match var {
+            "TWITCH_CLIENT_ID" => self.client_id.clone(),
+            "TWITCH_CHANNEL" => self.channel.clone(),
+            "TWITCH_BOT_USERNAME" => self.bot_username.clone(),
+            "DATA_DIR" => self.data_dir.clone(),
+            "STORE_BACKEND" => self.store_backend.clone(),
+            "COMMAND_PREFIX" => self.command_prefix.clone(),
+            "WELCOME_MODE" => self.welcome_mode.clone(),
+            "WELCOME_WINDOW_SECS" => self.welcome_window_secs.map(|secs| secs.to_string()),
+            "CAPTURE_FIRST_WORDS" => self.capture_first_words.map(|capture| capture.to_string()),
+            "WELCOME_GRACE_SECS" => self.welcome_grace_secs.map(|secs| secs.to_string()),
+            "WELCOME_ENABLED" => self.welcome_enabled.map(|enabled| enabled.to_string()),
+            "WELCOME_USE_AI" => self.welcome_use_ai.map(|use_ai| use_ai.to_string()),
+            "ANNOUNCEMENT_MIN_CHAT_MESSAGES" => self
+                .announcement_min_chat_messages
+                .map(|count| count.to_string()),
+            _ => None,
+        }
+    }
+}
 
 /// Configuration for the Twitch chatbot
 pub struct Config {
@@ -10,35 +141,767 @@ pub struct Config {
     pub channel_name: String,
     /// The bot's username on Twitch
     pub bot_username: String,
+    /// Prefix that marks a chat message as a command
+    pub command_prefix: String,
+    /// Prefixes for channels that use a different one, keyed by channel login
+    pub channel_prefixes: HashMap<String, String>,
+    /// Which features are on in each channel
+    pub channel_features: ChannelFeatures,
+    /// Channels that limited commands work in, keyed by command name
+    pub command_channels: HashMap<String, Vec<String>>,
+    /// Reply to a limited command used in another channel; None ignores it
+    pub wrong_channel_reply: Option<String>,
+    /// Whether to warn when another bot answers the same commands
+    pub prefix_conflict_detection: bool,
     /// The data directory for storing tokens and other data
     pub data_dir: String,
+    /// Which backend keeps quotes and other feature state
+    pub store_backend: StoreBackend,
+    /// How often the file store saves changes; None saves every change right away
+    pub store_save_interval: Option<Duration>,
+    /// Optional passphrase for encrypting the stored OAuth token
+    pub token_encryption_key: Option<String>,
+    /// Client secret for the authorization code flow; None uses the device code flow
+    pub client_secret: Option<String>,
+    /// Redirect URL registered for the application, for the authorization code flow
+    pub oauth_redirect_uri: String,
+    /// Base URL of an OpenAI-compatible API for AI features
+    pub ai_api_base: Option<String>,
+    /// API key for the AI service
+    pub ai_api_key: Option<String>,
+    /// The model to use for AI features
+    pub ai_model: String,
+    /// What to do with AI responses too long for a chat message
+    pub ai_length_strategy: LengthStrategy,
+    /// Accounts younger than this many days are flagged (None disables the check)
+    pub new_account_min_age_days: Option<i64>,
+    /// What to do with messages from accounts that are too new
+    pub new_account_action: NewAccountAction,
+    /// Which restrictions !lockdown turns on
+    pub lockdown: LockdownSettings,
+    /// Which chat messages are automatically timed out
+    pub automod: AutomodRules,
+    /// How many raffle entries subscribers and VIPs get
+    pub raffle_weights: RaffleWeights,
+    /// Points each watching chatter earns per interval
+    pub points_per_interval: u64,
+    /// How often points are handed out
+    pub points_interval: Duration,
+    /// The slot machine's symbols and payouts
+    pub slots_table: SlotsTable,
+    /// How welcome messages are paced when many new chatters arrive
+    pub welcome_mode: WelcomeMode,
+    /// How long welcomes are held back after a raid or going live (zero disables it)
+    pub welcome_grace_period: Duration,
+    /// Whether new and returning chatters are welcomed at all
+    pub welcome_enabled: bool,
+    /// Whether welcomes are AI-generated when an AI API is configured
+    pub welcome_use_ai: bool,
+    /// Custom welcome templates (None uses welcome_messages.txt or the personality's)
+    pub welcome_messages: Option<Vec<String>>,
+    /// Announcements added to the store at startup
+    pub announcements: Vec<ConfiguredAnnouncement>,
+    /// How many chat messages must arrive between announcements
+    pub announcement_min_chat_messages: u64,
+    /// Color announcements are posted in as Twitch announcements (None posts chat messages)
+    pub announcement_color: Option<AnnouncementColor>,
+    /// How long chat must be quiet while live before a prompt is posted (None disables it)
+    pub silence_prompt_after: Option<Duration>,
+    /// The message posted to break a long silence
+    pub silence_prompt: String,
+    /// Streamers who get an automatic shoutout the first time they chat each stream
+    pub auto_shoutout_users: Vec<String>,
+    /// How often each command may be used
+    pub cooldowns: CooldownSettings,
+    /// Commands only followers of the channel (and mods) can use
+    pub follower_only_commands: Vec<String>,
+    /// Commands only subscribers (and mods) can use
+    pub subscriber_only_commands: Vec<String>,
+    /// Counter commands that always exist, e.g. "deaths" for !deaths
+    pub counters: Vec<String>,
+    /// Promo line posted by !so (use {login} as placeholder)
+    pub shoutout_message: String,
+    /// Reply posted by !8ball ({user}, {question}, and {response} are replaced)
+    pub eight_ball_format: String,
+    /// TOML file of themed 8-ball responses (None uses the built-in ones)
+    pub eight_ball_file: Option<String>,
+    /// How many of its latest answers the 8-ball avoids repeating
+    pub eight_ball_repeat_window: usize,
+    /// Unit words used by !uptime
+    pub uptime_units: UptimeUnits,
+    /// Tone preset for AI prompts and canned phrases
+    pub personality: Personality,
+    /// Text file whose contents replace the personality's AI prompt (None uses the preset's)
+    pub ai_prompt_file: Option<String>,
+    /// Thank-you messages for subs, gift subs, and raids
+    pub event_messages: EventMessages,
+    /// Reaction to highlighted messages (None disables it)
+    pub highlight_message: Option<String>,
+    /// TOML file of FAQ rules (None disables automatic answers)
+    pub faq_file: Option<String>,
+    /// Chat messages the bot may send per 30 seconds
+    pub chat_rate_limit: u32,
+    /// Chat messages the bot may send per 30 seconds while it moderates the channel
+    pub chat_rate_limit_moderator: u32,
+    /// Whether to connect to chat over TLS
+    pub irc_tls: bool,
+    /// How long to wait after joining before posting the online message
+    pub greeting_delay: Duration,
+    /// The most extra time, chosen at random, added to the greeting delay
+    pub greeting_jitter: Duration,
+    /// Whether to store the first message of each new chatter
+    pub capture_first_words: bool,
+    /// Whether to check that sent messages reach chat
+    pub confirm_delivery: bool,
+    /// Whether to append bans, timeouts, and deleted messages to a file
+    pub moderation_log: bool,
+    /// Whether to receive subscriptions, raids, and redemptions over EventSub as well as IRC
+    pub eventsub: bool,
+    /// What the bot does when each channel point reward is redeemed
+    pub redemption_actions: RedemptionActions,
 }
 
 impl Config {
-    /// Load configuration from environment variables
+    /// Load configuration from environment variables and an optional TOML file
+    ///
+    /// Environment variables take precedence over values in the file.
+    ///
+    /// # Arguments
+    /// * `path` - Path to a TOML config file, if one was given
+    ///
+    /// # Returns
+    /// A Result containing the Config if successful, or an error if required variables are missing
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        dotenv().ok();
+
+        let file = match path {
+            Some(path) => FileConfig::read(Path::new(path))?,
+            None => FileConfig::default(),
+        };
+
+        Self::from_sources(
+            |name| env::var(name).or_else(|_| file.get(name).ok_or(VarError::NotPresent)),
+            &file,
+            None,
+        )
+    }
+
+    /// Load the configuration for `start`, which joins the channel
+    ///
+    /// Settings come from the same places as `load`. If no channel is given
+    /// anywhere and the bot runs in a terminal, it asks for one.
+    ///
+    /// # Arguments
+    /// * `path` - Path to a TOML config file, if one was given
+    /// * `channel_override` - Channel given on the command line, which wins over the config
     ///
     /// # Returns
     /// A Result containing the Config if successful, or an error if required variables are missing
-    pub fn from_env() -> Result<Self> {
+    pub fn load_for_start(path: Option<&str>, channel_override: Option<&str>) -> Result<Self> {
         dotenv().ok();
 
-        let client_id = env::var("TWITCH_CLIENT_ID")
+        let file = match path {
+            Some(path) => FileConfig::read(Path::new(path))?,
+            None => FileConfig::default(),
+        };
+        let var =
+            |name: &str| env::var(name).or_else(|_| file.get(name).ok_or(VarError::NotPresent));
+
+        let prompted;
+        let channel_override = match channel_override {
+            Some(channel) => Some(channel),
+            None if var("TWITCH_CHANNEL").is_err() && io::stdin().is_terminal() => {
+                prompted = prompt_for_channel()?;
+                prompted.as_deref()
+            }
+            None => None,
+        };
+
+        Self::from_sources(var, &file, channel_override)
+    }
+
+    /// Load the configuration for running offline, e.g. `simulate`
+    ///
+    /// Settings come from the same places as `load`, but Twitch credentials
+    /// aren't needed, and made-up names fill in a missing channel or bot.
+    ///
+    /// # Arguments
+    /// * `path` - Path to a TOML config file, if one was given
+    ///
+    /// # Returns
+    /// A Result containing the Config if successful, or an error if a setting is invalid
+    pub fn load_offline(path: Option<&str>) -> Result<Self> {
+        dotenv().ok();
+
+        let file = match path {
+            Some(path) => FileConfig::read(Path::new(path))?,
+            None => FileConfig::default(),
+        };
+        let var = |name: &str| {
+            env::var(name)
+                .or_else(|_| file.get(name).ok_or(VarError::NotPresent))
+                .or_else(|e| match name {
+                    "TWITCH_CLIENT_ID" => Ok("offline".to_string()),
+                    "TWITCH_CHANNEL" => Ok(OFFLINE_CHANNEL.to_string()),
+                    "TWITCH_BOT_USERNAME" => Ok(OFFLINE_BOT_USERNAME.to_string()),
+                    _ => Err(e),
+                })
+        };
+
+        Self::from_sources(var, &file, None)
+    }
+
+    /// Build the configuration from a lookup of settings by environment variable name
+    ///
+    /// # Arguments
+    /// * `var` - Looks up a setting, like `std::env::var`
+    /// * `file` - The config file, for the settings it gives as lists, which `var` overrides
+    /// * `channel_override` - Channel to join instead of TWITCH_CHANNEL, if given
+    ///
+    /// # Returns
+    /// A Result containing the Config if successful, or an error if required settings are missing
+    fn from_sources(
+        var: impl Fn(&str) -> Result<String, VarError>,
+        file: &FileConfig,
+        channel_override: Option<&str>,
+    ) -> Result<Self> {
+        let client_id = var("TWITCH_CLIENT_ID")
             .map_err(|_| anyhow::anyhow!("TWITCH_CLIENT_ID environment variable not set"))?;
 
-        let channel_name = env::var("TWITCH_CHANNEL")
-            .map_err(|_| anyhow::anyhow!("TWITCH_CHANNEL environment variable not set"))?;
+        let channel_name = channel_override
+            .map(str::to_string)
+            .or_else(|| var("TWITCH_CHANNEL").ok())
+            .map(|channel| channel.trim().to_string())
+            .filter(|channel| !channel.is_empty())
+            .ok_or_else(|| anyhow::anyhow!(NO_CHANNEL_ERROR))?;
 
-        let bot_username = env::var("TWITCH_BOT_USERNAME")
+        let bot_username = var("TWITCH_BOT_USERNAME")
             .map_err(|_| anyhow::anyhow!("TWITCH_BOT_USERNAME environment variable not set"))?;
 
+        // Prefix that marks a chat message as a command
+        let command_prefix = var("COMMAND_PREFIX")
+            .ok()
+            .filter(|prefix| !prefix.is_empty())
+            .unwrap_or_else(|| DEFAULT_COMMAND_PREFIX.to_string());
+
+        // Per-channel prefixes, e.g. "busy_channel=?,other=~"
+        let channel_prefixes = match var("CHANNEL_PREFIXES") {
+            Ok(value) => parse_channel_prefixes(&value)?,
+            Err(_) => file
+                .channel_prefixes
+                .iter()
+                .flatten()
+                .map(|(channel, prefix)| (channel_key(channel), prefix.trim().to_string()))
+                .collect(),
+        };
+        // Per-channel features, e.g. "busy_channel=welcomes+filters+commands,other=commands"
+        let channel_features = match var("CHANNEL_FEATURES") {
+            Ok(value) => parse_channel_features(&value)?,
+            Err(_) => ChannelFeatures::default(),
+        };
+        // Commands limited to some channels, e.g. "deaths=busy_channel+other"
+        let command_channels = match var("COMMAND_CHANNELS") {
+            Ok(value) => parse_command_channels(&value)?,
+            Err(_) => HashMap::new(),
+        };
+        let wrong_channel_reply = var("WRONG_CHANNEL_REPLY").ok();
+        let prefix_conflict_detection = match var("PREFIX_CONFLICT_DETECTION") {
+            Ok(value) => parse_bool(&value).ok_or_else(|| {
+                anyhow::anyhow!("PREFIX_CONFLICT_DETECTION must be true or false")
+            })?,
+            Err(_) => false,
+        };
+
         // Optional data directory, default to ./data
-        let data_dir = env::var("DATA_DIR").unwrap_or_else(|_| "./data".to_string());
+        let data_dir = var("DATA_DIR").unwrap_or_else(|_| "./data".to_string());
+
+        // Where quotes and other feature state are kept: file or sqlite
+        let store_backend = match var("STORE_BACKEND") {
+            Ok(backend) => backend.parse()?,
+            Err(_) => StoreBackend::default(),
+        };
+
+        // How often the file store saves; 0 saves every change right away
+        let store_save_interval = match var("STORE_SAVE_INTERVAL_SECS") {
+            Ok(secs) => Some(Duration::from_secs(secs.parse().map_err(|_| {
+                anyhow::anyhow!("STORE_SAVE_INTERVAL_SECS must be a number of seconds")
+            })?)),
+            Err(_) => Some(DEFAULT_SAVE_INTERVAL),
+        }
+        .filter(|interval| !interval.is_zero());
+
+        // Optional passphrase for encrypting the OAuth token at rest
+        let token_encryption_key = var("TOKEN_ENCRYPTION_KEY")
+            .ok()
+            .filter(|key| !key.is_empty());
+
+        // Optional client secret for confidential applications
+        let client_secret = var("TWITCH_CLIENT_SECRET")
+            .ok()
+            .filter(|secret| !secret.is_empty());
+        let oauth_redirect_uri =
+            var("OAUTH_REDIRECT_URI").unwrap_or_else(|_| DEFAULT_REDIRECT_URI.to_string());
+
+        // Optional OpenAI-compatible API for AI-generated messages
+        let ai_api_base = var("AI_API_BASE").ok().filter(|v| !v.is_empty());
+        let ai_api_key = var("AI_API_KEY").ok().filter(|v| !v.is_empty());
+        let ai_model = var("AI_MODEL").unwrap_or_else(|_| DEFAULT_AI_MODEL.to_string());
+        let ai_length_strategy = match var("AI_LENGTH_STRATEGY") {
+            Ok(strategy) => strategy.parse()?,
+            Err(_) => LengthStrategy::default(),
+        };
+
+        // Optional flagging of recently created accounts
+        let new_account_min_age_days = match var("NEW_ACCOUNT_MIN_AGE_DAYS") {
+            Ok(days) => Some(days.parse().map_err(|_| {
+                anyhow::anyhow!("NEW_ACCOUNT_MIN_AGE_DAYS must be a number of days")
+            })?),
+            Err(_) => None,
+        };
+        let mut new_account_action = match var("NEW_ACCOUNT_ACTION") {
+            Ok(action) => action.parse()?,
+            Err(_) => NewAccountAction::Flag,
+        };
+        if let (NewAccountAction::Timeout(_), Ok(secs)) =
+            (new_account_action, var("NEW_ACCOUNT_TIMEOUT_SECS"))
+        {
+            new_account_action = NewAccountAction::Timeout(secs.parse().map_err(|_| {
+                anyhow::anyhow!("NEW_ACCOUNT_TIMEOUT_SECS must be a number of seconds")
+            })?);
+        }
+
+        // Chat modes applied by !lockdown
+        let mut lockdown = LockdownSettings::default();
+        if let Ok(minutes) = var("LOCKDOWN_FOLLOW_MINUTES") {
+            lockdown.follow_minutes = match minutes.parse() {
+                Ok(minutes) if minutes <= MAX_LOCKDOWN_FOLLOW_MINUTES => minutes,
+                _ => anyhow::bail!(
+                    "LOCKDOWN_FOLLOW_MINUTES must be a number of minutes up to {}",
+                    MAX_LOCKDOWN_FOLLOW_MINUTES
+                ),
+            };
+        }
+        if let Ok(value) = var("LOCKDOWN_SUBSCRIBERS_ONLY") {
+            lockdown.subscribers_only = parse_bool(&value).ok_or_else(|| {
+                anyhow::anyhow!("LOCKDOWN_SUBSCRIBERS_ONLY must be true or false")
+            })?;
+        }
+        if let Ok(value) = var("LOCKDOWN_EMOTE_ONLY") {
+            lockdown.emote_only = parse_bool(&value)
+                .ok_or_else(|| anyhow::anyhow!("LOCKDOWN_EMOTE_ONLY must be true or false"))?;
+        }
+
+        // Automatic timeouts for links, caps, and emote spam
+        let mut automod = AutomodRules::default();
+        if let Ok(value) = var("AUTOMOD_BLOCK_LINKS") {
+            automod.block_links = parse_bool(&value)
+                .ok_or_else(|| anyhow::anyhow!("AUTOMOD_BLOCK_LINKS must be true or false"))?;
+        }
+        for (name, pattern) in [
+            ("AUTOMOD_ALLOW_LINKS", &mut automod.allow_links),
+            ("AUTOMOD_DENY_LINKS", &mut automod.deny_links),
+        ] {
+            if let Ok(value) = var(name).map(|value| value.trim().to_string())
+                && !value.is_empty()
+            {
+                *pattern = Some(
+                    regex::RegexBuilder::new(&value)
+                        .case_insensitive(true)
+                        .build()
+                        .map_err(|e| anyhow::anyhow!("{} is not a valid pattern: {}", name, e))?,
+                );
+            }
+        }
+        if let Ok(value) = var("AUTOMOD_PERMIT_KNOWN_USERS") {
+            automod.permit_known_users = parse_bool(&value).ok_or_else(|| {
+                anyhow::anyhow!("AUTOMOD_PERMIT_KNOWN_USERS must be true or false")
+            })?;
+        }
+        if let Ok(percent) = var("AUTOMOD_CAPS_PERCENT") {
+            automod.max_caps_percent = Some(
+                percent
+                    .parse()
+                    .ok()
+                    .filter(|percent| *percent <= 100)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("AUTOMOD_CAPS_PERCENT must be a percentage from 0 to 100")
+                    })?,
+            );
+        }
+        if let Ok(count) = var("AUTOMOD_MAX_EMOTES") {
+            automod.max_emotes =
+                Some(count.parse().map_err(|_| {
+                    anyhow::anyhow!("AUTOMOD_MAX_EMOTES must be a number of emotes")
+                })?);
+        }
+        if let Ok(secs) = var("AUTOMOD_TIMEOUT_SECS") {
+            automod.timeout_secs = match secs.parse() {
+                Ok(secs) if (1..=MAX_TIMEOUT_SECS).contains(&secs) => secs,
+                _ => anyhow::bail!(
+                    "AUTOMOD_TIMEOUT_SECS must be a number of seconds from 1 to {}",
+                    MAX_TIMEOUT_SECS
+                ),
+            };
+        }
+
+        // Extra raffle entries for subscribers and VIPs
+        let mut raffle_weights = RaffleWeights::default();
+        if let Ok(weight) = var("RAFFLE_SUBSCRIBER_WEIGHT") {
+            raffle_weights.subscriber = weight.parse().map_err(|_| {
+                anyhow::anyhow!("RAFFLE_SUBSCRIBER_WEIGHT must be a number of entries")
+            })?;
+        }
+        if let Ok(weight) = var("RAFFLE_VIP_WEIGHT") {
+            raffle_weights.vip = weight
+                .parse()
+                .map_err(|_| anyhow::anyhow!("RAFFLE_VIP_WEIGHT must be a number of entries"))?;
+        }
+
+        // Loyalty points and what the slot machine pays
+        let points_per_interval = match var("POINTS_PER_INTERVAL") {
+            Ok(points) => points
+                .parse()
+                .map_err(|_| anyhow::anyhow!("POINTS_PER_INTERVAL must be a number of points"))?,
+            Err(_) => DEFAULT_POINTS_PER_INTERVAL,
+        };
+        let points_interval = match var("POINTS_INTERVAL_SECS") {
+            Ok(secs) => match secs.parse() {
+                Ok(secs) if secs > 0 => Duration::from_secs(secs),
+                _ => anyhow::bail!("POINTS_INTERVAL_SECS must be a positive number of seconds"),
+            },
+            Err(_) => DEFAULT_POINTS_INTERVAL,
+        };
+        let slots_table = match var("SLOTS_PAYOUTS") {
+            Ok(table) => SlotsTable::parse(&table)
+                .map_err(|e| anyhow::anyhow!("Invalid SLOTS_PAYOUTS: {}", e))?,
+            Err(_) => SlotsTable::default(),
+        };
+
+        // Welcome pacing: immediate, cooldown, or batch over a window
+        let welcome_window_secs = match var("WELCOME_WINDOW_SECS") {
+            Ok(secs) => secs
+                .parse()
+                .map_err(|_| anyhow::anyhow!("WELCOME_WINDOW_SECS must be a number of seconds"))?,
+            Err(_) => 30,
+        };
+        let welcome_mode = WelcomeMode::from_name(
+            &var("WELCOME_MODE").unwrap_or_else(|_| "immediate".to_string()),
+            Duration::from_secs(welcome_window_secs),
+        )?;
+        // Hold back welcomes after a raid or going live
+        let welcome_grace_period = match var("WELCOME_GRACE_SECS") {
+            Ok(secs) => match secs.parse() {
+                Ok(secs) if secs <= MAX_WELCOME_GRACE_SECS => Duration::from_secs(secs),
+                _ => anyhow::bail!(
+                    "WELCOME_GRACE_SECS must be a number of seconds up to {}",
+                    MAX_WELCOME_GRACE_SECS
+                ),
+            },
+            Err(_) => Duration::ZERO,
+        };
+        let welcome_enabled = match var("WELCOME_ENABLED") {
+            Ok(value) => parse_bool(&value)
+                .ok_or_else(|| anyhow::anyhow!("WELCOME_ENABLED must be true or false"))?,
+            Err(_) => true,
+        };
+        let welcome_use_ai = match var("WELCOME_USE_AI") {
+            Ok(value) => parse_bool(&value)
+                .ok_or_else(|| anyhow::anyhow!("WELCOME_USE_AI must be true or false"))?,
+            Err(_) => true,
+        };
+        // Custom templates separated by "|", e.g. "Hi {username}!|Welcome, {username}!"
+        let welcome_messages = match var("WELCOME_MESSAGES") {
+            Ok(value) => Some(
+                value
+                    .split(WELCOME_TEMPLATE_SEPARATOR)
+                    .map(str::to_string)
+                    .collect(),
+            ),
+            Err(_) => file.welcome_messages.clone(),
+        }
+        .map(|templates| {
+            templates
+                .iter()
+                .map(|template| template.trim().to_string())
+                .filter(|template| !template.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|templates| !templates.is_empty());
+
+        // Recurring announcements separated by "|", e.g. "15m Follow!|1h Join the Discord!"
+        let announcements = match var("ANNOUNCEMENTS") {
+            Ok(value) => value
+                .split(ANNOUNCEMENT_SEPARATOR)
+                .map(str::trim)
+                .filter(|announcement| !announcement.is_empty())
+                .map(|announcement| {
+                    announcement.parse().map_err(|e| {
+                        anyhow::anyhow!("Invalid announcement '{}': {}", announcement, e)
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?,
+            Err(_) => file.announcements.clone().unwrap_or_default(),
+        };
+        let announcement_min_chat_messages = match var("ANNOUNCEMENT_MIN_CHAT_MESSAGES") {
+            Ok(count) => count.parse().map_err(|_| {
+                anyhow::anyhow!("ANNOUNCEMENT_MIN_CHAT_MESSAGES must be a number of messages")
+            })?,
+            Err(_) => 0,
+        };
+        let announcement_color = match var("ANNOUNCEMENT_COLOR") {
+            Ok(color) if !color.trim().is_empty() => Some(color.parse()?),
+            _ => None,
+        };
+
+        // Prompt chat after it goes quiet for this long while live
+        let silence_prompt_after = match var("SILENCE_PROMPT_SECS") {
+            Ok(secs) => Some(Duration::from_secs(secs.parse().map_err(|_| {
+                anyhow::anyhow!("SILENCE_PROMPT_SECS must be a number of seconds")
+            })?))
+            .filter(|after| !after.is_zero()),
+            Err(_) => None,
+        };
+        let silence_prompt = var("SILENCE_PROMPT_MESSAGE")
+            .ok()
+            .filter(|prompt| !prompt.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_SILENCE_PROMPT.to_string());
+
+        // Comma-separated list of streamers to shout out automatically
+        let auto_shoutout_users = var("AUTO_SHOUTOUT_USERS")
+            .map(|users| parse_list(&users))
+            .unwrap_or_default();
+
+        // Command cooldowns, and the roles that skip them
+        let mut cooldowns = CooldownSettings::default();
+        if let Ok(secs) = var("COMMAND_COOLDOWN_SECS") {
+            cooldowns.global = Duration::from_secs(secs.parse().map_err(|_| {
+                anyhow::anyhow!("COMMAND_COOLDOWN_SECS must be a number of seconds")
+            })?);
+        }
+        if let Ok(secs) = var("COMMAND_USER_COOLDOWN_SECS") {
+            cooldowns.per_user = Duration::from_secs(secs.parse().map_err(|_| {
+                anyhow::anyhow!("COMMAND_USER_COOLDOWN_SECS must be a number of seconds")
+            })?);
+        }
+        if let Ok(roles) = var("COOLDOWN_EXEMPT_ROLES") {
+            cooldowns.exempt_roles = parse_list(&roles)
+                .iter()
+                .map(|role| role.to_lowercase())
+                .collect();
+        }
+
+        // Commands limited to followers or subscribers, by name without the prefix
+        let command_list = |name: &str| -> Vec<String> {
+            var(name)
+                .map(|commands| {
+                    parse_list(&commands)
+                        .iter()
+                        .map(|command| command.to_lowercase())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let follower_only_commands = command_list("FOLLOWER_ONLY_COMMANDS");
+        let subscriber_only_commands = command_list("SUBSCRIBER_ONLY_COMMANDS");
+
+        // Counter commands like !deaths
+        let counters = var("COUNTERS")
+            .map(|counters| {
+                parse_list(&counters)
+                    .iter()
+                    .map(|name| name.to_lowercase())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_else(|_| vec!["deaths".to_string()]);
+        if let Some(name) = counters.iter().find(|name| !Counters::is_valid_name(name)) {
+            anyhow::bail!(
+                "COUNTERS entry '{}' can only use letters, digits, and underscores",
+                name
+            );
+        }
+
+        // Promo line posted by !so
+        let shoutout_message = var("SHOUTOUT_MESSAGE")
+            .ok()
+            .filter(|message| !message.is_empty())
+            .unwrap_or_else(|| DEFAULT_SHOUTOUT_MESSAGE.to_string());
+
+        // Reply posted by !8ball
+        let eight_ball_format = var("EIGHT_BALL_FORMAT")
+            .ok()
+            .filter(|format| !format.is_empty())
+            .unwrap_or_else(|| DEFAULT_EIGHT_BALL_FORMAT.to_string());
+        let eight_ball_file = var("EIGHT_BALL_FILE").ok().filter(|path| !path.is_empty());
+        let eight_ball_repeat_window = match var("EIGHT_BALL_REPEAT_WINDOW") {
+            Ok(window) => window.parse().map_err(|_| {
+                anyhow::anyhow!("EIGHT_BALL_REPEAT_WINDOW must be a number of answers")
+            })?,
+            Err(_) => DEFAULT_EIGHT_BALL_REPEAT_WINDOW,
+        };
+
+        // Language for the unit words shown by !uptime
+        let uptime_units = match var("UPTIME_LOCALE") {
+            Ok(locale) => UptimeUnits::for_locale(&locale)
+                .ok_or_else(|| anyhow::anyhow!("Unsupported UPTIME_LOCALE: {}", locale))?,
+            Err(_) => UptimeUnits::default(),
+        };
+
+        // Tone preset: friendly, snarky, or formal
+        let personality = match var("PERSONALITY") {
+            Ok(name) => name.parse()?,
+            Err(_) => Personality::default(),
+        };
+
+        // Thank-you messages for channel events; an empty value turns one off
+        let mut event_messages = EventMessages::default();
+        for (name, template) in [
+            ("SUB_MESSAGE", &mut event_messages.sub),
+            ("RESUB_MESSAGE", &mut event_messages.resub),
+            ("GIFT_SUB_MESSAGE", &mut event_messages.gift_sub),
+            ("MYSTERY_GIFT_MESSAGE", &mut event_messages.mystery_gift),
+            ("RAID_MESSAGE", &mut event_messages.raid),
+        ] {
+            if let Ok(value) = var(name) {
+                *template = value;
+            }
+        }
+
+        // Optional reaction to "Highlight My Message" redemptions
+        let highlight_message = var("HIGHLIGHT_MESSAGE")
+            .ok()
+            .filter(|message| !message.is_empty());
+
+        // Optional AI prompt read from a file instead of the personality's
+        let ai_prompt_file = var("AI_PROMPT_FILE").ok().filter(|path| !path.is_empty());
+
+        // Optional automatic answers to common questions
+        let faq_file = var("FAQ_FILE").ok().filter(|path| !path.is_empty());
+
+        // Outbound chat pacing, higher when the bot is a moderator
+        let chat_rate_limit = parse_rate_limit(&var, "CHAT_RATE_LIMIT", CHAT_MESSAGES_PER_WINDOW)?;
+        let chat_rate_limit_moderator = parse_rate_limit(
+            &var,
+            "CHAT_RATE_LIMIT_MODERATOR",
+            MODERATOR_CHAT_MESSAGES_PER_WINDOW,
+        )?;
+
+        // TLS is on unless explicitly turned off
+        let irc_tls = match var("IRC_TLS") {
+            Ok(value) => parse_bool(&value)
+                .ok_or_else(|| anyhow::anyhow!("IRC_TLS must be true or false"))?,
+            Err(_) => true,
+        };
+
+        // Let the join settle before the online message
+        let greeting_delay =
+            match var("GREETING_DELAY_SECS") {
+                Ok(secs) => Duration::from_secs(secs.parse().map_err(|_| {
+                    anyhow::anyhow!("GREETING_DELAY_SECS must be a number of seconds")
+                })?),
+                Err(_) => DEFAULT_GREETING_DELAY,
+            };
+        let greeting_jitter = match var("GREETING_JITTER_SECS") {
+            Ok(secs) => Duration::from_secs(secs.parse().map_err(|_| {
+                anyhow::anyhow!("GREETING_JITTER_SECS must be a number of seconds")
+            })?),
+            Err(_) => Duration::ZERO,
+        };
+
+        // Storing chatters' first messages is opt-in for privacy
+        let capture_first_words = match var("CAPTURE_FIRST_WORDS") {
+            Ok(value) => parse_bool(&value)
+                .ok_or_else(|| anyhow::anyhow!("CAPTURE_FIRST_WORDS must be true or false"))?,
+            Err(_) => false,
+        };
+
+        let confirm_delivery = match var("CONFIRM_DELIVERY") {
+            Ok(value) => parse_bool(&value)
+                .ok_or_else(|| anyhow::anyhow!("CONFIRM_DELIVERY must be true or false"))?,
+            Err(_) => false,
+        };
+
+        // Appending moderation events to DATA_DIR/moderation.log is opt-in
+        let moderation_log = match var("MODERATION_LOG") {
+            Ok(value) => parse_bool(&value)
+                .ok_or_else(|| anyhow::anyhow!("MODERATION_LOG must be true or false"))?,
+            Err(_) => false,
+        };
+
+        let eventsub = match var("EVENTSUB_ENABLED") {
+            Ok(value) => parse_bool(&value)
+                .ok_or_else(|| anyhow::anyhow!("EVENTSUB_ENABLED must be true or false"))?,
+            Err(_) => false,
+        };
+        // Reward actions separated by "|", e.g. "Hydrate=say Drink up {username}!"
+        let redemption_actions = match var("REDEMPTION_ACTIONS") {
+            Ok(value) => RedemptionActions::parse(&value)
+                .map_err(|e| anyhow::anyhow!("Invalid REDEMPTION_ACTIONS: {}", e))?,
+            Err(_) => RedemptionActions::default(),
+        };
 
         Ok(Config {
             client_id,
             channel_name,
             bot_username,
+            command_prefix,
+            channel_prefixes,
+            channel_features,
+            command_channels,
+            wrong_channel_reply,
+            prefix_conflict_detection,
             data_dir,
+            store_backend,
+            store_save_interval,
+            token_encryption_key,
+            client_secret,
+            oauth_redirect_uri,
+            ai_api_base,
+            ai_api_key,
+            ai_model,
+            ai_length_strategy,
+            new_account_min_age_days,
+            new_account_action,
+            lockdown,
+            automod,
+            raffle_weights,
+            points_per_interval,
+            points_interval,
+            slots_table,
+            welcome_mode,
+            welcome_grace_period,
+            welcome_enabled,
+            welcome_use_ai,
+            welcome_messages,
+            announcements,
+            announcement_min_chat_messages,
+            announcement_color,
+            silence_prompt_after,
+            silence_prompt,
+            auto_shoutout_users,
+            cooldowns,
+            follower_only_commands,
+            subscriber_only_commands,
+            counters,
+            shoutout_message,
+            eight_ball_format,
+            eight_ball_file,
+            eight_ball_repeat_window,
+            uptime_units,
+            personality,
+            ai_prompt_file,
+            event_messages,
+            highlight_message,
+            faq_file,
+            chat_rate_limit,
+            chat_rate_limit_moderator,
+            irc_tls,
+            greeting_delay,
+            greeting_jitter,
+            capture_first_words,
+            confirm_delivery,
+            moderation_log,
+            eventsub,
+            redemption_actions,
         })
     }
 
@@ -54,7 +917,65 @@ impl Config {
             client_id,
             channel_name,
             bot_username,
+            command_prefix: DEFAULT_COMMAND_PREFIX.to_string(),
+            channel_prefixes: HashMap::new(),
+            channel_features: ChannelFeatures::default(),
+            command_channels: HashMap::new(),
+            wrong_channel_reply: None,
+            prefix_conflict_detection: false,
             data_dir,
+            store_backend: StoreBackend::default(),
+            store_save_interval: Some(DEFAULT_SAVE_INTERVAL),
+            token_encryption_key: None,
+            client_secret: None,
+            oauth_redirect_uri: DEFAULT_REDIRECT_URI.to_string(),
+            ai_api_base: None,
+            ai_api_key: None,
+            ai_model: DEFAULT_AI_MODEL.to_string(),
+            ai_length_strategy: LengthStrategy::default(),
+            new_account_min_age_days: None,
+            new_account_action: NewAccountAction::Flag,
+            lockdown: LockdownSettings::default(),
+            automod: AutomodRules::default(),
+            raffle_weights: RaffleWeights::default(),
+            points_per_interval: DEFAULT_POINTS_PER_INTERVAL,
+            points_interval: DEFAULT_POINTS_INTERVAL,
+            slots_table: SlotsTable::default(),
+            welcome_mode: WelcomeMode::Immediate,
+            welcome_grace_period: Duration::ZERO,
+            welcome_enabled: true,
+            welcome_use_ai: true,
+            welcome_messages: None,
+            announcements: Vec::new(),
+            announcement_min_chat_messages: 0,
+            announcement_color: None,
+            silence_prompt_after: None,
+            silence_prompt: DEFAULT_SILENCE_PROMPT.to_string(),
+            auto_shoutout_users: Vec::new(),
+            cooldowns: CooldownSettings::default(),
+            follower_only_commands: Vec::new(),
+            subscriber_only_commands: Vec::new(),
+            counters: vec!["deaths".to_string()],
+            shoutout_message: DEFAULT_SHOUTOUT_MESSAGE.to_string(),
+            eight_ball_format: DEFAULT_EIGHT_BALL_FORMAT.to_string(),
+            eight_ball_file: None,
+            eight_ball_repeat_window: DEFAULT_EIGHT_BALL_REPEAT_WINDOW,
+            uptime_units: UptimeUnits::default(),
+            personality: Personality::default(),
+            ai_prompt_file: None,
+            event_messages: EventMessages::default(),
+            highlight_message: None,
+            faq_file: None,
+            chat_rate_limit: CHAT_MESSAGES_PER_WINDOW,
+            chat_rate_limit_moderator: MODERATOR_CHAT_MESSAGES_PER_WINDOW,
+            irc_tls: true,
+            greeting_delay: DEFAULT_GREETING_DELAY,
+            greeting_jitter: Duration::ZERO,
+            capture_first_words: false,
+            confirm_delivery: false,
+            moderation_log: false,
+            eventsub: false,
+            redemption_actions: RedemptionActions::default(),
         }
     }
 
@@ -64,9 +985,124 @@ impl Config {
     }
 }
 
+/// Read a messages-per-30-seconds limit, which must be at least 1
+fn parse_rate_limit(
+    var: &impl Fn(&str) -> Result<String, VarError>,
+    name: &str,
+    default: u32,
+) -> Result<u32> {
+    match var(name) {
+        Ok(value) => value
+            .parse()
+            .ok()
+            .filter(|limit| *limit > 0)
+            .ok_or_else(|| anyhow::anyhow!("{} must be a positive number of messages", name)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Parse a boolean setting like "true", "false", "1", "0", "yes", or "no"
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Ask on the terminal which channel to join
+///
+/// # Returns
+/// The channel typed in, or None if the answer was empty
+fn prompt_for_channel() -> Result<Option<String>> {
+    print!("No channel is configured. Which channel should the bot join? ");
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    let channel = answer.trim().trim_start_matches('#');
+    Ok((!channel.is_empty()).then(|| channel.to_string()))
+}
+
+/// Normalize a channel given in the config, like " #Busy_Channel", to its login
+fn channel_key(channel: &str) -> String {
+    channel.trim().trim_start_matches('#').to_lowercase()
+}
+
+/// Parse a comma-separated list of `channel=prefix` pairs
+fn parse_channel_prefixes(value: &str) -> Result<HashMap<String, String>> {
+    parse_list(value)
+        .iter()
+        .map(|entry| match entry.split_once('=') {
+            Some((channel, prefix)) if !channel.trim().is_empty() => {
+                Ok((channel_key(channel), prefix.trim().to_string()))
+            }
+            _ => Err(anyhow::anyhow!(
+                "CHANNEL_PREFIXES entry '{}' must look like channel=prefix",
+                entry
+            )),
+        })
+        .collect()
+}
+
+/// Parse per-channel features like "busy_channel=welcomes+commands,other=commands"
+fn parse_channel_features(value: &str) -> Result<ChannelFeatures> {
+    let channels = parse_list(value)
+        .iter()
+        .map(|entry| match entry.split_once('=') {
+            Some((channel, features)) if !channel.trim().is_empty() => Ok((
+                channel.trim().to_string(),
+                features
+                    .split('+')
+                    .map(str::trim)
+                    .filter(|feature| !feature.is_empty())
+                    .map(str::parse)
+                    .collect::<Result<_>>()?,
+            )),
+            _ => Err(anyhow::anyhow!(
+                "CHANNEL_FEATURES entry '{}' must look like channel=feature+feature",
+                entry
+            )),
+        })
+        .collect::<Result<_>>()?;
+    Ok(ChannelFeatures::new(channels))
+}
+
+/// Parse COMMAND_CHANNELS, a list of command=channel+channel entries
+fn parse_command_channels(value: &str) -> Result<HashMap<String, Vec<String>>> {
+    parse_list(value)
+        .iter()
+        .map(|entry| match entry.split_once('=') {
+            Some((command, channels)) if !command.trim().is_empty() => Ok((
+                command.trim().to_lowercase(),
+                channels
+                    .split('+')
+                    .map(str::trim)
+                    .filter(|channel| !channel.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            )),
+            _ => Err(anyhow::anyhow!(
+                "COMMAND_CHANNELS entry '{}' must look like command=channel+channel",
+                entry
+            )),
+        })
+        .collect()
+}
+
+/// Split a comma-separated list, dropping empty entries
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::features::Feature;
 
     #[test]
     fn test_config_from_env() {
@@ -84,6 +1120,164 @@ mod tests {
         assert_eq!(config.bot_username, "test_bot");
         assert_eq!(config.data_dir, "./test_data");
         assert_eq!(config.get_token_path(), "./test_data/oauth_token.json");
+        assert!(config.token_encryption_key.is_none());
+        assert!(config.ai_api_base.is_none());
+        assert_eq!(config.ai_model, "gpt-4o-mini");
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(parse_list("a, b,,c "), vec!["a", "b", "c"]);
+        assert!(parse_list("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_channel_prefixes() -> Result<()> {
+        let prefixes = parse_channel_prefixes("#Busy_Channel=?, other = ~")?;
+        assert_eq!(prefixes.get("busy_channel").map(String::as_str), Some("?"));
+        assert_eq!(prefixes.get("other").map(String::as_str), Some("~"));
+        assert!(parse_channel_prefixes("busy_channel").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_channel_features() -> Result<()> {
+        let features = parse_channel_features("#Busy=welcomes+filters+commands, other = commands")?;
+        assert!(features.is_enabled("busy", Feature::Filters));
+        assert!(!features.is_enabled("other", Feature::Welcomes));
+        assert!(features.is_enabled("elsewhere", Feature::Welcomes));
+        assert!(parse_channel_features("busy=points")?.is_enabled("busy", Feature::Points));
+        assert!(parse_channel_features("busy=lasers").is_err());
+        assert!(parse_channel_features("welcomes").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_command_channels() -> Result<()> {
+        let channels = parse_command_channels("Deaths=busy+other, quote = busy")?;
+        assert_eq!(channels["deaths"], vec!["busy", "other"]);
+        assert_eq!(channels["quote"], vec!["busy"]);
+        assert!(parse_command_channels("deaths").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_bool() {
+        assert_eq!(parse_bool("true"), Some(true));
+        assert_eq!(parse_bool(" OFF "), Some(false));
+        assert_eq!(parse_bool("0"), Some(false));
+        assert_eq!(parse_bool("maybe"), None);
+    }
+
+    #[test]
+    fn test_config_file_with_env_override() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("bot.toml");
+        std::fs::write(
+            &path,
+            r#"
+client_id = "file_client_id"
+channel = "file_channel"
+bot_username = "file_bot"
+command_prefix = "?"
+welcome_mode = "batch"
+welcome_window_secs = 10
+welcome_grace_secs = 90
+capture_first_words = true
+welcome_use_ai = false
+welcome_messages = ["Hi {username}!", "Welcome, {username} | enjoy the stream"]
+announcements = ["15m Follow the channel!", "1h 18:00-23:00 Check out our sponsor"]
+announcement_min_chat_messages = 5
+
+[channel_prefixes]
+Busy_Channel = "~"
+"#,
+        )?;
+        let file = FileConfig::read(&path)?;
+
+        // The channel is overridden by the environment
+        let config = Config::from_sources(
+            |name| match name {
+                "TWITCH_CHANNEL" => Ok("env_channel".to_string()),
+                _ => file.get(name).ok_or(VarError::NotPresent),
+            },
+            &file,
+            None,
+        )?;
+
+        assert_eq!(config.client_id, "file_client_id");
+        assert_eq!(config.channel_name, "env_channel");
+        assert_eq!(config.bot_username, "file_bot");
+        assert_eq!(config.data_dir, "./data");
+        assert_eq!(config.command_prefix, "?");
+        assert_eq!(
+            config.channel_prefixes,
+            HashMap::from([("busy_channel".to_string(), "~".to_string())])
+        );
+        assert_eq!(
+            config.welcome_mode,
+            WelcomeMode::Batch(Duration::from_secs(10))
+        );
+        assert_eq!(config.welcome_grace_period, Duration::from_secs(90));
+        assert!(config.capture_first_words);
+        assert!(config.welcome_enabled);
+        assert!(!config.welcome_use_ai);
+        assert_eq!(
+            config.welcome_messages,
+            Some(vec![
+                "Hi {username}!".to_string(),
+                // A | in a template from the file isn't a separator
+                "Welcome, {username} | enjoy the stream".to_string()
+            ])
+        );
+        assert_eq!(config.announcements.len(), 2);
+        assert_eq!(config.announcements[1].message, "Check out our sponsor");
+        assert_eq!(config.announcement_min_chat_messages, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_file_rejects_unknown_settings() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("bot.toml");
+        std::fs::write(&path, "chanel = \"typo\"\n")?;
+
+        assert!(FileConfig::read(&path).is_err());
+        assert!(FileConfig::read(&temp_dir.path().join("missing.toml")).is_err());
+
+        // Each announcement is checked as the file is read
+        std::fs::write(&path, "announcements = [\"15m Follow!\", \"10s Spam\"]\n")?;
+        let error = FileConfig::read(&path).unwrap_err().to_string();
+        assert!(error.contains("at most once a minute"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_missing_required_setting() {
+        let result =
+            Config::from_sources(|_| Err(VarError::NotPresent), &FileConfig::default(), None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_config_without_channel() -> Result<()> {
+        let var = |name: &str| match name {
+            "TWITCH_CLIENT_ID" => Ok("client_id".to_string()),
+            "TWITCH_BOT_USERNAME" => Ok("bot".to_string()),
+            _ => Err(VarError::NotPresent),
+        };
+
+        let Err(error) = Config::from_sources(var, &FileConfig::default(), None) else {
+            panic!("a config without a channel should be rejected");
+        };
+        let error = error.to_string();
+        assert!(error.contains("--channel"));
+        assert!(error.contains("TWITCH_CHANNEL"));
+
+        // --channel is enough on its own
+        let config = Config::from_sources(var, &FileConfig::default(), Some("cli_channel"))?;
+        assert_eq!(config.channel_name, "cli_channel");
+        Ok(())
     }
 
     // We are skipping this test for now because we don't want to interfere with the system
@@ -0,0 +1,159 @@
+//! Named counters (e.g. a death counter), kept in the shared store
+
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+
+use crate::store::Store;
+
+/// Store namespace counters are kept under, keyed by name
+const NAMESPACE: &str = "counters";
+
+/// Named counters persisted in the store
+pub struct Counters {
+    /// Where the counts are kept
+    store: Arc<dyn Store>,
+    /// Serializes read-modify-write updates
+    update: Mutex<()>,
+}
+
+impl Counters {
+    /// Create counters kept in a store
+    ///
+    /// # Arguments
+    /// * `store` - Where the counts are kept
+    ///
+    /// # Returns
+    /// A new Counters instance
+    pub fn new(store: Arc<dyn Store>) -> Self {
+        Counters {
+            store,
+            update: Mutex::new(()),
+        }
+    }
+
+    /// Check whether a name is usable as a counter (and command) name
+    ///
+    /// # Arguments
+    /// * `name` - The proposed name
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name.len() <= 25
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    }
+
+    /// Get the names of all counters
+    pub fn names(&self) -> Result<Vec<String>> {
+        Ok(self
+            .store
+            .list(NAMESPACE)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    /// Check whether a counter exists
+    ///
+    /// # Arguments
+    /// * `name` - The counter's name
+    pub fn exists(&self, name: &str) -> Result<bool> {
+        Ok(self.store.get(NAMESPACE, name)?.is_some())
+    }
+
+    /// Create a counter at zero, leaving an existing one untouched
+    ///
+    /// # Arguments
+    /// * `name` - The counter's name
+    ///
+    /// # Returns
+    /// true if the counter was created
+    pub fn create(&self, name: &str) -> Result<bool> {
+        let _update = self.update.lock().unwrap();
+        if self.exists(name)? {
+            return Ok(false);
+        }
+        self.store.set_as(NAMESPACE, name, &0i64)?;
+        Ok(true)
+    }
+
+    /// Delete a counter
+    ///
+    /// # Arguments
+    /// * `name` - The counter's name
+    ///
+    /// # Returns
+    /// true if the counter existed
+    pub fn delete(&self, name: &str) -> Result<bool> {
+        let _update = self.update.lock().unwrap();
+        self.store.remove(NAMESPACE, name)
+    }
+
+    /// Get a counter's value
+    ///
+    /// # Arguments
+    /// * `name` - The counter's name
+    pub fn get(&self, name: &str) -> Result<i64> {
+        Ok(self.store.get_as(NAMESPACE, name)?.unwrap_or(0))
+    }
+
+    /// Add to (or subtract from) a counter
+    ///
+    /// # Arguments
+    /// * `name` - The counter's name
+    /// * `delta` - How much to change the counter by
+    ///
+    /// # Returns
+    /// The new value
+    pub fn add(&self, name: &str, delta: i64) -> Result<i64> {
+        let _update = self.update.lock().unwrap();
+        let value = self.get(name)?.saturating_add(delta);
+        self.store.set_as(NAMESPACE, name, &value)?;
+        Ok(value)
+    }
+
+    /// Set a counter's value
+    ///
+    /// # Arguments
+    /// * `name` - The counter's name
+    /// * `value` - The new value
+    pub fn set(&self, name: &str, value: i64) -> Result<()> {
+        let _update = self.update.lock().unwrap();
+        self.store.set_as(NAMESPACE, name, &value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{StoreBackend, open_store};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_counters_persist() -> Result<()> {
+        let temp_dir = tempdir()?;
+
+        let counters = Counters::new(open_store(StoreBackend::File, temp_dir.path(), None)?);
+        assert!(counters.create("deaths")?);
+        assert!(!counters.create("deaths")?);
+        assert_eq!(counters.add("deaths", 1)?, 1);
+        assert_eq!(counters.add("deaths", 2)?, 3);
+        counters.set("wins", 5)?;
+
+        let counters = Counters::new(open_store(StoreBackend::File, temp_dir.path(), None)?);
+        assert_eq!(counters.get("deaths")?, 3);
+        assert_eq!(counters.names()?, vec!["deaths", "wins"]);
+        assert!(counters.delete("wins")?);
+        assert!(!counters.exists("wins")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_valid_names() {
+        assert!(Counters::is_valid_name("deaths"));
+        assert!(Counters::is_valid_name("boss_2"));
+        assert!(!Counters::is_valid_name(""));
+        assert!(!Counters::is_valid_name("Deaths"));
+        assert!(!Counters::is_valid_name("no spaces"));
+    }
+}
@@ -0,0 +1,237 @@
+//! Parsing and formatting of human-friendly durations like "10m" or "1h30m"
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Datelike, Utc};
+use std::time::Duration;
+
+/// The longest duration `parse_duration` accepts, far more than any
+/// mute, timeout, or interval needs and small enough to add to any instant
+const MAX_DURATION: Duration = Duration::from_secs(365 * 86400);
+
+/// Parse a duration like "30s", "10m", "2h", "1d", or "1h30m"
+///
+/// A bare number is taken as seconds. Durations over a year are rejected.
+///
+/// # Arguments
+/// * `input` - The text to parse
+///
+/// # Returns
+/// A Result containing the parsed Duration, or an error if the text is invalid or too long
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim().to_lowercase();
+    if input.is_empty() {
+        return Err(anyhow!("Empty duration"));
+    }
+
+    let too_long = || {
+        anyhow!(
+            "{} is longer than {} days",
+            input,
+            MAX_DURATION.as_secs() / 86400
+        )
+    };
+
+    // A plain number of seconds
+    if input.chars().all(|c| c.is_ascii_digit()) {
+        let secs: u64 = input.parse().map_err(|_| too_long())?;
+        return match Duration::from_secs(secs) {
+            duration if duration <= MAX_DURATION => Ok(duration),
+            _ => Err(too_long()),
+        };
+    }
+
+    let mut total = 0u64;
+    let mut number = String::new();
+
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let multiplier = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return Err(anyhow!("Invalid duration unit '{}' in {}", c, input)),
+        };
+        if number.is_empty() {
+            return Err(anyhow!("Missing number before '{}' in {}", c, input));
+        }
+        total = number
+            .parse::<u64>()
+            .ok()
+            .and_then(|value| value.checked_mul(multiplier))
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(too_long)?;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return Err(anyhow!("Missing unit after {} in {}", number, input));
+    }
+
+    let duration = Duration::from_secs(total);
+    if duration > MAX_DURATION {
+        return Err(too_long());
+    }
+    Ok(duration)
+}
+
+/// Format a duration compactly, like "1h 30m" or "45s"
+///
+/// # Arguments
+/// * `duration` - The duration to format
+///
+/// # Returns
+/// The formatted duration, leaving out zero units
+pub fn format_duration(duration: Duration) -> String {
+    let total = duration.as_secs();
+    let parts = [
+        (total / 86400, "d"),
+        ((total % 86400) / 3600, "h"),
+        ((total % 3600) / 60, "m"),
+        (total % 60, "s"),
+    ];
+
+    let formatted: Vec<String> = parts
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect();
+
+    if formatted.is_empty() {
+        "0s".to_string()
+    } else {
+        formatted.join(" ")
+    }
+}
+
+/// Describe how long ago something happened, like "3 months ago"
+///
+/// Only the largest unit is used, so the result reads naturally in chat.
+///
+/// # Arguments
+/// * `elapsed` - How much time has passed
+///
+/// # Returns
+/// The relative time, or "just now" for less than a minute
+pub fn format_ago(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    let (value, unit) = match secs {
+        0..60 => return "just now".to_string(),
+        60..3600 => (secs / 60, "minute"),
+        3600..86400 => (secs / 3600, "hour"),
+        86400..2_592_000 => (secs / 86400, "day"),
+        2_592_000..31_536_000 => (secs / 2_592_000, "month"),
+        _ => (secs / 31_536_000, "year"),
+    };
+
+    let plural = if value == 1 { "" } else { "s" };
+    format!("{} {}{} ago", value, unit, plural)
+}
+
+/// Format a count with its unit, like "1 year" or "3 days"
+fn plural(count: i64, unit: &str) -> String {
+    if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
+
+/// Describe how long it has been since a date in calendar terms, like "2 years, 3 months"
+///
+/// # Arguments
+/// * `since` - When the period started, like an account's creation
+/// * `now` - The current time
+///
+/// # Returns
+/// The time in years and months, or in days when it's under a month
+pub fn format_calendar_age(since: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let created = since.date_naive();
+    let today = now.date_naive();
+
+    let mut months =
+        (today.year() - created.year()) as i64 * 12 + today.month() as i64 - created.month() as i64;
+    if today.day() < created.day() {
+        months -= 1;
+    }
+
+    if months < 1 {
+        let days = (today - created).num_days();
+        return if days < 1 {
+            "less than a day".to_string()
+        } else {
+            plural(days, "day")
+        };
+    }
+
+    match (months / 12, months % 12) {
+        (0, months) => plural(months, "month"),
+        (years, 0) => plural(years, "year"),
+        (years, months) => format!("{}, {}", plural(years, "year"), plural(months, "month")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("10m").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_duration("45").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_duration("1H30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(86400));
+
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("1h30").is_err());
+
+        // Enormous values are refused rather than overflowing
+        assert_eq!(parse_duration("365d").unwrap(), MAX_DURATION);
+        assert!(parse_duration("366d").is_err());
+        assert!(parse_duration("18446744073709551615").is_err());
+        assert!(parse_duration("99999999999999999999").is_err());
+        assert!(parse_duration("99999999999h").is_err());
+        assert!(parse_duration("5124095576030431h5124095576030431h").is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(600)), "10m");
+        assert_eq!(format_duration(Duration::from_secs(5400)), "1h 30m");
+        assert_eq!(format_duration(Duration::from_secs(90061)), "1d 1h 1m 1s");
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+    }
+
+    #[test]
+    fn test_format_ago() {
+        assert_eq!(format_ago(Duration::from_secs(30)), "just now");
+        assert_eq!(format_ago(Duration::from_secs(60)), "1 minute ago");
+        assert_eq!(
+            format_ago(Duration::from_secs(5 * 3600 + 59)),
+            "5 hours ago"
+        );
+        assert_eq!(format_ago(Duration::from_secs(86400)), "1 day ago");
+        assert_eq!(format_ago(Duration::from_secs(95 * 86400)), "3 months ago");
+        assert_eq!(format_ago(Duration::from_secs(800 * 86400)), "2 years ago");
+    }
+
+    #[test]
+    fn test_format_calendar_age() {
+        let now = Utc.with_ymd_and_hms(2024, 10, 15, 12, 0, 0).unwrap();
+        let age =
+            |y, m, d| format_calendar_age(Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap(), now);
+
+        assert_eq!(age(2016, 12, 14), "7 years, 10 months");
+        assert_eq!(age(2023, 10, 15), "1 year");
+        assert_eq!(age(2024, 8, 20), "1 month");
+        assert_eq!(age(2024, 9, 20), "25 days");
+        assert_eq!(age(2024, 10, 15), "less than a day");
+    }
+}
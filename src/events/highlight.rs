@@ -0,0 +1,327 @@
+//! Reactions to "Highlight My Message" channel point redemptions
+//!
+//! A highlight shows up twice: as the chat message itself and as a channel
+//! point redemption event. The `HighlightCorrelator` pairs the two by sender
+//! and text so the bot reacts exactly once, whichever arrives first. Messages
+//! carrying the `msg-id=highlighted-message` IRC tag are recognized directly;
+//! redemption events come over EventSub when it's turned on.
+
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::info;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::twitch::MessageSender;
+
+/// How long a message and its redemption may be apart and still be paired
+const CORRELATION_WINDOW: Duration = Duration::from_secs(30);
+
+/// IRC `msg-id` tag value Twitch puts on highlighted messages
+const HIGHLIGHTED_MESSAGE_TAG: &str = "highlighted-message";
+
+/// A chat message that was highlighted with channel points
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    /// The channel the message was sent in
+    pub channel: String,
+    /// ID of the user who redeemed the highlight
+    pub user_id: String,
+    /// Display name of the user who redeemed the highlight
+    pub username: String,
+    /// The highlighted text
+    pub text: String,
+}
+
+/// A recent chat message kept for pairing with a later redemption
+struct RecentMessage {
+    highlight: Highlight,
+    received: Instant,
+    /// Whether the bot already reacted (via the IRC tag)
+    reacted: bool,
+}
+
+/// A redemption waiting for its chat message
+struct PendingRedemption {
+    user_id: String,
+    text: String,
+    received: Instant,
+}
+
+/// Pairs highlighted chat messages with their channel point redemptions
+#[derive(Default)]
+pub struct HighlightCorrelator {
+    messages: VecDeque<RecentMessage>,
+    redemptions: VecDeque<PendingRedemption>,
+}
+
+impl HighlightCorrelator {
+    /// Create an empty correlator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop entries older than the correlation window
+    fn prune(&mut self, now: Instant) {
+        self.messages
+            .retain(|m| now.duration_since(m.received) < CORRELATION_WINDOW);
+        self.redemptions
+            .retain(|r| now.duration_since(r.received) < CORRELATION_WINDOW);
+    }
+
+    /// Process a chat message
+    ///
+    /// # Arguments
+    /// * `msg` - The chat message
+    ///
+    /// # Returns
+    /// The highlight to react to, if this message is a new highlight
+    pub fn on_message(&mut self, msg: &PrivmsgMessage) -> Option<Highlight> {
+        let now = Instant::now();
+        self.prune(now);
+
+        let highlight = Highlight {
+            channel: msg.channel_login.clone(),
+            user_id: msg.sender.id.clone(),
+            username: msg.sender.name.clone(),
+            text: msg.message_text.trim().to_string(),
+        };
+
+        // The redemption arrived first
+        if let Some(index) = self
+            .redemptions
+            .iter()
+            .position(|r| r.user_id == highlight.user_id && r.text == highlight.text)
+        {
+            self.redemptions.remove(index);
+            return Some(highlight);
+        }
+
+        let tagged = is_tagged_highlight(msg);
+        self.messages.push_back(RecentMessage {
+            highlight: highlight.clone(),
+            received: now,
+            reacted: tagged,
+        });
+
+        tagged.then_some(highlight)
+    }
+
+    /// Process a "Highlight My Message" redemption event
+    ///
+    /// # Arguments
+    /// * `user_id` - ID of the user who redeemed the highlight
+    /// * `text` - The text they entered
+    ///
+    /// # Returns
+    /// The highlight to react to, if its message arrived already and hasn't been reacted to
+    pub fn on_redemption(&mut self, user_id: &str, text: &str) -> Option<Highlight> {
+        let now = Instant::now();
+        self.prune(now);
+        let text = text.trim();
+
+        let message = self
+            .messages
+            .iter_mut()
+            .rev()
+            .find(|m| m.highlight.user_id == user_id && m.highlight.text == text);
+
+        match message {
+            Some(message) if message.reacted => None,
+            Some(message) => {
+                message.reacted = true;
+                Some(message.highlight.clone())
+            }
+            None => {
+                self.redemptions.push_back(PendingRedemption {
+                    user_id: user_id.to_string(),
+                    text: text.to_string(),
+                    received: now,
+                });
+                None
+            }
+        }
+    }
+}
+
+/// Check whether Twitch tagged a chat message as highlighted
+fn is_tagged_highlight(msg: &PrivmsgMessage) -> bool {
+    matches!(
+        msg.source.tags.0.get("msg-id"),
+        Some(Some(id)) if id == HIGHLIGHTED_MESSAGE_TAG
+    )
+}
+
+/// Format the reaction to a highlight
+///
+/// # Arguments
+/// * `template` - The message template ({username} and {message} are replaced)
+/// * `highlight` - The highlighted message
+///
+/// # Returns
+/// The formatted reaction
+pub fn format_highlight_reaction(template: &str, highlight: &Highlight) -> String {
+    template
+        .replace("{username}", &highlight.username)
+        .replace("{message}", &highlight.text)
+}
+
+/// Reacts in chat when someone highlights a message with channel points
+pub struct HighlightService {
+    /// The Twitch client for sending messages
+    client: Arc<dyn MessageSender>,
+    /// Pairs messages with redemptions
+    correlator: Mutex<HighlightCorrelator>,
+    /// The reaction template ({username} and {message} are replaced)
+    template: String,
+}
+
+impl HighlightService {
+    /// Create a new highlight service
+    ///
+    /// # Arguments
+    /// * `client` - The Twitch client for sending messages
+    /// * `template` - The reaction template ({username} and {message} are replaced)
+    ///
+    /// # Returns
+    /// A new HighlightService instance
+    pub fn new(client: Arc<dyn MessageSender>, template: String) -> Self {
+        HighlightService {
+            client,
+            correlator: Mutex::new(HighlightCorrelator::new()),
+            template,
+        }
+    }
+
+    /// React if a chat message is a highlight
+    ///
+    /// # Arguments
+    /// * `msg` - The chat message to process
+    ///
+    /// # Returns
+    /// The highlight that was reacted to, if any
+    pub async fn process_message(&self, msg: &PrivmsgMessage) -> Result<Option<Highlight>> {
+        let highlight = self.correlator.lock().unwrap().on_message(msg);
+        self.react(highlight).await
+    }
+
+    /// React to a "Highlight My Message" redemption once its message is known
+    ///
+    /// # Arguments
+    /// * `user_id` - ID of the user who redeemed the highlight
+    /// * `text` - The text they entered
+    ///
+    /// # Returns
+    /// The highlight that was reacted to, if any
+    pub async fn process_redemption(&self, user_id: &str, text: &str) -> Result<Option<Highlight>> {
+        let highlight = self.correlator.lock().unwrap().on_redemption(user_id, text);
+        self.react(highlight).await
+    }
+
+    async fn react(&self, highlight: Option<Highlight>) -> Result<Option<Highlight>> {
+        let Some(highlight) = highlight else {
+            return Ok(None);
+        };
+
+        info!("{} highlighted a message", highlight.username);
+        let message = format_highlight_reaction(&self.template, &highlight);
+        self.client.say(&highlight.channel, &message).await?;
+
+        Ok(Some(highlight))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::create_test_privmsg_with_badges;
+    use crate::users::MockTwitchClient;
+
+    fn highlighted_privmsg(text: &str) -> PrivmsgMessage {
+        let mut msg = create_test_privmsg_with_badges(text, &[]);
+        msg.source.tags.0.insert(
+            "msg-id".to_string(),
+            Some(HIGHLIGHTED_MESSAGE_TAG.to_string()),
+        );
+        msg
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_message_then_redemption() {
+        let mut correlator = HighlightCorrelator::new();
+        let msg = create_test_privmsg_with_badges("look at me!", &[]);
+
+        assert!(correlator.on_message(&msg).is_none());
+        let highlight = correlator.on_redemption("123", "look at me!").unwrap();
+        assert_eq!(highlight.username, "Test_User");
+        assert_eq!(highlight.text, "look at me!");
+
+        // A repeated event doesn't react twice
+        assert!(correlator.on_redemption("123", "look at me!").is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_redemption_then_message() {
+        let mut correlator = HighlightCorrelator::new();
+
+        assert!(correlator.on_redemption("123", "look at me!").is_none());
+        let msg = create_test_privmsg_with_badges("look at me!", &[]);
+        assert!(correlator.on_message(&msg).is_some());
+
+        // Other messages from the same user aren't highlights
+        let msg = create_test_privmsg_with_badges("another message", &[]);
+        assert!(correlator.on_message(&msg).is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_unrelated_or_late_redemption_is_ignored() {
+        let mut correlator = HighlightCorrelator::new();
+        let msg = create_test_privmsg_with_badges("look at me!", &[]);
+        correlator.on_message(&msg);
+
+        // Different user
+        assert!(correlator.on_redemption("999", "look at me!").is_none());
+
+        // Too late to pair with the message
+        tokio::time::advance(CORRELATION_WINDOW).await;
+        assert!(correlator.on_redemption("123", "look at me!").is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tagged_message_reacts_once() {
+        let mut correlator = HighlightCorrelator::new();
+
+        assert!(correlator.on_message(&highlighted_privmsg("hi!")).is_some());
+        // The redemption event for the same message was already handled
+        assert!(correlator.on_redemption("123", "hi!").is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_highlight_service_reacts() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let service = HighlightService::new(
+            Arc::new(client.clone()),
+            "{username} highlighted: {message}".to_string(),
+        );
+
+        let msg = create_test_privmsg_with_badges("hello chat", &[]);
+        assert!(service.process_message(&msg).await?.is_none());
+        assert!(
+            service
+                .process_redemption("123", "hello chat")
+                .await?
+                .is_some()
+        );
+
+        assert_eq!(
+            client.sent_messages(),
+            vec![(
+                "test_channel".to_string(),
+                "Test_User highlighted: hello chat".to_string()
+            )]
+        );
+        Ok(())
+    }
+}
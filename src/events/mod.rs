@@ -0,0 +1,591 @@
+//! Channel events such as subscriptions, gift subs, and raids
+//!
+//! Twitch announces these with USERNOTICE messages in chat and, when EventSub
+//! is on, again over EventSub. Both are converted into `ChannelEvent`s and the
+//! `EventService` thanks the people involved, once per event whichever source
+//! reports it first.
+
+mod highlight;
+
+pub use highlight::HighlightService;
+
+use anyhow::Result;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, info};
+use twitch_irc::message::{UserNoticeEvent, UserNoticeMessage};
+
+use crate::twitch::{EventSubEvent, MessageSender};
+
+/// How long after an event the same event reported by the other source is ignored
+const DUPLICATE_WINDOW: Duration = Duration::from_secs(120);
+
+/// The login Twitch sends anonymous gifts from, which anonymous batches are tracked under
+const ANONYMOUS_GIFTER: &str = "ananonymousgifter";
+
+/// A channel event the bot can react to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelEvent {
+    /// A new subscription or a resubscription
+    Subscription {
+        /// Display name of the subscriber
+        username: String,
+        /// Total months subscribed
+        months: u64,
+        /// Consecutive months subscribed, if the subscriber shared it
+        streak_months: Option<u64>,
+        /// Subscription plan ("Prime", "1000", "2000", or "3000")
+        plan: String,
+        /// Whether this is a resubscription
+        is_resub: bool,
+    },
+    /// A sub gifted to a specific user
+    GiftSub {
+        /// Display name of the gifter (None if anonymous)
+        gifter: Option<String>,
+        /// Display name of the recipient
+        recipient: String,
+        /// Number of months gifted at once
+        months: u64,
+    },
+    /// A batch of subs gifted to random viewers
+    MysteryGift {
+        /// Display name of the gifter (None if anonymous)
+        gifter: Option<String>,
+        /// Login of the gifter (None if anonymous)
+        gifter_login: Option<String>,
+        /// Number of subs gifted
+        count: u64,
+    },
+    /// An incoming raid
+    Raid {
+        /// Display name of the raiding broadcaster
+        username: String,
+        /// Login of the raiding broadcaster
+        login: String,
+        /// Number of viewers brought along
+        viewer_count: u64,
+    },
+}
+
+impl ChannelEvent {
+    /// Convert a USERNOTICE into a channel event
+    ///
+    /// # Arguments
+    /// * `msg` - The USERNOTICE message
+    ///
+    /// # Returns
+    /// The event, or None for notices the bot doesn't react to
+    pub fn from_user_notice(msg: &UserNoticeMessage) -> Option<Self> {
+        match &msg.event {
+            UserNoticeEvent::SubOrResub {
+                is_resub,
+                cumulative_months,
+                streak_months,
+                sub_plan,
+                ..
+            } => Some(ChannelEvent::Subscription {
+                username: msg.sender.name.clone(),
+                months: *cumulative_months,
+                streak_months: *streak_months,
+                plan: sub_plan.clone(),
+                is_resub: *is_resub,
+            }),
+            UserNoticeEvent::SubGift {
+                is_sender_anonymous,
+                recipient,
+                num_gifted_months,
+                ..
+            } => Some(ChannelEvent::GiftSub {
+                gifter: (!is_sender_anonymous).then(|| msg.sender.name.clone()),
+                recipient: recipient.name.clone(),
+                months: *num_gifted_months,
+            }),
+            UserNoticeEvent::SubMysteryGift {
+                mass_gift_count, ..
+            } => Some(ChannelEvent::MysteryGift {
+                gifter: Some(msg.sender.name.clone()),
+                gifter_login: Some(msg.sender.login.clone()),
+                count: *mass_gift_count,
+            }),
+            UserNoticeEvent::AnonSubMysteryGift {
+                mass_gift_count, ..
+            } => Some(ChannelEvent::MysteryGift {
+                gifter: None,
+                gifter_login: None,
+                count: *mass_gift_count,
+            }),
+            UserNoticeEvent::Raid { viewer_count, .. } => Some(ChannelEvent::Raid {
+                username: msg.sender.name.clone(),
+                login: msg.sender.login.clone(),
+                viewer_count: *viewer_count,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Convert an EventSub event into a channel event
+    ///
+    /// # Arguments
+    /// * `event` - The EventSub event
+    ///
+    /// # Returns
+    /// The event, or None for events thanked elsewhere or not at all
+    pub fn from_eventsub(event: &EventSubEvent) -> Option<Self> {
+        match event {
+            // Gifted subs are thanked through the gifter's USERNOTICE
+            EventSubEvent::Subscribe { is_gift: true, .. } => None,
+            EventSubEvent::Subscribe {
+                user_name, tier, ..
+            } => Some(ChannelEvent::Subscription {
+                username: user_name.clone(),
+                months: 1,
+                streak_months: None,
+                plan: tier.clone(),
+                is_resub: false,
+            }),
+            EventSubEvent::Raid {
+                from_login,
+                from_name,
+                viewers,
+            } => Some(ChannelEvent::Raid {
+                username: from_name.clone(),
+                login: from_login.clone(),
+                viewer_count: *viewers,
+            }),
+            EventSubEvent::Redemption(_) | EventSubEvent::Highlight { .. } => None,
+        }
+    }
+
+    /// Identify an event that both IRC and EventSub report
+    ///
+    /// # Returns
+    /// A key shared by both reports, or None if only one source reports the event
+    fn duplicate_key(&self) -> Option<String> {
+        match self {
+            ChannelEvent::Subscription {
+                username,
+                is_resub: false,
+                ..
+            } => Some(format!("sub:{}", username.to_lowercase())),
+            ChannelEvent::Raid { login, .. } => Some(format!("raid:{}", login.to_lowercase())),
+            _ => None,
+        }
+    }
+}
+
+/// Thank-you message templates for channel events
+///
+/// An empty template turns off thank-yous for that event.
+#[derive(Debug, Clone)]
+pub struct EventMessages {
+    /// New subscriptions (placeholders: {username}, {plan})
+    pub sub: String,
+    /// Resubscriptions (placeholders: {username}, {months}, {plan})
+    pub resub: String,
+    /// Gift subs (placeholders: {gifter}, {recipient}, {months})
+    pub gift_sub: String,
+    /// Batches of gift subs (placeholders: {gifter}, {count})
+    pub mystery_gift: String,
+    /// Raids (placeholders: {username}, {viewers})
+    pub raid: String,
+}
+
+impl Default for EventMessages {
+    fn default() -> Self {
+        EventMessages {
+            sub: "Thank you for subscribing, {username}!".to_string(),
+            resub: "Thank you for resubscribing for {months} months, {username}!".to_string(),
+            gift_sub: "Thank you {gifter} for gifting a sub to {recipient}!".to_string(),
+            mystery_gift: "Thank you {gifter} for gifting {count} subs!".to_string(),
+            raid: "Thank you for the raid, {username}!".to_string(),
+        }
+    }
+}
+
+impl EventMessages {
+    /// Format the thank-you message for an event
+    ///
+    /// # Arguments
+    /// * `event` - The event to thank
+    ///
+    /// # Returns
+    /// The message, or None if thank-yous are turned off for the event
+    pub fn format(&self, event: &ChannelEvent) -> Option<String> {
+        let message = match event {
+            ChannelEvent::Subscription {
+                username,
+                months,
+                plan,
+                is_resub,
+                ..
+            } => {
+                let template = if *is_resub { &self.resub } else { &self.sub };
+                template
+                    .replace("{username}", username)
+                    .replace("{months}", &months.to_string())
+                    .replace("{plan}", plan)
+            }
+            ChannelEvent::GiftSub {
+                gifter,
+                recipient,
+                months,
+            } => self
+                .gift_sub
+                .replace("{gifter}", gifter.as_deref().unwrap_or("Anonymous"))
+                .replace("{recipient}", recipient)
+                .replace("{months}", &months.to_string()),
+            ChannelEvent::MysteryGift { gifter, count, .. } => self
+                .mystery_gift
+                .replace("{gifter}", gifter.as_deref().unwrap_or("Anonymous"))
+                .replace("{count}", &count.to_string()),
+            ChannelEvent::Raid {
+                username,
+                viewer_count,
+                ..
+            } => self
+                .raid
+                .replace("{username}", username)
+                .replace("{viewers}", &viewer_count.to_string()),
+        };
+
+        (!message.trim().is_empty()).then_some(message)
+    }
+}
+
+/// Service that thanks subscribers, gifters, and raiders
+pub struct EventService {
+    /// The client for sending messages
+    client: Arc<dyn MessageSender>,
+    /// Thank-you message templates
+    messages: EventMessages,
+    /// Gift subs still expected from each gifter's batch, keyed by login
+    /// (anonymous batches under `ANONYMOUS_GIFTER`)
+    pending_gifts: Mutex<HashMap<String, u64>>,
+    /// Events handled recently, to drop the same event from the other source
+    recent: Mutex<VecDeque<(String, Instant)>>,
+}
+
+impl EventService {
+    /// Create a new event service
+    ///
+    /// # Arguments
+    /// * `client` - The Twitch client for sending messages
+    /// * `messages` - The thank-you message templates
+    ///
+    /// # Returns
+    /// A new EventService instance
+    pub fn new(client: Arc<dyn MessageSender>, messages: EventMessages) -> Self {
+        EventService {
+            client,
+            messages,
+            pending_gifts: Mutex::new(HashMap::new()),
+            recent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Process a USERNOTICE, thanking the people involved
+    ///
+    /// # Arguments
+    /// * `msg` - The USERNOTICE message
+    ///
+    /// # Returns
+    /// The event the notice described, if the bot reacts to it
+    pub async fn process_notice(&self, msg: &UserNoticeMessage) -> Result<Option<ChannelEvent>> {
+        let Some(event) = ChannelEvent::from_user_notice(msg) else {
+            debug!("Ignoring USERNOTICE: {}", msg.system_message);
+            return Ok(None);
+        };
+
+        if self.is_part_of_gift_batch(msg, &event) {
+            info!("[EVENT] {:?}", event);
+            debug!("Gift sub is part of an already thanked batch");
+            return Ok(Some(event));
+        }
+
+        self.process_event(&msg.channel_login, event).await
+    }
+
+    /// Handle a channel event from IRC or EventSub, thanking the people involved
+    ///
+    /// # Arguments
+    /// * `channel` - The channel the event happened in
+    /// * `event` - The event
+    ///
+    /// # Returns
+    /// The event, or None if the other source already reported it
+    pub async fn process_event(
+        &self,
+        channel: &str,
+        event: ChannelEvent,
+    ) -> Result<Option<ChannelEvent>> {
+        if self.is_duplicate(&event) {
+            debug!("Already handled {:?}", event);
+            return Ok(None);
+        }
+
+        info!("[EVENT] {:?}", event);
+        if let Some(message) = self.messages.format(&event) {
+            self.client.say(channel, &message).await?;
+        }
+
+        Ok(Some(event))
+    }
+
+    /// Check whether an event was already handled, remembering it if not
+    fn is_duplicate(&self, event: &ChannelEvent) -> bool {
+        let Some(key) = event.duplicate_key() else {
+            return false;
+        };
+
+        let mut recent = self.recent.lock().unwrap();
+        while recent
+            .front()
+            .is_some_and(|(_, handled)| handled.elapsed() >= DUPLICATE_WINDOW)
+        {
+            recent.pop_front();
+        }
+
+        if recent.iter().any(|(handled, _)| *handled == key) {
+            return true;
+        }
+        recent.push_back((key, Instant::now()));
+        false
+    }
+
+    /// Track batches of gift subs so each batch is thanked once
+    ///
+    /// A batch is announced first, followed by one gift sub notice per recipient.
+    ///
+    /// # Returns
+    /// true if the event is a gift sub already covered by a batch thank-you
+    fn is_part_of_gift_batch(&self, msg: &UserNoticeMessage, event: &ChannelEvent) -> bool {
+        let mut pending = self.pending_gifts.lock().unwrap();
+
+        match event {
+            ChannelEvent::MysteryGift {
+                gifter_login,
+                count,
+                ..
+            } => {
+                let gifter = gifter_login.as_deref().unwrap_or(ANONYMOUS_GIFTER);
+                *pending.entry(gifter.to_string()).or_default() += count;
+                false
+            }
+            ChannelEvent::GiftSub { gifter, .. } => {
+                let gifter = match gifter {
+                    Some(_) => msg.sender.login.as_str(),
+                    None => ANONYMOUS_GIFTER,
+                };
+                match pending.get_mut(gifter) {
+                    Some(remaining) => {
+                        *remaining -= 1;
+                        if *remaining == 0 {
+                            pending.remove(gifter);
+                        }
+                        true
+                    }
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::users::MockTwitchClient;
+    use twitch_irc::message::IRCMessage;
+
+    fn parse_notice(raw: &str) -> UserNoticeMessage {
+        UserNoticeMessage::try_from(IRCMessage::parse(raw).unwrap()).unwrap()
+    }
+
+    fn resub_notice() -> UserNoticeMessage {
+        parse_notice(
+            "@badge-info=subscriber/14;badges=subscriber/12;color=;display-name=SubUser;emotes=;flags=;id=1;login=subuser;mod=0;msg-id=resub;msg-param-cumulative-months=14;msg-param-should-share-streak=1;msg-param-streak-months=3;msg-param-sub-plan-name=Channel\\sSub;msg-param-sub-plan=1000;room-id=456;subscriber=1;system-msg=SubUser\\ssubscribed;tmi-sent-ts=1594583782376;user-id=100;user-type= :tmi.twitch.tv USERNOTICE #test_channel :Still here!",
+        )
+    }
+
+    fn raid_notice() -> UserNoticeMessage {
+        parse_notice(
+            "@badge-info=;badges=;color=;display-name=Raider;emotes=;flags=;id=2;login=raider;mod=0;msg-id=raid;msg-param-displayName=Raider;msg-param-login=raider;msg-param-profileImageURL=https://example.com/%s.png;msg-param-viewerCount=42;room-id=456;subscriber=0;system-msg=42\\sraiders;tmi-sent-ts=1594583782376;user-id=101;user-type= :tmi.twitch.tv USERNOTICE #test_channel",
+        )
+    }
+
+    fn mystery_gift_notice(count: u64) -> UserNoticeMessage {
+        parse_notice(&format!(
+            "@badge-info=;badges=;color=;display-name=Gifter;emotes=;flags=;id=3;login=gifter;mod=0;msg-id=submysterygift;msg-param-mass-gift-count={count};msg-param-sender-count={count};msg-param-sub-plan=1000;room-id=456;subscriber=0;system-msg=gifts;tmi-sent-ts=1594583782376;user-id=102;user-type= :tmi.twitch.tv USERNOTICE #test_channel"
+        ))
+    }
+
+    fn gift_notice(recipient: &str) -> UserNoticeMessage {
+        parse_notice(&format!(
+            "@badge-info=;badges=;color=;display-name=Gifter;emotes=;flags=;id=4;login=gifter;mod=0;msg-id=subgift;msg-param-months=1;msg-param-recipient-display-name={recipient};msg-param-recipient-id=200;msg-param-recipient-user-name={login};msg-param-sub-plan-name=Channel\\sSub;msg-param-sub-plan=1000;msg-param-gift-months=1;room-id=456;subscriber=0;system-msg=gift;tmi-sent-ts=1594583782376;user-id=102;user-type= :tmi.twitch.tv USERNOTICE #test_channel",
+            login = recipient.to_lowercase()
+        ))
+    }
+
+    fn anonymous_mystery_gift_notice(count: u64) -> UserNoticeMessage {
+        parse_notice(&format!(
+            "@badge-info=;badges=;color=;display-name=AnAnonymousGifter;emotes=;flags=;id=5;login=ananonymousgifter;mod=0;msg-id=submysterygift;msg-param-mass-gift-count={count};msg-param-sub-plan=1000;room-id=456;subscriber=0;system-msg=gifts;tmi-sent-ts=1594583782376;user-id=274598607;user-type= :tmi.twitch.tv USERNOTICE #test_channel"
+        ))
+    }
+
+    fn anonymous_gift_notice(recipient: &str) -> UserNoticeMessage {
+        parse_notice(&format!(
+            "@badge-info=;badges=;color=;display-name=AnAnonymousGifter;emotes=;flags=;id=6;login=ananonymousgifter;mod=0;msg-id=subgift;msg-param-months=1;msg-param-recipient-display-name={recipient};msg-param-recipient-id=200;msg-param-recipient-user-name={login};msg-param-sub-plan-name=Channel\\sSub;msg-param-sub-plan=1000;msg-param-gift-months=1;room-id=456;subscriber=0;system-msg=gift;tmi-sent-ts=1594583782376;user-id=274598607;user-type= :tmi.twitch.tv USERNOTICE #test_channel",
+            login = recipient.to_lowercase()
+        ))
+    }
+
+    fn sent_messages(client: &MockTwitchClient) -> Vec<String> {
+        client
+            .sent_messages()
+            .into_iter()
+            .map(|(_, message)| message)
+            .collect()
+    }
+
+    #[test]
+    fn test_event_metadata() {
+        assert_eq!(
+            ChannelEvent::from_user_notice(&resub_notice()),
+            Some(ChannelEvent::Subscription {
+                username: "SubUser".to_string(),
+                months: 14,
+                streak_months: Some(3),
+                plan: "1000".to_string(),
+                is_resub: true,
+            })
+        );
+        assert_eq!(
+            ChannelEvent::from_user_notice(&raid_notice()),
+            Some(ChannelEvent::Raid {
+                username: "Raider".to_string(),
+                login: "raider".to_string(),
+                viewer_count: 42,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_thanks_subscribers_and_raiders() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let service = EventService::new(Arc::new(client.clone()), EventMessages::default());
+
+        service.process_notice(&resub_notice()).await?;
+        service.process_notice(&raid_notice()).await?;
+
+        assert_eq!(
+            sent_messages(&client),
+            vec![
+                "Thank you for resubscribing for 14 months, SubUser!",
+                "Thank you for the raid, Raider!",
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_gift_batch_is_thanked_once() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let service = EventService::new(Arc::new(client.clone()), EventMessages::default());
+
+        service.process_notice(&mystery_gift_notice(2)).await?;
+        service.process_notice(&gift_notice("First")).await?;
+        service.process_notice(&gift_notice("Second")).await?;
+        // A later standalone gift is thanked on its own
+        service.process_notice(&gift_notice("Third")).await?;
+
+        assert_eq!(
+            sent_messages(&client),
+            vec![
+                "Thank you Gifter for gifting 2 subs!",
+                "Thank you Gifter for gifting a sub to Third!",
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_gift_batch_is_thanked_once() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let service = EventService::new(Arc::new(client.clone()), EventMessages::default());
+
+        service
+            .process_notice(&anonymous_mystery_gift_notice(2))
+            .await?;
+        service
+            .process_notice(&anonymous_gift_notice("First"))
+            .await?;
+        service
+            .process_notice(&anonymous_gift_notice("Second"))
+            .await?;
+        service
+            .process_notice(&anonymous_gift_notice("Third"))
+            .await?;
+
+        assert_eq!(
+            sent_messages(&client),
+            vec![
+                "Thank you Anonymous for gifting 2 subs!",
+                "Thank you Anonymous for gifting a sub to Third!",
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_event_reported_twice_is_thanked_once() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let service = EventService::new(Arc::new(client.clone()), EventMessages::default());
+        let raid = EventSubEvent::Raid {
+            from_login: "raider".to_string(),
+            from_name: "Raider".to_string(),
+            viewers: 42,
+        };
+        let gifted = EventSubEvent::Subscribe {
+            user_name: "Lucky".to_string(),
+            tier: "1000".to_string(),
+            is_gift: true,
+        };
+        assert_eq!(ChannelEvent::from_eventsub(&gifted), None);
+
+        // EventSub first, then the same raid over IRC
+        let event = ChannelEvent::from_eventsub(&raid).unwrap();
+        assert!(
+            service
+                .process_event("test_channel", event)
+                .await?
+                .is_some()
+        );
+        assert_eq!(service.process_notice(&raid_notice()).await?, None);
+
+        // A raid from the same channel much later is a new raid
+        tokio::time::advance(DUPLICATE_WINDOW).await;
+        assert!(service.process_notice(&raid_notice()).await?.is_some());
+
+        assert_eq!(sent_messages(&client).len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_empty_template_disables_thanks() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let messages = EventMessages {
+            raid: String::new(),
+            ..EventMessages::default()
+        };
+        let service = EventService::new(Arc::new(client.clone()), messages);
+
+        let event = service.process_notice(&raid_notice()).await?;
+        assert!(matches!(event, Some(ChannelEvent::Raid { .. })));
+        assert!(client.sent_messages().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,339 @@
+//! Instant answers to common viewer questions
+//!
+//! Rules are read from a TOML file. Each rule matches a message by regex or by
+//! a set of keywords that must all appear, and answers at most once per
+//! cooldown in each channel so a busy chat doesn't get the same answer over
+//! and over.
+//!
+//! ```toml
+//! [[rule]]
+//! name = "specs"
+//! keywords = ["pc", "specs"]
+//! answer = "Specs: Ryzen 7 7800X3D, RTX 4080, 32GB RAM"
+//!
+//! [[rule]]
+//! name = "schedule"
+//! pattern = "when (do|does) (you|he|she|they) stream"
+//! answer = "Live Monday, Wednesday, and Friday at 7pm ET!"
+//! cooldown_secs = 120
+//! ```
+
+use anyhow::{Result, anyhow, bail};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, info};
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::twitch::MessageSender;
+
+/// How long a rule stays quiet after answering, unless the rule sets its own
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// A rule as written in the rules file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    name: String,
+    #[serde(default)]
+    pattern: Option<String>,
+    #[serde(default)]
+    keywords: Vec<String>,
+    answer: String,
+    #[serde(default)]
+    cooldown_secs: Option<u64>,
+}
+
+/// The rules file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<RuleConfig>,
+}
+
+/// A question and its canned answer
+#[derive(Debug, Clone)]
+pub struct FaqRule {
+    /// Name of the rule, used in logs
+    pub name: String,
+    /// Case-insensitive pattern the message must match
+    pattern: Option<Regex>,
+    /// Lowercase words that must all appear in the message
+    keywords: Vec<String>,
+    /// The answer posted in chat
+    pub answer: String,
+    /// How long the rule stays quiet after answering
+    pub cooldown: Duration,
+}
+
+impl FaqRule {
+    /// Check whether a message asks this rule's question
+    ///
+    /// A rule with both a pattern and keywords matches if either does.
+    ///
+    /// # Arguments
+    /// * `text` - The chat message text
+    pub fn matches(&self, text: &str) -> bool {
+        let pattern_matches = self
+            .pattern
+            .as_ref()
+            .is_some_and(|pattern| pattern.is_match(text));
+
+        let keywords_match = !self.keywords.is_empty() && {
+            let words: Vec<String> = text
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .map(str::to_lowercase)
+                .collect();
+            self.keywords.iter().all(|keyword| words.contains(keyword))
+        };
+
+        pattern_matches || keywords_match
+    }
+}
+
+impl TryFrom<RuleConfig> for FaqRule {
+    type Error = anyhow::Error;
+
+    fn try_from(config: RuleConfig) -> Result<Self> {
+        if config.pattern.is_none() && config.keywords.is_empty() {
+            bail!("FAQ rule '{}' needs a pattern or keywords", config.name);
+        }
+
+        let pattern = config
+            .pattern
+            .map(|pattern| {
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| {
+                        anyhow!("FAQ rule '{}' has an invalid pattern: {}", config.name, e)
+                    })
+            })
+            .transpose()?;
+
+        Ok(FaqRule {
+            name: config.name,
+            pattern,
+            keywords: config
+                .keywords
+                .iter()
+                .map(|keyword| keyword.to_lowercase())
+                .collect(),
+            answer: config.answer,
+            cooldown: config
+                .cooldown_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_COOLDOWN),
+        })
+    }
+}
+
+/// Parse FAQ rules from TOML
+///
+/// # Arguments
+/// * `contents` - The rules file contents
+///
+/// # Returns
+/// The rules in file order
+pub fn parse_rules(contents: &str) -> Result<Vec<FaqRule>> {
+    let file: RulesFile = toml::from_str(contents)?;
+    file.rule.into_iter().map(FaqRule::try_from).collect()
+}
+
+/// Read FAQ rules from a TOML file
+///
+/// # Arguments
+/// * `path` - Path to the rules file
+///
+/// # Returns
+/// The rules in file order
+pub fn load_rules(path: &Path) -> Result<Vec<FaqRule>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Couldn't read FAQ file {}: {}", path.display(), e))?;
+    parse_rules(&contents).map_err(|e| anyhow!("Invalid FAQ file {}: {}", path.display(), e))
+}
+
+/// Answers viewer questions that match the FAQ rules
+pub struct FaqService {
+    /// The client for sending messages
+    client: Arc<dyn MessageSender>,
+    /// The rules, checked in order
+    rules: Vec<FaqRule>,
+    /// Login of the bot, whose own messages are ignored
+    bot_login: String,
+    /// When each rule last answered, by channel and rule name
+    last_answered: Mutex<HashMap<(String, String), Instant>>,
+}
+
+impl FaqService {
+    /// Create a new FAQ service
+    ///
+    /// # Arguments
+    /// * `client` - The Twitch client for sending messages
+    /// * `rules` - The rules, checked in order
+    /// * `bot_login` - Login of the bot, so it doesn't answer itself
+    ///
+    /// # Returns
+    /// A new FaqService instance
+    pub fn new(client: Arc<dyn MessageSender>, rules: Vec<FaqRule>, bot_login: &str) -> Self {
+        FaqService {
+            client,
+            rules,
+            bot_login: bot_login.to_lowercase(),
+            last_answered: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Find the rule to answer a message with, starting its cooldown
+    ///
+    /// Only the first matching rule answers. If it's cooling down the message
+    /// gets no answer, rather than a less specific one from a later rule.
+    ///
+    /// # Arguments
+    /// * `msg` - The chat message
+    ///
+    /// # Returns
+    /// The rule to answer with, if any
+    fn take_answer(&self, msg: &PrivmsgMessage) -> Option<&FaqRule> {
+        if msg.sender.login.eq_ignore_ascii_case(&self.bot_login) {
+            return None;
+        }
+
+        let rule = self
+            .rules
+            .iter()
+            .find(|rule| rule.matches(&msg.message_text))?;
+
+        let mut last_answered = self.last_answered.lock().unwrap();
+        let key = (msg.channel_login.clone(), rule.name.clone());
+        let now = Instant::now();
+        match last_answered.get(&key) {
+            Some(last) if now.duration_since(*last) < rule.cooldown => {
+                debug!("FAQ '{}' is cooling down", rule.name);
+                None
+            }
+            _ => {
+                last_answered.insert(key, now);
+                Some(rule)
+            }
+        }
+    }
+
+    /// Answer a chat message if it asks a known question
+    ///
+    /// # Arguments
+    /// * `msg` - The chat message to process
+    ///
+    /// # Returns
+    /// The name of the rule that answered, if any
+    pub async fn process_message(&self, msg: &PrivmsgMessage) -> Result<Option<String>> {
+        let Some(rule) = self.take_answer(msg) else {
+            return Ok(None);
+        };
+
+        info!(
+            "Answering {}'s question with FAQ '{}'",
+            msg.sender.name, rule.name
+        );
+        self.client.say(&msg.channel_login, &rule.answer).await?;
+        Ok(Some(rule.name.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::create_test_privmsg_with_badges;
+    use crate::users::MockTwitchClient;
+
+    const RULES: &str = r#"
+        [[rule]]
+        name = "specs"
+        keywords = ["pc", "specs"]
+        answer = "Specs: a potato"
+
+        [[rule]]
+        name = "schedule"
+        pattern = "when (do|does) (you|he|she|they) stream"
+        answer = "Mondays at 7pm!"
+        cooldown_secs = 120
+    "#;
+
+    fn create_service() -> (FaqService, MockTwitchClient) {
+        let client = MockTwitchClient::new();
+        let service = FaqService::new(
+            Arc::new(client.clone()),
+            parse_rules(RULES).unwrap(),
+            "test_bot",
+        );
+        (service, client)
+    }
+
+    #[test]
+    fn test_rule_matching() {
+        let rules = parse_rules(RULES).unwrap();
+        assert!(rules[0].matches("What are your PC specs?"));
+        // Every keyword must appear, as a whole word
+        assert!(!rules[0].matches("nice pc"));
+        assert!(!rules[0].matches("pcs and specsheets"));
+        assert!(rules[1].matches("When does she STREAM again?"));
+        assert!(!rules[1].matches("stream when?"));
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        let no_trigger = "[[rule]]\nname = \"x\"\nanswer = \"y\"";
+        assert!(parse_rules(no_trigger).is_err());
+        let bad_pattern = "[[rule]]\nname = \"x\"\npattern = \"(\"\nanswer = \"y\"";
+        assert!(parse_rules(bad_pattern).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_answers_are_rate_limited() -> Result<()> {
+        let (service, client) = create_service();
+        let msg = create_test_privmsg_with_badges("when do you stream?", &[]);
+
+        assert_eq!(
+            service.process_message(&msg).await?,
+            Some("schedule".to_string())
+        );
+        assert_eq!(service.process_message(&msg).await?, None);
+
+        // Other questions have their own cooldown
+        let specs = create_test_privmsg_with_badges("pc specs?", &[]);
+        assert_eq!(
+            service.process_message(&specs).await?,
+            Some("specs".to_string())
+        );
+
+        tokio::time::advance(Duration::from_secs(120)).await;
+        assert_eq!(
+            service.process_message(&msg).await?,
+            Some("schedule".to_string())
+        );
+        assert_eq!(client.sent_messages().len(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unrelated_messages_are_ignored() -> Result<()> {
+        let (service, client) = create_service();
+
+        let msg = create_test_privmsg_with_badges("hello chat", &[]);
+        assert_eq!(service.process_message(&msg).await?, None);
+
+        // The bot doesn't answer its own messages
+        let mut own = create_test_privmsg_with_badges("my pc specs are great", &[]);
+        own.sender.login = "test_bot".to_string();
+        assert_eq!(service.process_message(&own).await?, None);
+
+        assert!(client.sent_messages().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+//! Features that can be turned off in some channels
+//!
+//! When the bot is in several channels, each can choose which features the
+//! bot uses there, e.g. welcomes and filters in one and only commands in
+//! another. Channels without their own list get every feature.
+
+use anyhow::{Result, anyhow};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// A part of the bot that can be turned off per channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// Chat commands
+    Commands,
+    /// Welcome messages for new and returning chatters
+    Welcomes,
+    /// Automod and new account filtering
+    Filters,
+    /// Recurring announcements
+    Announcements,
+    /// AI-generated messages and the AI persona
+    Ai,
+    /// Loyalty points and the games that spend them
+    Points,
+}
+
+impl FromStr for Feature {
+    type Err = anyhow::Error;
+
+    /// Parse a feature name like "welcomes" or "ai"
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "commands" => Ok(Feature::Commands),
+            "welcomes" => Ok(Feature::Welcomes),
+            "filters" => Ok(Feature::Filters),
+            "announcements" | "announcer" => Ok(Feature::Announcements),
+            "ai" => Ok(Feature::Ai),
+            "points" => Ok(Feature::Points),
+            other => Err(anyhow!("Unknown feature: {}", other)),
+        }
+    }
+}
+
+/// Which features are on in each channel
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelFeatures {
+    /// Features for specific channels, keyed by lowercase channel login
+    channels: HashMap<String, HashSet<Feature>>,
+}
+
+impl ChannelFeatures {
+    /// Create a feature set where listed channels only get their features
+    ///
+    /// # Arguments
+    /// * `channels` - The features for specific channels, keyed by channel login
+    ///
+    /// # Returns
+    /// A new ChannelFeatures instance
+    pub fn new(channels: HashMap<String, HashSet<Feature>>) -> Self {
+        let channels = channels
+            .into_iter()
+            .map(|(channel, features)| (channel.trim_start_matches('#').to_lowercase(), features))
+            .collect();
+        ChannelFeatures { channels }
+    }
+
+    /// Check whether a feature is on in a channel
+    ///
+    /// # Arguments
+    /// * `channel` - Login of the channel
+    /// * `feature` - The feature to check
+    ///
+    /// # Returns
+    /// true if the channel has no list of its own or lists the feature
+    pub fn is_enabled(&self, channel: &str, feature: Feature) -> bool {
+        self.channels
+            .get(&channel.trim_start_matches('#').to_lowercase())
+            .is_none_or(|features| features.contains(&feature))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_features() -> Result<()> {
+        let features = ChannelFeatures::new(HashMap::from([(
+            "#Quiet_Channel".to_string(),
+            HashSet::from(["commands".parse()?]),
+        )]));
+
+        assert!(features.is_enabled("quiet_channel", Feature::Commands));
+        assert!(!features.is_enabled("quiet_channel", Feature::Welcomes));
+        // Channels without a list get everything
+        for feature in [
+            Feature::Commands,
+            Feature::Welcomes,
+            Feature::Filters,
+            Feature::Announcements,
+            Feature::Ai,
+            Feature::Points,
+        ] {
+            assert!(features.is_enabled("busy_channel", feature));
+        }
+        assert_eq!("Points".parse::<Feature>()?, Feature::Points);
+        assert!("lasers".parse::<Feature>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+//! The message posted when the bot comes online
+//!
+//! Right after joining, the IRC connection may not have settled yet, and a
+//! message sent too soon can be lost. The greeting waits a short, optionally
+//! jittered delay first, and is retried once if sending fails.
+
+use anyhow::Result;
+use rand::{Rng, rng};
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::warn;
+
+/// How long the greeting waits after joining unless the config says otherwise
+pub const DEFAULT_GREETING_DELAY: Duration = Duration::from_secs(2);
+
+/// How long to wait before retrying a greeting that failed to send
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Send the greeting after the delay plus up to `jitter` more, retrying once on failure
+///
+/// # Arguments
+/// * `delay` - How long to wait before sending
+/// * `jitter` - The most extra time to wait, chosen at random
+/// * `send` - Sends the greeting
+///
+/// # Returns
+/// A Result indicating whether the greeting was sent
+pub async fn send_greeting<F, Fut>(delay: Duration, jitter: Duration, mut send: F) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let jitter = if jitter.is_zero() {
+        Duration::ZERO
+    } else {
+        rng().random_range(Duration::ZERO..=jitter)
+    };
+    sleep(delay.saturating_add(jitter)).await;
+
+    if let Err(e) = send().await {
+        warn!(
+            "Couldn't send the online message, retrying in {:?}: {}",
+            RETRY_DELAY, e
+        );
+        sleep(RETRY_DELAY).await;
+        send().await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::sync::{Arc, Mutex};
+    use tokio::time::Instant;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_greeting_waits_and_retries() -> Result<()> {
+        let start = Instant::now();
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+
+        let delay = Duration::from_secs(3);
+        let jitter = Duration::from_secs(2);
+        send_greeting(delay, jitter, || {
+            let attempts = attempts.clone();
+            async move {
+                let mut attempts = attempts.lock().unwrap();
+                attempts.push(start.elapsed());
+                // The first attempt is lost, as if the join hadn't settled
+                if attempts.len() == 1 {
+                    Err(anyhow!("not joined yet"))
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .await?;
+
+        let attempts = attempts.lock().unwrap().clone();
+        assert_eq!(attempts.len(), 2);
+        assert!(attempts[0] >= delay && attempts[0] <= delay + jitter);
+        assert_eq!(attempts[1] - attempts[0], RETRY_DELAY);
+
+        // A second failure is reported
+        let result = send_greeting(Duration::ZERO, Duration::ZERO, || async {
+            Err(anyhow!("still failing"))
+        })
+        .await;
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_huge_delay_with_jitter_does_not_overflow() -> Result<()> {
+        send_greeting(Duration::MAX, Duration::from_secs(2), || async { Ok(()) }).await
+    }
+}
@@ -0,0 +1,219 @@
+//! Logging setup with a filter that can be changed at runtime
+//!
+//! Logs always go to stdout, and optionally to a file rotated daily. The
+//! filter sits in front of both, so `--debug` and `!verbose` apply to each.
+
+use anyhow::{Result, anyhow};
+use std::path::Path;
+use tracing::Subscriber;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{EnvFilter, Layer, Registry, fmt, reload};
+
+/// Lets the log filter be changed while the bot runs
+pub struct LogControl {
+    /// Handle for swapping the active filter
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// The filter directives to return to when verbose logging is turned off
+    base: String,
+    /// Keeps the log file writer flushing until the bot exits
+    file_guard: Option<WorkerGuard>,
+}
+
+impl LogControl {
+    /// Create a reloadable filter layer and the control for it
+    ///
+    /// # Arguments
+    /// * `base` - The filter directives to start with (e.g. "info" or "info,som_chatbot::twitch=debug")
+    ///
+    /// # Returns
+    /// The layer to install in a subscriber, and the control for it
+    pub fn new(base: &str) -> Result<(reload::Layer<EnvFilter, Registry>, Self)> {
+        let (layer, handle) = reload::Layer::new(EnvFilter::try_new(base)?);
+        let control = LogControl {
+            handle,
+            base: base.to_string(),
+            file_guard: None,
+        };
+        Ok((layer, control))
+    }
+
+    /// Replace the active filter
+    ///
+    /// # Arguments
+    /// * `directives` - The new filter directives, in `RUST_LOG` syntax
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn set_filter(&self, directives: &str) -> Result<()> {
+        let filter = EnvFilter::try_new(directives)?;
+        self.handle.reload(filter)?;
+        Ok(())
+    }
+
+    /// Turn verbose (debug) logging on or off, for everything or a single target
+    ///
+    /// # Arguments
+    /// * `verbose` - Whether debug messages should be logged
+    /// * `target` - Only log debug messages from this target (e.g. "som_chatbot::twitch::helix")
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub fn set_verbose(&self, verbose: bool, target: Option<&str>) -> Result<()> {
+        match (verbose, target) {
+            (false, _) => self.set_filter(&self.base),
+            (true, Some(target)) => self.set_filter(&format!("{},{}=debug", self.base, target)),
+            (true, None) => self.set_filter("debug"),
+        }
+    }
+
+    /// Get the filter currently being applied
+    pub fn current_filter(&self) -> String {
+        self.handle
+            .with_current(|filter| filter.to_string())
+            .unwrap_or_else(|_| self.base.clone())
+    }
+}
+
+/// Create a layer that writes logs to a file rotated daily
+///
+/// Rotated files get the date appended, e.g. `bot.log.2024-01-31`.
+///
+/// # Arguments
+/// * `path` - Path of the log file
+///
+/// # Returns
+/// The layer, and a guard that flushes pending lines when dropped
+pub fn file_layer<S>(path: &Path) -> Result<(impl Layer<S>, WorkerGuard)>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Log file path has no file name: {}", path.display()))?;
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(directory)?;
+
+    let appender = tracing_appender::rolling::daily(directory, file_name);
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    let layer = fmt::layer().with_ansi(false).with_writer(writer);
+    Ok((layer, guard))
+}
+
+/// Install the global logger
+///
+/// The starting filter comes from `RUST_LOG` when it's set.
+///
+/// # Arguments
+/// * `debug` - Whether to log debug messages from the start
+/// * `log_file` - Also write logs to this file, rotated daily
+///
+/// # Returns
+/// The control for changing the filter later
+pub fn init(debug: bool, log_file: Option<&Path>) -> Result<LogControl> {
+    let base = match std::env::var(EnvFilter::DEFAULT_ENV) {
+        Ok(directives) if !directives.is_empty() => directives,
+        _ if debug => "debug".to_string(),
+        _ => "info".to_string(),
+    };
+
+    let (filter, mut control) = LogControl::new(&base)?;
+    let file = match log_file {
+        Some(path) => {
+            let (layer, guard) = file_layer(path)?;
+            control.file_guard = Some(guard);
+            Some(layer)
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .with(file)
+        .try_init()?;
+
+    Ok(control)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::Level;
+
+    #[test]
+    fn test_set_verbose_changes_filter() -> Result<()> {
+        let (filter, control) = LogControl::new("info")?;
+        let subscriber = tracing_subscriber::registry().with(filter);
+
+        tracing::subscriber::with_default(subscriber, || -> Result<()> {
+            assert!(!tracing::enabled!(Level::DEBUG));
+            assert!(tracing::enabled!(Level::INFO));
+
+            control.set_verbose(true, None)?;
+            assert!(tracing::enabled!(Level::DEBUG));
+
+            control.set_verbose(false, None)?;
+            assert_eq!(control.current_filter(), "info");
+            assert!(!tracing::enabled!(Level::DEBUG));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_set_verbose_for_target() -> Result<()> {
+        let (filter, control) = LogControl::new("info")?;
+        let subscriber = tracing_subscriber::registry().with(filter);
+
+        tracing::subscriber::with_default(subscriber, || -> Result<()> {
+            control.set_verbose(true, Some("som_chatbot::twitch::helix"))?;
+            assert!(tracing::enabled!(target: "som_chatbot::twitch::helix", Level::DEBUG));
+            assert!(!tracing::enabled!(target: "som_chatbot::users", Level::DEBUG));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_file_layer_uses_filter() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (filter, control) = LogControl::new("info")?;
+        let (file, guard) = file_layer(&dir.path().join("bot.log"))?;
+        let subscriber = tracing_subscriber::registry().with(filter).with(file);
+
+        tracing::subscriber::with_default(subscriber, || -> Result<()> {
+            tracing::info!("kept");
+            tracing::debug!("filtered out");
+            control.set_verbose(true, None)?;
+            tracing::debug!("kept after verbose");
+            Ok(())
+        })?;
+        drop(guard);
+
+        let entries: Vec<_> = std::fs::read_dir(dir.path())?.collect::<Result<_, _>>()?;
+        assert_eq!(entries.len(), 1);
+        assert!(
+            entries[0]
+                .file_name()
+                .to_string_lossy()
+                .starts_with("bot.log.")
+        );
+
+        let contents = std::fs::read_to_string(entries[0].path())?;
+        assert!(contents.contains("kept"));
+        assert!(!contents.contains("filtered out"));
+        assert!(contents.contains("kept after verbose"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_filter_is_rejected() -> Result<()> {
+        let (_filter, control) = LogControl::new("info")?;
+        assert!(control.set_filter("not a [valid filter").is_err());
+        assert_eq!(control.current_filter(), "info");
+        Ok(())
+    }
+}
@@ -1,28 +1,123 @@
+mod ai;
+mod announcements;
+mod build_info;
 mod cli;
 mod commands;
 mod config;
+mod counters;
+mod duration;
+mod events;
+mod faq;
+mod features;
+mod greeting;
+mod logging;
+mod moderation;
+mod personality;
+mod points;
+mod polls;
+mod quotes;
+mod raffle;
+mod redemptions;
+mod silence;
+mod simulate;
+mod store;
 #[cfg(test)]
 mod test_helpers;
 mod twitch;
 mod users;
+mod watch_time;
 
 use anyhow::Result;
 use clap::Parser;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
-use tracing::{Level, debug, error, info};
-use tracing_subscriber::FmtSubscriber;
+use tracing::{debug, error, info, warn};
 use twitch_irc::message::ServerMessage;
 
+use ai::AiClient;
+use announcements::Announcer;
 use cli::{Cli, Commands};
 use commands::{
-    CommandHandler, CommandRegistry, EightBallCommand, HelpCommand, PingCommand, UptimeCommand,
+    AccountAgeCommand, ActivityCommand, AddQuoteCommand, AiPersonaCommand, AnnounceCommand,
+    BanCommand, BroadcastCommand, ChannelsCommand, CommandChannels, CommandContext, CommandHandler,
+    CommandPrefixes, CommandRegistry, CommandUsage, CommandsCommand, CooldownTracker,
+    CounterAdminCommand, CounterCommand, EightBallCommand, EightBallOddsCommand,
+    EightBallResponses, FirstSeenCommand, FollowGate, FollowageCommand, ForgetUserCommand,
+    GambleCommand, HealthCommand, HelpCommand, JoinCommand, LockdownCommand, ModLogCommand,
+    MuteWelcomeCommand, Permission, PingCommand, PointsCommand, PollCommand,
+    PrefixConflictDetector, QuoteCommand, RaffleCommand, RateLimitCommand, ShoutoutCommand,
+    SilenceCommand, SimulateNewChatterCommand, SlotsCommand, StatsCommand, StreamUptimeCommand,
+    TimeoutCommand, UnbanCommand, UndoCommand, UptimeCommand, VerboseCommand, VersionCommand,
+    VoteCommand, WatchTimeCommand,
 };
 use config::Config;
-use twitch::{OAuthManager, TwitchClient};
-use users::{UserManager, WelcomeService};
+use counters::Counters;
+use events::{ChannelEvent, EventService, HighlightService};
+use faq::FaqService;
+use features::Feature;
+use logging::LogControl;
+use moderation::{
+    Lockdown, ModLog, ModLogEntry, ModerationAuditLog, ModerationService, NewAccountGuard,
+};
+use personality::AiPersona;
+use points::PointsService;
+use polls::PollService;
+use quotes::QuoteStore;
+use raffle::Raffle;
+use redemptions::RedemptionService;
+use silence::SilenceTracker;
+use simulate::SimulatedMessage;
+use twitch::{
+    Backoff, EventSubClient, EventSubEvent, HelixChatClient, MessageSender, OAuthManager,
+    TwitchClient, TwitchError,
+};
+use users::{
+    AutoShoutoutService, MockTwitchClient, UserManager, WELCOME_MESSAGES_FILE, WelcomeService,
+    read_welcome_templates, templates_missing_username,
+};
+use watch_time::WatchTimeTracker;
+
+/// The OAuth scopes the bot requests
+fn oauth_scopes() -> Vec<String> {
+    vec![
+        "chat:read".to_string(),
+        "chat:edit".to_string(),
+        "user:read:email".to_string(), // Needed to get the bot's user ID
+        "user:write:chat".to_string(), // Needed for sending replies via Helix API
+        "moderator:manage:banned_users".to_string(), // Needed for unbanning users
+        "moderator:manage:shoutouts".to_string(), // Needed for native shoutouts
+        "moderator:manage:chat_settings".to_string(), // Needed for !lockdown
+        "user:manage:whispers".to_string(), // Needed for whispered replies
+        "moderator:read:followers".to_string(), // Needed for !followage
+        "moderator:read:chatters".to_string(), // Needed to count lurkers' watch time
+        "moderator:manage:announcements".to_string(), // Needed for Twitch announcements
+        "channel:read:subscriptions".to_string(), // Needed for EventSub subscription events
+        "channel:manage:redemptions".to_string(), // Needed for redemption events and actions
+    ]
+}
+
+/// Follow up on a channel event reported by IRC or EventSub
+///
+/// # Arguments
+/// * `result` - The outcome of handling the event
+/// * `channel` - The channel the event happened in
+/// * `welcome_service` - The welcome service, which holds welcomes back after a raid
+fn after_channel_event(
+    result: Result<Option<ChannelEvent>>,
+    channel: &str,
+    welcome_service: &WelcomeService,
+) {
+    match result {
+        // Raiders arrive all at once, so welcome them together
+        Ok(Some(ChannelEvent::Raid { .. })) => welcome_service.start_grace_period(channel),
+        Ok(_) => {}
+        Err(e) => error!("Error handling channel event: {}", e),
+    }
+}
 
 /// The main entry point for the application
 #[tokio::main]
@@ -30,25 +125,54 @@ async fn main() -> Result<()> {
     // Parse command line arguments
     let cli = Cli::parse();
 
-    // Setup logging
-    let log_level = if cli.debug { Level::DEBUG } else { Level::INFO };
-    let subscriber = FmtSubscriber::builder().with_max_level(log_level).finish();
-    tracing::subscriber::set_global_default(subscriber)
-        .expect("Failed to set global default subscriber");
+    // Setup logging, keeping a handle so !verbose can change the level later.
+    // Load .env first so LOG_FILE can come from it.
+    dotenv::dotenv().ok();
+    let log_file = cli
+        .log_file
+        .clone()
+        .or_else(|| std::env::var("LOG_FILE").ok().filter(|v| !v.is_empty()));
+    let log_control = Arc::new(logging::init(
+        cli.debug,
+        log_file.as_deref().map(Path::new),
+    )?);
 
     match &cli.command {
-        Some(Commands::Start { channel }) => {
-            start_bot(cli.debug, cli.prefix.clone(), channel.clone()).await?;
+        Some(Commands::Start { channel, no_tls }) => {
+            start_bot(
+                log_control,
+                cli.config.as_deref(),
+                cli.prefix.clone(),
+                channel.clone(),
+                *no_tls,
+            )
+            .await?;
         }
         Some(Commands::GenEnv { path }) => {
             generate_env_file(path)?;
         }
         Some(Commands::Auth { force }) => {
-            authenticate(*force).await?;
+            authenticate(cli.config.as_deref(), *force).await?;
+        }
+        Some(Commands::Logout) => {
+            logout(cli.config.as_deref()).await?;
+        }
+        Some(Commands::ExportStats { path }) => {
+            export_stats(cli.config.as_deref(), path).await?;
+        }
+        Some(Commands::Simulate { input }) => {
+            simulate(cli.config.as_deref(), cli.prefix.clone(), input.as_deref()).await?;
         }
         None => {
             // Default to start command if no subcommand is specified
-            start_bot(cli.debug, cli.prefix.clone(), None).await?;
+            start_bot(
+                log_control,
+                cli.config.as_deref(),
+                cli.prefix.clone(),
+                None,
+                false,
+            )
+            .await?;
         }
     }
 
@@ -58,14 +182,15 @@ async fn main() -> Result<()> {
 /// Authenticate with Twitch
 ///
 /// # Arguments
+/// * `config_path` - Path to a TOML config file, if one was given
 /// * `force` - Force re-authentication even if tokens exist
 ///
 /// # Returns
 /// A Result indicating success or failure
-async fn authenticate(force: bool) -> Result<()> {
+async fn authenticate(config_path: Option<&str>, force: bool) -> Result<()> {
     // Load configuration
     info!("Loading configuration");
-    let config = Config::from_env()?;
+    let config = Config::load(config_path)?;
 
     // Make sure data directory exists
     let data_dir = std::path::Path::new(&config.data_dir);
@@ -74,17 +199,15 @@ async fn authenticate(force: bool) -> Result<()> {
     }
 
     // Set up OAuth manager
-    let oauth_scopes = vec![
-        "chat:read".to_string(),
-        "chat:edit".to_string(),
-        "user:read:email".to_string(), // Needed to get the bot's user ID
-        "user:write:chat".to_string(), // Needed for sending replies via Helix API
-    ];
-
     let oauth_manager = Arc::new(Mutex::new(OAuthManager::new(
         config.client_id.clone(),
-        oauth_scopes,
+        oauth_scopes(),
     )));
+    {
+        let mut oauth_manager = oauth_manager.lock().await;
+        oauth_manager.set_encryption_key(config.token_encryption_key.clone());
+        oauth_manager.set_client_secret(config.client_secret.clone(), &config.oauth_redirect_uri);
+    }
 
     // Try to load existing token if not forcing re-auth
     let token_path = config.get_token_path();
@@ -114,20 +237,198 @@ async fn authenticate(force: bool) -> Result<()> {
     Ok(())
 }
 
+/// Revoke the stored OAuth token with Twitch and delete the token file
+///
+/// # Arguments
+/// * `config_path` - Path to a TOML config file, if one was given
+///
+/// # Returns
+/// A Result indicating success or failure
+async fn logout(config_path: Option<&str>) -> Result<()> {
+    info!("Loading configuration");
+    let config = Config::load(config_path)?;
+
+    let token_path = config.get_token_path();
+    if !std::path::Path::new(&token_path).exists() {
+        println!("No stored token at {}, nothing to revoke.", token_path);
+        return Ok(());
+    }
+
+    let mut oauth_manager = OAuthManager::new(config.client_id.clone(), oauth_scopes());
+    oauth_manager.set_encryption_key(config.token_encryption_key.clone());
+    oauth_manager.load_token(&token_path)?;
+
+    info!("Revoking OAuth token");
+    oauth_manager.revoke_token().await?;
+    std::fs::remove_file(&token_path)?;
+
+    println!("Token revoked and {} deleted.", token_path);
+    Ok(())
+}
+
+/// Export per-user chat statistics from the known users database
+///
+/// # Arguments
+/// * `config_path` - Path to a TOML config file, if one was given
+/// * `path` - Path to write the CSV file to
+///
+/// # Returns
+/// A Result indicating success or failure
+async fn export_stats(config_path: Option<&str>, path: &str) -> Result<()> {
+    info!("Loading configuration");
+    let config = Config::load(config_path)?;
+
+    let user_manager = UserManager::new(&format!("{}/known_users.db", config.data_dir))?;
+    user_manager.load().await?;
+
+    let exported = user_manager.export_stats(std::path::Path::new(path))?;
+    println!("Exported stats for {} users to {}", exported, path);
+
+    Ok(())
+}
+
+/// Run the bot offline, printing what it would send instead of connecting to Twitch
+///
+/// Chat is read from the input one message per line (see `simulate`). Only
+/// commands that work without Twitch are available, and known users and
+/// feature state are kept in memory so the real data isn't touched.
+///
+/// # Arguments
+/// * `config_path` - Path to a TOML config file, if one was given
+/// * `prefix_override` - The command prefix from the command line, if given
+/// * `input` - File to read chat from, or None for stdin
+///
+/// # Returns
+/// A Result indicating success or failure
+async fn simulate(
+    config_path: Option<&str>,
+    prefix_override: Option<String>,
+    input: Option<&str>,
+) -> Result<()> {
+    use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+
+    info!("Loading configuration");
+    let config = Config::load_offline(config_path)?;
+    let prefixes = CommandPrefixes::new(
+        prefix_override.unwrap_or_else(|| config.command_prefix.clone()),
+        config.channel_prefixes.clone(),
+    )?;
+    let prefix = prefixes.default_prefix().to_string();
+    let channel_features = Arc::new(config.channel_features.clone());
+
+    let client: Arc<dyn MessageSender> = Arc::new(MockTwitchClient::printing());
+    // Never loaded, so known users stay in memory
+    let user_manager = Arc::new(UserManager::new(&format!(
+        "{}/known_users.db",
+        config.data_dir
+    ))?);
+
+    let mut welcome_service = WelcomeService::new(client.clone(), user_manager.clone(), None);
+    welcome_service.set_mode(config.welcome_mode);
+    welcome_service.set_personality(config.personality);
+    welcome_service.set_capture_first_words(config.capture_first_words);
+    welcome_service.set_enabled(config.welcome_enabled);
+    welcome_service.set_features(channel_features.clone());
+    if let Some(templates) = config.welcome_messages.clone() {
+        welcome_service.set_welcome_messages(templates);
+    }
+
+    let registry_arc = Arc::new(RwLock::new(CommandRegistry::new()));
+    {
+        let mut registry = registry_arc.write().await;
+        registry.register("ping", Arc::new(PingCommand));
+        registry.register("version", Arc::new(VersionCommand));
+        registry.register(
+            "uptime",
+            Arc::new(UptimeCommand::with_units(config.uptime_units.clone())),
+        );
+        let eight_ball_responses = match &config.eight_ball_file {
+            Some(path) => EightBallResponses::load(Path::new(path))?,
+            None => EightBallResponses::default(),
+        };
+        let mut eight_ball = EightBallCommand::with_responses(
+            Arc::new(eight_ball_responses),
+            config.personality,
+            config.eight_ball_format.clone(),
+        );
+        eight_ball.set_repeat_window(config.eight_ball_repeat_window);
+        registry.register(
+            "8ballodds",
+            Arc::new(EightBallOddsCommand::new(eight_ball.responses())),
+        );
+        registry.register("8ball", Arc::new(eight_ball));
+        registry.register(
+            "commands",
+            Arc::new(CommandsCommand::new(prefixes.clone(), registry_arc.clone())),
+        );
+        registry.register("firstseen", Arc::new(FirstSeenCommand));
+        registry.register("activity", Arc::new(ActivityCommand));
+    }
+
+    let command_context = CommandContext::new(user_manager.clone(), config.channel_name.clone());
+    let mut command_handler = CommandHandler::new(client, registry_arc, prefixes, command_context);
+    command_handler.set_cooldowns(Arc::new(CooldownTracker::new(config.cooldowns.clone())));
+    command_handler.set_features(channel_features.clone());
+
+    let reader: Box<dyn AsyncBufRead + Unpin> = match input {
+        Some(path) => Box::new(BufReader::new(tokio::fs::File::open(path).await?)),
+        None => {
+            println!(
+                "Simulating chat in #{}. Type messages like `alice: hello` or `bob/moderator: {}ping`.",
+                config.channel_name, prefix
+            );
+            Box::new(BufReader::new(tokio::io::stdin()))
+        }
+    };
+
+    let mut lines = reader.lines();
+    let mut message_id = 0;
+    while let Some(line) = lines.next_line().await? {
+        let Some(message) = SimulatedMessage::parse(&line) else {
+            continue;
+        };
+        message_id += 1;
+        let privmsg = message.to_privmsg(&config.channel_name, message_id);
+
+        if let Err(e) = welcome_service.process_message(privmsg.clone()).await {
+            error!("Error processing welcome: {}", e);
+        }
+        if let Err(e) = command_handler.handle_message(privmsg).await {
+            error!("Error handling command: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
 /// Start the bot with the given configuration
-async fn start_bot(_debug: bool, prefix: String, channel_override: Option<String>) -> Result<()> {
+async fn start_bot(
+    log_control: Arc<LogControl>,
+    config_path: Option<&str>,
+    prefix_override: Option<String>,
+    channel_override: Option<String>,
+    no_tls: bool,
+) -> Result<()> {
     // Load configuration
     info!("Loading configuration");
-    let mut config = Config::from_env()?;
+    let mut config = Config::load_for_start(config_path, channel_override.as_deref())?;
+    let prefixes = CommandPrefixes::new(
+        prefix_override.unwrap_or_else(|| config.command_prefix.clone()),
+        config.channel_prefixes.clone(),
+    )?;
+    let prefix = prefixes.default_prefix().to_string();
 
-    // Override channel if specified
-    if let Some(channel) = channel_override {
-        config.channel_name = channel;
+    // Disable TLS if requested on the command line
+    if no_tls {
+        config.irc_tls = false;
     }
 
-    info!("Starting SOM Chatbot");
+    info!("Starting SOM Chatbot ({})", build_info::current());
     info!("Connecting to channel: {}", config.channel_name);
 
+    // Features each channel uses, shared by the services that check them
+    let channel_features = Arc::new(config.channel_features.clone());
+
     // Make sure data directory exists
     let data_dir = std::path::Path::new(&config.data_dir);
     if !data_dir.exists() {
@@ -135,17 +436,15 @@ async fn start_bot(_debug: bool, prefix: String, channel_override: Option<String
     }
 
     // Set up OAuth manager
-    let oauth_scopes = vec![
-        "chat:read".to_string(),
-        "chat:edit".to_string(),
-        "user:read:email".to_string(), // Needed to get the bot's user ID
-        "user:write:chat".to_string(), // Needed for sending replies via Helix API
-    ];
-
     let oauth_manager = Arc::new(Mutex::new(OAuthManager::new(
         config.client_id.clone(),
-        oauth_scopes,
+        oauth_scopes(),
     )));
+    {
+        let mut oauth_manager = oauth_manager.lock().await;
+        oauth_manager.set_encryption_key(config.token_encryption_key.clone());
+        oauth_manager.set_client_secret(config.client_secret.clone(), &config.oauth_redirect_uri);
+    }
 
     // Try to load existing token
     let token_path = config.get_token_path();
@@ -168,75 +467,623 @@ async fn start_bot(_debug: bool, prefix: String, channel_override: Option<String
         oauth_manager.lock().await.save_token(&token_path)?;
     }
 
+    // Use the token's account if TWITCH_BOT_USERNAME names a different one
+    let mut helix = HelixChatClient::new(oauth_manager.clone()).await?;
+    match helix.check_bot_username(&config.bot_username).await {
+        Ok(Some(account)) => {
+            warn!(
+                "TWITCH_BOT_USERNAME is {} but the OAuth token belongs to {}; using {}. Update TWITCH_BOT_USERNAME or re-authenticate as {}.",
+                config.bot_username, account.login, account.login, config.bot_username
+            );
+            config.bot_username = account.login;
+        }
+        Ok(None) => debug!("Authenticated as {}", config.bot_username),
+        Err(e) => warn!(
+            "Couldn't check which account the OAuth token belongs to: {}",
+            e
+        ),
+    }
+
     // Create Twitch client with OAuth
     let (incoming_messages, mut client) = TwitchClient::new(&config, oauth_manager.clone()).await?;
 
     // Set up user manager
-    let users_file_path = format!("{}/known_users.txt", config.data_dir);
-    let user_manager = Arc::new(UserManager::new(&users_file_path));
+    // Users from an older known_users.txt are migrated into the database on load
+    let users_db_path = format!("{}/known_users.db", config.data_dir);
+    let user_manager = Arc::new(UserManager::new(&users_db_path)?);
 
     // Load known users
-    info!("Loading known users from {}", users_file_path);
+    info!("Loading known users from {}", users_db_path);
     user_manager.load().await?;
 
+    // Open the store shared by stateful features
+    info!(
+        "Opening {:?} store in {}",
+        config.store_backend, config.data_dir
+    );
+    let store = store::open_store(
+        config.store_backend,
+        std::path::Path::new(&config.data_dir),
+        config.store_save_interval,
+    )?;
+    if let Some(interval) = config.store_save_interval {
+        tokio::spawn(store::flush_periodically(store.clone(), interval));
+    }
+
+    // Quotes, shared by !quote and !addquote
+    let mut quote_store = QuoteStore::new(store.clone());
+    let legacy_quotes = std::path::Path::new(&config.data_dir).join("quotes.json");
+    let imported = quote_store.import_legacy(&legacy_quotes)?;
+    if imported > 0 {
+        info!(
+            "Migrated {} quotes from {}",
+            imported,
+            legacy_quotes.display()
+        );
+    }
+    let quotes = Arc::new(RwLock::new(quote_store));
+
+    // Counters like !deaths; the configured ones always exist
+    let counters = Arc::new(Counters::new(store.clone()));
+    for name in &config.counters {
+        counters.create(name)?;
+    }
+
+    // Recurring announcements, each on its own schedule
+    let announcer = {
+        let mut announcer = Announcer::new(
+            Arc::new(client.clone()),
+            store.clone(),
+            &config.channel_name,
+        );
+        announcer.set_min_chat_messages(config.announcement_min_chat_messages);
+        announcer.set_features(channel_features.clone());
+        announcer.set_helix(client.get_helix_client());
+        announcer.set_color(config.announcement_color);
+        let added = announcer.add_configured(&config.announcements)?;
+        if added > 0 {
+            info!("Added {} announcements from the config", added);
+        }
+        Arc::new(announcer)
+    };
+    tokio::spawn(announcer.clone().run());
+
+    // How long chat has been quiet, for !silence and prompts after long silences
+    let silence = {
+        let mut silence = SilenceTracker::new(Arc::new(client.clone()), &config.channel_name);
+        if let Some(after) = config.silence_prompt_after {
+            info!(
+                "Prompting chat after {} of silence while live",
+                duration::format_duration(after)
+            );
+            silence.set_prompt(after, &config.silence_prompt);
+        }
+        Arc::new(silence)
+    };
+    if config.silence_prompt_after.is_some() {
+        tokio::spawn(silence.clone().run());
+    }
+
+    // How often each command is used, for !stats
+    let command_usage = Arc::new(CommandUsage::load(store.clone())?);
+
+    // Loyalty points for viewers who stick around, spent on !slots and !gamble
+    let points = Arc::new(PointsService::new(
+        store.clone(),
+        config.points_per_interval,
+    ));
+
     // Join channel
     client
         .join_channel(&config.channel_name, &config.bot_username)
         .await?;
     info!("Joined channel: {}", config.channel_name);
 
+    // The tone of AI responses, which !aipersona can change while the bot runs
+    let ai_persona = Arc::new(AiPersona::new(config.personality));
+    if let Some(path) = &config.ai_prompt_file {
+        ai_persona.load_file(Path::new(path))?;
+        info!("Using the AI prompt from {}", path);
+    }
+
     // Create welcome service with random messages
-    let welcome_service = Arc::new(WelcomeService::new(
+    let mut welcome_service = WelcomeService::new(
         Arc::new(client.clone()),
         user_manager.clone(),
         None, // Use default random messages
+    );
+    welcome_service.set_mode(config.welcome_mode);
+    welcome_service.set_personality(config.personality);
+    welcome_service.set_ai_persona(ai_persona.clone());
+    welcome_service.set_capture_first_words(config.capture_first_words);
+    welcome_service.set_grace_period(config.welcome_grace_period);
+    welcome_service.set_enabled(config.welcome_enabled);
+    welcome_service.set_features(channel_features.clone());
+    if !config.welcome_enabled {
+        info!("Welcome messages disabled");
+    }
+
+    // Custom templates from the config, or from a file in the data directory
+    let welcome_templates = match config.welcome_messages.clone() {
+        Some(templates) => Some(templates),
+        None => read_welcome_templates(
+            &std::path::Path::new(&config.data_dir).join(WELCOME_MESSAGES_FILE),
+        )?,
+    };
+    if let Some(templates) = welcome_templates {
+        for template in templates_missing_username(&templates) {
+            warn!(
+                "Welcome template doesn't mention the chatter with {{username}}: {}",
+                template
+            );
+        }
+        info!("Using {} custom welcome templates", templates.len());
+        welcome_service.set_welcome_messages(templates);
+    }
+
+    // Use AI-generated welcomes when an AI API is configured
+    if !config.welcome_use_ai {
+        info!("AI welcome messages turned off, using templates");
+    } else if let (Some(api_base), Some(api_key)) = (&config.ai_api_base, &config.ai_api_key) {
+        info!(
+            "AI welcome messages enabled using model {}",
+            config.ai_model
+        );
+        let mut ai_client = AiClient::new(api_base, api_key, &config.ai_model)?;
+        ai_client.set_length_strategy(config.ai_length_strategy);
+        welcome_service.set_ai_client(Arc::new(ai_client));
+        welcome_service.set_use_ai(true);
+    }
+    let welcome_service = Arc::new(welcome_service);
+
+    // Count how long viewers watch, including lurkers the chatters list shows
+    let watch_time = Arc::new(WatchTimeTracker::new(user_manager.clone()));
+    tokio::spawn(
+        watch_time
+            .clone()
+            .run(client.get_helix_client(), config.channel_name.clone()),
+    );
+    // Points follow the same presence and live state as watch time
+    if channel_features.is_enabled(&config.channel_name, Feature::Points) {
+        tokio::spawn(
+            points
+                .clone()
+                .run(watch_time.clone(), config.points_interval),
+        );
+    }
+
+    // Automatically shout out fellow streamers the first time they chat
+    let auto_shoutout = if config.auto_shoutout_users.is_empty() {
+        None
+    } else {
+        info!(
+            "Auto-shoutouts enabled for: {}",
+            config.auto_shoutout_users.join(", ")
+        );
+        Some(Arc::new(AutoShoutoutService::new(
+            Arc::new(client.clone()),
+            config.auto_shoutout_users.clone(),
+        )))
+    };
+
+    // Watch for the stream going live to hold back the first wave of welcomes
+    // and re-arm auto-shoutouts, so silence prompts are only posted while
+    // live, and so watch time only counts while live
+    {
+        let helix = client.get_helix_client();
+        let welcome_service = welcome_service.clone();
+        let silence = silence.clone();
+        let watch_time = watch_time.clone();
+        let auto_shoutout = auto_shoutout.clone();
+        let channel = config.channel_name.clone();
+        tokio::spawn(async move {
+            let mut was_live = None;
+            loop {
+                match helix.lock().await.get_stream_started_at(&channel).await {
+                    Ok(started_at) => {
+                        let live = started_at.is_some();
+                        if live && was_live == Some(false) {
+                            info!("{} went live", channel);
+                            welcome_service.start_grace_period(&channel);
+                            if let Some(auto_shoutout) = &auto_shoutout {
+                                auto_shoutout.reset();
+                            }
+                        }
+                        silence.set_live(live);
+                        watch_time.set_live(live);
+                        was_live = Some(live);
+                    }
+                    Err(e) => debug!("Couldn't check whether {} is live: {}", channel, e),
+                }
+                tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+            }
+        });
+    }
+
+    // Thank subscribers, gifters, and raiders
+    let event_service = Arc::new(EventService::new(
+        Arc::new(client.clone()),
+        config.event_messages.clone(),
     ));
 
+    // React to "Highlight My Message" redemptions
+    let highlight_service = config
+        .highlight_message
+        .clone()
+        .map(|template| Arc::new(HighlightService::new(Arc::new(client.clone()), template)));
+
+    // Answer common questions from the FAQ rules file
+    let faq_service = match &config.faq_file {
+        Some(path) => {
+            let rules = faq::load_rules(Path::new(path))?;
+            info!("Loaded {} FAQ rules from {}", rules.len(), path);
+            Some(Arc::new(FaqService::new(
+                Arc::new(client.clone()),
+                rules,
+                &config.bot_username,
+            )))
+        }
+        None => None,
+    };
+
     // Set up command registry
     let registry = CommandRegistry::new();
     let registry_arc = Arc::new(RwLock::new(registry));
 
-    // Set up command descriptions for help command
-    let descriptions = vec![
-        ("ping".to_string(), "Responds with Pong!".to_string()),
-        (
-            "uptime".to_string(),
-            "Shows how long the bot has been running".to_string(),
-        ),
-        (
-            "help".to_string(),
-            "Shows help information for available commands".to_string(),
-        ),
-        (
-            "8ball".to_string(),
-            "Ask the Magic 8-Ball a yes/no question. Usage: !8ball <question>".to_string(),
-        ),
-    ];
+    // Log of moderation actions taken by the bot, used by !undo
+    let audit_log = Arc::new(ModerationAuditLog::new());
+
+    // Log of bans, timeouts, and deleted messages by any moderator
+    let mod_log_path = config
+        .moderation_log
+        .then(|| std::path::Path::new(&config.data_dir).join("moderation.log"));
+    if let Some(path) = &mod_log_path {
+        info!("Appending moderation events to {}", path.display());
+    }
+    let mod_log = Arc::new(ModLog::new(mod_log_path));
+
+    // Flag messages from recently created accounts. Without a configured
+    // minimum age the check only runs during a lockdown.
+    let new_account_guard = {
+        let days = config.new_account_min_age_days.unwrap_or(7);
+        let mut guard = NewAccountGuard::new(
+            client.get_helix_client(),
+            user_manager.clone(),
+            chrono::Duration::days(days),
+            config.new_account_action,
+        );
+        guard.set_moderation(client.get_helix_client(), audit_log.clone());
+        guard.set_enabled(config.new_account_min_age_days.is_some());
+        guard.set_features(channel_features.clone());
+        if config.new_account_min_age_days.is_some() {
+            info!(
+                "Flagging accounts younger than {} days ({:?})",
+                days, config.new_account_action
+            );
+        }
+        Arc::new(guard)
+    };
+
+    // Time out links, shouting, and emote spam from regular chatters
+    let moderation_service = config.automod.is_active().then(|| {
+        info!(
+            "Automod is on (timeouts of {}s)",
+            config.automod.timeout_secs
+        );
+        let mut service = ModerationService::new(
+            Arc::new(client.clone()),
+            client.get_helix_client(),
+            audit_log.clone(),
+            user_manager.clone(),
+            config.automod.clone(),
+            &config.bot_username,
+        );
+        service.set_features(channel_features.clone());
+        Arc::new(service)
+    });
+
+    // Anti-raid lockdown, toggled with !lockdown
+    let lockdown = Arc::new(Lockdown::new(
+        client.get_helix_client(),
+        new_account_guard.clone(),
+        config.lockdown,
+    ));
 
     // Create and register commands
     {
         let mut registry = registry_arc.write().await;
         registry.register("ping", Arc::new(PingCommand));
-        registry.register("uptime", Arc::new(UptimeCommand::new()));
-        registry.register("8ball", Arc::new(EightBallCommand::new()));
+        registry.register("version", Arc::new(VersionCommand));
+        registry.register(
+            "uptime",
+            Arc::new(StreamUptimeCommand::new(config.uptime_units.clone())),
+        );
+        registry.register(
+            "botuptime",
+            Arc::new(UptimeCommand::with_units(config.uptime_units.clone())),
+        );
+        let eight_ball_responses = match &config.eight_ball_file {
+            Some(path) => {
+                let responses = EightBallResponses::load(Path::new(path))?;
+                info!("Loaded 8-ball responses from {}", path);
+                responses
+            }
+            None => EightBallResponses::default(),
+        };
+        let mut eight_ball = EightBallCommand::with_responses(
+            Arc::new(eight_ball_responses),
+            config.personality,
+            config.eight_ball_format.clone(),
+        );
+        eight_ball.set_repeat_window(config.eight_ball_repeat_window);
+        registry.register(
+            "8ballodds",
+            Arc::new(EightBallOddsCommand::new(eight_ball.responses())),
+        );
+        registry.register("8ball", Arc::new(eight_ball));
         registry.register(
             "help",
-            Arc::new(HelpCommand::new(prefix.clone(), descriptions)),
+            Arc::new(HelpCommand::new(prefixes.clone(), registry_arc.clone())),
+        );
+        registry.register(
+            "commands",
+            Arc::new(CommandsCommand::new(prefixes.clone(), registry_arc.clone())),
+        );
+
+        // Moderation commands; !untimeout is the same operation as !unban
+        registry.register("ban", Arc::new(BanCommand::new(audit_log.clone())));
+        registry.register("timeout", Arc::new(TimeoutCommand::new(audit_log.clone())));
+        let unban = Arc::new(UnbanCommand);
+        registry.register("unban", unban.clone());
+        registry.register("untimeout", unban);
+        registry.register("undo", Arc::new(UndoCommand::new(audit_log.clone())));
+
+        registry.register(
+            "mutewelcome",
+            Arc::new(MuteWelcomeCommand::new(welcome_service.clone())),
+        );
+
+        registry.register(
+            "simnew",
+            Arc::new(SimulateNewChatterCommand::new(welcome_service.clone())),
+        );
+
+        registry.register("forget", Arc::new(ForgetUserCommand));
+
+        registry.register(
+            "ratelimit",
+            Arc::new(RateLimitCommand::new(client.get_outbound_rate_limit())),
+        );
+
+        let shoutout_command = Arc::new(ShoutoutCommand::new(config.shoutout_message.clone()));
+        registry.register("so", shoutout_command);
+        registry.register_alias("shoutout", "so");
+
+        registry.register("firstseen", Arc::new(FirstSeenCommand));
+        registry.register("watchtime", Arc::new(WatchTimeCommand));
+        registry.register("modlog", Arc::new(ModLogCommand::new(mod_log.clone())));
+        registry.register("activity", Arc::new(ActivityCommand));
+
+        registry.register("accountage", Arc::new(AccountAgeCommand::new()));
+
+        registry.register("followage", Arc::new(FollowageCommand));
+
+        registry.register("verbose", Arc::new(VerboseCommand::new(log_control)));
+
+        registry.register(
+            "channels",
+            Arc::new(ChannelsCommand::new(
+                client.get_channels(),
+                Arc::new(client.clone()),
+            )),
+        );
+        registry.register(
+            "broadcast",
+            Arc::new(BroadcastCommand::new(
+                Arc::new(client.clone()),
+                client.get_channels(),
+            )),
+        );
+
+        registry.register(
+            "health",
+            Arc::new(HealthCommand::new(
+                client.get_channels(),
+                client.get_outbound_rate_limit(),
+                client.get_delivery_tracker(),
+            )),
+        );
+
+        registry.register("stats", Arc::new(StatsCommand::new(command_usage.clone())));
+
+        registry.register("quote", Arc::new(QuoteCommand::new(quotes.clone())));
+        registry.register("addquote", Arc::new(AddQuoteCommand::new(quotes)));
+
+        let raffle = Arc::new(std::sync::Mutex::new(Raffle::new(config.raffle_weights)));
+        registry.register("raffle", Arc::new(RaffleCommand::new(raffle.clone())));
+        registry.register("join", Arc::new(JoinCommand::new(raffle)));
+
+        registry.register("points", Arc::new(PointsCommand::new(points.clone())));
+        registry.register(
+            "slots",
+            Arc::new(SlotsCommand::new(
+                points.clone(),
+                config.slots_table.clone(),
+            )),
+        );
+        registry.register("gamble", Arc::new(GambleCommand::new(points.clone())));
+
+        let polls = Arc::new(PollService::new());
+        registry.register("poll", Arc::new(PollCommand::new(polls.clone())));
+        registry.register("vote", Arc::new(VoteCommand::new(polls)));
+
+        registry.register("lockdown", Arc::new(LockdownCommand::new(lockdown.clone())));
+
+        registry.register("silence", Arc::new(SilenceCommand::new(silence.clone())));
+
+        registry.register(
+            "aipersona",
+            Arc::new(AiPersonaCommand::new(
+                ai_persona.clone(),
+                config.ai_prompt_file.as_ref().map(PathBuf::from),
+            )),
+        );
+
+        registry.register(
+            "announce",
+            Arc::new(AnnounceCommand::new(announcer.clone())),
+        );
+
+        // Counter commands go last so they can't replace a built-in command
+        for name in counters.names()? {
+            if registry.has_command(&name) {
+                warn!(
+                    "Not registering the {} counter: a command has that name",
+                    name
+                );
+                continue;
+            }
+            registry.register(
+                name.clone(),
+                Arc::new(CounterCommand::new(&name, counters.clone())),
+            );
+            info!("Registered counter command: {}{}", prefix, name);
+        }
+        registry.register(
+            "counter",
+            Arc::new(CounterAdminCommand::new(
+                counters.clone(),
+                registry_arc.clone(),
+            )),
         );
 
         info!(
-            "Registered commands: ping, uptime, 8ball, help with prefix: '{}'",
+            "Registered commands: ping, version, uptime, botuptime, 8ball, 8ballodds, help, commands, ban, timeout, unban, untimeout, undo, mutewelcome, simnew, forget, ratelimit, so, shoutout, firstseen, watchtime, activity, accountage, followage, verbose, channels, broadcast, health, stats, lockdown, silence, aipersona, quote, addquote, raffle, join, points, slots, gamble, poll, vote, counter, announce with prefix: '{}'",
             prefix
         );
     }
 
     // Create command handler
-    let command_handler = Arc::new(CommandHandler::new(
+    let mut command_context =
+        CommandContext::new(user_manager.clone(), config.channel_name.clone());
+    command_context.set_helix(client.get_helix_client());
+    let mut command_handler = CommandHandler::new(
         Arc::new(client.clone()),
         registry_arc.clone(),
-        prefix,
-        config.bot_username.clone(), // Pass bot username for responding
-    ));
+        prefixes,
+        command_context,
+    );
+    command_handler.set_cooldowns(Arc::new(CooldownTracker::new(config.cooldowns.clone())));
+    // Commands the config limits to followers or subscribers; aliases in the
+    // lists limit the command they stand for
+    let mut permissions = HashMap::new();
+    {
+        let registry = registry_arc.read().await;
+        let limits = [
+            (&config.follower_only_commands, Permission::Follower),
+            (&config.subscriber_only_commands, Permission::Subscriber),
+        ];
+        for (names, permission) in limits {
+            for name in names {
+                let (Some(canonical), Some(command)) =
+                    (registry.canonical_name(name), registry.get_command(name))
+                else {
+                    warn!(
+                        "Unknown command '{}' can't be limited to {:?}s",
+                        name, permission
+                    );
+                    continue;
+                };
+                // Mod-only commands stay mod-only
+                if matches!(
+                    command.permission(),
+                    Permission::Moderator | Permission::Broadcaster
+                ) {
+                    warn!("'{}' is already limited to moderators", canonical);
+                    continue;
+                }
+                permissions.insert(canonical, permission);
+            }
+        }
+    }
+    command_handler.set_permissions(permissions);
+    if !config.command_channels.is_empty() {
+        let registry = registry_arc.read().await;
+        let limits = config
+            .command_channels
+            .iter()
+            .filter_map(|(name, channels)| {
+                let canonical = registry.canonical_name(name);
+                if canonical.is_none() {
+                    warn!("COMMAND_CHANNELS lists unknown command '{}'", name);
+                }
+                canonical.map(|canonical| (canonical, channels.clone()))
+            })
+            .collect();
+        let mut channel_limits = CommandChannels::new(limits);
+        if let Some(reply) = &config.wrong_channel_reply {
+            channel_limits.set_reply(reply);
+        }
+        command_handler.set_channel_limits(Arc::new(channel_limits));
+    }
+    command_handler.set_follow_gate(Arc::new(FollowGate::new()));
+    command_handler.set_usage(command_usage.clone());
+    command_handler.set_features(channel_features.clone());
+    if config.prefix_conflict_detection {
+        command_handler.set_conflict_detector(PrefixConflictDetector::new(&config.bot_username));
+    }
+    let command_handler = Arc::new(command_handler);
+
+    // Hear about subscriptions, raids, and redemptions that IRC may miss,
+    // handled the same way as USERNOTICEs
+    if !config.eventsub && !config.redemption_actions.is_empty() {
+        warn!("REDEMPTION_ACTIONS needs EVENTSUB_ENABLED=true to hear about redemptions");
+    }
+    if config.eventsub {
+        let (eventsub_tx, mut eventsub_rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(
+            EventSubClient::new(client.get_helix_client(), &config.channel_name).run(eventsub_tx),
+        );
+
+        let redemption_service = RedemptionService::new(
+            Arc::new(client.clone()),
+            command_handler.clone(),
+            client.get_helix_client(),
+            config.redemption_actions.clone(),
+        );
+        let event_service = event_service.clone();
+        let welcome_service = welcome_service.clone();
+        let highlight_service = highlight_service.clone();
+        let channel = config.channel_name.clone();
+        tokio::spawn(async move {
+            while let Some(event) = eventsub_rx.recv().await {
+                if let EventSubEvent::Highlight { user_id, text } = &event {
+                    if let Some(highlight_service) = &highlight_service
+                        && let Err(e) = highlight_service.process_redemption(user_id, text).await
+                    {
+                        error!("Error reacting to highlight: {}", e);
+                    }
+                    continue;
+                }
+
+                if let EventSubEvent::Redemption(redemption) = &event {
+                    info!(
+                        "[REDEMPTION] {} redeemed \"{}\" {}",
+                        redemption.user_name, redemption.reward.title, redemption.user_input
+                    );
+                    if let Err(e) = redemption_service.process(&channel, redemption).await {
+                        error!("Redemption action failed: {}", e);
+                    }
+                    continue;
+                }
+
+                if let Some(event) = ChannelEvent::from_eventsub(&event) {
+                    let result = event_service.process_event(&channel, event).await;
+                    after_channel_event(result, &channel, &welcome_service);
+                }
+            }
+        });
+    }
 
     // Set up message handling
     info!("Setting up message handling");
@@ -244,8 +1091,11 @@ async fn start_bot(_debug: bool, prefix: String, channel_override: Option<String
     // Clone services for the async block
     let welcome_service_clone = welcome_service.clone();
     let command_handler_clone = command_handler.clone();
-    let user_manager_clone = user_manager.clone();
     let channel_name = config.channel_name.clone();
+    let bot_username = config.bot_username.clone();
+    let mut reconnect_client = client.clone();
+    let delivery_tracker = client.get_delivery_tracker();
+    delivery_tracker.set_enabled(config.confirm_delivery);
 
     // Spawn a task to process incoming messages
     tokio::spawn(async move {
@@ -256,6 +1106,7 @@ async fn start_bot(_debug: bool, prefix: String, channel_override: Option<String
         // Add a test log every 10 seconds to confirm the task is still running
         let message_task = Arc::new(Mutex::new(0));
         let message_task_clone = message_task.clone();
+        let heartbeat_delivery_tracker = delivery_tracker.clone();
 
         // Spawn a task to periodically log that we're still waiting for messages
         tokio::spawn(async move {
@@ -264,57 +1115,230 @@ async fn start_bot(_debug: bool, prefix: String, channel_override: Option<String
                 let mut counter = message_task_clone.lock().await;
                 *counter += 1;
                 info!("Still waiting for messages... (heartbeat: {})", *counter);
+
+                for send in heartbeat_delivery_tracker.expire() {
+                    warn!(
+                        "Message to {} was never confirmed by Twitch ({} unconfirmed, {} confirmed so far): {}",
+                        send.channel,
+                        heartbeat_delivery_tracker.unconfirmed_count(),
+                        heartbeat_delivery_tracker.confirmed_count(),
+                        send.text
+                    );
+                }
             }
         });
 
-        while let Some(msg) = incoming_messages.recv().await {
-            info!("Received a message from Twitch: {:?}", msg);
+        // Shared across outages, and only reset once a connection delivers a
+        // message, since twitch-irc connects lazily and `reconnect` succeeding
+        // doesn't mean the connection works
+        let mut backoff = Backoff::default();
+        loop {
+            let mut reconnect_requested = false;
+            let mut received = false;
+            while let Some(msg) = incoming_messages.recv().await {
+                debug!("Received a message from Twitch: {:?}", msg);
+                if !received {
+                    backoff.reset();
+                    received = true;
+                }
+
+                // Log every message we receive
+                match &msg {
+                    ServerMessage::Privmsg(privmsg) => {
+                        info!("[CHAT] {}: {}", privmsg.sender.name, privmsg.message_text);
+
+                        // The bot's own messages echo back when sent through Helix
+                        if privmsg.sender.login.eq_ignore_ascii_case(&bot_username) {
+                            delivery_tracker
+                                .confirm_echo(&privmsg.channel_login, &privmsg.message_text);
+                        }
+
+                        // Chat activity lets waiting announcements post
+                        if !privmsg.sender.login.eq_ignore_ascii_case(&bot_username) {
+                            announcer.record_chat_message(&privmsg.channel_login);
+                            silence.record_chat_message(&privmsg.channel_login);
+                            // Watch time only follows the channel whose live state it polls
+                            if privmsg.channel_login.eq_ignore_ascii_case(&channel_name) {
+                                watch_time.record_seen(&privmsg.sender.id, &privmsg.sender.login);
+                            }
+                        }
+
+                        // Time out rule breakers before their message does anything else
+                        if let Some(moderation_service) = &moderation_service {
+                            match moderation_service.process_message(privmsg).await {
+                                Ok(Some(_)) => continue,
+                                Ok(None) => {}
+                                Err(e) => error!("Error applying automod rules: {}", e),
+                            }
+                        }
+
+                        // Hold messages from suspiciously new accounts for mod review
+                        match new_account_guard.process_message(privmsg).await {
+                            Ok(true) => continue,
+                            Ok(false) => {}
+                            Err(e) => error!("Error checking account age: {}", e),
+                        }
 
-            // Log every message we receive
-            match &msg {
-                ServerMessage::Privmsg(privmsg) => {
-                    info!("[CHAT] {}: {}", privmsg.sender.name, privmsg.message_text);
+                        // React to highlighted messages
+                        if let Some(highlight_service) = &highlight_service
+                            && let Err(e) = highlight_service.process_message(privmsg).await
+                        {
+                            error!("Error reacting to highlight: {}", e);
+                        }
 
-                    // Process for welcome service
-                    if let Err(e) = welcome_service_clone.process_message(privmsg.clone()).await {
-                        error!("Error processing welcome: {}", e);
+                        // Answer common questions
+                        if let Some(faq_service) = &faq_service
+                            && let Err(e) = faq_service.process_message(privmsg).await
+                        {
+                            error!("Error answering FAQ: {}", e);
+                        }
+
+                        // Shout out listed streamers
+                        if let Some(auto_shoutout) = &auto_shoutout
+                            && let Err(e) = auto_shoutout.process_message(privmsg).await
+                        {
+                            error!("Error sending auto-shoutout: {}", e);
+                        }
+
+                        // Process for welcome service
+                        if let Err(e) = welcome_service_clone.process_message(privmsg.clone()).await
+                        {
+                            error!("Error processing welcome: {}", e);
+                        }
+
+                        // Process for command handling
+                        if let Err(e) = command_handler_clone.handle_message(privmsg.clone()).await
+                        {
+                            error!("Error handling command: {}", e);
+                        }
+                    }
+                    ServerMessage::Join(join) => {
+                        info!("[JOIN] {} joined the channel", join.user_login);
+                        if join.user_login.eq_ignore_ascii_case(&bot_username) {
+                            delivery_tracker.record_join(&join.channel_login);
+                        }
+                        if join.channel_login.eq_ignore_ascii_case(&channel_name) {
+                            watch_time.record_join(&join.user_login);
+                        }
+                    }
+                    ServerMessage::Part(part) => {
+                        info!("[PART] {} left the channel", part.user_login);
+                        if part.channel_login.eq_ignore_ascii_case(&channel_name) {
+                            watch_time.record_part(&part.user_login);
+                        }
                     }
+                    ServerMessage::Notice(notice) => {
+                        info!("[NOTICE] Channel {}: {}", channel_name, notice.message_text);
+                    }
+                    ServerMessage::UserNotice(notice) => {
+                        info!("[USERNOTICE] {}", notice.system_message);
 
-                    // Process for command handling
-                    if let Err(e) = command_handler_clone.handle_message(privmsg.clone()).await {
-                        error!("Error handling command: {}", e);
+                        // Thank subscribers, gifters, and raiders
+                        let result = event_service.process_notice(notice).await;
+                        after_channel_event(result, &notice.channel_login, &welcome_service_clone);
+                    }
+                    ServerMessage::ClearChat(clear) => {
+                        // Bans, timeouts, and clearing the whole chat
+                        if let Err(e) = mod_log.record(ModLogEntry::from(clear)) {
+                            error!("Error writing moderation log: {}", e);
+                        }
+                    }
+                    ServerMessage::ClearMsg(clear) => {
+                        // A single deleted message
+                        if let Err(e) = mod_log.record(ModLogEntry::from(clear)) {
+                            error!("Error writing moderation log: {}", e);
+                        }
+                    }
+                    ServerMessage::RoomState(state) => {
+                        // Remember chat modes so a lockdown can restore them
+                        lockdown.update_room_state(state);
+                    }
+                    ServerMessage::UserState(state) => {
+                        // Moderators and the broadcaster may send messages faster
+                        let is_moderator = state
+                            .badges
+                            .iter()
+                            .any(|badge| badge.name == "moderator" || badge.name == "broadcaster");
+                        reconnect_client.set_moderator(&state.channel_login, is_moderator);
+
+                        // Twitch acknowledges each message sent over IRC with a USERSTATE,
+                        // apart from the one answering the bot's JOIN
+                        delivery_tracker.confirm_next(&state.channel_login);
+                    }
+                    ServerMessage::Reconnect(_) => {
+                        // Twitch is about to drop the connection (e.g. for server
+                        // maintenance), so reconnect now rather than be cut off
+                        reconnect_requested = true;
+                        break;
+                    }
+                    _ => {
+                        debug!("Received other message type: {:?}", msg);
                     }
-                }
-                ServerMessage::Join(join) => {
-                    info!("[JOIN] {} joined the channel", join.user_login);
-                }
-                ServerMessage::Part(part) => {
-                    info!("[PART] {} left the channel", part.user_login);
-                }
-                ServerMessage::Notice(notice) => {
-                    info!("[NOTICE] Channel {}: {}", channel_name, notice.message_text);
-                }
-                _ => {
-                    debug!("Received other message type: {:?}", msg);
                 }
             }
-        }
 
-        // Save known users when shutting down
-        if let Err(e) = user_manager_clone.save().await {
-            error!("Error saving known users: {}", e);
+            // The connection dropped or Twitch asked us to reconnect
+            if reconnect_requested {
+                info!("Twitch asked the bot to reconnect, reconnecting");
+            } else {
+                warn!("Disconnected from Twitch chat");
+            }
+
+            // A connection that dropped before delivering anything counts as a failed attempt
+            if let Some(delay) = backoff.delay_after_disconnect(received, reconnect_requested) {
+                warn!(
+                    "The connection dropped before any messages arrived, waiting {:?}",
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+            }
+
+            // Reconnect with a fresh token, backing off between failed attempts
+            incoming_messages = loop {
+                match reconnect_client.reconnect(&bot_username).await {
+                    Ok(incoming) => break incoming,
+                    Err(e @ (TwitchError::Token(_) | TwitchError::AuthExpired)) => {
+                        let delay = backoff.next_delay();
+                        error!(
+                            "Reconnect failed because the bot couldn't authenticate, retrying in {:?}: {}. If this keeps happening, run `som_chatbot auth --force`.",
+                            delay, e
+                        );
+                        tokio::time::sleep(delay).await;
+                    }
+                    Err(e) => {
+                        let delay = backoff.next_delay();
+                        error!("Reconnect failed, retrying in {:?}: {}", delay, e);
+                        tokio::time::sleep(delay).await;
+                    }
+                }
+            };
         }
     });
 
     // Send a message to the channel to indicate the bot is running
-    client
-        .send_message(
-            &config.channel_name,
-            "SOM Chatbot is now online!",
-            &config.bot_username,
-        )
-        .await?;
-    info!("Sent greeting message to channel: {}", config.channel_name);
+    {
+        let client = client.clone();
+        let channel = config.channel_name.clone();
+        let bot_username = config.bot_username.clone();
+        let (delay, jitter) = (config.greeting_delay, config.greeting_jitter);
+        tokio::spawn(async move {
+            let sent = greeting::send_greeting(delay, jitter, || {
+                let mut client = client.clone();
+                let (channel, bot_username) = (channel.clone(), bot_username.clone());
+                async move {
+                    client
+                        .send_message(&channel, "SOM Chatbot is now online!", &bot_username)
+                        .await?;
+                    Ok(())
+                }
+            })
+            .await;
+            match sent {
+                Ok(()) => info!("Sent greeting message to channel: {}", channel),
+                Err(e) => error!("Couldn't send the online message: {}", e),
+            }
+        });
+    }
 
     // Keep the application running
     info!("Bot is now running. Press Ctrl+C to exit.");
@@ -322,9 +1346,8 @@ async fn start_bot(_debug: bool, prefix: String, channel_override: Option<String
 
     info!("Shutting down...");
 
-    // Save known users before exiting
-    info!("Saving known users...");
-    user_manager.save().await?;
+    // Known users are already in their database; only the feature store may have changes waiting
+    store.flush()?;
 
     Ok(())
 }
@@ -341,6 +1364,137 @@ TWITCH_CHANNEL=channel_name
 TWITCH_BOT_USERNAME=your_bot_username
 # Optional: Data directory for storing tokens and user data
 # DATA_DIR=./data
+# Optional: Where quotes and other feature state are kept: file (store.json) or sqlite (store.db)
+# STORE_BACKEND=file
+# Optional: How often the file store saves changes, in seconds (0 saves every change)
+# STORE_SAVE_INTERVAL_SECS=5
+# Optional: Prefix that marks a chat message as a command
+# COMMAND_PREFIX=!
+# Optional: Prefixes for channels where another bot already uses the main one
+# CHANNEL_PREFIXES=busy_channel=?,other_channel=~
+# Optional: Warn when another bot answers the same commands as this one
+# PREFIX_CONFLICT_DETECTION=false
+# Optional: Features each channel uses (commands, welcomes, filters, announcements, ai, points); unlisted channels use all
+# CHANNEL_FEATURES=busy_channel=welcomes+filters+commands,other_channel=commands
+# Optional: Commands that only work in some channels, and the reply when used elsewhere
+# ({command} and {channels} are replaced; without a reply they're ignored)
+# COMMAND_CHANNELS=deaths=busy_channel+other_channel
+# WRONG_CHANNEL_REPLY={command} only works in {channels}.
+# Optional: Also write logs to this file, rotated daily
+# LOG_FILE=./logs/bot.log
+# Optional: Passphrase used to encrypt the stored OAuth token
+# TOKEN_ENCRYPTION_KEY=change_me
+# Optional: Client secret of a confidential app; authenticates with the authorization code
+# flow instead of the device code flow, catching Twitch's redirect on OAUTH_REDIRECT_URI
+# TWITCH_CLIENT_SECRET=your_client_secret
+# OAUTH_REDIRECT_URI=http://localhost:3000
+# Optional: OpenAI-compatible API for AI-generated welcome messages
+# AI_API_BASE=https://api.openai.com/v1
+# AI_API_KEY=your_api_key
+# AI_MODEL=gpt-4o-mini
+# Optional: What to do with AI replies too long for chat: retry (ask once for a shorter one) or truncate
+# AI_LENGTH_STRATEGY=retry
+# Optional: Recurring announcements separated by "|" (<interval> [HH:MM-HH:MM] <message>)
+# ANNOUNCEMENTS=15m Remember to follow!|1h 18:00-23:00 Check out our sponsor
+# Optional: Chat messages needed between announcements, so the bot doesn't post to an empty channel
+# ANNOUNCEMENT_MIN_CHAT_MESSAGES=0
+# Optional: Post announcements as highlighted Twitch announcements: blue, green, orange, purple, or primary
+# ANNOUNCEMENT_COLOR=
+# Optional: Post a prompt when chat has been quiet this many seconds while live
+# SILENCE_PROMPT_SECS=600
+# SILENCE_PROMPT_MESSAGE=It's awfully quiet in here... how's everyone doing?
+# Optional: Flag messages from accounts younger than this many days
+# NEW_ACCOUNT_MIN_AGE_DAYS=7
+# Optional: What to do with new accounts: flag or timeout
+# NEW_ACCOUNT_ACTION=flag
+# NEW_ACCOUNT_TIMEOUT_SECS=600
+# Optional: Time out links (unless they match AUTOMOD_ALLOW_LINKS), shouting, and emote spam
+# AUTOMOD_BLOCK_LINKS=false
+# AUTOMOD_ALLOW_LINKS=clips\.twitch\.tv|youtube\.com
+# AUTOMOD_DENY_LINKS=
+# AUTOMOD_PERMIT_KNOWN_USERS=false
+# AUTOMOD_CAPS_PERCENT=70
+# AUTOMOD_MAX_EMOTES=10
+# Optional: Automod timeout length in seconds (up to 1209600)
+# AUTOMOD_TIMEOUT_SECS=60
+# Optional: Chat modes !lockdown turns on (minimum follow age in minutes, up to 129600)
+# LOCKDOWN_FOLLOW_MINUTES=10
+# LOCKDOWN_SUBSCRIBERS_ONLY=false
+# LOCKDOWN_EMOTE_ONLY=false
+# Optional: How many raffle entries subscribers and VIPs get
+# RAFFLE_SUBSCRIBER_WEIGHT=2
+# RAFFLE_VIP_WEIGHT=2
+# Optional: Loyalty points each recent chatter earns, and how often
+# POINTS_PER_INTERVAL=10
+# POINTS_INTERVAL_SECS=300
+# Optional: Slot machine symbols and what three of each multiply the bet by
+# SLOTS_PAYOUTS=🍒=2,🍋=3,🔔=5,💎=10,7️⃣=25
+# Optional: How welcomes are paced: immediate, cooldown, or batch
+# WELCOME_MODE=immediate
+# WELCOME_WINDOW_SECS=30
+# Optional: Seconds (up to 3600) after a raid or going live during which welcomes are held and sent together (0 = off)
+# WELCOME_GRACE_SECS=0
+# Optional: Turn welcomes off, or keep canned templates even when an AI API is set
+# WELCOME_ENABLED=true
+# WELCOME_USE_AI=true
+# Optional: Custom welcome templates separated by | (or one per line in DATA_DIR/welcome_messages.txt)
+# WELCOME_MESSAGES=Welcome to the stream, {username}!|Hey {username}, glad you're here!
+# Optional: Comma-separated streamers to shout out the first time they chat
+# AUTO_SHOUTOUT_USERS=friend_one,friend_two
+# Optional: Promo line posted by !so ({login} is replaced with the streamer's login)
+# SHOUTOUT_MESSAGE=Go check out @{login} at https://twitch.tv/{login}!
+# Optional: Reply posted by !8ball ({user}, {question}, and {response} are replaced)
+# EIGHT_BALL_FORMAT=🎱 {response}
+# Optional: Themed 8-ball responses from a TOML file (see README)
+# EIGHT_BALL_FILE=eight_ball.toml
+# Optional: How many of its latest answers the 8-ball avoids repeating (0 allows repeats)
+# EIGHT_BALL_REPEAT_WINDOW=1
+# Optional: Seconds between uses of each command, by anyone and by the same user (0 is no limit)
+# COMMAND_COOLDOWN_SECS=0
+# COMMAND_USER_COOLDOWN_SECS=0
+# Optional: Comma-separated roles that skip command cooldowns (broadcaster, moderator, vip, subscriber)
+# COOLDOWN_EXEMPT_ROLES=broadcaster
+# Optional: Comma-separated commands only followers or subscribers (and mods) can use
+# FOLLOWER_ONLY_COMMANDS=quote,8ball
+# SUBSCRIBER_ONLY_COMMANDS=
+# Optional: Comma-separated counter commands that always exist (e.g. !deaths)
+# COUNTERS=deaths
+# Optional: Language for !uptime units (en, de, es, fr)
+# UPTIME_LOCALE=en
+# Optional: Bot personality preset: friendly, snarky, or formal
+# PERSONALITY=friendly
+# Optional: Text file whose contents replace the personality's AI prompt (reload with !aipersona reload)
+# AI_PROMPT_FILE=ai_prompt.txt
+# Optional: Chat messages per 30 seconds, and the higher limit while the bot is a moderator
+# CHAT_RATE_LIMIT=20
+# CHAT_RATE_LIMIT_MODERATOR=100
+# Optional: Connect to chat over TLS (default true)
+# IRC_TLS=true
+# Optional: Seconds to wait after joining before the online message, plus up to JITTER more at random
+# GREETING_DELAY_SECS=2
+# GREETING_JITTER_SECS=0
+# Optional: Store each new chatter's first message (off by default for privacy)
+# CAPTURE_FIRST_WORDS=false
+# Optional: Warn when Twitch accepts a message but it never shows up in chat
+# CONFIRM_DELIVERY=false
+# Optional: Append bans, timeouts, and deleted messages to DATA_DIR/moderation.log
+# MODERATION_LOG=false
+# Optional: Also receive subscriptions, raids, and channel point redemptions over EventSub
+# EVENTSUB_ENABLED=false
+# Optional: What the bot does when a channel point reward is redeemed over EventSub, as
+# reward=action entries separated by "|". The reward is its ID or title, and the action is
+# "say <message>" or "run <command>" ({username}, {input}, and {reward} are replaced)
+# REDEMPTION_ACTIONS=Hydrate=say Drink some water, {username}!|Shoutout=run !so {input}
+# Optional: Thank-you messages for channel events (set one empty to turn it off)
+# SUB_MESSAGE=Thank you for subscribing, {username}!
+# RESUB_MESSAGE=Thank you for resubscribing for {months} months, {username}!
+# GIFT_SUB_MESSAGE=Thank you {gifter} for gifting a sub to {recipient}!
+# MYSTERY_GIFT_MESSAGE=Thank you {gifter} for gifting {count} subs!
+# RAID_MESSAGE=Thank you for the raid, {username}!
+# Optional: React to "Highlight My Message" redemptions ({username} and {message} are replaced)
+# HIGHLIGHT_MESSAGE=Thanks for the highlight, {username}!
+# Optional: Answer common questions from a TOML rules file (see README)
+# FAQ_FILE=faq.toml
 "#;
 
     let mut file = File::create(path)?;
@@ -0,0 +1,437 @@
+//! Automatic timeouts for links, shouting, and emote spam
+//!
+//! Every chat message is checked before commands run. Messages from moderators
+//! and the broadcaster are never checked.
+
+use anyhow::Result;
+use regex::Regex;
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, info};
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::features::{ChannelFeatures, Feature};
+use crate::moderation::{AuditEntry, ModerationAction, ModerationAuditLog};
+use crate::twitch::{HelixChatClient, MessageSender};
+use crate::users::UserManager;
+
+/// Badges whose holders are never moderated
+const EXEMPT_BADGES: &[&str] = &["broadcaster", "moderator"];
+
+/// How long rule breakers are timed out for by default, in seconds
+const DEFAULT_AUTOMOD_TIMEOUT_SECS: u64 = 60;
+
+/// Messages with fewer letters than this are never counted as shouting
+const MIN_CAPS_LETTERS: usize = 10;
+
+/// Something that looks like a link: a scheme, a www. prefix, or a bare
+/// domain with a common top-level domain
+const LINK_PATTERN: &str = r"(?i)\b(?:https?://\S+|www\.\S+|[a-z0-9-]+(?:\.[a-z0-9-]+)*\.(?:com|net|org|tv|gg|io|ly|me|co|xyz|ru|info|biz|link|app|dev)\b(?:/\S*)?)";
+
+/// Which messages are timed out
+#[derive(Debug, Clone)]
+pub struct AutomodRules {
+    /// Whether links are timed out
+    pub block_links: bool,
+    /// Links matching this are allowed even when links are blocked
+    pub allow_links: Option<Regex>,
+    /// Links matching this are always timed out, even from known users
+    pub deny_links: Option<Regex>,
+    /// Whether chatters the bot already knows may post links
+    pub permit_known_users: bool,
+    /// Highest share of capital letters allowed, as a percentage
+    pub max_caps_percent: Option<u8>,
+    /// Most emotes allowed in one message
+    pub max_emotes: Option<usize>,
+    /// How long rule breakers are timed out for, in seconds
+    pub timeout_secs: u64,
+}
+
+impl Default for AutomodRules {
+    fn default() -> Self {
+        AutomodRules {
+            block_links: false,
+            allow_links: None,
+            deny_links: None,
+            permit_known_users: false,
+            max_caps_percent: None,
+            max_emotes: None,
+            timeout_secs: DEFAULT_AUTOMOD_TIMEOUT_SECS,
+        }
+    }
+}
+
+impl AutomodRules {
+    /// Check whether any rule is turned on
+    pub fn is_active(&self) -> bool {
+        self.block_links
+            || self.deny_links.is_some()
+            || self.max_caps_percent.is_some()
+            || self.max_emotes.is_some()
+    }
+}
+
+/// Why a message was timed out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    /// The message contained a link that isn't allowed
+    Link,
+    /// The message was mostly capital letters
+    Caps,
+    /// The message had too many emotes
+    Emotes,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            Violation::Link => "posting links",
+            Violation::Caps => "excessive caps",
+            Violation::Emotes => "emote spam",
+        };
+        f.write_str(reason)
+    }
+}
+
+/// Times out chatters who break the automod rules
+pub struct ModerationService {
+    /// The client for sending warnings
+    client: Arc<dyn MessageSender>,
+    /// The Helix client for the moderation API
+    helix: Arc<Mutex<HelixChatClient>>,
+    /// The log timeouts are recorded in so they can be undone
+    audit_log: Arc<ModerationAuditLog>,
+    /// The user manager, used to permit known chatters
+    user_manager: Arc<UserManager>,
+    /// Which messages are timed out
+    rules: AutomodRules,
+    /// Finds links in messages
+    link_pattern: Regex,
+    /// Login of the bot, whose own messages are ignored
+    bot_login: String,
+    /// Which features are on in each channel
+    features: Arc<ChannelFeatures>,
+}
+
+impl ModerationService {
+    /// Create a new moderation service
+    ///
+    /// # Arguments
+    /// * `client` - The Twitch client for sending warnings
+    /// * `helix` - The Helix client for the moderation API
+    /// * `audit_log` - The log timeouts are recorded in so they can be undone
+    /// * `user_manager` - The user manager, used to permit known chatters
+    /// * `rules` - Which messages are timed out
+    /// * `bot_login` - Login of the bot, so it doesn't moderate itself
+    ///
+    /// # Returns
+    /// A new ModerationService instance
+    pub fn new(
+        client: Arc<dyn MessageSender>,
+        helix: Arc<Mutex<HelixChatClient>>,
+        audit_log: Arc<ModerationAuditLog>,
+        user_manager: Arc<UserManager>,
+        rules: AutomodRules,
+        bot_login: &str,
+    ) -> Self {
+        ModerationService {
+            client,
+            helix,
+            audit_log,
+            user_manager,
+            rules,
+            link_pattern: Regex::new(LINK_PATTERN).expect("link pattern is valid"),
+            bot_login: bot_login.to_lowercase(),
+            features: Arc::new(ChannelFeatures::default()),
+        }
+    }
+
+    /// Set which features are on in each channel
+    ///
+    /// # Arguments
+    /// * `features` - The features for each channel; messages aren't checked where filters are off
+    pub fn set_features(&mut self, features: Arc<ChannelFeatures>) {
+        self.features = features;
+    }
+
+    /// Check whether a link in a message breaks the rules
+    fn has_forbidden_link(&self, msg: &PrivmsgMessage) -> bool {
+        let mut links = self
+            .link_pattern
+            .find_iter(&msg.message_text)
+            .map(|link| link.as_str());
+
+        links.any(|link| {
+            if self
+                .rules
+                .deny_links
+                .as_ref()
+                .is_some_and(|deny| deny.is_match(link))
+            {
+                return true;
+            }
+            self.rules.block_links
+                && !self
+                    .rules
+                    .allow_links
+                    .as_ref()
+                    .is_some_and(|allow| allow.is_match(link))
+                && !(self.rules.permit_known_users
+                    && self.user_manager.is_known_user(&msg.sender.id))
+        })
+    }
+
+    /// Check whether a message is mostly capital letters, ignoring emotes
+    fn is_shouting(&self, msg: &PrivmsgMessage, max_percent: u8) -> bool {
+        let (mut letters, mut capitals) = (0, 0);
+        for (index, c) in msg.message_text.chars().enumerate() {
+            if !c.is_alphabetic()
+                || msg
+                    .emotes
+                    .iter()
+                    .any(|emote| emote.char_range.contains(&index))
+            {
+                continue;
+            }
+            letters += 1;
+            if c.is_uppercase() {
+                capitals += 1;
+            }
+        }
+        letters >= MIN_CAPS_LETTERS && capitals * 100 > letters * usize::from(max_percent)
+    }
+
+    /// Check a message against the rules
+    ///
+    /// # Arguments
+    /// * `msg` - The chat message to check
+    ///
+    /// # Returns
+    /// The first rule the message breaks, if any
+    pub fn check(&self, msg: &PrivmsgMessage) -> Option<Violation> {
+        let exempt = msg
+            .badges
+            .iter()
+            .any(|badge| EXEMPT_BADGES.contains(&badge.name.as_str()));
+        if exempt
+            || msg.sender.login.eq_ignore_ascii_case(&self.bot_login)
+            || !self
+                .features
+                .is_enabled(&msg.channel_login, Feature::Filters)
+        {
+            return None;
+        }
+
+        if self.has_forbidden_link(msg) {
+            return Some(Violation::Link);
+        }
+        if let Some(max_percent) = self.rules.max_caps_percent
+            && self.is_shouting(msg, max_percent)
+        {
+            return Some(Violation::Caps);
+        }
+        if let Some(max_emotes) = self.rules.max_emotes
+            && msg.emotes.len() > max_emotes
+        {
+            return Some(Violation::Emotes);
+        }
+        None
+    }
+
+    /// Time out the sender of a message that breaks the rules and warn them
+    ///
+    /// # Arguments
+    /// * `msg` - The chat message to process
+    ///
+    /// # Returns
+    /// The rule the message broke, if it was acted on
+    pub async fn process_message(&self, msg: &PrivmsgMessage) -> Result<Option<Violation>> {
+        let Some(violation) = self.check(msg) else {
+            return Ok(None);
+        };
+        debug!("{} broke the automod rules: {}", msg.sender.name, violation);
+
+        let duration_secs = self.rules.timeout_secs;
+        {
+            let mut helix = self.helix.lock().await;
+            let moderator_id = helix.get_bot_user_id().await?;
+            helix
+                .ban_user(
+                    &msg.channel_id,
+                    &moderator_id,
+                    &msg.sender.id,
+                    Some(duration_secs),
+                    &format!("Automod: {}", violation),
+                )
+                .await?;
+        }
+
+        info!(
+            "Timed out {} for {}s ({})",
+            msg.sender.name, duration_secs, violation
+        );
+        self.audit_log.record(AuditEntry::new(
+            ModerationAction::Timeout { duration_secs },
+            &msg.channel_id,
+            &msg.sender.id,
+            &msg.sender.login,
+        ));
+
+        let warning = format!(
+            "@{}, no {} please! (timed out for {}s)",
+            msg.sender.name, violation, duration_secs
+        );
+        self.client.say(&msg.channel_login, &warning).await?;
+        Ok(Some(violation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{
+        create_authenticated_oauth_manager, create_test_privmsg_with_badges,
+    };
+    use crate::users::MockTwitchClient;
+    use mockito::{Matcher, Server};
+    use twitch_irc::message::Emote;
+
+    async fn create_service(server: &Server, rules: AutomodRules) -> Result<ModerationService> {
+        let helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+        Ok(ModerationService::new(
+            Arc::new(MockTwitchClient::new()),
+            Arc::new(Mutex::new(helix)),
+            Arc::new(ModerationAuditLog::new()),
+            Arc::new(UserManager::new("test_users.db")?),
+            rules,
+            "test_bot",
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_link_rules() -> Result<()> {
+        let server = Server::new_async().await;
+        let service = create_service(
+            &server,
+            AutomodRules {
+                block_links: true,
+                allow_links: Some(Regex::new(r"clips\.twitch\.tv")?),
+                deny_links: Some(Regex::new(r"(?i)free-followers")?),
+                permit_known_users: true,
+                ..AutomodRules::default()
+            },
+        )
+        .await?;
+
+        let link = create_test_privmsg_with_badges("check out https://example.com/x", &[]);
+        assert_eq!(service.check(&link), Some(Violation::Link));
+        let bare = create_test_privmsg_with_badges("go to spam.xyz now", &[]);
+        assert_eq!(service.check(&bare), Some(Violation::Link));
+        let allowed = create_test_privmsg_with_badges("lol https://clips.twitch.tv/abc", &[]);
+        assert_eq!(service.check(&allowed), None);
+        let plain = create_test_privmsg_with_badges("ok. sounds good.", &[]);
+        assert_eq!(service.check(&plain), None);
+
+        // Moderators are never checked
+        let moderator = create_test_privmsg_with_badges("https://example.com", &["moderator"]);
+        assert_eq!(service.check(&moderator), None);
+
+        // Known chatters may post links, but not denied ones
+        service.user_manager.is_first_time_chatter(&link.sender.id);
+        assert_eq!(service.check(&link), None);
+        let denied = create_test_privmsg_with_badges("www.Free-Followers.com", &[]);
+        assert_eq!(service.check(&denied), Some(Violation::Link));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_caps_and_emote_limits() -> Result<()> {
+        let server = Server::new_async().await;
+        let service = create_service(
+            &server,
+            AutomodRules {
+                max_caps_percent: Some(70),
+                max_emotes: Some(2),
+                ..AutomodRules::default()
+            },
+        )
+        .await?;
+
+        let shouting = create_test_privmsg_with_badges("WHY IS NOBODY LISTENING", &[]);
+        assert_eq!(service.check(&shouting), Some(Violation::Caps));
+        // Short messages and links are fine
+        assert_eq!(
+            service.check(&create_test_privmsg_with_badges("GG WP", &[])),
+            None
+        );
+        assert_eq!(
+            service.check(&create_test_privmsg_with_badges("https://example.com", &[])),
+            None
+        );
+
+        let mut emotes = create_test_privmsg_with_badges("KAPPA KAPPA KAPPA hi", &[]);
+        emotes.emotes = (0..3)
+            .map(|i| Emote {
+                id: "25".to_string(),
+                char_range: i * 6..i * 6 + 5,
+                code: "KAPPA".to_string(),
+            })
+            .collect();
+        assert_eq!(service.check(&emotes), Some(Violation::Emotes));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_violations_are_timed_out() -> Result<()> {
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/users")
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"999","login":"test_bot","display_name":"Test_Bot"}]}"#)
+            .create_async()
+            .await;
+        let timeout = server
+            .mock("POST", "/moderation/bans")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("broadcaster_id".into(), "456".into()),
+                Matcher::UrlEncoded("moderator_id".into(), "999".into()),
+            ]))
+            .match_body(Matcher::Json(serde_json::json!({
+                "data": {"user_id": "123", "duration": 30, "reason": "Automod: posting links"}
+            })))
+            .with_status(200)
+            .with_body("{}")
+            .create_async()
+            .await;
+
+        let client = MockTwitchClient::new();
+        let mut service = create_service(
+            &server,
+            AutomodRules {
+                block_links: true,
+                timeout_secs: 30,
+                ..AutomodRules::default()
+            },
+        )
+        .await?;
+        service.client = Arc::new(client.clone());
+
+        let msg = create_test_privmsg_with_badges("buy followers at cheap.biz", &[]);
+        assert_eq!(service.process_message(&msg).await?, Some(Violation::Link));
+        timeout.assert_async().await;
+
+        assert_eq!(
+            client.sent_messages(),
+            vec![(
+                "test_channel".to_string(),
+                "@Test_User, no posting links please! (timed out for 30s)".to_string()
+            )]
+        );
+        // The timeout can be undone
+        assert!(service.audit_log.pop_last_for_channel("456").is_some());
+        Ok(())
+    }
+}
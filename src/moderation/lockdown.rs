@@ -0,0 +1,429 @@
+//! Anti-raid lockdown that restricts chat and restores it afterwards
+//!
+//! Turning the lockdown on remembers the channel's chat modes from the latest
+//! ROOMSTATE, switches on followers-only mode (plus subscribers-only and
+//! emote-only mode if configured), and enables new account flagging in that
+//! channel. Turning it off puts everything back the way it was. If no
+//! ROOMSTATE had been seen, the earlier chat modes are unknown and are left
+//! for moderators to restore rather than guessed.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+use twitch_irc::message::{FollowersOnlyMode, RoomStateMessage};
+
+use crate::moderation::NewAccountGuard;
+use crate::twitch::{ChatSettings, HelixChatClient};
+
+/// Followers-only minimum follow age used when none is configured
+pub const DEFAULT_LOCKDOWN_FOLLOW_MINUTES: u64 = 10;
+
+/// The longest followers-only follow age Twitch accepts (three months)
+pub const MAX_LOCKDOWN_FOLLOW_MINUTES: u64 = 129_600;
+
+/// How lifting a lockdown went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiftOutcome {
+    /// The channel wasn't locked down
+    NotActive,
+    /// The chat modes from before the lockdown were put back
+    Restored,
+    /// The lockdown ended, but the chat modes from before it weren't known
+    ChatModesUnknown,
+}
+
+/// Where chat mode changes are sent
+#[async_trait]
+pub trait ChatSettingsApi: Send + Sync {
+    /// Change a channel's chat modes
+    ///
+    /// # Arguments
+    /// * `broadcaster_id` - ID of the channel to change
+    /// * `settings` - The modes to set
+    async fn update_chat_settings(
+        &self,
+        broadcaster_id: &str,
+        settings: &ChatSettings,
+    ) -> Result<()>;
+}
+
+#[async_trait]
+impl ChatSettingsApi for tokio::sync::Mutex<HelixChatClient> {
+    async fn update_chat_settings(
+        &self,
+        broadcaster_id: &str,
+        settings: &ChatSettings,
+    ) -> Result<()> {
+        self.lock()
+            .await
+            .update_chat_settings(broadcaster_id, settings)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Which restrictions a lockdown turns on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockdownSettings {
+    /// How many minutes someone must have followed for to chat
+    pub follow_minutes: u64,
+    /// Whether to also make chat subscribers-only
+    pub subscribers_only: bool,
+    /// Whether to also make chat emote-only
+    pub emote_only: bool,
+}
+
+impl Default for LockdownSettings {
+    fn default() -> Self {
+        LockdownSettings {
+            follow_minutes: DEFAULT_LOCKDOWN_FOLLOW_MINUTES,
+            subscribers_only: false,
+            emote_only: false,
+        }
+    }
+}
+
+impl LockdownSettings {
+    /// Get the chat modes to switch to
+    fn chat_settings(&self) -> ChatSettings {
+        ChatSettings {
+            follower_mode: true,
+            follower_mode_duration: Some(self.follow_minutes),
+            subscriber_mode: self.subscribers_only,
+            emote_mode: self.emote_only,
+        }
+    }
+}
+
+/// What to restore when a lockdown ends
+#[derive(Debug, Clone, Copy)]
+struct SavedState {
+    /// Chat modes from before the lockdown, if a ROOMSTATE had been seen
+    chat_settings: Option<ChatSettings>,
+    /// Whether new account flagging was on before the lockdown
+    new_account_check: bool,
+}
+
+/// Applies and lifts anti-raid lockdowns
+pub struct Lockdown {
+    /// Where chat mode changes are sent
+    api: Arc<dyn ChatSettingsApi>,
+    /// New account flagging, switched on during a lockdown
+    guard: Arc<NewAccountGuard>,
+    /// Which restrictions to turn on
+    settings: LockdownSettings,
+    /// Latest chat modes of each channel, by channel ID
+    room_states: Mutex<HashMap<String, ChatSettings>>,
+    /// State to restore for each locked down channel, by channel ID
+    saved: Mutex<HashMap<String, SavedState>>,
+}
+
+impl Lockdown {
+    /// Create a new lockdown controller
+    ///
+    /// # Arguments
+    /// * `api` - Where chat mode changes are sent
+    /// * `guard` - New account flagging to switch on during a lockdown
+    /// * `settings` - Which restrictions to turn on
+    ///
+    /// # Returns
+    /// A new Lockdown instance
+    pub fn new(
+        api: Arc<dyn ChatSettingsApi>,
+        guard: Arc<NewAccountGuard>,
+        settings: LockdownSettings,
+    ) -> Self {
+        Lockdown {
+            api,
+            guard,
+            settings,
+            room_states: Mutex::new(HashMap::new()),
+            saved: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Track a channel's chat modes from a ROOMSTATE message
+    ///
+    /// ROOMSTATE on join carries every mode; later ones only carry what changed.
+    ///
+    /// # Arguments
+    /// * `msg` - The ROOMSTATE message
+    pub fn update_room_state(&self, msg: &RoomStateMessage) {
+        let mut room_states = self.room_states.lock().unwrap();
+        let state = room_states.entry(msg.channel_id.clone()).or_default();
+
+        match msg.follwers_only {
+            Some(FollowersOnlyMode::Enabled(duration)) => {
+                state.follower_mode = true;
+                state.follower_mode_duration = Some(duration.as_secs() / 60);
+            }
+            Some(FollowersOnlyMode::Disabled) => {
+                state.follower_mode = false;
+                state.follower_mode_duration = None;
+            }
+            None => {}
+        }
+        if let Some(subscribers_only) = msg.subscribers_only {
+            state.subscriber_mode = subscribers_only;
+        }
+        if let Some(emote_only) = msg.emote_only {
+            state.emote_mode = emote_only;
+        }
+    }
+
+    /// Check whether a channel is locked down
+    ///
+    /// # Arguments
+    /// * `broadcaster_id` - ID of the channel
+    pub fn is_active(&self, broadcaster_id: &str) -> bool {
+        self.saved.lock().unwrap().contains_key(broadcaster_id)
+    }
+
+    /// Lock a channel down
+    ///
+    /// # Arguments
+    /// * `broadcaster_id` - ID of the channel to lock down
+    ///
+    /// # Returns
+    /// false if the channel was already locked down
+    pub async fn enable(&self, broadcaster_id: &str) -> Result<bool> {
+        if self.is_active(broadcaster_id) {
+            return Ok(false);
+        }
+
+        let saved = SavedState {
+            chat_settings: self
+                .room_states
+                .lock()
+                .unwrap()
+                .get(broadcaster_id)
+                .copied(),
+            new_account_check: self.guard.is_enabled_in(broadcaster_id),
+        };
+
+        self.api
+            .update_chat_settings(broadcaster_id, &self.settings.chat_settings())
+            .await?;
+        self.guard.set_enabled_in(broadcaster_id, true);
+        self.saved
+            .lock()
+            .unwrap()
+            .insert(broadcaster_id.to_string(), saved);
+
+        info!("Lockdown enabled in channel {}", broadcaster_id);
+        Ok(true)
+    }
+
+    /// Lift a lockdown, restoring the chat modes from before it
+    ///
+    /// # Arguments
+    /// * `broadcaster_id` - ID of the channel to restore
+    ///
+    /// # Returns
+    /// Whether the chat modes were restored, or that the channel wasn't locked down
+    pub async fn disable(&self, broadcaster_id: &str) -> Result<LiftOutcome> {
+        let Some(saved) = self.saved.lock().unwrap().get(broadcaster_id).copied() else {
+            return Ok(LiftOutcome::NotActive);
+        };
+
+        let outcome = match &saved.chat_settings {
+            Some(chat_settings) => {
+                self.api
+                    .update_chat_settings(broadcaster_id, chat_settings)
+                    .await?;
+                LiftOutcome::Restored
+            }
+            None => {
+                warn!(
+                    "Chat modes from before the lockdown in channel {} are unknown, leaving them as they are",
+                    broadcaster_id
+                );
+                LiftOutcome::ChatModesUnknown
+            }
+        };
+        self.guard
+            .set_enabled_in(broadcaster_id, saved.new_account_check);
+        self.saved.lock().unwrap().remove(broadcaster_id);
+
+        info!("Lockdown lifted in channel {}", broadcaster_id);
+        Ok(outcome)
+    }
+
+    /// Describe the restrictions a lockdown turns on
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!(
+            "followers-only ({} min)",
+            self.settings.follow_minutes
+        )];
+        if self.settings.subscribers_only {
+            parts.push("subscribers-only".to_string());
+        }
+        if self.settings.emote_only {
+            parts.push("emote-only".to_string());
+        }
+        parts.push("new account flagging".to_string());
+        parts.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moderation::NewAccountAction;
+    use crate::moderation::new_account::AccountLookup;
+    use crate::users::UserManager;
+    use chrono::{DateTime, Utc};
+    use twitch_irc::message::{AsRawIRC, IRCMessage, ServerMessage};
+
+    /// Chat settings API that records every change
+    #[derive(Default)]
+    struct MockChatSettings {
+        updates: Mutex<Vec<(String, ChatSettings)>>,
+    }
+
+    #[async_trait]
+    impl ChatSettingsApi for MockChatSettings {
+        async fn update_chat_settings(
+            &self,
+            broadcaster_id: &str,
+            settings: &ChatSettings,
+        ) -> Result<()> {
+            self.updates
+                .lock()
+                .unwrap()
+                .push((broadcaster_id.to_string(), *settings));
+            Ok(())
+        }
+    }
+
+    /// Account lookup that is never consulted in these tests
+    struct NoLookup;
+
+    #[async_trait]
+    impl AccountLookup for NoLookup {
+        async fn account_created_at(&self, _user_id: &str) -> Result<DateTime<Utc>> {
+            Ok(Utc::now())
+        }
+    }
+
+    fn room_state(raw: &str) -> RoomStateMessage {
+        let irc = IRCMessage::parse(raw).unwrap();
+        match ServerMessage::try_from(irc).unwrap() {
+            ServerMessage::RoomState(state) => state,
+            other => panic!("not a ROOMSTATE: {}", other.source().as_raw_irc()),
+        }
+    }
+
+    fn create_lockdown(
+        settings: LockdownSettings,
+    ) -> (Lockdown, Arc<MockChatSettings>, Arc<NewAccountGuard>) {
+        let api = Arc::new(MockChatSettings::default());
+        let guard = Arc::new(NewAccountGuard::new(
+            Arc::new(NoLookup),
+            Arc::new(UserManager::new("test.db").unwrap()),
+            chrono::Duration::days(7),
+            NewAccountAction::Flag,
+        ));
+        guard.set_enabled(false);
+        let lockdown = Lockdown::new(api.clone(), guard.clone(), settings);
+        (lockdown, api, guard)
+    }
+
+    #[tokio::test]
+    async fn test_lockdown_applies_settings_and_restores_prior_state() -> Result<()> {
+        let settings = LockdownSettings {
+            follow_minutes: 30,
+            subscribers_only: false,
+            emote_only: true,
+        };
+        let (lockdown, api, guard) = create_lockdown(settings);
+
+        // Before the raid the channel had subscribers-only mode on
+        lockdown.update_room_state(&room_state(
+            "@emote-only=0;followers-only=-1;r9k=0;room-id=456;slow=0;subs-only=1 :tmi.twitch.tv ROOMSTATE #test_channel",
+        ));
+
+        assert!(lockdown.enable("456").await?);
+        assert!(lockdown.is_active("456"));
+        assert!(guard.is_enabled_in("456"));
+        // Other channels keep their own new account setting
+        assert!(!guard.is_enabled_in("789"));
+        assert!(!lockdown.enable("456").await?);
+
+        assert_eq!(lockdown.disable("456").await?, LiftOutcome::Restored);
+        assert!(!lockdown.is_active("456"));
+        assert!(!guard.is_enabled_in("456"));
+        assert_eq!(lockdown.disable("456").await?, LiftOutcome::NotActive);
+
+        let updates = api.updates.lock().unwrap().clone();
+        assert_eq!(
+            updates,
+            vec![
+                (
+                    "456".to_string(),
+                    ChatSettings {
+                        follower_mode: true,
+                        follower_mode_duration: Some(30),
+                        subscriber_mode: false,
+                        emote_mode: true,
+                    }
+                ),
+                (
+                    "456".to_string(),
+                    ChatSettings {
+                        follower_mode: false,
+                        follower_mode_duration: None,
+                        subscriber_mode: true,
+                        emote_mode: false,
+                    }
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unknown_chat_modes_are_left_alone() -> Result<()> {
+        let (lockdown, api, guard) = create_lockdown(LockdownSettings::default());
+
+        // No ROOMSTATE was seen, so there's nothing known to restore
+        lockdown.enable("456").await?;
+        assert_eq!(
+            lockdown.disable("456").await?,
+            LiftOutcome::ChatModesUnknown
+        );
+        assert_eq!(api.updates.lock().unwrap().len(), 1);
+        assert!(!guard.is_enabled_in("456"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_room_state_updates_are_merged() -> Result<()> {
+        let (lockdown, api, _guard) = create_lockdown(LockdownSettings::default());
+
+        lockdown.update_room_state(&room_state(
+            "@emote-only=0;followers-only=-1;r9k=0;room-id=456;slow=0;subs-only=0 :tmi.twitch.tv ROOMSTATE #test_channel",
+        ));
+        // A partial update only changes followers-only mode
+        lockdown.update_room_state(&room_state(
+            "@followers-only=5;room-id=456 :tmi.twitch.tv ROOMSTATE #test_channel",
+        ));
+
+        lockdown.enable("456").await?;
+        lockdown.disable("456").await?;
+
+        let restored = api.updates.lock().unwrap()[1].1;
+        assert_eq!(
+            restored,
+            ChatSettings {
+                follower_mode: true,
+                follower_mode_duration: Some(5),
+                subscriber_mode: false,
+                emote_mode: false,
+            }
+        );
+        Ok(())
+    }
+}
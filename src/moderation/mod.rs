@@ -0,0 +1,143 @@
+mod automod;
+mod lockdown;
+mod mod_log;
+mod new_account;
+
+use chrono::{DateTime, Utc};
+use std::sync::Mutex;
+
+pub use automod::{AutomodRules, ModerationService};
+pub use lockdown::{LiftOutcome, Lockdown, LockdownSettings, MAX_LOCKDOWN_FOLLOW_MINUTES};
+pub use mod_log::{ModLog, ModLogEntry};
+pub use new_account::{NewAccountAction, NewAccountGuard};
+
+/// Longest timeout Twitch allows (2 weeks)
+pub const MAX_TIMEOUT_SECS: u64 = 1_209_600;
+
+/// A moderation action the bot performed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModerationAction {
+    /// A permanent ban
+    Ban,
+    /// A timeout for the given number of seconds
+    Timeout { duration_secs: u64 },
+}
+
+/// A single entry in the moderation audit log
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    /// The action that was taken
+    pub action: ModerationAction,
+    /// ID of the channel the action was taken in
+    pub broadcaster_id: String,
+    /// ID of the user the action was taken against
+    pub user_id: String,
+    /// Login of the user the action was taken against
+    pub user_login: String,
+    /// When the action was taken
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditEntry {
+    /// Create a new audit entry timestamped now
+    ///
+    /// # Arguments
+    /// * `action` - The action that was taken
+    /// * `broadcaster_id` - ID of the channel the action was taken in
+    /// * `user_id` - ID of the affected user
+    /// * `user_login` - Login of the affected user
+    ///
+    /// # Returns
+    /// A new AuditEntry instance
+    pub fn new(
+        action: ModerationAction,
+        broadcaster_id: &str,
+        user_id: &str,
+        user_login: &str,
+    ) -> Self {
+        AuditEntry {
+            action,
+            broadcaster_id: broadcaster_id.to_string(),
+            user_id: user_id.to_string(),
+            user_login: user_login.to_string(),
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// In-memory log of the moderation actions taken by the bot
+pub struct ModerationAuditLog {
+    /// Entries in the order they were recorded
+    entries: Mutex<Vec<AuditEntry>>,
+}
+
+impl Default for ModerationAuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModerationAuditLog {
+    /// Create a new empty audit log
+    pub fn new() -> Self {
+        ModerationAuditLog {
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Record a moderation action
+    ///
+    /// # Arguments
+    /// * `entry` - The entry to append to the log
+    pub fn record(&self, entry: AuditEntry) {
+        self.entries.lock().unwrap().push(entry);
+    }
+
+    /// Remove and return the most recent action taken in a channel
+    ///
+    /// # Arguments
+    /// * `broadcaster_id` - ID of the channel to look in
+    ///
+    /// # Returns
+    /// The most recent entry for the channel, or None if there is none
+    pub fn pop_last_for_channel(&self, broadcaster_id: &str) -> Option<AuditEntry> {
+        let mut entries = self.entries.lock().unwrap();
+        let index = entries
+            .iter()
+            .rposition(|entry| entry.broadcaster_id == broadcaster_id)?;
+        Some(entries.remove(index))
+    }
+
+    /// Get the number of recorded entries
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pop_last_for_channel() {
+        let log = ModerationAuditLog::new();
+        log.record(AuditEntry::new(ModerationAction::Ban, "1", "10", "first"));
+        log.record(AuditEntry::new(
+            ModerationAction::Timeout { duration_secs: 60 },
+            "1",
+            "11",
+            "second",
+        ));
+        log.record(AuditEntry::new(ModerationAction::Ban, "2", "12", "other"));
+
+        // The most recent entry for channel 1 is the timeout, not channel 2's ban
+        let entry = log.pop_last_for_channel("1").unwrap();
+        assert_eq!(entry.user_login, "second");
+        assert_eq!(log.len(), 2);
+
+        assert_eq!(log.pop_last_for_channel("1").unwrap().user_login, "first");
+        assert!(log.pop_last_for_channel("1").is_none());
+        assert_eq!(log.len(), 1);
+    }
+}
@@ -0,0 +1,289 @@
+//! Log of bans, timeouts, and deleted messages seen in chat
+//!
+//! Twitch announces moderation by anyone, not just the bot, with `CLEARCHAT`
+//! (bans, timeouts, and clearing the whole chat) and `CLEARMSG` (a single
+//! deleted message). Each one is logged, kept in memory for commands to look
+//! at, and optionally appended to a file.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::info;
+use twitch_irc::message::{ClearChatAction, ClearChatMessage, ClearMsgMessage};
+
+use crate::duration::format_duration;
+
+/// How many recent entries are kept in memory
+const RECENT_ENTRIES: usize = 200;
+
+/// What happened in chat
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModLogEvent {
+    /// A moderator cleared the whole chat
+    ChatCleared,
+    /// A user was banned
+    Ban { user_login: String, user_id: String },
+    /// A user was timed out
+    Timeout {
+        user_login: String,
+        user_id: String,
+        duration: Duration,
+    },
+    /// A single message was deleted
+    MessageDeleted {
+        user_login: String,
+        message_id: String,
+        text: String,
+    },
+}
+
+/// A moderation event seen in a channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModLogEntry {
+    /// The channel it happened in
+    pub channel: String,
+    /// What happened
+    pub event: ModLogEvent,
+    /// When Twitch says it happened
+    pub timestamp: DateTime<Utc>,
+}
+
+impl ModLogEntry {
+    /// Get the user the event affected, if it affected one
+    pub fn user_login(&self) -> Option<&str> {
+        match &self.event {
+            ModLogEvent::ChatCleared => None,
+            ModLogEvent::Ban { user_login, .. }
+            | ModLogEvent::Timeout { user_login, .. }
+            | ModLogEvent::MessageDeleted { user_login, .. } => Some(user_login),
+        }
+    }
+
+    /// Describe the event in one line
+    pub fn describe(&self) -> String {
+        let what = match &self.event {
+            ModLogEvent::ChatCleared => "chat was cleared".to_string(),
+            ModLogEvent::Ban {
+                user_login,
+                user_id,
+            } => format!("{} ({}) was banned", user_login, user_id),
+            ModLogEvent::Timeout {
+                user_login,
+                user_id,
+                duration,
+            } => format!(
+                "{} ({}) was timed out for {}",
+                user_login,
+                user_id,
+                format_duration(*duration)
+            ),
+            ModLogEvent::MessageDeleted {
+                user_login, text, ..
+            } => format!("message from {} was deleted: {}", user_login, text),
+        };
+        format!("#{}: {}", self.channel, what)
+    }
+}
+
+impl From<&ClearChatMessage> for ModLogEntry {
+    fn from(msg: &ClearChatMessage) -> Self {
+        let event = match &msg.action {
+            ClearChatAction::ChatCleared => ModLogEvent::ChatCleared,
+            ClearChatAction::UserBanned {
+                user_login,
+                user_id,
+            } => ModLogEvent::Ban {
+                user_login: user_login.clone(),
+                user_id: user_id.clone(),
+            },
+            ClearChatAction::UserTimedOut {
+                user_login,
+                user_id,
+                timeout_length,
+            } => ModLogEvent::Timeout {
+                user_login: user_login.clone(),
+                user_id: user_id.clone(),
+                duration: *timeout_length,
+            },
+        };
+        ModLogEntry {
+            channel: msg.channel_login.clone(),
+            event,
+            timestamp: msg.server_timestamp,
+        }
+    }
+}
+
+impl From<&ClearMsgMessage> for ModLogEntry {
+    fn from(msg: &ClearMsgMessage) -> Self {
+        ModLogEntry {
+            channel: msg.channel_login.clone(),
+            event: ModLogEvent::MessageDeleted {
+                user_login: msg.sender_login.clone(),
+                message_id: msg.message_id.clone(),
+                text: msg.message_text.clone(),
+            },
+            timestamp: msg.server_timestamp,
+        }
+    }
+}
+
+/// Keeps the moderation events seen in chat
+pub struct ModLog {
+    /// File to append each entry to, if any
+    path: Option<PathBuf>,
+    /// The most recent entries, oldest first
+    recent: Mutex<VecDeque<ModLogEntry>>,
+}
+
+impl ModLog {
+    /// Create a new moderation log
+    ///
+    /// # Arguments
+    /// * `path` - File to append each entry to, or None to only keep them in memory
+    ///
+    /// # Returns
+    /// A new ModLog instance
+    pub fn new(path: Option<PathBuf>) -> Self {
+        ModLog {
+            path,
+            recent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Record a moderation event
+    ///
+    /// # Arguments
+    /// * `entry` - The event to record
+    ///
+    /// # Returns
+    /// A Result indicating whether the entry could be written to the file
+    pub fn record(&self, entry: ModLogEntry) -> Result<()> {
+        info!("[MOD] {}", entry.describe());
+        let line = format!("{} {}", entry.timestamp.to_rfc3339(), entry.describe());
+
+        // Keep the entry even if the file can't be written
+        {
+            let mut recent = self.recent.lock().unwrap();
+            if recent.len() == RECENT_ENTRIES {
+                recent.pop_front();
+            }
+            recent.push_back(entry);
+        }
+
+        if let Some(path) = &self.path {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    /// Get the most recent entries, oldest first
+    pub fn recent(&self) -> Vec<ModLogEntry> {
+        self.recent.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Get the recent entries that affected a user, oldest first
+    ///
+    /// # Arguments
+    /// * `user_login` - Login of the user
+    pub fn for_user(&self, user_login: &str) -> Vec<ModLogEntry> {
+        self.recent
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| {
+                entry
+                    .user_login()
+                    .is_some_and(|login| login.eq_ignore_ascii_case(user_login))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use twitch_irc::message::IRCMessage;
+
+    fn clear_chat(src: &str) -> ClearChatMessage {
+        ClearChatMessage::try_from(IRCMessage::parse(src).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_entries_from_irc() {
+        let timeout = ModLogEntry::from(&clear_chat(
+            "@ban-duration=600;room-id=456;target-user-id=300;tmi-sent-ts=1594553828245 :tmi.twitch.tv CLEARCHAT #test_channel :spammer",
+        ));
+        assert_eq!(
+            timeout.event,
+            ModLogEvent::Timeout {
+                user_login: "spammer".to_string(),
+                user_id: "300".to_string(),
+                duration: Duration::from_secs(600),
+            }
+        );
+        assert_eq!(
+            timeout.describe(),
+            "#test_channel: spammer (300) was timed out for 10m"
+        );
+
+        let cleared = ModLogEntry::from(&clear_chat(
+            "@room-id=456;tmi-sent-ts=1594553828245 :tmi.twitch.tv CLEARCHAT #test_channel",
+        ));
+        assert_eq!(cleared.event, ModLogEvent::ChatCleared);
+        assert_eq!(cleared.user_login(), None);
+
+        let deleted = ModLogEntry::from(
+            &ClearMsgMessage::try_from(
+                IRCMessage::parse(
+                    "@login=spammer;room-id=;target-msg-id=abc;tmi-sent-ts=1594561955611 :tmi.twitch.tv CLEARMSG #test_channel :buy followers",
+                )
+                .unwrap(),
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            deleted.describe(),
+            "#test_channel: message from spammer was deleted: buy followers"
+        );
+    }
+
+    #[test]
+    fn test_record_appends_to_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("moderation.log");
+        let log = ModLog::new(Some(path.clone()));
+
+        log.record(ModLogEntry::from(&clear_chat(
+            "@room-id=456;target-user-id=300;tmi-sent-ts=1594561360331 :tmi.twitch.tv CLEARCHAT #test_channel :spammer",
+        )))?;
+        log.record(ModLogEntry::from(&clear_chat(
+            "@room-id=456;tmi-sent-ts=1594561360331 :tmi.twitch.tv CLEARCHAT #test_channel",
+        )))?;
+
+        let contents = std::fs::read_to_string(&path)?;
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("#test_channel: spammer (300) was banned"));
+        assert_eq!(log.recent().len(), 2);
+        assert_eq!(log.for_user("Spammer").len(), 1);
+
+        // An unwritable file still leaves the entry in memory
+        let log = ModLog::new(Some(dir.path().join("missing").join("moderation.log")));
+        assert!(
+            log.record(ModLogEntry::from(&clear_chat(
+                "@room-id=456;tmi-sent-ts=1594561360331 :tmi.twitch.tv CLEARCHAT #test_channel",
+            )))
+            .is_err()
+        );
+        assert_eq!(log.recent().len(), 1);
+        Ok(())
+    }
+}
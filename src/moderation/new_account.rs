@@ -0,0 +1,347 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::features::{ChannelFeatures, Feature};
+use crate::moderation::{AuditEntry, ModerationAction, ModerationAuditLog};
+use crate::twitch::HelixChatClient;
+use crate::users::UserManager;
+
+/// Badges whose holders are never flagged as suspicious
+const EXEMPT_BADGES: &[&str] = &["broadcaster", "moderator", "vip", "subscriber", "founder"];
+
+/// What to do when a message comes from an account that is too new
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewAccountAction {
+    /// Log a warning so moderators can review the account
+    Flag,
+    /// Time the account out for the given number of seconds
+    Timeout(u64),
+}
+
+impl FromStr for NewAccountAction {
+    type Err = anyhow::Error;
+
+    /// Parse an action like "flag" or "timeout" (uses a 10 minute timeout)
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "flag" => Ok(NewAccountAction::Flag),
+            "timeout" => Ok(NewAccountAction::Timeout(600)),
+            other => Err(anyhow!("Unknown new account action: {}", other)),
+        }
+    }
+}
+
+/// Source of account creation dates
+#[async_trait]
+pub trait AccountLookup: Send + Sync {
+    /// Get when a user's account was created
+    ///
+    /// # Arguments
+    /// * `user_id` - The ID of the user to look up
+    async fn account_created_at(&self, user_id: &str) -> Result<DateTime<Utc>>;
+}
+
+#[async_trait]
+impl AccountLookup for Mutex<HelixChatClient> {
+    async fn account_created_at(&self, user_id: &str) -> Result<DateTime<Utc>> {
+        self.lock()
+            .await
+            .get_user_by_id(user_id)
+            .await?
+            .created_at
+            .ok_or_else(|| anyhow!("No creation date returned for user {}", user_id))
+    }
+}
+
+/// Flags or restricts messages from recently created accounts
+pub struct NewAccountGuard {
+    /// Where account creation dates come from
+    lookup: Arc<dyn AccountLookup>,
+    /// The user manager, used to exempt known chatters
+    user_manager: Arc<UserManager>,
+    /// Accounts younger than this are flagged
+    min_age: Duration,
+    /// What to do with flagged accounts
+    action: NewAccountAction,
+    /// Users that have already passed the check
+    cleared_users: RwLock<HashSet<String>>,
+    /// Helix client and audit log used for timeouts
+    moderation: Option<(Arc<Mutex<HelixChatClient>>, Arc<ModerationAuditLog>)>,
+    /// Whether messages are checked in channels without their own setting
+    enabled: AtomicBool,
+    /// Channels where checking was turned on or off, e.g. by a lockdown, by channel ID
+    channel_enabled: RwLock<HashMap<String, bool>>,
+    /// Which features are on in each channel
+    features: Arc<ChannelFeatures>,
+}
+
+impl NewAccountGuard {
+    /// Create a new account guard
+    ///
+    /// # Arguments
+    /// * `lookup` - Source of account creation dates
+    /// * `user_manager` - The user manager, used to exempt known chatters
+    /// * `min_age` - Accounts younger than this are flagged
+    /// * `action` - What to do with flagged accounts
+    ///
+    /// # Returns
+    /// A new NewAccountGuard instance
+    pub fn new(
+        lookup: Arc<dyn AccountLookup>,
+        user_manager: Arc<UserManager>,
+        min_age: Duration,
+        action: NewAccountAction,
+    ) -> Self {
+        NewAccountGuard {
+            lookup,
+            user_manager,
+            min_age,
+            action,
+            cleared_users: RwLock::new(HashSet::new()),
+            moderation: None,
+            enabled: AtomicBool::new(true),
+            channel_enabled: RwLock::new(HashMap::new()),
+            features: Arc::new(ChannelFeatures::default()),
+        }
+    }
+
+    /// Set which features are on in each channel
+    ///
+    /// # Arguments
+    /// * `features` - The features for each channel; messages aren't checked where filters are off
+    pub fn set_features(&mut self, features: Arc<ChannelFeatures>) {
+        self.features = features;
+    }
+
+    /// Turn the check on or off in channels without their own setting
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether messages should be checked
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Turn the check on or off in one channel
+    ///
+    /// # Arguments
+    /// * `channel_id` - ID of the channel
+    /// * `enabled` - Whether messages in it should be checked
+    pub fn set_enabled_in(&self, channel_id: &str, enabled: bool) {
+        self.channel_enabled
+            .write()
+            .unwrap()
+            .insert(channel_id.to_string(), enabled);
+    }
+
+    /// Check whether messages in a channel are being checked
+    ///
+    /// # Arguments
+    /// * `channel_id` - ID of the channel
+    pub fn is_enabled_in(&self, channel_id: &str) -> bool {
+        self.channel_enabled
+            .read()
+            .unwrap()
+            .get(channel_id)
+            .copied()
+            .unwrap_or_else(|| self.enabled.load(Ordering::Relaxed))
+    }
+
+    /// Set the Helix client and audit log used to time out flagged accounts
+    ///
+    /// # Arguments
+    /// * `helix` - The Helix client for the moderation API
+    /// * `audit_log` - The log timeouts are recorded in so they can be undone
+    pub fn set_moderation(
+        &mut self,
+        helix: Arc<Mutex<HelixChatClient>>,
+        audit_log: Arc<ModerationAuditLog>,
+    ) {
+        self.moderation = Some((helix, audit_log));
+    }
+
+    /// Check whether a message comes from a suspiciously new account
+    ///
+    /// Known chatters, subscribers, VIPs, and moderators are always exempt.
+    ///
+    /// # Arguments
+    /// * `msg` - The chat message to check
+    ///
+    /// # Returns
+    /// true if the sender's account is younger than the configured minimum age
+    pub async fn is_suspicious(&self, msg: &PrivmsgMessage) -> Result<bool> {
+        let user_id = &msg.sender.id;
+
+        let exempt = msg
+            .badges
+            .iter()
+            .any(|badge| EXEMPT_BADGES.contains(&badge.name.as_str()));
+        if exempt
+            || self.user_manager.is_known_user(user_id)
+            || self.cleared_users.read().unwrap().contains(user_id)
+        {
+            return Ok(false);
+        }
+
+        let created_at = self.lookup.account_created_at(user_id).await?;
+        let age = Utc::now() - created_at;
+
+        if age < self.min_age {
+            return Ok(true);
+        }
+
+        debug!("{} passed the new account check", msg.sender.name);
+        self.cleared_users.write().unwrap().insert(user_id.clone());
+        Ok(false)
+    }
+
+    /// Check a message and apply the configured action to new accounts
+    ///
+    /// # Arguments
+    /// * `msg` - The chat message to process
+    ///
+    /// # Returns
+    /// true if the message was flagged
+    pub async fn process_message(&self, msg: &PrivmsgMessage) -> Result<bool> {
+        if !self.is_enabled_in(&msg.channel_id)
+            || !self
+                .features
+                .is_enabled(&msg.channel_login, Feature::Filters)
+            || !self.is_suspicious(msg).await?
+        {
+            return Ok(false);
+        }
+
+        match self.action {
+            NewAccountAction::Flag => {
+                warn!(
+                    "[NEW ACCOUNT] {} ({}) is younger than {} days: {}",
+                    msg.sender.name,
+                    msg.sender.id,
+                    self.min_age.num_days(),
+                    msg.message_text
+                );
+            }
+            NewAccountAction::Timeout(duration_secs) => {
+                let Some((helix, audit_log)) = &self.moderation else {
+                    return Err(anyhow!("New account timeouts need a Helix client"));
+                };
+
+                let mut helix = helix.lock().await;
+                let moderator_id = helix.get_bot_user_id().await?;
+                helix
+                    .ban_user(
+                        &msg.channel_id,
+                        &moderator_id,
+                        &msg.sender.id,
+                        Some(duration_secs),
+                        "Account is too new to chat",
+                    )
+                    .await?;
+
+                info!(
+                    "Timed out new account {} for {}s",
+                    msg.sender.name, duration_secs
+                );
+                audit_log.record(AuditEntry::new(
+                    ModerationAction::Timeout { duration_secs },
+                    &msg.channel_id,
+                    &msg.sender.id,
+                    &msg.sender.login,
+                ));
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::create_test_privmsg_with_badges;
+
+    /// Account lookup returning a fixed account age
+    struct MockLookup {
+        age: Duration,
+    }
+
+    #[async_trait]
+    impl AccountLookup for MockLookup {
+        async fn account_created_at(&self, _user_id: &str) -> Result<DateTime<Utc>> {
+            Ok(Utc::now() - self.age)
+        }
+    }
+
+    fn create_guard(account_age: Duration) -> NewAccountGuard {
+        NewAccountGuard::new(
+            Arc::new(MockLookup { age: account_age }),
+            Arc::new(UserManager::new("test_users.db").unwrap()),
+            Duration::days(7),
+            NewAccountAction::Flag,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_new_account_is_flagged() -> Result<()> {
+        let guard = create_guard(Duration::hours(2));
+        let msg = create_test_privmsg_with_badges("follow me at ...", &[]);
+
+        assert!(guard.is_suspicious(&msg).await?);
+        assert!(guard.process_message(&msg).await?);
+
+        // A disabled guard lets everyone through
+        guard.set_enabled(false);
+        assert!(!guard.process_message(&msg).await?);
+
+        // Turning it on in another channel doesn't check this one
+        guard.set_enabled_in("789", true);
+        assert!(!guard.process_message(&msg).await?);
+        guard.set_enabled_in(&msg.channel_id, true);
+        assert!(guard.process_message(&msg).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_old_account_is_not_flagged() -> Result<()> {
+        let guard = create_guard(Duration::days(365));
+        let msg = create_test_privmsg_with_badges("hello", &[]);
+
+        assert!(!guard.is_suspicious(&msg).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_and_known_users_are_exempt() -> Result<()> {
+        let guard = create_guard(Duration::hours(2));
+
+        let subscriber = create_test_privmsg_with_badges("hello", &["subscriber"]);
+        assert!(!guard.is_suspicious(&subscriber).await?);
+
+        // Mark the sender as a known chatter
+        let msg = create_test_privmsg_with_badges("hello", &[]);
+        guard.user_manager.is_first_time_chatter(&msg.sender.id);
+        assert!(!guard.is_suspicious(&msg).await?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_action() {
+        assert_eq!(
+            "flag".parse::<NewAccountAction>().unwrap(),
+            NewAccountAction::Flag
+        );
+        assert_eq!(
+            "Timeout".parse::<NewAccountAction>().unwrap(),
+            NewAccountAction::Timeout(600)
+        );
+        assert!("explode".parse::<NewAccountAction>().is_err());
+    }
+}
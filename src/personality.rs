@@ -0,0 +1,266 @@
+//! Personality presets that set the bot's tone
+//!
+//! A preset bundles the system prompt used for AI features with the pools of
+//! canned phrases (welcomes, the 8-ball intro) so they all sound alike. The
+//! AI's tone can also be switched, or read from a file, while the bot runs.
+
+use anyhow::{Result, anyhow, bail};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+/// A bundle of tone settings for the bot's responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Personality {
+    /// Warm and welcoming
+    #[default]
+    Friendly,
+    /// Playfully sarcastic
+    Snarky,
+    /// Polite and professional
+    Formal,
+}
+
+impl FromStr for Personality {
+    type Err = anyhow::Error;
+
+    /// Parse a preset name like "friendly", "snarky", or "formal"
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "friendly" => Ok(Personality::Friendly),
+            "snarky" => Ok(Personality::Snarky),
+            "formal" => Ok(Personality::Formal),
+            other => Err(anyhow!("Unknown personality: {}", other)),
+        }
+    }
+}
+
+impl Personality {
+    /// Describe the bot's tone for the start of an AI system prompt
+    ///
+    /// # Returns
+    /// A sentence describing who the bot is and how it talks
+    pub fn system_prompt(&self) -> &'static str {
+        match self {
+            Personality::Friendly => "You are a friendly Twitch chatbot.",
+            Personality::Snarky => {
+                "You are a snarky Twitch chatbot with a dry, sarcastic sense of humor \
+                 who is never actually mean."
+            }
+            Personality::Formal => {
+                "You are a polite, formal Twitch chatbot who speaks like a courteous concierge."
+            }
+        }
+    }
+
+    /// Get the welcome templates for first-time chatters (use {username} as placeholder)
+    ///
+    /// # Returns
+    /// The preset's welcome message templates
+    pub fn welcome_messages(&self) -> Vec<String> {
+        let messages: &[&str] = match self {
+            Personality::Friendly => &[
+                "Welcome to the channel, {username}! Thanks for dropping by!",
+                "Hey {username}! Great to see you here for the first time!",
+                "Welcome aboard, {username}! Hope you enjoy the stream!",
+                "A wild {username} appears! Welcome to the stream!",
+                "Welcome, {username}! Make yourself at home!",
+                "Thanks for joining us, {username}! Glad to have you here!",
+                "First time here, {username}? Welcome to the community!",
+                "{username} has entered the chat! Welcome!",
+                "Welcome to the stream, {username}! Don't forget to follow if you enjoy the content!",
+                "Hello, {username}! Welcome to the channel!",
+            ],
+            Personality::Snarky => &[
+                "Oh look, {username} found us. Welcome, I guess.",
+                "{username} has joined. Lower your expectations accordingly.",
+                "Welcome, {username}! Don't touch anything.",
+                "Another one! Welcome, {username}. The chairs are imaginary.",
+                "{username} is here. Everybody act natural.",
+            ],
+            Personality::Formal => &[
+                "Good day, {username}, and welcome to the channel.",
+                "Welcome, {username}. We are delighted to have you with us.",
+                "It is a pleasure to welcome you, {username}.",
+                "Greetings, {username}. Please make yourself comfortable.",
+            ],
+        };
+        messages.iter().map(|message| message.to_string()).collect()
+    }
+
+    /// Get the templates for known chatters returning after a while (use {username} as placeholder)
+    ///
+    /// # Returns
+    /// The preset's welcome back message templates
+    pub fn returning_messages(&self) -> Vec<String> {
+        let messages: &[&str] = match self {
+            Personality::Friendly => &[
+                "Welcome back, {username}!",
+                "Good to see you again, {username}!",
+                "{username} is back! Welcome back!",
+                "Hey {username}, welcome back to the stream!",
+            ],
+            Personality::Snarky => &[
+                "{username} is back. Couldn't stay away, huh?",
+                "Look who it is. Welcome back, {username}.",
+                "Welcome back, {username}. We barely noticed you were gone.",
+            ],
+            Personality::Formal => &[
+                "Welcome back, {username}. It is good to see you again.",
+                "A pleasure to have you back, {username}.",
+            ],
+        };
+        messages.iter().map(|message| message.to_string()).collect()
+    }
+
+    /// Get the 8-ball's reply when it's called without a question
+    ///
+    /// # Returns
+    /// The preset's 8-ball intro
+    pub fn eight_ball_intro(&self) -> &'static str {
+        match self {
+            Personality::Friendly => "Ask me a question and I shall reveal your fate!",
+            Personality::Snarky => "You have to actually ask something. I'm magic, not psychic.",
+            Personality::Formal => "Kindly pose a yes-or-no question and the 8-ball will answer.",
+        }
+    }
+}
+
+/// The tone currently used for AI prompts, changeable while the bot runs
+#[derive(Debug)]
+pub struct AiPersona {
+    /// Name of the active persona and the prompt describing its tone
+    active: RwLock<(String, String)>,
+}
+
+impl AiPersona {
+    /// Create a persona that uses a preset's tone
+    ///
+    /// # Arguments
+    /// * `personality` - The preset to start with
+    ///
+    /// # Returns
+    /// A new AiPersona instance
+    pub fn new(personality: Personality) -> Self {
+        AiPersona {
+            active: RwLock::new(Self::preset(personality)),
+        }
+    }
+
+    /// The name and prompt of a preset
+    fn preset(personality: Personality) -> (String, String) {
+        (
+            format!("{:?}", personality).to_lowercase(),
+            personality.system_prompt().to_string(),
+        )
+    }
+
+    /// Switch to a preset's tone
+    ///
+    /// # Arguments
+    /// * `personality` - The preset to use
+    pub fn set_preset(&self, personality: Personality) {
+        *self.active.write().unwrap() = Self::preset(personality);
+    }
+
+    /// Use a tone described in a text file
+    ///
+    /// # Arguments
+    /// * `path` - The file holding the prompt
+    ///
+    /// # Returns
+    /// A Result with an error if the file can't be read or is empty
+    pub fn load_file(&self, path: &Path) -> Result<()> {
+        let prompt = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Couldn't read AI prompt file {}: {}", path.display(), e))?;
+        let prompt = prompt.trim();
+        if prompt.is_empty() {
+            bail!("AI prompt file {} is empty", path.display());
+        }
+        *self.active.write().unwrap() = (path.display().to_string(), prompt.to_string());
+        Ok(())
+    }
+
+    /// Get the name of the active persona
+    ///
+    /// # Returns
+    /// The preset's name, or the path of the prompt file in use
+    pub fn name(&self) -> String {
+        self.active.read().unwrap().0.clone()
+    }
+
+    /// Get the prompt describing the active tone, for the start of an AI system prompt
+    pub fn system_prompt(&self) -> String {
+        self.active.read().unwrap().1.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_personality() {
+        assert_eq!(
+            "Snarky".parse::<Personality>().unwrap(),
+            Personality::Snarky
+        );
+        assert_eq!(
+            "formal".parse::<Personality>().unwrap(),
+            Personality::Formal
+        );
+        assert!("grumpy".parse::<Personality>().is_err());
+    }
+
+    #[test]
+    fn test_presets_have_distinct_phrases() {
+        let friendly = Personality::Friendly;
+        let snarky = Personality::Snarky;
+
+        assert_ne!(friendly.system_prompt(), snarky.system_prompt());
+        assert_ne!(friendly.welcome_messages(), snarky.welcome_messages());
+        assert_ne!(friendly.eight_ball_intro(), snarky.eight_ball_intro());
+
+        // Every template personalizes the message
+        for personality in [
+            Personality::Friendly,
+            Personality::Snarky,
+            Personality::Formal,
+        ] {
+            assert!(
+                personality
+                    .welcome_messages()
+                    .iter()
+                    .chain(personality.returning_messages().iter())
+                    .all(|message| message.contains("{username}"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_ai_persona_switches_tone() -> Result<()> {
+        let persona = AiPersona::new(Personality::Friendly);
+        assert_eq!(persona.name(), "friendly");
+
+        persona.set_preset(Personality::Formal);
+        assert_eq!(persona.name(), "formal");
+        assert_eq!(persona.system_prompt(), Personality::Formal.system_prompt());
+
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("prompt.txt");
+        std::fs::write(&path, "You are a pirate Twitch chatbot.\n")?;
+        persona.load_file(&path)?;
+        assert_eq!(persona.system_prompt(), "You are a pirate Twitch chatbot.");
+
+        // A bad file leaves the current tone alone
+        std::fs::write(&path, "  \n")?;
+        assert!(persona.load_file(&path).is_err());
+        assert!(
+            persona
+                .load_file(&temp_dir.path().join("missing.txt"))
+                .is_err()
+        );
+        assert_eq!(persona.system_prompt(), "You are a pirate Twitch chatbot.");
+        Ok(())
+    }
+}
@@ -0,0 +1,310 @@
+//! Loyalty points viewers earn by watching the stream
+//!
+//! Who is watching comes from the watch time tracker, so points follow the
+//! same presence and live state as watch time. Every interval while the
+//! stream is live, each present viewer earns points, which are kept in the
+//! shared store by user ID and can be spent on `!slots` and `!gamble`.
+
+use anyhow::{Result, anyhow};
+use rand::Rng;
+use rand::prelude::IndexedRandom;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, error};
+
+use crate::store::Store;
+use crate::watch_time::WatchTimeTracker;
+
+/// Store namespace balances are kept under, keyed by user ID
+const NAMESPACE: &str = "points";
+
+/// How many points each watching chatter earns per interval unless the config says otherwise
+pub const DEFAULT_POINTS_PER_INTERVAL: u64 = 10;
+
+/// How often points are handed out unless the config says otherwise
+pub const DEFAULT_POINTS_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// The slots payout table used when SLOTS_PAYOUTS is not set
+const DEFAULT_SLOTS_PAYOUTS: &[(&str, u64)] =
+    &[("🍒", 2), ("🍋", 3), ("🔔", 5), ("💎", 10), ("7️⃣", 25)];
+
+/// What happened when a bet was settled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BetOutcome {
+    /// Points paid back, including the stake (0 for a loss)
+    pub winnings: u64,
+    /// The balance after the bet
+    pub balance: u64,
+}
+
+/// Balances of loyalty points, persisted in the store
+pub struct PointsService {
+    /// Where balances are kept
+    store: Arc<dyn Store>,
+    /// Serializes read-modify-write updates
+    update: Mutex<()>,
+    /// Points each watching chatter earns per interval
+    per_interval: u64,
+}
+
+impl PointsService {
+    /// Create a points service kept in a store
+    ///
+    /// # Arguments
+    /// * `store` - Where balances are kept
+    /// * `per_interval` - Points each watching chatter earns per interval
+    ///
+    /// # Returns
+    /// A new PointsService instance
+    pub fn new(store: Arc<dyn Store>, per_interval: u64) -> Self {
+        PointsService {
+            store,
+            update: Mutex::new(()),
+            per_interval,
+        }
+    }
+
+    /// Get a user's balance
+    ///
+    /// # Arguments
+    /// * `user_id` - The user's ID
+    pub fn balance(&self, user_id: &str) -> Result<u64> {
+        Ok(self.store.get_as(NAMESPACE, user_id)?.unwrap_or(0))
+    }
+
+    /// Give each watching viewer their points for the interval
+    ///
+    /// # Arguments
+    /// * `user_ids` - The IDs of the viewers watching
+    ///
+    /// # Returns
+    /// How many viewers earned points
+    pub fn accrue(&self, user_ids: &[String]) -> Result<usize> {
+        let _update = self.update.lock().unwrap();
+        for user_id in user_ids {
+            let balance = self.balance(user_id)?.saturating_add(self.per_interval);
+            self.store.set_as(NAMESPACE, user_id, &balance)?;
+        }
+        Ok(user_ids.len())
+    }
+
+    /// Stake points and pay back whatever the bet wins
+    ///
+    /// # Arguments
+    /// * `user_id` - The user betting
+    /// * `amount` - How many points they stake
+    /// * `winnings` - Works out what the stake pays back, including the stake itself
+    ///
+    /// # Returns
+    /// The outcome, or None if the user doesn't have enough points
+    pub fn bet(
+        &self,
+        user_id: &str,
+        amount: u64,
+        winnings: impl FnOnce(u64) -> u64,
+    ) -> Result<Option<BetOutcome>> {
+        let _update = self.update.lock().unwrap();
+        let balance = self.balance(user_id)?;
+        if amount > balance {
+            return Ok(None);
+        }
+
+        let winnings = winnings(amount);
+        let balance = (balance - amount).saturating_add(winnings);
+        self.store.set_as(NAMESPACE, user_id, &balance)?;
+        Ok(Some(BetOutcome { winnings, balance }))
+    }
+
+    /// Hand out points to the viewers watching every `interval`, forever
+    ///
+    /// # Arguments
+    /// * `watch_time` - Tracks who is watching and whether the stream is live
+    /// * `interval` - How long to wait between handouts
+    pub async fn run(self: Arc<Self>, watch_time: Arc<WatchTimeTracker>, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            let user_ids: Vec<String> = watch_time
+                .watching()
+                .into_iter()
+                .map(|(user_id, _)| user_id)
+                .collect();
+            match self.accrue(&user_ids) {
+                Ok(0) => {}
+                Ok(earned) => debug!("{} viewers earned points", earned),
+                Err(e) => error!("Error handing out points: {}", e),
+            }
+        }
+    }
+}
+
+/// The symbols on the slot machine's reels and what three of each pay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotsTable {
+    /// Each symbol with the multiple of the bet that three of it pay
+    payouts: Vec<(String, u64)>,
+}
+
+impl Default for SlotsTable {
+    fn default() -> Self {
+        SlotsTable {
+            payouts: DEFAULT_SLOTS_PAYOUTS
+                .iter()
+                .map(|(symbol, multiplier)| (symbol.to_string(), *multiplier))
+                .collect(),
+        }
+    }
+}
+
+impl SlotsTable {
+    /// Parse a payout table like "🍒=2,💎=10"
+    ///
+    /// # Arguments
+    /// * `table` - Comma-separated symbol=multiplier pairs
+    ///
+    /// # Returns
+    /// The payout table
+    pub fn parse(table: &str) -> Result<Self> {
+        let payouts = table
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (symbol, multiplier) = entry
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Expected symbol=multiplier, got {}", entry))?;
+                let multiplier = multiplier
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid multiplier for {}", symbol.trim()))?;
+                Ok((symbol.trim().to_string(), multiplier))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if payouts.len() < 2 {
+            return Err(anyhow!("The slots need at least two symbols"));
+        }
+        Ok(SlotsTable { payouts })
+    }
+
+    /// Spin the three reels
+    ///
+    /// # Arguments
+    /// * `rng` - The source of randomness
+    ///
+    /// # Returns
+    /// The symbol each reel stopped on
+    pub fn spin(&self, rng: &mut impl Rng) -> [String; 3] {
+        std::array::from_fn(|_| {
+            self.payouts
+                .choose(rng)
+                .map(|(symbol, _)| symbol.clone())
+                .unwrap_or_default()
+        })
+    }
+
+    /// Work out what a spin pays back
+    ///
+    /// Three of a kind pay the symbol's multiple of the bet, and any two
+    /// matching symbols give the bet back.
+    ///
+    /// # Arguments
+    /// * `reels` - The symbols the reels stopped on
+    /// * `bet` - The points staked
+    ///
+    /// # Returns
+    /// The points paid back, including the stake
+    pub fn payout(&self, reels: &[String; 3], bet: u64) -> u64 {
+        let [a, b, c] = reels;
+        if a == b && b == c {
+            let multiplier = self
+                .payouts
+                .iter()
+                .find(|(symbol, _)| symbol == a)
+                .map_or(0, |(_, multiplier)| *multiplier);
+            bet.saturating_mul(multiplier)
+        } else if a == b || b == c || a == c {
+            bet
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{StoreBackend, open_store};
+    use crate::users::UserManager;
+    use tempfile::tempdir;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_points_accrue_and_bets_settle() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let points = Arc::new(PointsService::new(
+            open_store(StoreBackend::File, temp_dir.path(), None)?,
+            10,
+        ));
+        let watch_time = Arc::new(WatchTimeTracker::new(Arc::new(UserManager::new(
+            "test_points.db",
+        )?)));
+        tokio::spawn(
+            points
+                .clone()
+                .run(watch_time.clone(), Duration::from_secs(60)),
+        );
+        watch_time.record_seen("1", "first");
+        watch_time.record_seen("2", "second");
+
+        // Nothing is earned while the stream is offline
+        tokio::time::sleep(Duration::from_secs(61)).await;
+        assert_eq!(points.balance("1")?, 0);
+
+        watch_time.set_live(true);
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(points.balance("1")?, 10);
+        assert_eq!(points.balance("2")?, 10);
+
+        // Only viewers still present keep earning
+        watch_time.record_part("second");
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(points.balance("1")?, 20);
+        assert_eq!(points.balance("2")?, 10);
+
+        // A bet can't stake more than the balance
+        assert_eq!(points.bet("2", 11, |bet| bet * 2)?, None);
+        assert_eq!(points.balance("2")?, 10);
+        assert_eq!(
+            points.bet("1", 5, |bet| bet * 3)?,
+            Some(BetOutcome {
+                winnings: 15,
+                balance: 30
+            })
+        );
+        assert_eq!(
+            points.bet("1", 30, |_| 0)?,
+            Some(BetOutcome {
+                winnings: 0,
+                balance: 0
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_slots_table() -> Result<()> {
+        let table = SlotsTable::parse("🍒=2, 🍋=3, 🔔=5 ,💎=10,7️⃣=25")?;
+        assert_eq!(table, SlotsTable::default());
+        let reels = |symbols: [&str; 3]| symbols.map(str::to_string);
+
+        assert_eq!(table.payout(&reels(["💎", "💎", "💎"]), 20), 200);
+        assert_eq!(table.payout(&reels(["🍒", "🍋", "🍒"]), 20), 20);
+        assert_eq!(table.payout(&reels(["🍒", "🍋", "🔔"]), 20), 0);
+        assert!(table.spin(&mut rand::rng()).iter().all(|symbol| {
+            ["🍒", "🍋", "🔔", "💎", "7️⃣"].contains(&symbol.as_str())
+        }));
+
+        assert!(SlotsTable::parse("🍒=2").is_err());
+        assert!(SlotsTable::parse("🍒=2,💎=lots").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+//! Quick chat polls with one vote per viewer
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// A running poll
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Poll {
+    /// The question being asked
+    question: String,
+    /// The answers to choose from
+    options: Vec<String>,
+    /// Each voter's chosen option index, by user ID
+    votes: HashMap<String, usize>,
+}
+
+/// The outcome of a vote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoteOutcome {
+    /// The vote was counted
+    Counted,
+    /// The user had voted already and their vote was changed
+    Changed,
+    /// No poll is running in the channel
+    NoPoll,
+    /// The option number isn't on the poll, which has this many options
+    OutOfRange(usize),
+}
+
+/// Final results of a poll
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollResults {
+    /// The question that was asked
+    pub question: String,
+    /// Each option with its vote count, in poll order
+    pub tallies: Vec<(String, usize)>,
+}
+
+impl PollResults {
+    /// Get the total number of votes
+    pub fn total(&self) -> usize {
+        self.tallies.iter().map(|(_, votes)| votes).sum()
+    }
+
+    /// Format the results for chat
+    ///
+    /// # Returns
+    /// A summary like "Best game?: A 3 (60%), B 2 (40%). Winner: A"
+    pub fn summary(&self) -> String {
+        let total = self.total();
+        if total == 0 {
+            return format!("📊 {}: no votes were cast.", self.question);
+        }
+
+        let tallies: Vec<String> = self
+            .tallies
+            .iter()
+            .map(|(option, votes)| {
+                format!(
+                    "{} {} ({:.0}%)",
+                    option,
+                    votes,
+                    *votes as f64 * 100.0 / total as f64
+                )
+            })
+            .collect();
+
+        let most = self
+            .tallies
+            .iter()
+            .map(|(_, votes)| *votes)
+            .max()
+            .unwrap_or(0);
+        let leaders: Vec<&str> = self
+            .tallies
+            .iter()
+            .filter(|(_, votes)| *votes == most)
+            .map(|(option, _)| option.as_str())
+            .collect();
+        let outcome = match leaders.as_slice() {
+            [winner] => format!("Winner: {}", winner),
+            tied => format!("Tie between {}", tied.join(" and ")),
+        };
+
+        format!("📊 {}: {}. {}", self.question, tallies.join(", "), outcome)
+    }
+}
+
+/// Runs polls, one per channel at a time
+#[derive(Default)]
+pub struct PollService {
+    /// Running polls by channel
+    polls: Mutex<HashMap<String, Poll>>,
+}
+
+impl PollService {
+    /// Create a poll service with no polls running
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a poll
+    ///
+    /// # Arguments
+    /// * `channel` - The channel to run the poll in
+    /// * `question` - The question being asked
+    /// * `options` - The answers to choose from
+    ///
+    /// # Returns
+    /// false if a poll is already running in the channel
+    pub fn start(&self, channel: &str, question: &str, options: Vec<String>) -> bool {
+        let mut polls = self.polls.lock().unwrap();
+        if polls.contains_key(channel) {
+            return false;
+        }
+
+        polls.insert(
+            channel.to_string(),
+            Poll {
+                question: question.to_string(),
+                options,
+                votes: HashMap::new(),
+            },
+        );
+        true
+    }
+
+    /// Record a vote, replacing the user's earlier vote if any
+    ///
+    /// # Arguments
+    /// * `channel` - The channel the vote was cast in
+    /// * `user_id` - ID of the voter
+    /// * `option` - The chosen option number, starting at 1
+    pub fn vote(&self, channel: &str, user_id: &str, option: usize) -> VoteOutcome {
+        let mut polls = self.polls.lock().unwrap();
+        let Some(poll) = polls.get_mut(channel) else {
+            return VoteOutcome::NoPoll;
+        };
+        if option == 0 || option > poll.options.len() {
+            return VoteOutcome::OutOfRange(poll.options.len());
+        }
+
+        match poll.votes.insert(user_id.to_string(), option - 1) {
+            Some(_) => VoteOutcome::Changed,
+            None => VoteOutcome::Counted,
+        }
+    }
+
+    /// Get the number of options on a channel's running poll
+    ///
+    /// # Arguments
+    /// * `channel` - The channel to look in
+    ///
+    /// # Returns
+    /// The number of options, or None if no poll is running
+    pub fn option_count(&self, channel: &str) -> Option<usize> {
+        let polls = self.polls.lock().unwrap();
+        polls.get(channel).map(|poll| poll.options.len())
+    }
+
+    /// End a channel's poll and tally the votes
+    ///
+    /// # Arguments
+    /// * `channel` - The channel whose poll should end
+    ///
+    /// # Returns
+    /// The results, or None if no poll was running
+    pub fn end(&self, channel: &str) -> Option<PollResults> {
+        let poll = self.polls.lock().unwrap().remove(channel)?;
+
+        let mut counts = vec![0; poll.options.len()];
+        for option in poll.votes.values() {
+            counts[*option] += 1;
+        }
+
+        Some(PollResults {
+            question: poll.question,
+            tallies: poll.options.into_iter().zip(counts).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_votes_are_tallied_once_per_user() {
+        let polls = PollService::new();
+        assert!(polls.start("chan", "Best game?", options(&["A", "B", "C"])));
+        assert!(!polls.start("chan", "Another?", options(&["X", "Y"])));
+
+        assert_eq!(polls.vote("chan", "1", 1), VoteOutcome::Counted);
+        assert_eq!(polls.vote("chan", "2", 1), VoteOutcome::Counted);
+        assert_eq!(polls.vote("chan", "3", 2), VoteOutcome::Counted);
+        // A second vote replaces the first instead of adding to it
+        assert_eq!(polls.vote("chan", "3", 1), VoteOutcome::Changed);
+        assert_eq!(polls.vote("chan", "4", 2), VoteOutcome::Counted);
+
+        let results = polls.end("chan").unwrap();
+        assert_eq!(
+            results.tallies,
+            vec![
+                ("A".to_string(), 3),
+                ("B".to_string(), 1),
+                ("C".to_string(), 0)
+            ]
+        );
+        assert_eq!(
+            results.summary(),
+            "📊 Best game?: A 3 (75%), B 1 (25%), C 0 (0%). Winner: A"
+        );
+        assert!(polls.end("chan").is_none());
+    }
+
+    #[test]
+    fn test_invalid_votes_are_rejected() {
+        let polls = PollService::new();
+        assert_eq!(polls.vote("chan", "1", 1), VoteOutcome::NoPoll);
+
+        polls.start("chan", "Yes or no?", options(&["Yes", "No"]));
+        assert_eq!(polls.vote("chan", "1", 0), VoteOutcome::OutOfRange(2));
+        assert_eq!(polls.vote("chan", "1", 3), VoteOutcome::OutOfRange(2));
+        // Polls are per channel
+        assert_eq!(polls.vote("other", "1", 1), VoteOutcome::NoPoll);
+
+        assert_eq!(
+            polls.end("chan").unwrap().summary(),
+            "📊 Yes or no?: no votes were cast."
+        );
+    }
+
+    #[test]
+    fn test_tie_summary() {
+        let results = PollResults {
+            question: "Pizza?".to_string(),
+            tallies: vec![("Yes".to_string(), 1), ("No".to_string(), 1)],
+        };
+        assert_eq!(
+            results.summary(),
+            "📊 Pizza?: Yes 1 (50%), No 1 (50%). Tie between Yes and No"
+        );
+    }
+}
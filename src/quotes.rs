@@ -0,0 +1,259 @@
+//! Quotes saved from chat, kept in the shared store
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rand::prelude::IndexedRandom;
+use rand::rng;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::store::Store;
+
+/// Store namespace quotes are kept under, keyed by number
+const NAMESPACE: &str = "quotes";
+
+/// A saved quote
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quote {
+    /// Number used to look the quote up with !quote
+    pub id: u64,
+    /// The quoted text
+    pub text: String,
+    /// Lowercase login of the person who said it, if given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Login of the user who added the quote
+    pub added_by: String,
+    /// When the quote was added
+    pub created_at: DateTime<Utc>,
+}
+
+impl Quote {
+    /// Check whether the quote was said by someone
+    ///
+    /// # Arguments
+    /// * `author` - The person's login, with or without a leading @
+    pub fn is_by(&self, author: &str) -> bool {
+        let author = author.trim_start_matches('@');
+        self.author
+            .as_ref()
+            .is_some_and(|quoted| quoted.eq_ignore_ascii_case(author))
+    }
+
+    /// Check whether the quote's text or author mentions a keyword, ignoring case
+    ///
+    /// # Arguments
+    /// * `keyword` - The word or phrase to look for
+    pub fn mentions(&self, keyword: &str) -> bool {
+        let keyword = keyword.to_lowercase();
+        self.text.to_lowercase().contains(&keyword)
+            || self
+                .author
+                .as_ref()
+                .is_some_and(|author| author.contains(&keyword))
+    }
+}
+
+/// The quote database
+pub struct QuoteStore {
+    /// Where the quotes are kept
+    store: Arc<dyn Store>,
+}
+
+impl QuoteStore {
+    /// Create a quote database in a store
+    ///
+    /// # Arguments
+    /// * `store` - Where the quotes are kept
+    ///
+    /// # Returns
+    /// A new QuoteStore instance
+    pub fn new(store: Arc<dyn Store>) -> Self {
+        QuoteStore { store }
+    }
+
+    /// Import quotes from the `quotes.json` file used by older versions
+    ///
+    /// Quotes whose number is already taken are skipped. The file is renamed
+    /// to `.json.migrated` afterwards.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the old quotes file
+    ///
+    /// # Returns
+    /// The number of quotes imported
+    pub fn import_legacy(&mut self, path: &Path) -> Result<usize> {
+        if !path.exists() {
+            return Ok(0);
+        }
+
+        let quotes: Vec<Quote> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let mut imported = 0;
+        for quote in quotes {
+            if self.get(quote.id)?.is_none() {
+                self.store
+                    .set_as(NAMESPACE, &quote.id.to_string(), &quote)?;
+                imported += 1;
+            }
+        }
+
+        let mut migrated_path = path.as_os_str().to_owned();
+        migrated_path.push(".migrated");
+        std::fs::rename(path, migrated_path)?;
+        Ok(imported)
+    }
+
+    /// Get every quote, ordered by number
+    fn all(&self) -> Result<Vec<Quote>> {
+        let mut quotes: Vec<Quote> = self
+            .store
+            .list_as(NAMESPACE)?
+            .into_iter()
+            .map(|(_, quote)| quote)
+            .collect();
+        quotes.sort_by_key(|quote| quote.id);
+        Ok(quotes)
+    }
+
+    /// Add a quote
+    ///
+    /// # Arguments
+    /// * `text` - The quoted text
+    /// * `author` - Login of the person who said it, if known
+    /// * `added_by` - Login of the user adding the quote
+    ///
+    /// # Returns
+    /// The new quote
+    pub fn add(&mut self, text: &str, author: Option<&str>, added_by: &str) -> Result<Quote> {
+        let id = self.all()?.last().map_or(0, |quote| quote.id) + 1;
+        let quote = Quote {
+            id,
+            text: text.to_string(),
+            author: author.map(|author| author.trim_start_matches('@').to_lowercase()),
+            added_by: added_by.to_string(),
+            created_at: Utc::now(),
+        };
+
+        self.store.set_as(NAMESPACE, &id.to_string(), &quote)?;
+        Ok(quote)
+    }
+
+    /// Get a quote by its number
+    ///
+    /// # Arguments
+    /// * `id` - The quote's number
+    pub fn get(&self, id: u64) -> Result<Option<Quote>> {
+        self.store.get_as(NAMESPACE, &id.to_string())
+    }
+
+    /// Pick a random quote
+    ///
+    /// # Returns
+    /// A random quote, or None if there are no quotes
+    pub fn random(&self) -> Result<Option<Quote>> {
+        Ok(self.all()?.choose(&mut rng()).cloned())
+    }
+
+    /// Pick a random quote among those that pass a filter
+    ///
+    /// # Arguments
+    /// * `filter` - Which quotes may be picked
+    ///
+    /// # Returns
+    /// A random matching quote, or None if no quote matches
+    pub fn random_where(&self, filter: impl Fn(&Quote) -> bool) -> Result<Option<Quote>> {
+        let matching: Vec<Quote> = self
+            .all()?
+            .into_iter()
+            .filter(|quote| filter(quote))
+            .collect();
+        Ok(matching.choose(&mut rng()).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{StoreBackend, open_store};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_add_and_reload_quotes() -> Result<()> {
+        let temp_dir = tempdir()?;
+
+        let mut quotes = QuoteStore::new(open_store(StoreBackend::File, temp_dir.path(), None)?);
+        assert!(quotes.random()?.is_none());
+        assert_eq!(quotes.add("first quote", None, "mod_one")?.id, 1);
+        assert_eq!(quotes.add("second quote", None, "mod_two")?.id, 2);
+
+        // Quotes survive a restart and numbering continues
+        let mut quotes = QuoteStore::new(open_store(StoreBackend::File, temp_dir.path(), None)?);
+        assert_eq!(quotes.get(2)?.unwrap().text, "second quote");
+        assert_eq!(quotes.get(1)?.unwrap().added_by, "mod_one");
+        assert!(quotes.get(3)?.is_none());
+        assert_eq!(quotes.add("third quote", None, "mod_one")?.id, 3);
+        assert!(quotes.random()?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_legacy() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let legacy_path = temp_dir.path().join("quotes.json");
+        std::fs::write(
+            &legacy_path,
+            r#"[{"id":1,"text":"old quote","added_by":"mod_one","created_at":"2024-01-01T12:00:00Z"}]"#,
+        )?;
+
+        let mut quotes = QuoteStore::new(open_store(StoreBackend::Sqlite, temp_dir.path(), None)?);
+        assert_eq!(quotes.import_legacy(&legacy_path)?, 1);
+        assert!(!legacy_path.exists());
+        assert!(temp_dir.path().join("quotes.json.migrated").exists());
+
+        assert_eq!(quotes.get(1)?.unwrap().text, "old quote");
+        assert_eq!(quotes.add("new quote", None, "mod_two")?.id, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filtered_random_quote() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let mut quotes = QuoteStore::new(open_store(StoreBackend::File, temp_dir.path(), None)?);
+        quotes.add("I never miss", Some("@StreamerOne"), "mod_one")?;
+        quotes.add("chat is lagging again", Some("streamerone"), "mod_one")?;
+        quotes.add("the boss is easy", Some("guest"), "mod_two")?;
+        quotes.add("nobody knows who said this", None, "mod_two")?;
+
+        for _ in 0..10 {
+            let quote = quotes
+                .random_where(|quote| quote.is_by("@streamerONE"))?
+                .unwrap();
+            assert!(quote.id == 1 || quote.id == 2);
+        }
+        let quote = quotes
+            .random_where(|quote| quote.mentions("BOSS"))?
+            .unwrap();
+        assert_eq!(quote.id, 3);
+        // Authors count as keywords too
+        assert_eq!(
+            quotes
+                .random_where(|quote| quote.mentions("guest"))?
+                .unwrap()
+                .id,
+            3
+        );
+
+        assert!(
+            quotes
+                .random_where(|quote| quote.is_by("nobody"))?
+                .is_none()
+        );
+        assert!(
+            quotes
+                .random_where(|quote| quote.mentions("speedrun"))?
+                .is_none()
+        );
+        Ok(())
+    }
+}
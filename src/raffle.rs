@@ -0,0 +1,264 @@
+//! Giveaway raffles with weighted entries
+//!
+//! Subscribers and VIPs can be given extra entries. Anyone who wins is left
+//! out of later draws until the raffle is reset, so one person can't win
+//! every giveaway in a stream.
+
+use rand::Rng;
+use rand::prelude::IndexedRandom;
+use std::collections::HashSet;
+use twitch_irc::message::PrivmsgMessage;
+
+/// How many entries each kind of chatter gets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RaffleWeights {
+    /// Entries for subscribers (and founders)
+    pub subscriber: u32,
+    /// Entries for VIPs
+    pub vip: u32,
+}
+
+impl Default for RaffleWeights {
+    fn default() -> Self {
+        RaffleWeights {
+            subscriber: 2,
+            vip: 2,
+        }
+    }
+}
+
+impl RaffleWeights {
+    /// Get how many entries the sender of a message gets
+    ///
+    /// Chatters who are both subscribers and VIPs get the larger weight.
+    ///
+    /// # Arguments
+    /// * `msg` - A message from the chatter
+    pub fn weight_for(&self, msg: &PrivmsgMessage) -> u32 {
+        let has_badge = |name: &str| msg.badges.iter().any(|badge| badge.name == name);
+
+        let mut weight = 1;
+        if has_badge("subscriber") || has_badge("founder") {
+            weight = weight.max(self.subscriber);
+        }
+        if has_badge("vip") {
+            weight = weight.max(self.vip);
+        }
+        weight
+    }
+}
+
+/// Someone who entered the raffle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RaffleEntry {
+    /// ID of the user who entered
+    pub user_id: String,
+    /// Display name of the user who entered
+    pub username: String,
+    /// How many entries they have
+    pub weight: u32,
+}
+
+/// Why an entry was turned away
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryRejection {
+    /// No raffle is open
+    Closed,
+    /// The user already entered
+    AlreadyEntered,
+    /// The user already won this session
+    AlreadyWon,
+}
+
+/// A giveaway raffle
+#[derive(Debug, Default)]
+pub struct Raffle {
+    /// Entry weights by chatter type
+    weights: RaffleWeights,
+    /// Whether entries are being accepted
+    open: bool,
+    /// Everyone who entered, in the order they joined
+    entries: Vec<RaffleEntry>,
+    /// IDs of users who won since the last reset
+    winners: HashSet<String>,
+}
+
+impl Raffle {
+    /// Create a closed raffle
+    ///
+    /// # Arguments
+    /// * `weights` - Entry weights by chatter type
+    ///
+    /// # Returns
+    /// A new Raffle instance
+    pub fn new(weights: RaffleWeights) -> Self {
+        Raffle {
+            weights,
+            ..Default::default()
+        }
+    }
+
+    /// Open the raffle for entries, clearing earlier entrants
+    pub fn open(&mut self) {
+        self.open = true;
+        self.entries.clear();
+    }
+
+    /// Stop accepting entries
+    ///
+    /// # Returns
+    /// How many people entered
+    pub fn close(&mut self) -> usize {
+        self.open = false;
+        self.entries.len()
+    }
+
+    /// Close the raffle and forget entrants and past winners
+    pub fn reset(&mut self) {
+        self.open = false;
+        self.entries.clear();
+        self.winners.clear();
+    }
+
+    /// Enter the sender of a message into the raffle
+    ///
+    /// # Arguments
+    /// * `msg` - The message the user entered with
+    ///
+    /// # Returns
+    /// How many entries the user got, or why they weren't entered
+    pub fn enter(&mut self, msg: &PrivmsgMessage) -> Result<u32, EntryRejection> {
+        if !self.open {
+            return Err(EntryRejection::Closed);
+        }
+        if self.winners.contains(&msg.sender.id) {
+            return Err(EntryRejection::AlreadyWon);
+        }
+        if self.entries.iter().any(|e| e.user_id == msg.sender.id) {
+            return Err(EntryRejection::AlreadyEntered);
+        }
+
+        let weight = self.weights.weight_for(msg);
+        self.entries.push(RaffleEntry {
+            user_id: msg.sender.id.clone(),
+            username: msg.sender.name.clone(),
+            weight,
+        });
+        Ok(weight)
+    }
+
+    /// Pick a random entrant, honoring weights and skipping past winners
+    ///
+    /// # Arguments
+    /// * `rng` - Source of randomness
+    ///
+    /// # Returns
+    /// The picked entrant, or None if nobody is eligible
+    pub fn pick<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<&RaffleEntry> {
+        let eligible: Vec<&RaffleEntry> = self
+            .entries
+            .iter()
+            .filter(|e| !self.winners.contains(&e.user_id))
+            .collect();
+
+        eligible
+            .choose_weighted(rng, |entry| entry.weight)
+            .ok()
+            .copied()
+    }
+
+    /// Draw a winner, who is then excluded until the raffle is reset
+    ///
+    /// # Arguments
+    /// * `rng` - Source of randomness
+    ///
+    /// # Returns
+    /// The winner, or None if nobody is eligible
+    pub fn draw<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<RaffleEntry> {
+        let winner = self.pick(rng)?.clone();
+        self.winners.insert(winner.user_id.clone());
+        Some(winner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::create_test_privmsg_with_badges;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    /// A message from a different user than the default test sender
+    fn privmsg_from(id: &str, name: &str, badges: &[&str]) -> PrivmsgMessage {
+        let mut msg = create_test_privmsg_with_badges("!join", badges);
+        msg.sender.id = id.to_string();
+        msg.sender.login = name.to_lowercase();
+        msg.sender.name = name.to_string();
+        msg
+    }
+
+    #[test]
+    fn test_weights_skew_the_draw() {
+        let weights = RaffleWeights {
+            subscriber: 3,
+            vip: 1,
+        };
+        let mut raffle = Raffle::new(weights);
+        raffle.open();
+        assert_eq!(
+            raffle.enter(&privmsg_from("1", "Sub", &["subscriber"])),
+            Ok(3)
+        );
+        assert_eq!(raffle.enter(&privmsg_from("2", "Viewer", &[])), Ok(1));
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let draws = 4000;
+        let sub_wins = (0..draws)
+            .filter(|_| raffle.pick(&mut rng).unwrap().user_id == "1")
+            .count();
+
+        // Subscribers should win about 3 in 4 draws
+        let share = sub_wins as f64 / draws as f64;
+        assert!((0.70..0.80).contains(&share), "subscriber share {}", share);
+    }
+
+    #[test]
+    fn test_winners_are_excluded() {
+        let mut raffle = Raffle::new(RaffleWeights::default());
+        assert_eq!(
+            raffle.enter(&privmsg_from("1", "First", &[])),
+            Err(EntryRejection::Closed)
+        );
+
+        raffle.open();
+        raffle.enter(&privmsg_from("1", "First", &[])).unwrap();
+        raffle
+            .enter(&privmsg_from("2", "Second", &["vip"]))
+            .unwrap();
+        assert_eq!(
+            raffle.enter(&privmsg_from("2", "Second", &["vip"])),
+            Err(EntryRejection::AlreadyEntered)
+        );
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let first = raffle.draw(&mut rng).unwrap();
+        let second = raffle.draw(&mut rng).unwrap();
+        assert_ne!(first.user_id, second.user_id);
+        assert!(raffle.draw(&mut rng).is_none());
+
+        // Winners can't enter the next raffle this session
+        raffle.open();
+        assert_eq!(
+            raffle.enter(&privmsg_from(&first.user_id, &first.username, &[])),
+            Err(EntryRejection::AlreadyWon)
+        );
+
+        raffle.reset();
+        raffle.open();
+        assert!(
+            raffle
+                .enter(&privmsg_from(&first.user_id, &first.username, &[]))
+                .is_ok()
+        );
+    }
+}
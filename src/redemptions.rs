@@ -0,0 +1,416 @@
+//! Bot actions for channel point redemptions
+//!
+//! REDEMPTION_ACTIONS maps rewards, by ID or title, to something the bot
+//! does when one is redeemed: post a message, or run a command as the viewer
+//! who redeemed it. A redemption still waiting in the reward's request queue
+//! is marked fulfilled if its action worked and cancelled, refunding the
+//! points, if it didn't. Twitch only allows that for rewards created with the
+//! bot's client ID; other redemptions are left in the queue.
+
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+use twitch_irc::message::{IRCMessage, IRCPrefix, IRCTags, PrivmsgMessage, TwitchUserBasics};
+
+use crate::commands::CommandHandler;
+use crate::twitch::{HelixChatClient, MessageSender, Redemption};
+
+/// Separates the entries in REDEMPTION_ACTIONS
+const ENTRY_SEPARATOR: &str = "|";
+
+/// What the bot does when a reward is redeemed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedemptionAction {
+    /// Post a message in chat
+    Say(String),
+    /// Run a command as the viewer who redeemed the reward
+    Run(String),
+}
+
+impl FromStr for RedemptionAction {
+    type Err = anyhow::Error;
+
+    /// Parse an action like "say Thanks {username}!" or "run !so {input}"
+    fn from_str(action: &str) -> Result<Self> {
+        let (kind, template) = action.trim().split_once(' ').unwrap_or((action.trim(), ""));
+        let template = template.trim().to_string();
+        if template.is_empty() {
+            return Err(anyhow!("'{}' needs a message or command", action.trim()));
+        }
+        match kind.to_lowercase().as_str() {
+            "say" => Ok(RedemptionAction::Say(template)),
+            "run" => Ok(RedemptionAction::Run(template)),
+            _ => Err(anyhow!("Unknown action '{}', expected say or run", kind)),
+        }
+    }
+}
+
+/// The actions for each reward
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedemptionActions {
+    /// Each reward's ID or lowercase title with its action
+    actions: Vec<(String, RedemptionAction)>,
+}
+
+impl RedemptionActions {
+    /// Parse actions like "Hydrate=say Drink up {username}!|Shoutout=run !so {input}"
+    ///
+    /// # Arguments
+    /// * `value` - reward=action entries separated by "|"
+    ///
+    /// # Returns
+    /// The actions
+    pub fn parse(value: &str) -> Result<Self> {
+        let actions = value
+            .split(ENTRY_SEPARATOR)
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (reward, action) = entry
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Expected reward=action, got {}", entry))?;
+                Ok((reward.trim().to_lowercase(), action.parse()?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(RedemptionActions { actions })
+    }
+
+    /// Whether no reward has an action
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Find the action for a redemption, matching the reward's ID or title
+    ///
+    /// # Arguments
+    /// * `redemption` - The redemption
+    ///
+    /// # Returns
+    /// The action, or None if the reward has none
+    pub fn get(&self, redemption: &Redemption) -> Option<&RedemptionAction> {
+        let title = redemption.reward.title.to_lowercase();
+        self.actions
+            .iter()
+            .find(|(reward, _)| *reward == redemption.reward.id.to_lowercase() || *reward == title)
+            .map(|(_, action)| action)
+    }
+}
+
+/// Fill in a template's {username}, {input}, and {reward} placeholders
+fn render(template: &str, redemption: &Redemption) -> String {
+    template
+        .replace("{username}", &redemption.user_name)
+        .replace("{input}", redemption.user_input.trim())
+        .replace("{reward}", &redemption.reward.title)
+}
+
+/// Build the chat message a command would have come in if the viewer typed it
+///
+/// # Arguments
+/// * `channel` - The channel the reward was redeemed in
+/// * `redemption` - The redemption
+/// * `text` - The command to run
+///
+/// # Returns
+/// The chat message
+fn redemption_privmsg(channel: &str, redemption: &Redemption, text: &str) -> PrivmsgMessage {
+    let source = IRCMessage {
+        tags: IRCTags::new(),
+        prefix: Some(IRCPrefix::HostOnly {
+            host: format!("{0}!{0}@{0}.tmi.twitch.tv", redemption.user_login),
+        }),
+        command: "PRIVMSG".to_string(),
+        params: vec![format!("#{}", channel), text.to_string()],
+    };
+
+    PrivmsgMessage {
+        channel_login: channel.to_string(),
+        message_text: text.to_string(),
+        sender: TwitchUserBasics {
+            id: redemption.user_id.clone(),
+            login: redemption.user_login.clone(),
+            name: redemption.user_name.clone(),
+        },
+        source,
+        channel_id: redemption.broadcaster_id.clone(),
+        message_id: format!("redemption-{}", redemption.id),
+        server_timestamp: Utc::now(),
+        name_color: None,
+        badges: Vec::new(),
+        badge_info: Vec::new(),
+        emotes: Vec::new(),
+        bits: None,
+        is_action: false,
+    }
+}
+
+/// Carries out the configured actions when rewards are redeemed
+pub struct RedemptionService {
+    /// Used to post messages
+    client: Arc<dyn MessageSender>,
+    /// Runs commands for redemptions
+    commands: Arc<CommandHandler>,
+    /// Used to fulfill or cancel redemptions
+    helix: Arc<Mutex<HelixChatClient>>,
+    /// The action for each reward
+    actions: RedemptionActions,
+}
+
+impl RedemptionService {
+    /// Create a new redemption service
+    ///
+    /// # Arguments
+    /// * `client` - The client used to post messages
+    /// * `commands` - The handler that runs commands
+    /// * `helix` - The Helix client used to fulfill or cancel redemptions
+    /// * `actions` - The action for each reward
+    ///
+    /// # Returns
+    /// A new RedemptionService instance
+    pub fn new(
+        client: Arc<dyn MessageSender>,
+        commands: Arc<CommandHandler>,
+        helix: Arc<Mutex<HelixChatClient>>,
+        actions: RedemptionActions,
+    ) -> Self {
+        RedemptionService {
+            client,
+            commands,
+            helix,
+            actions,
+        }
+    }
+
+    /// Carry out the action for a redemption and settle it if it's queued
+    ///
+    /// # Arguments
+    /// * `channel` - The channel the reward was redeemed in
+    /// * `redemption` - The redemption
+    ///
+    /// # Returns
+    /// Whether the reward has an action, or the action's error
+    pub async fn process(&self, channel: &str, redemption: &Redemption) -> Result<bool> {
+        let Some(action) = self.actions.get(redemption) else {
+            return Ok(false);
+        };
+        info!(
+            "{} redeemed \"{}\", running {:?}",
+            redemption.user_name, redemption.reward.title, action
+        );
+
+        let result = match action {
+            RedemptionAction::Say(template) => {
+                self.client
+                    .say(channel, &render(template, redemption))
+                    .await
+            }
+            RedemptionAction::Run(template) => {
+                let msg = redemption_privmsg(channel, redemption, &render(template, redemption));
+                self.commands.run_unchecked(&msg).await
+            }
+        };
+
+        // Rewards that skip the request queue arrive already fulfilled
+        if redemption.status == "unfulfilled" {
+            let settled = self
+                .helix
+                .lock()
+                .await
+                .update_redemption_status(
+                    &redemption.broadcaster_id,
+                    &redemption.reward.id,
+                    &redemption.id,
+                    result.is_ok(),
+                )
+                .await;
+            if let Err(e) = settled {
+                warn!("Couldn't settle redemption {}: {}", redemption.id, e);
+            }
+        }
+
+        result.map(|()| true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{CommandPrefixes, CommandRegistry, ShoutoutCommand};
+    use crate::test_helpers::{
+        create_authenticated_oauth_manager, create_test_context, create_test_context_with_helix,
+    };
+    use crate::users::MockTwitchClient;
+    use std::collections::HashMap;
+    use tokio::sync::RwLock;
+
+    /// A queued redemption as EventSub delivers it
+    fn redemption(reward_title: &str, user_input: &str) -> Redemption {
+        serde_json::from_value(serde_json::json!({
+            "id": "r1",
+            "broadcaster_user_id": "100",
+            "user_id": "200",
+            "user_login": "viewer",
+            "user_name": "Viewer",
+            "user_input": user_input,
+            "status": "unfulfilled",
+            "reward": {
+                "id": format!("{}-id", reward_title.to_lowercase()),
+                "title": reward_title,
+            },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_redemption_actions() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let fulfilled = server
+            .mock("PATCH", "/channel_points/custom_rewards/redemptions")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "reward_id".to_string(),
+                "hydrate-id".to_string(),
+            ))
+            .match_body(r#"{"status":"FULFILLED"}"#)
+            .with_status(200)
+            .create_async()
+            .await;
+        let canceled = server
+            .mock("PATCH", "/channel_points/custom_rewards/redemptions")
+            .match_query(mockito::Matcher::Any)
+            .match_body(r#"{"status":"CANCELED"}"#)
+            .with_status(200)
+            .create_async()
+            .await;
+        let helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+
+        let client = MockTwitchClient::new();
+        let handler = CommandHandler::new(
+            Arc::new(client.clone()),
+            Arc::new(RwLock::new(CommandRegistry::new())),
+            CommandPrefixes::new("!".to_string(), HashMap::new())?,
+            create_test_context(),
+        );
+        let actions = RedemptionActions::parse(
+            "Hydrate = say Drink up {username}! {input} | broken-id=run !nope",
+        )?;
+        let service = RedemptionService::new(
+            Arc::new(client.clone()),
+            Arc::new(handler),
+            Arc::new(Mutex::new(helix)),
+            actions,
+        );
+
+        assert!(
+            service
+                .process("test_channel", &redemption("HYDRATE", " cheers "))
+                .await?
+        );
+        assert_eq!(
+            client.sent_messages(),
+            vec![(
+                "test_channel".to_string(),
+                "Drink up Viewer! cheers".to_string()
+            )]
+        );
+        fulfilled.assert_async().await;
+
+        // A failed action refunds the points
+        assert!(
+            service
+                .process("test_channel", &redemption("Broken", ""))
+                .await
+                .is_err()
+        );
+        canceled.assert_async().await;
+
+        assert!(
+            !service
+                .process("test_channel", &redemption("Other", ""))
+                .await?
+        );
+
+        assert!(RedemptionActions::parse("Hydrate=shout hi").is_err());
+        assert!(RedemptionActions::parse("Hydrate").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rejected_command_refunds_points() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let canceled = server
+            .mock("PATCH", "/channel_points/custom_rewards/redemptions")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "reward_id".to_string(),
+                "shoutout-id".to_string(),
+            ))
+            .match_body(r#"{"status":"CANCELED"}"#)
+            .with_status(200)
+            .expect(2)
+            .create_async()
+            .await;
+        let _unknown = server
+            .mock("GET", "/users")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "login".to_string(),
+                "nobody".to_string(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"data":[]}"#)
+            .create_async()
+            .await;
+        let helix = || async {
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await
+                .map(|helix| Arc::new(Mutex::new(helix)))
+        };
+
+        let client = MockTwitchClient::new();
+        let mut registry = CommandRegistry::new();
+        registry.register(
+            "so",
+            Arc::new(ShoutoutCommand::new("Go see {login}!".to_string())),
+        );
+        let handler = CommandHandler::new(
+            Arc::new(client.clone()),
+            Arc::new(RwLock::new(registry)),
+            CommandPrefixes::new("!".to_string(), HashMap::new())?,
+            create_test_context_with_helix(&server.url()).await,
+        );
+        let service = RedemptionService::new(
+            Arc::new(client.clone()),
+            Arc::new(handler),
+            helix().await?,
+            RedemptionActions::parse("Shoutout=run !so {input}")?,
+        );
+
+        // Blank input gets the usage, and a channel that doesn't exist is turned down
+        for input in ["  ", "nobody"] {
+            assert!(
+                service
+                    .process("test_channel", &redemption("Shoutout", input))
+                    .await
+                    .is_err()
+            );
+        }
+        assert_eq!(
+            client.sent_messages(),
+            vec![
+                (
+                    "test_channel".to_string(),
+                    "Usage: !so @streamer".to_string()
+                ),
+                (
+                    "test_channel".to_string(),
+                    "Couldn't find a channel named nobody".to_string()
+                ),
+            ]
+        );
+        canceled.assert_async().await;
+        Ok(())
+    }
+}
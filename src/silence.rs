@@ -0,0 +1,205 @@
+//! How long chat has gone quiet
+//!
+//! Tracks when the last chat message arrived, so !silence can say how long
+//! chat was quiet before it, and can post a prompt to get chat going again
+//! after a long enough silence while the stream is live. Only one prompt is
+//! posted per silence; the next one waits until someone has chatted again.
+
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{error, info};
+
+use crate::twitch::MessageSender;
+
+/// The prompt posted after a long silence unless the config sets one
+pub const DEFAULT_SILENCE_PROMPT: &str = "It's awfully quiet in here... how's everyone doing?";
+
+/// How often the silence is checked against the prompt threshold
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// When chat messages arrived
+#[derive(Default)]
+struct LastMessages {
+    /// When the latest chat message arrived
+    latest: Option<Instant>,
+    /// How long chat was quiet before the latest message
+    gap: Option<Duration>,
+    /// Whether the current silence has already been prompted
+    prompted: bool,
+}
+
+/// Keeps track of chat silence and prompts chat after long ones
+pub struct SilenceTracker {
+    /// The client for sending messages
+    client: Arc<dyn MessageSender>,
+    /// The channel whose chat is tracked
+    channel: String,
+    /// When chat messages arrived
+    messages: Mutex<LastMessages>,
+    /// Whether the stream is live; prompts are only posted while it is
+    live: AtomicBool,
+    /// How long a silence lasts before it's prompted (None disables prompts)
+    prompt_after: Option<Duration>,
+    /// The message posted to break a long silence
+    prompt: String,
+}
+
+impl SilenceTracker {
+    /// Create a silence tracker that doesn't prompt
+    ///
+    /// # Arguments
+    /// * `client` - The Twitch client for sending messages
+    /// * `channel` - The channel whose chat is tracked
+    ///
+    /// # Returns
+    /// A new SilenceTracker instance
+    pub fn new(client: Arc<dyn MessageSender>, channel: &str) -> Self {
+        SilenceTracker {
+            client,
+            channel: channel.to_string(),
+            messages: Mutex::new(LastMessages::default()),
+            live: AtomicBool::new(false),
+            prompt_after: None,
+            prompt: DEFAULT_SILENCE_PROMPT.to_string(),
+        }
+    }
+
+    /// Post a prompt when chat goes quiet for long enough while live
+    ///
+    /// # Arguments
+    /// * `after` - How long a silence lasts before it's prompted
+    /// * `prompt` - The message posted to break the silence
+    pub fn set_prompt(&mut self, after: Duration, prompt: &str) {
+        self.prompt_after = Some(after);
+        self.prompt = prompt.to_string();
+    }
+
+    /// Record whether the stream is live
+    ///
+    /// # Arguments
+    /// * `live` - true while the stream is live
+    pub fn set_live(&self, live: bool) {
+        self.live.store(live, Ordering::Relaxed);
+    }
+
+    /// Record that a chat message arrived
+    ///
+    /// # Arguments
+    /// * `channel` - Login of the channel the message was sent in
+    pub fn record_chat_message(&self, channel: &str) {
+        if !channel.eq_ignore_ascii_case(&self.channel) {
+            return;
+        }
+        let now = Instant::now();
+        let mut messages = self.messages.lock().unwrap();
+        messages.gap = messages.latest.map(|latest| now - latest);
+        messages.latest = Some(now);
+        messages.prompted = false;
+    }
+
+    /// Get how long chat was quiet before the latest message
+    ///
+    /// # Returns
+    /// The gap before the latest message, or None if fewer than two messages have arrived
+    pub fn previous_silence(&self) -> Option<Duration> {
+        self.messages.lock().unwrap().gap
+    }
+
+    /// Post the prompt if chat has been quiet for long enough while live
+    ///
+    /// # Returns
+    /// true if the prompt was posted
+    pub async fn tick(&self) -> Result<bool> {
+        let Some(prompt_after) = self.prompt_after else {
+            return Ok(false);
+        };
+        if !self.live.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+
+        {
+            let mut messages = self.messages.lock().unwrap();
+            // Before anyone chats there's no silence to break yet
+            let Some(latest) = messages.latest else {
+                return Ok(false);
+            };
+            if messages.prompted || latest.elapsed() < prompt_after {
+                return Ok(false);
+            }
+            messages.prompted = true;
+        }
+
+        info!("Chat has been quiet in {}, posting a prompt", self.channel);
+        self.client.say(&self.channel, &self.prompt).await?;
+        Ok(true)
+    }
+
+    /// Check for long silences periodically, forever
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            if let Err(e) = self.tick().await {
+                error!("Error posting silence prompt: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::users::MockTwitchClient;
+    use tokio::time::advance;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_silence_durations() {
+        let tracker = SilenceTracker::new(Arc::new(MockTwitchClient::new()), "test_channel");
+        tracker.record_chat_message("test_channel");
+        assert_eq!(tracker.previous_silence(), None);
+
+        advance(Duration::from_secs(95)).await;
+        tracker.record_chat_message("test_channel");
+        assert_eq!(tracker.previous_silence(), Some(Duration::from_secs(95)));
+        advance(Duration::from_secs(2)).await;
+        tracker.record_chat_message("test_channel");
+        assert_eq!(tracker.previous_silence(), Some(Duration::from_secs(2)));
+
+        // Other channels don't count
+        advance(Duration::from_secs(5)).await;
+        tracker.record_chat_message("other_channel");
+        assert_eq!(tracker.previous_silence(), Some(Duration::from_secs(2)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_prompt_after_long_silence_while_live() -> Result<()> {
+        let client = Arc::new(MockTwitchClient::new());
+        let mut tracker = SilenceTracker::new(client.clone(), "test_channel");
+        tracker.set_prompt(Duration::from_secs(300), "Anyone there?");
+
+        tracker.record_chat_message("test_channel");
+        advance(Duration::from_secs(600)).await;
+        // Offline silences are left alone
+        assert!(!tracker.tick().await?);
+
+        tracker.set_live(true);
+        assert!(tracker.tick().await?);
+        // One prompt per silence
+        advance(Duration::from_secs(600)).await;
+        assert!(!tracker.tick().await?);
+        assert_eq!(
+            client.sent_messages(),
+            vec![("test_channel".to_string(), "Anyone there?".to_string())]
+        );
+
+        tracker.record_chat_message("test_channel");
+        advance(Duration::from_secs(299)).await;
+        assert!(!tracker.tick().await?);
+        advance(Duration::from_secs(1)).await;
+        assert!(tracker.tick().await?);
+        assert_eq!(client.sent_messages().len(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+//! Chat read from text for running the bot offline
+//!
+//! `som_chatbot simulate` reads chat one message per line, like
+//! `alice: hello everyone`, and feeds each through the welcome service and
+//! command handler as if it came from Twitch. Badges go after the name,
+//! e.g. `bob/moderator+vip: !quote add ...`. Lines without a name are from
+//! `viewer`, and blank lines and lines starting with `#` are skipped.
+
+use chrono::Utc;
+use twitch_irc::message::{
+    Badge, IRCMessage, IRCPrefix, IRCTags, PrivmsgMessage, TwitchUserBasics,
+};
+
+/// Who sends lines that don't name a chatter
+const DEFAULT_CHATTER: &str = "viewer";
+
+/// A chat message read from a line of input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedMessage {
+    /// The sender's login
+    pub login: String,
+    /// Names of the sender's badges, e.g. "moderator"
+    pub badges: Vec<String>,
+    /// The message text
+    pub text: String,
+}
+
+impl SimulatedMessage {
+    /// Read a chat message from a line like `alice/moderator: hello`
+    ///
+    /// # Arguments
+    /// * `line` - The line of input
+    ///
+    /// # Returns
+    /// The message, or None for blank lines and comments
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        // Only a single word of name characters counts as the sender, so
+        // messages that just contain a colon are left alone
+        let is_sender = |sender: &str| {
+            let name = sender.split('/').next().unwrap_or_default();
+            !name.is_empty()
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !sender.contains(char::is_whitespace)
+        };
+        let (sender, text) = match line.split_once(':') {
+            Some((sender, text)) if is_sender(sender) => (sender, text.trim()),
+            _ => (DEFAULT_CHATTER, line),
+        };
+
+        let (login, badges) = sender.split_once('/').unwrap_or((sender, ""));
+        Some(SimulatedMessage {
+            login: login.to_lowercase(),
+            badges: badges
+                .split('+')
+                .filter(|badge| !badge.is_empty())
+                .map(|badge| badge.to_lowercase())
+                .collect(),
+            text: text.to_string(),
+        })
+    }
+
+    /// Build the chat message Twitch would deliver for this line
+    ///
+    /// The sender's login doubles as their user ID.
+    ///
+    /// # Arguments
+    /// * `channel` - The channel the message is sent in
+    /// * `message_id` - A unique ID for the message
+    ///
+    /// # Returns
+    /// The chat message
+    pub fn to_privmsg(&self, channel: &str, message_id: u64) -> PrivmsgMessage {
+        let source = IRCMessage {
+            tags: IRCTags::new(),
+            prefix: Some(IRCPrefix::HostOnly {
+                host: format!("{0}!{0}@{0}.tmi.twitch.tv", self.login),
+            }),
+            command: "PRIVMSG".to_string(),
+            params: vec![format!("#{}", channel), self.text.clone()],
+        };
+
+        PrivmsgMessage {
+            channel_login: channel.to_string(),
+            message_text: self.text.clone(),
+            sender: TwitchUserBasics {
+                id: self.login.clone(),
+                login: self.login.clone(),
+                name: self.login.clone(),
+            },
+            source,
+            channel_id: channel.to_string(),
+            message_id: format!("simulated-{}", message_id),
+            server_timestamp: Utc::now(),
+            name_color: None,
+            badges: self
+                .badges
+                .iter()
+                .map(|name| Badge {
+                    name: name.clone(),
+                    version: "1".to_string(),
+                })
+                .collect(),
+            badge_info: Vec::new(),
+            emotes: Vec::new(),
+            bits: None,
+            is_action: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_simulated_lines() {
+        let message = SimulatedMessage::parse("Bob/moderator+vip: !quote 3").unwrap();
+        assert_eq!(message.login, "bob");
+        assert_eq!(message.badges, vec!["moderator", "vip"]);
+        assert_eq!(message.text, "!quote 3");
+
+        let message = SimulatedMessage::parse("alice: hi: there").unwrap();
+        assert_eq!(
+            (message.login.as_str(), message.text.as_str()),
+            ("alice", "hi: there")
+        );
+        assert!(message.badges.is_empty());
+
+        // Without a name the line is from the default chatter
+        let message = SimulatedMessage::parse("!8ball will it rain: yes?").unwrap();
+        assert_eq!(message.login, DEFAULT_CHATTER);
+        assert_eq!(message.text, "!8ball will it rain: yes?");
+
+        assert_eq!(SimulatedMessage::parse("   "), None);
+        assert_eq!(SimulatedMessage::parse("# a comment"), None);
+
+        let privmsg = SimulatedMessage::parse("bob/moderator: hi")
+            .unwrap()
+            .to_privmsg("test_channel", 7);
+        assert_eq!(privmsg.sender.id, "bob");
+        assert_eq!(privmsg.message_id, "simulated-7");
+        assert_eq!(privmsg.badges[0].name, "moderator");
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Get the temporary file a write to `path` goes through, e.g. `store.json.tmp`
+///
+/// # Arguments
+/// * `path` - The file being written
+///
+/// # Returns
+/// The path of the temporary file, in the same directory so the rename stays atomic
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Replace a file's contents without ever leaving it half-written
+///
+/// The contents go to a temporary file next to it, which is flushed to disk
+/// and then renamed over the file. A crash leaves either the old file or the
+/// new one, plus at worst a stray temporary file that the next write replaces.
+///
+/// # Arguments
+/// * `path` - The file to write
+/// * `contents` - The new contents
+///
+/// # Returns
+/// A Result indicating success or failure
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let temp_path = temp_path(path);
+    let mut file = std::fs::File::create(&temp_path)?;
+    file.write_all(contents.as_ref())?;
+    file.sync_all()?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_atomic() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("store.json");
+        assert_eq!(temp_path(&path), temp_dir.path().join("store.json.tmp"));
+
+        write_atomic(&path, "{\"first\":1}")?;
+        assert_eq!(std::fs::read_to_string(&path)?, "{\"first\":1}");
+
+        // A crash after the temporary file was partly written leaves the old file alone
+        std::fs::write(temp_path(&path), "{\"sec")?;
+        assert_eq!(std::fs::read_to_string(&path)?, "{\"first\":1}");
+
+        // The next write replaces the leftover and cleans up after itself
+        write_atomic(&path, "{\"second\":2}")?;
+        assert_eq!(std::fs::read_to_string(&path)?, "{\"second\":2}");
+        assert!(!temp_path(&path).exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::store::{Store, write_atomic};
+
+/// Every namespace's keys and values
+type Namespaces = BTreeMap<String, BTreeMap<String, Value>>;
+
+/// Store kept in a single JSON file
+///
+/// The whole file is rewritten on each change, which suits the small amounts
+/// of state the bot keeps. For state that changes on every chat message,
+/// saves can be deferred so that changes are written together by `flush`.
+pub struct FileStore {
+    /// Path of the JSON file
+    path: PathBuf,
+    /// Contents of the file
+    data: Mutex<Namespaces>,
+    /// Whether changes wait for `flush` instead of being saved right away
+    deferred: bool,
+    /// Whether there are changes not yet on disk (only set while deferred)
+    dirty: AtomicBool,
+}
+
+impl FileStore {
+    /// Open (or create) a store file
+    ///
+    /// A file that isn't valid JSON, e.g. one cut off by an older version
+    /// crashing mid-write, is refused rather than loaded as partial data.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the JSON file
+    ///
+    /// # Returns
+    /// A Result containing the FileStore if successful
+    pub fn open(path: &Path) -> Result<Self> {
+        let data = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(path)?).map_err(|e| {
+                anyhow!(
+                    "Store file {} is corrupt or incomplete: {}",
+                    path.display(),
+                    e
+                )
+            })?
+        } else {
+            Namespaces::new()
+        };
+
+        Ok(FileStore {
+            path: path.to_path_buf(),
+            data: Mutex::new(data),
+            deferred: false,
+            dirty: AtomicBool::new(false),
+        })
+    }
+
+    /// Keep changes in memory until `flush` instead of saving each one
+    ///
+    /// Changes made since the last flush are lost if the bot crashes.
+    pub fn defer_saves(&mut self) {
+        self.deferred = true;
+    }
+
+    /// Write the data to disk
+    ///
+    /// Writes atomically so a crash can't leave a half-written store.
+    fn save(&self, data: &Namespaces) -> Result<()> {
+        write_atomic(&self.path, serde_json::to_string_pretty(data)?)
+    }
+}
+
+impl Store for FileStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Value>> {
+        let data = self.data.lock().unwrap();
+        Ok(data.get(namespace).and_then(|ns| ns.get(key)).cloned())
+    }
+
+    fn set(&self, namespace: &str, key: &str, value: Value) -> Result<()> {
+        let mut data = self.data.lock().unwrap();
+        let previous = data
+            .entry(namespace.to_string())
+            .or_default()
+            .insert(key.to_string(), value);
+        if self.deferred {
+            self.dirty.store(true, Ordering::Release);
+            return Ok(());
+        }
+
+        // Keep memory and disk in step if the write fails
+        if let Err(e) = self.save(&data) {
+            let ns = data.get_mut(namespace).unwrap();
+            match previous {
+                Some(previous) => ns.insert(key.to_string(), previous),
+                None => ns.remove(key),
+            };
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn remove(&self, namespace: &str, key: &str) -> Result<bool> {
+        let mut data = self.data.lock().unwrap();
+        let Some(previous) = data.get_mut(namespace).and_then(|ns| ns.remove(key)) else {
+            return Ok(false);
+        };
+        if self.deferred {
+            self.dirty.store(true, Ordering::Release);
+            return Ok(true);
+        }
+
+        if let Err(e) = self.save(&data) {
+            data.get_mut(namespace)
+                .unwrap()
+                .insert(key.to_string(), previous);
+            return Err(e);
+        }
+        Ok(true)
+    }
+
+    fn list(&self, namespace: &str) -> Result<Vec<(String, Value)>> {
+        let data = self.data.lock().unwrap();
+        Ok(data
+            .get(namespace)
+            .map(|ns| ns.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+            .unwrap_or_default())
+    }
+
+    fn flush(&self) -> Result<bool> {
+        let data = self.data.lock().unwrap();
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return Ok(false);
+        }
+
+        // Try again on the next flush if the write fails
+        if let Err(e) = self.save(&data) {
+            self.dirty.store(true, Ordering::Release);
+            return Err(e);
+        }
+        Ok(true)
+    }
+}
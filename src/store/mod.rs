@@ -0,0 +1,338 @@
+//! Key-value persistence shared by the bot's stateful features
+//!
+//! Each feature keeps its data under its own namespace (e.g. "quotes") as
+//! JSON values, so adding state doesn't mean inventing another file format.
+//! The backend is picked with `STORE_BACKEND`.
+//!
+//! Known users aren't kept here: they're written on every chat message and
+//! queried across all users, so they have their own SQLite database in
+//! `users::store`.
+
+mod atomic;
+mod file;
+mod sqlite;
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error};
+
+pub use atomic::write_atomic;
+pub use file::FileStore;
+pub use sqlite::SqliteStore;
+
+/// Namespaced key-value storage with JSON values
+pub trait Store: Send + Sync {
+    /// Get a value
+    ///
+    /// # Arguments
+    /// * `namespace` - The feature the value belongs to
+    /// * `key` - The value's key within the namespace
+    ///
+    /// # Returns
+    /// The value, or None if the key isn't set
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Value>>;
+
+    /// Set a value, replacing any existing one
+    ///
+    /// # Arguments
+    /// * `namespace` - The feature the value belongs to
+    /// * `key` - The value's key within the namespace
+    /// * `value` - The value to store
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    fn set(&self, namespace: &str, key: &str, value: Value) -> Result<()>;
+
+    /// Remove a value
+    ///
+    /// # Arguments
+    /// * `namespace` - The feature the value belongs to
+    /// * `key` - The value's key within the namespace
+    ///
+    /// # Returns
+    /// true if the key was set
+    fn remove(&self, namespace: &str, key: &str) -> Result<bool>;
+
+    /// List every key and value in a namespace, ordered by key
+    ///
+    /// # Arguments
+    /// * `namespace` - The namespace to list
+    fn list(&self, namespace: &str) -> Result<Vec<(String, Value)>>;
+
+    /// Save changes that are still only in memory
+    ///
+    /// Backends that save every change right away have nothing to do.
+    ///
+    /// # Returns
+    /// true if anything was written
+    fn flush(&self) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+impl<'a> dyn Store + 'a {
+    /// Get a value and deserialize it
+    ///
+    /// # Arguments
+    /// * `namespace` - The feature the value belongs to
+    /// * `key` - The value's key within the namespace
+    pub fn get_as<T: DeserializeOwned>(&self, namespace: &str, key: &str) -> Result<Option<T>> {
+        self.get(namespace, key)?
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    /// Serialize a value and set it
+    ///
+    /// # Arguments
+    /// * `namespace` - The feature the value belongs to
+    /// * `key` - The value's key within the namespace
+    /// * `value` - The value to store
+    pub fn set_as<T: Serialize>(&self, namespace: &str, key: &str, value: &T) -> Result<()> {
+        self.set(namespace, key, serde_json::to_value(value)?)
+    }
+
+    /// List a namespace, deserializing each value
+    ///
+    /// # Arguments
+    /// * `namespace` - The namespace to list
+    pub fn list_as<T: DeserializeOwned>(&self, namespace: &str) -> Result<Vec<(String, T)>> {
+        self.list(namespace)?
+            .into_iter()
+            .map(|(key, value)| Ok((key, serde_json::from_value(value)?)))
+            .collect()
+    }
+}
+
+/// How often a file store saves pending changes unless the config says otherwise
+pub const DEFAULT_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Which backend stores the data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StoreBackend {
+    /// A single JSON file (`store.json`)
+    #[default]
+    File,
+    /// A SQLite database (`store.db`)
+    Sqlite,
+}
+
+impl FromStr for StoreBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "file" | "json" => Ok(StoreBackend::File),
+            "sqlite" => Ok(StoreBackend::Sqlite),
+            other => Err(anyhow!("Unknown store backend: {}", other)),
+        }
+    }
+}
+
+/// Open the store in the data directory
+///
+/// # Arguments
+/// * `backend` - Which backend to use
+/// * `data_dir` - Directory the store's file is kept in
+/// * `save_interval` - How often `flush_periodically` will save a file store,
+///   which then keeps changes in memory until then; None saves every change
+///
+/// # Returns
+/// The opened store
+pub fn open_store(
+    backend: StoreBackend,
+    data_dir: &Path,
+    save_interval: Option<Duration>,
+) -> Result<Arc<dyn Store>> {
+    Ok(match backend {
+        StoreBackend::File => {
+            let mut store = FileStore::open(&data_dir.join("store.json"))?;
+            if save_interval.is_some() {
+                store.defer_saves();
+            }
+            Arc::new(store)
+        }
+        // Each SQLite write only touches the rows that changed, so there's
+        // nothing to gain from deferring them
+        StoreBackend::Sqlite => Arc::new(SqliteStore::open(&data_dir.join("store.db"))?),
+    })
+}
+
+/// Save a store's pending changes every `interval`, forever
+///
+/// However many changes are made in between, each interval writes at most once.
+///
+/// # Arguments
+/// * `store` - The store to flush
+/// * `interval` - How long to wait between saves
+pub async fn flush_periodically(store: Arc<dyn Store>, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        match store.flush() {
+            Ok(true) => debug!("Saved the store's pending changes"),
+            Ok(false) => {}
+            Err(e) => error!("Error saving the store: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::thread;
+    use tempfile::tempdir;
+
+    /// Open each backend in a fresh directory, run a check, then reopen and run another
+    fn for_each_backend(
+        check: impl Fn(&dyn Store) -> Result<()>,
+        check_reopened: impl Fn(&dyn Store) -> Result<()>,
+    ) -> Result<()> {
+        for backend in [StoreBackend::File, StoreBackend::Sqlite] {
+            let temp_dir = tempdir()?;
+            check(open_store(backend, temp_dir.path(), None)?.as_ref())?;
+            check_reopened(open_store(backend, temp_dir.path(), None)?.as_ref())?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        for_each_backend(
+            |store| {
+                store.set("counters", "deaths", json!(3))?;
+                store.set_as("counters", "wins", &5)?;
+                assert_eq!(store.get("counters", "deaths")?, Some(json!(3)));
+                assert_eq!(store.get_as::<u32>("counters", "wins")?, Some(5));
+                assert_eq!(store.get("counters", "missing")?, None);
+
+                store.set("counters", "deaths", json!(4))?;
+                assert!(store.remove("counters", "wins")?);
+                assert!(!store.remove("counters", "wins")?);
+                Ok(())
+            },
+            |store| {
+                // Values survive reopening the store
+                assert_eq!(
+                    store.list_as::<u32>("counters")?,
+                    vec![("deaths".to_string(), 4)]
+                );
+                Ok(())
+            },
+        )
+    }
+
+    #[test]
+    fn test_namespaces_are_separate() -> Result<()> {
+        for_each_backend(
+            |store| {
+                store.set("quotes", "1", json!({"text": "hello"}))?;
+                store.set("counters", "1", json!(10))?;
+                assert_eq!(store.get("quotes", "1")?, Some(json!({"text": "hello"})));
+                assert_eq!(store.list("counters")?, vec![("1".to_string(), json!(10))]);
+
+                assert!(store.remove("counters", "1")?);
+                assert!(store.get("quotes", "1")?.is_some());
+                Ok(())
+            },
+            |store| {
+                assert!(store.list("counters")?.is_empty());
+                assert_eq!(store.list("quotes")?.len(), 1);
+                Ok(())
+            },
+        )
+    }
+
+    #[test]
+    fn test_concurrent_writes() -> Result<()> {
+        for backend in [StoreBackend::File, StoreBackend::Sqlite] {
+            let temp_dir = tempdir()?;
+            let store = open_store(backend, temp_dir.path(), None)?;
+
+            let writers: Vec<_> = (0..8)
+                .map(|writer| {
+                    let store = store.clone();
+                    thread::spawn(move || -> Result<()> {
+                        for i in 0..25 {
+                            store.set("points", &format!("{}-{}", writer, i), json!(i))?;
+                        }
+                        Ok(())
+                    })
+                })
+                .collect();
+            for writer in writers {
+                writer.join().unwrap()?;
+            }
+
+            // Every write made it, including to disk
+            assert_eq!(store.list("points")?.len(), 200);
+            drop(store);
+            assert_eq!(
+                open_store(backend, temp_dir.path(), None)?
+                    .list("points")?
+                    .len(),
+                200
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_store_file_is_refused() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("store.json");
+        std::fs::write(&path, r#"{"counters":{"deaths":3,"wi"#)?;
+
+        let error = open_store(StoreBackend::File, temp_dir.path(), None)
+            .err()
+            .expect("a truncated store should not load");
+        assert!(error.to_string().contains("corrupt or incomplete"));
+        // The damaged file is kept for recovery rather than overwritten
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            r#"{"counters":{"deaths":3,"wi"#
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_deferred_saves_are_coalesced() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("store.json");
+        let interval = Duration::from_secs(5);
+        let store = open_store(StoreBackend::File, temp_dir.path(), Some(interval))?;
+        tokio::spawn(flush_periodically(store.clone(), interval));
+
+        // A burst of changes stays in memory until the interval is up
+        for points in 1..=20 {
+            store.set("points", "regular", json!(points))?;
+        }
+        store.set("points", "lurker", json!(1))?;
+        assert!(store.remove("points", "lurker")?);
+        tokio::time::sleep(interval / 2).await;
+        assert!(!path.exists());
+        assert_eq!(store.get("points", "regular")?, Some(json!(20)));
+
+        // Then they're written together, and only once
+        tokio::time::sleep(interval).await;
+        let saved: Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        assert_eq!(saved, json!({"points": {"regular": 20}}));
+        assert!(!store.flush()?);
+
+        // A flush on shutdown saves whatever is left
+        store.set("points", "regular", json!(21))?;
+        assert!(store.flush()?);
+        assert_eq!(
+            open_store(StoreBackend::File, temp_dir.path(), None)?.get("points", "regular")?,
+            Some(json!(21))
+        );
+        Ok(())
+    }
+}
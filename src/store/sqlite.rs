@@ -0,0 +1,83 @@
+use anyhow::Result;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::store::Store;
+
+/// Schema for the key-value table
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS kv (
+    namespace TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (namespace, key)
+)";
+
+/// Store kept in a SQLite database
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Open (or create) a store database
+    ///
+    /// # Arguments
+    /// * `path` - Path to the SQLite database file
+    ///
+    /// # Returns
+    /// A Result containing the SqliteStore if successful
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute(SCHEMA, [])?;
+        Ok(SqliteStore {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+impl Store for SqliteStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Value>> {
+        let conn = self.conn.lock().unwrap();
+        let value: Option<String> = conn
+            .query_row(
+                "SELECT value FROM kv WHERE namespace = ?1 AND key = ?2",
+                params![namespace, key],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(value.map(|v| serde_json::from_str(&v)).transpose()?)
+    }
+
+    fn set(&self, namespace: &str, key: &str, value: Value) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO kv (namespace, key, value) VALUES (?1, ?2, ?3)",
+            params![namespace, key, value.to_string()],
+        )?;
+        Ok(())
+    }
+
+    fn remove(&self, namespace: &str, key: &str) -> Result<bool> {
+        let removed = self.conn.lock().unwrap().execute(
+            "DELETE FROM kv WHERE namespace = ?1 AND key = ?2",
+            params![namespace, key],
+        )?;
+        Ok(removed > 0)
+    }
+
+    fn list(&self, namespace: &str) -> Result<Vec<(String, Value)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement =
+            conn.prepare("SELECT key, value FROM kv WHERE namespace = ?1 ORDER BY key")?;
+        let rows = statement
+            .query_map(params![namespace], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        rows.into_iter()
+            .map(|(key, value)| Ok((key, serde_json::from_str(&value)?)))
+            .collect()
+    }
+}
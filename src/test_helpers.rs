@@ -1,9 +1,15 @@
 #![allow(dead_code)]
 /// Test helpers for unit tests
+use crate::commands::CommandContext;
 use crate::config::Config;
-use crate::twitch::{OAuthManager, TwitchClient};
+use crate::twitch::{HelixChatClient, OAuthManager, TokenResponse, TwitchClient};
+use crate::users::UserManager;
+use chrono::Utc;
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use twitch_irc::message::{
+    Badge, IRCMessage, IRCPrefix, IRCTags, PrivmsgMessage, TwitchUserBasics,
+};
 
 /// Create a test TwitchClient that doesn't actually connect to Twitch
 pub fn create_test_client() -> TwitchClient {
@@ -22,6 +28,24 @@ pub fn create_test_config() -> Config {
     )
 }
 
+/// Create a test command context with an empty user manager and no Helix client
+pub fn create_test_context() -> CommandContext {
+    CommandContext::new(
+        Arc::new(UserManager::new("test.db").unwrap()),
+        "test_channel".to_string(),
+    )
+}
+
+/// Create a test command context whose Helix client talks to a mock server
+pub async fn create_test_context_with_helix(base_url: &str) -> CommandContext {
+    let helix = HelixChatClient::with_base_url(create_authenticated_oauth_manager(), base_url)
+        .await
+        .unwrap();
+    let mut ctx = create_test_context();
+    ctx.set_helix(Arc::new(Mutex::new(helix)));
+    ctx
+}
+
 /// Create a test OAuth manager
 pub fn create_test_oauth_manager() -> Arc<Mutex<OAuthManager>> {
     Arc::new(Mutex::new(OAuthManager::new(
@@ -29,3 +53,61 @@ pub fn create_test_oauth_manager() -> Arc<Mutex<OAuthManager>> {
         vec!["chat:read".to_string(), "chat:edit".to_string()],
     )))
 }
+
+/// Create a test OAuth manager that already holds a valid access token
+pub fn create_authenticated_oauth_manager() -> Arc<Mutex<OAuthManager>> {
+    let mut manager = OAuthManager::new(
+        "test_client_id".to_string(),
+        vec!["chat:read".to_string(), "chat:edit".to_string()],
+    );
+    manager.set_token(TokenResponse {
+        access_token: "test_access_token".to_string(),
+        expires_in: 3600,
+        refresh_token: "test_refresh_token".to_string(),
+        scope: vec!["chat:read".to_string(), "chat:edit".to_string()],
+        token_type: "bearer".to_string(),
+    });
+    Arc::new(Mutex::new(manager))
+}
+
+/// Create a test chat message from `test_user` in `test_channel`
+///
+/// # Arguments
+/// * `text` - The message text
+/// * `badges` - Names of the badges the sender has (e.g. "moderator")
+pub fn create_test_privmsg_with_badges(text: &str, badges: &[&str]) -> PrivmsgMessage {
+    let irc_message = IRCMessage {
+        tags: IRCTags::new(),
+        prefix: Some(IRCPrefix::HostOnly {
+            host: "test_user!test_user@test_user.tmi.twitch.tv".to_string(),
+        }),
+        command: "PRIVMSG".to_string(),
+        params: vec!["#test_channel".to_string(), text.to_string()],
+    };
+
+    PrivmsgMessage {
+        channel_login: "test_channel".to_string(),
+        message_text: text.to_string(),
+        sender: TwitchUserBasics {
+            id: "123".to_string(),
+            login: "test_user".to_string(),
+            name: "Test_User".to_string(),
+        },
+        source: irc_message,
+        channel_id: "456".to_string(),
+        message_id: "abc".to_string(),
+        server_timestamp: Utc::now(),
+        name_color: None,
+        badges: badges
+            .iter()
+            .map(|name| Badge {
+                name: (*name).to_string(),
+                version: "1".to_string(),
+            })
+            .collect(),
+        badge_info: Vec::new(),
+        emotes: Vec::new(),
+        bits: None,
+        is_action: false,
+    }
+}
@@ -0,0 +1,114 @@
+//! Exponential backoff between reconnection attempts
+
+use std::time::Duration;
+
+/// Delay before the first reconnection attempt
+pub const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest delay between reconnection attempts
+pub const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Doubles the delay after every failed attempt, up to a maximum
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    /// Delay for the first attempt
+    initial: Duration,
+    /// Longest delay to wait
+    max: Duration,
+    /// Delay for the next attempt
+    next: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(INITIAL_RECONNECT_DELAY, MAX_RECONNECT_DELAY)
+    }
+}
+
+impl Backoff {
+    /// Create a new backoff
+    ///
+    /// # Arguments
+    /// * `initial` - Delay before the first attempt
+    /// * `max` - Longest delay between attempts
+    ///
+    /// # Returns
+    /// A new Backoff instance
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Backoff {
+            initial,
+            max,
+            next: initial,
+        }
+    }
+
+    /// Get the delay before the next attempt and double it for the one after
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    /// Start over from the initial delay, e.g. after a successful attempt
+    pub fn reset(&mut self) {
+        self.next = self.initial;
+    }
+
+    /// Get how long to wait before reconnecting after a connection ends
+    ///
+    /// A connection that ended before delivering any message counts as a
+    /// failed attempt. One Twitch asked us to leave with RECONNECT doesn't,
+    /// so it's replaced right away and the delay isn't doubled.
+    ///
+    /// # Arguments
+    /// * `received` - Whether the connection delivered any message
+    /// * `reconnect_requested` - Whether Twitch sent RECONNECT
+    ///
+    /// # Returns
+    /// The delay to wait, or None to reconnect right away
+    pub fn delay_after_disconnect(
+        &mut self,
+        received: bool,
+        reconnect_requested: bool,
+    ) -> Option<Duration> {
+        (!received && !reconnect_requested).then(|| self.next_delay())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+
+        let delays: Vec<u64> = (0..5).map(|_| backoff.next_delay().as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_requested_reconnect_is_not_a_failure() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+
+        // Connections that drop before any message back off further each time
+        assert_eq!(
+            backoff.delay_after_disconnect(false, false),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            backoff.delay_after_disconnect(false, false),
+            Some(Duration::from_secs(2))
+        );
+
+        // RECONNECT and working connections reconnect right away without
+        // touching the delay
+        assert_eq!(backoff.delay_after_disconnect(false, true), None);
+        assert_eq!(backoff.delay_after_disconnect(true, true), None);
+        assert_eq!(backoff.delay_after_disconnect(true, false), None);
+        assert_eq!(backoff.next_delay(), Duration::from_secs(4));
+    }
+}
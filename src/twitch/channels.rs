@@ -0,0 +1,128 @@
+//! Tracking of the channels the bot has joined
+
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// The bot's state in a joined channel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelStatus {
+    /// Whether the chat connection for the channel is up
+    pub connected: bool,
+    /// Whether the bot is a moderator (or the broadcaster) in the channel
+    pub moderator: bool,
+}
+
+/// The set of channels the bot is in, kept in sync with joins and parts
+#[derive(Debug, Default)]
+pub struct JoinedChannels {
+    channels: RwLock<BTreeMap<String, ChannelStatus>>,
+}
+
+impl JoinedChannels {
+    /// Create an empty channel set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that the bot joined a channel
+    ///
+    /// # Arguments
+    /// * `channel` - The channel name (lowercase, without # prefix)
+    pub fn join(&self, channel: &str) {
+        self.channels.write().unwrap().insert(
+            channel.to_string(),
+            ChannelStatus {
+                connected: true,
+                moderator: false,
+            },
+        );
+    }
+
+    /// Record that the bot left a channel
+    ///
+    /// # Arguments
+    /// * `channel` - The channel name (lowercase, without # prefix)
+    ///
+    /// # Returns
+    /// Whether the bot was in the channel
+    pub fn part(&self, channel: &str) -> bool {
+        self.channels.write().unwrap().remove(channel).is_some()
+    }
+
+    /// Mark every channel as connected or disconnected
+    ///
+    /// # Arguments
+    /// * `connected` - Whether the chat connection is up
+    pub fn set_connected(&self, connected: bool) {
+        for status in self.channels.write().unwrap().values_mut() {
+            status.connected = connected;
+        }
+    }
+
+    /// Record whether the bot moderates a channel
+    ///
+    /// # Arguments
+    /// * `channel` - The channel name (lowercase, without # prefix)
+    /// * `moderator` - Whether the bot is a moderator (or the broadcaster)
+    pub fn set_moderator(&self, channel: &str, moderator: bool) {
+        if let Some(status) = self.channels.write().unwrap().get_mut(channel) {
+            status.moderator = moderator;
+        }
+    }
+
+    /// Whether the bot moderates every joined channel
+    ///
+    /// # Returns
+    /// true if the bot is in at least one channel and a moderator in all of them
+    pub fn moderates_all(&self) -> bool {
+        let channels = self.channels.read().unwrap();
+        !channels.is_empty() && channels.values().all(|status| status.moderator)
+    }
+
+    /// Get the joined channels with their status, sorted by name
+    pub fn list(&self) -> Vec<(String, ChannelStatus)> {
+        self.channels
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, status)| (name.clone(), *status))
+            .collect()
+    }
+
+    /// Get the names of the joined channels, sorted
+    pub fn names(&self) -> Vec<String> {
+        self.channels.read().unwrap().keys().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_part_and_status() {
+        let channels = JoinedChannels::new();
+        channels.join("second");
+        channels.join("first");
+        channels.set_moderator("first", true);
+        assert_eq!(channels.names(), vec!["first", "second"]);
+        assert!(!channels.moderates_all());
+
+        channels.set_connected(false);
+        assert_eq!(
+            channels.list()[0],
+            (
+                "first".to_string(),
+                ChannelStatus {
+                    connected: false,
+                    moderator: true
+                }
+            )
+        );
+
+        assert!(channels.part("second"));
+        assert!(!channels.part("second"));
+        assert_eq!(channels.names(), vec!["first"]);
+        assert!(channels.moderates_all());
+    }
+}
@@ -1,29 +1,160 @@
-use anyhow::{Result, anyhow};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 // Just import the UnboundedReceiver which is what we need
 use crate::config::Config;
+use crate::twitch::channels::JoinedChannels;
+use crate::twitch::delivery::DeliveryTracker;
+use crate::twitch::error::{Result, TwitchError};
 use crate::twitch::helix::HelixChatClient;
 use crate::twitch::oauth::OAuthManager;
+use crate::twitch::rate_limit::OutboundLimiter;
 use tokio::sync::mpsc::UnboundedReceiver;
 use tracing::{error, info, warn};
 use twitch_irc::ClientConfig;
 use twitch_irc::TwitchIRCClient;
 use twitch_irc::login::StaticLoginCredentials;
 use twitch_irc::message::ServerMessage;
-use twitch_irc::transport::tcp::{NoTLS, TCPTransport};
+use twitch_irc::transport::tcp::{PlainTCPTransport, SecureTCPTransport};
+
+/// Longest message Twitch chat accepts, in characters
+pub const MAX_MESSAGE_LENGTH: usize = 500;
+
+/// Split text into chat-sized messages on word boundaries
+///
+/// Words longer than a whole message are split mid-word as a last resort.
+///
+/// # Arguments
+/// * `text` - The text to split
+/// * `max_chars` - The most characters each message may have
+///
+/// # Returns
+/// The messages, in order
+pub fn split_message(text: &str, max_chars: usize) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split_whitespace() {
+        let mut word = word.to_string();
+
+        // Break up words that can't fit in any message
+        while word.chars().count() > max_chars {
+            if !current.is_empty() {
+                messages.push(std::mem::take(&mut current));
+            }
+            let head: String = word.chars().take(max_chars).collect();
+            word = word.chars().skip(max_chars).collect();
+            messages.push(head);
+        }
+
+        let needed = if current.is_empty() {
+            word.chars().count()
+        } else {
+            current.chars().count() + 1 + word.chars().count()
+        };
+        if needed > max_chars {
+            messages.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(&word);
+    }
+
+    if !current.is_empty() {
+        messages.push(current);
+    }
+    messages
+}
+
+/// An IRC connection to Twitch chat, over TLS or plaintext
+#[derive(Clone)]
+enum IrcClient {
+    /// Encrypted connection (port 6697)
+    Secure(TwitchIRCClient<SecureTCPTransport, StaticLoginCredentials>),
+    /// Unencrypted connection (port 6667)
+    Plain(TwitchIRCClient<PlainTCPTransport, StaticLoginCredentials>),
+}
+
+impl IrcClient {
+    /// Create an IRC client that logs in with the given token
+    ///
+    /// # Arguments
+    /// * `username` - The bot's username
+    /// * `token` - The OAuth access token
+    /// * `use_tls` - Whether to encrypt the connection
+    ///
+    /// # Returns
+    /// The receiver for incoming messages and the client
+    fn connect(
+        username: &str,
+        token: String,
+        use_tls: bool,
+    ) -> (UnboundedReceiver<ServerMessage>, Self) {
+        let credentials = StaticLoginCredentials::new(username.to_string(), Some(token));
+        let client_config = ClientConfig::new_simple(credentials);
+
+        if use_tls {
+            let (incoming_messages, client) = TwitchIRCClient::new(client_config);
+            (incoming_messages, IrcClient::Secure(client))
+        } else {
+            warn!("Connecting to Twitch chat without TLS; the OAuth token is sent in plaintext");
+            let (incoming_messages, client) = TwitchIRCClient::new(client_config);
+            (incoming_messages, IrcClient::Plain(client))
+        }
+    }
 
-// Now using the types from twitch_api crate
+    /// Join a channel
+    fn join(&self, channel: String) -> Result<()> {
+        let result = match self {
+            IrcClient::Secure(client) => client.join(channel),
+            IrcClient::Plain(client) => client.join(channel),
+        };
+        result.map_err(|e| irc_error(e.to_string()))
+    }
+
+    /// Leave a channel
+    fn part(&self, channel: String) {
+        match self {
+            IrcClient::Secure(client) => client.part(channel),
+            IrcClient::Plain(client) => client.part(channel),
+        }
+    }
+
+    /// Send a message to a channel
+    async fn say(&self, channel: String, message: String) -> Result<()> {
+        let result = match self {
+            IrcClient::Secure(client) => client
+                .say(channel, message)
+                .await
+                .map_err(|e| e.to_string()),
+            IrcClient::Plain(client) => client
+                .say(channel, message)
+                .await
+                .map_err(|e| e.to_string()),
+        };
+        result.map_err(irc_error)
+    }
+}
 
 /// Represents a connection to Twitch chat
 #[derive(Clone)]
 pub struct TwitchClient {
-    /// IRC client for traditional chat operations
-    inner: TwitchIRCClient<TCPTransport<NoTLS>, StaticLoginCredentials>,
+    /// IRC client for traditional chat operations, shared so a reconnect reaches every clone
+    inner: Arc<std::sync::RwLock<IrcClient>>,
     /// OAuth manager for authentication
     oauth_manager: Arc<Mutex<OAuthManager>>,
     /// Helix API client for modern chat operations
     helix: Arc<Mutex<HelixChatClient>>,
+    /// Paces outbound chat messages to stay under Twitch's rate limit
+    outbound: Arc<OutboundLimiter>,
+    /// Whether chat connections use TLS
+    use_tls: bool,
+    /// The channels the bot is in
+    channels: Arc<JoinedChannels>,
+    /// Sent messages waiting for Twitch to acknowledge them
+    delivery: Arc<DeliveryTracker>,
+    /// The bot's username, used when reconnecting with a fresh token
+    username: String,
 }
 
 impl TwitchClient {
@@ -42,17 +173,15 @@ impl TwitchClient {
         // Get the current access token
         let token = {
             let mut manager = oauth_manager.lock().await;
-            manager.get_access_token().await?
+            manager
+                .get_access_token()
+                .await
+                .map_err(TwitchError::Token)?
         };
 
         // Create the IRC client with static credentials
-        let client_config = ClientConfig::new_simple(StaticLoginCredentials::new(
-            config.bot_username.clone(),
-            Some(token),
-        ));
-
         let (incoming_messages, inner) =
-            TwitchIRCClient::<TCPTransport<NoTLS>, StaticLoginCredentials>::new(client_config);
+            IrcClient::connect(&config.bot_username, token, config.irc_tls);
 
         // Create Helix API client
         let helix = HelixChatClient::new(oauth_manager.clone()).await?;
@@ -60,9 +189,17 @@ impl TwitchClient {
         Ok((
             incoming_messages,
             TwitchClient {
-                inner,
+                inner: Arc::new(std::sync::RwLock::new(inner)),
                 oauth_manager: oauth_manager.clone(),
                 helix: Arc::new(Mutex::new(helix)),
+                outbound: Arc::new(OutboundLimiter::new(
+                    config.chat_rate_limit,
+                    config.chat_rate_limit_moderator,
+                )),
+                use_tls: config.irc_tls,
+                channels: Arc::new(JoinedChannels::new()),
+                delivery: Arc::new(DeliveryTracker::new()),
+                username: config.bot_username.clone(),
             },
         ))
     }
@@ -74,20 +211,17 @@ impl TwitchClient {
         // Get a fresh token
         let token = {
             let mut manager = self.oauth_manager.lock().await;
-            manager.get_access_token().await?
+            manager
+                .get_access_token()
+                .await
+                .map_err(TwitchError::Token)?
         };
 
         // Create a new IRC client with the fresh token
-        let client_config = ClientConfig::new_simple(StaticLoginCredentials::new(
-            username.to_string(),
-            Some(token),
-        ));
-
-        let (_incoming_messages, inner) =
-            TwitchIRCClient::<TCPTransport<NoTLS>, StaticLoginCredentials>::new(client_config);
+        let (_incoming_messages, inner) = IrcClient::connect(username, token, self.use_tls);
 
         // Replace the inner client
-        self.inner = inner;
+        *self.inner.write().unwrap() = inner;
 
         // The Helix client doesn't need to be recreated as it will automatically
         // get fresh tokens via the shared OAuth manager
@@ -95,6 +229,50 @@ impl TwitchClient {
         Ok(())
     }
 
+    /// Reconnect to chat with a fresh token and rejoin the joined channels
+    ///
+    /// Every clone of this client switches to the new connection.
+    ///
+    /// # Arguments
+    /// * `username` - The bot's username
+    ///
+    /// # Returns
+    /// The receiver for messages on the new connection
+    pub async fn reconnect(&mut self, username: &str) -> Result<UnboundedReceiver<ServerMessage>> {
+        info!("Reconnecting to Twitch chat");
+        self.channels.set_connected(false);
+        let channels = self.channels.names();
+
+        let token = {
+            let mut manager = self.oauth_manager.lock().await;
+            manager
+                .get_access_token()
+                .await
+                .map_err(TwitchError::Token)?
+        };
+
+        let (incoming_messages, inner) = IrcClient::connect(username, token, self.use_tls);
+
+        for channel in &channels {
+            inner.join(channel.clone())?;
+        }
+
+        *self.inner.write().unwrap() = inner;
+        self.channels.set_connected(true);
+        info!("Reconnected and rejoined: {}", channels.join(", "));
+        Ok(incoming_messages)
+    }
+
+    /// Get the bot's username
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Get the current IRC client
+    fn irc(&self) -> IrcClient {
+        self.inner.read().unwrap().clone()
+    }
+
     /// Create a new Twitch client with static credentials (used for testing)
     ///
     /// # Arguments
@@ -108,13 +286,7 @@ impl TwitchClient {
         username: &str,
         token: &str,
     ) -> (UnboundedReceiver<ServerMessage>, Self) {
-        let client_config = ClientConfig::new_simple(StaticLoginCredentials::new(
-            username.to_string(),
-            Some(token.to_string()),
-        ));
-
-        let (incoming_messages, inner) =
-            TwitchIRCClient::<TCPTransport<NoTLS>, StaticLoginCredentials>::new(client_config);
+        let (incoming_messages, inner) = IrcClient::connect(username, token.to_string(), true);
 
         // Create a dummy OAuth manager
         let oauth_manager = Arc::new(Mutex::new(OAuthManager::new(
@@ -136,9 +308,14 @@ impl TwitchClient {
         (
             incoming_messages,
             TwitchClient {
-                inner,
+                inner: Arc::new(std::sync::RwLock::new(inner)),
                 oauth_manager: oauth_manager.clone(),
                 helix: Arc::new(Mutex::new(dummy_helix)),
+                outbound: Arc::new(OutboundLimiter::default()),
+                use_tls: true,
+                channels: Arc::new(JoinedChannels::new()),
+                delivery: Arc::new(DeliveryTracker::new()),
+                username: username.to_string(),
             },
         )
     }
@@ -164,18 +341,18 @@ impl TwitchClient {
 
         info!("Formatted channel name for joining: {}", channel_name);
 
-        let join_result = self.inner.join(channel_name.clone());
+        let join_result = self.irc().join(channel_name.clone());
 
         // Check if join failed due to auth issues
         if let Err(e) = &join_result {
             warn!("Join failed with error: {}", e);
-            if e.to_string().contains("authentication") {
+            if matches!(e, TwitchError::AuthExpired) {
                 warn!(
                     "Join failed due to authentication issue, refreshing token: {}",
                     e
                 );
                 self.recreate_client(username).await?;
-                let retry_result = self.inner.join(channel_name.clone());
+                let retry_result = self.irc().join(channel_name.clone());
                 if let Err(retry_err) = retry_result {
                     warn!("Join retry failed after token refresh: {}", retry_err);
                 } else {
@@ -186,9 +363,24 @@ impl TwitchClient {
             info!("Successfully joined channel: {}", channel_name);
         }
 
+        self.channels.join(&channel_name);
         Ok(())
     }
 
+    /// Leave a Twitch channel
+    ///
+    /// # Arguments
+    /// * `channel` - The channel name to leave
+    ///
+    /// # Returns
+    /// Whether the bot was in the channel
+    pub fn part_channel(&self, channel: &str) -> bool {
+        let channel_name = normalize_channel(channel);
+        self.irc().part(channel_name.clone());
+        info!("Left channel: {}", channel_name);
+        self.channels.part(&channel_name)
+    }
+
     /// Send a message to a channel
     ///
     /// # Arguments
@@ -211,21 +403,23 @@ impl TwitchClient {
             channel.to_lowercase()
         };
 
+        self.outbound.acquire().await;
         info!("Sending message to {}: {}", channel_name, message);
 
         // First try to send via IRC for backward compatibility
         match self
-            .inner
+            .irc()
             .say(channel_name.clone(), message.to_string())
             .await
         {
             Ok(_) => {
                 info!("Successfully sent message to {} via IRC", channel_name);
+                self.delivery.record(&channel_name, message, true);
                 return Ok(());
             }
             Err(e) => {
                 // If it's an auth error, try refreshing the token and retry via IRC
-                if e.to_string().contains("authentication") {
+                if matches!(e, TwitchError::AuthExpired) {
                     warn!("Message send failed due to authentication issue, refreshing token");
                     if let Err(e) = self.recreate_client(username).await {
                         warn!("Failed to refresh token: {}", e);
@@ -233,12 +427,13 @@ impl TwitchClient {
                     } else {
                         // Retry via IRC
                         match self
-                            .inner
+                            .irc()
                             .say(channel_name.clone(), message.to_string())
                             .await
                         {
                             Ok(_) => {
                                 info!("Successfully sent message after token refresh");
+                                self.delivery.record(&channel_name, message, true);
                                 return Ok(());
                             }
                             Err(retry_e) => {
@@ -271,11 +466,12 @@ impl TwitchClient {
                     "Successfully sent message via Helix API to {}",
                     channel_name
                 );
+                self.delivery.record(&channel_name, message, false);
                 Ok(())
             }
             Err(api_e) => {
                 error!("Failed to send message via Helix API: {}", api_e);
-                Err(anyhow!("Failed to send message: {}", api_e))
+                Err(api_e)
             }
         }
     }
@@ -309,20 +505,42 @@ impl TwitchClient {
             reply_to, channel_name, message
         );
 
+        self.outbound.acquire().await;
+
         // We'll use the Helix API client to send a reply
         let mut helix = self.helix.lock().await;
         match helix.send_reply(&channel_name, message, reply_to).await {
             Ok(_) => {
                 info!("Successfully sent reply via Helix API");
+                self.delivery.record(&channel_name, message, false);
                 Ok(())
             }
             Err(api_e) => {
                 error!("Failed to send reply via Helix API: {}", api_e);
-                Err(anyhow!("Failed to send reply: {}", api_e))
+                Err(api_e)
             }
         }
     }
 
+    /// Send a whisper to a user through the Helix API
+    ///
+    /// Whispers have their own limits, so they don't wait on the chat rate limiter.
+    ///
+    /// # Arguments
+    /// * `to_user_id` - ID of the user to whisper to
+    /// * `message` - The message to send
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn send_whisper(&self, to_user_id: &str, message: &str) -> Result<()> {
+        info!("Sending whisper to user {}: {}", to_user_id, message);
+        self.helix
+            .lock()
+            .await
+            .send_whisper(to_user_id, message)
+            .await
+    }
+
     /// Get the OAuth manager used by this client
     ///
     /// # Returns
@@ -332,18 +550,90 @@ impl TwitchClient {
         self.oauth_manager.clone()
     }
 
+    /// Get the limiter that paces outbound chat messages
+    ///
+    /// # Returns
+    /// The outbound rate limiter
+    pub fn get_outbound_rate_limit(&self) -> Arc<OutboundLimiter> {
+        self.outbound.clone()
+    }
+
+    /// Record whether the bot moderates a channel
+    ///
+    /// All channels share one outbound limiter, so the higher moderator
+    /// rate is only used while the bot moderates every joined channel.
+    ///
+    /// # Arguments
+    /// * `channel` - The channel the status applies to
+    /// * `is_moderator` - Whether the bot is a moderator (or the broadcaster)
+    pub fn set_moderator(&self, channel: &str, is_moderator: bool) {
+        self.channels
+            .set_moderator(&normalize_channel(channel), is_moderator);
+        self.outbound.set_moderator(self.channels.moderates_all());
+    }
+
+    /// Get the channels the bot is in
+    ///
+    /// # Returns
+    /// The tracked channel set
+    pub fn get_channels(&self) -> Arc<JoinedChannels> {
+        self.channels.clone()
+    }
+
+    /// Get the tracker for confirming that sent messages reached chat
+    ///
+    /// # Returns
+    /// The delivery tracker
+    pub fn get_delivery_tracker(&self) -> Arc<DeliveryTracker> {
+        self.delivery.clone()
+    }
+
     /// Get the Helix API client for direct access to Twitch API functions
     ///
     /// # Returns
     /// The Helix client
-    #[allow(dead_code)]
     pub fn get_helix_client(&self) -> Arc<Mutex<HelixChatClient>> {
         self.helix.clone()
     }
 }
 
+/// Classify an error from the IRC library
+fn irc_error(message: String) -> TwitchError {
+    if message.contains("authentication") {
+        TwitchError::AuthExpired
+    } else {
+        TwitchError::Irc(message)
+    }
+}
+
+/// Format a channel name the way the IRC library expects: lowercase, without a # prefix
+fn normalize_channel(channel: &str) -> String {
+    channel.trim_start_matches('#').to_lowercase()
+}
+
 #[cfg(test)]
 mod tests {
     // Note: Testing actual Twitch connection would require mocking the Twitch API
-    // These tests will be added later
+    use super::*;
+
+    #[test]
+    fn test_split_message() {
+        assert_eq!(split_message("short message", 500), vec!["short message"]);
+        assert_eq!(
+            split_message("one two three four", 9),
+            vec!["one two", "three", "four"]
+        );
+        // Words longer than a message are split mid-word
+        assert_eq!(split_message("ab abcdefgh", 4), vec!["ab", "abcd", "efgh"]);
+
+        let long = "word ".repeat(200);
+        let messages = split_message(&long, MAX_MESSAGE_LENGTH);
+        assert_eq!(messages.len(), 2);
+        assert!(
+            messages
+                .iter()
+                .all(|m| m.chars().count() <= MAX_MESSAGE_LENGTH)
+        );
+        assert_eq!(messages.join(" "), long.trim_end());
+    }
 }
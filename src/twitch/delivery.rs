@@ -0,0 +1,232 @@
+//! Confirmation that sent chat messages actually reached chat
+//!
+//! Twitch can accept a message and then silently drop it. When confirmation
+//! is enabled, each send is remembered until Twitch acknowledges it: messages
+//! sent through Helix echo back as the bot's own PRIVMSG, and messages sent
+//! over IRC are answered with a USERSTATE for the channel. Twitch also sends
+//! a USERSTATE when the bot joins a channel, which acknowledges nothing.
+//! Sends that are never acknowledged are counted as unconfirmed.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How long to wait for a send to be acknowledged before counting it as unconfirmed
+pub const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A sent message waiting to be acknowledged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingSend {
+    /// The channel the message was sent to
+    pub channel: String,
+    /// The message text
+    pub text: String,
+    /// Whether the message was sent over IRC, so a USERSTATE acknowledges it
+    over_irc: bool,
+    /// When the message was sent
+    sent_at: Instant,
+}
+
+/// Tracks sent messages until Twitch acknowledges them
+#[derive(Debug, Default)]
+pub struct DeliveryTracker {
+    /// Whether sends are being tracked
+    enabled: AtomicBool,
+    /// Sends waiting for an acknowledgement, oldest first
+    pending: Mutex<VecDeque<PendingSend>>,
+    /// Channels just joined, whose next USERSTATE answers the JOIN
+    joining: Mutex<HashSet<String>>,
+    /// Sends that were acknowledged
+    confirmed: AtomicU64,
+    /// Sends that were never acknowledged
+    unconfirmed: AtomicU64,
+}
+
+impl DeliveryTracker {
+    /// Create a tracker with confirmation turned off
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Turn delivery confirmation on or off
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether to track sends
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.pending.lock().unwrap().clear();
+        }
+    }
+
+    /// Remember a message that was just sent
+    ///
+    /// # Arguments
+    /// * `channel` - The channel the message was sent to
+    /// * `text` - The message text
+    /// * `over_irc` - Whether it was sent over IRC rather than through Helix
+    pub fn record(&self, channel: &str, text: &str, over_irc: bool) {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+
+        self.pending.lock().unwrap().push_back(PendingSend {
+            channel: channel.to_string(),
+            text: text.trim().to_string(),
+            over_irc,
+            sent_at: Instant::now(),
+        });
+    }
+
+    /// Confirm a send from the bot's own message echoing back
+    ///
+    /// # Arguments
+    /// * `channel` - The channel the echo arrived in
+    /// * `text` - The echoed message text
+    ///
+    /// # Returns
+    /// Whether a pending send matched the echo
+    pub fn confirm_echo(&self, channel: &str, text: &str) -> bool {
+        let text = text.trim();
+        self.confirm_first(|send| send.channel == channel && send.text == text)
+    }
+
+    /// Note that the bot joined a channel, so the USERSTATE answering the
+    /// JOIN isn't taken as a send being acknowledged
+    ///
+    /// # Arguments
+    /// * `channel` - The channel the bot joined
+    pub fn record_join(&self, channel: &str) {
+        self.joining.lock().unwrap().insert(channel.to_string());
+    }
+
+    /// Confirm the oldest IRC send in a channel, when Twitch acknowledges it with a USERSTATE
+    ///
+    /// # Arguments
+    /// * `channel` - The channel the acknowledgement arrived in
+    ///
+    /// # Returns
+    /// Whether a pending send was confirmed
+    pub fn confirm_next(&self, channel: &str) -> bool {
+        if self.joining.lock().unwrap().remove(channel) {
+            return false;
+        }
+        self.confirm_first(|send| send.channel == channel && send.over_irc)
+    }
+
+    fn confirm_first(&self, matches: impl Fn(&PendingSend) -> bool) -> bool {
+        let mut pending = self.pending.lock().unwrap();
+        let Some(index) = pending.iter().position(matches) else {
+            return false;
+        };
+
+        pending.remove(index);
+        self.confirmed.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Give up on sends that have waited longer than the timeout
+    ///
+    /// # Returns
+    /// The sends that were never acknowledged
+    pub fn expire(&self) -> Vec<PendingSend> {
+        let now = Instant::now();
+        let mut pending = self.pending.lock().unwrap();
+        let mut expired = Vec::new();
+
+        while let Some(send) = pending.front() {
+            if now.duration_since(send.sent_at) < DELIVERY_TIMEOUT {
+                break;
+            }
+            expired.extend(pending.pop_front());
+        }
+
+        self.unconfirmed
+            .fetch_add(expired.len() as u64, Ordering::Relaxed);
+        expired
+    }
+
+    /// Get the number of sends still waiting for an acknowledgement
+    pub fn pending_count(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// Get the number of sends that were acknowledged
+    pub fn confirmed_count(&self) -> u64 {
+        self.confirmed.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of sends that were never acknowledged
+    pub fn unconfirmed_count(&self) -> u64 {
+        self.unconfirmed.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_echo_confirms_send() {
+        let tracker = DeliveryTracker::new();
+        tracker.set_enabled(true);
+        tracker.record("test_channel", "hello chat", false);
+        tracker.record("test_channel", "second message", false);
+        assert_eq!(tracker.pending_count(), 2);
+
+        assert!(!tracker.confirm_echo("other_channel", "hello chat"));
+        assert!(tracker.confirm_echo("test_channel", "hello chat "));
+        assert_eq!(tracker.confirmed_count(), 1);
+
+        // The unmatched send is counted once the timeout passes
+        tokio::time::advance(DELIVERY_TIMEOUT).await;
+        let expired = tracker.expire();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].text, "second message");
+        assert_eq!(tracker.unconfirmed_count(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_userstate_confirms_oldest_send() {
+        let tracker = DeliveryTracker::new();
+        tracker.set_enabled(true);
+        tracker.record("test_channel", "first", true);
+        tracker.record("test_channel", "second", true);
+
+        assert!(tracker.confirm_next("test_channel"));
+        assert!(!tracker.confirm_echo("test_channel", "first"));
+        assert!(tracker.confirm_echo("test_channel", "second"));
+        assert!(!tracker.confirm_next("test_channel"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_join_userstate_leaves_send_pending() {
+        let tracker = DeliveryTracker::new();
+        tracker.set_enabled(true);
+        tracker.record_join("test_channel");
+        tracker.record("test_channel", "hello", true);
+
+        // The USERSTATE answering the JOIN doesn't acknowledge the send
+        assert!(!tracker.confirm_next("test_channel"));
+        assert_eq!(tracker.pending_count(), 1);
+        assert!(tracker.confirm_next("test_channel"));
+        assert_eq!(tracker.confirmed_count(), 1);
+
+        // A Helix send waits for its echo, not a USERSTATE
+        tracker.record("test_channel", "via helix", false);
+        assert!(!tracker.confirm_next("test_channel"));
+        assert!(tracker.confirm_echo("test_channel", "via helix"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_disabled_tracker_ignores_sends() {
+        let tracker = DeliveryTracker::new();
+        tracker.record("test_channel", "hello", true);
+
+        tokio::time::advance(DELIVERY_TIMEOUT).await;
+        assert!(tracker.expire().is_empty());
+        assert!(!tracker.confirm_echo("test_channel", "hello"));
+    }
+}
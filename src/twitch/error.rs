@@ -0,0 +1,259 @@
+//! Errors returned by the Twitch chat and Helix clients
+//!
+//! These let callers tell an expired token from a network failure or a
+//! message Twitch refused to deliver. Elsewhere in the bot they convert into
+//! `anyhow::Error` like any other error.
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Result type for Twitch client operations
+pub type Result<T> = std::result::Result<T, TwitchError>;
+
+/// Something that went wrong talking to Twitch
+#[derive(Debug, Error)]
+pub enum TwitchError {
+    /// Twitch rejected the OAuth token
+    #[error(
+        "The bot's token has expired or been revoked. Re-authenticate with `som_chatbot auth --force`."
+    )]
+    AuthExpired,
+
+    /// The OAuth token lacks a scope the request needs
+    #[error(
+        "The bot's token is missing the {0} scope. Re-authenticate with `som_chatbot auth --force`."
+    )]
+    MissingScope(String),
+
+    /// No access token could be obtained or refreshed
+    #[error("Couldn't get an access token: {0}")]
+    Token(anyhow::Error),
+
+    /// Twitch is rate limiting the bot
+    #[error("Rate limited by Twitch")]
+    RateLimited,
+
+    /// Twitch accepted the request but didn't deliver the chat message
+    #[error("Message not sent: {code} - {reason}")]
+    MessageDropped {
+        /// Twitch's code for why the message was dropped
+        code: String,
+        /// A human-readable explanation
+        reason: String,
+    },
+
+    /// Twitch only lets accounts with a verified phone number send whispers
+    #[error(
+        "Twitch only lets accounts with a verified phone number send whispers. Add a phone number to the bot's account under Settings > Security and Privacy."
+    )]
+    PhoneNotVerified,
+
+    /// A user or channel doesn't exist
+    #[error("{0}")]
+    NotFound(String),
+
+    /// Helix returned an unexpected error
+    #[error("{context}: {message}")]
+    Api {
+        /// What the bot was trying to do
+        context: String,
+        /// HTTP status code of the response
+        status: u16,
+        /// Twitch's short name for the status, e.g. "Bad Request"
+        error: String,
+        /// Twitch's explanation, or the raw body if it wasn't the usual JSON
+        message: String,
+    },
+
+    /// The IRC connection failed
+    #[error("Chat connection error: {0}")]
+    Irc(String),
+
+    /// The HTTP request failed
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+}
+
+impl TwitchError {
+    /// Say what the bot was trying to do when an API error happened
+    ///
+    /// Only `Api` errors carry a context; other errors are returned as they are.
+    ///
+    /// # Arguments
+    /// * `context` - What the request was trying to do, e.g. "Failed to ban user"
+    ///
+    /// # Returns
+    /// The error with the context set
+    pub fn with_context(self, context: &str) -> Self {
+        match self {
+            TwitchError::Api {
+                status,
+                error,
+                message,
+                ..
+            } => TwitchError::Api {
+                context: context.to_string(),
+                status,
+                error,
+                message,
+            },
+            other => other,
+        }
+    }
+}
+
+/// The error body Twitch returns from Helix and its OAuth endpoints
+///
+/// Helix always includes `error`; the OAuth endpoints leave it out.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ErrorResponse {
+    /// Twitch's short name for the status, e.g. "Unauthorized"
+    #[serde(default)]
+    pub error: Option<String>,
+    /// HTTP status code
+    pub status: u16,
+    /// Error message
+    pub message: String,
+}
+
+impl ErrorResponse {
+    /// Parse an error body, if it has the usual shape
+    ///
+    /// # Arguments
+    /// * `body` - The response body
+    ///
+    /// # Returns
+    /// The parsed body, or None if it isn't Twitch's error JSON
+    pub fn parse(body: &str) -> Option<Self> {
+        serde_json::from_str(body).ok()
+    }
+}
+
+/// Get the message from an error body, falling back to the raw body
+///
+/// # Arguments
+/// * `body` - The response body
+///
+/// # Returns
+/// Twitch's message, or the trimmed body if it isn't Twitch's error JSON
+pub fn error_message(body: &str) -> String {
+    match ErrorResponse::parse(body) {
+        Some(error) if !error.message.is_empty() => error.message,
+        _ => body.trim().to_string(),
+    }
+}
+
+/// Turn an unsuccessful Helix response into a typed error
+///
+/// A 401 naming a missing scope becomes `MissingScope` and any other 401
+/// `AuthExpired`; a 429 becomes `RateLimited`. Everything else becomes `Api`
+/// with Twitch's message, to be given a context with `with_context`.
+///
+/// # Arguments
+/// * `status` - The response's status code
+/// * `body` - The response body
+///
+/// # Returns
+/// The error describing the response
+pub fn parse_helix_error(status: StatusCode, body: &str) -> TwitchError {
+    let reason = status.canonical_reason().unwrap_or("Unknown").to_string();
+    let (error, message) = match ErrorResponse::parse(body) {
+        Some(response) => (response.error.unwrap_or(reason), error_message(body)),
+        None => (reason, body.trim().to_string()),
+    };
+
+    match status {
+        StatusCode::UNAUTHORIZED => match message.strip_prefix("Missing scope:") {
+            Some(scope) => TwitchError::MissingScope(scope.trim().to_string()),
+            None => TwitchError::AuthExpired,
+        },
+        StatusCode::TOO_MANY_REQUESTS => TwitchError::RateLimited,
+        _ => TwitchError::Api {
+            context: "Twitch API request failed".to_string(),
+            status: status.as_u16(),
+            error,
+            message,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_helix_error() {
+        let error = parse_helix_error(
+            StatusCode::UNAUTHORIZED,
+            r#"{"error":"Unauthorized","status":401,"message":"Missing scope: moderator:manage:banned_users"}"#,
+        );
+        assert!(
+            matches!(error, TwitchError::MissingScope(scope) if scope == "moderator:manage:banned_users")
+        );
+
+        let error = parse_helix_error(
+            StatusCode::UNAUTHORIZED,
+            r#"{"error":"Unauthorized","status":401,"message":"Invalid OAuth token"}"#,
+        );
+        assert!(matches!(error, TwitchError::AuthExpired));
+
+        let error = parse_helix_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            r#"{"error":"Too Many Requests","status":429,"message":""}"#,
+        );
+        assert!(matches!(error, TwitchError::RateLimited));
+
+        let error = parse_helix_error(
+            StatusCode::BAD_REQUEST,
+            r#"{"error":"Bad Request","status":400,"message":"The user specified in the user_id field may not be banned."}"#,
+        )
+        .with_context("Failed to ban user");
+        assert_eq!(
+            error.to_string(),
+            "Failed to ban user: The user specified in the user_id field may not be banned."
+        );
+        assert!(matches!(
+            error,
+            TwitchError::Api { status: 400, ref error, .. } if error == "Bad Request"
+        ));
+    }
+
+    #[test]
+    fn test_parse_helix_error_unusual_bodies() {
+        // An empty message falls back to the raw body
+        let error = parse_helix_error(
+            StatusCode::NOT_FOUND,
+            r#"{"error":"Not Found","status":404,"message":""}"#,
+        );
+        assert!(matches!(
+            error,
+            TwitchError::Api { status: 404, ref error, ref message, .. }
+                if error == "Not Found" && message.contains("\"status\":404")
+        ));
+
+        // Gateway errors don't come from Helix and aren't JSON
+        let error = parse_helix_error(
+            StatusCode::BAD_GATEWAY,
+            "<html><body>502 Bad Gateway</body></html>\n",
+        );
+        assert!(matches!(
+            error,
+            TwitchError::Api { status: 502, ref error, ref message, .. }
+                if error == "Bad Gateway" && message == "<html><body>502 Bad Gateway</body></html>"
+        ));
+
+        // The OAuth endpoints leave out "error"
+        let body = r#"{"status":400,"message":"authorization_pending"}"#;
+        assert_eq!(
+            ErrorResponse::parse(body),
+            Some(ErrorResponse {
+                error: None,
+                status: 400,
+                message: "authorization_pending".to_string(),
+            })
+        );
+        assert_eq!(error_message(body), "authorization_pending");
+        assert_eq!(error_message("Service Unavailable"), "Service Unavailable");
+    }
+}
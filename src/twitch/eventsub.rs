@@ -0,0 +1,689 @@
+//! EventSub over WebSocket, for channel events IRC may miss
+//!
+//! IRC USERNOTICEs for subscriptions and raids are sometimes lost, and channel
+//! point redemptions, including "Highlight My Message", don't come over IRC at all. EventSub delivers them
+//! reliably: the bot opens a WebSocket session, Twitch sends a welcome with
+//! the session ID, and the bot then has a few seconds to subscribe to event
+//! types through Helix. After that the session carries keepalives and
+//! notifications. If nothing arrives within the keepalive timeout the
+//! connection is presumed dead and a new session is started. When Twitch asks
+//! the bot to move to another server, the old connection keeps being read
+//! until the new one is welcomed, and the subscriptions carry over.
+
+use anyhow::{Result, anyhow};
+use futures::future::BoxFuture;
+use futures::{FutureExt, StreamExt};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::{Mutex, mpsc};
+use tokio::time::timeout;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async};
+use tracing::{debug, info, warn};
+
+use crate::twitch::{Backoff, HelixChatClient};
+
+/// Where EventSub WebSocket sessions start
+const EVENTSUB_URL: &str = "wss://eventsub.wss.twitch.tv/ws";
+
+/// The event types the bot subscribes to: type, version, condition field, and needed scope
+const SUBSCRIPTIONS: &[(&str, &str, &str, Option<&str>)] = &[
+    (
+        "channel.subscribe",
+        "1",
+        "broadcaster_user_id",
+        Some("channel:read:subscriptions"),
+    ),
+    ("channel.raid", "1", "to_broadcaster_user_id", None),
+    (
+        "channel.channel_points_custom_reward_redemption.add",
+        "1",
+        "broadcaster_user_id",
+        Some("channel:manage:redemptions"),
+    ),
+    (
+        "channel.channel_points_automatic_reward_redemption.add",
+        "2",
+        "broadcaster_user_id",
+        Some("channel:manage:redemptions"),
+    ),
+];
+
+/// The built-in reward type of "Highlight My Message"
+const HIGHLIGHT_REWARD: &str = "send_highlighted_message";
+
+/// How long to wait for the welcome message after connecting
+const WELCOME_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Extra time allowed past the keepalive timeout before giving up on a connection
+const KEEPALIVE_GRACE: Duration = Duration::from_secs(5);
+
+/// How many notification IDs to remember, so redelivered ones are dropped
+const SEEN_MESSAGES: usize = 100;
+
+/// An event delivered over EventSub
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventSubEvent {
+    /// A new subscription (not a resubscription)
+    Subscribe {
+        /// Display name of the subscriber
+        user_name: String,
+        /// Subscription tier ("1000", "2000", or "3000")
+        tier: String,
+        /// Whether the subscription was gifted
+        is_gift: bool,
+    },
+    /// An incoming raid
+    Raid {
+        /// Login of the raiding broadcaster
+        from_login: String,
+        /// Display name of the raiding broadcaster
+        from_name: String,
+        /// Number of viewers brought along
+        viewers: u64,
+    },
+    /// A custom channel point reward was redeemed
+    Redemption(Redemption),
+    /// A message was highlighted with the built-in "Highlight My Message" reward
+    Highlight {
+        /// ID of the viewer who redeemed it
+        user_id: String,
+        /// The highlighted text
+        text: String,
+    },
+}
+
+/// A redemption of a custom channel point reward
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Redemption {
+    /// The redemption's ID
+    pub id: String,
+    /// ID of the channel the reward belongs to
+    #[serde(rename = "broadcaster_user_id")]
+    pub broadcaster_id: String,
+    /// ID of the viewer who redeemed it
+    pub user_id: String,
+    /// Login of the viewer who redeemed it
+    pub user_login: String,
+    /// Display name of the viewer who redeemed it
+    pub user_name: String,
+    /// Text the viewer entered, if the reward asks for any
+    #[serde(default)]
+    pub user_input: String,
+    /// "unfulfilled" while waiting in the request queue, or "fulfilled" if the reward skips it
+    pub status: String,
+    /// The reward that was redeemed
+    pub reward: Reward,
+}
+
+/// A custom channel point reward
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Reward {
+    /// The reward's ID
+    pub id: String,
+    /// The reward's title
+    pub title: String,
+}
+
+/// A message received on an EventSub WebSocket
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Frame {
+    /// The session is ready for subscriptions
+    Welcome {
+        session_id: String,
+        keepalive: Duration,
+    },
+    /// The connection is still alive
+    Keepalive,
+    /// An event for a subscription (None for types the bot doesn't understand)
+    Notification {
+        message_id: String,
+        event: Option<EventSubEvent>,
+    },
+    /// Twitch wants the session moved to another server
+    Reconnect { url: String },
+    /// Twitch ended a subscription, e.g. because the token lost a scope
+    Revocation { kind: String, status: String },
+}
+
+#[derive(Debug, Deserialize)]
+struct RawFrame {
+    metadata: Metadata,
+    #[serde(default)]
+    payload: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    message_id: String,
+    message_type: String,
+    #[serde(default)]
+    subscription_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionPayload {
+    session: Session,
+}
+
+#[derive(Debug, Deserialize)]
+struct Session {
+    id: String,
+    #[serde(default)]
+    keepalive_timeout_seconds: Option<u64>,
+    #[serde(default)]
+    reconnect_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotificationPayload {
+    event: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct RevocationPayload {
+    subscription: RevokedSubscription,
+}
+
+#[derive(Debug, Deserialize)]
+struct RevokedSubscription {
+    #[serde(rename = "type")]
+    kind: String,
+    status: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubscribeEvent {
+    user_name: String,
+    tier: String,
+    is_gift: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct AutomaticRedemptionEvent {
+    user_id: String,
+    reward: AutomaticReward,
+    message: Option<RedemptionMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AutomaticReward {
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedemptionMessage {
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RaidEvent {
+    from_broadcaster_user_login: String,
+    from_broadcaster_user_name: String,
+    viewers: u64,
+}
+
+/// Parse a message received on an EventSub WebSocket
+///
+/// # Arguments
+/// * `text` - The JSON text of the message
+///
+/// # Returns
+/// The parsed message
+fn parse_frame(text: &str) -> Result<Frame> {
+    let frame: RawFrame = serde_json::from_str(text)?;
+    let frame = match frame.metadata.message_type.as_str() {
+        "session_welcome" => {
+            let session = serde_json::from_value::<SessionPayload>(frame.payload)?.session;
+            Frame::Welcome {
+                session_id: session.id,
+                keepalive: Duration::from_secs(session.keepalive_timeout_seconds.unwrap_or(10)),
+            }
+        }
+        "session_keepalive" => Frame::Keepalive,
+        "notification" => {
+            let payload: NotificationPayload = serde_json::from_value(frame.payload)?;
+            let kind = frame.metadata.subscription_type.unwrap_or_default();
+            Frame::Notification {
+                message_id: frame.metadata.message_id,
+                event: parse_event(&kind, payload.event)?,
+            }
+        }
+        "session_reconnect" => {
+            let session = serde_json::from_value::<SessionPayload>(frame.payload)?.session;
+            Frame::Reconnect {
+                url: session
+                    .reconnect_url
+                    .ok_or_else(|| anyhow!("Reconnect message without a URL"))?,
+            }
+        }
+        "revocation" => {
+            let revoked = serde_json::from_value::<RevocationPayload>(frame.payload)?.subscription;
+            Frame::Revocation {
+                kind: revoked.kind,
+                status: revoked.status,
+            }
+        }
+        other => return Err(anyhow!("Unknown EventSub message type {}", other)),
+    };
+    Ok(frame)
+}
+
+/// Parse the event of a notification
+fn parse_event(kind: &str, event: serde_json::Value) -> Result<Option<EventSubEvent>> {
+    let event = match kind {
+        "channel.subscribe" => {
+            let event: SubscribeEvent = serde_json::from_value(event)?;
+            EventSubEvent::Subscribe {
+                user_name: event.user_name,
+                tier: event.tier,
+                is_gift: event.is_gift,
+            }
+        }
+        "channel.raid" => {
+            let event: RaidEvent = serde_json::from_value(event)?;
+            EventSubEvent::Raid {
+                from_login: event.from_broadcaster_user_login,
+                from_name: event.from_broadcaster_user_name,
+                viewers: event.viewers,
+            }
+        }
+        "channel.channel_points_custom_reward_redemption.add" => {
+            EventSubEvent::Redemption(serde_json::from_value(event)?)
+        }
+        // Only highlights are wanted among the built-in rewards
+        "channel.channel_points_automatic_reward_redemption.add" => {
+            let event: AutomaticRedemptionEvent = serde_json::from_value(event)?;
+            match (event.reward.kind.as_str(), event.message) {
+                (HIGHLIGHT_REWARD, Some(message)) => EventSubEvent::Highlight {
+                    user_id: event.user_id,
+                    text: message.text,
+                },
+                _ => return Ok(None),
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(event))
+}
+
+type Connection = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Receives channel events from EventSub over a WebSocket
+pub struct EventSubClient {
+    /// The Helix client used to create subscriptions, sharing the bot's OAuth token
+    helix: Arc<Mutex<HelixChatClient>>,
+    /// The channel whose events are wanted
+    channel: String,
+    /// Where sessions start
+    url: String,
+}
+
+impl EventSubClient {
+    /// Create an EventSub client for a channel
+    ///
+    /// # Arguments
+    /// * `helix` - The Helix client used to create subscriptions
+    /// * `channel` - The channel whose events are wanted
+    ///
+    /// # Returns
+    /// A new EventSubClient instance
+    pub fn new(helix: Arc<Mutex<HelixChatClient>>, channel: &str) -> Self {
+        Self::with_url(helix, channel, EVENTSUB_URL)
+    }
+
+    /// Create an EventSub client that starts sessions at a custom URL
+    ///
+    /// # Arguments
+    /// * `helix` - The Helix client used to create subscriptions
+    /// * `channel` - The channel whose events are wanted
+    /// * `url` - Where sessions start (e.g. a local server in tests)
+    ///
+    /// # Returns
+    /// A new EventSubClient instance
+    pub fn with_url(helix: Arc<Mutex<HelixChatClient>>, channel: &str, url: &str) -> Self {
+        EventSubClient {
+            helix,
+            channel: channel.to_string(),
+            url: url.to_string(),
+        }
+    }
+
+    /// Receive events until `events` is closed, starting new sessions as needed
+    ///
+    /// # Arguments
+    /// * `events` - Where received events are sent
+    pub async fn run(self, events: mpsc::UnboundedSender<EventSubEvent>) {
+        let mut backoff = Backoff::default();
+        let mut seen = VecDeque::new();
+        loop {
+            match self.session(&events, &mut seen, &mut backoff).await {
+                Ok(()) => return,
+                Err(e) => {
+                    let delay = backoff.next_delay();
+                    warn!("EventSub session ended, reconnecting in {:?}: {}", delay, e);
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    /// Start a session, subscribe, and pass on its events until it fails
+    ///
+    /// # Returns
+    /// Ok once `events` is closed, or the error that ended the session
+    async fn session(
+        &self,
+        events: &mpsc::UnboundedSender<EventSubEvent>,
+        seen: &mut VecDeque<String>,
+        backoff: &mut Backoff,
+    ) -> Result<()> {
+        let (mut connection, session_id, mut keepalive) = connect(&self.url).await?;
+        self.subscribe(&session_id).await?;
+        info!("EventSub session {} started", session_id);
+        backoff.reset();
+
+        // The connection being opened when Twitch asks the session to move
+        let mut moving: Option<BoxFuture<'static, Result<(Connection, String, Duration)>>> = None;
+        loop {
+            let received = tokio::select! {
+                connected = async {
+                    match moving.as_mut() {
+                        Some(connecting) => connecting.await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    // The old connection kept delivering until now
+                    let (new_connection, _, new_keepalive) = connected?;
+                    info!("EventSub session moved");
+                    connection = new_connection;
+                    keepalive = new_keepalive;
+                    moving = None;
+                    continue;
+                }
+                received = timeout(keepalive + KEEPALIVE_GRACE, connection.next()) => received,
+            };
+            let message = match received {
+                Ok(Some(message)) => message?,
+                Ok(None) => return Err(anyhow!("Connection closed")),
+                Err(_) => return Err(anyhow!("No keepalive within {:?}", keepalive)),
+            };
+            let text = match message {
+                Message::Text(text) => text,
+                Message::Close(frame) => return Err(anyhow!("Connection closed: {:?}", frame)),
+                _ => continue,
+            };
+
+            match parse_frame(text.as_str()) {
+                Ok(Frame::Keepalive) | Ok(Frame::Welcome { .. }) => {}
+                Ok(Frame::Notification { message_id, event }) => {
+                    // Twitch may deliver a notification more than once
+                    if seen.contains(&message_id) {
+                        debug!("Dropping redelivered EventSub message {}", message_id);
+                        continue;
+                    }
+                    if seen.len() == SEEN_MESSAGES {
+                        seen.pop_front();
+                    }
+                    seen.push_back(message_id);
+
+                    if let Some(event) = event
+                        && events.send(event).is_err()
+                    {
+                        return Ok(());
+                    }
+                }
+                Ok(Frame::Reconnect { url }) => {
+                    // Subscriptions move with the session; the old connection
+                    // keeps being read until the new one is welcomed
+                    info!("EventSub asked to reconnect");
+                    moving = Some(async move { connect(&url).await }.boxed());
+                }
+                Ok(Frame::Revocation { kind, status }) => {
+                    warn!(
+                        "Twitch revoked the {} EventSub subscription: {}",
+                        kind, status
+                    );
+                }
+                Err(e) => warn!("Couldn't read EventSub message: {}", e),
+            }
+        }
+    }
+
+    /// Subscribe a session to every event type the bot handles
+    ///
+    /// Types whose scope the token lacks are skipped with a warning.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session to subscribe
+    ///
+    /// # Returns
+    /// An error if no subscription could be made
+    async fn subscribe(&self, session_id: &str) -> Result<()> {
+        let mut helix = self.helix.lock().await;
+        let broadcaster_id = helix.get_broadcaster_id(&self.channel).await?;
+
+        let mut subscribed = 0;
+        for (kind, version, condition, scope) in SUBSCRIPTIONS {
+            match helix
+                .create_eventsub_subscription(
+                    kind,
+                    version,
+                    (condition, &broadcaster_id),
+                    session_id,
+                    *scope,
+                )
+                .await
+            {
+                Ok(()) => subscribed += 1,
+                Err(e) => warn!("Couldn't subscribe to {} events: {}", kind, e),
+            }
+        }
+
+        if subscribed == 0 {
+            return Err(anyhow!("No EventSub subscriptions could be made"));
+        }
+        Ok(())
+    }
+}
+
+/// Connect to an EventSub server and wait for its welcome
+///
+/// # Arguments
+/// * `url` - The server to connect to
+///
+/// # Returns
+/// The connection, the session ID, and the keepalive timeout
+async fn connect(url: &str) -> Result<(Connection, String, Duration)> {
+    let (mut connection, _) = connect_async(url).await?;
+
+    loop {
+        let message = timeout(WELCOME_TIMEOUT, connection.next())
+            .await
+            .map_err(|_| anyhow!("No EventSub welcome within {:?}", WELCOME_TIMEOUT))?
+            .ok_or_else(|| anyhow!("Connection closed before the EventSub welcome"))??;
+
+        if let Message::Text(text) = message
+            && let Frame::Welcome {
+                session_id,
+                keepalive,
+            } = parse_frame(text.as_str())?
+        {
+            return Ok((connection, session_id, keepalive));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::create_authenticated_oauth_manager;
+    use futures::SinkExt;
+    use mockito::{Matcher, Server};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    fn frame(message_id: &str, message_type: &str, extra: &str, payload: &str) -> Message {
+        Message::text(format!(
+            r#"{{"metadata":{{"message_id":"{}","message_type":"{}","message_timestamp":"2023-07-19T14:56:51Z"{}}},"payload":{}}}"#,
+            message_id, message_type, extra, payload
+        ))
+    }
+
+    fn welcome(session_id: &str) -> Message {
+        frame(
+            "w",
+            "session_welcome",
+            "",
+            &format!(
+                r#"{{"session":{{"id":"{}","status":"connected","keepalive_timeout_seconds":10,"reconnect_url":null}}}}"#,
+                session_id
+            ),
+        )
+    }
+
+    fn raid(message_id: &str, from: &str) -> Message {
+        frame(
+            message_id,
+            "notification",
+            r#","subscription_type":"channel.raid","subscription_version":"1""#,
+            &format!(
+                r#"{{"subscription":{{"type":"channel.raid"}},"event":{{"from_broadcaster_user_id":"1","from_broadcaster_user_login":"{}","from_broadcaster_user_name":"Raider","to_broadcaster_user_id":"456","viewers":9}}}}"#,
+                from
+            ),
+        )
+    }
+
+    #[test]
+    fn test_parse_highlight_redemption() -> Result<()> {
+        let redemption = |reward_type: &str| {
+            serde_json::json!({
+                "broadcaster_user_id": "456",
+                "user_id": "123",
+                "user_login": "viewer",
+                "user_name": "Viewer",
+                "id": "r1",
+                "reward": {"type": reward_type, "channel_points": 300, "emote": null},
+                "message": {"text": "look at me!", "fragments": []},
+                "redeemed_at": "2024-08-14T15:20:00Z"
+            })
+        };
+        let kind = "channel.channel_points_automatic_reward_redemption.add";
+
+        assert_eq!(
+            parse_event(kind, redemption("send_highlighted_message"))?,
+            Some(EventSubEvent::Highlight {
+                user_id: "123".to_string(),
+                text: "look at me!".to_string(),
+            })
+        );
+        // Other built-in rewards are ignored
+        assert_eq!(
+            parse_event(kind, redemption("single_message_bypass_sub_mode"))?,
+            None
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_session_subscribes_delivers_and_moves() -> Result<()> {
+        let mut helix_server = Server::new_async().await;
+        let _broadcaster = helix_server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "test_channel".into()))
+            .with_status(200)
+            .with_body(
+                r#"{"data":[{"id":"456","login":"test_channel","display_name":"Test_Channel"}]}"#,
+            )
+            .create_async()
+            .await;
+        let raid_subscription = helix_server
+            .mock("POST", "/eventsub/subscriptions")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "type": "channel.raid",
+                "condition": {"to_broadcaster_user_id": "456"},
+                "transport": {"method": "websocket", "session_id": "first"}
+            })))
+            .with_status(202)
+            .expect(1)
+            .create_async()
+            .await;
+        // The bot isn't the broadcaster, so the other types are refused
+        let _refused = helix_server
+            .mock("POST", "/eventsub/subscriptions")
+            .with_status(403)
+            .with_body(r#"{"error":"Forbidden","status":403,"message":"subscription missing proper authorization"}"#)
+            .create_async()
+            .await;
+
+        let first = TcpListener::bind("127.0.0.1:0").await?;
+        let first_url = format!("ws://{}", first.local_addr()?);
+        let second = TcpListener::bind("127.0.0.1:0").await?;
+        let second_url = format!("ws://{}", second.local_addr()?);
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel::<()>();
+        let (moved_tx, moved_rx) = tokio::sync::oneshot::channel::<()>();
+
+        tokio::spawn(async move {
+            let mut ws = accept_async(first.accept().await.unwrap().0).await.unwrap();
+            ws.send(welcome("first")).await.unwrap();
+            // Give the client time to subscribe before events flow
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            ws.send(raid("1", "raider")).await.unwrap();
+            ws.send(raid("1", "raider")).await.unwrap();
+            ws.send(frame(
+                "r",
+                "session_reconnect",
+                "",
+                &format!(
+                    r#"{{"session":{{"id":"first","status":"reconnecting","keepalive_timeout_seconds":null,"reconnect_url":"{}"}}}}"#,
+                    second_url
+                ),
+            ))
+            .await
+            .unwrap();
+            // Events keep coming on the old connection until the new one is welcomed
+            ws.send(raid("3", "late_raider")).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let _ = moved_tx.send(());
+            let _ = done_rx.await;
+        });
+        tokio::spawn(async move {
+            let mut ws = accept_async(second.accept().await.unwrap().0)
+                .await
+                .unwrap();
+            let _ = moved_rx.await;
+            ws.send(welcome("second")).await.unwrap();
+            ws.send(raid("2", "another_raider")).await.unwrap();
+            let _ = ws.next().await;
+        });
+
+        let helix = Arc::new(Mutex::new(
+            HelixChatClient::with_base_url(
+                create_authenticated_oauth_manager(),
+                &helix_server.url(),
+            )
+            .await?,
+        ));
+        let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+        tokio::spawn(EventSubClient::with_url(helix, "test_channel", &first_url).run(events_tx));
+
+        // The redelivered raid is dropped, and no events are lost in the move
+        let mut raiders = Vec::new();
+        for _ in 0..3 {
+            match timeout(Duration::from_secs(10), events_rx.recv()).await? {
+                Some(EventSubEvent::Raid { from_login, .. }) => raiders.push(from_login),
+                other => panic!("Unexpected event {:?}", other),
+            }
+        }
+        assert_eq!(raiders, vec!["raider", "late_raider", "another_raider"]);
+        let _ = done_tx.send(());
+
+        // The session kept its subscriptions when it moved
+        raid_subscription.assert_async().await;
+        Ok(())
+    }
+}
@@ -3,15 +3,97 @@
 //! This module provides a client for interacting with the Twitch Helix API,
 //! focusing on chat message operations like sending messages and replies.
 
-use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
 use reqwest::Client as HttpClient;
+use reqwest::Response;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
-use tracing::{error, info};
+use tracing::{debug, error, info, warn};
 
+use crate::twitch::error::{Result, TwitchError, parse_helix_error};
 use crate::twitch::oauth::OAuthManager;
+use crate::twitch::rate_limit::HelixRateLimit;
+
+/// Base URL of the Twitch Helix API
+const HELIX_BASE_URL: &str = "https://api.twitch.tv/helix";
+
+/// How long to wait before retrying a rate-limited request without a reset time
+const DEFAULT_RETRY_WAIT: Duration = Duration::from_secs(1);
+
+/// Scope needed to ban, time out, and unban users
+const BANNED_USERS_SCOPE: &str = "moderator:manage:banned_users";
+
+/// Scope needed to change chat modes like followers-only
+const CHAT_SETTINGS_SCOPE: &str = "moderator:manage:chat_settings";
+
+/// Scope needed to see who follows the channel
+const FOLLOWERS_SCOPE: &str = "moderator:read:followers";
+
+/// Scope needed to list the users in chat
+const CHATTERS_SCOPE: &str = "moderator:read:chatters";
+
+/// Scope needed to fulfill or cancel channel point redemptions
+const REDEMPTIONS_SCOPE: &str = "channel:manage:redemptions";
+
+/// Scope needed to send whispers
+const WHISPERS_SCOPE: &str = "user:manage:whispers";
+
+/// Scope needed to send Twitch announcements
+const ANNOUNCEMENTS_SCOPE: &str = "moderator:manage:announcements";
+
+/// Turn an unsuccessful response into an error describing the cause
+///
+/// # Arguments
+/// * `response` - The unsuccessful response
+/// * `context` - What the request was trying to do, e.g. "Failed to ban user"
+/// * `scope` - The scope the endpoint needs, if any
+///
+/// # Returns
+/// The error to return to the caller
+async fn error_from_response(
+    response: Response,
+    context: &str,
+    scope: Option<&str>,
+) -> TwitchError {
+    let status = response.status();
+    match response.text().await {
+        Ok(body) => error_from_body(status, body, context, scope),
+        Err(e) => e.into(),
+    }
+}
+
+/// Turn the status and body of an unsuccessful response into an error
+///
+/// # Arguments
+/// * `status` - The response's status code
+/// * `body` - The response body
+/// * `context` - What the request was trying to do, e.g. "Failed to ban user"
+/// * `scope` - The scope the endpoint needs, if any
+///
+/// # Returns
+/// The error to return to the caller
+fn error_from_body(
+    status: reqwest::StatusCode,
+    body: String,
+    context: &str,
+    scope: Option<&str>,
+) -> TwitchError {
+    error!("API error: {}", body);
+
+    match parse_helix_error(status, &body) {
+        // A scope complaint Twitch didn't name the scope in
+        TwitchError::AuthExpired if body.contains("scope") => match scope {
+            Some(scope) => TwitchError::MissingScope(scope.to_string()),
+            None => TwitchError::AuthExpired,
+        },
+        error => error.with_context(context),
+    }
+}
 
 /// Response from Twitch API when sending a message
 #[derive(Debug, Deserialize)]
@@ -42,19 +124,182 @@ struct SendMessageRequest {
     reply_parent_message_id: Option<String>,
 }
 
+/// Request body for the send announcement API
+#[derive(Debug, Serialize)]
+struct SendAnnouncementRequest<'a> {
+    message: &'a str,
+    color: AnnouncementColor,
+}
+
+/// The highlight color of a Twitch announcement
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnouncementColor {
+    /// The channel's accent color
+    #[default]
+    Primary,
+    Blue,
+    Green,
+    Orange,
+    Purple,
+}
+
+impl FromStr for AnnouncementColor {
+    type Err = anyhow::Error;
+
+    /// Parse one of the colors Twitch allows: blue, green, orange, purple, or primary
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "primary" => Ok(AnnouncementColor::Primary),
+            "blue" => Ok(AnnouncementColor::Blue),
+            "green" => Ok(AnnouncementColor::Green),
+            "orange" => Ok(AnnouncementColor::Orange),
+            "purple" => Ok(AnnouncementColor::Purple),
+            other => Err(anyhow::anyhow!(
+                "Unknown announcement color: {} (use blue, green, orange, purple, or primary)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AnnouncementColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AnnouncementColor::Primary => "primary",
+            AnnouncementColor::Blue => "blue",
+            AnnouncementColor::Green => "green",
+            AnnouncementColor::Orange => "orange",
+            AnnouncementColor::Purple => "purple",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Request body for the send whisper API
+#[derive(Debug, Serialize)]
+struct SendWhisperRequest<'a> {
+    message: &'a str,
+}
+
+/// Request body for updating a channel point redemption
+#[derive(Debug, Serialize)]
+struct UpdateRedemptionRequest<'a> {
+    status: &'a str,
+}
+
+/// Request body for creating an EventSub subscription
+#[derive(Debug, Serialize)]
+struct CreateSubscriptionRequest<'a> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    version: &'a str,
+    condition: HashMap<&'a str, &'a str>,
+    transport: WebSocketTransport<'a>,
+}
+
+/// Delivery of EventSub events over a WebSocket session
+#[derive(Debug, Serialize)]
+struct WebSocketTransport<'a> {
+    method: &'a str,
+    session_id: &'a str,
+}
+
 /// Twitch User data response
 #[derive(Debug, Deserialize)]
 struct UserResponse {
-    data: Vec<UserData>,
+    data: Vec<HelixUser>,
+}
+
+/// A Twitch user as returned by the Helix users endpoint
+#[derive(Debug, Clone, Deserialize)]
+pub struct HelixUser {
+    /// The user's ID
+    pub id: String,
+    /// The user's login name
+    pub login: String,
+    /// The user's display name
+    pub display_name: String,
+    /// When the account was created
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Response from the channel followers endpoint
+#[derive(Debug, Deserialize)]
+struct FollowersResponse {
+    data: Vec<HelixFollow>,
+}
+
+/// A follow as returned by the channel followers endpoint
+#[derive(Debug, Deserialize)]
+struct HelixFollow {
+    /// When the user followed the channel
+    followed_at: DateTime<Utc>,
+}
+
+/// Response from the chatters endpoint
+#[derive(Debug, Deserialize)]
+struct ChattersResponse {
+    data: Vec<HelixChatter>,
+    #[serde(default)]
+    pagination: Pagination,
+}
+
+/// A user in chat as returned by the chatters endpoint
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct HelixChatter {
+    /// The user's ID
+    pub user_id: String,
+    /// The user's login name
+    pub user_login: String,
+}
+
+/// The cursor for the next page of a paginated response
+#[derive(Debug, Default, Deserialize)]
+struct Pagination {
+    cursor: Option<String>,
 }
 
+/// Response from the streams endpoint
 #[derive(Debug, Deserialize)]
-struct UserData {
-    id: String,
-    #[allow(dead_code)]
-    login: String,
-    #[allow(dead_code)]
-    display_name: String,
+struct StreamResponse {
+    data: Vec<HelixStream>,
+}
+
+/// A live stream as returned by the Helix streams endpoint
+#[derive(Debug, Deserialize)]
+struct HelixStream {
+    /// When the stream went live
+    started_at: DateTime<Utc>,
+}
+
+/// Request body for the ban user API
+#[derive(Debug, Serialize)]
+struct BanUserRequest {
+    data: BanUserData,
+}
+
+#[derive(Debug, Serialize)]
+struct BanUserData {
+    user_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration: Option<u64>,
+    reason: String,
+}
+
+/// The chat modes that restrict who can talk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ChatSettings {
+    /// Whether only followers may chat
+    pub follower_mode: bool,
+    /// How many minutes someone must have followed for to chat in followers-only mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub follower_mode_duration: Option<u64>,
+    /// Whether only subscribers may chat
+    pub subscriber_mode: bool,
+    /// Whether messages may only contain emotes
+    pub emote_mode: bool,
 }
 
 /// Helix API-enabled Twitch client for chat operations
@@ -66,7 +311,13 @@ pub struct HelixChatClient {
     /// Bot's Twitch user ID
     bot_user_id: Option<String>,
     /// Channel cache to avoid repeated API lookups
-    channel_cache: std::collections::HashMap<String, String>,
+    channel_cache: HashMap<String, String>,
+    /// Base URL for Helix requests (overridable for tests)
+    base_url: String,
+    /// Rate-limit state from the most recent response
+    rate_limit: Option<HelixRateLimit>,
+    /// Latest rate-limit state for each endpoint
+    endpoint_rate_limits: HashMap<String, HelixRateLimit>,
 }
 
 impl HelixChatClient {
@@ -78,6 +329,21 @@ impl HelixChatClient {
     /// # Returns
     /// A new HelixChatClient instance
     pub async fn new(oauth_manager: Arc<Mutex<OAuthManager>>) -> Result<Self> {
+        Self::with_base_url(oauth_manager, HELIX_BASE_URL).await
+    }
+
+    /// Create a new Helix API client that sends requests to a custom base URL
+    ///
+    /// # Arguments
+    /// * `oauth_manager` - Manager for OAuth tokens
+    /// * `base_url` - Base URL of the Helix API (e.g. a mock server in tests)
+    ///
+    /// # Returns
+    /// A new HelixChatClient instance
+    pub async fn with_base_url(
+        oauth_manager: Arc<Mutex<OAuthManager>>,
+        base_url: &str,
+    ) -> Result<Self> {
         // Create HTTP client with reasonable timeout
         let http_client = HttpClient::builder()
             .timeout(Duration::from_secs(10))
@@ -87,105 +353,470 @@ impl HelixChatClient {
             http_client,
             oauth_manager,
             bot_user_id: None,
-            channel_cache: std::collections::HashMap::new(),
+            channel_cache: HashMap::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            rate_limit: None,
+            endpoint_rate_limits: HashMap::new(),
         })
     }
 
+    /// Build the full URL for a Helix endpoint
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// Remember the rate-limit state reported with a response
+    fn record_rate_limit(&mut self, response: &Response) {
+        let Some(rate_limit) = HelixRateLimit::from_headers(response.headers()) else {
+            return;
+        };
+
+        // Key by endpoint path, without the base URL or query string
+        let url = response.url().as_str();
+        let endpoint = url
+            .strip_prefix(self.base_url.as_str())
+            .unwrap_or(url)
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        debug!(
+            "Helix rate limit for {}: {}/{}",
+            endpoint, rate_limit.remaining, rate_limit.limit
+        );
+        self.endpoint_rate_limits
+            .insert(endpoint, rate_limit.clone());
+        self.rate_limit = Some(rate_limit);
+    }
+
+    /// Slow down before a request if the endpoint's bucket is nearly empty
+    ///
+    /// # Arguments
+    /// * `path` - The endpoint about to be called
+    async fn wait_for_rate_limit(&self, path: &str) {
+        let delay = self
+            .endpoint_rate_limits
+            .get(path)
+            .and_then(|rate_limit| rate_limit.delay_before_next(Utc::now()));
+
+        if let Some(delay) = delay {
+            warn!(
+                "Helix rate limit for {} nearly exhausted, waiting {:?}",
+                path, delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Get the latest rate-limit state reported for an endpoint
+    ///
+    /// # Arguments
+    /// * `path` - The endpoint path, e.g. "/users"
+    ///
+    /// # Returns
+    /// The endpoint's rate-limit state, or None if it hasn't been called
+    pub fn rate_limit_for(&self, path: &str) -> Option<HelixRateLimit> {
+        self.endpoint_rate_limits.get(path).cloned()
+    }
+
+    /// Get the rate-limit state reported with the most recent response
+    ///
+    /// # Returns
+    /// The latest rate-limit state, or None if no response has reported one
+    pub fn rate_limit(&self) -> Option<HelixRateLimit> {
+        self.rate_limit.clone()
+    }
+
+    /// Get a fresh access token and the client ID for an API request
+    async fn credentials(&self) -> Result<(String, String)> {
+        let mut manager = self.oauth_manager.lock().await;
+        let token = manager
+            .get_access_token()
+            .await
+            .map_err(TwitchError::Token)?;
+        Ok((token, manager.get_client_id().to_string()))
+    }
+
     /// Get the bot's user ID (cached or from API)
-    async fn get_bot_user_id(&mut self) -> Result<String> {
+    pub async fn get_bot_user_id(&mut self) -> Result<String> {
         // Return cached value if available
         if let Some(id) = &self.bot_user_id {
             return Ok(id.clone());
         }
 
-        // Get a fresh token
-        let token = {
-            let mut manager = self.oauth_manager.lock().await;
-            manager.get_access_token().await?
-        };
+        Ok(self.get_bot_user().await?.id)
+    }
 
-        // Get client ID for API request
-        let client_id = {
-            let manager = self.oauth_manager.lock().await;
-            manager.get_client_id().to_string()
-        };
+    /// Get the account the OAuth token belongs to
+    ///
+    /// # Returns
+    /// The bot's user, whose ID is cached for later calls
+    pub async fn get_bot_user(&mut self) -> Result<HelixUser> {
+        let (token, client_id) = self.credentials().await?;
 
         // Make the API call to get the bot's user ID
+        self.wait_for_rate_limit("/users").await;
         let response = self
             .http_client
-            .get("https://api.twitch.tv/helix/users")
+            .get(self.url("/users"))
             .header("Authorization", format!("Bearer {}", token))
             .header("Client-Id", client_id)
             .send()
             .await?;
+        self.record_rate_limit(&response);
 
         if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("Failed to get user ID: {}", error_text));
+            return Err(error_from_response(response, "Failed to get user ID", None).await);
         }
 
         // Parse the response
-        let users: UserResponse = response.json().await?;
+        let mut users: UserResponse = response.json().await?;
 
         if users.data.is_empty() {
-            return Err(anyhow!("No user data returned"));
+            return Err(TwitchError::NotFound("No user data returned".to_string()));
         }
 
-        // Cache and return the user ID
-        let user_id = users.data[0].id.clone();
-        self.bot_user_id = Some(user_id.clone());
+        // Cache the user ID and return the user
+        let user = users.data.swap_remove(0);
+        self.bot_user_id = Some(user.id.clone());
 
-        Ok(user_id)
+        Ok(user)
+    }
+
+    /// Check the configured bot username against the account the token belongs to
+    ///
+    /// Chat logins and command replies go wrong in confusing ways when the
+    /// two differ, so the caller should use the token's account instead.
+    ///
+    /// # Arguments
+    /// * `configured` - The bot username from the configuration
+    ///
+    /// # Returns
+    /// The token's account if its login differs from `configured` (ignoring case), or None if they match
+    pub async fn check_bot_username(&mut self, configured: &str) -> Result<Option<HelixUser>> {
+        let user = self.get_bot_user().await?;
+        if user.login.eq_ignore_ascii_case(configured) {
+            Ok(None)
+        } else {
+            Ok(Some(user))
+        }
     }
 
     /// Get a broadcaster's user ID from their username
-    async fn get_broadcaster_id(&mut self, username: &str) -> Result<String> {
+    pub async fn get_broadcaster_id(&mut self, username: &str) -> Result<String> {
         // Check cache first
         if let Some(id) = self.channel_cache.get(username) {
             return Ok(id.clone());
         }
 
-        // Get a fresh token
-        let token = {
-            let mut manager = self.oauth_manager.lock().await;
-            manager.get_access_token().await?
-        };
+        let user_id = self.get_user_by_login(username).await?.id;
 
-        // Get client ID for API request
-        let client_id = {
-            let manager = self.oauth_manager.lock().await;
-            manager.get_client_id().to_string()
-        };
+        // Cache the result
+        self.channel_cache
+            .insert(username.to_string(), user_id.clone());
 
-        // Make the API call to get the broadcaster's user ID
+        Ok(user_id)
+    }
+
+    /// Look up a user by their login name
+    ///
+    /// # Arguments
+    /// * `login` - The login name of the user to look up
+    ///
+    /// # Returns
+    /// The user's details, including when the account was created
+    pub async fn get_user_by_login(&mut self, login: &str) -> Result<HelixUser> {
+        let (token, client_id) = self.credentials().await?;
+
+        self.wait_for_rate_limit("/users").await;
         let response = self
             .http_client
-            .get("https://api.twitch.tv/helix/users")
+            .get(self.url("/users"))
             .header("Authorization", format!("Bearer {}", token))
             .header("Client-Id", client_id)
-            .query(&[("login", username)])
+            .query(&[("login", login)])
             .send()
             .await?;
+        self.record_rate_limit(&response);
 
         if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("Failed to get broadcaster ID: {}", error_text));
+            return Err(error_from_response(response, "Failed to get user", None).await);
         }
 
-        // Parse the response
         let users: UserResponse = response.json().await?;
+        users
+            .data
+            .into_iter()
+            .next()
+            .ok_or_else(|| TwitchError::NotFound(format!("No user data found for {}", login)))
+    }
 
-        if users.data.is_empty() {
-            return Err(anyhow!("No user data found for {}", username));
+    /// Look up a user by their ID
+    ///
+    /// # Arguments
+    /// * `user_id` - The ID of the user to look up
+    ///
+    /// # Returns
+    /// The user's details, including when the account was created
+    pub async fn get_user_by_id(&mut self, user_id: &str) -> Result<HelixUser> {
+        let (token, client_id) = self.credentials().await?;
+
+        self.wait_for_rate_limit("/users").await;
+        let response = self
+            .http_client
+            .get(self.url("/users"))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Client-Id", client_id)
+            .query(&[("id", user_id)])
+            .send()
+            .await?;
+        self.record_rate_limit(&response);
+
+        if !response.status().is_success() {
+            return Err(error_from_response(response, "Failed to get user", None).await);
         }
 
-        let user_id = users.data[0].id.clone();
+        let users: UserResponse = response.json().await?;
+        users
+            .data
+            .into_iter()
+            .next()
+            .ok_or_else(|| TwitchError::NotFound(format!("No user data found for ID {}", user_id)))
+    }
 
-        // Cache the result
-        self.channel_cache
-            .insert(username.to_string(), user_id.clone());
+    /// Get when a user followed a channel
+    ///
+    /// # Arguments
+    /// * `broadcaster_id` - The ID of the channel
+    /// * `user_id` - The ID of the user
+    ///
+    /// # Returns
+    /// When the user followed, or None if they don't follow the channel
+    pub async fn get_followed_at(
+        &mut self,
+        broadcaster_id: &str,
+        user_id: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let (token, client_id) = self.credentials().await?;
 
-        Ok(user_id)
+        self.wait_for_rate_limit("/channels/followers").await;
+        let response = self
+            .http_client
+            .get(self.url("/channels/followers"))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Client-Id", client_id)
+            .query(&[("broadcaster_id", broadcaster_id), ("user_id", user_id)])
+            .send()
+            .await?;
+        self.record_rate_limit(&response);
+
+        if !response.status().is_success() {
+            return Err(error_from_response(
+                response,
+                "Failed to get follower",
+                Some(FOLLOWERS_SCOPE),
+            )
+            .await);
+        }
+
+        // Users who don't follow the channel aren't listed
+        let followers: FollowersResponse = response.json().await?;
+        Ok(followers
+            .data
+            .into_iter()
+            .next()
+            .map(|follow| follow.followed_at))
+    }
+
+    /// Get everyone connected to a channel's chat, including lurkers
+    ///
+    /// # Arguments
+    /// * `broadcaster_id` - The ID of the channel
+    ///
+    /// # Returns
+    /// Every user in chat, gathered across all pages
+    pub async fn get_chatters(&mut self, broadcaster_id: &str) -> Result<Vec<HelixChatter>> {
+        let moderator_id = self.get_bot_user_id().await?;
+        let mut chatters = Vec::new();
+        let mut cursor = None;
+
+        loop {
+            let (token, client_id) = self.credentials().await?;
+            let mut query = vec![
+                ("broadcaster_id", broadcaster_id.to_string()),
+                ("moderator_id", moderator_id.clone()),
+                ("first", "1000".to_string()),
+            ];
+            if let Some(cursor) = cursor {
+                query.push(("after", cursor));
+            }
+
+            self.wait_for_rate_limit("/chat/chatters").await;
+            let response = self
+                .http_client
+                .get(self.url("/chat/chatters"))
+                .header("Authorization", format!("Bearer {}", token))
+                .header("Client-Id", client_id)
+                .query(&query)
+                .send()
+                .await?;
+            self.record_rate_limit(&response);
+
+            if !response.status().is_success() {
+                return Err(error_from_response(
+                    response,
+                    "Failed to get chatters",
+                    Some(CHATTERS_SCOPE),
+                )
+                .await);
+            }
+
+            let page: ChattersResponse = response.json().await?;
+            chatters.extend(page.data);
+            cursor = page.pagination.cursor.filter(|cursor| !cursor.is_empty());
+            if cursor.is_none() {
+                return Ok(chatters);
+            }
+        }
+    }
+
+    /// Subscribe an EventSub WebSocket session to an event type
+    ///
+    /// # Arguments
+    /// * `kind` - The subscription type, e.g. "channel.raid"
+    /// * `version` - The version of the subscription type
+    /// * `condition` - The condition field and the ID it must match, e.g. ("to_broadcaster_user_id", "123")
+    /// * `session_id` - The WebSocket session to deliver events to
+    /// * `scope` - The scope the subscription type needs, if any
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn create_eventsub_subscription(
+        &mut self,
+        kind: &str,
+        version: &str,
+        condition: (&str, &str),
+        session_id: &str,
+        scope: Option<&str>,
+    ) -> Result<()> {
+        let (token, client_id) = self.credentials().await?;
+
+        let request = CreateSubscriptionRequest {
+            kind,
+            version,
+            condition: HashMap::from([condition]),
+            transport: WebSocketTransport {
+                method: "websocket",
+                session_id,
+            },
+        };
+
+        self.wait_for_rate_limit("/eventsub/subscriptions").await;
+        let response = self
+            .http_client
+            .post(self.url("/eventsub/subscriptions"))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Client-Id", client_id)
+            .json(&request)
+            .send()
+            .await?;
+        self.record_rate_limit(&response);
+
+        if !response.status().is_success() {
+            let context = format!("Failed to subscribe to {}", kind);
+            return Err(error_from_response(response, &context, scope).await);
+        }
+        debug!("Subscribed to {} events", kind);
+        Ok(())
+    }
+
+    /// Mark a channel point redemption fulfilled, or cancel it and refund the points
+    ///
+    /// Twitch only lets a client update redemptions of rewards it created.
+    ///
+    /// # Arguments
+    /// * `broadcaster_id` - The ID of the channel the reward belongs to
+    /// * `reward_id` - The ID of the reward
+    /// * `redemption_id` - The ID of the redemption
+    /// * `fulfilled` - true to fulfill the redemption, false to cancel it
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn update_redemption_status(
+        &mut self,
+        broadcaster_id: &str,
+        reward_id: &str,
+        redemption_id: &str,
+        fulfilled: bool,
+    ) -> Result<()> {
+        let (token, client_id) = self.credentials().await?;
+        let status = if fulfilled { "FULFILLED" } else { "CANCELED" };
+
+        self.wait_for_rate_limit("/channel_points/custom_rewards/redemptions")
+            .await;
+        let response = self
+            .http_client
+            .patch(self.url("/channel_points/custom_rewards/redemptions"))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Client-Id", client_id)
+            .query(&[
+                ("id", redemption_id),
+                ("broadcaster_id", broadcaster_id),
+                ("reward_id", reward_id),
+            ])
+            .json(&UpdateRedemptionRequest { status })
+            .send()
+            .await?;
+        self.record_rate_limit(&response);
+
+        if !response.status().is_success() {
+            return Err(error_from_response(
+                response,
+                "Failed to update redemption",
+                Some(REDEMPTIONS_SCOPE),
+            )
+            .await);
+        }
+        info!("Marked redemption {} {}", redemption_id, status);
+        Ok(())
+    }
+
+    /// Get when a channel's current stream went live
+    ///
+    /// # Arguments
+    /// * `user_login` - The broadcaster's login name
+    ///
+    /// # Returns
+    /// When the stream started, or None if the channel is offline
+    pub async fn get_stream_started_at(
+        &mut self,
+        user_login: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let (token, client_id) = self.credentials().await?;
+
+        self.wait_for_rate_limit("/streams").await;
+        let response = self
+            .http_client
+            .get(self.url("/streams"))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Client-Id", client_id)
+            .query(&[("user_login", user_login)])
+            .send()
+            .await?;
+        self.record_rate_limit(&response);
+
+        if !response.status().is_success() {
+            return Err(error_from_response(response, "Failed to get stream", None).await);
+        }
+
+        // Offline channels return an empty list
+        let streams: StreamResponse = response.json().await?;
+        Ok(streams
+            .data
+            .into_iter()
+            .next()
+            .map(|stream| stream.started_at))
     }
 
     /// Send a chat message via Helix API
@@ -207,17 +838,7 @@ impl HelixChatClient {
         let bot_user_id = self.get_bot_user_id().await?;
         let broadcaster_id = self.get_broadcaster_id(channel).await?;
 
-        // Get a fresh token
-        let token = {
-            let mut manager = self.oauth_manager.lock().await;
-            manager.get_access_token().await?
-        };
-
-        // Get client ID for API request
-        let client_id = {
-            let manager = self.oauth_manager.lock().await;
-            manager.get_client_id().to_string()
-        };
+        let (token, client_id) = self.credentials().await?;
 
         // Prepare the request body
         let request_body = SendMessageRequest {
@@ -229,41 +850,60 @@ impl HelixChatClient {
 
         // Make the API call
         info!("Sending message to {}: {}", channel, message);
-        let response = self
-            .http_client
-            .post("https://api.twitch.tv/helix/chat/messages")
-            .header("Authorization", format!("Bearer {}", token))
-            .header("Client-Id", client_id)
-            .header("Content-Type", "application/json")
-            .json(&request_body)
-            .send()
-            .await?;
+        self.wait_for_rate_limit("/chat/messages").await;
+        let mut retried = false;
+        let response = loop {
+            let response = self
+                .http_client
+                .post(self.url("/chat/messages"))
+                .header("Authorization", format!("Bearer {}", token))
+                .header("Client-Id", &client_id)
+                .header("Content-Type", "application/json")
+                .json(&request_body)
+                .send()
+                .await?;
+            self.record_rate_limit(&response);
+
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS || retried {
+                break response;
+            }
+
+            // Wait for the bucket to refill, then try once more
+            let wait = HelixRateLimit::from_headers(response.headers())
+                .map(|rate_limit| rate_limit.until_reset(Utc::now()))
+                .unwrap_or(DEFAULT_RETRY_WAIT);
+            warn!(
+                "Rate limited sending a chat message, retrying in {:?}",
+                wait
+            );
+            tokio::time::sleep(wait).await;
+            retried = true;
+        };
 
         if !response.status().is_success() {
-            let error_text = response.text().await?;
-            error!("API error: {}", error_text);
-            return Err(anyhow!("Failed to send message: {}", error_text));
+            return Err(error_from_response(response, "Failed to send message", None).await);
         }
 
         // Parse the response
         let send_response: SendMessageResponse = response.json().await?;
 
         if send_response.data.is_empty() {
-            return Err(anyhow!("No data returned from send message API"));
+            return Err(TwitchError::Api {
+                context: "Failed to send message".to_string(),
+                status: 200,
+                error: "OK".to_string(),
+                message: "No data returned from send message API".to_string(),
+            });
         }
 
         let message_data = &send_response.data[0];
 
         if !message_data.is_sent {
-            if let Some(reason) = &message_data.drop_reason {
-                return Err(anyhow!(
-                    "Message not sent: {} - {}",
-                    reason.code,
-                    reason.message
-                ));
-            } else {
-                return Err(anyhow!("Message not sent for unknown reason"));
-            }
+            let (code, reason) = match &message_data.drop_reason {
+                Some(reason) => (reason.code.clone(), reason.message.clone()),
+                None => ("unknown".to_string(), "no reason given".to_string()),
+            };
+            return Err(TwitchError::MessageDropped { code, reason });
         }
 
         info!("Successfully sent message, ID: {}", message_data.message_id);
@@ -288,4 +928,772 @@ impl HelixChatClient {
         self.send_chat_message(channel, message, Some(reply_to))
             .await
     }
+
+    /// Ban or time out a user
+    ///
+    /// # Arguments
+    /// * `broadcaster_id` - ID of the channel to ban the user in
+    /// * `moderator_id` - ID of the moderator issuing the ban (usually the bot)
+    /// * `user_id` - ID of the user to ban
+    /// * `duration` - Timeout length in seconds, or None for a permanent ban
+    /// * `reason` - The reason shown to moderators
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn ban_user(
+        &mut self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+        user_id: &str,
+        duration: Option<u64>,
+        reason: &str,
+    ) -> Result<()> {
+        let (token, client_id) = self.credentials().await?;
+
+        let request_body = BanUserRequest {
+            data: BanUserData {
+                user_id: user_id.to_string(),
+                duration,
+                reason: reason.to_string(),
+            },
+        };
+
+        info!(
+            "Banning user {} in channel {} (duration: {:?})",
+            user_id, broadcaster_id, duration
+        );
+        self.wait_for_rate_limit("/moderation/bans").await;
+        let response = self
+            .http_client
+            .post(self.url("/moderation/bans"))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Client-Id", client_id)
+            .query(&[
+                ("broadcaster_id", broadcaster_id),
+                ("moderator_id", moderator_id),
+            ])
+            .json(&request_body)
+            .send()
+            .await?;
+        self.record_rate_limit(&response);
+
+        if !response.status().is_success() {
+            return Err(error_from_response(
+                response,
+                "Failed to ban user",
+                Some(BANNED_USERS_SCOPE),
+            )
+            .await);
+        }
+
+        Ok(())
+    }
+
+    /// Give another broadcaster a native Twitch shoutout, as the bot
+    ///
+    /// Twitch allows one shoutout every 2 minutes per channel, and one per
+    /// target every 60 minutes.
+    ///
+    /// # Arguments
+    /// * `from_broadcaster_id` - ID of the channel giving the shoutout
+    /// * `to_broadcaster_id` - ID of the channel being shouted out
+    ///
+    /// # Returns
+    /// true if the shoutout was sent, or false if it is on cooldown
+    pub async fn send_shoutout(
+        &mut self,
+        from_broadcaster_id: &str,
+        to_broadcaster_id: &str,
+    ) -> Result<bool> {
+        let moderator_id = self.get_bot_user_id().await?;
+        let (token, client_id) = self.credentials().await?;
+
+        info!(
+            "Sending shoutout from {} to {}",
+            from_broadcaster_id, to_broadcaster_id
+        );
+        self.wait_for_rate_limit("/chat/shoutouts").await;
+        let response = self
+            .http_client
+            .post(self.url("/chat/shoutouts"))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Client-Id", client_id)
+            .query(&[
+                ("from_broadcaster_id", from_broadcaster_id),
+                ("to_broadcaster_id", to_broadcaster_id),
+                ("moderator_id", moderator_id.as_str()),
+            ])
+            .send()
+            .await?;
+        self.record_rate_limit(&response);
+
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            debug!("Shoutout to {} is on cooldown", to_broadcaster_id);
+            return Ok(false);
+        }
+
+        if !response.status().is_success() {
+            return Err(error_from_response(response, "Failed to send shoutout", None).await);
+        }
+
+        Ok(true)
+    }
+
+    /// Post a highlighted Twitch announcement in a channel
+    ///
+    /// # Arguments
+    /// * `broadcaster_id` - ID of the channel to post in
+    /// * `moderator_id` - ID of the moderator posting it (usually the bot)
+    /// * `message` - The announcement text
+    /// * `color` - The highlight color
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn send_announcement(
+        &mut self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+        message: &str,
+        color: AnnouncementColor,
+    ) -> Result<()> {
+        let (token, client_id) = self.credentials().await?;
+
+        info!(
+            "Sending {} announcement in channel {}",
+            color, broadcaster_id
+        );
+        self.wait_for_rate_limit("/chat/announcements").await;
+        let response = self
+            .http_client
+            .post(self.url("/chat/announcements"))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Client-Id", client_id)
+            .query(&[
+                ("broadcaster_id", broadcaster_id),
+                ("moderator_id", moderator_id),
+            ])
+            .json(&SendAnnouncementRequest { message, color })
+            .send()
+            .await?;
+        self.record_rate_limit(&response);
+
+        if !response.status().is_success() {
+            return Err(error_from_response(
+                response,
+                "Failed to send announcement",
+                Some(ANNOUNCEMENTS_SCOPE),
+            )
+            .await);
+        }
+
+        Ok(())
+    }
+
+    /// Change a channel's chat modes, as the bot
+    ///
+    /// # Arguments
+    /// * `broadcaster_id` - ID of the channel to change
+    /// * `settings` - The modes to set
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn update_chat_settings(
+        &mut self,
+        broadcaster_id: &str,
+        settings: &ChatSettings,
+    ) -> Result<()> {
+        let moderator_id = self.get_bot_user_id().await?;
+        let (token, client_id) = self.credentials().await?;
+
+        info!(
+            "Updating chat settings in channel {}: {:?}",
+            broadcaster_id, settings
+        );
+        self.wait_for_rate_limit("/chat/settings").await;
+        let response = self
+            .http_client
+            .patch(self.url("/chat/settings"))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Client-Id", client_id)
+            .query(&[
+                ("broadcaster_id", broadcaster_id),
+                ("moderator_id", moderator_id.as_str()),
+            ])
+            .json(settings)
+            .send()
+            .await?;
+        self.record_rate_limit(&response);
+
+        if !response.status().is_success() {
+            return Err(error_from_response(
+                response,
+                "Failed to update chat settings",
+                Some(CHAT_SETTINGS_SCOPE),
+            )
+            .await);
+        }
+
+        Ok(())
+    }
+
+    /// Remove a ban or timeout from a user
+    ///
+    /// # Arguments
+    /// * `broadcaster_id` - ID of the channel the user is banned in
+    /// * `moderator_id` - ID of the moderator lifting the ban (usually the bot)
+    /// * `user_id` - ID of the user to unban
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn unban_user(
+        &mut self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+        user_id: &str,
+    ) -> Result<()> {
+        let (token, client_id) = self.credentials().await?;
+
+        info!("Unbanning user {} in channel {}", user_id, broadcaster_id);
+        self.wait_for_rate_limit("/moderation/bans").await;
+        let response = self
+            .http_client
+            .delete(self.url("/moderation/bans"))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Client-Id", client_id)
+            .query(&[
+                ("broadcaster_id", broadcaster_id),
+                ("moderator_id", moderator_id),
+                ("user_id", user_id),
+            ])
+            .send()
+            .await?;
+        self.record_rate_limit(&response);
+
+        if !response.status().is_success() {
+            return Err(error_from_response(
+                response,
+                "Failed to unban user",
+                Some(BANNED_USERS_SCOPE),
+            )
+            .await);
+        }
+
+        Ok(())
+    }
+
+    /// Send a whisper from the bot to a user
+    ///
+    /// The bot's account needs a verified phone number to send whispers.
+    ///
+    /// # Arguments
+    /// * `to_user_id` - ID of the user to whisper to
+    /// * `message` - The message to send
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn send_whisper(&mut self, to_user_id: &str, message: &str) -> Result<()> {
+        let from_user_id = self.get_bot_user_id().await?;
+        let (token, client_id) = self.credentials().await?;
+
+        info!("Sending whisper to user {}", to_user_id);
+        self.wait_for_rate_limit("/whispers").await;
+        let response = self
+            .http_client
+            .post(self.url("/whispers"))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Client-Id", client_id)
+            .query(&[
+                ("from_user_id", from_user_id.as_str()),
+                ("to_user_id", to_user_id),
+            ])
+            .json(&SendWhisperRequest { message })
+            .send()
+            .await?;
+        self.record_rate_limit(&response);
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        let body = response.text().await?;
+        if body.contains("verified phone number") {
+            error!("Whisper failed: {}", body);
+            return Err(TwitchError::PhoneNotVerified);
+        }
+        Err(error_from_body(
+            status,
+            body,
+            "Failed to send whisper",
+            Some(WHISPERS_SCOPE),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::create_authenticated_oauth_manager;
+    use mockito::{Matcher, Server};
+
+    #[tokio::test]
+    async fn test_unban_user() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("DELETE", "/moderation/bans")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("broadcaster_id".into(), "100".into()),
+                Matcher::UrlEncoded("moderator_id".into(), "200".into()),
+                Matcher::UrlEncoded("user_id".into(), "300".into()),
+            ]))
+            .match_header("authorization", "Bearer test_access_token")
+            .with_status(204)
+            .create_async()
+            .await;
+
+        let mut helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+        helix.unban_user("100", "200", "300").await?;
+
+        mock.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_user_by_id() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("id".into(), "300".into()))
+            .with_status(200)
+            .with_body(
+                r#"{"data":[{"id":"300","login":"someone","display_name":"Someone",
+                "created_at":"2016-12-14T20:32:28Z"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let mut helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+        let user = helix.get_user_by_id("300").await?;
+
+        assert_eq!(user.login, "someone");
+        assert_eq!(
+            user.created_at.unwrap().to_rfc3339(),
+            "2016-12-14T20:32:28+00:00"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_bot_username() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/users")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"999","login":"real_bot","display_name":"Real_Bot"}]}"#)
+            .create_async()
+            .await;
+
+        let mut helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+
+        assert!(helix.check_bot_username("Real_Bot").await?.is_none());
+
+        let user = helix.check_bot_username("old_bot_name").await?.unwrap();
+        assert_eq!(user.login, "real_bot");
+        assert_eq!(user.display_name, "Real_Bot");
+        assert_eq!(helix.get_bot_user_id().await?, "999");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_followed_at() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let _following = server
+            .mock("GET", "/channels/followers")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("broadcaster_id".into(), "456".into()),
+                Matcher::UrlEncoded("user_id".into(), "123".into()),
+            ]))
+            .with_status(200)
+            .with_body(
+                r#"{"total":1,"data":[{"user_id":"123","user_login":"test_user",
+                "user_name":"Test_User","followed_at":"2022-05-24T22:22:08Z"}],"pagination":{}}"#,
+            )
+            .create_async()
+            .await;
+        let _not_following = server
+            .mock("GET", "/channels/followers")
+            .match_query(Matcher::UrlEncoded("user_id".into(), "300".into()))
+            .with_status(200)
+            .with_body(r#"{"total":5,"data":[],"pagination":{}}"#)
+            .create_async()
+            .await;
+
+        let mut helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+
+        assert_eq!(
+            helix
+                .get_followed_at("456", "123")
+                .await?
+                .unwrap()
+                .to_rfc3339(),
+            "2022-05-24T22:22:08+00:00"
+        );
+        assert_eq!(helix.get_followed_at("456", "300").await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_chatters_follows_pages() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let _bot = server
+            .mock("GET", "/users")
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"999","login":"bot","display_name":"Bot"}]}"#)
+            .create_async()
+            .await;
+        let _second_page = server
+            .mock("GET", "/chat/chatters")
+            .match_query(Matcher::UrlEncoded("after".into(), "abc".into()))
+            .with_status(200)
+            .with_body(
+                r#"{"data":[{"user_id":"2","user_login":"lurker","user_name":"Lurker"}],
+                "pagination":{},"total":2}"#,
+            )
+            .create_async()
+            .await;
+        let _first_page = server
+            .mock("GET", "/chat/chatters")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("broadcaster_id".into(), "456".into()),
+                Matcher::UrlEncoded("moderator_id".into(), "999".into()),
+                Matcher::UrlEncoded("first".into(), "1000".into()),
+            ]))
+            .with_status(200)
+            .with_body(
+                r#"{"data":[{"user_id":"1","user_login":"chatty","user_name":"Chatty"}],
+                "pagination":{"cursor":"abc"},"total":2}"#,
+            )
+            .create_async()
+            .await;
+
+        let mut helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+
+        let logins: Vec<String> = helix
+            .get_chatters("456")
+            .await?
+            .into_iter()
+            .map(|chatter| chatter.user_login)
+            .collect();
+        assert_eq!(logins, vec!["chatty", "lurker"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ban_user_with_duration() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/moderation/bans")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("broadcaster_id".into(), "100".into()),
+                Matcher::UrlEncoded("moderator_id".into(), "200".into()),
+            ]))
+            .match_body(Matcher::Json(serde_json::json!({
+                "data": {"user_id": "300", "duration": 60, "reason": "spam"}
+            })))
+            .with_status(200)
+            .with_body("{}")
+            .create_async()
+            .await;
+
+        let mut helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+        helix
+            .ban_user("100", "200", "300", Some(60), "spam")
+            .await?;
+
+        mock.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_announcement() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/announcements")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("broadcaster_id".into(), "100".into()),
+                Matcher::UrlEncoded("moderator_id".into(), "200".into()),
+            ]))
+            .match_body(Matcher::Json(serde_json::json!({
+                "message": "Stream starts soon!", "color": "purple"
+            })))
+            .with_status(204)
+            .create_async()
+            .await;
+
+        let mut helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+        let color: AnnouncementColor = "Purple".parse().unwrap();
+        helix
+            .send_announcement("100", "200", "Stream starts soon!", color)
+            .await?;
+
+        mock.assert_async().await;
+        assert!("red".parse::<AnnouncementColor>().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unban_user_error() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("DELETE", "/moderation/bans")
+            .match_query(Matcher::Any)
+            .with_status(400)
+            .with_body(r#"{"error":"Bad Request","status":400,"message":"user is not banned"}"#)
+            .create_async()
+            .await;
+
+        let mut helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+        let result = helix.unban_user("100", "200", "300").await;
+
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("user is not banned")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_whisper() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let _bot = server
+            .mock("GET", "/users")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"999","login":"test_bot","display_name":"Test_Bot"}]}"#)
+            .create_async()
+            .await;
+        let whisper = server
+            .mock("POST", "/whispers")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("from_user_id".into(), "999".into()),
+                Matcher::UrlEncoded("to_user_id".into(), "300".into()),
+            ]))
+            .match_body(Matcher::Json(serde_json::json!({"message": "psst"})))
+            .with_status(204)
+            .create_async()
+            .await;
+
+        let mut helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+        helix.send_whisper("300", "psst").await?;
+
+        whisper.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_whisper_without_verified_phone() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let _bot = server
+            .mock("GET", "/users")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"999","login":"test_bot","display_name":"Test_Bot"}]}"#)
+            .create_async()
+            .await;
+        let _whisper = server
+            .mock("POST", "/whispers")
+            .match_query(Matcher::Any)
+            .with_status(401)
+            .with_body(r#"{"error":"Unauthorized","status":401,"message":"the sender does not have a verified phone number"}"#)
+            .create_async()
+            .await;
+
+        let mut helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+        let result = helix.send_whisper("300", "psst").await;
+
+        assert!(matches!(result, Err(TwitchError::PhoneNotVerified)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_headers_stored_per_endpoint() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let _users = server
+            .mock("GET", "/users")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_header("Ratelimit-Limit", "800")
+            .with_header("Ratelimit-Remaining", "790")
+            .with_header("Ratelimit-Reset", "1700000000")
+            .with_body(r#"{"data":[{"id":"999","login":"test_bot","display_name":"Test_Bot"}]}"#)
+            .create_async()
+            .await;
+        let _bans = server
+            .mock("DELETE", "/moderation/bans")
+            .match_query(Matcher::Any)
+            .with_status(204)
+            .with_header("Ratelimit-Limit", "800")
+            .with_header("Ratelimit-Remaining", "789")
+            .with_header("Ratelimit-Reset", "1700000001")
+            .create_async()
+            .await;
+
+        let mut helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+        assert!(helix.rate_limit().is_none());
+
+        helix.get_broadcaster_id("someone").await?;
+        helix.unban_user("100", "200", "300").await?;
+
+        let users = helix.rate_limit_for("/users").unwrap();
+        assert_eq!(users.remaining, 790);
+        assert_eq!(users.reset.timestamp(), 1700000000);
+        assert_eq!(
+            helix.rate_limit_for("/moderation/bans").unwrap().remaining,
+            789
+        );
+        // The overall state is from the most recent response
+        assert_eq!(helix.rate_limit().unwrap().remaining, 789);
+        assert!(helix.rate_limit_for("/chat/messages").is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_chat_message_retries_after_429() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let _bot = server
+            .mock("GET", "/users")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"999","login":"test_bot","display_name":"Test_Bot"}]}"#)
+            .create_async()
+            .await;
+        let _channel = server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "test_channel".into()))
+            .with_status(200)
+            .with_body(
+                r#"{"data":[{"id":"456","login":"test_channel","display_name":"Test_Channel"}]}"#,
+            )
+            .create_async()
+            .await;
+        // The bucket resets right away so the test doesn't wait
+        let reset = Utc::now().timestamp().to_string();
+        let limited = server
+            .mock("POST", "/chat/messages")
+            .with_status(429)
+            .with_header("Ratelimit-Limit", "800")
+            .with_header("Ratelimit-Remaining", "0")
+            .with_header("Ratelimit-Reset", &reset)
+            .with_body(r#"{"error":"Too Many Requests","status":429,"message":""}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let sent = server
+            .mock("POST", "/chat/messages")
+            .with_status(200)
+            .with_header("Ratelimit-Limit", "800")
+            .with_header("Ratelimit-Remaining", "799")
+            .with_header("Ratelimit-Reset", &reset)
+            .with_body(r#"{"data":[{"message_id":"abc-123","is_sent":true}]}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+        let message_id = helix
+            .send_chat_message("test_channel", "hello", None)
+            .await?;
+
+        assert_eq!(message_id, "abc-123");
+        limited.assert_async().await;
+        sent.assert_async().await;
+        assert_eq!(
+            helix.rate_limit_for("/chat/messages").unwrap().remaining,
+            799
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_errors_report_their_cause() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let _bot = server
+            .mock("GET", "/users")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_body(r#"{"data":[{"id":"999","login":"test_bot","display_name":"Test_Bot"}]}"#)
+            .create_async()
+            .await;
+        let _expired = server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "someone".into()))
+            .with_status(401)
+            .with_body(r#"{"error":"Unauthorized","status":401,"message":"Invalid OAuth token"}"#)
+            .create_async()
+            .await;
+        let _channel = server
+            .mock("GET", "/users")
+            .match_query(Matcher::UrlEncoded("login".into(), "test_channel".into()))
+            .with_status(200)
+            .with_body(
+                r#"{"data":[{"id":"456","login":"test_channel","display_name":"Test_Channel"}]}"#,
+            )
+            .create_async()
+            .await;
+        let _dropped = server
+            .mock("POST", "/chat/messages")
+            .with_status(200)
+            .with_body(
+                r#"{"data":[{"message_id":"","is_sent":false,"drop_reason":{"code":"msg_duplicate","message":"Duplicate message"}}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let mut helix =
+            HelixChatClient::with_base_url(create_authenticated_oauth_manager(), &server.url())
+                .await?;
+
+        let error = helix.get_broadcaster_id("someone").await.unwrap_err();
+        assert!(matches!(error, TwitchError::AuthExpired));
+
+        let error = helix
+            .send_chat_message("test_channel", "hello", None)
+            .await
+            .unwrap_err();
+        match error {
+            TwitchError::MessageDropped { code, reason } => {
+                assert_eq!(code, "msg_duplicate");
+                assert_eq!(reason, "Duplicate message");
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+        Ok(())
+    }
 }
@@ -1,6 +1,26 @@
+mod backoff;
+mod channels;
 mod client;
+mod delivery;
+mod error;
+mod eventsub;
 mod helix;
 mod oauth;
+mod rate_limit;
+mod sender;
+mod token_crypto;
 
-pub use client::TwitchClient;
-pub use oauth::OAuthManager;
+pub use backoff::Backoff;
+pub use channels::{ChannelStatus, JoinedChannels};
+pub use client::{MAX_MESSAGE_LENGTH, TwitchClient, split_message};
+pub use delivery::DeliveryTracker;
+pub use error::TwitchError;
+pub use eventsub::{EventSubClient, EventSubEvent, Redemption};
+pub use helix::{AnnouncementColor, ChatSettings, HelixChatClient, HelixChatter, HelixUser};
+#[allow(unused_imports)]
+pub use oauth::TokenResponse;
+pub use oauth::{DEFAULT_REDIRECT_URI, OAuthManager};
+pub use rate_limit::{
+    CHAT_MESSAGES_PER_WINDOW, HelixRateLimit, MODERATOR_CHAT_MESSAGES_PER_WINDOW, OutboundLimiter,
+};
+pub use sender::{ChannelParter, MessageSender};
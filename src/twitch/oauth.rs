@@ -1,9 +1,25 @@
 use anyhow::{Result, anyhow};
-use reqwest::Client;
+use rand::Rng;
+use rand::distr::Alphanumeric;
+use reqwest::{Client, Url};
 use serde::Deserialize;
-use std::thread::sleep;
+use std::collections::HashMap;
+use std::path::Path;
 use std::time::{Duration, Instant};
-use tracing::{debug, info};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+use crate::store::write_atomic;
+use crate::twitch::error::error_message;
+use crate::twitch::token_crypto;
+
+/// Base URL of Twitch's OAuth endpoints
+const OAUTH_BASE_URL: &str = "https://id.twitch.tv/oauth2";
+
+/// Where Twitch sends the browser back to in the authorization code flow
+pub const DEFAULT_REDIRECT_URI: &str = "http://localhost:3000";
 
 /// The response from the device code request
 #[derive(Debug, Deserialize)]
@@ -35,16 +51,6 @@ pub struct TokenResponse {
     pub token_type: String,
 }
 
-/// The error response from the token request
-#[derive(Debug, Deserialize)]
-pub struct ErrorResponse {
-    /// HTTP status code
-    #[allow(dead_code)]
-    pub status: u16,
-    /// Error message
-    pub message: String,
-}
-
 /// Structure to manage OAuth authentication
 pub struct OAuthManager {
     /// HTTP client for making requests
@@ -57,6 +63,14 @@ pub struct OAuthManager {
     token: Option<TokenResponse>,
     /// When the token was obtained
     token_obtained_at: Option<Instant>,
+    /// Passphrase used to encrypt the token at rest, if configured
+    encryption_key: Option<String>,
+    /// Client secret of a confidential application, if configured
+    client_secret: Option<String>,
+    /// Redirect URL registered for the application, used with a client secret
+    redirect_uri: String,
+    /// Base URL for OAuth requests (overridable for tests)
+    base_url: String,
 }
 
 impl OAuthManager {
@@ -69,15 +83,58 @@ impl OAuthManager {
     /// # Returns
     /// A new OAuthManager
     pub fn new(client_id: String, scopes: Vec<String>) -> Self {
+        Self::with_base_url(client_id, scopes, OAUTH_BASE_URL)
+    }
+
+    /// Create a new OAuth manager that sends requests to a custom base URL
+    ///
+    /// # Arguments
+    /// * `client_id` - The client ID for the application
+    /// * `scopes` - The scopes needed for the application
+    /// * `base_url` - Base URL of the OAuth endpoints (e.g. a mock server in tests)
+    ///
+    /// # Returns
+    /// A new OAuthManager
+    pub fn with_base_url(client_id: String, scopes: Vec<String>, base_url: &str) -> Self {
         OAuthManager {
             client: Client::new(),
             client_id,
             scopes,
             token: None,
             token_obtained_at: None,
+            encryption_key: None,
+            client_secret: None,
+            redirect_uri: DEFAULT_REDIRECT_URI.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
         }
     }
 
+    /// Build the full URL for an OAuth endpoint
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// Set the passphrase used to encrypt the token file
+    ///
+    /// # Arguments
+    /// * `key` - The passphrase, or None to store the token in plaintext
+    pub fn set_encryption_key(&mut self, key: Option<String>) {
+        self.encryption_key = key.filter(|k| !k.is_empty());
+    }
+
+    /// Authenticate as a confidential application with a client secret
+    ///
+    /// With a secret, `authenticate` uses the authorization code flow instead
+    /// of the device code flow, and token refreshes send the secret too.
+    ///
+    /// # Arguments
+    /// * `secret` - The client secret, or None to use the device code flow
+    /// * `redirect_uri` - The redirect URL registered for the application
+    pub fn set_client_secret(&mut self, secret: Option<String>, redirect_uri: &str) {
+        self.client_secret = secret.filter(|s| !s.is_empty());
+        self.redirect_uri = redirect_uri.to_string();
+    }
+
     /// Get the current access token, refreshing if necessary
     ///
     /// # Returns
@@ -114,16 +171,16 @@ impl OAuthManager {
 
         let response = self
             .client
-            .post("https://id.twitch.tv/oauth2/device")
+            .post(self.url("/device"))
             .multipart(form)
             .send()
             .await?;
 
         if !response.status().is_success() {
-            let error: ErrorResponse = response.json().await?;
+            let error_text = response.text().await?;
             return Err(anyhow!(
                 "Failed to start device code flow: {}",
-                error.message
+                error_message(&error_text)
             ));
         }
 
@@ -148,20 +205,17 @@ impl OAuthManager {
             device_code.expires_in
         );
 
+        let scopes = self.scopes.join(" ");
         while start_time.elapsed() < expiry {
-            let form = reqwest::multipart::Form::new()
-                .text("client_id", self.client_id.clone())
-                .text("scopes", self.scopes.join(" "))
-                .text("device_code", device_code.device_code.clone())
-                .text(
-                    "grant_type",
-                    "urn:ietf:params:oauth:grant-type:device_code".to_string(),
-                );
-
             let response = self
                 .client
-                .post("https://id.twitch.tv/oauth2/token")
-                .multipart(form)
+                .post(self.url("/token"))
+                .form(&[
+                    ("client_id", self.client_id.as_str()),
+                    ("scopes", scopes.as_str()),
+                    ("device_code", device_code.device_code.as_str()),
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                ])
                 .send()
                 .await?;
 
@@ -173,25 +227,119 @@ impl OAuthManager {
             }
 
             // Parse the error response
-            let error_text = response.text().await?;
+            let message = error_message(&response.text().await?);
 
             // Check if we need to keep waiting
-            if error_text.contains("authorization_pending") {
+            if message == "authorization_pending" {
                 debug!(
                     "Authorization pending, waiting {} seconds",
                     interval.as_secs()
                 );
-                sleep(interval);
+                sleep(interval).await;
                 continue;
             }
 
             // Any other error is fatal
-            return Err(anyhow!("Error polling for token: {}", error_text));
+            return Err(anyhow!("Error polling for token: {}", message));
         }
 
         Err(anyhow!("Device code flow timed out"))
     }
 
+    /// Build the URL the user visits to authorize the application
+    ///
+    /// # Arguments
+    /// * `state` - A random value Twitch sends back with the code
+    ///
+    /// # Returns
+    /// The authorization URL
+    pub fn authorize_url(&self, state: &str) -> Result<String> {
+        let url = Url::parse_with_params(
+            &self.url("/authorize"),
+            &[
+                ("response_type", "code"),
+                ("client_id", self.client_id.as_str()),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("scope", self.scopes.join(" ").as_str()),
+                ("state", state),
+            ],
+        )?;
+        Ok(url.into())
+    }
+
+    /// Exchange an authorization code for a token
+    ///
+    /// # Arguments
+    /// * `code` - The code Twitch sent to the redirect URL
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn exchange_code(&mut self, code: &str) -> Result<()> {
+        let secret = self
+            .client_secret
+            .as_deref()
+            .ok_or_else(|| anyhow!("The authorization code flow needs a client secret"))?;
+
+        let response = self
+            .client
+            .post(self.url("/token"))
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", secret),
+                ("code", code),
+                ("grant_type", "authorization_code"),
+                ("redirect_uri", self.redirect_uri.as_str()),
+            ])
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!(
+                "Failed to exchange authorization code: {}",
+                error_message(&error_text)
+            ));
+        }
+
+        let token: TokenResponse = response.json().await?;
+        self.token = Some(token);
+        self.token_obtained_at = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Run the authorization code flow, catching the redirect on a local server
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    async fn authenticate_with_code(&mut self) -> Result<()> {
+        let redirect = Url::parse(&self.redirect_uri)?;
+        let host = redirect
+            .host_str()
+            .ok_or_else(|| anyhow!("Redirect URL {} has no host", self.redirect_uri))?;
+        let port = redirect.port_or_known_default().unwrap_or(80);
+        let listener = TcpListener::bind((host, port)).await.map_err(|e| {
+            anyhow!(
+                "Couldn't listen for the redirect on {}:{}: {}",
+                host,
+                port,
+                e
+            )
+        })?;
+
+        let state: String = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+
+        println!("\n=== Twitch Authentication Required ===");
+        println!("Please visit: {}", self.authorize_url(&state)?);
+        println!("Waiting for Twitch to redirect to {}...", self.redirect_uri);
+
+        let code = receive_authorization_code(&listener, &state).await?;
+        self.exchange_code(&code).await
+    }
+
     /// Refresh the access token using the refresh token
     ///
     /// # Returns
@@ -203,21 +351,29 @@ impl OAuthManager {
 
         let refresh_token = self.token.as_ref().unwrap().refresh_token.clone();
 
-        let form = reqwest::multipart::Form::new()
-            .text("client_id", self.client_id.clone())
-            .text("refresh_token", refresh_token)
-            .text("grant_type", "refresh_token".to_string());
+        let mut form = vec![
+            ("client_id", self.client_id.as_str()),
+            ("refresh_token", refresh_token.as_str()),
+            ("grant_type", "refresh_token"),
+        ];
+        // Confidential applications must prove themselves on refresh too
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
 
         let response = self
             .client
-            .post("https://id.twitch.tv/oauth2/token")
-            .multipart(form)
+            .post(self.url("/token"))
+            .form(&form)
             .send()
             .await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
-            return Err(anyhow!("Failed to refresh token: {}", error_text));
+            return Err(anyhow!(
+                "Failed to refresh token: {}",
+                error_message(&error_text)
+            ));
         }
 
         let token: TokenResponse = response.json().await?;
@@ -227,11 +383,55 @@ impl OAuthManager {
         Ok(())
     }
 
-    /// Run the device code flow and wait for user authentication
+    /// Revoke the access token with Twitch so it can't be used again
+    ///
+    /// A token Twitch no longer recognizes counts as revoked.
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn revoke_token(&mut self) -> Result<()> {
+        let Some(token) = &self.token else {
+            return Err(anyhow!("No token to revoke"));
+        };
+
+        let response = self
+            .client
+            .post(self.url("/revoke"))
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("token", token.access_token.as_str()),
+            ])
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let message = error_message(&response.text().await?);
+            if message != "Invalid token" {
+                return Err(anyhow!("Failed to revoke token: {}", message));
+            }
+            debug!("Token was already invalid");
+        }
+
+        self.token = None;
+        self.token_obtained_at = None;
+        Ok(())
+    }
+
+    /// Run the OAuth flow and wait for user authentication
+    ///
+    /// Uses the authorization code flow when a client secret is set, and the
+    /// device code flow otherwise.
     ///
     /// # Returns
     /// A Result indicating success or failure
     pub async fn authenticate(&mut self) -> Result<()> {
+        if self.client_secret.is_some() {
+            self.authenticate_with_code().await?;
+            info!("Authentication successful!");
+            println!("Authentication successful! You can now use the bot.");
+            return Ok(());
+        }
+
         // Start the device code flow
         let device_code = self.start_device_code_flow().await?;
 
@@ -267,6 +467,16 @@ impl OAuthManager {
         self.token.clone()
     }
 
+    /// Use an already obtained token (e.g. from tests or another flow)
+    ///
+    /// # Arguments
+    /// * `token` - The token to use for subsequent requests
+    #[allow(dead_code)]
+    pub fn set_token(&mut self, token: TokenResponse) {
+        self.token = Some(token);
+        self.token_obtained_at = Some(Instant::now());
+    }
+
     /// Get the client ID used for this OAuth session
     ///
     /// # Returns
@@ -277,6 +487,9 @@ impl OAuthManager {
 
     /// Save token to a file for later use
     ///
+    /// The token is encrypted when an encryption key is set, and written as
+    /// plaintext JSON (with a warning) otherwise.
+    ///
     /// # Arguments
     /// * `path` - The path to save the token to
     ///
@@ -285,7 +498,17 @@ impl OAuthManager {
     pub fn save_token(&self, path: &str) -> Result<()> {
         if let Some(token) = &self.token {
             let token_json = serde_json::to_string_pretty(token)?;
-            std::fs::write(path, token_json)?;
+            let contents = match &self.encryption_key {
+                Some(key) => token_crypto::encrypt(&token_json, key)?,
+                None => {
+                    warn!(
+                        "Saving OAuth token in plaintext; set TOKEN_ENCRYPTION_KEY to encrypt it"
+                    );
+                    token_json
+                }
+            };
+            // A token file cut off mid-write would force signing in again
+            write_atomic(Path::new(path), contents)?;
             Ok(())
         } else {
             Err(anyhow!("No token to save"))
@@ -294,13 +517,32 @@ impl OAuthManager {
 
     /// Load token from a file
     ///
+    /// Encrypted token files are decrypted with the configured encryption key.
+    /// Plaintext token files are still accepted so existing setups keep working.
+    ///
     /// # Arguments
     /// * `path` - The path to load the token from
     ///
     /// # Returns
     /// A Result indicating success or failure
     pub fn load_token(&mut self, path: &str) -> Result<()> {
-        let token_json = std::fs::read_to_string(path)?;
+        let contents = std::fs::read_to_string(path)?;
+
+        let token_json = match token_crypto::parse_envelope(&contents) {
+            Some(envelope) => {
+                let key = self.encryption_key.as_ref().ok_or_else(|| {
+                    anyhow!("OAuth token is encrypted but TOKEN_ENCRYPTION_KEY is not set")
+                })?;
+                token_crypto::decrypt(&envelope, key)?
+            }
+            None => {
+                if self.encryption_key.is_some() {
+                    warn!("OAuth token file is plaintext; it will be encrypted on next save");
+                }
+                contents
+            }
+        };
+
         let token: TokenResponse = serde_json::from_str(&token_json)?;
         self.token = Some(token);
         self.token_obtained_at = Some(Instant::now());
@@ -308,21 +550,158 @@ impl OAuthManager {
     }
 }
 
+/// Wait for Twitch to send the browser back with an authorization code
+///
+/// Requests without the expected state (like a browser's favicon request)
+/// are turned away and waiting continues.
+///
+/// # Arguments
+/// * `listener` - The server listening on the redirect URL
+/// * `state` - The state sent with the authorization request
+///
+/// # Returns
+/// The authorization code, or an error if the user declined
+async fn receive_authorization_code(listener: &TcpListener, state: &str) -> Result<String> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let mut buffer = vec![0; 8192];
+        let read = stream.read(&mut buffer).await?;
+        let request = String::from_utf8_lossy(&buffer[..read]);
+
+        // The request line looks like "GET /?code=...&state=... HTTP/1.1"
+        let Some(target) = request
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+        else {
+            continue;
+        };
+        let params: HashMap<String, String> = Url::parse(&format!("http://localhost{}", target))?
+            .query_pairs()
+            .into_owned()
+            .collect();
+
+        if params.get("state").map(String::as_str) != Some(state) {
+            respond(&mut stream, "404 Not Found", "Not found").await;
+            continue;
+        }
+        if let Some(code) = params.get("code") {
+            respond(
+                &mut stream,
+                "200 OK",
+                "The bot is authorized. You can close this tab.",
+            )
+            .await;
+            return Ok(code.clone());
+        }
+
+        let error = params
+            .get("error_description")
+            .or_else(|| params.get("error"))
+            .cloned()
+            .unwrap_or_else(|| "no code was sent".to_string());
+        respond(
+            &mut stream,
+            "400 Bad Request",
+            &format!("Authorization failed: {}", error),
+        )
+        .await;
+        return Err(anyhow!("Authorization failed: {}", error));
+    }
+}
+
+/// Answer a request to the redirect server with a plain text page
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        debug!("Couldn't answer the redirect request: {}", e);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use mockito::Server;
+    use mockito::{Matcher, Server};
+    use tempfile::tempdir;
+
+    fn create_test_token() -> TokenResponse {
+        TokenResponse {
+            access_token: "test_access_token".to_string(),
+            expires_in: 3600,
+            refresh_token: "test_refresh_token".to_string(),
+            scope: vec!["chat:read".to_string()],
+            token_type: "bearer".to_string(),
+        }
+    }
+
+    fn create_test_manager() -> OAuthManager {
+        OAuthManager::new("test_client_id".to_string(), vec!["chat:read".to_string()])
+    }
+
+    #[test]
+    fn test_save_and_load_encrypted_token() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("token.json");
+        let path = path.to_str().unwrap();
+
+        let mut manager = create_test_manager();
+        manager.set_encryption_key(Some("passphrase".to_string()));
+        manager.token = Some(create_test_token());
+        manager.save_token(path)?;
+
+        // The access token must not appear in the file
+        let contents = std::fs::read_to_string(path)?;
+        assert!(!contents.contains("test_access_token"));
+
+        let mut loaded = create_test_manager();
+        loaded.set_encryption_key(Some("passphrase".to_string()));
+        loaded.load_token(path)?;
+        assert_eq!(
+            loaded.get_token().unwrap().access_token,
+            "test_access_token"
+        );
+
+        // Without the key the encrypted token can't be read
+        let mut without_key = create_test_manager();
+        assert!(without_key.load_token(path).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_plaintext_token_with_key_set() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("token.json");
+        let path = path.to_str().unwrap();
+
+        // Save without a key, as older versions did
+        let mut manager = create_test_manager();
+        manager.token = Some(create_test_token());
+        manager.save_token(path)?;
+        assert!(std::fs::read_to_string(path)?.contains("test_access_token"));
+
+        let mut loaded = create_test_manager();
+        loaded.set_encryption_key(Some("passphrase".to_string()));
+        loaded.load_token(path)?;
+        assert_eq!(
+            loaded.get_token().unwrap().refresh_token,
+            "test_refresh_token"
+        );
+
+        Ok(())
+    }
 
-    // This test can't test the actual API call since we need to modify the client to point
-    // to our mock server. For now, we'll just test that the function is defined correctly
-    // and would execute if we had a real server to test against.
     #[tokio::test]
-    #[ignore]
     async fn test_start_device_code_flow() -> Result<()> {
-        let mut server = Server::new();
+        let mut server = Server::new_async().await;
 
         // Mock the device code endpoint
-        let _mock = server
+        let mock = server
             .mock("POST", "/oauth2/device")
             .with_status(200)
             .with_header("content-type", "application/json")
@@ -335,16 +714,236 @@ mod tests {
                 "verification_uri": "https://www.twitch.tv/activate"
             }"#,
             )
-            .create();
+            .create_async()
+            .await;
 
-        let _oauth = OAuthManager::new(
+        let oauth = OAuthManager::with_base_url(
             "test_client_id".to_string(),
             vec!["chat:read".to_string(), "chat:edit".to_string()],
+            &format!("{}/oauth2", server.url()),
+        );
+
+        let device_code = oauth.start_device_code_flow().await?;
+        assert_eq!(device_code.device_code, "test_device_code");
+        assert_eq!(device_code.user_code, "ABCDEFGH");
+        assert_eq!(device_code.interval, 5);
+        assert_eq!(
+            device_code.verification_uri,
+            "https://www.twitch.tv/activate"
         );
+        mock.assert_async().await;
 
-        // In a real test, we would modify the client's base URL to point to our mock server
-        // For now, we're skipping this test
+        Ok(())
+    }
 
+    #[tokio::test]
+    async fn test_refresh_token() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/oauth2/token")
+            .match_header("content-type", "application/x-www-form-urlencoded")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("client_id".into(), "test_client_id".into()),
+                Matcher::UrlEncoded("refresh_token".into(), "test_refresh_token".into()),
+                Matcher::UrlEncoded("grant_type".into(), "refresh_token".into()),
+            ]))
+            .with_status(200)
+            .with_body(
+                r#"{
+                "access_token": "new_access_token",
+                "expires_in": 3600,
+                "refresh_token": "new_refresh_token",
+                "scope": ["chat:read"],
+                "token_type": "bearer"
+            }"#,
+            )
+            .create_async()
+            .await;
+
+        let mut oauth = OAuthManager::with_base_url(
+            "test_client_id".to_string(),
+            vec!["chat:read".to_string()],
+            &format!("{}/oauth2", server.url()),
+        );
+        oauth.set_token(create_test_token());
+
+        oauth.refresh_token().await?;
+        assert_eq!(oauth.get_token().unwrap().access_token, "new_access_token");
+        mock.assert_async().await;
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_for_token_waits_for_authorization() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let form = Matcher::AllOf(vec![
+            Matcher::UrlEncoded("client_id".into(), "test_client_id".into()),
+            Matcher::UrlEncoded("device_code".into(), "test_device_code".into()),
+            Matcher::UrlEncoded(
+                "grant_type".into(),
+                "urn:ietf:params:oauth:grant-type:device_code".into(),
+            ),
+        ]);
+        let pending = server
+            .mock("POST", "/oauth2/token")
+            .match_header("content-type", "application/x-www-form-urlencoded")
+            .match_body(form.clone())
+            .with_status(400)
+            .with_body(r#"{"status":400,"message":"authorization_pending"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let granted = server
+            .mock("POST", "/oauth2/token")
+            .match_header("content-type", "application/x-www-form-urlencoded")
+            .match_body(form)
+            .with_status(200)
+            .with_body(
+                r#"{
+                "access_token": "device_access_token",
+                "expires_in": 3600,
+                "refresh_token": "device_refresh_token",
+                "scope": ["chat:read"],
+                "token_type": "bearer"
+            }"#,
+            )
+            .create_async()
+            .await;
+
+        let mut oauth = OAuthManager::with_base_url(
+            "test_client_id".to_string(),
+            vec!["chat:read".to_string()],
+            &format!("{}/oauth2", server.url()),
+        );
+        let device_code = DeviceCodeResponse {
+            device_code: "test_device_code".to_string(),
+            expires_in: 1800,
+            interval: 5,
+            user_code: "ABCDEFGH".to_string(),
+            verification_uri: "https://www.twitch.tv/activate".to_string(),
+        };
+
+        oauth.poll_for_token(&device_code).await?;
+        assert_eq!(
+            oauth.get_token().unwrap().access_token,
+            "device_access_token"
+        );
+        pending.assert_async().await;
+        granted.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_revoke_token() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let form = Matcher::AllOf(vec![
+            Matcher::UrlEncoded("client_id".into(), "test_client_id".into()),
+            Matcher::UrlEncoded("token".into(), "test_access_token".into()),
+        ]);
+        let revoked = server
+            .mock("POST", "/oauth2/revoke")
+            .match_body(form.clone())
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let mut oauth = OAuthManager::with_base_url(
+            "test_client_id".to_string(),
+            vec!["chat:read".to_string()],
+            &format!("{}/oauth2", server.url()),
+        );
+        assert!(oauth.revoke_token().await.is_err());
+
+        oauth.set_token(create_test_token());
+        oauth.revoke_token().await?;
+        assert!(!oauth.is_authenticated());
+        revoked.assert_async().await;
+
+        // A token Twitch already forgot is as good as revoked
+        revoked.remove_async().await;
+        server
+            .mock("POST", "/oauth2/revoke")
+            .match_body(form)
+            .with_status(400)
+            .with_body(r#"{"status":400,"message":"Invalid token"}"#)
+            .create_async()
+            .await;
+        oauth.set_token(create_test_token());
+        oauth.revoke_token().await?;
+        assert!(!oauth.is_authenticated());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_authorization_code_flow() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let exchange = server
+            .mock("POST", "/oauth2/token")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("client_secret".into(), "test_secret".into()),
+                Matcher::UrlEncoded("code".into(), "test_code".into()),
+                Matcher::UrlEncoded("grant_type".into(), "authorization_code".into()),
+                Matcher::UrlEncoded("redirect_uri".into(), DEFAULT_REDIRECT_URI.into()),
+            ]))
+            .with_status(200)
+            .with_body(serde_json::to_string(&create_test_token())?)
+            .create_async()
+            .await;
+        // Refreshes send the secret too
+        let refresh = server
+            .mock("POST", "/oauth2/token")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex("test_secret".into()),
+                Matcher::Regex("refresh_token".into()),
+            ]))
+            .with_status(200)
+            .with_body(serde_json::to_string(&create_test_token())?)
+            .create_async()
+            .await;
+
+        let mut oauth = OAuthManager::with_base_url(
+            "test_client_id".to_string(),
+            vec!["chat:read".to_string(), "chat:edit".to_string()],
+            &format!("{}/oauth2", server.url()),
+        );
+        oauth.set_client_secret(Some("test_secret".to_string()), DEFAULT_REDIRECT_URI);
+        let url = oauth.authorize_url("xyz")?;
+        assert!(url.starts_with(&format!(
+            "{}/oauth2/authorize?response_type=code",
+            server.url()
+        )));
+        assert!(url.contains("scope=chat%3Aread+chat%3Aedit&state=xyz"));
+
+        // The browser's redirect is caught by the local server
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let redirect = format!("http://{}", listener.local_addr()?);
+        let browser = tokio::spawn(async move {
+            let client = Client::new();
+            let favicon = client
+                .get(format!("{}/favicon.ico", redirect))
+                .send()
+                .await?;
+            assert_eq!(favicon.status(), 404);
+            let page = client
+                .get(format!(
+                    "{}/?code=test_code&scope=chat%3Aread&state=xyz",
+                    redirect
+                ))
+                .send()
+                .await?;
+            assert_eq!(page.status(), 200);
+            anyhow::Ok(())
+        });
+        let code = receive_authorization_code(&listener, "xyz").await?;
+        browser.await??;
+        assert_eq!(code, "test_code");
+
+        oauth.exchange_code(&code).await?;
+        assert!(oauth.is_authenticated());
+        exchange.assert_async().await;
+        oauth.refresh_token().await?;
+        refresh.assert_async().await;
         Ok(())
     }
 }
@@ -0,0 +1,372 @@
+//! Rate limiting for outbound chat messages and tracking of Helix rate limits
+
+use chrono::{DateTime, TimeZone, Utc};
+use reqwest::header::HeaderMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::debug;
+
+/// Messages a regular (non-moderator) bot may send per window
+pub const CHAT_MESSAGES_PER_WINDOW: u32 = 20;
+
+/// Messages a bot that moderates the channel may send per window
+pub const MODERATOR_CHAT_MESSAGES_PER_WINDOW: u32 = 100;
+
+/// Length of the chat rate-limit window
+pub const CHAT_WINDOW: Duration = Duration::from_secs(30);
+
+/// Below this many remaining Helix points, requests are spread out until the reset
+const HELIX_SLOW_DOWN_BELOW: u32 = 10;
+
+/// Longest the bot waits before a Helix request
+const MAX_HELIX_DELAY: Duration = Duration::from_secs(60);
+
+/// A token bucket that refills continuously up to its capacity
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// Maximum number of tokens
+    capacity: u32,
+    /// Time it takes an empty bucket to refill completely
+    window: Duration,
+    /// Tokens added per second
+    refill_per_sec: f64,
+    /// Tokens currently available
+    tokens: f64,
+    /// When the bucket was last refilled
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket that allows `capacity` uses per `window`
+    ///
+    /// # Arguments
+    /// * `capacity` - Maximum number of tokens
+    /// * `window` - Time it takes an empty bucket to refill completely
+    ///
+    /// # Returns
+    /// A new TokenBucket instance
+    pub fn new(capacity: u32, window: Duration) -> Self {
+        TokenBucket {
+            capacity,
+            window,
+            refill_per_sec: capacity as f64 / window.as_secs_f64(),
+            tokens: capacity as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Change the capacity, keeping the same window
+    ///
+    /// Raising the capacity makes the extra tokens available right away.
+    ///
+    /// # Arguments
+    /// * `capacity` - The new maximum number of tokens
+    pub fn set_capacity(&mut self, capacity: u32) {
+        self.refill();
+        self.tokens =
+            (self.tokens + capacity as f64 - self.capacity as f64).clamp(0.0, capacity as f64);
+        self.capacity = capacity;
+        self.refill_per_sec = capacity as f64 / self.window.as_secs_f64();
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity as f64);
+        self.last_refill = now;
+    }
+
+    /// Get the number of whole tokens currently available
+    pub fn available(&mut self) -> u32 {
+        self.refill();
+        self.tokens.floor() as u32
+    }
+
+    /// Get the maximum number of tokens
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Take a token if one is available
+    ///
+    /// # Returns
+    /// Ok(()) if a token was taken, or Err with how long until one is available
+    pub fn try_acquire(&mut self) -> Result<(), Duration> {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            let missing = 1.0 - self.tokens;
+            Err(Duration::from_secs_f64(missing / self.refill_per_sec))
+        }
+    }
+}
+
+/// Paces outbound chat messages, queuing them in order when the limit is reached
+#[derive(Debug)]
+pub struct OutboundLimiter {
+    /// Tokens for sending messages
+    bucket: std::sync::Mutex<TokenBucket>,
+    /// Held while waiting for a token so messages go out in order
+    queue: tokio::sync::Mutex<()>,
+    /// Messages currently waiting for their turn
+    queued: AtomicUsize,
+    /// Messages per window as a regular user
+    normal_rate: u32,
+    /// Messages per window as a moderator
+    moderator_rate: u32,
+}
+
+impl Default for OutboundLimiter {
+    fn default() -> Self {
+        Self::new(CHAT_MESSAGES_PER_WINDOW, MODERATOR_CHAT_MESSAGES_PER_WINDOW)
+    }
+}
+
+impl OutboundLimiter {
+    /// Create a limiter that starts at the regular user rate
+    ///
+    /// # Arguments
+    /// * `normal_rate` - Messages per 30 seconds as a regular user
+    /// * `moderator_rate` - Messages per 30 seconds when the bot is a moderator
+    ///
+    /// # Returns
+    /// A new OutboundLimiter instance
+    pub fn new(normal_rate: u32, moderator_rate: u32) -> Self {
+        OutboundLimiter {
+            bucket: std::sync::Mutex::new(TokenBucket::new(normal_rate, CHAT_WINDOW)),
+            queue: tokio::sync::Mutex::new(()),
+            queued: AtomicUsize::new(0),
+            normal_rate,
+            moderator_rate,
+        }
+    }
+
+    /// Switch between the regular and moderator rates
+    ///
+    /// # Arguments
+    /// * `is_moderator` - Whether the bot moderates the channel
+    pub fn set_moderator(&self, is_moderator: bool) {
+        let rate = if is_moderator {
+            self.moderator_rate
+        } else {
+            self.normal_rate
+        };
+
+        let mut bucket = self.bucket.lock().unwrap();
+        if bucket.capacity() != rate {
+            debug!("Outbound chat rate set to {} per {:?}", rate, CHAT_WINDOW);
+            bucket.set_capacity(rate);
+        }
+    }
+
+    /// Wait for a turn to send a message
+    ///
+    /// Callers are served in the order they arrive.
+    pub async fn acquire(&self) {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        // Counted until the turn is over, even if the caller gives up waiting
+        let _queued = QueuedGuard(&self.queued);
+
+        let _turn = self.queue.lock().await;
+        loop {
+            let wait = match self.bucket.lock().unwrap().try_acquire() {
+                Ok(()) => return,
+                Err(wait) => wait,
+            };
+            debug!("Outbound rate limit reached, waiting {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Get the number of messages waiting for their turn to be sent
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of messages that can be sent right now
+    pub fn available(&self) -> u32 {
+        self.bucket.lock().unwrap().available()
+    }
+
+    /// Get the current number of messages allowed per window
+    pub fn capacity(&self) -> u32 {
+        self.bucket.lock().unwrap().capacity()
+    }
+}
+
+/// Takes a message off the outbound queue count when dropped
+struct QueuedGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The latest rate-limit state reported by the Helix API
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelixRateLimit {
+    /// Points the bucket holds when full
+    pub limit: u32,
+    /// Points left in the bucket
+    pub remaining: u32,
+    /// When the bucket will be full again
+    pub reset: DateTime<Utc>,
+}
+
+impl HelixRateLimit {
+    /// Parse the `Ratelimit-*` headers of a Helix response
+    ///
+    /// # Arguments
+    /// * `headers` - The response headers
+    ///
+    /// # Returns
+    /// The rate-limit state, or None if the headers are missing or invalid
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name)?.to_str().ok()?.trim().parse::<i64>().ok();
+
+        Some(HelixRateLimit {
+            limit: header("ratelimit-limit")?.try_into().ok()?,
+            remaining: header("ratelimit-remaining")?.try_into().ok()?,
+            reset: Utc.timestamp_opt(header("ratelimit-reset")?, 0).single()?,
+        })
+    }
+
+    /// How long to wait before the next request to avoid running out of points
+    ///
+    /// When few points remain, the time until the reset is shared evenly
+    /// between them so the bucket is never emptied before it refills.
+    ///
+    /// # Arguments
+    /// * `now` - The current time
+    ///
+    /// # Returns
+    /// The delay, or None if the request can go ahead immediately
+    pub fn delay_before_next(&self, now: DateTime<Utc>) -> Option<Duration> {
+        if self.remaining >= HELIX_SLOW_DOWN_BELOW || self.reset <= now {
+            return None;
+        }
+
+        let until_reset = (self.reset - now).to_std().ok()?;
+        Some((until_reset / (self.remaining + 1)).min(MAX_HELIX_DELAY))
+    }
+
+    /// How long until the bucket is full again
+    ///
+    /// # Arguments
+    /// * `now` - The current time
+    ///
+    /// # Returns
+    /// The time until the reset, capped so a bad clock can't stall the bot
+    pub fn until_reset(&self, now: DateTime<Utc>) -> Duration {
+        (self.reset - now)
+            .to_std()
+            .unwrap_or_default()
+            .min(MAX_HELIX_DELAY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use std::sync::Arc;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_token_bucket_refills() {
+        let mut bucket = TokenBucket::new(2, Duration::from_secs(10));
+
+        assert!(bucket.try_acquire().is_ok());
+        assert!(bucket.try_acquire().is_ok());
+        assert_eq!(bucket.available(), 0);
+
+        // One token refills every 5 seconds
+        let wait = bucket.try_acquire().unwrap_err();
+        assert_eq!(wait, Duration::from_secs(5));
+
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(bucket.available(), 1);
+
+        // Never exceeds capacity
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert_eq!(bucket.available(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_outbound_limiter_queues_overflow() {
+        let limiter = Arc::new(OutboundLimiter::new(2, 4));
+        let start = Instant::now();
+
+        // The first two go out immediately; the third waits for a refill
+        limiter.acquire().await;
+        limiter.acquire().await;
+        let third = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire().await }
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(limiter.queued(), 1);
+
+        third.await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(15));
+        assert_eq!(limiter.queued(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_outbound_limiter_moderator_rate() {
+        let limiter = OutboundLimiter::new(20, 100);
+        assert_eq!(limiter.capacity(), 20);
+
+        limiter.acquire().await;
+        limiter.set_moderator(true);
+        assert_eq!(limiter.capacity(), 100);
+        assert_eq!(limiter.available(), 99);
+
+        limiter.set_moderator(false);
+        assert_eq!(limiter.capacity(), 20);
+        assert_eq!(limiter.available(), 19);
+    }
+
+    #[test]
+    fn test_parse_helix_rate_limit_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Ratelimit-Limit", HeaderValue::from_static("800"));
+        headers.insert("Ratelimit-Remaining", HeaderValue::from_static("799"));
+        headers.insert("Ratelimit-Reset", HeaderValue::from_static("1700000000"));
+
+        let rate_limit = HelixRateLimit::from_headers(&headers).unwrap();
+        assert_eq!(rate_limit.limit, 800);
+        assert_eq!(rate_limit.remaining, 799);
+        assert_eq!(rate_limit.reset.timestamp(), 1700000000);
+
+        headers.remove("Ratelimit-Reset");
+        assert!(HelixRateLimit::from_headers(&headers).is_none());
+    }
+
+    #[test]
+    fn test_helix_delay_before_next() {
+        let now = Utc::now();
+        let rate_limit = |remaining, reset_in_secs| HelixRateLimit {
+            limit: 800,
+            remaining,
+            reset: now + chrono::Duration::seconds(reset_in_secs),
+        };
+
+        // Plenty of points left
+        assert_eq!(rate_limit(500, 30).delay_before_next(now), None);
+        // Few points left: spread them out until the reset
+        assert_eq!(
+            rate_limit(3, 20).delay_before_next(now),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            rate_limit(0, 10).delay_before_next(now),
+            Some(Duration::from_secs(10))
+        );
+        // The bucket has already refilled
+        assert_eq!(rate_limit(0, -5).delay_before_next(now), None);
+    }
+}
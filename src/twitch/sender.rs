@@ -0,0 +1,122 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use twitch_irc::message::TwitchUserBasics;
+
+use crate::twitch::TwitchClient;
+
+/// Something that can post chat messages, like the Twitch client or a mock offline
+#[async_trait]
+pub trait MessageSender: Send + Sync {
+    /// Send a message to a channel
+    ///
+    /// # Arguments
+    /// * `channel` - The channel to send to
+    /// * `message` - The message to send
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    async fn say(&self, channel: &str, message: &str) -> Result<()>;
+
+    /// Send a message as a reply to another message
+    ///
+    /// Senders without threaded replies post a normal message.
+    ///
+    /// # Arguments
+    /// * `channel` - The channel to send to
+    /// * `message` - The message to send
+    /// * `reply_to` - The ID of the message being replied to
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    async fn reply(&self, channel: &str, message: &str, _reply_to: &str) -> Result<()> {
+        self.say(channel, message).await
+    }
+
+    /// Whisper a message to a user
+    ///
+    /// Senders without whispers show it in the channel instead.
+    ///
+    /// # Arguments
+    /// * `channel` - The channel the whisper was asked for in
+    /// * `user` - The user to whisper to
+    /// * `message` - The message to send
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    async fn whisper(&self, channel: &str, user: &TwitchUserBasics, message: &str) -> Result<()> {
+        let whisper = format!("(whisper to {}) {}", user.name, message);
+        self.say(channel, &whisper).await
+    }
+}
+
+/// Something that can leave chat channels, like the Twitch client
+pub trait ChannelParter: Send + Sync {
+    /// Leave a channel
+    ///
+    /// # Arguments
+    /// * `channel` - The channel name to leave
+    ///
+    /// # Returns
+    /// Whether the bot was in the channel
+    fn part_channel(&self, channel: &str) -> bool;
+}
+
+impl ChannelParter for TwitchClient {
+    fn part_channel(&self, channel: &str) -> bool {
+        TwitchClient::part_channel(self, channel)
+    }
+}
+
+#[async_trait]
+impl MessageSender for TwitchClient {
+    async fn say(&self, channel: &str, message: &str) -> Result<()> {
+        let username = self.username().to_string();
+        self.clone()
+            .send_message(channel, message, &username)
+            .await?;
+        Ok(())
+    }
+
+    async fn reply(&self, channel: &str, message: &str, reply_to: &str) -> Result<()> {
+        let username = self.username().to_string();
+        self.clone()
+            .send_reply(channel, message, reply_to, &username)
+            .await?;
+        Ok(())
+    }
+
+    async fn whisper(&self, _channel: &str, user: &TwitchUserBasics, message: &str) -> Result<()> {
+        self.send_whisper(&user.id, message).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::users::MockTwitchClient;
+
+    #[tokio::test]
+    async fn test_default_reply_and_whisper_post_in_chat() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let user = TwitchUserBasics {
+            id: "123".to_string(),
+            login: "test_user".to_string(),
+            name: "Test_User".to_string(),
+        };
+
+        client.reply("test_channel", "pong", "abc").await?;
+        client.whisper("test_channel", &user, "your code").await?;
+        assert_eq!(
+            client.sent_messages(),
+            vec![
+                ("test_channel".to_string(), "pong".to_string()),
+                (
+                    "test_channel".to_string(),
+                    "(whisper to Test_User) your code".to_string()
+                ),
+            ]
+        );
+        Ok(())
+    }
+}
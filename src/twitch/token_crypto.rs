@@ -0,0 +1,140 @@
+//! Encryption of the stored OAuth token
+//!
+//! Tokens are encrypted with ChaCha20-Poly1305 using a key derived from a
+//! passphrase with PBKDF2-HMAC-SHA256. The encrypted token is stored as a small
+//! JSON envelope so it can be told apart from a legacy plaintext token file.
+
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+/// Version tag written into every encrypted envelope
+const ENVELOPE_VERSION: u32 = 1;
+/// Number of PBKDF2 rounds used to derive the key from the passphrase
+const PBKDF2_ROUNDS: u32 = 100_000;
+/// Length of the random salt in bytes
+const SALT_LEN: usize = 16;
+/// Length of the ChaCha20-Poly1305 nonce in bytes
+const NONCE_LEN: usize = 12;
+
+/// On-disk representation of an encrypted token
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EncryptedToken {
+    /// Envelope format version
+    pub version: u32,
+    /// Base64-encoded PBKDF2 salt
+    pub salt: String,
+    /// Base64-encoded AEAD nonce
+    pub nonce: String,
+    /// Base64-encoded ciphertext (including the authentication tag)
+    pub ciphertext: String,
+}
+
+/// Derive a 256-bit key from a passphrase and salt
+fn derive_key(passphrase: &str, salt: &[u8]) -> Key {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    Key::from(key)
+}
+
+/// Encrypt a plaintext token with the given passphrase
+///
+/// # Arguments
+/// * `plaintext` - The serialized token to encrypt
+/// * `passphrase` - The passphrase to derive the encryption key from
+///
+/// # Returns
+/// The JSON-serialized encrypted envelope
+pub fn encrypt(plaintext: &str, passphrase: &str) -> Result<String> {
+    let mut rng = rand::rng();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill_bytes(&mut salt);
+    rng.fill_bytes(&mut nonce);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|_| anyhow!("Failed to encrypt OAuth token"))?;
+
+    let envelope = EncryptedToken {
+        version: ENVELOPE_VERSION,
+        salt: BASE64.encode(salt),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    };
+
+    Ok(serde_json::to_string_pretty(&envelope)?)
+}
+
+/// Decrypt an encrypted envelope with the given passphrase
+///
+/// # Arguments
+/// * `envelope` - The parsed encrypted envelope
+/// * `passphrase` - The passphrase used when the token was encrypted
+///
+/// # Returns
+/// The decrypted plaintext token
+pub fn decrypt(envelope: &EncryptedToken, passphrase: &str) -> Result<String> {
+    if envelope.version != ENVELOPE_VERSION {
+        return Err(anyhow!(
+            "Unsupported encrypted token version: {}",
+            envelope.version
+        ));
+    }
+
+    let salt = BASE64.decode(&envelope.salt)?;
+    let nonce = BASE64.decode(&envelope.nonce)?;
+    let ciphertext = BASE64.decode(&envelope.ciphertext)?;
+
+    if nonce.len() != NONCE_LEN {
+        return Err(anyhow!("Encrypted token has an invalid nonce"));
+    }
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt));
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| anyhow!("Failed to decrypt OAuth token (wrong TOKEN_ENCRYPTION_KEY?)"))?;
+
+    Ok(String::from_utf8(plaintext)?)
+}
+
+/// Try to parse file contents as an encrypted envelope
+///
+/// # Returns
+/// Some(envelope) if the contents are an encrypted token, None for plaintext
+pub fn parse_envelope(contents: &str) -> Option<EncryptedToken> {
+    serde_json::from_str(contents).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        let encrypted = encrypt("{\"access_token\":\"abc\"}", "hunter2").unwrap();
+        assert!(!encrypted.contains("access_token"));
+
+        let envelope = parse_envelope(&encrypted).unwrap();
+        let decrypted = decrypt(&envelope, "hunter2").unwrap();
+        assert_eq!(decrypted, "{\"access_token\":\"abc\"}");
+    }
+
+    #[test]
+    fn test_decrypt_with_wrong_passphrase_fails() {
+        let encrypted = encrypt("secret", "right").unwrap();
+        let envelope = parse_envelope(&encrypted).unwrap();
+        assert!(decrypt(&envelope, "wrong").is_err());
+    }
+
+    #[test]
+    fn test_plaintext_is_not_an_envelope() {
+        assert!(parse_envelope("{\"access_token\":\"abc\",\"expires_in\":1}").is_none());
+    }
+}
@@ -1,106 +1,281 @@
+mod shoutout;
+mod stats;
+mod store;
 mod welcome;
 
 use anyhow::Result;
-use std::collections::HashSet;
+use chrono::{DateTime, Duration, Utc};
 use std::path::Path;
-use std::sync::RwLock;
+use std::sync::Mutex;
 use tokio::fs;
-use tracing::{debug, info};
+use tracing::{info, warn};
 
-pub use welcome::WelcomeService;
+pub use shoutout::{AutoShoutoutService, DEFAULT_SHOUTOUT_MESSAGE, format_shoutout};
+pub use welcome::MockTwitchClient;
+pub use welcome::{
+    MAX_WELCOME_GRACE_SECS, WELCOME_MESSAGES_FILE, WelcomeMode, WelcomeService,
+    read_welcome_templates, templates_missing_username,
+};
+
+use crate::store::write_atomic;
+use store::UserStore;
+
+/// How long a user must be away before they count as returning
+const DEFAULT_RETURNING_AFTER_HOURS: i64 = 12;
+
+/// What the bot knows about a user who has chatted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserRecord {
+    /// The user's login, if it has been seen
+    pub login: Option<String>,
+    /// When the user was first seen chatting (None for users migrated from the old text file)
+    pub first_seen: Option<DateTime<Utc>>,
+    /// When the user was last seen chatting (None for users migrated from the old text file)
+    pub last_seen: Option<DateTime<Utc>>,
+    /// How many messages the user has sent
+    pub message_count: u64,
+    /// When the bot last welcomed the user, if ever
+    pub welcomed_at: Option<DateTime<Utc>>,
+    /// The first message the user sent, if first words capture was on
+    pub first_message: Option<String>,
+}
+
+/// How a chat message relates to the sender's chat history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatterStatus {
+    /// The user has never chatted before
+    FirstTime,
+    /// The user chatted recently, within the same session
+    SameSession,
+    /// The user is known but hasn't chatted for a while
+    Returning,
+}
 
 /// User manager that tracks users who have interacted with the chat
 pub struct UserManager {
-    /// Set of user IDs who have already chatted at least once
-    known_users: RwLock<HashSet<String>>,
-    /// Path to file for persistence
-    users_file_path: String,
+    /// Known users; an in-memory database until `load` opens the file
+    store: Mutex<UserStore>,
+    /// Path to the SQLite database for persistence
+    db_path: String,
+    /// How long a user must be away before they count as returning
+    returning_after: Duration,
 }
 
 impl UserManager {
     /// Create a new user manager
     ///
     /// # Arguments
-    /// * `users_file_path` - Path to file for persisting known users
+    /// * `db_path` - Path to the SQLite database for persisting known users
     ///
     /// # Returns
-    /// A new UserManager instance
-    pub fn new(users_file_path: &str) -> Self {
-        UserManager {
-            known_users: RwLock::new(HashSet::new()),
-            users_file_path: users_file_path.to_string(),
-        }
+    /// A new UserManager instance, or an error if the in-memory database can't be set up
+    pub fn new(db_path: &str) -> Result<Self> {
+        Ok(UserManager {
+            store: Mutex::new(UserStore::open_in_memory()?),
+            db_path: db_path.to_string(),
+            returning_after: Duration::hours(DEFAULT_RETURNING_AFTER_HOURS),
+        })
     }
 
-    /// Load known users from file
+    /// Set how long a user must be away before they count as returning
+    ///
+    /// # Arguments
+    /// * `returning_after` - The minimum gap since the user was last seen
+    #[allow(dead_code)]
+    pub fn set_returning_after(&mut self, returning_after: Duration) {
+        self.returning_after = returning_after;
+    }
+
+    /// Open the known users database, creating it if needed
+    ///
+    /// If a `.txt` file from older versions sits next to the database, its
+    /// users are imported and the file is renamed to `.txt.migrated`.
     ///
     /// # Returns
     /// A Result indicating success or failure
     pub async fn load(&self) -> Result<()> {
-        let path = Path::new(&self.users_file_path);
+        let path = Path::new(&self.db_path);
 
-        // If the file doesn't exist, return without error
-        if !path.exists() {
-            debug!("Known users file doesn't exist yet. Starting with empty set.");
-            return Ok(());
+        // Ensure the directory exists
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+            && !parent.exists()
+        {
+            fs::create_dir_all(parent).await?;
         }
 
-        // Read and parse the file
-        let content = fs::read_to_string(path).await?;
-        let mut users = HashSet::new();
+        let mut store = UserStore::open(path)?;
 
-        for line in content.lines() {
-            let trimmed = line.trim();
-            if !trimmed.is_empty() {
-                users.insert(trimmed.to_string());
-            }
-        }
+        // Migrate the old newline-delimited known users file
+        let legacy_path = path.with_extension("txt");
+        if legacy_path != path && legacy_path.exists() {
+            let content = fs::read_to_string(&legacy_path).await?;
+            let imported = store.import_legacy(&content)?;
 
-        // Update the known users set
-        {
-            let mut known_users = self.known_users.write().unwrap();
-            *known_users = users;
+            let mut migrated_path = legacy_path.clone().into_os_string();
+            migrated_path.push(".migrated");
+            fs::rename(&legacy_path, &migrated_path).await?;
+
+            info!(
+                "Migrated {} known users from {}",
+                imported,
+                legacy_path.display()
+            );
         }
 
-        info!(
-            "Loaded {} known users from {}",
-            self.known_users.read().unwrap().len(),
-            self.users_file_path
-        );
+        let count = store.count()?;
+        *self.store.lock().unwrap() = store;
+
+        info!("Loaded {} known users from {}", count, self.db_path);
         Ok(())
     }
 
-    /// Save known users to file
+    /// Check if a user has chatted before, without marking them as seen
+    ///
+    /// # Arguments
+    /// * `user_id` - The Twitch user ID to check
     ///
     /// # Returns
-    /// A Result indicating success or failure
-    pub async fn save(&self) -> Result<()> {
-        let users = {
-            let known_users = self.known_users.read().unwrap();
-            let mut users: Vec<String> = known_users.iter().cloned().collect();
-            users.sort(); // Sort for consistent file output
-            users
+    /// true if the user is already known, false otherwise
+    pub fn is_known_user(&self, user_id: &str) -> bool {
+        match self.get_user(user_id) {
+            Ok(record) => record.is_some(),
+            Err(e) => {
+                warn!("Failed to look up user {}: {}", user_id, e);
+                false
+            }
+        }
+    }
+
+    /// Get what's known about a user
+    ///
+    /// # Arguments
+    /// * `user_id` - The Twitch user ID to look up
+    ///
+    /// # Returns
+    /// The user's record, or None if they've never chatted
+    pub fn get_user(&self, user_id: &str) -> Result<Option<UserRecord>> {
+        self.store.lock().unwrap().get(user_id)
+    }
+
+    /// Get what's known about a user by their login
+    ///
+    /// # Arguments
+    /// * `login` - The login to look up (case-insensitive)
+    ///
+    /// # Returns
+    /// The user's record, or None if they've never chatted
+    pub fn get_user_by_login(&self, login: &str) -> Result<Option<UserRecord>> {
+        self.store.lock().unwrap().get_by_login(login)
+    }
+
+    /// Record that a user chatted, classifying them against their history
+    ///
+    /// # Arguments
+    /// * `user_id` - The Twitch user ID that sent a message
+    /// * `login` - The user's login
+    ///
+    /// # Returns
+    /// Whether this is their first message, a returning visit, or the same session
+    pub fn record_chat(&self, user_id: &str, login: &str) -> Result<ChatterStatus> {
+        self.record_chat_at(user_id, Some(login), Utc::now())
+    }
+
+    /// Record that a user chatted at the given time
+    fn record_chat_at(
+        &self,
+        user_id: &str,
+        login: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<ChatterStatus> {
+        let store = self.store.lock().unwrap();
+
+        let status = match store.get(user_id)? {
+            None => ChatterStatus::FirstTime,
+            Some(record) => match record.last_seen {
+                Some(last_seen) if now - last_seen < self.returning_after => {
+                    ChatterStatus::SameSession
+                }
+                // Users migrated from the old text file have no history this session
+                _ => ChatterStatus::Returning,
+            },
         };
 
-        // Create the content as a sorted list of user IDs
-        let content = users.join("\n");
+        store.record_message(user_id, login, now)?;
+        Ok(status)
+    }
 
-        // Ensure the directory exists
-        if let Some(parent) = Path::new(&self.users_file_path).parent() {
-            if !parent.exists() {
-                fs::create_dir_all(parent).await?;
-            }
-        }
+    /// Record that a user was just welcomed
+    ///
+    /// # Arguments
+    /// * `user_id` - The Twitch user ID that was welcomed
+    pub fn mark_welcomed(&self, user_id: &str) -> Result<()> {
+        self.store
+            .lock()
+            .unwrap()
+            .mark_welcomed(user_id, Utc::now())
+    }
 
-        // Write to the file
-        fs::write(&self.users_file_path, content).await?;
+    /// Store the first message a user sent, keeping any earlier one
+    ///
+    /// # Arguments
+    /// * `user_id` - The Twitch user ID that sent the message
+    /// * `text` - The message text
+    pub fn set_first_message(&self, user_id: &str, text: &str) -> Result<()> {
+        self.store.lock().unwrap().set_first_message(user_id, text)
+    }
 
-        debug!(
-            "Saved {} known users to {}",
-            users.len(),
-            self.users_file_path
-        );
-        Ok(())
+    /// Forget a user so their next message counts as their first
+    ///
+    /// # Arguments
+    /// * `user_id` - The Twitch user ID to forget
+    ///
+    /// # Returns
+    /// true if the user was known
+    pub fn forget_user(&self, user_id: &str) -> Result<bool> {
+        self.store.lock().unwrap().remove(user_id)
+    }
+
+    /// Add watch time for viewers who were present
+    ///
+    /// # Arguments
+    /// * `viewers` - Each present viewer's user ID and login
+    /// * `minutes` - How many minutes each of them watched
+    pub fn add_watch_minutes(&self, viewers: &[(String, String)], minutes: u64) -> Result<()> {
+        self.store
+            .lock()
+            .unwrap()
+            .add_watch_minutes(viewers, minutes)
+    }
+
+    /// Look up how long a viewer has watched
+    ///
+    /// # Arguments
+    /// * `login` - The viewer's login (case-insensitive)
+    ///
+    /// # Returns
+    /// The minutes watched, or None if they've never been seen watching
+    pub fn watch_minutes_by_login(&self, login: &str) -> Result<Option<u64>> {
+        self.store.lock().unwrap().watch_minutes_by_login(login)
+    }
+
+    /// Write every known user's chat statistics to a CSV file
+    ///
+    /// The file is replaced atomically, so an interrupted export leaves any
+    /// earlier export intact rather than cut off.
+    ///
+    /// # Arguments
+    /// * `path` - Where to write the CSV
+    ///
+    /// # Returns
+    /// The number of users exported
+    pub fn export_stats(&self, path: &Path) -> Result<usize> {
+        // Snapshot the users so the database isn't locked while writing
+        let users = self.store.lock().unwrap().list()?;
+        let mut csv = Vec::new();
+        stats::write_stats_csv(&users, &mut csv)?;
+        write_atomic(path, csv)?;
+        Ok(users.len())
     }
 
     /// Check if a user is a first-time chatter
@@ -110,16 +285,14 @@ impl UserManager {
     ///
     /// # Returns
     /// true if this is the first time seeing this user, false otherwise
+    #[allow(dead_code)]
     pub fn is_first_time_chatter(&self, user_id: &str) -> bool {
-        let mut known_users = self.known_users.write().unwrap();
-
-        if known_users.contains(user_id) {
-            // User already known
-            false
-        } else {
-            // New user! Add them to our known users set
-            known_users.insert(user_id.to_string());
-            true
+        match self.record_chat_at(user_id, None, Utc::now()) {
+            Ok(status) => status == ChatterStatus::FirstTime,
+            Err(e) => {
+                warn!("Failed to record chat from {}: {}", user_id, e);
+                false
+            }
         }
     }
 }
@@ -127,12 +300,11 @@ impl UserManager {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
+    use tempfile::tempdir;
 
     #[test]
     fn test_first_time_chatter() {
-        let user_manager = UserManager::new("test_users.txt");
+        let user_manager = UserManager::new("test_users.db").unwrap();
 
         // First time should be true
         assert!(user_manager.is_first_time_chatter("user1"));
@@ -144,35 +316,135 @@ mod tests {
         assert!(user_manager.is_first_time_chatter("user2"));
     }
 
+    #[test]
+    fn test_is_known_user_does_not_mark_user() {
+        let user_manager = UserManager::new("test_users.db").unwrap();
+
+        assert!(!user_manager.is_known_user("user1"));
+        assert!(!user_manager.is_known_user("user1"));
+
+        user_manager.is_first_time_chatter("user1");
+        assert!(user_manager.is_known_user("user1"));
+    }
+
     #[tokio::test]
-    async fn test_load_save_users() -> Result<()> {
-        // Create a temporary file
-        let mut temp_file = NamedTempFile::new()?;
-        let initial_users = "user1\nuser2\nuser3";
-        write!(temp_file, "{}", initial_users)?;
+    async fn test_users_persist_across_restarts() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("known_users.db");
+        let db_path = db_path.to_str().unwrap();
+
+        let user_manager = UserManager::new(db_path)?;
+        user_manager.load().await?;
+        assert!(user_manager.is_first_time_chatter("user1"));
+        user_manager.record_chat("user1", "first_user")?;
 
-        let temp_path = temp_file.path().to_str().unwrap().to_string();
-        let user_manager = UserManager::new(&temp_path);
+        // Users persist across restarts
+        let reloaded = UserManager::new(db_path)?;
+        reloaded.load().await?;
+        let record = reloaded.get_user("user1")?.unwrap();
+        assert_eq!(record.login.as_deref(), Some("first_user"));
+        assert_eq!(record.message_count, 2);
+        assert!(record.first_seen.is_some());
+        assert!(!reloaded.is_first_time_chatter("user1"));
 
-        // Load the users
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_stats() -> Result<()> {
+        let dir = tempdir()?;
+        let manager = UserManager::new(dir.path().join("users.db").to_str().unwrap())?;
+        manager.load().await?;
+
+        manager.record_chat("1", "welcomed_user")?;
+        manager.mark_welcomed("1")?;
+        manager.record_chat("2", "lurker")?;
+        manager.record_chat("2", "lurker")?;
+
+        let csv_path = dir.path().join("stats.csv");
+        assert_eq!(manager.export_stats(&csv_path)?, 2);
+
+        let csv = std::fs::read_to_string(&csv_path)?;
+        let rows: Vec<Vec<&str>> = csv.lines().map(|line| line.split(',').collect()).collect();
+        assert_eq!(rows[0][..3], ["user_id", "login", "message_count"]);
+        assert_eq!(rows[1][..3], ["2", "lurker", "2"]);
+        assert_eq!(rows[1][5], "false");
+        assert_eq!(rows[2][..3], ["1", "welcomed_user", "1"]);
+        assert_eq!(rows[2][5], "true");
+
+        // Exporting again replaces the file in one step
+        manager.record_chat("3", "newcomer")?;
+        assert_eq!(manager.export_stats(&csv_path)?, 3);
+        assert_eq!(std::fs::read_to_string(&csv_path)?.lines().count(), 4);
+        assert!(!dir.path().join("stats.csv.tmp").exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_migrates_legacy_file() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let legacy_path = temp_dir.path().join("known_users.txt");
+        fs::write(&legacy_path, "user1\nuser2\nuser3").await?;
+
+        let db_path = temp_dir.path().join("known_users.db");
+        let user_manager = UserManager::new(db_path.to_str().unwrap())?;
         user_manager.load().await?;
 
-        // Check if the users were loaded
+        // Check if the users were migrated
         assert!(!user_manager.is_first_time_chatter("user1"));
         assert!(!user_manager.is_first_time_chatter("user2"));
         assert!(!user_manager.is_first_time_chatter("user3"));
         assert!(user_manager.is_first_time_chatter("user4"));
 
-        // Save the users
-        user_manager.save().await?;
+        // The old file is kept aside so it isn't imported twice
+        assert!(!legacy_path.exists());
+        assert!(temp_dir.path().join("known_users.txt.migrated").exists());
 
-        // Read the file and check its contents
-        let content = fs::read_to_string(&temp_path).await?;
-        let mut lines: Vec<&str> = content.lines().collect();
-        lines.sort();
+        Ok(())
+    }
 
-        assert_eq!(lines, vec!["user1", "user2", "user3", "user4"]);
+    #[test]
+    fn test_record_chat_classifies_chatters() -> Result<()> {
+        let user_manager = UserManager::new("test_users.db")?;
+        let start = Utc::now();
+
+        assert_eq!(
+            user_manager.record_chat_at("user1", None, start)?,
+            ChatterStatus::FirstTime
+        );
+        assert_eq!(
+            user_manager.record_chat_at("user1", None, start + Duration::minutes(30))?,
+            ChatterStatus::SameSession
+        );
+        // The gap is measured from the last message, not the first
+        assert_eq!(
+            user_manager.record_chat_at("user1", None, start + Duration::hours(12))?,
+            ChatterStatus::SameSession
+        );
+        assert_eq!(
+            user_manager.record_chat_at("user1", None, start + Duration::days(2))?,
+            ChatterStatus::Returning
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_legacy_users_are_returning() -> Result<()> {
+        let temp_dir = tempdir()?;
+        fs::write(temp_dir.path().join("known_users.txt"), "legacy_user\n").await?;
 
+        let db_path = temp_dir.path().join("known_users.db");
+        let user_manager = UserManager::new(db_path.to_str().unwrap())?;
+        user_manager.load().await?;
+
+        assert_eq!(
+            user_manager.record_chat("legacy_user", "legacy_user")?,
+            ChatterStatus::Returning
+        );
+        assert_eq!(
+            user_manager.record_chat("legacy_user", "legacy_user")?,
+            ChatterStatus::SameSession
+        );
         Ok(())
     }
 }
@@ -0,0 +1,162 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+use twitch_irc::message::PrivmsgMessage;
+
+use crate::twitch::{MessageSender, TwitchClient};
+
+/// Default promo line posted for a shoutout (use {login} as placeholder)
+pub const DEFAULT_SHOUTOUT_MESSAGE: &str = "Go check out @{login} at https://twitch.tv/{login}!";
+
+/// Format a shoutout promo line
+///
+/// # Arguments
+/// * `template` - The message template (use {login} as placeholder)
+/// * `login` - The login of the streamer being shouted out
+///
+/// # Returns
+/// The formatted promo line
+pub fn format_shoutout(template: &str, login: &str) -> String {
+    template.replace("{login}", login)
+}
+
+/// Something that can give a streamer a shoutout in a channel
+#[async_trait]
+pub trait Shouter: Send + Sync {
+    /// Give a streamer a shoutout
+    ///
+    /// # Arguments
+    /// * `channel` - The channel to post the shoutout in
+    /// * `login` - The login of the streamer to shout out
+    async fn shoutout(&self, channel: &str, login: &str) -> Result<()>;
+}
+
+#[async_trait]
+impl Shouter for TwitchClient {
+    async fn shoutout(&self, channel: &str, login: &str) -> Result<()> {
+        let message = format_shoutout(DEFAULT_SHOUTOUT_MESSAGE, login);
+        self.say(channel, &message).await
+    }
+}
+
+/// Automatically shouts out configured streamers the first time they chat each stream
+pub struct AutoShoutoutService {
+    /// Performs the actual shoutout
+    shouter: Arc<dyn Shouter>,
+    /// Logins of the streamers to shout out (lowercase)
+    streamers: HashSet<String>,
+    /// (channel, login) pairs already shouted out this stream
+    shouted: Mutex<HashSet<(String, String)>>,
+}
+
+impl AutoShoutoutService {
+    /// Create a new auto-shoutout service
+    ///
+    /// # Arguments
+    /// * `shouter` - Performs the actual shoutout
+    /// * `streamers` - Logins of the streamers to shout out
+    ///
+    /// # Returns
+    /// A new AutoShoutoutService instance
+    pub fn new(shouter: Arc<dyn Shouter>, streamers: Vec<String>) -> Self {
+        AutoShoutoutService {
+            shouter,
+            streamers: streamers
+                .iter()
+                .map(|login| login.trim().trim_start_matches('@').to_lowercase())
+                .filter(|login| !login.is_empty())
+                .collect(),
+            shouted: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Forget who has been shouted out, e.g. when the stream goes live
+    pub fn reset(&self) {
+        self.shouted.lock().unwrap().clear();
+    }
+
+    /// Shout out the sender if they're a listed streamer not yet shouted out this stream
+    ///
+    /// # Arguments
+    /// * `msg` - The chat message to process
+    ///
+    /// # Returns
+    /// true if a shoutout was given
+    pub async fn process_message(&self, msg: &PrivmsgMessage) -> Result<bool> {
+        let login = msg.sender.login.to_lowercase();
+        if !self.streamers.contains(&login) {
+            return Ok(false);
+        }
+
+        let key = (msg.channel_login.clone(), login.clone());
+        if !self.shouted.lock().unwrap().insert(key.clone()) {
+            return Ok(false);
+        }
+
+        info!("Auto-shoutout for {} in {}", login, msg.channel_login);
+        if let Err(e) = self.shouter.shoutout(&msg.channel_login, &login).await {
+            // Allow another attempt on their next message
+            self.shouted.lock().unwrap().remove(&key);
+            return Err(e);
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::create_test_privmsg_with_badges;
+
+    /// Shouter that records who it shouted out
+    #[derive(Default)]
+    struct MockShouter {
+        shoutouts: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Shouter for MockShouter {
+        async fn shoutout(&self, _channel: &str, login: &str) -> Result<()> {
+            self.shoutouts.lock().unwrap().push(login.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_format_shoutout() {
+        assert_eq!(
+            format_shoutout(DEFAULT_SHOUTOUT_MESSAGE, "friend"),
+            "Go check out @friend at https://twitch.tv/friend!"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_listed_streamer_is_shouted_out_once() -> Result<()> {
+        let shouter = Arc::new(MockShouter::default());
+        let service = AutoShoutoutService::new(shouter.clone(), vec!["@Test_User".to_string()]);
+        let msg = create_test_privmsg_with_badges("hey!", &[]);
+
+        assert!(service.process_message(&msg).await?);
+        assert!(!service.process_message(&msg).await?);
+        assert_eq!(*shouter.shoutouts.lock().unwrap(), vec!["test_user"]);
+
+        // A new stream shouts them out again
+        service.reset();
+        assert!(service.process_message(&msg).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unlisted_chatter_is_ignored() -> Result<()> {
+        let shouter = Arc::new(MockShouter::default());
+        let service = AutoShoutoutService::new(shouter.clone(), vec!["someone_else".to_string()]);
+        let msg = create_test_privmsg_with_badges("hey!", &[]);
+
+        assert!(!service.process_message(&msg).await?);
+        assert!(shouter.shoutouts.lock().unwrap().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+//! CSV export of per-user chat statistics
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::io::Write;
+
+use crate::users::UserRecord;
+
+/// Header row of the exported CSV
+const HEADER: &str =
+    "user_id,login,message_count,first_seen,last_seen,welcomed,welcomed_at,first_message";
+
+/// Write per-user chat statistics as CSV
+///
+/// # Arguments
+/// * `users` - Each user's ID with their record
+/// * `out` - Where to write the CSV
+///
+/// # Returns
+/// A Result indicating success or failure
+pub fn write_stats_csv<W: Write>(users: &[(String, UserRecord)], mut out: W) -> Result<()> {
+    writeln!(out, "{}", HEADER)?;
+
+    for (user_id, record) in users {
+        let fields = [
+            user_id.clone(),
+            record.login.clone().unwrap_or_default(),
+            record.message_count.to_string(),
+            format_timestamp(record.first_seen),
+            format_timestamp(record.last_seen),
+            record.welcomed_at.is_some().to_string(),
+            format_timestamp(record.welcomed_at),
+            record.first_message.clone().unwrap_or_default(),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| escape_field(field)).collect();
+        writeln!(out, "{}", row.join(","))?;
+    }
+
+    Ok(())
+}
+
+/// Format an optional timestamp, leaving it empty when unknown
+fn format_timestamp(timestamp: Option<DateTime<Utc>>) -> String {
+    timestamp.map(|t| t.to_rfc3339()).unwrap_or_default()
+}
+
+/// Quote a field if it contains a comma, quote, or newline
+fn escape_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_write_stats_csv() -> Result<()> {
+        let first = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let last = Utc.with_ymd_and_hms(2024, 1, 2, 18, 30, 0).unwrap();
+        let users = vec![
+            (
+                "123".to_string(),
+                UserRecord {
+                    login: Some("chatty".to_string()),
+                    first_seen: Some(first),
+                    last_seen: Some(last),
+                    message_count: 42,
+                    welcomed_at: Some(first),
+                    first_message: Some("hi, came from a raid".to_string()),
+                },
+            ),
+            (
+                "456".to_string(),
+                UserRecord {
+                    login: None,
+                    first_seen: None,
+                    last_seen: None,
+                    message_count: 0,
+                    welcomed_at: None,
+                    first_message: None,
+                },
+            ),
+        ];
+
+        let mut out = Vec::new();
+        write_stats_csv(&users, &mut out)?;
+
+        let csv = String::from_utf8(out)?;
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], HEADER);
+        assert_eq!(
+            lines[1],
+            "123,chatty,42,2024-01-01T12:00:00+00:00,2024-01-02T18:30:00+00:00,true,2024-01-01T12:00:00+00:00,\"hi, came from a raid\""
+        );
+        assert_eq!(lines[2], "456,,0,,,false,,");
+        Ok(())
+    }
+
+    #[test]
+    fn test_escape_field() {
+        assert_eq!(escape_field("plain"), "plain");
+        assert_eq!(escape_field("a,b"), "\"a,b\"");
+        assert_eq!(escape_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,458 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use std::path::Path;
+
+use crate::users::UserRecord;
+
+/// Schema for the known users table
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS users (
+    user_id TEXT PRIMARY KEY,
+    login TEXT,
+    first_seen TEXT,
+    last_seen TEXT,
+    message_count INTEGER NOT NULL DEFAULT 0,
+    welcomed_at TEXT,
+    first_message TEXT
+)";
+
+/// Schema for watch time, kept apart from `users` so viewers who only lurk
+/// don't count as having chatted
+const WATCH_TIME_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS watch_time (
+    user_id TEXT PRIMARY KEY,
+    login TEXT NOT NULL,
+    minutes INTEGER NOT NULL DEFAULT 0
+)";
+
+/// Columns added after the first release, created on older databases when opened
+const ADDED_COLUMNS: &[(&str, &str)] = &[("welcomed_at", "TEXT"), ("first_message", "TEXT")];
+
+/// Columns selected to build a UserRecord
+const RECORD_COLUMNS: &str =
+    "login, first_seen, last_seen, message_count, welcomed_at, first_message";
+
+/// SQLite-backed storage for known users
+pub struct UserStore {
+    conn: Connection,
+}
+
+impl UserStore {
+    /// Open (or create) a user database file
+    ///
+    /// Every chat message updates a row, so the database is written through a
+    /// write-ahead log: each update is a cheap append rather than a rewrite
+    /// synced to disk, and a crash still keeps everything already committed.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the SQLite database file
+    ///
+    /// # Returns
+    /// A Result containing the UserStore if successful
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        Self::init(conn)
+    }
+
+    /// Open a temporary database that lives only in memory
+    ///
+    /// # Returns
+    /// A Result containing the UserStore if successful
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute(SCHEMA, [])?;
+        conn.execute(WATCH_TIME_SCHEMA, [])?;
+
+        // Databases created by older versions lack the newer columns
+        for (column, column_type) in ADDED_COLUMNS {
+            let exists = conn
+                .prepare("SELECT 1 FROM pragma_table_info('users') WHERE name = ?1")?
+                .exists(params![column])?;
+            if !exists {
+                conn.execute(
+                    &format!("ALTER TABLE users ADD COLUMN {} {}", column, column_type),
+                    [],
+                )?;
+            }
+        }
+
+        Ok(UserStore { conn })
+    }
+
+    /// Look up a user's record
+    ///
+    /// # Arguments
+    /// * `user_id` - The Twitch user ID to look up
+    ///
+    /// # Returns
+    /// The user's record, or None if they aren't known
+    pub fn get(&self, user_id: &str) -> Result<Option<UserRecord>> {
+        let record = self
+            .conn
+            .query_row(
+                &format!("SELECT {} FROM users WHERE user_id = ?1", RECORD_COLUMNS),
+                params![user_id],
+                |row| read_record(row, 0),
+            )
+            .optional()?;
+        Ok(record)
+    }
+
+    /// Look up a user's record by their login
+    ///
+    /// # Arguments
+    /// * `login` - The login to look up (case-insensitive)
+    ///
+    /// # Returns
+    /// The user's record, or None if no known user has that login
+    pub fn get_by_login(&self, login: &str) -> Result<Option<UserRecord>> {
+        let record = self
+            .conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM users WHERE login = ?1 COLLATE NOCASE",
+                    RECORD_COLUMNS
+                ),
+                params![login],
+                |row| read_record(row, 0),
+            )
+            .optional()?;
+        Ok(record)
+    }
+
+    /// Record a chat message from a user, creating them if they're new
+    ///
+    /// # Arguments
+    /// * `user_id` - The Twitch user ID that sent a message
+    /// * `login` - The user's login, if known
+    /// * `now` - When the message was sent
+    pub fn record_message(
+        &self,
+        user_id: &str,
+        login: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        let now = now.to_rfc3339();
+        self.conn.execute(
+            "INSERT INTO users (user_id, login, first_seen, last_seen, message_count)
+             VALUES (?1, ?2, ?3, ?3, 1)
+             ON CONFLICT(user_id) DO UPDATE SET
+                login = COALESCE(excluded.login, users.login),
+                last_seen = excluded.last_seen,
+                message_count = users.message_count + 1",
+            params![user_id, login, now],
+        )?;
+        Ok(())
+    }
+
+    /// Record that a user was welcomed
+    ///
+    /// # Arguments
+    /// * `user_id` - The Twitch user ID that was welcomed
+    /// * `now` - When the welcome was sent
+    pub fn mark_welcomed(&self, user_id: &str, now: DateTime<Utc>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE users SET welcomed_at = ?2 WHERE user_id = ?1",
+            params![user_id, now.to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Record the first message a user sent, unless one is already stored
+    ///
+    /// # Arguments
+    /// * `user_id` - The Twitch user ID that sent the message
+    /// * `text` - The message text
+    pub fn set_first_message(&self, user_id: &str, text: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE users SET first_message = ?2 WHERE user_id = ?1 AND first_message IS NULL",
+            params![user_id, text],
+        )?;
+        Ok(())
+    }
+
+    /// Delete a user's record
+    ///
+    /// # Arguments
+    /// * `user_id` - The Twitch user ID to delete
+    ///
+    /// # Returns
+    /// true if the user was known
+    pub fn remove(&self, user_id: &str) -> Result<bool> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM users WHERE user_id = ?1", params![user_id])?;
+        Ok(removed > 0)
+    }
+
+    /// List every known user, most active first
+    ///
+    /// # Returns
+    /// Each user's ID with their record
+    pub fn list(&self) -> Result<Vec<(String, UserRecord)>> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT user_id, {} FROM users ORDER BY message_count DESC, user_id",
+            RECORD_COLUMNS
+        ))?;
+        let users = statement
+            .query_map([], |row| Ok((row.get(0)?, read_record(row, 1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(users)
+    }
+
+    /// Import user IDs from the old newline-delimited known users file
+    ///
+    /// Imported users have no timestamps or message count. Users already in
+    /// the database are left untouched.
+    ///
+    /// # Arguments
+    /// * `content` - The contents of the old file
+    ///
+    /// # Returns
+    /// The number of users imported
+    pub fn import_legacy(&mut self, content: &str) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut imported = 0;
+        {
+            let mut insert = tx.prepare("INSERT OR IGNORE INTO users (user_id) VALUES (?1)")?;
+            for line in content.lines() {
+                // Older files may carry tab-separated timestamps after the ID
+                let user_id = line.split('\t').next().unwrap_or_default().trim();
+                if !user_id.is_empty() {
+                    imported += insert.execute(params![user_id])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(imported)
+    }
+
+    /// Add watch time for viewers who were present
+    ///
+    /// Twitch lets a login pass to another account after a rename, so a
+    /// viewer's login is cleared from any other viewer still holding it.
+    ///
+    /// # Arguments
+    /// * `viewers` - Each present viewer's user ID and login
+    /// * `minutes` - How many minutes each of them watched
+    pub fn add_watch_minutes(&mut self, viewers: &[(String, String)], minutes: u64) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut release = tx.prepare(
+                "UPDATE watch_time SET login = '' WHERE login = ?2 COLLATE NOCASE AND user_id <> ?1",
+            )?;
+            let mut add = tx.prepare(
+                "INSERT INTO watch_time (user_id, login, minutes) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(user_id) DO UPDATE SET
+                    login = excluded.login,
+                    minutes = watch_time.minutes + excluded.minutes",
+            )?;
+            for (user_id, login) in viewers {
+                release.execute(params![user_id, login])?;
+                add.execute(params![user_id, login, minutes])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Look up how long a viewer has watched
+    ///
+    /// # Arguments
+    /// * `login` - The viewer's login (case-insensitive)
+    ///
+    /// # Returns
+    /// The minutes watched, or None if they've never been seen watching
+    pub fn watch_minutes_by_login(&self, login: &str) -> Result<Option<u64>> {
+        if login.is_empty() {
+            return Ok(None);
+        }
+        // Databases from before logins were released can still share one;
+        // the viewer added last is the likelier current owner
+        let minutes = self
+            .conn
+            .query_row(
+                "SELECT minutes FROM watch_time WHERE login = ?1 COLLATE NOCASE
+                 ORDER BY rowid DESC LIMIT 1",
+                params![login],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(minutes)
+    }
+
+    /// Get the number of known users
+    pub fn count(&self) -> Result<usize> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+}
+
+/// Build a UserRecord from the RECORD_COLUMNS starting at `offset`
+fn read_record(row: &Row, offset: usize) -> rusqlite::Result<UserRecord> {
+    Ok(UserRecord {
+        login: row.get(offset)?,
+        first_seen: parse_timestamp(row.get(offset + 1)?),
+        last_seen: parse_timestamp(row.get(offset + 2)?),
+        message_count: row.get(offset + 3)?,
+        welcomed_at: parse_timestamp(row.get(offset + 4)?),
+        first_message: row.get(offset + 5)?,
+    })
+}
+
+/// Parse a stored RFC 3339 timestamp
+fn parse_timestamp(value: Option<String>) -> Option<DateTime<Utc>> {
+    value
+        .and_then(|value| DateTime::parse_from_rfc3339(&value).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_message_counts_messages() -> Result<()> {
+        let store = UserStore::open_in_memory()?;
+        let first = Utc::now();
+        let later = first + chrono::Duration::minutes(5);
+
+        store.record_message("1", Some("someone"), first)?;
+        store.record_message("1", None, later)?;
+
+        let record = store.get("1")?.unwrap();
+        assert_eq!(record.login.as_deref(), Some("someone"));
+        assert_eq!(record.message_count, 2);
+        assert_eq!(record.first_seen.unwrap().timestamp(), first.timestamp());
+        assert_eq!(record.last_seen.unwrap().timestamp(), later.timestamp());
+        assert!(store.get("2")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mark_welcomed_and_list() -> Result<()> {
+        let store = UserStore::open_in_memory()?;
+        let now = Utc::now();
+
+        store.record_message("1", Some("quiet"), now)?;
+        store.record_message("2", Some("chatty"), now)?;
+        store.record_message("2", None, now)?;
+        store.mark_welcomed("2", now)?;
+
+        let users = store.list()?;
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].0, "2");
+        assert_eq!(users[0].1.welcomed_at.unwrap().timestamp(), now.timestamp());
+        assert_eq!(users[1].0, "1");
+        assert!(users[1].1.welcomed_at.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_adds_welcomed_column_to_old_databases() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute(
+            "CREATE TABLE users (
+                user_id TEXT PRIMARY KEY,
+                login TEXT,
+                first_seen TEXT,
+                last_seen TEXT,
+                message_count INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+        conn.execute("INSERT INTO users (user_id) VALUES ('old')", [])?;
+
+        let store = UserStore::init(conn)?;
+        store.mark_welcomed("old", Utc::now())?;
+        store.set_first_message("old", "hello")?;
+
+        let record = store.get("old")?.unwrap();
+        assert!(record.welcomed_at.is_some());
+        assert_eq!(record.first_message.as_deref(), Some("hello"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_first_message_is_not_overwritten() -> Result<()> {
+        let store = UserStore::open_in_memory()?;
+        store.record_message("1", Some("newbie"), Utc::now())?;
+
+        store.set_first_message("1", "found you through a raid!")?;
+        store.set_first_message("1", "second message")?;
+
+        let record = store.get("1")?.unwrap();
+        assert_eq!(
+            record.first_message.as_deref(),
+            Some("found you through a raid!")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_legacy() -> Result<()> {
+        let mut store = UserStore::open_in_memory()?;
+        store.record_message("user1", Some("user1"), Utc::now())?;
+
+        let imported = store.import_legacy("user1\nuser2\n\nuser3\t2024-01-01T00:00:00Z\n")?;
+
+        // user1 was already in the database
+        assert_eq!(imported, 2);
+        assert_eq!(store.count()?, 3);
+        assert_eq!(store.get("user1")?.unwrap().message_count, 1);
+        assert!(store.get("user3")?.unwrap().last_seen.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_watch_time_is_kept_apart_from_chatters() -> Result<()> {
+        let mut store = UserStore::open_in_memory()?;
+        let viewers = vec![
+            ("1".to_string(), "chatty".to_string()),
+            ("2".to_string(), "lurker".to_string()),
+        ];
+
+        store.add_watch_minutes(&viewers, 5)?;
+        store.add_watch_minutes(&viewers[..1], 1)?;
+
+        assert_eq!(store.watch_minutes_by_login("Chatty")?, Some(6));
+        assert_eq!(store.watch_minutes_by_login("lurker")?, Some(5));
+        assert_eq!(store.watch_minutes_by_login("nobody")?, None);
+
+        // Watching doesn't make someone a known chatter
+        assert!(store.get("2")?.is_none());
+
+        // A login that passes to a new account follows it, and the old
+        // account keeps its minutes under its new login
+        store.add_watch_minutes(&[("3".to_string(), "chatty".to_string())], 2)?;
+        assert_eq!(store.watch_minutes_by_login("chatty")?, Some(2));
+        assert_eq!(store.watch_minutes_by_login("")?, None);
+        store.add_watch_minutes(&[("1".to_string(), "chatty_renamed".to_string())], 1)?;
+        assert_eq!(store.watch_minutes_by_login("chatty_renamed")?, Some(7));
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_file_uses_write_ahead_log() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("known_users.db");
+
+        let store = UserStore::open(&path)?;
+        let journal_mode: String = store
+            .conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))?;
+        assert_eq!(journal_mode, "wal");
+
+        // Each message is committed as it's recorded, with no save step
+        store.record_message("1", Some("someone"), Utc::now())?;
+        let reopened = UserStore::open(&path)?;
+        assert_eq!(reopened.get("1")?.unwrap().message_count, 1);
+        Ok(())
+    }
+}
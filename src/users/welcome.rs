@@ -1,59 +1,199 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
 use rand::prelude::IndexedRandom;
 use rand::rng;
-use std::any::Any;
-use std::sync::Arc;
-use tracing::{debug, info};
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, error, info, warn};
 use twitch_irc::message::PrivmsgMessage;
 
-use crate::twitch::TwitchClient;
-use crate::users::UserManager;
+use crate::ai::AiClient;
+use crate::features::{ChannelFeatures, Feature};
+use crate::personality::{AiPersona, Personality};
+use crate::twitch::{MAX_MESSAGE_LENGTH, MessageSender};
+use crate::users::{ChatterStatus, UserManager};
 
-/// Mock TwitchClient for testing
-#[derive(Clone)]
-pub struct MockTwitchClient {}
+/// Mock TwitchClient for testing and offline simulation
+#[derive(Clone, Default)]
+pub struct MockTwitchClient {
+    /// Messages "sent" through this client as (channel, message) pairs
+    sent: Arc<Mutex<Vec<(String, String)>>>,
+    /// Whether sent messages are also printed to the console
+    print: bool,
+}
 
 // Implement the necessary methods for MockTwitchClient
 impl MockTwitchClient {
-    // Mock the send_message method for testing
-    pub async fn send_message(
-        &mut self,
-        _channel: &str,
-        _message: &str,
-        _username: &str,
-    ) -> Result<()> {
-        // Just return success without actually sending anything
-        Ok(())
+    /// Create a new mock client with no sent messages
+    #[allow(dead_code)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a mock client that prints what it sends to the console
+    pub fn printing() -> Self {
+        MockTwitchClient {
+            print: true,
+            ..Self::default()
+        }
     }
 
-    // Mock the send_reply method for testing
+    /// Get the messages sent through this client so far
     #[allow(dead_code)]
-    pub async fn send_reply(
-        &mut self,
-        _channel: &str,
-        _message: &str,
-        _reply_to: &str,
-        _username: &str,
-    ) -> Result<()> {
-        // Just return success without actually sending anything
+    pub fn sent_messages(&self) -> Vec<(String, String)> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl MessageSender for MockTwitchClient {
+    async fn say(&self, channel: &str, message: &str) -> Result<()> {
+        // Record the message instead of actually sending anything
+        if self.print {
+            println!("[#{}] {}", channel, message);
+        }
+        self.sent
+            .lock()
+            .unwrap()
+            .push((channel.to_string(), message.to_string()));
         Ok(())
     }
 }
 
+/// How welcome messages are paced when many new chatters arrive at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WelcomeMode {
+    /// Welcome every new chatter immediately
+    Immediate,
+    /// Welcome at most one new chatter per channel within the interval
+    Cooldown(Duration),
+    /// Collect new chatters over the window and welcome them in one message
+    Batch(Duration),
+}
+
+impl WelcomeMode {
+    /// Build a welcome mode from its name and window length
+    ///
+    /// # Arguments
+    /// * `name` - One of "immediate", "cooldown", or "batch"
+    /// * `window` - The cooldown interval or batch window
+    ///
+    /// # Returns
+    /// The parsed welcome mode
+    pub fn from_name(name: &str, window: Duration) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "immediate" => Ok(WelcomeMode::Immediate),
+            "cooldown" => Ok(WelcomeMode::Cooldown(window)),
+            "batch" => Ok(WelcomeMode::Batch(window)),
+            other => Err(anyhow!("Unknown welcome mode: {}", other)),
+        }
+    }
+}
+
+impl FromStr for WelcomeMode {
+    type Err = anyhow::Error;
+
+    /// Parse a welcome mode name using a 30 second window
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_name(s, Duration::from_secs(30))
+    }
+}
+
+/// File in the data directory with custom welcome templates, one per line
+pub const WELCOME_MESSAGES_FILE: &str = "welcome_messages.txt";
+
+/// Read welcome templates from a file, one per line
+///
+/// Blank lines and lines starting with `#` are skipped.
+///
+/// # Arguments
+/// * `path` - Path to the templates file
+///
+/// # Returns
+/// The templates, or None if the file doesn't exist or has none
+pub fn read_welcome_templates(path: &Path) -> Result<Option<Vec<String>>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Couldn't read {}: {}", path.display(), e))?;
+    let templates: Vec<String> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect();
+
+    Ok((!templates.is_empty()).then_some(templates))
+}
+
+/// Find templates that never mention the chatter
+///
+/// # Arguments
+/// * `templates` - The welcome templates to check
+///
+/// # Returns
+/// The templates without a `{username}` placeholder
+pub fn templates_missing_username(templates: &[String]) -> Vec<&str> {
+    templates
+        .iter()
+        .filter(|template| !template.contains("{username}"))
+        .map(String::as_str)
+        .collect()
+}
+
 /// Service to welcome new chatters in the channel
 pub struct WelcomeService {
-    /// The client for sending messages (can be TwitchClient or MockTwitchClient for testing)
-    client: Arc<dyn Any + Send + Sync>,
+    /// The client for sending messages
+    client: Arc<dyn MessageSender>,
     /// The user manager for tracking users
     user_manager: Arc<UserManager>,
     /// Whether the welcome feature is enabled
     enabled: bool,
     /// Welcome message templates (use {username} as placeholder)
     welcome_messages: Vec<String>,
+    /// Templates for known chatters returning after a while (use {username} as placeholder)
+    returning_messages: Vec<String>,
+    /// The tone used for AI-generated welcomes
+    persona: Arc<AiPersona>,
     /// Whether to use AI for generating welcome messages
     use_ai: bool,
+    /// Client for generating AI welcome messages
+    ai_client: Option<Arc<AiClient>>,
+    /// How welcomes are paced
+    mode: WelcomeMode,
+    /// When the last welcome was sent in each channel (cooldown mode)
+    last_welcome: Mutex<HashMap<String, Instant>>,
+    /// New chatters waiting to be welcomed in each channel (batch mode)
+    pending_batches: Arc<Mutex<HashMap<String, Vec<String>>>>,
+    /// Welcomes are suppressed until this time (set by !mutewelcome)
+    muted_until: Arc<Mutex<Option<Instant>>>,
+    /// Whether to store the first message of each new chatter
+    capture_first_words: bool,
+    /// How long welcomes are held back after a raid or going live (zero disables it)
+    grace_period: Duration,
+    /// When the grace period ends in each channel
+    grace_until: Mutex<HashMap<String, Instant>>,
+    /// Which features are on in each channel
+    features: Arc<ChannelFeatures>,
 }
 
+/// The longest welcomes can be muted for, so the end time can't overflow
+const LONGEST_MUTE: Duration = Duration::from_secs(365 * 86400);
+
+/// The longest WELCOME_GRACE_SECS can be; a raid's rush is long over by then
+pub const MAX_WELCOME_GRACE_SECS: u64 = 3600;
+
+/// Instructions given to the AI when generating a welcome message, after the personality's tone
+const AI_WELCOME_INSTRUCTIONS: &str = "Write a single short, \
+     warm welcome message (under 200 characters) for a first-time chatter. Mention them by \
+     name, do not use hashtags, and reply with the message only.";
+
 impl WelcomeService {
     /// Create a new welcome service
     ///
@@ -65,39 +205,83 @@ impl WelcomeService {
     /// # Returns
     /// A new WelcomeService instance
     pub fn new(
-        client: Arc<dyn Any + Send + Sync>,
+        client: Arc<dyn MessageSender>,
         user_manager: Arc<UserManager>,
         custom_messages: Option<Vec<String>>,
     ) -> Self {
         // Default welcome messages if none provided
-        let default_messages = vec![
-            "Welcome to the channel, {username}! Thanks for dropping by!".to_string(),
-            "Hey {username}! Great to see you here for the first time!".to_string(),
-            "Welcome aboard, {username}! Hope you enjoy the stream!".to_string(),
-            "A wild {username} appears! Welcome to the stream!".to_string(),
-            "Welcome, {username}! Make yourself at home!".to_string(),
-            "Thanks for joining us, {username}! Glad to have you here!".to_string(),
-            "First time here, {username}? Welcome to the community!".to_string(),
-            "{username} has entered the chat! Welcome!".to_string(),
-            "Welcome to the stream, {username}! Don't forget to follow if you enjoy the content!"
-                .to_string(),
-            "Hello, {username}! Welcome to the channel!".to_string(),
-        ];
+        let personality = Personality::default();
 
         WelcomeService {
             client,
             user_manager,
             enabled: true,
-            welcome_messages: custom_messages.unwrap_or(default_messages),
+            welcome_messages: custom_messages.unwrap_or_else(|| personality.welcome_messages()),
+            returning_messages: personality.returning_messages(),
+            persona: Arc::new(AiPersona::new(personality)),
             use_ai: false,
+            ai_client: None,
+            mode: WelcomeMode::Immediate,
+            last_welcome: Mutex::new(HashMap::new()),
+            pending_batches: Arc::new(Mutex::new(HashMap::new())),
+            muted_until: Arc::new(Mutex::new(None)),
+            capture_first_words: false,
+            grace_period: Duration::ZERO,
+            grace_until: Mutex::new(HashMap::new()),
+            features: Arc::new(ChannelFeatures::default()),
         }
     }
 
+    /// Set how welcome messages are paced
+    ///
+    /// # Arguments
+    /// * `mode` - The welcome mode to use
+    pub fn set_mode(&mut self, mode: WelcomeMode) {
+        self.mode = mode;
+    }
+
+    /// Switch to a personality preset
+    ///
+    /// Replaces the welcome and returning templates with the preset's, and
+    /// sets the tone of AI-generated welcomes.
+    ///
+    /// # Arguments
+    /// * `personality` - The preset to use
+    pub fn set_personality(&mut self, personality: Personality) {
+        self.persona.set_preset(personality);
+        self.welcome_messages = personality.welcome_messages();
+        self.returning_messages = personality.returning_messages();
+    }
+
+    /// Share the AI tone with other parts of the bot, such as !aipersona
+    ///
+    /// # Arguments
+    /// * `persona` - The persona whose prompt AI-generated welcomes use
+    pub fn set_ai_persona(&mut self, persona: Arc<AiPersona>) {
+        self.persona = persona;
+    }
+
+    /// Enable or disable storing the first message of each new chatter
+    ///
+    /// # Arguments
+    /// * `capture` - Whether first messages should be stored
+    pub fn set_capture_first_words(&mut self, capture: bool) {
+        self.capture_first_words = capture;
+    }
+
+    /// Set how long welcomes are held back after a raid or going live
+    ///
+    /// # Arguments
+    /// * `grace_period` - The length of the grace period, or zero to disable
+    ///   it; anything over MAX_WELCOME_GRACE_SECS is cut down to that
+    pub fn set_grace_period(&mut self, grace_period: Duration) {
+        self.grace_period = grace_period.min(Duration::from_secs(MAX_WELCOME_GRACE_SECS));
+    }
+
     /// Enable or disable the welcome service
     ///
     /// # Arguments
     /// * `enabled` - Whether the service should be enabled
-    #[allow(dead_code)]
     pub fn set_enabled(&mut self, enabled: bool) {
         self.enabled = enabled;
     }
@@ -115,20 +299,44 @@ impl WelcomeService {
     ///
     /// # Arguments
     /// * `messages` - The new list of welcome message templates
-    #[allow(dead_code)]
     pub fn set_welcome_messages(&mut self, messages: Vec<String>) {
         self.welcome_messages = messages;
     }
 
+    /// Set all returning chatter message templates
+    ///
+    /// # Arguments
+    /// * `messages` - The new list of returning chatter message templates
+    #[allow(dead_code)]
+    pub fn set_returning_messages(&mut self, messages: Vec<String>) {
+        self.returning_messages = messages;
+    }
+
     /// Toggle AI-generated welcome messages
     ///
     /// # Arguments
     /// * `use_ai` - Whether to use AI for generating welcome messages
-    #[allow(dead_code)]
     pub fn set_use_ai(&mut self, use_ai: bool) {
         self.use_ai = use_ai;
     }
 
+    /// Set the AI client used to generate welcome messages
+    ///
+    /// # Arguments
+    /// * `ai_client` - The client for the OpenAI-compatible API
+    pub fn set_ai_client(&mut self, ai_client: Arc<AiClient>) {
+        self.ai_client = Some(ai_client);
+    }
+
+    /// Set which features are on in each channel
+    ///
+    /// # Arguments
+    /// * `features` - The features for each channel; chatters are still tracked
+    ///   where welcomes are off, and templates are used where AI is off
+    pub fn set_features(&mut self, features: Arc<ChannelFeatures>) {
+        self.features = features;
+    }
+
     /// Get a random welcome message
     ///
     /// # Arguments
@@ -137,37 +345,151 @@ impl WelcomeService {
     /// # Returns
     /// A personalized welcome message
     fn get_random_welcome_message(&self, username: &str) -> String {
-        let mut rng = rng();
-
-        // Get a random message template
-        let template = if let Some(message) = self.welcome_messages.choose(&mut rng) {
-            message
-        } else {
-            // Fallback if the messages list is somehow empty
-            "Welcome, {username}!"
-        };
+        fill_random_template(&self.welcome_messages, "Welcome, {username}!", username)
+    }
 
-        // Replace the placeholder with the actual username
-        template.replace("{username}", username)
+    /// Get a random message for a returning chatter
+    ///
+    /// # Arguments
+    /// * `username` - The username to insert into the message
+    ///
+    /// # Returns
+    /// A personalized welcome back message
+    fn get_random_returning_message(&self, username: &str) -> String {
+        fill_random_template(
+            &self.returning_messages,
+            "Welcome back, {username}!",
+            username,
+        )
     }
 
-    /// Get an AI-generated welcome message (placeholder for future implementation)
+    /// Get an AI-generated welcome message
+    ///
+    /// Falls back to a random template if no AI client is configured or the
+    /// API errors or times out, so a broken API never blocks welcoming.
     ///
     /// # Arguments
     /// * `username` - The username to welcome
+    /// * `channel` - The channel the user is chatting in
     ///
     /// # Returns
     /// A personalized welcome message
-    async fn get_ai_welcome_message(&self, username: &str) -> Result<String> {
-        // This is a placeholder for future AI implementation
-        // For now, it just returns a static message with the username
-        Ok(format!(
-            "AI welcomes you to the channel, {}! This message would normally be AI-generated.",
-            username
-        ))
+    async fn get_ai_welcome_message(&self, username: &str, channel: &str) -> String {
+        let Some(ai_client) = &self.ai_client else {
+            debug!("AI welcomes enabled but no AI client configured, using a template");
+            return self.get_random_welcome_message(username);
+        };
+
+        let prompt = format!(
+            "Welcome {} to {}'s Twitch channel. This is their first message in chat.",
+            username, channel
+        );
+
+        let system_prompt = format!(
+            "{} {}",
+            self.persona.system_prompt(),
+            AI_WELCOME_INSTRUCTIONS
+        );
+
+        match ai_client.complete_for_chat(&system_prompt, &prompt).await {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("AI welcome failed, falling back to a template: {}", e);
+                self.get_random_welcome_message(username)
+            }
+        }
+    }
+
+    /// Suppress welcomes for a while, announcing in chat when they resume
+    ///
+    /// Muting again replaces the previous mute.
+    ///
+    /// # Arguments
+    /// * `channel` - The channel to announce the resume in
+    /// * `duration` - How long to suppress welcomes for
+    pub fn mute_for(&self, channel: &str, duration: Duration) {
+        let duration = duration.min(LONGEST_MUTE);
+        let until = Instant::now() + duration;
+        *self.muted_until.lock().unwrap() = Some(until);
+        info!("Welcomes muted for {:?}", duration);
+
+        let client = self.client.clone();
+        let muted_until = self.muted_until.clone();
+        let channel = channel.to_string();
+
+        tokio::spawn(async move {
+            tokio::time::sleep_until(until).await;
+
+            // Only the latest mute gets to end it
+            {
+                let mut muted_until = muted_until.lock().unwrap();
+                if *muted_until != Some(until) {
+                    return;
+                }
+                *muted_until = None;
+            }
+
+            info!("Welcomes resumed");
+            if let Err(e) = client.say(&channel, "Welcome messages have resumed.").await {
+                error!("Error announcing resumed welcomes: {}", e);
+            }
+        });
+    }
+
+    /// Lift a mute early
+    ///
+    /// # Returns
+    /// true if welcomes were muted
+    pub fn unmute(&self) -> bool {
+        self.muted_until.lock().unwrap().take().is_some()
+    }
+
+    /// Check whether welcomes are currently muted
+    pub fn is_muted(&self) -> bool {
+        matches!(*self.muted_until.lock().unwrap(), Some(until) if Instant::now() < until)
+    }
+
+    /// Start the grace period in a channel, when a raid lands or the stream goes live
+    ///
+    /// Chatters who arrive during the grace period are welcomed together in a
+    /// single message once it ends. Starting it again extends it.
+    ///
+    /// # Arguments
+    /// * `channel` - The channel the raid or go-live happened in
+    pub fn start_grace_period(&self, channel: &str) {
+        if self.grace_period.is_zero() {
+            return;
+        }
+
+        info!(
+            "Holding back welcomes in {} for {:?}",
+            channel, self.grace_period
+        );
+        self.grace_until
+            .lock()
+            .unwrap()
+            .insert(channel.to_string(), Instant::now() + self.grace_period);
+    }
+
+    /// Get how much of a channel's grace period is left
+    ///
+    /// # Returns
+    /// The time remaining, or None if the channel isn't in a grace period
+    fn grace_remaining(&self, channel: &str) -> Option<Duration> {
+        let mut grace_until = self.grace_until.lock().unwrap();
+        let until = *grace_until.get(channel)?;
+        let now = Instant::now();
+        if now < until {
+            Some(until - now)
+        } else {
+            grace_until.remove(channel);
+            None
+        }
     }
 
-    /// Process a chat message to detect and welcome first-time chatters
+    /// Process a chat message to welcome first-time and returning chatters
+    ///
+    /// Chatters already seen this session are not welcomed again.
     ///
     /// # Arguments
     /// * `msg` - The chat message to process
@@ -184,42 +506,195 @@ impl WelcomeService {
         let username = msg.sender.name.clone();
         let channel = msg.channel_login.clone();
 
-        // Check if this is a first-time chatter
-        if self.user_manager.is_first_time_chatter(&user_id) {
-            info!("First-time chatter detected: {} ({})", username, user_id);
-
-            // Get the welcome message (either AI-generated or random)
-            let welcome_message = if self.use_ai {
-                self.get_ai_welcome_message(&username).await?
-            } else {
-                self.get_random_welcome_message(&username)
-            };
-
-            // Send the welcome message
-            debug!("Sending welcome message to: {}", username);
-
-            // For actual TwitchClient: send the message
-            if let Some(_twitch_client) = self.client.downcast_ref::<TwitchClient>() {
-                // Clone the client to make it mutable
-                let client_arc = self.client.clone();
-                let twitch_client = client_arc.downcast_ref::<TwitchClient>().unwrap();
-                let mut client_mut = twitch_client.clone();
-                // Use channel name for the bot username parameter - the actual bot username will be used
-                client_mut
-                    .send_message(&channel, &welcome_message, &channel)
-                    .await?;
+        let status = self.user_manager.record_chat(&user_id, &msg.sender.login)?;
+        match status {
+            ChatterStatus::FirstTime => {
+                info!("First-time chatter detected: {} ({})", username, user_id);
+                if self.capture_first_words {
+                    self.user_manager
+                        .set_first_message(&user_id, &msg.message_text)?;
+                }
+            }
+            ChatterStatus::Returning => {
+                info!("Returning chatter detected: {} ({})", username, user_id);
             }
-            // For MockTwitchClient: handle in the mock implementation
-            else if let Some(mock_client) = self.client.downcast_ref::<MockTwitchClient>() {
-                let mut mock_client = mock_client.clone();
-                mock_client
-                    .send_message(&channel, &welcome_message, &channel)
-                    .await?;
+            ChatterStatus::SameSession => return Ok(()),
+        }
+
+        if !self.features.is_enabled(&channel, Feature::Welcomes) {
+            debug!(
+                "Welcomes are off in {}, not welcoming {}",
+                channel, username
+            );
+            return Ok(());
+        }
+
+        if self.is_muted() {
+            debug!("Welcomes are muted, not welcoming {}", username);
+            return Ok(());
+        }
+
+        // During a grace period everyone is welcomed together when it ends
+        if let Some(remaining) = self.grace_remaining(&channel) {
+            debug!("Grace period active, batching the welcome for {}", username);
+            self.queue_for_batch(&channel, &username, remaining);
+            self.user_manager.mark_welcomed(&user_id)?;
+            return Ok(());
+        }
+
+        match self.mode {
+            WelcomeMode::Immediate => self.welcome(&channel, &username, status).await?,
+            WelcomeMode::Cooldown(interval) => {
+                if self.try_start_cooldown(&channel, interval) {
+                    self.welcome(&channel, &username, status).await?;
+                } else {
+                    debug!("Welcome cooldown active, not welcoming {}", username);
+                    return Ok(());
+                }
             }
+            WelcomeMode::Batch(window) => self.queue_for_batch(&channel, &username, window),
         }
 
+        // Batched chatters count as welcomed once they're queued
+        self.user_manager.mark_welcomed(&user_id)?;
+
         Ok(())
     }
+
+    /// Welcome someone as though they had just chatted for the first time
+    ///
+    /// Used to demo and test welcomes on a live channel. The user's record is
+    /// left untouched, and mutes and pacing are ignored.
+    ///
+    /// # Arguments
+    /// * `channel` - The channel to send the welcome in
+    /// * `username` - The username to welcome
+    ///
+    /// # Returns
+    /// A Result indicating success or failure
+    pub async fn simulate_first_time(&self, channel: &str, username: &str) -> Result<()> {
+        info!("Simulating a first-time chatter: {}", username);
+        self.welcome(channel, username, ChatterStatus::FirstTime)
+            .await
+    }
+
+    /// Send a welcome message for a single chatter
+    async fn welcome(&self, channel: &str, username: &str, status: ChatterStatus) -> Result<()> {
+        // Get the welcome message (either AI-generated or random)
+        let welcome_message = match status {
+            ChatterStatus::Returning => self.get_random_returning_message(username),
+            _ if self.use_ai && self.features.is_enabled(channel, Feature::Ai) => {
+                self.get_ai_welcome_message(username, channel).await
+            }
+            _ => self.get_random_welcome_message(username),
+        };
+
+        // Send the welcome message
+        debug!("Sending welcome message to: {}", username);
+        self.client.say(channel, &welcome_message).await
+    }
+
+    /// Start the welcome cooldown for a channel if it has expired
+    ///
+    /// # Returns
+    /// true if a welcome may be sent now
+    fn try_start_cooldown(&self, channel: &str, interval: Duration) -> bool {
+        let mut last_welcome = self.last_welcome.lock().unwrap();
+        let now = Instant::now();
+
+        match last_welcome.get(channel) {
+            Some(last) if now.duration_since(*last) < interval => false,
+            _ => {
+                last_welcome.insert(channel.to_string(), now);
+                true
+            }
+        }
+    }
+
+    /// Add a new chatter to the channel's batch, scheduling a flush for a new batch
+    fn queue_for_batch(&self, channel: &str, username: &str, window: Duration) {
+        {
+            let mut pending = self.pending_batches.lock().unwrap();
+            let batch = pending.entry(channel.to_string()).or_default();
+            batch.push(username.to_string());
+
+            // A flush is already scheduled for this batch
+            if batch.len() > 1 {
+                return;
+            }
+        }
+
+        let client = self.client.clone();
+        let pending = self.pending_batches.clone();
+        let channel = channel.to_string();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(window).await;
+
+            let usernames = pending.lock().unwrap().remove(&channel).unwrap_or_default();
+            if usernames.is_empty() {
+                return;
+            }
+
+            for message in format_batch_welcome(&usernames) {
+                if let Err(e) = client.say(&channel, &message).await {
+                    error!("Error sending batched welcome: {}", e);
+                }
+            }
+        });
+    }
+}
+
+/// Fill a random template from a list with a username
+///
+/// # Arguments
+/// * `templates` - The templates to choose from (use {username} as placeholder)
+/// * `fallback` - The template to use if the list is empty
+/// * `username` - The username to insert into the message
+///
+/// # Returns
+/// A personalized message
+fn fill_random_template(templates: &[String], fallback: &str, username: &str) -> String {
+    let template = templates
+        .choose(&mut rng())
+        .map(String::as_str)
+        .unwrap_or(fallback);
+
+    // Replace the placeholder with the actual username
+    template.replace("{username}", username)
+}
+
+/// Format the welcome messages for a batch of new chatters
+///
+/// The mentions are spread over as few messages as fit in chat.
+///
+/// # Arguments
+/// * `usernames` - The new chatters to welcome
+///
+/// # Returns
+/// Messages like "Welcome @a, @b, @c!"
+fn format_batch_welcome(usernames: &[String]) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut mentions: Vec<String> = Vec::new();
+    for name in usernames {
+        let mention = format!("@{}", name);
+        // "Welcome " and "!" around the mentions, with ", " between them
+        let length: usize = mentions
+            .iter()
+            .map(|m| m.chars().count() + 2)
+            .sum::<usize>()
+            + mention.chars().count()
+            + "Welcome !".len();
+        if !mentions.is_empty() && length > MAX_MESSAGE_LENGTH {
+            messages.push(format!("Welcome {}!", mentions.join(", ")));
+            mentions.clear();
+        }
+        mentions.push(mention);
+    }
+    if !mentions.is_empty() {
+        messages.push(format!("Welcome {}!", mentions.join(", ")));
+    }
+    messages
 }
 
 #[cfg(test)]
@@ -267,13 +742,13 @@ mod tests {
     async fn test_welcome_service_random() -> Result<()> {
         // Create a temporary directory for user data
         let temp_dir = tempdir()?;
-        let users_path = temp_dir.path().join("users.txt");
+        let users_path = temp_dir.path().join("users.db");
 
         // Create mock client for testing
-        let client = Arc::new(MockTwitchClient {});
+        let client = Arc::new(MockTwitchClient::new());
 
         // Create user manager
-        let user_manager = Arc::new(UserManager::new(users_path.to_str().unwrap()));
+        let user_manager = Arc::new(UserManager::new(users_path.to_str().unwrap())?);
 
         // Create welcome service with custom messages
         let custom_messages = vec![
@@ -304,18 +779,101 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_first_words_captured_once() -> Result<()> {
+        let user_manager = Arc::new(UserManager::new("test.db")?);
+        let mut welcome_service = WelcomeService::new(
+            Arc::new(MockTwitchClient::new()),
+            user_manager.clone(),
+            None,
+        );
+
+        // Nothing is stored while capture is off
+        welcome_service
+            .process_message(create_test_message("user1", "User1"))
+            .await?;
+        assert!(
+            user_manager
+                .get_user("user1")?
+                .unwrap()
+                .first_message
+                .is_none()
+        );
+
+        welcome_service.set_capture_first_words(true);
+        let mut first = create_test_message("user2", "User2");
+        first.message_text = "Found you from a raid!".to_string();
+        welcome_service.process_message(first).await?;
+
+        let mut second = create_test_message("user2", "User2");
+        second.message_text = "Second message".to_string();
+        welcome_service.process_message(second).await?;
+
+        let record = user_manager.get_user("user2")?.unwrap();
+        assert_eq!(
+            record.first_message.as_deref(),
+            Some("Found you from a raid!")
+        );
+        assert!(
+            user_manager
+                .get_user("user1")?
+                .unwrap()
+                .first_message
+                .is_none()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_returning_chatter_welcomed_back() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let users_path = temp_dir.path().join("users.db");
+        // A known user from an older users file, with no last-seen time
+        tokio::fs::write(temp_dir.path().join("users.txt"), "user1\n").await?;
+
+        let client = MockTwitchClient::new();
+        let user_manager = Arc::new(UserManager::new(users_path.to_str().unwrap())?);
+        user_manager.load().await?;
+
+        let mut welcome_service = WelcomeService::new(
+            Arc::new(client.clone()),
+            user_manager,
+            Some(vec!["Welcome, {username}!".to_string()]),
+        );
+        welcome_service.set_returning_messages(vec!["Welcome back, {username}!".to_string()]);
+
+        welcome_service
+            .process_message(create_test_message("user1", "User1"))
+            .await?;
+        // Same session, so no second welcome
+        welcome_service
+            .process_message(create_test_message("user1", "User1"))
+            .await?;
+        welcome_service
+            .process_message(create_test_message("user2", "User2"))
+            .await?;
+
+        let sent: Vec<String> = client
+            .sent_messages()
+            .into_iter()
+            .map(|(_, message)| message)
+            .collect();
+        assert_eq!(sent, vec!["Welcome back, User1!", "Welcome, User2!"]);
+        Ok(())
+    }
+
     // Fixed async test with proper mocking
     #[tokio::test]
     async fn test_welcome_service_ai() -> Result<()> {
         // Create a temporary directory for user data
         let temp_dir = tempdir()?;
-        let users_path = temp_dir.path().join("users.txt");
+        let users_path = temp_dir.path().join("users.db");
 
         // Create mock client for testing
-        let client = Arc::new(MockTwitchClient {});
+        let client = Arc::new(MockTwitchClient::new());
 
         // Create user manager
-        let user_manager = Arc::new(UserManager::new(users_path.to_str().unwrap()));
+        let user_manager = Arc::new(UserManager::new(users_path.to_str().unwrap())?);
 
         // Create welcome service with AI enabled
         let mut welcome_service = WelcomeService::new(
@@ -342,7 +900,7 @@ mod tests {
     #[test]
     fn test_random_welcome_message() {
         // No need for config or client connection
-        let user_manager = Arc::new(UserManager::new("test.txt"));
+        let user_manager = Arc::new(UserManager::new("test.db").unwrap());
 
         // Create service with just two messages for testing
         let messages = vec![
@@ -352,11 +910,22 @@ mod tests {
 
         // Create a welcome service with direct access to the method
         let service = WelcomeService {
-            client: Arc::new(MockTwitchClient {}),
+            client: Arc::new(MockTwitchClient::new()),
             user_manager,
             enabled: true,
             welcome_messages: messages,
+            returning_messages: Vec::new(),
+            persona: Arc::new(AiPersona::new(Personality::Friendly)),
             use_ai: false,
+            ai_client: None,
+            mode: WelcomeMode::Immediate,
+            last_welcome: Mutex::new(HashMap::new()),
+            pending_batches: Arc::new(Mutex::new(HashMap::new())),
+            muted_until: Arc::new(Mutex::new(None)),
+            capture_first_words: false,
+            grace_period: Duration::ZERO,
+            grace_until: Mutex::new(HashMap::new()),
+            features: Arc::new(ChannelFeatures::default()),
         };
 
         // Get a random message
@@ -365,4 +934,409 @@ mod tests {
         // Check that it contains the username and is one of our templates
         assert!(message == "Welcome, TestUser!" || message == "Hello, TestUser!");
     }
+
+    #[tokio::test]
+    async fn test_ai_welcome_message_from_api() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .with_status(200)
+            .with_body(
+                r#"{"choices":[{"message":{"content":"Hey User5, welcome to\nthe stream!"}}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let mut service = WelcomeService::new(
+            Arc::new(MockTwitchClient::new()),
+            Arc::new(UserManager::new("test.db")?),
+            None,
+        );
+        service.set_use_ai(true);
+        service.set_ai_client(Arc::new(AiClient::new(&server.url(), "key", "model")?));
+
+        let message = service
+            .get_ai_welcome_message("User5", "test_channel")
+            .await;
+        assert_eq!(message, "Hey User5, welcome to the stream!");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_personality_sets_ai_prompt_and_templates() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::Regex("snarky Twitch chatbot".to_string()))
+            .with_status(200)
+            .with_body(r#"{"choices":[{"message":{"content":"Oh, it's you."}}]}"#)
+            .create_async()
+            .await;
+
+        let mut service = WelcomeService::new(
+            Arc::new(MockTwitchClient::new()),
+            Arc::new(UserManager::new("test.db")?),
+            None,
+        );
+        service.set_personality(Personality::Snarky);
+        service.set_ai_client(Arc::new(AiClient::new(&server.url(), "key", "model")?));
+
+        let message = service
+            .get_ai_welcome_message("User5", "test_channel")
+            .await;
+        mock.assert_async().await;
+        assert_eq!(message, "Oh, it's you.");
+
+        // Canned templates come from the preset too
+        let snarky: Vec<String> = Personality::Snarky
+            .welcome_messages()
+            .iter()
+            .map(|template| template.replace("{username}", "User5"))
+            .collect();
+        assert!(snarky.contains(&service.get_random_welcome_message("User5")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_switching_ai_persona_changes_welcome_prompt() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let formal = server
+            .mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::Regex("formal Twitch chatbot".to_string()))
+            .with_status(200)
+            .with_body(r#"{"choices":[{"message":{"content":"Good evening, User5."}}]}"#)
+            .create_async()
+            .await;
+
+        let persona = Arc::new(AiPersona::new(Personality::Friendly));
+        let mut service = WelcomeService::new(
+            Arc::new(MockTwitchClient::new()),
+            Arc::new(UserManager::new("test.db")?),
+            None,
+        );
+        service.set_ai_persona(persona.clone());
+        service.set_ai_client(Arc::new(AiClient::new(&server.url(), "key", "model")?));
+
+        // Switched after the service was set up, as !aipersona does
+        persona.set_preset(Personality::Formal);
+        let message = service
+            .get_ai_welcome_message("User5", "test_channel")
+            .await;
+        formal.assert_async().await;
+        assert_eq!(message, "Good evening, User5.");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ai_welcome_message_falls_back_on_error() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .with_status(500)
+            .create_async()
+            .await;
+
+        let mut service = WelcomeService::new(
+            Arc::new(MockTwitchClient::new()),
+            Arc::new(UserManager::new("test.db")?),
+            Some(vec!["Welcome, {username}!".to_string()]),
+        );
+        service.set_use_ai(true);
+        service.set_ai_client(Arc::new(AiClient::new(&server.url(), "key", "model")?));
+
+        let message = service
+            .get_ai_welcome_message("User6", "test_channel")
+            .await;
+        assert_eq!(message, "Welcome, User6!");
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_welcome_templates() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(WELCOME_MESSAGES_FILE);
+        assert_eq!(read_welcome_templates(&path)?, None);
+
+        std::fs::write(
+            &path,
+            "# One template per line\nHi {username}!\n\n  Welcome aboard!  \n",
+        )?;
+        let templates = read_welcome_templates(&path)?.unwrap();
+        assert_eq!(templates, vec!["Hi {username}!", "Welcome aboard!"]);
+        assert_eq!(
+            templates_missing_username(&templates),
+            vec!["Welcome aboard!"]
+        );
+
+        std::fs::write(&path, "# nothing yet\n")?;
+        assert_eq!(read_welcome_templates(&path)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_batch_welcome() {
+        let usernames = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        assert_eq!(
+            format_batch_welcome(&usernames),
+            vec!["Welcome @a, @b, @c!"]
+        );
+
+        // A big raid's worth of chatters is spread over messages that fit in chat
+        let usernames: Vec<String> = (0..60).map(|i| format!("raider_number_{:02}", i)).collect();
+        let messages = format_batch_welcome(&usernames);
+        assert!(messages.len() > 1);
+        assert!(
+            messages
+                .iter()
+                .all(|message| message.chars().count() <= MAX_MESSAGE_LENGTH)
+        );
+        assert!(
+            messages
+                .iter()
+                .all(|message| message.starts_with("Welcome @"))
+        );
+        assert_eq!(messages.join(" ").matches('@').count(), 60);
+    }
+
+    #[test]
+    fn test_parse_welcome_mode() {
+        let window = Duration::from_secs(10);
+        assert_eq!(
+            WelcomeMode::from_name("batch", window).unwrap(),
+            WelcomeMode::Batch(window)
+        );
+        assert_eq!(
+            "immediate".parse::<WelcomeMode>().unwrap(),
+            WelcomeMode::Immediate
+        );
+        assert!(WelcomeMode::from_name("sometimes", window).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_welcome_cooldown() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let user_manager = Arc::new(UserManager::new("test.db")?);
+        let mut service = WelcomeService::new(
+            Arc::new(client.clone()),
+            user_manager,
+            Some(vec!["Welcome, {username}!".to_string()]),
+        );
+        service.set_mode(WelcomeMode::Cooldown(Duration::from_secs(60)));
+
+        service
+            .process_message(create_test_message("user1", "User1"))
+            .await?;
+        service
+            .process_message(create_test_message("user2", "User2"))
+            .await?;
+        assert_eq!(client.sent_messages().len(), 1);
+
+        // After the cooldown expires the next new chatter is welcomed
+        tokio::time::advance(Duration::from_secs(61)).await;
+        service
+            .process_message(create_test_message("user3", "User3"))
+            .await?;
+
+        let sent = client.sent_messages();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].1, "Welcome, User3!");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_welcomes_follow_channel_features() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let user_manager = Arc::new(UserManager::new("test.db")?);
+        let mut service = WelcomeService::new(
+            Arc::new(client.clone()),
+            user_manager.clone(),
+            Some(vec!["Welcome, {username}!".to_string()]),
+        );
+        service.set_features(Arc::new(ChannelFeatures::new(HashMap::from([(
+            "quiet_channel".to_string(),
+            [Feature::Commands].into(),
+        )]))));
+
+        let mut quiet = create_test_message("user1", "User1");
+        quiet.channel_login = "quiet_channel".to_string();
+        service.process_message(quiet).await?;
+        service
+            .process_message(create_test_message("user2", "User2"))
+            .await?;
+
+        // Only the channel with welcomes on gets one, but both chatters are known
+        assert_eq!(
+            client.sent_messages(),
+            vec![("test_channel".to_string(), "Welcome, User2!".to_string())]
+        );
+        assert!(!user_manager.is_first_time_chatter("user1"));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_mute_suppresses_welcomes_until_resumed() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let service = WelcomeService::new(
+            Arc::new(client.clone()),
+            Arc::new(UserManager::new("test.db")?),
+            Some(vec!["Welcome, {username}!".to_string()]),
+        );
+
+        service.mute_for("test_channel", Duration::from_secs(600));
+        service
+            .process_message(create_test_message("user1", "User1"))
+            .await?;
+        assert!(service.is_muted());
+        assert!(client.sent_messages().is_empty());
+
+        // The mute expires on its own and announces the resume
+        tokio::time::sleep(Duration::from_secs(601)).await;
+        assert!(!service.is_muted());
+        assert_eq!(
+            client.sent_messages(),
+            vec![(
+                "test_channel".to_string(),
+                "Welcome messages have resumed.".to_string()
+            )]
+        );
+
+        service
+            .process_message(create_test_message("user2", "User2"))
+            .await?;
+        assert_eq!(client.sent_messages()[1].1, "Welcome, User2!");
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_unmute_lifts_mute_early() {
+        let client = MockTwitchClient::new();
+        let service = WelcomeService::new(
+            Arc::new(client.clone()),
+            Arc::new(UserManager::new("test.db").unwrap()),
+            None,
+        );
+
+        service.mute_for("test_channel", Duration::from_secs(600));
+        assert!(service.unmute());
+        assert!(!service.is_muted());
+        assert!(!service.unmute());
+
+        // A mute too long to add to now is cut short rather than overflowing
+        service.mute_for("test_channel", Duration::MAX);
+        assert!(service.is_muted());
+        assert!(service.unmute());
+
+        // The original timer doesn't announce a resume
+        tokio::time::sleep(Duration::from_secs(601)).await;
+        assert!(client.sent_messages().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_welcome_batch() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let user_manager = Arc::new(UserManager::new("test.db")?);
+        let mut service = WelcomeService::new(Arc::new(client.clone()), user_manager, None);
+        service.set_mode(WelcomeMode::Batch(Duration::from_secs(10)));
+
+        for (id, name) in [("user1", "A"), ("user2", "B"), ("user3", "C")] {
+            service
+                .process_message(create_test_message(id, name))
+                .await?;
+        }
+        // Returning chatters are not added to the batch
+        service
+            .process_message(create_test_message("user1", "A"))
+            .await?;
+        assert!(client.sent_messages().is_empty());
+
+        tokio::time::sleep(Duration::from_secs(11)).await;
+
+        assert_eq!(
+            client.sent_messages(),
+            vec![(
+                "test_channel".to_string(),
+                "Welcome @A, @B, @C!".to_string()
+            )]
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_grace_period_batches_welcomes() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let user_manager = Arc::new(UserManager::new("test.db")?);
+        let mut service = WelcomeService::new(
+            Arc::new(client.clone()),
+            user_manager,
+            Some(vec!["Welcome, {username}!".to_string()]),
+        );
+        service.set_grace_period(Duration::from_secs(60));
+
+        // Welcomes are held back as a raid lands
+        service.start_grace_period("test_channel");
+        for (id, name) in [("user1", "A"), ("user2", "B")] {
+            service
+                .process_message(create_test_message(id, name))
+                .await?;
+        }
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        service
+            .process_message(create_test_message("user3", "C"))
+            .await?;
+        assert!(client.sent_messages().is_empty());
+
+        // Everyone is welcomed at once when the grace period ends
+        tokio::time::sleep(Duration::from_secs(31)).await;
+        assert_eq!(
+            client.sent_messages(),
+            vec![(
+                "test_channel".to_string(),
+                "Welcome @A, @B, @C!".to_string()
+            )]
+        );
+
+        // Then welcomes go back to normal
+        service
+            .process_message(create_test_message("user4", "D"))
+            .await?;
+        assert_eq!(client.sent_messages().len(), 2);
+        assert_eq!(client.sent_messages()[1].1, "Welcome, D!");
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_grace_period_disabled_by_default() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let service = WelcomeService::new(
+            Arc::new(client.clone()),
+            Arc::new(UserManager::new("test.db")?),
+            Some(vec!["Welcome, {username}!".to_string()]),
+        );
+
+        service.start_grace_period("test_channel");
+        service
+            .process_message(create_test_message("user1", "A"))
+            .await?;
+        assert_eq!(client.sent_messages().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_grace_period_is_capped() -> Result<()> {
+        let client = MockTwitchClient::new();
+        let mut service = WelcomeService::new(
+            Arc::new(client.clone()),
+            Arc::new(UserManager::new("test.db")?),
+            Some(vec!["Welcome, {username}!".to_string()]),
+        );
+        service.set_grace_period(Duration::MAX);
+
+        // Starting an enormous grace period doesn't overflow the end time
+        service.start_grace_period("test_channel");
+        assert_eq!(
+            service.grace_remaining("test_channel"),
+            Some(Duration::from_secs(MAX_WELCOME_GRACE_SECS))
+        );
+        Ok(())
+    }
 }
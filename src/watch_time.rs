@@ -0,0 +1,238 @@
+//! How long each viewer has spent in the channel while the stream is live
+//!
+//! IRC JOIN and PART messages arrive late and in batches on busy channels, and
+//! not at all past a thousand viewers, so presence can't rest on them. Instead
+//! a viewer counts as present for a while after any sign of them: chatting,
+//! appearing in the Helix chatters list (polled every few minutes, and the
+//! only way to see lurkers), or a JOIN. A PART ends their presence until
+//! they're seen again. Every minute while live, each present viewer earns a
+//! minute of watch time in the user database.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, error, warn};
+
+use crate::twitch::{HelixChatClient, HelixChatter};
+use crate::users::UserManager;
+
+/// How often present viewers earn watch time
+const TICK: Duration = Duration::from_secs(60);
+
+/// How many ticks pass between polls of the chatters list
+const TICKS_PER_CHATTERS_POLL: u64 = 5;
+
+/// How long after the last sign of a viewer they still count as present;
+/// longer than the gap between chatters polls so lurkers stay counted
+const PRESENCE_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// The last sign of a viewer
+struct Presence {
+    /// The viewer's user ID
+    user_id: String,
+    /// When they were last seen
+    last_seen: Instant,
+}
+
+/// Tracks who is present and adds up their watch time
+pub struct WatchTimeTracker {
+    /// Where watch time is kept
+    users: Arc<UserManager>,
+    /// Viewers seen recently, keyed by lowercase login
+    present: Mutex<HashMap<String, Presence>>,
+    /// Whether the stream is live; watch time only counts while it is
+    live: AtomicBool,
+}
+
+impl WatchTimeTracker {
+    /// Create a tracker that keeps watch time in the user database
+    ///
+    /// # Arguments
+    /// * `users` - The user manager whose database keeps watch time
+    ///
+    /// # Returns
+    /// A new WatchTimeTracker instance
+    pub fn new(users: Arc<UserManager>) -> Self {
+        WatchTimeTracker {
+            users,
+            present: Mutex::new(HashMap::new()),
+            live: AtomicBool::new(false),
+        }
+    }
+
+    /// Record whether the stream is live
+    ///
+    /// # Arguments
+    /// * `live` - true while the stream is live
+    pub fn set_live(&self, live: bool) {
+        self.live.store(live, Ordering::Relaxed);
+    }
+
+    /// Note that a viewer was seen
+    ///
+    /// # Arguments
+    /// * `user_id` - The viewer's user ID
+    /// * `login` - The viewer's login
+    pub fn record_seen(&self, user_id: &str, login: &str) {
+        self.present.lock().unwrap().insert(
+            login.to_lowercase(),
+            Presence {
+                user_id: user_id.to_string(),
+                last_seen: Instant::now(),
+            },
+        );
+    }
+
+    /// Note everyone in the chatters list as seen
+    ///
+    /// # Arguments
+    /// * `chatters` - The users Helix reports in chat
+    pub fn record_chatters(&self, chatters: &[HelixChatter]) {
+        for chatter in chatters {
+            self.record_seen(&chatter.user_id, &chatter.user_login);
+        }
+    }
+
+    /// Take an IRC JOIN as a sign a viewer is still here
+    ///
+    /// JOIN only carries a login, so it can only refresh viewers whose user
+    /// ID is already known from chat or the chatters list.
+    ///
+    /// # Arguments
+    /// * `login` - The login that joined
+    pub fn record_join(&self, login: &str) {
+        if let Some(presence) = self.present.lock().unwrap().get_mut(&login.to_lowercase()) {
+            presence.last_seen = Instant::now();
+        }
+    }
+
+    /// Take an IRC PART as a sign a viewer has left
+    ///
+    /// # Arguments
+    /// * `login` - The login that left
+    pub fn record_part(&self, login: &str) {
+        self.present.lock().unwrap().remove(&login.to_lowercase());
+    }
+
+    /// Get the viewers watching right now
+    ///
+    /// # Returns
+    /// Each present viewer's user ID and login, or nobody while the stream is offline
+    pub fn watching(&self) -> Vec<(String, String)> {
+        if !self.live.load(Ordering::Relaxed) {
+            return Vec::new();
+        }
+
+        let mut present = self.present.lock().unwrap();
+        present.retain(|_, presence| presence.last_seen.elapsed() < PRESENCE_WINDOW);
+        present
+            .iter()
+            .map(|(login, presence)| (presence.user_id.clone(), login.clone()))
+            .collect()
+    }
+
+    /// Give every present viewer watch time, if the stream is live
+    ///
+    /// # Arguments
+    /// * `minutes` - How many minutes each present viewer earns
+    ///
+    /// # Returns
+    /// How many viewers earned watch time
+    pub fn accrue(&self, minutes: u64) -> Result<usize> {
+        let viewers = self.watching();
+        if viewers.is_empty() {
+            return Ok(0);
+        }
+
+        self.users.add_watch_minutes(&viewers, minutes)?;
+        Ok(viewers.len())
+    }
+
+    /// Poll the chatters list every few minutes and add watch time every minute, forever
+    ///
+    /// # Arguments
+    /// * `helix` - The Helix client used to list chatters
+    /// * `channel` - The channel being watched
+    pub async fn run(
+        self: Arc<Self>,
+        helix: Arc<tokio::sync::Mutex<HelixChatClient>>,
+        channel: String,
+    ) {
+        let mut warned = false;
+        for tick in 0u64.. {
+            if tick % TICKS_PER_CHATTERS_POLL == 0 && self.live.load(Ordering::Relaxed) {
+                match fetch_chatters(&helix, &channel).await {
+                    Ok(chatters) => self.record_chatters(&chatters),
+                    // Most likely the token lacks the scope; chat still counts
+                    Err(e) if !warned => {
+                        warn!(
+                            "Couldn't list chatters, counting watch time from chat only: {}",
+                            e
+                        );
+                        warned = true;
+                    }
+                    Err(e) => debug!("Couldn't list chatters: {}", e),
+                }
+            }
+
+            tokio::time::sleep(TICK).await;
+            match self.accrue(TICK.as_secs() / 60) {
+                Ok(0) => {}
+                Ok(viewers) => debug!("{} viewers earned watch time", viewers),
+                Err(e) => error!("Error adding watch time: {}", e),
+            }
+        }
+    }
+}
+
+/// List everyone in a channel's chat
+async fn fetch_chatters(
+    helix: &tokio::sync::Mutex<HelixChatClient>,
+    channel: &str,
+) -> Result<Vec<HelixChatter>> {
+    let mut helix = helix.lock().await;
+    let broadcaster_id = helix.get_broadcaster_id(channel).await?;
+    Ok(helix.get_chatters(&broadcaster_id).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_watch_time_follows_presence() -> Result<()> {
+        let users = Arc::new(UserManager::new("test_watch_time.db")?);
+        let tracker = WatchTimeTracker::new(users.clone());
+        tracker.record_seen("1", "Chatty");
+        tracker.record_chatters(&[HelixChatter {
+            user_id: "2".to_string(),
+            user_login: "lurker".to_string(),
+        }]);
+
+        // Nothing counts while offline
+        assert_eq!(tracker.accrue(1)?, 0);
+        tracker.set_live(true);
+        assert_eq!(tracker.accrue(1)?, 2);
+
+        // A PART ends presence, and a JOIN can't bring back an unknown ID
+        tracker.record_part("lurker");
+        tracker.record_join("lurker");
+        assert_eq!(tracker.accrue(1)?, 1);
+
+        // A JOIN keeps a known viewer around past the window
+        tokio::time::advance(PRESENCE_WINDOW - Duration::from_secs(1)).await;
+        tracker.record_join("chatty");
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(tracker.accrue(1)?, 1);
+        tokio::time::advance(PRESENCE_WINDOW).await;
+        assert_eq!(tracker.accrue(1)?, 0);
+
+        assert_eq!(users.watch_minutes_by_login("chatty")?, Some(3));
+        assert_eq!(users.watch_minutes_by_login("lurker")?, Some(1));
+        Ok(())
+    }
+}